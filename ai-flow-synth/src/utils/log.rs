@@ -1,6 +1,7 @@
 use std::path::Path;
 
 use serde::Deserialize;
+use tracing_subscriber::{Registry, filter::LevelFilter, fmt, layer::SubscriberExt, reload};
 
 #[derive(Debug, Deserialize)]
 pub struct LogConfig {
@@ -19,7 +20,33 @@ impl Default for LogConfig {
     }
 }
 
+fn level_filter(enable_debug: bool) -> LevelFilter {
+    if enable_debug {
+        LevelFilter::DEBUG
+    } else {
+        LevelFilter::INFO
+    }
+}
+
+/// Changes the level of the logging enabled by [`enable_reloadable_log`].
+#[derive(Debug, Clone)]
+pub struct LogLevelHandle(reload::Handle<LevelFilter, Registry>);
+
+impl LogLevelHandle {
+    pub fn apply(&self, config: &LogConfig) -> anyhow::Result<()> {
+        self.0
+            .reload(level_filter(config.enable_debug))
+            .map_err(|e| anyhow::anyhow!("Failed to change the log level: {}", e))
+    }
+}
+
 pub fn enable_log(config: &LogConfig) -> anyhow::Result<impl Drop> {
+    let (guard, _) = enable_reloadable_log(config)?;
+    Ok(guard)
+}
+
+/// Enable logging with a level which can be changed while running.
+pub fn enable_reloadable_log(config: &LogConfig) -> anyhow::Result<(impl Drop, LogLevelHandle)> {
     let file_path = Path::new(config.directory.as_deref().unwrap_or("./")).join("logs");
     let log_prefix = config.prefix.clone();
     let log_level = if config.enable_debug { "debug" } else { "info" };
@@ -32,16 +59,15 @@ pub fn enable_log(config: &LogConfig) -> anyhow::Result<impl Drop> {
 
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
 
-    let mut subscriber = tracing_subscriber::fmt()
+    let (filter, handle) = reload::Layer::new(level_filter(config.enable_debug));
+    let layer = fmt::layer()
         .with_writer(non_blocking)
-        .with_timer(tracing_subscriber::fmt::time::UtcTime::rfc_3339())
+        .with_timer(fmt::time::UtcTime::rfc_3339())
         .with_ansi(false);
-    if config.enable_debug {
-        subscriber = subscriber.with_max_level(tracing::Level::DEBUG);
-    }
-    tracing::subscriber::set_global_default(subscriber.finish())
+    let subscriber = tracing_subscriber::registry().with(filter).with(layer);
+    tracing::subscriber::set_global_default(subscriber)
         .map_err(|e| anyhow::anyhow!("Failed to set global default subscriber: {}", e))?;
     tracing::info!("Logging enabled with level: {}", log_level);
 
-    Ok(_guard)
+    Ok((_guard, LogLevelHandle(handle)))
 }
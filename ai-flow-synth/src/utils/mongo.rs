@@ -1,30 +1,98 @@
 use mongodb::{Client, Database, options::ClientOptions};
 use serde::Deserialize;
-use std::{error::Error, sync::Arc};
+use std::{error::Error, sync::Arc, time::Duration};
 
-#[derive(Debug, Deserialize)]
+/// The tuning settings override the options of the uri, the driver defaults
+/// apply when neither sets them.
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct MongoConfig {
     pub uri: String,
     pub db_name: String,
+    /// Connections to each server, at most and kept open.
+    pub max_pool_size: Option<u32>,
+    pub min_pool_size: Option<u32>,
+    pub connect_timeout_secs: Option<u64>,
+    /// How long an operation waits for a suitable server, e.g. a new primary.
+    pub server_selection_timeout_secs: Option<u64>,
+    /// Whether the driver retries a failed write or read once.
+    pub retry_writes: Option<bool>,
+    pub retry_reads: Option<bool>,
 }
 
 #[derive(Debug, Clone)]
 pub struct MongoClient {
-    _client: Arc<Client>,
+    client: Arc<Client>,
     db: Arc<Database>,
 }
 
 impl MongoClient {
     pub async fn new(config: &MongoConfig) -> Result<Self, Box<dyn Error>> {
-        let options = ClientOptions::parse(&config.uri).await?;
+        let mut options = ClientOptions::parse(&config.uri).await?;
+        options.max_pool_size = config.max_pool_size.or(options.max_pool_size);
+        options.min_pool_size = config.min_pool_size.or(options.min_pool_size);
+        options.connect_timeout = config
+            .connect_timeout_secs
+            .map(Duration::from_secs)
+            .or(options.connect_timeout);
+        options.server_selection_timeout = config
+            .server_selection_timeout_secs
+            .map(Duration::from_secs)
+            .or(options.server_selection_timeout);
+        options.retry_writes = config.retry_writes.or(options.retry_writes);
+        options.retry_reads = config.retry_reads.or(options.retry_reads);
         let client = Client::with_options(options)?;
         let db = client.database(&config.db_name);
         Ok(MongoClient {
-            _client: Arc::new(client),
+            client: Arc::new(client),
             db: Arc::new(db),
         })
     }
 
+    /// Round trip to the server, fails when it is unreachable.
+    pub async fn ping(&self) -> mongodb::error::Result<()> {
+        self.db
+            .run_command(mongodb::bson::doc! { "ping": 1 })
+            .await?;
+        Ok(())
+    }
+
+    /// Close the connections, waiting for the sessions and cursors in use to be dropped.
+    pub async fn shutdown(&self) {
+        Client::clone(&self.client).shutdown().await;
+    }
+
+    /// Start a session, e.g. to run several writes in one transaction.
+    pub async fn start_session(&self) -> mongodb::error::Result<mongodb::ClientSession> {
+        self.client.start_session().await
+    }
+
+    /// Start a session whose reads all see the same point in time.
+    pub async fn start_snapshot_session(&self) -> mongodb::error::Result<mongodb::ClientSession> {
+        self.client.start_session().snapshot(true).await
+    }
+
+    /// GridFS bucket to store files larger than a document.
+    pub fn gridfs_bucket(&self, name: &str) -> mongodb::gridfs::GridFsBucket {
+        let options = mongodb::options::GridFsBucketOptions::builder()
+            .bucket_name(name.to_string())
+            .build();
+        self.db.gridfs_bucket(options)
+    }
+
+    /// Rename the collection atomically, dropping any collection already named `to`.
+    pub async fn rename_collection(&self, from: &str, to: &str) -> mongodb::error::Result<()> {
+        let name = self.db.name();
+        self.client
+            .database("admin")
+            .run_command(mongodb::bson::doc! {
+                "renameCollection": format!("{}.{}", name, from),
+                "to": format!("{}.{}", name, to),
+                "dropTarget": true,
+            })
+            .await?;
+        Ok(())
+    }
+
     // pub fn db(&self) -> Arc<Database> {
     //     Arc::clone(&self.db)
     // }
@@ -49,6 +117,7 @@ mod tests {
         let config = MongoConfig {
             uri: "mongodb://localhost:27017".to_string(),
             db_name: "paper".to_string(),
+            ..Default::default()
         };
 
         let client = MongoClient::new(&config)
@@ -0,0 +1,102 @@
+//! Embedding worker
+//!
+//! Runs the embedding model out of the api process, so heavy vectorization
+//! (ideally on a GPU node) doesn't compete with api request latency.
+//!
+//! Protocol: `POST /embed` with `{"texts": [...]}`, answers `{"model": "...", "embeddings": [[...]]}`.
+
+use std::sync::Arc;
+
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use salvo::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// max texts accepted in one request
+const MAX_BATCH: usize = 256;
+
+#[derive(Debug, Deserialize)]
+struct EmbedRequest {
+    texts: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedResponse {
+    model: String,
+    embeddings: Vec<Vec<f32>>,
+}
+
+struct Worker {
+    model: TextEmbedding,
+    model_name: String,
+}
+
+#[handler]
+async fn embed(req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    let worker = depot
+        .obtain::<Arc<Worker>>()
+        .expect("worker not injected")
+        .clone();
+    let body = match req.parse_json::<EmbedRequest>().await {
+        Ok(body) => body,
+        Err(e) => {
+            res.status_code(StatusCode::BAD_REQUEST);
+            res.render(format!("Invalid request: {}", e));
+            return;
+        }
+    };
+    if body.texts.len() > MAX_BATCH {
+        res.status_code(StatusCode::PAYLOAD_TOO_LARGE);
+        res.render(format!("At most {} texts per request", MAX_BATCH));
+        return;
+    }
+
+    let texts = body.texts;
+    let model_name = worker.model_name.clone();
+    // the model is cpu/gpu bound, keep it off the async runtime
+    let result = tokio::task::spawn_blocking(move || worker.model.embed(texts, None)).await;
+    match result {
+        Ok(Ok(embeddings)) => res.render(Json(EmbedResponse {
+            model: model_name,
+            embeddings,
+        })),
+        Ok(Err(e)) => {
+            tracing::error!("Embedding failed: {}", e);
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render(format!("Embedding failed: {}", e));
+        }
+        Err(e) => {
+            tracing::error!("Embedding task panicked: {}", e);
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Embedding task failed");
+        }
+    }
+}
+
+#[handler]
+async fn health(res: &mut Response) {
+    res.render(Text::Plain("ok"));
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt().init();
+
+    let address = std::env::var("EMBEDDING_WORKER_ADDRESS").unwrap_or("127.0.0.1:7979".to_string());
+    let model = TextEmbedding::try_new(
+        InitOptions::new(EmbeddingModel::AllMiniLML6V2).with_show_download_progress(true),
+    )?;
+    let worker = Arc::new(Worker {
+        model,
+        model_name: "all-MiniLM-L6-v2".to_string(),
+    });
+
+    let router = Router::new()
+        .hoop(affix_state::inject(worker))
+        .push(Router::with_path("embed").post(embed))
+        .push(Router::with_path("healthz").get(health));
+
+    tracing::info!("Embedding worker listening on {}", address);
+    let acceptor = TcpListener::new(&address).bind().await;
+    Server::new(acceptor).serve(router).await;
+    Ok(())
+}
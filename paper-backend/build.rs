@@ -0,0 +1,5 @@
+fn main() {
+    // the gRPC stubs, generated only with the `grpc` feature, needs `protoc`
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/paper.proto").expect("Failed to compile proto/paper.proto");
+}
@@ -1,30 +1,429 @@
-use std::sync::Arc;
+use std::{
+    sync::{Arc, atomic::AtomicBool},
+    time::Duration,
+};
 
-use ai_flow_synth::utils::MongoClient;
-
-use crate::{config::Config, model::create_all_index};
+use crate::{
+    collab::NoteRooms,
+    config::{
+        BodyLimitConfig, Config, ExportConfig, LegalConfig, LlmConfig, QuotaConfig, RevisionConfig,
+        SearchConfig, SessionConfig, SettingsConfig, TenantConfig, TimeoutConfig, UsageConfig,
+    },
+    embedding::{Embedder, create_embedder},
+    error::{ServiceError, ServiceResult},
+    events::EventBus,
+    llm::LlmClient,
+    math::MathRenderer,
+    model::{
+        database::{self, Database},
+        folder::{Folder, FolderRepository},
+        llm_key::{KEY_PROVIDERS, LlmKeyRepository},
+        paper::{Paper, PaperRepository},
+        quota::{QuotaResource, Quotas, check_quota},
+        retry::{RetryMetrics, RetryingDatabase},
+        stats::{StatsRepository, schema::UserStatsResponse},
+        usage::{UsageRepository, month_start, next_month_start},
+        user::{User, UserRepository},
+    },
+    pdf::{extract::PdfTextExtractor, ocr::OcrEngine, thumbnail::ThumbnailRenderer},
+    rate_limit::RateLimiter,
+    resilience::{Resilience, breaker::CircuitBreaker},
+    scan::{ContentScanner, create_scanner},
+    tenant::DEFAULT_TENANT,
+    utils::{
+        cache::{Cache, CacheKey, TtlCache, create_cache, get_cached, set_cached},
+        crossref::CrossrefClient,
+        crypto::{Cipher, is_sealed, is_sealed_bytes},
+        jobs::JobTracker,
+        mailer::{BreakerMailer, LogMailer, Mailer, SmtpMailer},
+        semantic_scholar::SemanticScholarClient,
+    },
+};
 
 #[derive(Debug)]
 pub struct AppData {
-    pub mongo_client: MongoClient,
+    pub db: Arc<dyn Database>,
+    pub db_retries: Arc<RetryMetrics>,
+    pub mailer: Arc<dyn Mailer>,
+    pub llm: LlmClient,
+    // seals the secrets of the users, none when they cannot store any
+    pub cipher: Option<Cipher>,
+    pub embedder: Option<Arc<dyn Embedder>>,
+    pub pdf_extractor: PdfTextExtractor,
+    pub ocr: Option<OcrEngine>,
+    pub thumbnails: Option<ThumbnailRenderer>,
+    pub scanner: Arc<dyn ContentScanner>,
+    pub crossref: CrossrefClient,
+    // none when the external suggestions are disabled
+    pub semantic_scholar: Option<SemanticScholarClient>,
+    pub search_config: SearchConfig,
+    pub legal_config: LegalConfig,
+    pub usage_config: UsageConfig,
+    pub quota_config: QuotaConfig,
+    pub revision_config: RevisionConfig,
+    pub export_config: ExportConfig,
+    pub body_limit_config: BodyLimitConfig,
+    pub timeout_config: TimeoutConfig,
+    pub session_config: SessionConfig,
+    pub settings_config: SettingsConfig,
+    pub stats_cache: TtlCache<UserStatsResponse>,
+    pub math: MathRenderer,
+    pub cache: Arc<dyn Cache>,
+    pub resilience: Resilience,
+    pub rate_limiter: RateLimiter,
+    pub jobs: JobTracker,
+    pub events: EventBus,
+    pub notes: NoteRooms,
+    // set while a restore replaces the collections, see `backup::run_restore`
+    pub restoring: AtomicBool,
+    pub public_url: String,
+    // the organization served, see `tenant::Tenants`
+    pub tenant: TenantConfig,
 }
 
 pub type AppDataRef = Arc<AppData>;
 
+fn default_tenant() -> TenantConfig {
+    TenantConfig {
+        id: DEFAULT_TENANT.to_string(),
+        ..Default::default()
+    }
+}
+
+// the statistics are several aggregations, recomputed at most once a minute
+const STATS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+// the required settings only, the others have their defaults
+#[cfg(test)]
+const TEST_CONFIG: &str = r#"
+[frontend_config]
+cors = []
+
+[backend_config]
+address = "127.0.0.1:7878"
+
+[backend_config.jwt]
+access_secret = "test_access_secret"
+refresh_secret = "test_refresh_secret"
+
+[log_config]
+enable_debug = true
+prefix = "paper-backend-test"
+
+[llm_config]
+provider = "mock"
+"#;
+
 impl AppData {
     pub async fn new(config: &Config) -> AppDataRef {
-        let mongo_client = MongoClient::new(&config.mongo_config)
+        let db = database::connect(config)
             .await
-            .expect("Failed to create MongoDB client");
+            .expect("Failed to connect to the database");
+        AppData::with_database(config, default_tenant(), db).await
+    }
 
-        create_all_index(&mongo_client)
+    /// The state of the service for the tenant, over its own database.
+    pub async fn for_tenant(config: &Config, tenant: TenantConfig) -> AppDataRef {
+        let db = database::connect_tenant(config, &tenant)
             .await
-            .expect("Failed to create indexes");
+            .expect("Failed to connect to the database of the tenant");
+        AppData::with_database(config, tenant, db).await
+    }
+
+    /// The state of the service over the given database, in memory for the tests.
+    async fn with_database(
+        config: &Config,
+        tenant: TenantConfig,
+        db: Arc<dyn Database>,
+    ) -> AppDataRef {
+        let db_retries = Arc::new(RetryMetrics::default());
+        let db = Arc::new(RetryingDatabase::new(
+            db,
+            config.db_retry_config.clone(),
+            db_retries.clone(),
+        ));
+
+        let mailer: Arc<dyn Mailer> = match &config.smtp_config {
+            Some(smtp_config) => Arc::new(BreakerMailer::new(
+                SmtpMailer::new(smtp_config).expect("Failed to create smtp mailer"),
+                CircuitBreaker::new("smtp", &config.circuit_breaker_config),
+            )),
+            None => Arc::new(LogMailer),
+        };
+
+        // the usage of the tenant billed to its own key
+        let llm_config = match &tenant.llm_api_key {
+            Some(api_key) => LlmConfig {
+                api_key: api_key.clone(),
+                ..config.llm_config.clone()
+            },
+            None => config.llm_config.clone(),
+        };
+        let llm = LlmClient::new(
+            &llm_config,
+            &config.llm_providers,
+            &config.circuit_breaker_config,
+        )
+        .expect("Failed to create llm client");
+
+        let embedder = config.embedding_config.as_ref().map(create_embedder);
+        let cipher = config
+            .encryption_config
+            .as_ref()
+            .map(|encryption| Cipher::new(encryption).expect("Invalid encryption config"));
+
+        Arc::new(AppData {
+            db,
+            db_retries,
+            mailer,
+            llm,
+            cipher,
+            embedder,
+            pdf_extractor: PdfTextExtractor::new(&config.pdf_config),
+            ocr: config.pdf_config.ocr.as_ref().map(OcrEngine::new),
+            thumbnails: config
+                .pdf_config
+                .thumbnail
+                .as_ref()
+                .map(ThumbnailRenderer::new),
+            scanner: create_scanner(config.scan_config.as_ref()),
+            crossref: CrossrefClient::new(&config.circuit_breaker_config),
+            semantic_scholar: config.related_config.external.then(|| {
+                SemanticScholarClient::new(config.related_config.semantic_scholar_api_key.clone())
+            }),
+            search_config: config.search_config.clone(),
+            legal_config: config.legal_config.clone(),
+            usage_config: config.usage_config.clone(),
+            quota_config: tenant
+                .quota_config
+                .clone()
+                .unwrap_or_else(|| config.quota_config.clone()),
+            revision_config: config.revision_config.clone(),
+            export_config: config.export_config.clone(),
+            body_limit_config: config.body_limit_config.clone(),
+            timeout_config: config.timeout_config.clone(),
+            session_config: config.backend_config.session.clone(),
+            settings_config: config.settings_config.clone(),
+            stats_cache: TtlCache::new(STATS_CACHE_TTL),
+            math: MathRenderer::default(),
+            cache: create_cache(&config.cache_config)
+                .await
+                .expect("Invalid cache config"),
+            resilience: Resilience::default(),
+            rate_limiter: RateLimiter::new(&config.rate_limit_config).await,
+            jobs: JobTracker::default(),
+            events: EventBus::default(),
+            notes: NoteRooms::default(),
+            restoring: AtomicBool::new(false),
+            public_url: config.backend_config.public_url(),
+            tenant,
+        })
+    }
+
+    /// The state of the service for the handler tests: every repository in
+    /// memory, the mock llm, and no mail sent.
+    #[cfg(test)]
+    pub async fn for_tests() -> AppDataRef {
+        AppData::for_tenant_tests(default_tenant()).await
+    }
+
+    /// The state of the tenant for the handler tests, as `for_tests`.
+    #[cfg(test)]
+    pub async fn for_tenant_tests(tenant: TenantConfig) -> AppDataRef {
+        use crate::model::document::{DocumentDatabase, memory::MemoryStore};
+
+        let config: Config = toml::from_str(TEST_CONFIG).expect("Invalid test config");
+        let db = DocumentDatabase::new(Arc::new(MemoryStore::default()));
+        db.ensure_indexes().await.expect("Failed to create indexes");
+        AppData::with_database(&config, tenant, Arc::new(db)).await
+    }
+
+    /// The user by uid, read through the cache.
+    pub async fn cached_user(&self, uid: &str) -> ServiceResult<Option<User>> {
+        let key = CacheKey::User(uid);
+        if let Some(user) = get_cached(self.cache.as_ref(), key).await {
+            return Ok(Some(user));
+        }
+        let user = self.db.get_user_by_uid(uid).await?;
+        if let Some(user) = &user {
+            set_cached(self.cache.as_ref(), key, user).await;
+        }
+        Ok(user)
+    }
 
-        // create_all_index(&mongo_client)
-        //     .await
-        //     .expect("Failed to create indexes");
+    /// The secret sealed for storage, as is when no key is configured.
+    pub fn seal_secret(&self, secret: &str) -> ServiceResult<String> {
+        match &self.cipher {
+            Some(cipher) => cipher.encrypt(secret),
+            None => Ok(secret.to_string()),
+        }
+    }
+
+    /// The stored secret, opened when sealed.
+    pub fn open_secret(&self, stored: &str) -> ServiceResult<String> {
+        match &self.cipher {
+            Some(cipher) => cipher.decrypt(stored),
+            None if is_sealed(stored) => Err(ServiceError::InternalServerError(
+                "No encryption key to open a sealed secret".to_string(),
+            )),
+            None => Ok(stored.to_string()),
+        }
+    }
+
+    /// The state of collaborative notes, sealed for storage when configured.
+    pub fn seal_note(&self, bytes: Vec<u8>) -> ServiceResult<Vec<u8>> {
+        match &self.cipher {
+            Some(cipher) if cipher.encrypt_notes => cipher.encrypt_bytes(&bytes),
+            _ => Ok(bytes),
+        }
+    }
+
+    /// The stored state of collaborative notes, opened when sealed.
+    pub fn open_note(&self, bytes: Vec<u8>) -> ServiceResult<Vec<u8>> {
+        match &self.cipher {
+            Some(cipher) => cipher.decrypt_bytes(&bytes),
+            None if is_sealed_bytes(&bytes) => Err(ServiceError::InternalServerError(
+                "No encryption key to open sealed notes".to_string(),
+            )),
+            None => Ok(bytes),
+        }
+    }
+
+    /// The model of a llm call for the user: the requested one, else the one
+    /// they prefer while it is served, else the default one.
+    pub fn user_model(&self, user: &User, requested: Option<String>) -> String {
+        requested
+            .or_else(|| {
+                user.settings
+                    .ai_model
+                    .clone()
+                    .filter(|model| self.llm.supports(model))
+            })
+            .unwrap_or_else(|| self.llm.model.clone())
+    }
+
+    /// The api key the user stored for the provider serving the model, the
+    /// calls made for them with it are billed to their own account.
+    pub async fn user_llm_key(&self, uid: &str, model: &str) -> ServiceResult<Option<String>> {
+        let provider = self.llm.provider_of(model);
+        let Some(cipher) = &self.cipher else {
+            return Ok(None);
+        };
+        if !KEY_PROVIDERS.contains(&provider) {
+            return Ok(None);
+        }
+        match self.db.get_llm_key(uid, provider).await? {
+            Some(key) => cipher.decrypt_sealed(&key.encrypted_key).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Fail with `QuotaExceeded` when the user has consumed the monthly llm
+    /// token quota, checked before every llm call made for a user. The calls
+    /// made with their own key for the provider of the model are not limited.
+    pub async fn ensure_quota(&self, uid: &str, model: &str) -> ServiceResult<()> {
+        let Some(quota) = self.usage_config.monthly_token_quota else {
+            return Ok(());
+        };
+        if self.user_llm_key(uid, model).await?.is_some() {
+            return Ok(());
+        }
+        let now = chrono::Utc::now();
+        let used = self
+            .db
+            .sum_tokens_since(uid, month_start(now).into())
+            .await?;
+        if used >= quota {
+            return Err(ServiceError::QuotaExceeded {
+                used,
+                quota,
+                resets_at: next_month_start(now).timestamp_millis(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The limits on the library of the user: its overrides, then the config.
+    pub fn quotas(&self, user: &User) -> Quotas {
+        Quotas::of(&self.quota_config, &user.quota_overrides)
+    }
+
+    /// Fail with `ResourceQuotaExceeded` when the user cannot have `added` more folders.
+    pub async fn ensure_folder_quota(&self, user: &User, added: u64) -> ServiceResult<()> {
+        let limit = self.quotas(user).max_folders;
+        if limit.is_none() {
+            return Ok(());
+        }
+        let used = self.db.count_folders(&user.uid).await?;
+        check_quota(QuotaResource::Folders, limit, used, added)
+    }
+
+    /// Fail with `ResourceQuotaExceeded` when the folder has no room for `added`
+    /// more papers.
+    pub async fn ensure_folder_room(
+        &self,
+        user: &User,
+        folder_id: &str,
+        added: u64,
+    ) -> ServiceResult<()> {
+        let limit = self.quotas(user).max_papers_per_folder;
+        if limit.is_none() {
+            return Ok(());
+        }
+        let used = self.db.count_folder_papers(folder_id).await?;
+        check_quota(QuotaResource::PapersPerFolder, limit, used, added)
+    }
+
+    /// Fail with `ResourceQuotaExceeded` when the files of the user cannot take
+    /// a file of `added` bytes in place of one of `replaced` bytes.
+    pub async fn ensure_storage_quota(
+        &self,
+        user: &User,
+        added: u64,
+        replaced: u64,
+    ) -> ServiceResult<()> {
+        let limit = self.quotas(user).max_storage_bytes;
+        if limit.is_none() {
+            return Ok(());
+        }
+        let used = self.db.sum_storage_bytes(&user.uid).await?;
+        check_quota(
+            QuotaResource::StorageBytes,
+            limit,
+            used.saturating_sub(replaced),
+            added,
+        )
+    }
+
+    /// All folders of the user, read through the cache.
+    pub async fn cached_folders(&self, user_id: &str) -> ServiceResult<Vec<Folder>> {
+        let key = CacheKey::Folders(user_id);
+        if let Some(folders) = get_cached(self.cache.as_ref(), key).await {
+            return Ok(folders);
+        }
+        let folders = self.db.get_folders_by_user_id(user_id).await?;
+        set_cached(self.cache.as_ref(), key, &folders).await;
+        Ok(folders)
+    }
+
+    /// The paper by id, read through the cache. Only for reads, updates start
+    /// from the stored paper.
+    pub async fn cached_paper(&self, id: &str) -> ServiceResult<Option<Paper>> {
+        let key = CacheKey::Paper(id);
+        if let Some(paper) = get_cached(self.cache.as_ref(), key).await {
+            return Ok(Some(paper));
+        }
+        let paper = self.db.get_paper_by_id(id).await?;
+        if let Some(paper) = &paper {
+            set_cached(self.cache.as_ref(), key, paper).await;
+        }
+        Ok(paper)
+    }
 
-        Arc::new(AppData { mongo_client })
+    /// Drop the cached documents after a write.
+    pub async fn invalidate(&self, keys: &[CacheKey<'_>]) {
+        for key in keys {
+            self.cache.delete(&key.to_string()).await;
+        }
     }
 }
@@ -0,0 +1,259 @@
+use crate::{
+    app_data::AppData,
+    error::{ServiceError, ServiceResult},
+    model::{
+        block::Block,
+        custom_field::CustomField,
+        folder::Folder,
+        organization::Organization,
+        paper::Paper,
+        share::{ShareLink, SharePermission},
+        user::User,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Who acts on a resource.
+#[derive(Debug, Clone, Copy)]
+pub enum Principal<'a> {
+    /// A signed in user, `operator` when listed in the usage config.
+    User { user: &'a User, operator: bool },
+    /// An anonymous reviewer, holding a share link.
+    ShareLink(&'a ShareLink),
+}
+
+impl<'a> Principal<'a> {
+    pub fn of(state: &AppData, user: &'a User) -> Self {
+        Principal::User {
+            user,
+            operator: state.usage_config.is_operator(&user.uid),
+        }
+    }
+}
+
+/// What is read or written.
+#[derive(Debug, Clone, Copy)]
+pub enum Resource<'a> {
+    Folder(&'a Folder),
+    Paper(&'a Paper),
+    /// The notes of the paper, edited together in real time.
+    Notes(&'a Paper),
+    Block(&'a Block),
+    CustomField(&'a CustomField),
+    Organization(&'a Organization),
+}
+
+impl Resource<'_> {
+    fn kind(&self) -> &'static str {
+        match self {
+            Resource::Folder(_) => "folder",
+            Resource::Paper(_) => "paper",
+            Resource::Notes(_) => "notes",
+            Resource::Block(_) => "block",
+            Resource::CustomField(_) => "custom field",
+            Resource::Organization(_) => "organization",
+        }
+    }
+}
+
+/// Whether the principal may access the resource:
+/// - the owner reads and writes what they own;
+/// - an active share link reads the paper it was made for, an `edit` link
+///   also writes its notes;
+/// - the members of an organization read it and its team fields, its admins
+///   write them;
+/// - the operators read everything, for support, but only write their own.
+pub fn can(access: Access, resource: Resource, principal: Principal) -> bool {
+    let (user, operator) = match principal {
+        Principal::ShareLink(link) => {
+            let granted = match resource {
+                Resource::Paper(paper) => access == Access::Read && paper.id == link.paper_id,
+                Resource::Notes(paper) => {
+                    paper.id == link.paper_id
+                        && (access == Access::Read || link.permission == SharePermission::Edit)
+                }
+                _ => false,
+            };
+            return granted && link.is_active();
+        }
+        Principal::User { user, operator } => (user, operator),
+    };
+    if operator && access == Access::Read {
+        return true;
+    }
+    let member_of = |org_id: &str| user.org_id.as_deref() == Some(org_id);
+    match resource {
+        Resource::Folder(folder) => folder.user_id == user.uid,
+        Resource::Paper(paper) | Resource::Notes(paper) => paper.user_id == user.uid,
+        Resource::Block(block) => block.user_id == user.uid,
+        // writes to team fields are checked on the organization, by its admins
+        Resource::CustomField(field) if field.team => {
+            access == Access::Read && member_of(&field.owner_id)
+        }
+        Resource::CustomField(field) => field.owner_id == user.uid,
+        Resource::Organization(org) => match access {
+            Access::Read => member_of(&org.id),
+            Access::Write => member_of(&org.id) && org.is_admin(&user.uid),
+        },
+    }
+}
+
+/// Fail with `Unauthorized` unless the principal may access the resource.
+pub fn assert_can(access: Access, resource: Resource, principal: Principal) -> ServiceResult<()> {
+    if can(access, resource, principal) {
+        return Ok(());
+    }
+    let verb = match access {
+        Access::Read => "access",
+        Access::Write => "update",
+    };
+    Err(ServiceError::Unauthorized(format!(
+        "You do not have permission to {} this {}",
+        verb,
+        resource.kind()
+    )))
+}
+
+pub fn assert_can_read(resource: Resource, principal: Principal) -> ServiceResult<()> {
+    assert_can(Access::Read, resource, principal)
+}
+
+pub fn assert_can_write(resource: Resource, principal: Principal) -> ServiceResult<()> {
+    assert_can(Access::Write, resource, principal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::custom_field::FieldType;
+
+    fn user(org_id: Option<&str>) -> User {
+        let mut user = User::new_by_email("a@example.com".to_string(), None, String::new());
+        user.org_id = org_id.map(String::from);
+        user
+    }
+
+    fn member(user: &User) -> Principal<'_> {
+        Principal::User {
+            user,
+            operator: false,
+        }
+    }
+
+    fn share_link(paper_id: &str) -> ShareLink {
+        ShareLink {
+            id: "link".to_string(),
+            token: "token".to_string(),
+            paper_id: paper_id.to_string(),
+            owner_id: "owner".to_string(),
+            created_at: bson::DateTime::now(),
+            expires_at: None,
+            handle: "Reviewer A".to_string(),
+            permission: SharePermission::Comment,
+            watermark: None,
+            revoked: false,
+        }
+    }
+
+    fn team_field(org_id: &str) -> CustomField {
+        CustomField {
+            id: "field".to_string(),
+            owner_id: org_id.to_string(),
+            team: true,
+            created_at: bson::DateTime::now(),
+            key: "status".to_string(),
+            name: "Status".to_string(),
+            field_type: FieldType::Text,
+            options: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_owner() {
+        let owner = user(None);
+        let other = user(None);
+        let paper = Paper::new(&owner.uid, "folder", "BERT".to_string());
+        for access in [Access::Read, Access::Write] {
+            assert!(can(access, Resource::Paper(&paper), member(&owner)));
+            assert!(!can(access, Resource::Paper(&paper), member(&other)));
+        }
+        let err = assert_can_write(Resource::Paper(&paper), member(&other)).unwrap_err();
+        assert_eq!(
+            err.message(),
+            "Unauthorized: You do not have permission to update this paper"
+        );
+    }
+
+    #[test]
+    fn test_share_link() {
+        let paper = Paper::new("owner", "folder", "BERT".to_string());
+        let mut link = share_link(&paper.id);
+        assert!(assert_can_read(Resource::Paper(&paper), Principal::ShareLink(&link)).is_ok());
+        assert!(assert_can_write(Resource::Paper(&paper), Principal::ShareLink(&link)).is_err());
+
+        let other = Paper::new("owner", "folder", "GPT-2".to_string());
+        assert!(assert_can_read(Resource::Paper(&other), Principal::ShareLink(&link)).is_err());
+        link.revoked = true;
+        assert!(assert_can_read(Resource::Paper(&paper), Principal::ShareLink(&link)).is_err());
+    }
+
+    #[test]
+    fn test_share_link_notes() {
+        let paper = Paper::new("owner", "folder", "BERT".to_string());
+        let mut link = share_link(&paper.id);
+        assert!(assert_can_read(Resource::Notes(&paper), Principal::ShareLink(&link)).is_ok());
+        assert!(assert_can_write(Resource::Notes(&paper), Principal::ShareLink(&link)).is_err());
+
+        link.permission = SharePermission::Edit;
+        assert!(assert_can_write(Resource::Notes(&paper), Principal::ShareLink(&link)).is_ok());
+        // the metadata stays read only
+        assert!(assert_can_write(Resource::Paper(&paper), Principal::ShareLink(&link)).is_err());
+        link.revoked = true;
+        assert!(assert_can_write(Resource::Notes(&paper), Principal::ShareLink(&link)).is_err());
+    }
+
+    #[test]
+    fn test_team() {
+        let admin = user(Some("org"));
+        let mut org = Organization::new("Lab".to_string(), &admin.uid);
+        org.id = "org".to_string();
+        let colleague = user(Some("org"));
+        let outsider = user(None);
+        let field = team_field("org");
+
+        assert!(assert_can_read(Resource::Organization(&org), member(&colleague)).is_ok());
+        assert!(assert_can_write(Resource::Organization(&org), member(&colleague)).is_err());
+        assert!(assert_can_write(Resource::Organization(&org), member(&admin)).is_ok());
+        assert!(assert_can_read(Resource::Organization(&org), member(&outsider)).is_err());
+
+        assert!(assert_can_read(Resource::CustomField(&field), member(&colleague)).is_ok());
+        assert!(assert_can_write(Resource::CustomField(&field), member(&admin)).is_err());
+        assert!(assert_can_read(Resource::CustomField(&field), member(&outsider)).is_err());
+    }
+
+    #[test]
+    fn test_operator() {
+        let owner = user(None);
+        let operator = user(None);
+        let folder = Folder::new_from_template(
+            &owner.uid,
+            None,
+            &crate::model::organization::FolderTemplate {
+                name: "Inbox".to_string(),
+                description: None,
+                children: Vec::new(),
+            },
+        );
+        let principal = Principal::User {
+            user: &operator,
+            operator: true,
+        };
+        assert!(assert_can_read(Resource::Folder(&folder), principal).is_ok());
+        assert!(assert_can_write(Resource::Folder(&folder), principal).is_err());
+    }
+}
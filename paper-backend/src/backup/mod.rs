@@ -0,0 +1,434 @@
+use std::{
+    collections::HashSet,
+    io::{Cursor, Read},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use bson::{Bson, Document};
+use salvo::{Depot, FlowCtrl, Request, Response, handler};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zip::ZipArchive;
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult},
+    export::archive::zip_files,
+    migrations::run_migrations,
+    model::{
+        backup::{
+            BACKED_UP_COLLECTIONS, BackupJob, BackupRepository, BackupStatus, backup_file_key,
+        },
+        blob::BlobRepository,
+        paper::Progress,
+    },
+    utils::session::is_mutating,
+};
+
+// version of the layout of the archive, bumped on incompatible changes
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+// the progress is saved every so many blobs copied
+const PROGRESS_INTERVAL: usize = 20;
+
+// one backup or restore at a time
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Held by the backup or restore running, released on drop.
+#[derive(Debug)]
+pub struct RunningGuard(());
+
+impl RunningGuard {
+    /// None while another backup or restore runs.
+    pub fn acquire() -> Option<RunningGuard> {
+        RUNNING
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()
+            .map(|_| RunningGuard(()))
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Marks the restore of the tenant as running, until dropped.
+struct RestoringGuard<'a>(&'a AtomicBool);
+
+impl<'a> RestoringGuard<'a> {
+    fn new(restoring: &'a AtomicBool) -> Self {
+        restoring.store(true, Ordering::SeqCst);
+        RestoringGuard(restoring)
+    }
+}
+
+impl Drop for RestoringGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Refuse the writes while a restore replaces the collections and blobs, they
+/// would be lost or mixed with the restored data. The reads are served.
+#[handler]
+pub async fn refuse_writes_while_restoring(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    let Ok(state) = depot.obtain::<AppDataRef>() else {
+        return;
+    };
+    if state.restoring.load(Ordering::SeqCst) && is_mutating(req.method()) {
+        res.render(ServiceError::RestoreRunning);
+        ctrl.skip_rest();
+    }
+}
+
+/// The contents of the archive, listed first in it.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    version: u32,
+    created_at: i64, // timestamp in milliseconds
+    collections: Vec<CollectionEntry>,
+    blobs: Vec<BlobEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CollectionEntry {
+    name: String,
+    // a document per line, in canonical extended json
+    path: String,
+    count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlobEntry {
+    key: String,
+    path: String,
+    size: u64,
+    sha256: String,
+}
+
+/// The collections and blobs of a snapshot.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    pub collections: Vec<(String, Vec<Document>)>,
+    pub blobs: Vec<(String, Vec<u8>)>,
+}
+
+fn backup_error(e: impl std::fmt::Display) -> ServiceError {
+    ServiceError::InternalServerError(format!("Backup error: {}", e))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// the snapshots are blobs too, but are never part of one
+fn is_backup_key(key: &str) -> bool {
+    key.starts_with(&backup_file_key(""))
+}
+
+/// Pack the snapshot into a zip archive: the manifest, a file per collection
+/// and a file per blob.
+pub fn pack(snapshot: &Snapshot) -> ServiceResult<Vec<u8>> {
+    let mut files = Vec::new();
+    let mut manifest = Manifest {
+        version: FORMAT_VERSION,
+        created_at: bson::DateTime::now().timestamp_millis(),
+        collections: Vec::new(),
+        blobs: Vec::new(),
+    };
+    for (name, docs) in &snapshot.collections {
+        let mut lines = String::new();
+        for doc in docs {
+            lines.push_str(
+                &Bson::Document(doc.clone())
+                    .into_canonical_extjson()
+                    .to_string(),
+            );
+            lines.push('\n');
+        }
+        let path = format!("collections/{}.ndjson", name);
+        manifest.collections.push(CollectionEntry {
+            name: name.clone(),
+            path: path.clone(),
+            count: docs.len(),
+        });
+        files.push((path, lines.into_bytes()));
+    }
+    // keys hold slashes, the blobs are numbered instead
+    for (i, (key, bytes)) in snapshot.blobs.iter().enumerate() {
+        let path = format!("blobs/{}", i);
+        manifest.blobs.push(BlobEntry {
+            key: key.clone(),
+            path: path.clone(),
+            size: bytes.len() as u64,
+            sha256: sha256_hex(bytes),
+        });
+        files.push((path, bytes.clone()));
+    }
+    let manifest = serde_json::to_vec_pretty(&manifest).map_err(backup_error)?;
+    files.insert(0, (MANIFEST_FILE.to_string(), manifest));
+    zip_files(&files)
+}
+
+fn read_file(archive: &mut ZipArchive<Cursor<&[u8]>>, path: &str) -> ServiceResult<Vec<u8>> {
+    let mut file = archive.by_name(path).map_err(backup_error)?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Read back an archive of [`pack`], checking every blob against its checksum
+/// and every collection against the ones backed up.
+pub fn unpack(archive: &[u8]) -> ServiceResult<Snapshot> {
+    let mut archive = ZipArchive::new(Cursor::new(archive)).map_err(backup_error)?;
+    let manifest: Manifest =
+        serde_json::from_slice(&read_file(&mut archive, MANIFEST_FILE)?).map_err(backup_error)?;
+    if manifest.version != FORMAT_VERSION {
+        return Err(backup_error(format!(
+            "unsupported archive version {}",
+            manifest.version
+        )));
+    }
+
+    let mut snapshot = Snapshot::default();
+    for entry in manifest.collections {
+        if !BACKED_UP_COLLECTIONS.contains(&entry.name.as_str()) {
+            return Err(backup_error(format!("unknown collection {}", entry.name)));
+        }
+        let lines =
+            String::from_utf8(read_file(&mut archive, &entry.path)?).map_err(backup_error)?;
+        let docs = lines
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let value: serde_json::Value = serde_json::from_str(line).map_err(backup_error)?;
+                match Bson::try_from(value).map_err(backup_error)? {
+                    Bson::Document(doc) => Ok(doc),
+                    _ => Err(backup_error(format!("not a document in {}", entry.path))),
+                }
+            })
+            .collect::<ServiceResult<Vec<_>>>()?;
+        if docs.len() != entry.count {
+            return Err(backup_error(format!(
+                "{} documents in {}, {} expected",
+                docs.len(),
+                entry.path,
+                entry.count
+            )));
+        }
+        snapshot.collections.push((entry.name, docs));
+    }
+    for entry in manifest.blobs {
+        let bytes = read_file(&mut archive, &entry.path)?;
+        if bytes.len() as u64 != entry.size || sha256_hex(&bytes) != entry.sha256 {
+            return Err(backup_error(format!("corrupted blob {}", entry.key)));
+        }
+        snapshot.blobs.push((entry.key, bytes));
+    }
+    Ok(snapshot)
+}
+
+async fn save_progress(state: &AppDataRef, job: &BackupJob, done: usize, total: usize) {
+    let progress = Progress {
+        done: done as u32,
+        total: total as u32,
+    };
+    if let Err(e) = state.db.set_backup_progress(&job.id, progress).await {
+        tracing::warn!("Failed to save progress of backup {}: {}", job.id, e);
+    }
+}
+
+/// Dump every collection at one point in time, then copy the blobs.
+async fn take_snapshot(state: &AppDataRef, job: &BackupJob) -> ServiceResult<Snapshot> {
+    let collections = state.db.dump_collections(BACKED_UP_COLLECTIONS).await?;
+    let keys = state
+        .db
+        .list_blobs()
+        .await?
+        .into_iter()
+        .filter(|key| !is_backup_key(key))
+        .collect::<Vec<_>>();
+    let total = collections.len() + keys.len();
+    save_progress(state, job, collections.len(), total).await;
+
+    let mut blobs = Vec::new();
+    for (i, key) in keys.into_iter().enumerate() {
+        // deleted since it was listed
+        if let Some(bytes) = state.db.get_blob(&key).await? {
+            blobs.push((key, bytes));
+        }
+        if (i + 1) % PROGRESS_INTERVAL == 0 {
+            save_progress(state, job, collections.len() + i + 1, total).await;
+        }
+    }
+    save_progress(state, job, total, total).await;
+    Ok(Snapshot { collections, blobs })
+}
+
+/// Replace the collections and blobs with the ones of the snapshot, then run
+/// the migrations the snapshot predates.
+async fn load_snapshot(
+    state: &AppDataRef,
+    job: &BackupJob,
+    snapshot: Snapshot,
+) -> ServiceResult<()> {
+    let total = snapshot.collections.len() + snapshot.blobs.len();
+    let mut done = snapshot.collections.len();
+    state.db.replace_collections(snapshot.collections).await?;
+    save_progress(state, job, done, total).await;
+
+    let keys = snapshot
+        .blobs
+        .iter()
+        .map(|(key, _)| key.clone())
+        .collect::<HashSet<_>>();
+    for (key, bytes) in snapshot.blobs {
+        state.db.put_blob(&key, &bytes).await?;
+        done += 1;
+        if done % PROGRESS_INTERVAL == 0 {
+            save_progress(state, job, done, total).await;
+        }
+    }
+    for key in state.db.list_blobs().await? {
+        if !keys.contains(&key) && !is_backup_key(&key) {
+            state.db.delete_blob(&key).await?;
+        }
+    }
+    save_progress(state, job, total, total).await;
+
+    run_migrations(state.db.as_ref(), false).await?;
+    Ok(())
+}
+
+async fn finish(state: &AppDataRef, job: &BackupJob, result: ServiceResult<Option<u64>>) {
+    let (status, size, error) = match result {
+        Ok(size) => (BackupStatus::Ready, size, None),
+        Err(e) => {
+            tracing::error!("Backup job {} failed: {}", job.id, e);
+            (BackupStatus::Failed, None, Some(e.to_string()))
+        }
+    };
+    if let Err(e) = state
+        .db
+        .finish_backup_job(&job.id, status, size, error)
+        .await
+    {
+        tracing::error!("Failed to update status of backup job {}: {}", job.id, e);
+    }
+}
+
+/// Take a snapshot of the database and its blobs, and store its archive as a
+/// blob of its own.
+pub async fn run_backup(state: AppDataRef, job: BackupJob, _running: RunningGuard) {
+    let result = async {
+        let snapshot = take_snapshot(&state, &job).await?;
+        let archive = pack(&snapshot)?;
+        state
+            .db
+            .put_blob(&backup_file_key(&job.id), &archive)
+            .await?;
+        Ok(Some(archive.len() as u64))
+    }
+    .await;
+    finish(&state, &job, result).await;
+}
+
+/// Replace the database and its blobs with the snapshot of a backup. Nothing
+/// is written unless the whole archive reads back intact, and the writes of the
+/// api are refused until the restore ends.
+pub async fn run_restore(
+    state: AppDataRef,
+    job: BackupJob,
+    backup_id: String,
+    _running: RunningGuard,
+) {
+    let result = async {
+        let archive = state
+            .db
+            .get_blob(&backup_file_key(&backup_id))
+            .await?
+            .ok_or_else(|| ServiceError::NotFound("Backup archive".to_string()))?;
+        let snapshot = unpack(&archive)?;
+        let _restoring = RestoringGuard::new(&state.restoring);
+        load_snapshot(&state, &job, snapshot).await?;
+        tracing::info!("Restored backup {}", backup_id);
+        Ok(None)
+    }
+    .await;
+    finish(&state, &job, result).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::doc;
+
+    use super::*;
+    use crate::model::constant::{PAPER_COLLECTION_NAME, USER_COLLECTION_NAME};
+
+    #[test]
+    fn test_pack_unpack() {
+        let snapshot = Snapshot {
+            collections: vec![
+                (
+                    USER_COLLECTION_NAME.to_string(),
+                    vec![doc! { "_id": "u1", "created_at": bson::DateTime::now(), "n": 1i64 }],
+                ),
+                (PAPER_COLLECTION_NAME.to_string(), Vec::new()),
+            ],
+            blobs: vec![("paper/p1".to_string(), b"%PDF-1.7".to_vec())],
+        };
+        let archive = pack(&snapshot).unwrap();
+        let unpacked = unpack(&archive).unwrap();
+        assert_eq!(unpacked.collections, snapshot.collections);
+        assert_eq!(unpacked.blobs, snapshot.blobs);
+
+        let mut unknown = Snapshot::default();
+        unknown
+            .collections
+            .push(("backups".to_string(), Vec::new()));
+        assert!(unpack(&pack(&unknown).unwrap()).is_err());
+        assert!(is_backup_key(&backup_file_key("b1")));
+        assert!(!is_backup_key("paper/p1"));
+    }
+
+    #[tokio::test]
+    async fn test_replace_collections() {
+        use std::sync::Arc;
+
+        use crate::model::document::{DocumentDatabase, Query, memory::MemoryStore};
+
+        let db = DocumentDatabase::new(Arc::new(MemoryStore::default()));
+        let users = |ids: &[&str]| {
+            let docs = ids.iter().map(|id| doc! { "_id": *id }).collect();
+            vec![(USER_COLLECTION_NAME.to_string(), docs)]
+        };
+        async fn stored(db: &DocumentDatabase) -> usize {
+            db.find_documents(USER_COLLECTION_NAME, Query::default())
+                .await
+                .unwrap()
+                .len()
+        }
+        db.replace_collections(users(&["u1", "u2"])).await.unwrap();
+        assert_eq!(stored(&db).await, 2);
+        // all or none
+        let mut collections = users(&["u3"]);
+        collections.extend(users(&["u4", "u4"]));
+        assert!(db.replace_collections(collections).await.is_err());
+        assert_eq!(stored(&db).await, 2);
+        db.replace_collections(users(&["u3"])).await.unwrap();
+        assert_eq!(stored(&db).await, 1);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{
+    app_data::AppDataRef,
+    dedup::normalize_title,
+    error::ServiceResult,
+    model::{
+        citation::{Citation, CitationRepository},
+        paper::{Paper, PaperRepository},
+    },
+    pdf::references::{ParsedReference, parse_references},
+};
+
+// titles with fewer words are too generic to be matched inside a reference
+const MIN_MATCH_TITLE_WORDS: usize = 3;
+
+/// The paper of the library the reference points to: same DOI, or the
+/// normalized title appears in the reference.
+pub fn match_reference<'a>(reference: &ParsedReference, library: &'a [Paper]) -> Option<&'a Paper> {
+    if let Some(doi) = &reference.doi {
+        let by_doi = library.iter().find(|paper| {
+            paper
+                .doi
+                .as_deref()
+                .is_some_and(|d| d.trim().eq_ignore_ascii_case(doi))
+        });
+        if by_doi.is_some() {
+            return by_doi;
+        }
+    }
+    let raw = normalize_title(&reference.raw);
+    library.iter().find(|paper| {
+        let title = normalize_title(&paper.title);
+        title.split(' ').count() >= MIN_MATCH_TITLE_WORDS && raw.contains(&title)
+    })
+}
+
+/// Rebuild the references of the paper from its extracted pages, or from
+/// Crossref when the file has no parsable reference list, linking them to
+/// the papers of the library. Returns the number of references.
+pub async fn refresh_citations(
+    state: &AppDataRef,
+    paper: &Paper,
+    pages: &[String],
+) -> ServiceResult<usize> {
+    let mut references = parse_references(pages);
+    if let (true, Some(doi)) = (references.is_empty(), &paper.doi) {
+        references = state.crossref.fetch_references(doi).await?;
+    }
+
+    let library = state
+        .db
+        .get_papers_by_user_id(&paper.user_id)
+        .await?
+        .into_iter()
+        .filter(|p| p.id != paper.id)
+        .collect::<Vec<_>>();
+    let citations = references
+        .into_iter()
+        .map(|reference| {
+            let cited_id = match_reference(&reference, &library).map(|p| p.id.clone());
+            Citation {
+                cited_id,
+                ..Citation::new(&paper.user_id, &paper.id, reference.raw, reference.doi)
+            }
+        })
+        .collect::<Vec<_>>();
+    let count = citations.len();
+    state.db.replace_citations(&paper.id, citations).await?;
+    Ok(count)
+}
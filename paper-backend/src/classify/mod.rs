@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use ai_flow_synth::llm::model::ChatMessage;
+use serde::Deserialize;
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult},
+    llm::{
+        LlmClient,
+        prompt::{PAPER_CLASSIFY_PROMPT, render_prompt},
+    },
+    model::{
+        folder::Folder,
+        paper::{Paper, PaperRepository, PaperSuggestions},
+        usage::UsageEvent,
+    },
+    resilience::record_usage,
+    utils::{cache::CacheKey, validate::MAX_TAG_CHARS},
+};
+
+// usage feature of the llm calls classifying the imported papers
+const CLASSIFY_FEATURE: &str = "classify";
+const MAX_SUGGESTED_TAGS: usize = 5;
+// existing tags listed to the model, the library taxonomy can be large
+const MAX_LISTED_TAGS: usize = 200;
+const ABSTRACT_MAX_CHARS: usize = 4000;
+
+/// Answer of the model, `folder` is the number of a listed folder.
+#[derive(Debug, Default, Deserialize)]
+struct Classification {
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    folder: Option<usize>,
+}
+
+/// The folders a paper can be filed in, as (id, path from the root).
+fn candidate_folders(folders: &[Folder]) -> Vec<(String, String)> {
+    let by_id: HashMap<&str, &Folder> = folders.iter().map(|f| (f.id.as_str(), f)).collect();
+    let mut candidates = folders
+        .iter()
+        .filter(|folder| !folder.archived && !folder.is_smart())
+        .map(|folder| {
+            let mut names = vec![folder.name.as_str()];
+            let mut parent = folder.parent_id.as_deref();
+            // bounded in case of a cycle
+            while let Some(parent_folder) = parent.and_then(|id| by_id.get(id)) {
+                if names.len() > folders.len() {
+                    break;
+                }
+                names.push(parent_folder.name.as_str());
+                parent = parent_folder.parent_id.as_deref();
+            }
+            names.reverse();
+            (folder.id.clone(), names.join(" / "))
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| a.1.cmp(&b.1));
+    candidates
+}
+
+/// Suggestions from the answer of the model: the json object in it, tags spelled
+/// as the existing ones when they match, without those the paper already has.
+fn parse_suggestions(
+    answer: &str,
+    paper: &Paper,
+    existing_tags: &[String],
+    folders: &[(String, String)],
+) -> (Vec<String>, Option<String>) {
+    let classification = match (answer.find('{'), answer.rfind('}')) {
+        (Some(start), Some(end)) if start < end => {
+            serde_json::from_str::<Classification>(&answer[start..=end]).unwrap_or_default()
+        }
+        _ => Classification::default(),
+    };
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in classification.tags {
+        let tag = tag.trim();
+        if tag.is_empty() || tag.chars().count() > MAX_TAG_CHARS {
+            continue;
+        }
+        let tag = existing_tags
+            .iter()
+            .find(|existing| existing.eq_ignore_ascii_case(tag))
+            .map_or(tag, |existing| existing.as_str());
+        let known = |t: &String| t.eq_ignore_ascii_case(tag);
+        if !paper.tags.iter().any(known) && !tags.iter().any(known) {
+            tags.push(tag.to_string());
+        }
+        if tags.len() == MAX_SUGGESTED_TAGS {
+            break;
+        }
+    }
+
+    let folder_id = classification
+        .folder
+        .and_then(|number| folders.get(number.checked_sub(1)?))
+        .map(|(id, _)| id.clone())
+        .filter(|id| *id != paper.folder_id);
+    (tags, folder_id)
+}
+
+/// Ask the model for tags and a folder fitting the imported paper, from its
+/// abstract and the taxonomy of the user, and store them on the paper. Papers
+/// without abstract are skipped.
+pub async fn suggest_for_paper(state: &AppDataRef, paper_id: &str) -> ServiceResult<()> {
+    let Some(paper) = state.db.get_paper_by_id(paper_id).await? else {
+        return Ok(());
+    };
+    let Some(r#abstract) = paper.r#abstract.as_deref() else {
+        return Ok(());
+    };
+    let model = state.llm.model.clone();
+    match state.ensure_quota(&paper.user_id, &model).await {
+        Err(ServiceError::QuotaExceeded { .. }) => return Ok(()),
+        result => result?,
+    }
+
+    let folders = candidate_folders(&state.cached_folders(&paper.user_id).await?);
+    let mut existing_tags = state.db.get_user_tags(&paper.user_id).await?;
+    existing_tags.sort();
+    existing_tags.truncate(MAX_LISTED_TAGS);
+    let folder_list = folders
+        .iter()
+        .enumerate()
+        .map(|(i, (_, path))| format!("{}. {}", i + 1, path))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let r#abstract: String = r#abstract.chars().take(ABSTRACT_MAX_CHARS).collect();
+    let prompt = render_prompt(
+        &state.db,
+        PAPER_CLASSIFY_PROMPT,
+        None,
+        &[
+            ("title", paper.title.as_str()),
+            ("abstract", r#abstract.as_str()),
+            ("tags", existing_tags.join(", ").as_str()),
+            ("folders", folder_list.as_str()),
+        ],
+    )
+    .await;
+    let messages = vec![
+        ChatMessage::system("You classify academic papers. Answer with json only."),
+        ChatMessage::user(prompt),
+    ];
+    let api_key = state.user_llm_key(&paper.user_id, &model).await?;
+    let answer = state
+        .llm
+        .complete_with_key(Some(&model), &messages, api_key.as_deref())
+        .await?;
+    let (input_tokens, output_tokens) = LlmClient::estimate_usage(&messages, &answer);
+    let mut usage = UsageEvent::new(
+        &paper.user_id,
+        &model,
+        CLASSIFY_FEATURE,
+        input_tokens,
+        output_tokens,
+    );
+    usage.own_key = api_key.is_some();
+    record_usage(state, usage).await;
+
+    let (tags, folder_id) = parse_suggestions(&answer, &paper, &existing_tags, &folders);
+    if tags.is_empty() && folder_id.is_none() {
+        return Ok(());
+    }
+    let suggestions = PaperSuggestions {
+        tags,
+        folder_id,
+        model,
+        created_at: bson::DateTime::now(),
+    };
+    state
+        .db
+        .set_paper_suggestions(&paper.id, &suggestions)
+        .await?;
+    state.invalidate(&[CacheKey::Paper(&paper.id)]).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_suggestions() {
+        let mut paper = Paper::new("user", "inbox", "Attention Is All You Need".to_string());
+        paper.tags = vec!["nlp".to_string()];
+        let existing = vec!["NLP".to_string(), "Transformers".to_string()];
+        let folders = vec![
+            ("inbox".to_string(), "Inbox".to_string()),
+            ("models".to_string(), "Thesis / Models".to_string()),
+        ];
+
+        let answer = "Sure:\n```json\n{\"tags\": [\"transformers\", \"nlp\", \"attention\"], \
+            \"folder\": 2}\n```";
+        let (tags, folder_id) = parse_suggestions(answer, &paper, &existing, &folders);
+        assert_eq!(tags, vec!["Transformers", "attention"]);
+        assert_eq!(folder_id.as_deref(), Some("models"));
+
+        let (tags, folder_id) = parse_suggestions("{\"folder\": 1}", &paper, &existing, &folders);
+        assert!(tags.is_empty());
+        assert_eq!(folder_id, None);
+        let (tags, folder_id) = parse_suggestions("no idea", &paper, &existing, &folders);
+        assert!(tags.is_empty() && folder_id.is_none());
+    }
+}
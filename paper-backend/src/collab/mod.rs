@@ -0,0 +1,302 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use tokio::sync::broadcast;
+use yrs::{Doc, GetString, ReadTxn, StateVector, Text, Transact, Update, updates::decoder::Decode};
+
+use crate::{
+    app_data::AppData,
+    error::{ServiceError, ServiceResult},
+    model::{blob::BlobRepository, note::paper_note_key, paper::Paper},
+};
+
+// the shared text holding the markdown of the notes
+const NOTES_TEXT: &str = "notes";
+// updates buffered for slow participants, beyond they get the whole state again
+const ROOM_BUFFER: usize = 256;
+// the author of the edits merged from the stored state, relayed to everyone
+const STORED_STATE: u64 = u64::MAX;
+
+fn new_doc(markdown: &str) -> Doc {
+    let doc = Doc::new();
+    let text = doc.get_or_insert_text(NOTES_TEXT);
+    text.insert(&mut doc.transact_mut(), 0, markdown);
+    doc
+}
+
+fn markdown_of(doc: &Doc) -> String {
+    doc.get_or_insert_text(NOTES_TEXT)
+        .get_string(&doc.transact())
+}
+
+fn state_of(doc: &Doc) -> Vec<u8> {
+    doc.transact()
+        .encode_state_as_update_v1(&StateVector::default())
+}
+
+fn apply_update(doc: &Doc, bytes: &[u8]) -> ServiceResult<()> {
+    let invalid = |e: &dyn fmt::Display| {
+        ServiceError::invalid_field(
+            "update",
+            "invalid_update",
+            format!("Invalid notes update: {}", e),
+        )
+    };
+    let update = Update::decode_v1(bytes).map_err(|e| invalid(&e))?;
+    doc.transact_mut()
+        .apply_update(update)
+        .map_err(|e| invalid(&e))
+}
+
+/// An update of the notes, relayed to every participant but its author.
+#[derive(Debug, Clone)]
+pub struct NoteUpdate {
+    pub participant: u64,
+    pub bytes: Arc<Vec<u8>>,
+}
+
+/// The notes edited were replaced by an update of the paper meanwhile.
+pub fn notes_replaced(paper_id: &str) -> ServiceError {
+    ServiceError::VersionConflict(format!(
+        "Notes of paper {} were replaced while edited",
+        paper_id
+    ))
+}
+
+/// The notes of a closed room, to save into the paper.
+#[derive(Debug)]
+pub struct ClosedNotes {
+    // the notes of the paper when the room opened
+    pub base: String,
+    pub markdown: String,
+}
+
+/// The notes of a paper open for editing, merged by a CRDT so concurrent
+/// edits never conflict.
+pub struct NoteRoom {
+    paper_id: String,
+    base: String,
+    doc: Mutex<Doc>,
+    // held while the state is stored, whether there is a stored state to
+    // merge: the one the room opened from or stored
+    stored: tokio::sync::Mutex<bool>,
+    updates: broadcast::Sender<NoteUpdate>,
+}
+
+impl NoteRoom {
+    pub fn subscribe(&self) -> broadcast::Receiver<NoteUpdate> {
+        self.updates.subscribe()
+    }
+
+    pub fn markdown(&self) -> String {
+        markdown_of(&self.doc.lock().unwrap())
+    }
+
+    /// The whole CRDT state, as an update bringing a new participant up to date.
+    pub fn state(&self) -> Vec<u8> {
+        state_of(&self.doc.lock().unwrap())
+    }
+
+    /// Merge an update of a participant, store the new state and relay the
+    /// update to the others.
+    pub async fn apply(
+        &self,
+        state: &AppData,
+        participant: u64,
+        bytes: Vec<u8>,
+    ) -> ServiceResult<()> {
+        apply_update(&self.doc.lock().unwrap(), &bytes)?;
+        self.store(state).await?;
+        self.relay(participant, bytes);
+        Ok(())
+    }
+
+    // fails only when nobody else is listening
+    fn relay(&self, participant: u64, bytes: Vec<u8>) {
+        let _ = self.updates.send(NoteUpdate {
+            participant,
+            bytes: Arc::new(bytes),
+        });
+    }
+
+    /// Store the state of the room, merged first with the stored one, which
+    /// the rooms of the other instances write too. The writes of the room run
+    /// one at a time, each storing the state merged last.
+    async fn store(&self, state: &AppData) -> ServiceResult<()> {
+        let mut stored = self.stored.lock().await;
+        let key = paper_note_key(&self.paper_id);
+        match state.db.get_blob(&key).await? {
+            Some(bytes) => {
+                let bytes = state.open_note(bytes)?;
+                let merged = {
+                    let doc = self.doc.lock().unwrap();
+                    let before = doc.transact().state_vector();
+                    apply_update(&doc, &bytes)?;
+                    doc.transact().state_vector() != before
+                };
+                if merged {
+                    self.relay(STORED_STATE, bytes);
+                }
+            }
+            // dropped by an update replacing the notes, see `reset_notes`
+            None if *stored => return Err(notes_replaced(&self.paper_id)),
+            None => {}
+        }
+        let merged = state_of(&self.doc.lock().unwrap());
+        state.db.put_blob(&key, &state.seal_note(merged)?).await?;
+        *stored = true;
+        Ok(())
+    }
+}
+
+/// A participant in the editing of the notes of a paper.
+pub struct NoteSession {
+    pub room: Arc<NoteRoom>,
+    pub participant: u64,
+}
+
+/// The notes being edited on this instance, a room per paper as long as
+/// someone edits them.
+#[derive(Default)]
+pub struct NoteRooms {
+    rooms: tokio::sync::Mutex<HashMap<String, (Arc<NoteRoom>, usize)>>,
+    next_participant: AtomicU64,
+}
+
+impl fmt::Debug for NoteRooms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NoteRooms").finish_non_exhaustive()
+    }
+}
+
+impl NoteRooms {
+    /// Join the room of the paper, opened from the stored CRDT state or else
+    /// from the notes of the paper.
+    pub async fn join(&self, state: &AppData, paper: &Paper) -> ServiceResult<NoteSession> {
+        let mut rooms = self.rooms.lock().await;
+        let room = match rooms.get_mut(&paper.id) {
+            Some((room, participants)) => {
+                *participants += 1;
+                room.clone()
+            }
+            None => {
+                let base = paper.content.clone().unwrap_or_default();
+                let (doc, stored) = match state.db.get_blob(&paper_note_key(&paper.id)).await? {
+                    Some(bytes) => {
+                        let doc = Doc::new();
+                        apply_update(&doc, &state.open_note(bytes)?)?;
+                        (doc, true)
+                    }
+                    None => (new_doc(&base), false),
+                };
+                let room = Arc::new(NoteRoom {
+                    paper_id: paper.id.clone(),
+                    base,
+                    doc: Mutex::new(doc),
+                    stored: tokio::sync::Mutex::new(stored),
+                    updates: broadcast::channel(ROOM_BUFFER).0,
+                });
+                rooms.insert(paper.id.clone(), (room.clone(), 1));
+                room
+            }
+        };
+        Ok(NoteSession {
+            room,
+            participant: self.next_participant.fetch_add(1, Ordering::SeqCst),
+        })
+    }
+
+    /// Leave the room, closed with the last participant. The merged notes are
+    /// returned on close, to be saved into the paper.
+    pub async fn leave(&self, session: NoteSession) -> Option<ClosedNotes> {
+        let mut rooms = self.rooms.lock().await;
+        let (room, participants) = rooms.get_mut(&session.room.paper_id)?;
+        *participants -= 1;
+        if *participants > 0 {
+            return None;
+        }
+        let closed = ClosedNotes {
+            base: room.base.clone(),
+            markdown: room.markdown(),
+        };
+        rooms.remove(&session.room.paper_id);
+        Some(closed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_edits() {
+        let server = new_doc("# Notes\n");
+        // two participants start from the same state and edit offline
+        let (alice, bob) = (Doc::new(), Doc::new());
+        apply_update(&alice, &state_of(&server)).unwrap();
+        apply_update(&bob, &state_of(&server)).unwrap();
+        let before = server.transact().state_vector();
+        let edit = |doc: &Doc, text: &str| {
+            let notes = doc.get_or_insert_text(NOTES_TEXT);
+            let len = notes.len(&doc.transact());
+            notes.insert(&mut doc.transact_mut(), len, text);
+            doc.transact().encode_state_as_update_v1(&before)
+        };
+        let from_alice = edit(&alice, "alice\n");
+        let from_bob = edit(&bob, "bob\n");
+
+        apply_update(&server, &from_bob).unwrap();
+        apply_update(&server, &from_alice).unwrap();
+        // applying an update twice changes nothing
+        apply_update(&server, &from_alice).unwrap();
+        let merged = markdown_of(&server);
+        assert!(merged.starts_with("# Notes\n"));
+        assert!(merged.contains("alice\n") && merged.contains("bob\n"));
+        assert_eq!(merged.len(), "# Notes\nalice\nbob\n".len());
+
+        assert!(apply_update(&server, &[]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_notes_replaced() {
+        let state = AppData::for_tests().await;
+        let mut paper = Paper::new("u1", "f1", "Title".to_string());
+        paper.content = Some("# Notes\n".to_string());
+        let session = state.notes.join(&state, &paper).await.unwrap();
+        let client = Doc::new();
+        apply_update(&client, &session.room.state()).unwrap();
+        let edit = |text: &str| {
+            let before = client.transact().state_vector();
+            let notes = client.get_or_insert_text(NOTES_TEXT);
+            let len = notes.len(&client.transact());
+            notes.insert(&mut client.transact_mut(), len, text);
+            client.transact().encode_state_as_update_v1(&before)
+        };
+
+        let room = session.room.clone();
+        room.apply(&state, session.participant, edit("alice\n"))
+            .await
+            .unwrap();
+        // the merged state is stored
+        let stored = state.db.get_blob(&paper_note_key(&paper.id)).await;
+        assert!(stored.unwrap().is_some());
+        // an update of the paper drops the state, the room is stale
+        state
+            .db
+            .delete_blob(&paper_note_key(&paper.id))
+            .await
+            .unwrap();
+        let replaced = room.apply(&state, session.participant, edit("bob\n")).await;
+        assert!(matches!(replaced, Err(ServiceError::VersionConflict(_))));
+
+        let closed = state.notes.leave(session).await.unwrap();
+        assert_eq!(closed.base, "# Notes\n");
+        assert!(closed.markdown.contains("alice\n"));
+    }
+}
@@ -0,0 +1,236 @@
+use ai_flow_synth::llm::model::ChatMessage;
+use serde::Deserialize;
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult},
+    llm::{
+        LlmClient,
+        prompt::{PAPER_COMPARE_PROMPT, render_prompt},
+    },
+    model::{
+        comparison::{ComparedPaper, Comparison},
+        paper::{Paper, PaperRepository},
+        usage::UsageEvent,
+    },
+    resilience::record_usage,
+};
+
+// usage feature of the llm calls comparing papers
+const COMPARE_FEATURE: &str = "compare";
+// max characters of each paper given to the model
+const COMPARE_PAPER_MAX_CHARS: usize = 6000;
+
+/// Answer of the model, papers referred to by their number.
+#[derive(Debug, Default, Deserialize)]
+struct ModelComparison {
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    papers: Vec<ModelComparedPaper>,
+    #[serde(default)]
+    contradictions: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ModelComparedPaper {
+    #[serde(default)]
+    paper: usize,
+    #[serde(default)]
+    methods: String,
+    #[serde(default)]
+    datasets: String,
+    #[serde(default)]
+    results: String,
+}
+
+/// The papers of the comparison, in its order. Fails when one was deleted or
+/// does not belong to the user.
+pub async fn compared_papers(
+    state: &AppDataRef,
+    user_id: &str,
+    paper_ids: &[String],
+) -> ServiceResult<Vec<Paper>> {
+    let mut papers = state.db.get_papers_by_ids(user_id, paper_ids).await?;
+    paper_ids
+        .iter()
+        .map(|id| {
+            let index = papers
+                .iter()
+                .position(|p| p.id == *id)
+                .ok_or_else(|| ServiceError::PaperNotFound(id.clone()))?;
+            Ok(papers.swap_remove(index))
+        })
+        .collect()
+}
+
+/// Fill the comparison from the answer of the model: the json object in it,
+/// every paper listed in order even when the model skipped it. Fails when the
+/// answer cannot be read.
+fn parse_comparison(
+    answer: &str,
+    papers: &[Paper],
+    comparison: &mut Comparison,
+) -> ServiceResult<()> {
+    let parsed = match (answer.find('{'), answer.rfind('}')) {
+        (Some(start), Some(end)) if start < end => {
+            serde_json::from_str::<ModelComparison>(&answer[start..=end]).ok()
+        }
+        _ => None,
+    }
+    .ok_or_else(|| ServiceError::LLMError("Unreadable comparison".to_string()))?;
+
+    comparison.summary = parsed.summary.trim().to_string();
+    comparison.papers = papers
+        .iter()
+        .enumerate()
+        .map(|(i, paper)| {
+            let compared = parsed.papers.iter().find(|p| p.paper == i + 1);
+            let field = |f: fn(&ModelComparedPaper) -> &String| {
+                compared
+                    .map(|p| f(p).trim().to_string())
+                    .unwrap_or_default()
+            };
+            ComparedPaper {
+                paper_id: paper.id.clone(),
+                title: paper.title.clone(),
+                methods: field(|p| &p.methods),
+                datasets: field(|p| &p.datasets),
+                results: field(|p| &p.results),
+            }
+        })
+        .collect();
+    comparison.contradictions = parsed
+        .contradictions
+        .into_iter()
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .collect();
+    Ok(())
+}
+
+/// Ask the model to compare the papers, in the order of the comparison, and
+/// write its answer in the comparison.
+pub async fn generate_comparison(
+    state: &AppDataRef,
+    papers: &[Paper],
+    comparison: &mut Comparison,
+) -> ServiceResult<()> {
+    let materials = papers
+        .iter()
+        .enumerate()
+        .map(|(i, paper)| {
+            let body = [
+                paper.r#abstract.as_deref(),
+                paper.summary.as_deref(),
+                paper.content.as_deref(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n\n");
+            let body: String = body.chars().take(COMPARE_PAPER_MAX_CHARS).collect();
+            format!("## [{}] {}\n{}", i + 1, paper.title, body)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let prompt = render_prompt(
+        &state.db,
+        PAPER_COMPARE_PROMPT,
+        None,
+        &[("papers", materials.as_str())],
+    )
+    .await;
+    let messages = vec![
+        ChatMessage::system("You are a research assistant. Answer with json only."),
+        ChatMessage::user(prompt),
+    ];
+    let api_key = state
+        .user_llm_key(&comparison.user_id, &comparison.model)
+        .await?;
+    let answer = state
+        .llm
+        .complete_with_key(Some(&comparison.model), &messages, api_key.as_deref())
+        .await?;
+    let (input_tokens, output_tokens) = LlmClient::estimate_usage(&messages, &answer);
+    let mut usage = UsageEvent::new(
+        &comparison.user_id,
+        &comparison.model,
+        COMPARE_FEATURE,
+        input_tokens,
+        output_tokens,
+    );
+    usage.own_key = api_key.is_some();
+    record_usage(state, usage).await;
+
+    parse_comparison(&answer, papers, comparison)?;
+    comparison.generated_at = bson::DateTime::now();
+    Ok(())
+}
+
+/// The comparison as a markdown document.
+pub fn comparison_markdown(comparison: &Comparison) -> String {
+    let mut markdown = format!(
+        "# Comparison of {} papers\n\n{}\n",
+        comparison.papers.len(),
+        comparison.summary
+    );
+    let sections: [(&str, fn(&ComparedPaper) -> &str); 3] = [
+        ("Methods", |p| p.methods.as_str()),
+        ("Datasets", |p| p.datasets.as_str()),
+        ("Results", |p| p.results.as_str()),
+    ];
+    for (title, field) in sections {
+        markdown.push_str(&format!("\n## {}\n\n", title));
+        for paper in &comparison.papers {
+            let text = field(paper);
+            let text = if text.is_empty() { "Not stated." } else { text };
+            markdown.push_str(&format!("- **{}**: {}\n", paper.title, text));
+        }
+    }
+    markdown.push_str("\n## Contradictions\n\n");
+    if comparison.contradictions.is_empty() {
+        markdown.push_str("None found.\n");
+    }
+    for contradiction in &comparison.contradictions {
+        markdown.push_str(&format!("- {}\n", contradiction));
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_comparison() {
+        let papers = vec![
+            Paper::new("user", "folder", "BERT".to_string()),
+            Paper::new("user", "folder", "GPT-2".to_string()),
+        ];
+        let mut comparison = Comparison::new(
+            "user",
+            papers.iter().map(|p| p.id.clone()).collect(),
+            "mock".to_string(),
+        );
+        let answer = "```json\n{\"summary\": \"Two pretrained language models.\", \
+            \"papers\": [{\"paper\": 2, \"methods\": \"Decoder only\", \"results\": \"Zero-shot\"}], \
+            \"contradictions\": [\"Masked vs causal objective\", \" \"]}\n```";
+        parse_comparison(answer, &papers, &mut comparison).unwrap();
+        assert_eq!(comparison.summary, "Two pretrained language models.");
+        assert_eq!(comparison.papers.len(), 2);
+        assert_eq!(comparison.papers[0].title, "BERT");
+        assert!(comparison.papers[0].methods.is_empty());
+        assert_eq!(comparison.papers[1].methods, "Decoder only");
+        assert_eq!(
+            comparison.contradictions,
+            vec!["Masked vs causal objective"]
+        );
+
+        let markdown = comparison_markdown(&comparison);
+        assert!(markdown.contains("- **BERT**: Not stated.\n"));
+        assert!(markdown.contains("- **GPT-2**: Zero-shot\n"));
+
+        assert!(parse_comparison("I cannot compare", &papers, &mut comparison).is_err());
+    }
+}
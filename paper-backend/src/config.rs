@@ -1,33 +1,680 @@
 use ai_flow_synth::utils::{LogConfig, MongoConfig};
-use serde::Deserialize;
+use clap::Parser;
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, IntoDeserializer, Unexpected, Visitor},
+};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// `PAPER__BACKEND_CONFIG__ADDRESS` overrides `backend_config.address`
+const ENV_PREFIX: &str = "PAPER__";
+const ENV_SEPARATOR: &str = "__";
+
+/// Command line of the server, its flags override the environment and the file.
+#[derive(Debug, Clone, Parser)]
+#[command(version, about = "Paper api server")]
+pub struct Cli {
+    /// Path of the config file
+    #[arg(default_value = "config.toml")]
+    pub config: PathBuf,
+    /// Address to listen on, repeated for several, overrides `backend_config.address`
+    #[arg(long)]
+    pub address: Vec<String>,
+    /// Log debug messages, overrides `log_config.enable_debug`
+    #[arg(long)]
+    pub debug: bool,
+    /// Override a setting by its dotted path, e.g. `rate_limit_config.enabled=false`
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+    /// Do not reload the config file when it changes
+    #[arg(long)]
+    pub no_watch: bool,
+    /// List the pending database migrations and what they would change, then exit
+    #[arg(long)]
+    pub migrations_dry_run: bool,
+    /// Give the demo account a sample library, replacing its previous one, then exit
+    #[arg(long)]
+    pub seed: bool,
+    /// Print the openapi spec of the latest api version, then exit
+    #[arg(long)]
+    pub openapi: bool,
+}
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub frontend_config: FrontendConfig,
     pub backend_config: BackendConfig,
     pub log_config: LogConfig,
-    pub mongo_config: MongoConfig,
+    // the database is postgres when `postgres_config` is set, mongo otherwise
+    pub mongo_config: Option<MongoConfig>,
+    pub postgres_config: Option<PostgresConfig>,
+    #[serde(default)]
+    pub db_retry_config: DbRetryConfig,
+    pub llm_config: LlmConfig,
+    // more llm providers, for the models they serve
+    #[serde(default)]
+    pub llm_providers: Vec<LlmConfig>,
+    pub embedding_config: Option<EmbeddingConfig>,
+    // scan of the uploaded files, every file is let through when absent
+    pub scan_config: Option<ScanConfig>,
+    #[serde(alias = "smtp")]
+    pub smtp_config: Option<SmtpConfig>,
+    // the users can store secrets, e.g. their llm api keys, only when set
+    pub encryption_config: Option<EncryptionConfig>,
+    #[serde(default)]
+    pub pdf_config: PdfConfig,
+    #[serde(default)]
+    pub search_config: SearchConfig,
+    #[serde(default)]
+    pub legal_config: LegalConfig,
+    #[serde(default)]
+    pub rate_limit_config: RateLimitConfig,
+    #[serde(default)]
+    pub cache_config: CacheConfig,
+    #[serde(default)]
+    pub usage_config: UsageConfig,
+    #[serde(default)]
+    pub quota_config: QuotaConfig,
+    #[serde(default)]
+    pub settings_config: SettingsConfig,
+    #[serde(default)]
+    pub related_config: RelatedConfig,
+    #[serde(default)]
+    pub revision_config: RevisionConfig,
+    #[serde(default)]
+    pub export_config: ExportConfig,
+    #[serde(default)]
+    pub prompt_config: PromptConfig,
+    #[serde(default)]
+    pub body_limit_config: BodyLimitConfig,
+    #[serde(default)]
+    pub timeout_config: TimeoutConfig,
+    #[serde(default)]
+    pub compression_config: CompressionConfig,
+    #[serde(default)]
+    pub security_headers_config: SecurityHeadersConfig,
+    #[serde(default)]
+    pub docs_config: DocsConfig,
+    #[serde(default)]
+    pub circuit_breaker_config: CircuitBreakerConfig,
+    // organizations served besides the default one, each in its own database
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
+    // the grpc server for the internal services runs when set
+    pub grpc_config: Option<GrpcConfig>,
 }
 
 impl Config {
-    pub fn from_path<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
-        let content = fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&content)?;
+    /// Load the config in layers: the file, then the secrets and the `PAPER__`
+    /// environment variables, then the flags of the command line. Fails listing
+    /// the secrets missing from all of them.
+    pub fn load(cli: &Cli) -> anyhow::Result<Self> {
+        let env = |name: &str| std::env::var(name).ok();
+        let content = fs::read_to_string(&cli.config)?;
+        let mut table: toml::Table = toml::from_str(&content)?;
+        fill_secrets(&mut table, env)?;
+        // parsed by the type of the setting, see `Overridden`
+        let mut overrides = toml::Table::new();
+        for (key, value) in std::env::vars() {
+            if let Some(path) = env_override_path(&key) {
+                set_path(&mut table, &path, toml::Value::String(value.clone()))?;
+                set_path(&mut overrides, &path, toml::Value::String(value))?;
+            }
+        }
+        for item in &cli.overrides {
+            let (path, value) = item
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid override {}, expected KEY=VALUE", item))?;
+            let value = toml::Value::String(value.trim().to_string());
+            set_path(&mut table, path.trim(), value.clone())?;
+            set_path(&mut overrides, path.trim(), value)?;
+        }
+        if !cli.address.is_empty() {
+            let addresses = cli.address.iter().cloned().map(toml::Value::String);
+            set_path(
+                &mut table,
+                "backend_config.address",
+                toml::Value::Array(addresses.collect()),
+            )?;
+        }
+        if cli.debug {
+            set_path(
+                &mut table,
+                "log_config.enable_debug",
+                toml::Value::Boolean(true),
+            )?;
+        }
+        check_secrets(&table)?;
+        let config = Config::deserialize(Overridden::new(table, overrides))?;
         Ok(config)
     }
 }
 
+/// Dotted path of the setting an environment variable overrides, if it does.
+fn env_override_path(key: &str) -> Option<String> {
+    let path = key.strip_prefix(ENV_PREFIX)?;
+    let parts = path
+        .split(ENV_SEPARATOR)
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    if parts.iter().any(String::is_empty) {
+        return None;
+    }
+    Some(parts.join("."))
+}
+
+/// A toml value, e.g. `42`, `true` or `["a", "b"]`, a bare string otherwise.
+fn parse_value(raw: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+/// The config table with the overrides kept as strings, parsed by the type of
+/// the setting they override: `12345` stays a string for a password, `true` is
+/// a bool for a flag. Only where the type is not known, e.g. in the tagged
+/// enums, an override is guessed from its toml syntax.
+struct Overridden {
+    value: toml::Value,
+    // the overrides at the same path, the values of the file are read as is
+    overrides: Option<toml::Value>,
+}
+
+impl Overridden {
+    fn new(table: toml::Table, overrides: toml::Table) -> Self {
+        Overridden {
+            value: toml::Value::Table(table),
+            overrides: Some(toml::Value::Table(overrides)),
+        }
+    }
+
+    /// The override given for the value, if it is one.
+    fn raw_override(&self) -> Option<&str> {
+        match (&self.value, &self.overrides) {
+            (toml::Value::String(raw), Some(overrides)) if !overrides.is_table() => Some(raw),
+            _ => None,
+        }
+    }
+
+    /// The value, an override in toml syntax parsed, e.g. `["a", "b"]`.
+    fn parsed(self) -> toml::Value {
+        match self.raw_override() {
+            Some(raw) => parse_value(raw),
+            None => self.value,
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, toml::de::Error> for Overridden {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// The override parsed to the type asked for, the value itself otherwise.
+macro_rules! parse_override {
+    ($($method:ident => $visit:ident: $ty:ty),* $(,)?) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            let Some(raw) = self.raw_override() else {
+                return self.value.$method(visitor);
+            };
+            match raw.parse::<$ty>() {
+                Ok(value) => visitor.$visit(value),
+                Err(_) => Err(de::Error::invalid_value(Unexpected::Str(raw), &visitor)),
+            }
+        }
+    )*};
+}
+
+/// The value itself, as it has the type asked for or cannot be parsed to it.
+macro_rules! as_is {
+    ($($method:ident),* $(,)?) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            self.value.$method(visitor)
+        }
+    )*};
+}
+
+impl<'de> Deserializer<'de> for Overridden {
+    type Error = toml::de::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let overrides = match &self.overrides {
+            Some(toml::Value::Table(overrides)) => Some(overrides.clone()),
+            _ => None,
+        };
+        match self.parsed() {
+            toml::Value::Table(table) => visitor.visit_map(de::value::MapDeserializer::new(
+                table.into_iter().map(|(key, value)| {
+                    let overrides = overrides
+                        .as_ref()
+                        .and_then(|overrides| overrides.get(&key))
+                        .cloned();
+                    (key, Overridden { value, overrides })
+                }),
+            )),
+            toml::Value::Array(values) => visitor.visit_seq(de::value::SeqDeserializer::new(
+                values.into_iter().map(|value| Overridden {
+                    value,
+                    overrides: None,
+                }),
+            )),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.value.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.value.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    parse_override! {
+        deserialize_bool => visit_bool: bool,
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+    }
+
+    as_is! {
+        deserialize_char,
+        deserialize_str,
+        deserialize_string,
+        deserialize_bytes,
+        deserialize_byte_buf,
+        deserialize_unit,
+        deserialize_identifier,
+        deserialize_ignored_any,
+    }
+}
+
+/// Set the value at the dotted path, creating the missing tables.
+fn set_path(table: &mut toml::Table, path: &str, value: toml::Value) -> anyhow::Result<()> {
+    let (parents, key) = match path.rsplit_once('.') {
+        Some((parents, key)) => (parents.split('.').collect::<Vec<_>>(), key),
+        None => (Vec::new(), path),
+    };
+    let mut current = table;
+    for part in parents {
+        current = current
+            .entry(part)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("Cannot override {}, {} is not a table", path, part))?;
+    }
+    current.insert(key.to_string(), value);
+    Ok(())
+}
+
+/// A secret of the config which can be given by the `env` variable instead, or
+/// by the file at `{env}_FILE` (e.g. a Docker secret).
+#[derive(Debug)]
+struct Secret {
+    path: String,
+    env: String,
+}
+
+impl Secret {
+    fn new(path: impl Into<String>, env: impl Into<String>) -> Self {
+        Secret {
+            path: path.into(),
+            env: env.into(),
+        }
+    }
+
+    fn read(&self, env: impl Fn(&str) -> Option<String>) -> anyhow::Result<Option<String>> {
+        if let Some(value) = env(&self.env).filter(|value| !value.is_empty()) {
+            return Ok(Some(value));
+        }
+        let Some(file) = env(&format!("{}_FILE", self.env)) else {
+            return Ok(None);
+        };
+        let value = fs::read_to_string(&file)
+            .map_err(|e| anyhow::anyhow!("Failed to read {} from {}: {}", self.env, file, e))?;
+        Ok(Some(value.trim_end().to_string()))
+    }
+}
+
+/// The value at the dotted path, array items by their index.
+fn value_at<'a>(table: &'a toml::Table, path: &str) -> Option<&'a toml::Value> {
+    let (first, rest) = path.split_once('.').unwrap_or((path, ""));
+    let value = table.get(first)?;
+    rest.split('.')
+        .filter(|part| !part.is_empty())
+        .try_fold(value, |value, part| match value {
+            toml::Value::Array(items) => items.get(part.parse::<usize>().ok()?),
+            value => value.get(part),
+        })
+}
+
+fn needs_api_key(llm: Option<&toml::Value>) -> bool {
+    let provider = llm
+        .and_then(|llm| llm.get("provider"))
+        .and_then(toml::Value::as_str);
+    !matches!(provider, Some("ollama" | "mock"))
+}
+
+/// The secrets needed by the services the config enables.
+fn required_secrets(table: &toml::Table) -> Vec<Secret> {
+    let database = match table.contains_key("postgres_config") {
+        true => Secret::new("postgres_config.url", "PAPER_POSTGRES_URL"),
+        false => Secret::new("mongo_config.uri", "PAPER_MONGO_URI"),
+    };
+    let mut secrets = vec![
+        database,
+        Secret::new(
+            "backend_config.jwt.access_secret",
+            "PAPER_JWT_ACCESS_SECRET",
+        ),
+        Secret::new(
+            "backend_config.jwt.refresh_secret",
+            "PAPER_JWT_REFRESH_SECRET",
+        ),
+    ];
+    if needs_api_key(table.get("llm_config")) {
+        secrets.push(Secret::new("llm_config.api_key", "PAPER_LLM_API_KEY"));
+    }
+    if let Some(providers) = table.get("llm_providers").and_then(toml::Value::as_array) {
+        for (i, provider) in providers.iter().enumerate() {
+            if needs_api_key(Some(provider)) {
+                secrets.push(Secret::new(
+                    format!("llm_providers.{}.api_key", i),
+                    format!("PAPER_LLM_PROVIDERS_{}_API_KEY", i),
+                ));
+            }
+        }
+    }
+    if table.contains_key("encryption_config") {
+        secrets.push(Secret::new(
+            "encryption_config.master_key",
+            "PAPER_ENCRYPTION_MASTER_KEY",
+        ));
+    }
+    for section in ["smtp_config", "smtp"] {
+        if table.contains_key(section) {
+            secrets.push(Secret::new(
+                format!("{}.password", section),
+                "PAPER_SMTP_PASSWORD",
+            ));
+        }
+    }
+    if value_at(table, "embedding_config.mode").and_then(toml::Value::as_str) == Some("api") {
+        secrets.push(Secret::new(
+            "embedding_config.api_key",
+            "PAPER_EMBEDDING_API_KEY",
+        ));
+    }
+    secrets
+}
+
+/// Replace the secrets of the file by those given in the environment.
+fn fill_secrets(
+    table: &mut toml::Table,
+    env: impl Fn(&str) -> Option<String>,
+) -> anyhow::Result<()> {
+    for secret in required_secrets(table) {
+        let Some(value) = secret.read(&env)? else {
+            continue;
+        };
+        let value = toml::Value::String(value);
+        match secret.path.strip_prefix("llm_providers.") {
+            // only the providers of the file are given their key
+            Some(item) => {
+                let provider = item.split_once('.').and_then(|(index, key)| {
+                    let providers = table.get_mut("llm_providers")?.as_array_mut()?;
+                    let provider = providers.get_mut(index.parse::<usize>().ok()?)?;
+                    Some((provider.as_table_mut()?, key))
+                });
+                if let Some((provider, key)) = provider {
+                    provider.insert(key.to_string(), value);
+                }
+            }
+            None => set_path(table, &secret.path, value)?,
+        }
+    }
+    Ok(())
+}
+
+/// Fail fast when a secret is given neither by the file nor the environment.
+fn check_secrets(table: &toml::Table) -> anyhow::Result<()> {
+    let missing = required_secrets(table)
+        .into_iter()
+        .filter(|secret| {
+            value_at(table, &secret.path)
+                .and_then(toml::Value::as_str)
+                .is_none_or(|value| value.trim().is_empty())
+        })
+        .map(|secret| format!("{} ({} or {}_FILE)", secret.path, secret.env, secret.env))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        anyhow::bail!("Missing secrets: {}", missing.join(", "));
+    }
+    Ok(())
+}
+
 #[derive(Debug, Deserialize)]
 pub struct FrontendConfig {
     pub cors: Vec<String>,
+    // in seconds, cache time of the preflight response
+    pub cors_max_age: Option<u64>,
+    #[serde(default = "default_cors_expose_headers")]
+    pub cors_expose_headers: Vec<String>,
+    #[serde(default)]
+    pub cors_allow_credentials: bool,
+}
+
+fn default_cors_expose_headers() -> Vec<String> {
+    [
+        "x-request-id",
+        "x-ratelimit-limit",
+        "x-ratelimit-remaining",
+        "x-ratelimit-reset",
+        "retry-after",
+        "link",
+        "etag",
+        "x-degraded",
+        "idempotent-replayed",
+        "location",
+        "tus-resumable",
+        "tus-version",
+        "tus-extension",
+        "tus-max-size",
+        "tus-checksum-algorithm",
+        "upload-offset",
+        "upload-length",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
 }
 
 #[derive(Debug, Deserialize)]
 pub struct BackendConfig {
-    pub address: String,
+    // `host:port` or `unix:/path.sock`, or a list of them to listen on each
+    #[serde(deserialize_with = "one_or_many")]
+    pub address: Vec<String>,
+    // public base url used in links sent by email, defaults to `http(s)://{address}`
+    // of the first tcp address
+    pub public_url: Option<String>,
+    // in seconds, time given to in-flight requests and jobs on shutdown, defaults to 30
+    pub shutdown_timeout: Option<u64>,
     pub jwt: Jwt,
+    // serve https and http/2 without a reverse proxy
+    pub tls: Option<TlsConfig>,
+    #[serde(default)]
+    pub session: SessionConfig,
+}
+
+impl BackendConfig {
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout.unwrap_or(30))
+    }
+
+    pub fn listen_addresses(&self) -> Vec<ListenAddress> {
+        self.address
+            .iter()
+            .map(|address| match address.strip_prefix("unix:") {
+                Some(path) => ListenAddress::Unix(PathBuf::from(path)),
+                None => ListenAddress::Tcp(address.clone()),
+            })
+            .collect()
+    }
+
+    pub fn public_url(&self) -> String {
+        if let Some(public_url) = &self.public_url {
+            return public_url.clone();
+        }
+        let address = self
+            .listen_addresses()
+            .into_iter()
+            .find_map(|address| match address {
+                ListenAddress::Tcp(address) => Some(address),
+                ListenAddress::Unix(_) => None,
+            })
+            .unwrap_or_else(|| "localhost".to_string());
+        match self.tls {
+            Some(_) => format!("https://{}", address),
+            None => format!("http://{}", address),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListenAddress {
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(address) => Ok(vec![address]),
+        OneOrMany::Many(addresses) => Ok(addresses),
+    }
+}
+
+/// The cookies of the sessions. In cookie mode the web frontend is
+/// authenticated by an httpOnly cookie instead of a bearer token.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    // the access token is set in a cookie on login, the mutating requests it
+    // authenticates need the csrf token of the session
+    pub cookie_auth: bool,
+    // only sent over https, to be set in production
+    pub secure: bool,
+    pub same_site: SameSitePolicy,
+    pub domain: Option<String>,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            cookie_auth: false,
+            secure: false,
+            same_site: SameSitePolicy::Lax,
+            domain: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSitePolicy {
+    Strict,
+    Lax,
+    // cross-site requests carry the cookies, needs `secure`
+    None,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsConfig {
+    // pem files of the certificate chain and the private key
+    pub cert_path: String,
+    pub key_path: String,
+    // in seconds, how often the files are checked for a renewed certificate, defaults to 3600
+    pub reload_secs: Option<u64>,
+}
+
+impl TlsConfig {
+    pub fn reload_interval(&self) -> Duration {
+        Duration::from_secs(self.reload_secs.unwrap_or(3600))
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -37,3 +684,753 @@ pub struct Jwt {
     // pub access_expiration: Option<i64>, // in seconds
     // pub refresh_expiration: Option<i64>, // in seconds
 }
+
+#[derive(Debug, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: String,
+    pub password: String,
+    // sender mailbox, e.g. `Paper <no-reply@example.com>`
+    pub from: String,
+    // use STARTTLS instead of implicit TLS
+    #[serde(default)]
+    pub starttls: bool,
+}
+
+/// Keys sealing the sensitive fields stored for the users. A rotation moves the
+/// current key to `previous_keys` under its id and gives a new one another id,
+/// the fields are sealed again under it on the next start.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EncryptionConfig {
+    // id of the current key, recorded with every value it seals
+    #[serde(default = "default_key_id")]
+    pub key_id: String,
+    // base64 of 32 random bytes
+    pub master_key: String,
+    // retired keys by id, only opening the values not sealed again yet
+    #[serde(default)]
+    pub previous_keys: HashMap<String, String>,
+    // seal the editing state of the collaborative notes too, not the notes
+    // saved into the papers and their revisions, which are searched
+    #[serde(default)]
+    pub encrypt_notes: bool,
+}
+
+fn default_key_id() -> String {
+    "1".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LlmConfig {
+    // `openai` (or any OpenAI compatible api), `deepseek`, `anthropic`, `ollama` or `mock`
+    pub provider: String,
+    #[serde(default)]
+    pub api_key: String,
+    pub base_url: Option<String>,
+    // default model of the provider
+    pub model: Option<String>,
+    // other models served by the provider, requests for them are routed to it
+    #[serde(default)]
+    pub models: Vec<String>,
+    // bound on a whole completion, depends on the provider by default
+    pub timeout_secs: Option<u64>,
+    // retries of a call failing to start, 2 by default
+    pub max_retries: Option<u32>,
+}
+
+/// Where text embeddings are computed.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum EmbeddingConfig {
+    // in process, calling an OpenAI compatible embeddings api
+    Api {
+        base_url: String,
+        api_key: String,
+        model: String,
+    },
+    // offloaded to a separate `embedding-worker` process
+    Worker {
+        url: String,
+        timeout_secs: Option<u64>,
+    },
+}
+
+/// Where uploaded files are scanned for malware before being processed.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum ScanConfig {
+    // a clamd daemon, e.g. `127.0.0.1:3310`
+    Clamav {
+        address: String,
+        timeout_secs: Option<u64>,
+    },
+    // an external service answering the verdict as json
+    Http {
+        url: String,
+        api_key: Option<String>,
+        timeout_secs: Option<u64>,
+    },
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PdfConfig {
+    // path of `pdftotext` (poppler), used when the builtin extractor fails
+    pub external_extractor: Option<String>,
+    // OCR of scanned pdfs, disabled when absent
+    pub ocr: Option<OcrConfig>,
+    // thumbnails of the first page of uploads, disabled when absent
+    pub thumbnail: Option<ThumbnailConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OcrConfig {
+    // paths of the binaries, looked up in `PATH` by default
+    pub tesseract: Option<String>,
+    pub pdftoppm: Option<String>,
+    // tesseract language packs, e.g. `["eng", "chi_sim"]`, defaults to `eng`
+    #[serde(default)]
+    pub languages: Vec<String>,
+    // resolution of the rendered pages, defaults to 300
+    pub dpi: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ThumbnailConfig {
+    // path of the binary, looked up in `PATH` by default
+    pub pdftoppm: Option<String>,
+}
+
+/// Signals combined with the text relevance to rank search results.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SearchConfig {
+    // extra weight of a paper updated just now, decays with the half life
+    pub recency_boost: f64,
+    pub recency_half_life_days: f64,
+    // multiplier of starred papers
+    pub starred_boost: f64,
+    // multiplier of papers in the folder (and subfolders) searched from
+    pub folder_boost: f64,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig {
+            recency_boost: 0.5,
+            recency_half_life_days: 30.0,
+            starred_boost: 1.5,
+            folder_boost: 2.0,
+        }
+    }
+}
+
+/// Legal documents users have to accept, bumping a version asks for consent again.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LegalConfig {
+    // no consent is asked for a document which is absent
+    pub terms: Option<LegalDocument>,
+    pub privacy_policy: Option<LegalDocument>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LegalDocument {
+    pub version: String,
+    pub url: String,
+}
+
+/// Token buckets limiting the requests of each user, or ip when unauthenticated.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    // buckets shared between instances in redis when set, in memory otherwise
+    pub redis_url: Option<String>,
+    // every api request
+    pub global: RateLimit,
+    // login, registration and tokens, on top of the global limit
+    pub auth: RateLimit,
+    // llm backed endpoints, on top of the global limit
+    pub ai: RateLimit,
+    // failed logins of an account or an ip, counted in the database
+    pub login: LoginLimit,
+    // reverse proxies whose `X-Forwarded-For` names the client, as ips or
+    // ranges like `10.0.0.0/8`, the peer address is the client otherwise
+    pub trusted_proxies: Vec<String>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            enabled: true,
+            redis_url: None,
+            global: RateLimit {
+                burst: 120,
+                per_minute: 600,
+            },
+            auth: RateLimit {
+                burst: 10,
+                per_minute: 10,
+            },
+            ai: RateLimit {
+                burst: 5,
+                per_minute: 20,
+            },
+            login: LoginLimit::default(),
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RateLimit {
+    // size of the bucket, requests allowed at once
+    pub burst: u32,
+    // tokens added back to the bucket every minute
+    pub per_minute: u32,
+}
+
+/// Delays then locks the logins after failed ones, the counts are forgotten a
+/// day after the last failure.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct LoginLimit {
+    // failures of an account allowed without delay
+    pub free_failures: u32,
+    // delay after the first delayed failure, doubled on each next one
+    pub delay_secs: u64,
+    pub max_delay_secs: u64,
+    // failures locking the account, its owner is sent an email
+    pub lock_after: u32,
+    // failures from an ip locking it, whichever accounts
+    pub ip_lock_after: u32,
+    pub lock_secs: u64,
+}
+
+impl Default for LoginLimit {
+    fn default() -> Self {
+        LoginLimit {
+            free_failures: 3,
+            delay_secs: 2,
+            max_delay_secs: 60,
+            lock_after: 10,
+            ip_lock_after: 50,
+            lock_secs: 900,
+        }
+    }
+}
+
+/// Cache of the hot documents read by the frequent GET routes.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    // shared between instances in redis when set, in memory otherwise
+    pub redis_url: Option<String>,
+    // bound on the staleness of documents written outside the api
+    pub ttl_secs: u64,
+    // documents kept at most by the in memory cache
+    pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            redis_url: None,
+            ttl_secs: 300,
+            max_entries: 10_000,
+        }
+    }
+}
+
+/// Llm usage quotas and the operators allowed to see the usage of every user.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct UsageConfig {
+    // tokens a user can consume per calendar month, unlimited when absent
+    pub monthly_token_quota: Option<u64>,
+    // uids of the users allowed on the `/admin` routes
+    pub operator_ids: Vec<String>,
+}
+
+impl UsageConfig {
+    pub fn is_operator(&self, uid: &str) -> bool {
+        self.operator_ids.iter().any(|id| id == uid)
+    }
+}
+
+/// Limits on the library of every user, an operator can override them per user.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QuotaConfig {
+    // levels of folders below the root
+    pub max_folder_depth: u64,
+    // the others are unlimited when absent
+    pub max_folders: Option<u64>,
+    pub max_papers_per_folder: Option<u64>,
+    // bytes of the uploaded files
+    pub max_storage_bytes: Option<u64>,
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        QuotaConfig {
+            max_folder_depth: 8,
+            max_folders: None,
+            max_papers_per_folder: None,
+            max_storage_bytes: None,
+        }
+    }
+}
+
+/// Defaults of the settings of the users, for the ones they did not set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SettingsConfig {
+    // `en` or `zh-CN`
+    pub language: String,
+    // IANA name, e.g. `Asia/Shanghai`
+    pub timezone: String,
+}
+
+impl Default for SettingsConfig {
+    fn default() -> Self {
+        SettingsConfig {
+            language: "en".to_string(),
+            timezone: "UTC".to_string(),
+        }
+    }
+}
+
+/// Revisions kept of the metadata and notes of the papers, one per update.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RevisionConfig {
+    // latest revisions kept per paper
+    pub max_revisions: u32,
+    // revisions replaced longer ago are pruned, 0 keeps them
+    pub retention_days: u64,
+}
+
+impl Default for RevisionConfig {
+    fn default() -> Self {
+        RevisionConfig {
+            max_revisions: 50,
+            retention_days: 180,
+        }
+    }
+}
+
+/// Archives built for the users to download.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ExportConfig {
+    // exports started longer ago are deleted with their archive
+    pub retention_days: u64,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        ExportConfig { retention_days: 7 }
+    }
+}
+
+/// Papers recommended as related to a paper of the library.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RelatedConfig {
+    // suggestions from Semantic Scholar besides the library, off by default as
+    // the DOI or title of the paper is sent to it
+    pub external: bool,
+    // raises the rate limit of the public api
+    pub semantic_scholar_api_key: Option<String>,
+}
+
+/// Largest request bodies accepted, larger ones are answered with a 413.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BodyLimitConfig {
+    // json (and form) bodies, in bytes
+    pub json_bytes: u64,
+    // uploaded files, any other content type, in bytes
+    pub upload_bytes: u64,
+}
+
+impl Default for BodyLimitConfig {
+    fn default() -> Self {
+        BodyLimitConfig {
+            json_bytes: 2 * 1024 * 1024,
+            upload_bytes: 50 * 1024 * 1024,
+        }
+    }
+}
+
+/// Time given to a request to be answered, a 504 past it. Streamed responses
+/// and websockets are bounded until their first byte only.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    // every route but the ones below, in seconds
+    pub request_secs: u64,
+    // the llm backed routes, in seconds
+    pub ai_secs: u64,
+    // the file uploads, in seconds
+    pub upload_secs: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        TimeoutConfig {
+            request_secs: 30,
+            ai_secs: 300,
+            upload_secs: 600,
+        }
+    }
+}
+
+/// When the calls to an external dependency (llm providers, Crossref, smtp)
+/// fail fast after it failed repeatedly.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    // consecutive failures opening the circuit
+    pub failure_threshold: u32,
+    // seconds calls fail fast before one probes the dependency again
+    pub open_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            failure_threshold: 5,
+            open_secs: 30,
+        }
+    }
+}
+
+/// Gzip and brotli compression of the json and text responses, exported
+/// documents included, for the clients accepting it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    pub enabled: bool,
+    // smaller responses are sent as is, in bytes
+    pub min_bytes: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        CompressionConfig {
+            enabled: true,
+            min_bytes: 1024,
+        }
+    }
+}
+
+/// Headers of every response against content sniffing, framing, leaked
+/// referrers, e.g. the tokens of the share links, and injected scripts. The
+/// empty ones are not sent.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SecurityHeadersConfig {
+    pub enabled: bool,
+    // the api answers json and exported documents, none runs scripts
+    pub content_security_policy: String,
+    // the swagger ui runs its inline scripts and the ones of unpkg
+    pub docs_content_security_policy: String,
+    pub referrer_policy: String,
+    // `DENY` or `SAMEORIGIN`
+    pub frame_options: String,
+    // ignored by the browsers over http
+    pub strict_transport_security: String,
+}
+
+impl Default for SecurityHeadersConfig {
+    fn default() -> Self {
+        SecurityHeadersConfig {
+            enabled: true,
+            content_security_policy: "default-src 'none'; frame-ancestors 'none'; sandbox"
+                .to_string(),
+            docs_content_security_policy: "default-src 'self'; \
+                script-src 'self' 'unsafe-inline' https://unpkg.com; \
+                style-src 'self' 'unsafe-inline' https://unpkg.com; \
+                img-src 'self' data: https:; frame-ancestors 'none'"
+                .to_string(),
+            referrer_policy: "no-referrer".to_string(),
+            frame_options: "DENY".to_string(),
+            strict_transport_security: "max-age=31536000; includeSubDomains".to_string(),
+        }
+    }
+}
+
+/// Exposure of the swagger ui and of the openapi specs. The spec of the latest
+/// version is printed by `--openapi` in every case, e.g. to generate the
+/// clients in CI.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DocsConfig {
+    pub access: DocsAccess,
+    // served on this address only, e.g. `127.0.0.1:7879`, instead of the ones
+    // of the api
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocsAccess {
+    #[default]
+    Public,
+    // the access tokens of the operators of the usage config only
+    Operators,
+    Disabled,
+}
+
+/// PostgreSQL storing the documents, for the deployments which cannot run mongo.
+/// Needs the `postgres` feature.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PostgresConfig {
+    // e.g. `postgres://user@localhost/paper`
+    pub url: String,
+    #[serde(default = "default_max_connections")]
+    pub max_connections: u32,
+}
+
+fn default_max_connections() -> u32 {
+    10
+}
+
+/// Retries of the database calls failing on a transient error, with an
+/// exponential backoff and full jitter in between.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DbRetryConfig {
+    // attempts per call, 1 to never retry
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for DbRetryConfig {
+    fn default() -> Self {
+        DbRetryConfig {
+            max_attempts: 3,
+            base_delay_ms: 100,
+            max_delay_ms: 2000,
+        }
+    }
+}
+
+impl DbRetryConfig {
+    /// Upper bound of the wait before the retry following the attempt, from 1.
+    pub fn max_delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay_ms
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16));
+        Duration::from_millis(delay.min(self.max_delay_ms))
+    }
+}
+
+/// Prompts shipped with the service replaced by the operator, reloaded with the file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PromptConfig {
+    // bodies of the default prompts by name, e.g. `chat`
+    pub defaults: HashMap<String, String>,
+}
+
+/// The grpc server exposing the folders and papers to the internal services.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GrpcConfig {
+    // e.g. `127.0.0.1:50051`, on its own port next to the http server
+    pub address: String,
+}
+
+/// An organization served by the deployment. Its users and their data are in
+/// a database of its own, the settings it leaves out are the ones of the
+/// deployment.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TenantConfig {
+    // e.g. `acme`, in the tokens of its users
+    pub id: String,
+    // hosts its requests are sent to, e.g. `acme.papers.example.com`
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    // the mongo or postgres database, `paper_{id}` by default
+    pub database: Option<String>,
+    #[serde(default)]
+    pub branding: BrandingConfig,
+    pub quota_config: Option<QuotaConfig>,
+    // key of the llm provider billed for its usage
+    pub llm_api_key: Option<String>,
+}
+
+impl TenantConfig {
+    pub fn database(&self) -> String {
+        self.database
+            .clone()
+            .unwrap_or_else(|| format!("paper_{}", self.id))
+    }
+}
+
+/// How the frontends present a tenant.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct BrandingConfig {
+    pub name: Option<String>,
+    pub logo_url: Option<String>,
+    // e.g. `#1a73e8`
+    pub primary_color: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_override_path() {
+        assert_eq!(
+            env_override_path("PAPER__BACKEND_CONFIG__ADDRESS").as_deref(),
+            Some("backend_config.address")
+        );
+        assert_eq!(env_override_path("PAPER__"), None);
+        assert_eq!(env_override_path("PAPER__A____B"), None);
+        assert_eq!(env_override_path("HOME"), None);
+    }
+
+    #[test]
+    fn test_set_path() {
+        let mut table: toml::Table = toml::from_str("[backend_config]\naddress = \"a\"").unwrap();
+        set_path(
+            &mut table,
+            "backend_config.address",
+            parse_value("0.0.0.0:80"),
+        )
+        .unwrap();
+        set_path(
+            &mut table,
+            "rate_limit_config.global.burst",
+            parse_value("5"),
+        )
+        .unwrap();
+        set_path(
+            &mut table,
+            "frontend_config.cors",
+            parse_value("[\"http://a\"]"),
+        )
+        .unwrap();
+        assert_eq!(
+            table["backend_config"]["address"].as_str(),
+            Some("0.0.0.0:80")
+        );
+        assert_eq!(
+            table["rate_limit_config"]["global"]["burst"].as_integer(),
+            Some(5)
+        );
+        assert_eq!(
+            table["frontend_config"]["cors"][0].as_str(),
+            Some("http://a")
+        );
+        assert!(set_path(&mut table, "backend_config.address.port", parse_value("1")).is_err());
+    }
+
+    #[test]
+    fn test_overrides_by_type() {
+        #[derive(Debug, Deserialize)]
+        struct Settings {
+            burst: u32,
+            enabled: bool,
+            password: String,
+            cors: Vec<String>,
+            timeout_secs: Option<u64>,
+            embedding: EmbeddingConfig,
+        }
+
+        let file = "password = \"from file\"\n[embedding]\nmode = \"worker\"\nurl = \"5\"";
+        let mut table: toml::Table = toml::from_str(file).unwrap();
+        let mut overrides = toml::Table::new();
+        for (path, value) in [
+            ("burst", "5"),
+            ("enabled", "false"),
+            ("password", "12345"),
+            ("cors", "[\"http://a\"]"),
+            ("timeout_secs", "30"),
+            ("embedding.timeout_secs", "10"),
+        ] {
+            let value = toml::Value::String(value.to_string());
+            set_path(&mut table, path, value.clone()).unwrap();
+            set_path(&mut overrides, path, value).unwrap();
+        }
+        let settings = Settings::deserialize(Overridden::new(table, overrides)).unwrap();
+        assert_eq!(settings.burst, 5);
+        assert!(!settings.enabled);
+        assert_eq!(settings.password, "12345");
+        assert_eq!(settings.cors, vec!["http://a".to_string()]);
+        assert_eq!(settings.timeout_secs, Some(30));
+        // the values of the file are read as they are
+        assert!(matches!(
+            settings.embedding,
+            EmbeddingConfig::Worker { ref url, timeout_secs: Some(10) } if url == "5"
+        ));
+
+        let many = toml::Value::String("many".to_string());
+        let burst = Overridden {
+            value: many.clone(),
+            overrides: Some(many),
+        };
+        assert!(u32::deserialize(burst).is_err());
+    }
+
+    #[test]
+    fn test_secrets() {
+        let file = "[mongo_config]\ndb_name = \"paper\"\n\
+            [backend_config.jwt]\naccess_secret = \"a\"\nrefresh_secret = \"\"\n\
+            [llm_config]\nprovider = \"openai\"\n\
+            [[llm_providers]]\nprovider = \"ollama\"\n\
+            [[llm_providers]]\nprovider = \"anthropic\"";
+        let mut table: toml::Table = toml::from_str(file).unwrap();
+        let env = |name: &str| match name {
+            "PAPER_MONGO_URI" => Some("mongodb://db".to_string()),
+            "PAPER_LLM_PROVIDERS_1_API_KEY" => Some("key".to_string()),
+            _ => None,
+        };
+        fill_secrets(&mut table, env).unwrap();
+        assert_eq!(
+            value_at(&table, "mongo_config.uri").and_then(toml::Value::as_str),
+            Some("mongodb://db")
+        );
+        assert_eq!(
+            value_at(&table, "llm_providers.1.api_key").and_then(toml::Value::as_str),
+            Some("key")
+        );
+        let error = check_secrets(&table).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "Missing secrets: backend_config.jwt.refresh_secret (PAPER_JWT_REFRESH_SECRET or \
+            PAPER_JWT_REFRESH_SECRET_FILE), llm_config.api_key (PAPER_LLM_API_KEY or \
+            PAPER_LLM_API_KEY_FILE)"
+        );
+
+        let mut table: toml::Table = toml::from_str("[postgres_config]").unwrap();
+        let env = |name: &str| match name {
+            "PAPER_POSTGRES_URL" => Some("postgres://db".to_string()),
+            _ => None,
+        };
+        fill_secrets(&mut table, env).unwrap();
+        assert_eq!(
+            value_at(&table, "postgres_config.url").and_then(toml::Value::as_str),
+            Some("postgres://db")
+        );
+    }
+
+    #[test]
+    fn test_db_retry_delay() {
+        let config = DbRetryConfig {
+            max_attempts: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 1000,
+        };
+        assert_eq!(config.max_delay(1), Duration::from_millis(100));
+        assert_eq!(config.max_delay(3), Duration::from_millis(400));
+        assert_eq!(config.max_delay(10), Duration::from_millis(1000));
+        assert_eq!(config.max_delay(100), Duration::from_millis(1000));
+    }
+}
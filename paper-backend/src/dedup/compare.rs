@@ -0,0 +1,117 @@
+use std::collections::HashSet;
+
+pub use paper_schema::paper::{FieldComparison, PaperComparison, SectionComparison, SectionStatus};
+
+use crate::{dedup::normalize_title, model::paper::Paper};
+
+// sections with a body similarity at least this high are reported as common
+const SECTION_COMMON_THRESHOLD: f64 = 0.95;
+
+/// Split markdown into (heading, body) pairs, text before the first heading
+/// belongs to an empty heading.
+fn split_sections(text: &str) -> Vec<(String, String)> {
+    let mut sections: Vec<(String, String)> = vec![(String::new(), String::new())];
+    for line in text.lines() {
+        if line.starts_with('#') {
+            let heading = line.trim_start_matches('#').trim().to_string();
+            sections.push((heading, String::new()));
+        } else if let Some((_, body)) = sections.last_mut() {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+    sections
+        .into_iter()
+        .map(|(heading, body)| (heading, body.trim().to_string()))
+        .filter(|(heading, body)| !heading.is_empty() || !body.is_empty())
+        .collect()
+}
+
+/// Jaccard similarity of the word sets of the texts.
+fn text_similarity(a: &str, b: &str) -> f64 {
+    let words = |text: &str| -> HashSet<String> {
+        normalize_title(text)
+            .split(' ')
+            .filter(|w| !w.is_empty())
+            .map(String::from)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+}
+
+fn compare_field(field: &str, a: Option<String>, b: Option<String>) -> FieldComparison {
+    let normalize = |v: &Option<String>| v.as_deref().map(normalize_title).unwrap_or_default();
+    FieldComparison {
+        field: field.to_string(),
+        same: normalize(&a) == normalize(&b),
+        a,
+        b,
+    }
+}
+
+pub fn compare_papers(a: &Paper, b: &Paper) -> PaperComparison {
+    let join = |values: &[String]| (!values.is_empty()).then(|| values.join(", "));
+    let fields = vec![
+        compare_field("title", Some(a.title.clone()), Some(b.title.clone())),
+        compare_field("authors", join(&a.authors), join(&b.authors)),
+        compare_field("doi", a.doi.clone(), b.doi.clone()),
+        compare_field("abstract", a.r#abstract.clone(), b.r#abstract.clone()),
+        compare_field("tags", join(&a.tags), join(&b.tags)),
+    ];
+
+    let sections_a = split_sections(a.content.as_deref().unwrap_or_default());
+    let mut sections_b = split_sections(b.content.as_deref().unwrap_or_default());
+    let mut sections = Vec::new();
+    for (heading, body_a) in sections_a {
+        let key = normalize_title(&heading);
+        let matched = sections_b
+            .iter()
+            .position(|(heading_b, _)| normalize_title(heading_b) == key);
+        let Some(index) = matched else {
+            sections.push(SectionComparison {
+                heading,
+                status: SectionStatus::OnlyInA,
+                similarity: 0.0,
+                a: Some(body_a),
+                b: None,
+            });
+            continue;
+        };
+        let (_, body_b) = sections_b.remove(index);
+        let similarity = text_similarity(&body_a, &body_b);
+        let status = if similarity >= SECTION_COMMON_THRESHOLD {
+            SectionStatus::Common
+        } else {
+            SectionStatus::Divergent
+        };
+        sections.push(SectionComparison {
+            heading,
+            status,
+            similarity,
+            a: Some(body_a),
+            b: Some(body_b),
+        });
+    }
+    sections.extend(
+        sections_b
+            .into_iter()
+            .map(|(heading, body_b)| SectionComparison {
+                heading,
+                status: SectionStatus::OnlyInB,
+                similarity: 0.0,
+                a: None,
+                b: Some(body_b),
+            }),
+    );
+
+    PaperComparison {
+        a_id: a.id.clone(),
+        b_id: b.id.clone(),
+        fields,
+        sections,
+    }
+}
@@ -0,0 +1,203 @@
+pub mod compare;
+
+use std::collections::HashMap;
+
+pub use paper_schema::paper::DuplicateReason;
+
+use crate::model::paper::Paper;
+
+// titles at least this similar (1.0 = identical after normalization) are duplicates
+const TITLE_SIMILARITY_THRESHOLD: f64 = 0.9;
+
+/// Papers considered to be the same publication.
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    pub paper_ids: Vec<String>,
+    pub reasons: Vec<DuplicateReason>,
+}
+
+/// Lowercase the title and keep only letters and digits, separated by single spaces.
+pub fn normalize_title(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(current)
+            };
+            prev = current;
+        }
+    }
+    row[b.len()]
+}
+
+/// Similarity of two normalized titles, between 0.0 and 1.0.
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 0.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / max_len as f64
+}
+
+fn similar_titles(a: &str, b: &str) -> bool {
+    // titles differing a lot in length can't reach the threshold, skip the distance
+    let max_len = a.len().max(b.len());
+    !a.is_empty()
+        && a.len().abs_diff(b.len()) * 10 <= max_len
+        && title_similarity(a, b) >= TITLE_SIMILARITY_THRESHOLD
+}
+
+/// Group the papers sharing a DOI, a file hash or a near-identical title.
+/// Groups are transitive: if A matches B and B matches C, all three are one group.
+pub fn find_duplicates(papers: &[Paper]) -> Vec<DuplicateGroup> {
+    let mut parents = (0..papers.len()).collect::<Vec<_>>();
+    let mut reasons: HashMap<(usize, usize), DuplicateReason> = HashMap::new();
+
+    fn root(parents: &mut [usize], i: usize) -> usize {
+        let mut i = i;
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+    let mut union = |parents: &mut Vec<usize>, a: usize, b: usize, reason: DuplicateReason| {
+        reasons.entry((a, b)).or_insert(reason);
+        let (ra, rb) = (root(parents, a), root(parents, b));
+        if ra != rb {
+            parents[rb] = ra;
+        }
+    };
+
+    let titles = papers
+        .iter()
+        .map(|paper| normalize_title(&paper.title))
+        .collect::<Vec<_>>();
+    for i in 0..papers.len() {
+        for j in i + 1..papers.len() {
+            let (a, b) = (&papers[i], &papers[j]);
+            let same = |x: &Option<String>, y: &Option<String>| match (x, y) {
+                (Some(x), Some(y)) => x.trim().eq_ignore_ascii_case(y.trim()),
+                _ => false,
+            };
+            if same(&a.doi, &b.doi) {
+                union(&mut parents, i, j, DuplicateReason::Doi);
+            } else if same(&a.file_hash, &b.file_hash) {
+                union(&mut parents, i, j, DuplicateReason::FileHash);
+            } else if similar_titles(&titles[i], &titles[j]) {
+                union(&mut parents, i, j, DuplicateReason::Title);
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, DuplicateGroup> = HashMap::new();
+    for i in 0..papers.len() {
+        let r = root(&mut parents, i);
+        groups
+            .entry(r)
+            .or_insert_with(|| DuplicateGroup {
+                paper_ids: Vec::new(),
+                reasons: Vec::new(),
+            })
+            .paper_ids
+            .push(papers[i].id.clone());
+    }
+    for ((i, _), reason) in reasons {
+        let group = groups
+            .get_mut(&root(&mut parents, i))
+            .expect("group of paper");
+        if !group.reasons.contains(&reason) {
+            group.reasons.push(reason);
+        }
+    }
+    groups
+        .into_values()
+        .filter(|group| group.paper_ids.len() > 1)
+        .collect()
+}
+
+/// Merge the duplicates into the surviving paper: notes are appended, tags and
+/// authors are united and missing metadata is taken from the duplicates.
+pub fn merge_papers(survivor: &mut Paper, duplicates: &[Paper]) {
+    for duplicate in duplicates {
+        if let Some(content) = duplicate
+            .content
+            .as_deref()
+            .filter(|c| !c.trim().is_empty())
+        {
+            let merged = format!("> Merged from \"{}\"\n\n{}", duplicate.title, content);
+            survivor.content = Some(match survivor.content.take() {
+                Some(existing) if !existing.trim().is_empty() => {
+                    format!("{}\n\n---\n\n{}", existing, merged)
+                }
+                _ => merged,
+            });
+        }
+        for tag in &duplicate.tags {
+            if !survivor.tags.contains(tag) {
+                survivor.tags.push(tag.clone());
+            }
+        }
+        if survivor.authors.is_empty() {
+            survivor.authors = duplicate.authors.clone();
+        }
+        for (field, value) in [
+            (&mut survivor.r#abstract, &duplicate.r#abstract),
+            (&mut survivor.doi, &duplicate.doi),
+            (&mut survivor.summary, &duplicate.summary),
+            (&mut survivor.file_hash, &duplicate.file_hash),
+        ] {
+            if field.is_none() {
+                *field = value.clone();
+            }
+        }
+    }
+    survivor.updated_at = bson::DateTime::now();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_similarity() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(
+            normalize_title("Attention Is All You Need!"),
+            "attention is all you need"
+        );
+        assert!(title_similarity("attention is all you need", "attention is all you needs") > 0.9);
+        assert!(title_similarity("attention is all you need", "deep residual learning") < 0.5);
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let mut a = Paper::new("user", "folder", "Attention Is All You Need".to_string());
+        let b = Paper::new("user", "folder", "Attention is all you need.".to_string());
+        let mut c = Paper::new("user", "folder", "BERT".to_string());
+        let d = Paper::new("user", "folder", "Deep Residual Learning".to_string());
+        a.doi = Some("10.48550/arXiv.1706.03762".to_string());
+        c.doi = Some("10.48550/ARXIV.1706.03762".to_string());
+
+        let groups = find_duplicates(&[a, b, c, d]);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].paper_ids.len(), 3);
+        assert!(groups[0].reasons.contains(&DuplicateReason::Doi));
+        assert!(groups[0].reasons.contains(&DuplicateReason::Title));
+    }
+}
@@ -0,0 +1,211 @@
+use chrono::{DateTime, Timelike, Utc};
+use chrono_tz::Tz;
+use futures::TryStreamExt;
+
+use crate::{
+    app_data::AppDataRef,
+    error::ServiceResult,
+    i18n::{
+        GREETING_NAME, Locale, WEEKLY_DIGEST_BODY, WEEKLY_DIGEST_MORE, WEEKLY_DIGEST_SUBJECT,
+        default_locale,
+    },
+    model::{
+        paper::PaperRepository,
+        reading_list::{ReadingListRepository, ReadingStatus},
+        usage::UsageRepository,
+        user::{User, UserRepository},
+    },
+    utils::{cache::CacheKey, mailer::Mail},
+};
+
+const WEEK_MILLIS: i64 = 7 * 24 * 60 * 60 * 1000;
+// the digests go out in the hour starting at 8 in the morning of the users
+const DIGEST_HOUR: u32 = 8;
+// checked every hour, the digest of the last week may have gone out up to an
+// hour later in the morning
+const DIGEST_SLACK_MILLIS: i64 = 60 * 60 * 1000;
+// new papers listed by title, the others are only counted
+const MAX_LISTED_PAPERS: usize = 10;
+// usage feature of the llm calls writing the folder summaries
+const SUMMARY_FEATURE: &str = "wrap_up";
+
+/// What happened in the library of the user over the last week.
+#[derive(Debug, Default)]
+pub struct WeeklyDigest {
+    pub new_papers: Vec<String>, // titles, newest first
+    pub new_paper_count: usize,
+    pub unread_count: usize,
+    pub summary_count: u64,
+}
+
+impl WeeklyDigest {
+    pub fn is_empty(&self) -> bool {
+        self.new_paper_count == 0 && self.unread_count == 0 && self.summary_count == 0
+    }
+}
+
+/// Name used to greet the user in emails.
+pub fn greeting_name(user: &User, locale: Locale) -> &str {
+    user.username
+        .as_deref()
+        .or(user.email.as_deref())
+        .unwrap_or(locale.text(GREETING_NAME))
+}
+
+async fn collect_digest(
+    state: &AppDataRef,
+    user: &User,
+    since: bson::DateTime,
+) -> ServiceResult<WeeklyDigest> {
+    let mut digest = WeeklyDigest::default();
+
+    // newest first, stop at the first paper older than a week
+    let mut papers = state.db.find_papers(&user.uid, None, None, None).await?;
+    while let Some(paper) = papers.try_next().await? {
+        if paper.created_at < since {
+            break;
+        }
+        digest.new_paper_count += 1;
+        if digest.new_papers.len() < MAX_LISTED_PAPERS {
+            digest.new_papers.push(paper.title);
+        }
+    }
+
+    digest.unread_count = state
+        .db
+        .get_reading_list(&user.uid)
+        .await?
+        .iter()
+        .filter(|item| item.status == ReadingStatus::ToRead)
+        .count();
+    digest.summary_count = state
+        .db
+        .count_usage_since(&user.uid, SUMMARY_FEATURE, since)
+        .await?;
+    Ok(digest)
+}
+
+/// Whether it is the hour of the digest in the timezone of the user.
+pub fn is_digest_hour(timezone: Tz, now: DateTime<Utc>) -> bool {
+    now.with_timezone(&timezone).hour() == DIGEST_HOUR
+}
+
+pub fn render_digest(
+    locale: Locale,
+    name: &str,
+    public_url: &str,
+    digest: &WeeklyDigest,
+) -> String {
+    let mut new_papers = digest
+        .new_papers
+        .iter()
+        .map(|title| format!("  - {}\n", title))
+        .collect::<String>();
+    if digest.new_paper_count > digest.new_papers.len() {
+        let more = digest.new_paper_count - digest.new_papers.len();
+        new_papers.push_str(&locale.render(WEEKLY_DIGEST_MORE, &[("count", &more.to_string())]));
+    }
+    locale.render(
+        WEEKLY_DIGEST_BODY,
+        &[
+            ("name", name),
+            ("public_url", public_url),
+            ("new_paper_count", &digest.new_paper_count.to_string()),
+            ("new_papers", &new_papers),
+            ("unread_count", &digest.unread_count.to_string()),
+            ("summary_count", &digest.summary_count.to_string()),
+        ],
+    )
+}
+
+async fn send_digest(
+    state: &AppDataRef,
+    user: &User,
+    due_before: bson::DateTime,
+    since: bson::DateTime,
+) -> ServiceResult<()> {
+    let Some(email) = user.email.clone() else {
+        return Ok(());
+    };
+    // claimed first so that concurrent instances do not send it twice
+    if !state.db.claim_digest(&user.uid, due_before).await? {
+        return Ok(());
+    }
+    state.invalidate(&[CacheKey::User(&user.uid)]).await;
+
+    let digest = collect_digest(state, user, since).await?;
+    if digest.is_empty() {
+        return Ok(());
+    }
+    let locale = Locale::of_user(user, default_locale(&state.settings_config));
+    let name = greeting_name(user, locale);
+    state
+        .mailer
+        .send(Mail {
+            to: email,
+            subject: locale.text(WEEKLY_DIGEST_SUBJECT).to_string(),
+            body: render_digest(locale, name, &state.public_url, &digest),
+        })
+        .await
+}
+
+/// Send the weekly digest to every opted-in user who got none for a week, in
+/// the morning of their timezone.
+pub async fn send_weekly_digests(state: &AppDataRef) {
+    let now = Utc::now();
+    let since = bson::DateTime::from_millis(now.timestamp_millis() - WEEK_MILLIS);
+    let due_before =
+        bson::DateTime::from_millis(now.timestamp_millis() - WEEK_MILLIS + DIGEST_SLACK_MILLIS);
+    let users = match state.db.get_users_due_for_digest(due_before).await {
+        Ok(users) => users,
+        Err(e) => {
+            tracing::error!("Failed to load the users due for a digest: {}", e);
+            return;
+        }
+    };
+    let users = users
+        .into_iter()
+        .filter(|user| is_digest_hour(user.settings.timezone(&state.settings_config), now))
+        .collect::<Vec<_>>();
+    if !users.is_empty() {
+        tracing::info!("Sending the weekly digest to {} users", users.len());
+    }
+    for user in users {
+        if let Err(e) = send_digest(state, &user, due_before, since).await {
+            tracing::error!("Failed to send the digest of user {}: {}", user.uid, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_digest_lists_new_papers() {
+        let digest = WeeklyDigest {
+            new_papers: vec!["Attention Is All You Need".to_string()],
+            new_paper_count: 3,
+            unread_count: 2,
+            summary_count: 1,
+        };
+        let body = render_digest(Locale::En, "Ada", "https://paper.example.com", &digest);
+        assert!(body.starts_with("Hello Ada,"));
+        assert!(body.contains("New papers: 3\n  - Attention Is All You Need\n  and 2 more\n"));
+        assert!(body.contains("reading list: 2"));
+        assert!(!body.contains("{{"));
+    }
+
+    #[test]
+    fn test_digest_hour() {
+        let now = DateTime::parse_from_rfc3339("2024-05-06T00:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert!(is_digest_hour(chrono_tz::Asia::Shanghai, now));
+        assert!(!is_digest_hour(chrono_tz::UTC, now));
+        assert!(is_digest_hour(
+            chrono_tz::UTC,
+            now + chrono::Duration::hours(8)
+        ));
+    }
+}
@@ -0,0 +1,140 @@
+use std::{sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::EmbeddingConfig,
+    error::{ServiceError, ServiceResult},
+};
+
+#[async_trait::async_trait]
+pub trait Embedder: Send + Sync + std::fmt::Debug {
+    /// Embed the texts, one vector per text in the same order.
+    async fn embed(&self, texts: &[String]) -> ServiceResult<Vec<Vec<f32>>>;
+}
+
+/// Cosine similarity of two embeddings, 0 when one of them is null.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm =
+        a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|y| y * y).sum::<f32>().sqrt();
+    if norm == 0.0 { 0.0 } else { dot / norm }
+}
+
+pub fn create_embedder(config: &EmbeddingConfig) -> Arc<dyn Embedder> {
+    match config {
+        EmbeddingConfig::Api {
+            base_url,
+            api_key,
+            model,
+        } => Arc::new(ApiEmbedder {
+            client: reqwest::Client::new(),
+            base_url: base_url.clone(),
+            api_key: api_key.clone(),
+            model: model.clone(),
+        }),
+        EmbeddingConfig::Worker { url, timeout_secs } => Arc::new(WorkerEmbedder {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(timeout_secs.unwrap_or(60)))
+                .build()
+                .expect("Failed to create embedding worker client"),
+            url: url.clone(),
+        }),
+    }
+}
+
+/// Calls an OpenAI compatible `/v1/embeddings` api in process.
+pub struct ApiEmbedder {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl std::fmt::Debug for ApiEmbedder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiEmbedder")
+            .field("base_url", &self.base_url)
+            .field("model", &self.model)
+            .finish()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiEmbeddingResponse {
+    data: Vec<ApiEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[async_trait::async_trait]
+impl Embedder for ApiEmbedder {
+    async fn embed(&self, texts: &[String]) -> ServiceResult<Vec<Vec<f32>>> {
+        let response = self
+            .client
+            .post(format!("{}/v1/embeddings", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({
+                "model": self.model,
+                "input": texts,
+            }))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ServiceError::EmbeddingError(e.to_string()))?
+            .json::<ApiEmbeddingResponse>()
+            .await
+            .map_err(|e| ServiceError::EmbeddingError(e.to_string()))?;
+        let mut data = response.data;
+        data.sort_by_key(|d| d.index);
+        Ok(data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+
+/// Request body of the embedding worker `POST /embed`.
+#[derive(Debug, Serialize)]
+struct WorkerEmbedRequest<'a> {
+    texts: &'a [String],
+}
+
+/// Response body of the embedding worker `POST /embed`.
+#[derive(Debug, Deserialize)]
+struct WorkerEmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// Delegates the embedding to a separate `embedding-worker` process.
+#[derive(Debug)]
+pub struct WorkerEmbedder {
+    client: reqwest::Client,
+    url: String,
+}
+
+// keep a single request below the worker batch limit
+const WORKER_BATCH: usize = 128;
+
+#[async_trait::async_trait]
+impl Embedder for WorkerEmbedder {
+    async fn embed(&self, texts: &[String]) -> ServiceResult<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(WORKER_BATCH) {
+            let response = self
+                .client
+                .post(format!("{}/embed", self.url))
+                .json(&WorkerEmbedRequest { texts: batch })
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| ServiceError::EmbeddingError(e.to_string()))?
+                .json::<WorkerEmbedResponse>()
+                .await
+                .map_err(|e| ServiceError::EmbeddingError(e.to_string()))?;
+            embeddings.extend(response.embeddings);
+        }
+        Ok(embeddings)
+    }
+}
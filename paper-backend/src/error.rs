@@ -1,10 +1,69 @@
 use std::any::Any;
 
+pub use paper_schema::error::{ErrorCode, ErrorResponse, FieldError, ValidationErrorResponse};
 use salvo::{
-    Scribe,
-    http::{StatusCode, StatusError},
+    FlowCtrl, Response, Scribe, handler,
+    http::{
+        ParseError, ResBody, StatusCode,
+        header::{HeaderValue, RETRY_AFTER},
+    },
     oapi::{self, EndpointOutRegister, ToSchema},
+    writing::Json,
 };
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+use crate::{i18n::Locale, model::quota::QuotaResource, utils::api_version::ApiVersion};
+
+// set on responses by the request id middleware
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// seconds clients should wait before retrying during a database outage
+const RETRY_AFTER_SECS: &str = "30";
+
+// status of the tus protocol for a chunk not matching its `Upload-Checksum`
+const CHECKSUM_MISMATCH: u16 = 460;
+
+/// Whether the error means the database cannot be reached, rather than a failed query.
+pub fn is_db_outage(err: &mongodb::error::Error) -> bool {
+    matches!(
+        *err.kind,
+        mongodb::error::ErrorKind::ServerSelection { .. }
+            | mongodb::error::ErrorKind::ConnectionPoolCleared { .. }
+            | mongodb::error::ErrorKind::Io(_)
+    )
+}
+
+// the server stepped down or is not the primary anymore, the operation was refused
+const NOT_PRIMARY_CODES: &[i32] = &[91, 189, 10107, 11600, 11602, 13435, 13436];
+
+/// A failure which another attempt may not meet: no server could be selected,
+/// the connection pool was cleared, the primary stepped down, or the driver
+/// labels it safe to retry. Network errors in the middle of an operation are
+/// left out, the server may have applied it.
+pub fn is_transient(err: &mongodb::error::Error) -> bool {
+    if err.contains_label(mongodb::error::RETRYABLE_WRITE_ERROR)
+        || err.contains_label(mongodb::error::TRANSIENT_TRANSACTION_ERROR)
+    {
+        return true;
+    }
+    match *err.kind {
+        mongodb::error::ErrorKind::ServerSelection { .. }
+        | mongodb::error::ErrorKind::ConnectionPoolCleared { .. } => true,
+        _ => err
+            .code()
+            .is_some_and(|code| NOT_PRIMARY_CODES.contains(&code)),
+    }
+}
+
+/// A write rejected by a unique index.
+pub fn is_duplicate_key(err: &mongodb::error::Error) -> bool {
+    matches!(
+        *err.kind,
+        mongodb::error::ErrorKind::Write(mongodb::error::WriteFailure::WriteError(
+            mongodb::error::WriteError { code: 11000, .. }
+        ))
+    )
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum ServiceError {
@@ -12,10 +71,52 @@ pub enum ServiceError {
     BadRequest(String),
     #[error("401, Unauthorized {0}")]
     Unauthorized(String),
+    #[error("403, Consent Required {0}")]
+    ConsentRequired(String),
+    #[error("403, Csrf Rejected {0}")]
+    CsrfRejected(String),
     #[error("400, Duplicate User {0}")]
     DuplicateUser(String),
     #[error("404, Not Found {0}")]
     NotFound(String),
+    #[error("404, Folder Not Found {0}")]
+    FolderNotFound(String),
+    #[error("404, Paper Not Found {0}")]
+    PaperNotFound(String),
+    #[error("409, Name Conflict {0}")]
+    NameConflict(String),
+    #[error("409, Version Conflict {0}")]
+    VersionConflict(String),
+    #[error("412, Precondition Failed {0}")]
+    PreconditionFailed(String),
+    #[error("428, Precondition Required {0}")]
+    PreconditionRequired(String),
+    #[error("460, Checksum Mismatch {0}")]
+    ChecksumMismatch(String),
+    // the limit in bytes
+    #[error("413, Payload Too Large, limit {0} bytes")]
+    PayloadTooLarge(u64),
+    // seconds until the next request is allowed
+    #[error("429, Rate Limited, retry in {0}s")]
+    RateLimited(u64),
+    // seconds until the account or the ip is unlocked, after failed logins
+    #[error("429, Login Locked, retry in {0}s")]
+    LoginLocked(u64),
+    #[error("429, Quota Exceeded {used}/{quota} tokens")]
+    QuotaExceeded {
+        used: u64,
+        quota: u64,
+        // when the quota is reset, milliseconds since epoch
+        resets_at: i64,
+    },
+    #[error("403, Resource Quota Exceeded {resource} {used}/{limit}")]
+    ResourceQuotaExceeded {
+        resource: QuotaResource,
+        used: u64,
+        limit: u64,
+    },
+    #[error("422, Validation Error {0:?}")]
+    Validation(ValidationErrorResponse),
     #[error("500, Internal Server Error {0}")]
     InternalServerError(String),
 
@@ -27,49 +128,285 @@ pub enum ServiceError {
     BsonSerError(#[from] bson::ser::Error),
     #[error("JWT error: {0}")]
     JwtError(#[from] jsonwebtoken::errors::Error),
+    #[error("Mail error: {0}")]
+    MailError(String),
+    #[error("LLM error: {0}")]
+    LLMError(String),
+    #[error("Embedding error: {0}")]
+    EmbeddingError(String),
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("PDF error: {0}")]
+    PdfError(String),
+    #[error("Upstream error: {0}")]
+    UpstreamError(String),
+    // the budget of the request in seconds
+    #[error("504, Timeout after {0}s")]
+    Timeout(u64),
+    // calls to the failing dependency fail fast for `retry_after` seconds
+    #[error("503, Service Unavailable {service}, retry in {retry_after}s")]
+    CircuitOpen { service: String, retry_after: u64 },
+    // a restore replaces the collections, the writes are refused until it ends
+    #[error("503, Restore Running")]
+    RestoreRunning,
 }
 
 pub type ServiceResult<T> = std::result::Result<T, ServiceError>;
 
-impl Scribe for ServiceError {
-    fn render(self, res: &mut salvo::Response) {
+impl ServiceError {
+    pub fn code(&self) -> ErrorCode {
         match self {
-            ServiceError::BadRequest(msg) => {
-                res.status_code(StatusCode::BAD_REQUEST);
-                res.render(msg);
+            ServiceError::BadRequest(_) => ErrorCode::BadRequest,
+            ServiceError::Unauthorized(_) => ErrorCode::Unauthorized,
+            ServiceError::ConsentRequired(_) => ErrorCode::ConsentRequired,
+            ServiceError::CsrfRejected(_) => ErrorCode::CsrfRejected,
+            ServiceError::DuplicateUser(_) => ErrorCode::DuplicateUser,
+            ServiceError::NotFound(_) => ErrorCode::NotFound,
+            ServiceError::FolderNotFound(_) => ErrorCode::FolderNotFound,
+            ServiceError::PaperNotFound(_) => ErrorCode::PaperNotFound,
+            ServiceError::NameConflict(_) => ErrorCode::NameConflict,
+            ServiceError::VersionConflict(_) => ErrorCode::VersionConflict,
+            ServiceError::PreconditionFailed(_) => ErrorCode::PreconditionFailed,
+            ServiceError::PreconditionRequired(_) => ErrorCode::PreconditionRequired,
+            ServiceError::ChecksumMismatch(_) => ErrorCode::ChecksumMismatch,
+            ServiceError::PayloadTooLarge(_) => ErrorCode::PayloadTooLarge,
+            ServiceError::RateLimited(_) => ErrorCode::RateLimited,
+            ServiceError::LoginLocked(_) => ErrorCode::LoginLocked,
+            ServiceError::QuotaExceeded { .. } => ErrorCode::QuotaExceeded,
+            ServiceError::ResourceQuotaExceeded { .. } => ErrorCode::ResourceQuotaExceeded,
+            ServiceError::Validation(_) => ErrorCode::ValidationFailed,
+            ServiceError::InternalServerError(_) => ErrorCode::InternalError,
+            ServiceError::MongoClientError(err) if is_db_outage(err) => {
+                ErrorCode::DatabaseUnavailable
             }
-            ServiceError::DuplicateUser(msg) => {
-                res.status_code(StatusCode::BAD_REQUEST);
-                res.render(format!("Duplicate user: {}", msg));
+            ServiceError::MongoClientError(_) => ErrorCode::DatabaseError,
+            ServiceError::BsonDeError(_) | ServiceError::BsonSerError(_) => {
+                ErrorCode::SerializationError
             }
-            ServiceError::NotFound(msg) => {
-                res.status_code(StatusCode::NOT_FOUND);
-                res.render(format!("Not found: {}", msg));
+            ServiceError::JwtError(_) => ErrorCode::TokenInvalid,
+            ServiceError::MailError(_) => ErrorCode::MailError,
+            ServiceError::LLMError(_) => ErrorCode::LlmError,
+            ServiceError::EmbeddingError(_) => ErrorCode::EmbeddingError,
+            ServiceError::IoError(_) => ErrorCode::InternalError,
+            ServiceError::PdfError(_) => ErrorCode::PdfError,
+            ServiceError::UpstreamError(_) => ErrorCode::UpstreamError,
+            ServiceError::Timeout(_) => ErrorCode::Timeout,
+            ServiceError::CircuitOpen { .. } | ServiceError::RestoreRunning => {
+                ErrorCode::ServiceUnavailable
             }
-            ServiceError::InternalServerError(msg) => {
-                res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
-                res.render(msg);
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ServiceError::BadRequest(_) | ServiceError::DuplicateUser(_) => StatusCode::BAD_REQUEST,
+            ServiceError::Unauthorized(_) | ServiceError::JwtError(_) => StatusCode::UNAUTHORIZED,
+            ServiceError::ConsentRequired(_)
+            | ServiceError::CsrfRejected(_)
+            | ServiceError::ResourceQuotaExceeded { .. } => StatusCode::FORBIDDEN,
+            ServiceError::NotFound(_)
+            | ServiceError::FolderNotFound(_)
+            | ServiceError::PaperNotFound(_) => StatusCode::NOT_FOUND,
+            ServiceError::NameConflict(_) | ServiceError::VersionConflict(_) => {
+                StatusCode::CONFLICT
+            }
+            ServiceError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            ServiceError::PreconditionRequired(_) => StatusCode::PRECONDITION_REQUIRED,
+            ServiceError::ChecksumMismatch(_) => {
+                StatusCode::from_u16(CHECKSUM_MISMATCH).unwrap_or(StatusCode::BAD_REQUEST)
+            }
+            ServiceError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ServiceError::RateLimited(_)
+            | ServiceError::LoginLocked(_)
+            | ServiceError::QuotaExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ServiceError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ServiceError::MongoClientError(err) if is_db_outage(err) => {
+                StatusCode::SERVICE_UNAVAILABLE
             }
-            ServiceError::Unauthorized(msg) => {
-                res.status_code(StatusCode::UNAUTHORIZED);
-                res.render(format!("Unauthorized: {}", msg));
+            ServiceError::CircuitOpen { .. } | ServiceError::RestoreRunning => {
+                StatusCode::SERVICE_UNAVAILABLE
             }
+            ServiceError::LLMError(_)
+            | ServiceError::EmbeddingError(_)
+            | ServiceError::UpstreamError(_) => StatusCode::BAD_GATEWAY,
+            ServiceError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            ServiceError::InternalServerError(_)
+            | ServiceError::MongoClientError(_)
+            | ServiceError::BsonDeError(_)
+            | ServiceError::BsonSerError(_)
+            | ServiceError::MailError(_)
+            | ServiceError::IoError(_)
+            | ServiceError::PdfError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
 
-            ServiceError::MongoClientError(err) => {
-                res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
-                res.render(format!("MongoDB error: {}", err));
+    /// A 422 for a single field failing a check done outside the schema rules.
+    pub fn invalid_field(field: &str, code: &str, message: impl ToString) -> Self {
+        ServiceError::Validation(ValidationErrorResponse::new(vec![FieldError::new(
+            field, code, message,
+        )]))
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ServiceError::BadRequest(msg)
+            | ServiceError::InternalServerError(msg)
+            | ServiceError::NameConflict(msg)
+            | ServiceError::VersionConflict(msg) => msg.clone(),
+            ServiceError::Unauthorized(msg) => format!("Unauthorized: {}", msg),
+            ServiceError::PreconditionFailed(msg) => format!("Precondition failed: {}", msg),
+            ServiceError::PreconditionRequired(msg) => format!("Precondition required: {}", msg),
+            ServiceError::ChecksumMismatch(msg) => format!("Checksum mismatch: {}", msg),
+            ServiceError::PayloadTooLarge(limit) => {
+                format!("Request body is larger than the limit of {} bytes", limit)
+            }
+            ServiceError::RateLimited(secs) => {
+                format!("Too many requests, retry in {} seconds", secs)
             }
-            ServiceError::BsonDeError(err) => {
-                res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
-                res.render(format!("BSON error: {}", err));
+            ServiceError::LoginLocked(secs) => {
+                format!("Too many failed logins, locked for {} seconds", secs)
             }
-            ServiceError::BsonSerError(err) => {
-                res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
-                res.render(format!("BSON error: {}", err));
+            ServiceError::QuotaExceeded { used, quota, .. } => format!(
+                "Monthly llm quota exceeded, {} of {} tokens used",
+                used, quota
+            ),
+            ServiceError::ResourceQuotaExceeded {
+                resource, limit, ..
+            } => format!("Quota exceeded: {}", resource.describe(*limit)),
+            ServiceError::ConsentRequired(msg) => format!("Consent required: {}", msg),
+            ServiceError::CsrfRejected(msg) => format!("Csrf rejected: {}", msg),
+            ServiceError::DuplicateUser(msg) => format!("Duplicate user: {}", msg),
+            ServiceError::NotFound(msg) => format!("Not found: {}", msg),
+            ServiceError::FolderNotFound(id) => format!("Folder {} not found", id),
+            ServiceError::PaperNotFound(id) => format!("Paper {} not found", id),
+            ServiceError::Validation(_) => "Validation failed".to_string(),
+            ServiceError::MongoClientError(err) if is_db_outage(err) => {
+                "Database is temporarily unavailable, retry later".to_string()
             }
-            ServiceError::JwtError(err) => {
-                res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
-                res.render(format!("JWT error: {}", err));
+            ServiceError::MongoClientError(err) => format!("MongoDB error: {}", err),
+            ServiceError::BsonDeError(err) => format!("BSON error: {}", err),
+            ServiceError::BsonSerError(err) => format!("BSON error: {}", err),
+            ServiceError::JwtError(err) => format!("JWT error: {}", err),
+            ServiceError::MailError(msg) => format!("Mail error: {}", msg),
+            ServiceError::LLMError(msg) => format!("LLM error: {}", msg),
+            ServiceError::EmbeddingError(msg) => format!("Embedding error: {}", msg),
+            ServiceError::IoError(err) => format!("IO error: {}", err),
+            ServiceError::PdfError(msg) => format!("PDF error: {}", msg),
+            ServiceError::UpstreamError(msg) => format!("Upstream error: {}", msg),
+            ServiceError::Timeout(secs) => {
+                format!("Request did not complete within {} seconds", secs)
+            }
+            ServiceError::CircuitOpen {
+                service,
+                retry_after,
+            } => format!(
+                "{} is temporarily unavailable, retry in {} seconds",
+                service, retry_after
+            ),
+            ServiceError::RestoreRunning => {
+                "A backup is being restored, retry once it is done".to_string()
+            }
+        }
+    }
+
+    /// Structured information about the error, for the `details` of the response.
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            ServiceError::QuotaExceeded {
+                used,
+                quota,
+                resets_at,
+            } => Some(serde_json::json!({
+                "used": used,
+                "quota": quota,
+                "resetsAt": crate::utils::time::to_json(*resets_at),
+            })),
+            ServiceError::ResourceQuotaExceeded {
+                resource,
+                used,
+                limit,
+            } => Some(serde_json::json!({
+                "resource": resource,
+                "used": used,
+                "limit": limit,
+            })),
+            ServiceError::PayloadTooLarge(limit) => Some(serde_json::json!({ "limit": limit })),
+            ServiceError::Timeout(secs) => Some(serde_json::json!({ "timeoutSecs": secs })),
+            ServiceError::CircuitOpen {
+                service,
+                retry_after,
+            } => Some(serde_json::json!({
+                "service": service,
+                "retryAfter": retry_after,
+            })),
+            _ => None,
+        }
+    }
+}
+
+impl Scribe for ServiceError {
+    fn render(self, res: &mut salvo::Response) {
+        let status_code = self.status_code();
+        if status_code.is_server_error() {
+            tracing::error!("Service error: {}", self);
+        }
+        let request_id = res
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        res.status_code(status_code);
+        if status_code == StatusCode::SERVICE_UNAVAILABLE {
+            res.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from_static(RETRY_AFTER_SECS));
+        }
+        if let ServiceError::RateLimited(secs)
+        | ServiceError::LoginLocked(secs)
+        | ServiceError::CircuitOpen {
+            retry_after: secs, ..
+        } = &self
+        {
+            res.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(*secs));
+        }
+        if let ServiceError::QuotaExceeded { resets_at, .. } = &self {
+            let secs = (resets_at - chrono::Utc::now().timestamp_millis()).max(0) / 1000;
+            res.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(secs));
+        }
+        let locale = Locale::of_response(res);
+        // the payload of the version of the api answering
+        match ApiVersion::of_response(res) {
+            ApiVersion::V1 | ApiVersion::V2 => self.render_v1(res, request_id, locale),
+        }
+    }
+}
+
+impl ServiceError {
+    /// The `ErrorResponse` payload, a `ValidationErrorResponse` for a 422, in
+    /// the locale of the request.
+    fn render_v1(self, res: &mut salvo::Response, request_id: Option<String>, locale: Locale) {
+        let message = locale.error_message(&self);
+        match self {
+            ServiceError::Validation(mut errors) => {
+                errors.request_id = request_id;
+                if let Some(message) = message {
+                    errors.message = message;
+                }
+                for error in &mut errors.errors {
+                    if let Some(message) = locale.field_message(&error.code, &error.field) {
+                        error.message = message;
+                    }
+                }
+                res.render(Json(errors));
+            }
+            err => {
+                res.render(Json(ErrorResponse {
+                    code: err.code(),
+                    message: message.unwrap_or_else(|| err.message()),
+                    details: err.details(),
+                    request_id,
+                }));
             }
         }
     }
@@ -77,29 +414,81 @@ impl Scribe for ServiceError {
 
 impl EndpointOutRegister for ServiceError {
     fn register(components: &mut oapi::Components, operation: &mut oapi::Operation) {
+        for (status_code, description) in [
+            (StatusCode::BAD_REQUEST, "Bad request"),
+            (StatusCode::UNAUTHORIZED, "Unauthorized"),
+            (StatusCode::NOT_FOUND, "Not found"),
+            (StatusCode::CONFLICT, "Conflict"),
+            (StatusCode::PAYLOAD_TOO_LARGE, "Payload too large"),
+            (StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
+            (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
+            (StatusCode::BAD_GATEWAY, "Upstream error"),
+            (StatusCode::SERVICE_UNAVAILABLE, "Service unavailable"),
+            (StatusCode::GATEWAY_TIMEOUT, "Timeout"),
+        ] {
+            operation.responses.insert(
+                status_code.as_str(),
+                oapi::Response::new(description)
+                    .add_content("application/json", ErrorResponse::to_schema(components)),
+            );
+        }
         operation.responses.insert(
-            StatusCode::BAD_REQUEST.as_str(),
-            oapi::Response::new("Bad request")
-                .add_content("application/json", StatusError::to_schema(components)),
-        );
-        operation.responses.insert(
-            StatusCode::UNAUTHORIZED.as_str(),
-            oapi::Response::new("Unauthorized")
-                .add_content("application/json", StatusError::to_schema(components)),
-        );
-        operation.responses.insert(
-            StatusCode::NOT_FOUND.as_str(),
-            oapi::Response::new("Not found")
-                .add_content("application/json", StatusError::to_schema(components)),
-        );
-        operation.responses.insert(
-            StatusCode::INTERNAL_SERVER_ERROR.as_str(),
-            oapi::Response::new("Internal server error")
-                .add_content("application/json", StatusError::to_schema(components)),
+            StatusCode::UNPROCESSABLE_ENTITY.as_str(),
+            oapi::Response::new("Validation error").add_content(
+                "application/json",
+                ValidationErrorResponse::to_schema(components),
+            ),
         );
     }
 }
 
+impl From<ValidationErrors> for ServiceError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut fields = Vec::new();
+        flatten_validation_errors("", &errors, &mut fields);
+        ServiceError::Validation(ValidationErrorResponse::new(fields))
+    }
+}
+
+fn flatten_validation_errors(prefix: &str, errors: &ValidationErrors, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", prefix, field)
+        };
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                for error in errors {
+                    out.push(FieldError {
+                        field: path.clone(),
+                        code: error.code.to_string(),
+                        message: error
+                            .message
+                            .as_ref()
+                            .map(|m| m.to_string())
+                            .unwrap_or_else(|| format!("{} is invalid ({})", path, error.code)),
+                        // never echo back secrets
+                        rejected_value: if path.contains("password") {
+                            None
+                        } else {
+                            error.params.get("value").cloned()
+                        },
+                    });
+                }
+            }
+            ValidationErrorsKind::Struct(errors) => {
+                flatten_validation_errors(&path, errors, out);
+            }
+            ValidationErrorsKind::List(errors) => {
+                for (index, errors) in errors {
+                    flatten_validation_errors(&format!("{}[{}]", path, index), errors, out);
+                }
+            }
+        }
+    }
+}
+
 // for depot.get/obtain
 impl From<Option<&Box<dyn Any + Send + Sync>>> for ServiceError {
     fn from(value: Option<&Box<dyn Any + Send + Sync>>) -> Self {
@@ -112,6 +501,26 @@ impl From<Option<&Box<dyn Any + Send + Sync>>> for ServiceError {
     }
 }
 
+/// Catcher hoop answering the bodies which could not be parsed, e.g. a
+/// `JsonBody` of invalid json or of the wrong shape, with the 422 of the
+/// validation errors instead of the bare 400 of the framework.
+#[handler]
+pub async fn catch_parse_error(res: &mut Response, ctrl: &mut FlowCtrl) {
+    let message = match &res.body {
+        ResBody::Error(e) if e.code == StatusCode::BAD_REQUEST => e
+            .cause
+            .as_ref()
+            .and_then(|cause| cause.downcast_ref::<ParseError>())
+            .map(|cause| cause.to_string()),
+        _ => None,
+    };
+    if let Some(message) = message {
+        res.replace_body(ResBody::None);
+        ServiceError::invalid_field("body", "parse", message).render(res);
+        ctrl.skip_rest();
+    }
+}
+
 impl From<salvo::http::ParseError> for ServiceError {
     fn from(err: salvo::http::ParseError) -> Self {
         ServiceError::BadRequest(err.to_string())
@@ -0,0 +1,31 @@
+use crate::{
+    app_data::AppDataRef,
+    error::ServiceResult,
+    events::{DomainEvent, Event, EventSubscriber},
+    model::{
+        audit::{AuditAction, AuditLog, AuditLogRepository},
+        txn::TxnContext,
+    },
+};
+
+/// Keeps a trail of the destructive events.
+pub struct AuditLogger;
+
+#[async_trait::async_trait]
+impl EventSubscriber for AuditLogger {
+    fn name(&self) -> &'static str {
+        "audit_logger"
+    }
+
+    async fn handle(&self, state: &AppDataRef, event: &Event) -> ServiceResult<()> {
+        let DomainEvent::PaperDeleted { paper_id } = &event.payload else {
+            return Ok(());
+        };
+        let mut log = AuditLog::new(&event.user_id, AuditAction::PaperDeleted, None);
+        log.detail = Some(paper_id.clone());
+        state
+            .db
+            .create_audit_log(&mut TxnContext::none(), log)
+            .await
+    }
+}
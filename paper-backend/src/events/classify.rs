@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+
+use crate::{
+    app_data::AppDataRef,
+    classify::suggest_for_paper,
+    error::ServiceResult,
+    events::{DomainEvent, Event, EventSubscriber},
+};
+
+// classifications waiting on the model at once, a folder copy creates many papers
+const MAX_RUNNING_CLASSIFICATIONS: usize = 4;
+
+/// Suggests tags and a folder for the papers added by the user.
+pub struct PaperClassifier {
+    running: Arc<Semaphore>,
+}
+
+impl Default for PaperClassifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PaperClassifier {
+    pub fn new() -> Self {
+        PaperClassifier {
+            running: Arc::new(Semaphore::new(MAX_RUNNING_CLASSIFICATIONS)),
+        }
+    }
+}
+
+/// Classify the paper once a slot is free, logging a failure.
+async fn classify(state: AppDataRef, running: Arc<Semaphore>, paper_id: String) {
+    let Ok(_permit) = running.acquire().await else {
+        return;
+    };
+    if let Err(e) = suggest_for_paper(&state, &paper_id).await {
+        tracing::error!("Failed to classify paper {}: {}", paper_id, e);
+    }
+}
+
+#[async_trait::async_trait]
+impl EventSubscriber for PaperClassifier {
+    fn name(&self) -> &'static str {
+        "paper_classifier"
+    }
+
+    async fn handle(&self, state: &AppDataRef, event: &Event) -> ServiceResult<()> {
+        // the model call runs in the background, not to hold up the next events
+        if let DomainEvent::PaperCreated { paper_id, .. } = &event.payload {
+            state.jobs.spawn(classify(
+                state.clone(),
+                self.running.clone(),
+                paper_id.clone(),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{
+    app_data::AppDataRef,
+    digest::greeting_name,
+    error::ServiceResult,
+    events::{DomainEvent, Event, EventSubscriber},
+    i18n::{Locale, SUMMARY_READY_BODY, SUMMARY_READY_SUBJECT, default_locale},
+    model::{paper::PaperRepository, user::UserRepository},
+    utils::mailer::Mail,
+};
+
+/// Emails the users who opted in when their AI summaries are ready.
+pub struct EmailNotifier;
+
+#[async_trait::async_trait]
+impl EventSubscriber for EmailNotifier {
+    fn name(&self) -> &'static str {
+        "email_notifier"
+    }
+
+    async fn handle(&self, state: &AppDataRef, event: &Event) -> ServiceResult<()> {
+        let DomainEvent::SummaryReady { paper_id, .. } = &event.payload else {
+            return Ok(());
+        };
+        let Some(user) = state.db.get_user_by_uid(&event.user_id).await? else {
+            return Ok(());
+        };
+        if !user.notification_preferences.summary_ready_email {
+            return Ok(());
+        }
+        let Some(email) = user.email.clone() else {
+            return Ok(());
+        };
+        let Some(paper) = state.db.get_paper_by_id(paper_id).await? else {
+            return Ok(());
+        };
+        let locale = Locale::of_user(&user, default_locale(&state.settings_config));
+        let body = locale.render(
+            SUMMARY_READY_BODY,
+            &[
+                ("name", greeting_name(&user, locale)),
+                ("title", &paper.title),
+                ("public_url", &state.public_url),
+            ],
+        );
+        state
+            .mailer
+            .send(Mail {
+                to: email,
+                subject: locale.render(SUMMARY_READY_SUBJECT, &[("title", &paper.title)]),
+                body,
+            })
+            .await
+    }
+}
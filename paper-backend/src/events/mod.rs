@@ -0,0 +1,269 @@
+pub mod audit;
+pub mod classify;
+pub mod email;
+pub mod notify;
+pub mod search_index;
+pub mod webhook;
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{
+    app_data::AppDataRef,
+    error::ServiceResult,
+    model::{export::ExportStatus, paper::TextStatus},
+    utils::request_id::{current_request_id, scope_request},
+};
+
+// events buffered for slow subscribers, beyond they miss some
+const EVENT_BUFFER: usize = 1024;
+
+/// A mutation of the data of a user, published once it is stored.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum DomainEvent {
+    FolderCreated {
+        folder_id: String,
+        name: String,
+    },
+    // renamed, moved or archived
+    FolderUpdated {
+        folder_id: String,
+        name: String,
+    },
+    PaperCreated {
+        paper_id: String,
+        folder_id: String,
+    },
+    PaperUpdated {
+        paper_id: String,
+    },
+    PaperDeleted {
+        paper_id: String,
+    },
+    SummaryReady {
+        paper_id: String,
+        folder_id: String,
+    },
+    TextExtracted {
+        paper_id: String,
+        status: TextStatus,
+    },
+    NotificationCreated {
+        notification_id: String,
+        title: String,
+    },
+    ExportFinished {
+        export_id: String,
+        status: ExportStatus,
+    },
+}
+
+impl DomainEvent {
+    pub const KINDS: &'static [&'static str] = &[
+        "folder_created",
+        "folder_updated",
+        "paper_created",
+        "paper_updated",
+        "paper_deleted",
+        "summary_ready",
+        "text_extracted",
+        "notification_created",
+        "export_finished",
+    ];
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DomainEvent::FolderCreated { .. } => "folder_created",
+            DomainEvent::FolderUpdated { .. } => "folder_updated",
+            DomainEvent::PaperCreated { .. } => "paper_created",
+            DomainEvent::PaperUpdated { .. } => "paper_updated",
+            DomainEvent::PaperDeleted { .. } => "paper_deleted",
+            DomainEvent::SummaryReady { .. } => "summary_ready",
+            DomainEvent::TextExtracted { .. } => "text_extracted",
+            DomainEvent::NotificationCreated { .. } => "notification_created",
+            DomainEvent::ExportFinished { .. } => "export_finished",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    pub id: String, // uuid
+    pub user_id: String,
+    pub occurred_at: i64, // timestamp in milliseconds
+    /// Id of the request the event was published by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub payload: DomainEvent,
+}
+
+/// Broadcasts the domain events to the subscribers of this instance, so side
+/// effects stay out of the handlers.
+#[derive(Debug)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+    // events missed by the lagging subscribers and sockets since the start
+    missed: AtomicU64,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus {
+            sender: broadcast::channel(EVENT_BUFFER).0,
+            missed: AtomicU64::new(0),
+        }
+    }
+}
+
+impl EventBus {
+    pub fn publish(&self, user_id: &str, payload: DomainEvent) {
+        let event = Event {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            occurred_at: bson::DateTime::now().timestamp_millis(),
+            request_id: current_request_id(),
+            payload,
+        };
+        // fails only when nobody is subscribed
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Count the events a receiver missed by lagging behind the buffer.
+    pub fn record_missed(&self, receiver: &str, missed: u64) {
+        self.missed.fetch_add(missed, Ordering::Relaxed);
+        tracing::warn!("{} missed {} events", receiver, missed);
+    }
+
+    /// The events missed by every receiver since the start.
+    pub fn missed(&self) -> u64 {
+        self.missed.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait::async_trait]
+pub trait EventSubscriber: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn handle(&self, state: &AppDataRef, event: &Event) -> ServiceResult<()>;
+}
+
+/// Run the subscriber on every event published from now on, for the lifetime
+/// of the service. A failure is logged and does not stop the subscriber.
+pub fn subscribe(state: &AppDataRef, subscriber: Arc<dyn EventSubscriber>) {
+    let mut receiver = state.events.subscribe();
+    let state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let handled = subscriber.handle(&state, &event);
+                    let handled = match event.request_id.clone() {
+                        Some(request_id) => scope_request(request_id, handled).await,
+                        None => handled.await,
+                    };
+                    if let Err(e) = handled {
+                        tracing::error!(
+                            "Subscriber {} failed on event {}: {}",
+                            subscriber.name(),
+                            event.id,
+                            e
+                        );
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    let receiver = format!("Subscriber {}", subscriber.name());
+                    state.events.record_missed(&receiver, missed);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Register the builtin subscribers, the WebSocket fanout subscribes per connection.
+pub fn register_subscribers(state: &AppDataRef) {
+    subscribe(state, Arc::new(audit::AuditLogger));
+    subscribe(state, Arc::new(classify::PaperClassifier::new()));
+    subscribe(state, Arc::new(email::EmailNotifier));
+    subscribe(state, Arc::new(notify::NotificationCenter));
+    subscribe(state, Arc::new(webhook::WebhookDispatcher::new()));
+    if state.embedder.is_some() {
+        subscribe(state, Arc::new(search_index::SearchIndexer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::app_data::AppData;
+
+    struct Recorder(mpsc::UnboundedSender<Event>);
+
+    #[async_trait::async_trait]
+    impl EventSubscriber for Recorder {
+        fn name(&self) -> &'static str {
+            "recorder"
+        }
+
+        async fn handle(&self, _state: &AppDataRef, event: &Event) -> ServiceResult<()> {
+            let _ = self.0.send(event.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_receives_events() {
+        let state = AppData::for_tests().await;
+        let (sender, mut events) = mpsc::unbounded_channel();
+        subscribe(&state, Arc::new(Recorder(sender)));
+
+        let paper_id = "paper".to_string();
+        state
+            .events
+            .publish("user", DomainEvent::PaperDeleted { paper_id });
+        let event = tokio::time::timeout(Duration::from_secs(1), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.user_id, "user");
+        assert_eq!(event.payload.kind(), "paper_deleted");
+        assert!(DomainEvent::KINDS.contains(&event.payload.kind()));
+    }
+
+    #[tokio::test]
+    async fn test_lagging_receiver_is_counted() {
+        let bus = EventBus::default();
+        let mut receiver = bus.subscribe();
+        for i in 0..EVENT_BUFFER + 3 {
+            let paper_id = i.to_string();
+            bus.publish("user", DomainEvent::PaperUpdated { paper_id });
+        }
+        let Err(RecvError::Lagged(missed)) = receiver.recv().await else {
+            panic!("the receiver should lag");
+        };
+        bus.record_missed("test", missed);
+        assert_eq!(bus.missed(), 3);
+        // the oldest events kept are received next
+        assert!(receiver.recv().await.is_ok());
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{
+    app_data::AppDataRef,
+    error::ServiceResult,
+    events::{DomainEvent, Event, EventSubscriber},
+    model::{
+        export::{ExportRepository, ExportStatus},
+        folder::FolderRepository,
+        notification::{Notification, NotificationKind, NotificationRepository},
+        paper::{PaperRepository, TextStatus},
+    },
+};
+
+/// Fills the in-app notification center of the users, and pushes the new
+/// notifications to their open WebSockets.
+pub struct NotificationCenter;
+
+async fn notification_for(
+    state: &AppDataRef,
+    event: &Event,
+) -> ServiceResult<Option<Notification>> {
+    let notification = match &event.payload {
+        DomainEvent::SummaryReady {
+            paper_id,
+            folder_id,
+        } => {
+            let Some(folder) = state.db.get_folder_by_id(folder_id).await? else {
+                return Ok(None);
+            };
+            Notification::new(
+                &event.user_id,
+                NotificationKind::FolderWrappedUp,
+                format!("Project \"{}\" wrapped up", folder.name),
+            )
+            .with_content("The project summary is ready")
+            .with_resource(paper_id)
+        }
+        DomainEvent::TextExtracted { paper_id, status } => {
+            let Some(paper) = state.db.get_paper_by_id(paper_id).await? else {
+                return Ok(None);
+            };
+            let content = match status {
+                TextStatus::Ready => "The text is ready for search and citations",
+                TextStatus::Failed => "The text could not be extracted from the file",
+                TextStatus::Quarantined => "The file was refused by the content scan",
+                TextStatus::Pending | TextStatus::Ocr => return Ok(None),
+            };
+            Notification::new(
+                &event.user_id,
+                NotificationKind::ImportFinished,
+                format!("\"{}\" imported", paper.title),
+            )
+            .with_content(content)
+            .with_resource(paper_id)
+        }
+        DomainEvent::ExportFinished { export_id, status } => {
+            let Some(job) = state.db.get_export(&event.user_id, export_id).await? else {
+                return Ok(None);
+            };
+            let content = match status {
+                ExportStatus::Ready => "The archive is ready to download",
+                ExportStatus::Failed => "The archive could not be built",
+                ExportStatus::Pending => return Ok(None),
+            };
+            Notification::new(
+                &event.user_id,
+                NotificationKind::ExportReady,
+                format!("\"{}\" exported", job.file_name),
+            )
+            .with_content(content)
+            .with_resource(export_id)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(notification))
+}
+
+#[async_trait::async_trait]
+impl EventSubscriber for NotificationCenter {
+    fn name(&self) -> &'static str {
+        "notification_center"
+    }
+
+    async fn handle(&self, state: &AppDataRef, event: &Event) -> ServiceResult<()> {
+        let Some(notification) = notification_for(state, event).await? else {
+            return Ok(());
+        };
+        let payload = DomainEvent::NotificationCreated {
+            notification_id: notification.id.clone(),
+            title: notification.title.clone(),
+        };
+        state.db.create_notification(notification).await?;
+        state.events.publish(&event.user_id, payload);
+        Ok(())
+    }
+}
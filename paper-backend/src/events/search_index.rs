@@ -0,0 +1,70 @@
+use crate::{
+    app_data::AppDataRef,
+    error::ServiceResult,
+    events::{DomainEvent, Event, EventSubscriber},
+    model::{
+        embedding::{PaperEmbedding, PaperEmbeddingRepository},
+        paper::{Paper, PaperRepository},
+    },
+};
+
+// max characters of a paper sent to the embedder
+const EMBEDDING_MAX_CHARS: usize = 8000;
+
+/// Keeps the embeddings of the papers, used by semantic search, up to date.
+pub struct SearchIndexer;
+
+fn embedding_text(paper: &Paper) -> String {
+    [
+        Some(paper.title.as_str()),
+        paper.r#abstract.as_deref(),
+        paper.summary.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("\n\n")
+    .chars()
+    .take(EMBEDDING_MAX_CHARS)
+    .collect()
+}
+
+#[async_trait::async_trait]
+impl EventSubscriber for SearchIndexer {
+    fn name(&self) -> &'static str {
+        "search_indexer"
+    }
+
+    async fn handle(&self, state: &AppDataRef, event: &Event) -> ServiceResult<()> {
+        let Some(embedder) = &state.embedder else {
+            return Ok(());
+        };
+        let paper_id = match &event.payload {
+            DomainEvent::PaperCreated { paper_id, .. } | DomainEvent::PaperUpdated { paper_id } => {
+                paper_id
+            }
+            DomainEvent::PaperDeleted { paper_id } => {
+                return state.db.delete_paper_embedding(paper_id).await;
+            }
+            _ => return Ok(()),
+        };
+        let Some(paper) = state.db.get_paper_by_id(paper_id).await? else {
+            return Ok(());
+        };
+        let vector = embedder
+            .embed(&[embedding_text(&paper)])
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        state
+            .db
+            .upsert_paper_embedding(PaperEmbedding {
+                paper_id: paper.id,
+                user_id: paper.user_id,
+                updated_at: bson::DateTime::now(),
+                vector,
+            })
+            .await
+    }
+}
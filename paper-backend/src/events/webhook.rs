@@ -0,0 +1,306 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use hmac::{Hmac, Mac};
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect,
+};
+use sha2::Sha256;
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult},
+    events::{Event, EventSubscriber},
+    model::webhook::{Webhook, WebhookDelivery, WebhookRepository},
+};
+
+// attempts per delivery, waiting 1s, 4s, 16s... in between
+const MAX_ATTEMPTS: u32 = 4;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+pub const TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+pub const EVENT_HEADER: &str = "x-webhook-event";
+pub const EVENT_ID_HEADER: &str = "x-webhook-id";
+
+/// Posts the events of the user to its webhooks, as json signed with the secret
+/// of the webhook.
+pub struct WebhookDispatcher {
+    client: reqwest::Client,
+}
+
+impl Default for WebhookDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookDispatcher {
+    pub fn new() -> Self {
+        WebhookDispatcher {
+            // a redirect could lead to an internal address
+            client: reqwest::Client::builder()
+                .timeout(DELIVERY_TIMEOUT)
+                .user_agent(concat!("paper-backend/", env!("CARGO_PKG_VERSION")))
+                .redirect(redirect::Policy::none())
+                .dns_resolver(Arc::new(PublicResolver))
+                .build()
+                .expect("Failed to create the webhook client"),
+        }
+    }
+}
+
+/// Whether the address is reachable on the internet, the webhooks must not
+/// reach the loopback, private, link-local (e.g. the cloud metadata at
+/// 169.254.169.254) or otherwise reserved addresses of the network of the
+/// server.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                // shared address space of the carrier-grade nat
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let segment = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // unique local and link-local
+                || (segment & 0xfe00) == 0xfc00
+                || (segment & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Resolves the hosts of the webhooks at delivery time, refusing the ones
+/// with a non public address: the check of the url on creation is not enough,
+/// the name can point anywhere by then.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .collect::<Vec<SocketAddr>>();
+            if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+                let error = format!("{} resolves to the non public {}", host, addr.ip());
+                return Err(error.into());
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(addrs)
+        })
+    }
+}
+
+/// Refuse the urls naming a non public address, which are not resolved.
+pub fn check_host(url: &str) -> Result<(), String> {
+    let url = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| "The url has no host".to_string())?;
+    let Ok(ip) = host.trim_matches(['[', ']']).parse::<IpAddr>() else {
+        return Ok(());
+    };
+    match is_public_ip(ip) {
+        true => Ok(()),
+        false => Err(format!("{} is not a public address", ip)),
+    }
+}
+
+/// `sha256=<hex>` HMAC of `<timestamp>.<body>`, receivers recompute it with the
+/// secret and reject old timestamps to prevent replays.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    let signature = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!("sha256={}", signature)
+}
+
+/// Status code of the response, or the error of the last attempt.
+type AttemptResult = Result<u16, (Option<u16>, String)>;
+
+async fn post(
+    client: &reqwest::Client,
+    webhook: &Webhook,
+    event: &Event,
+    body: &str,
+) -> AttemptResult {
+    check_host(&webhook.url).map_err(|e| (None, e))?;
+    let timestamp = bson::DateTime::now().timestamp_millis() / 1000;
+    let response = client
+        .post(&webhook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, sign(&webhook.secret, timestamp, body))
+        .header(TIMESTAMP_HEADER, timestamp)
+        .header(EVENT_HEADER, event.payload.kind())
+        .header(EVENT_ID_HEADER, &event.id)
+        .body(body.to_string())
+        .send()
+        .await
+        .map_err(|e| (None, e.to_string()))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(status.as_u16());
+    }
+    // the body is not read, nor logged back to the user
+    Err((Some(status.as_u16()), format!("HTTP {}", status)))
+}
+
+/// Deliver the event with retries and log the outcome.
+async fn deliver(
+    state: AppDataRef,
+    client: reqwest::Client,
+    webhook: Webhook,
+    event: Event,
+    body: String,
+) {
+    let mut attempts = 0;
+    let outcome = loop {
+        attempts += 1;
+        let outcome = post(&client, &webhook, &event, &body).await;
+        // client errors other than rate limiting will not go away
+        let retryable = match &outcome {
+            Ok(_) => false,
+            Err((Some(status), _)) => *status == 429 || *status >= 500,
+            Err((None, _)) => true,
+        };
+        if !retryable || attempts >= MAX_ATTEMPTS {
+            break outcome;
+        }
+        tokio::time::sleep(RETRY_BASE_DELAY * 4u32.pow(attempts - 1)).await;
+    };
+
+    let (success, status_code, error) = match outcome {
+        Ok(status) => (true, Some(status), None),
+        Err((status, error)) => {
+            tracing::warn!(
+                "Delivery of event {} to webhook {} failed: {}",
+                event.id,
+                webhook.id,
+                error
+            );
+            (false, status, Some(error))
+        }
+    };
+    let delivery = WebhookDelivery {
+        id: uuid::Uuid::new_v4().to_string(),
+        webhook_id: webhook.id.clone(),
+        user_id: webhook.user_id.clone(),
+        created_at: bson::DateTime::now(),
+
+        event_id: event.id.clone(),
+        event_type: event.payload.kind().to_string(),
+        success,
+        attempts,
+        status_code,
+        error,
+    };
+    if let Err(e) = state.db.create_webhook_delivery(delivery).await {
+        tracing::error!("Failed to log delivery to webhook {}: {}", webhook.id, e);
+    }
+}
+
+#[async_trait::async_trait]
+impl EventSubscriber for WebhookDispatcher {
+    fn name(&self) -> &'static str {
+        "webhook_dispatcher"
+    }
+
+    async fn handle(&self, state: &AppDataRef, event: &Event) -> ServiceResult<()> {
+        let webhooks = state
+            .db
+            .get_webhooks(&event.user_id)
+            .await?
+            .into_iter()
+            .filter(|webhook| webhook.accepts(event.payload.kind()))
+            .collect::<Vec<_>>();
+        if webhooks.is_empty() {
+            return Ok(());
+        }
+        let body = serde_json::to_string(event)
+            .map_err(|e| ServiceError::InternalServerError(e.to_string()))?;
+        // deliveries retry in the background, not to hold up the next events
+        for mut webhook in webhooks {
+            webhook.secret = state.open_secret(&webhook.secret)?;
+            state.jobs.spawn(deliver(
+                state.clone(),
+                self.client.clone(),
+                webhook,
+                event.clone(),
+                body.clone(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_covers_timestamp_and_body() {
+        let signature = sign("secret", 1700000000, r#"{"type":"paper_created"}"#);
+        assert!(signature.starts_with("sha256="));
+        assert_eq!(signature.len(), "sha256=".len() + 64);
+        assert_ne!(
+            signature,
+            sign("secret", 1700000001, r#"{"type":"paper_created"}"#)
+        );
+        assert_ne!(
+            signature,
+            sign("other", 1700000000, r#"{"type":"paper_created"}"#)
+        );
+    }
+
+    #[test]
+    fn test_internal_addresses_are_refused() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        assert!(is_public_ip("93.184.216.34".parse().unwrap()));
+        assert!(is_public_ip("2606:2800:220:1::".parse().unwrap()));
+        assert!(check_host("http://169.254.169.254/latest/meta-data").is_err());
+        assert!(check_host("http://[::1]:8080/hook").is_err());
+        assert!(check_host("https://hooks.example.com/hook").is_ok());
+    }
+}
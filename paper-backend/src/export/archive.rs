@@ -0,0 +1,121 @@
+use std::{
+    fs::File,
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+};
+
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use crate::error::{ServiceError, ServiceResult};
+
+fn zip_error(e: impl std::fmt::Display) -> ServiceError {
+    ServiceError::InternalServerError(format!("Zip export error: {}", e))
+}
+
+/// Pack the files, as (name, content), into a zip archive.
+pub fn zip_files(files: &[(String, Vec<u8>)]) -> ServiceResult<Vec<u8>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, content) in files {
+        writer
+            .start_file(name.as_str(), options)
+            .map_err(zip_error)?;
+        writer.write_all(content).map_err(zip_error)?;
+    }
+    let archive = writer.finish().map_err(zip_error)?;
+    Ok(archive.into_inner())
+}
+
+/// A zip archive written to a temporary file as its files are added, so an
+/// export never holds all of them in memory. The file is removed on drop.
+pub struct ArchiveFile {
+    path: PathBuf,
+    writer: Option<ZipWriter<File>>,
+}
+
+impl ArchiveFile {
+    pub fn create() -> ServiceResult<Self> {
+        let path = std::env::temp_dir().join(format!("paper-export-{}.zip", uuid::Uuid::new_v4()));
+        let file = File::create(&path)?;
+        Ok(ArchiveFile {
+            path,
+            writer: Some(ZipWriter::new(file)),
+        })
+    }
+
+    pub fn add(&mut self, name: &str, content: &[u8]) -> ServiceResult<()> {
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| zip_error("archive already finished"))?;
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        writer.start_file(name, options).map_err(zip_error)?;
+        writer.write_all(content).map_err(zip_error)
+    }
+
+    /// Write the end of the archive, answering its size.
+    pub fn finish(&mut self) -> ServiceResult<u64> {
+        let writer = self
+            .writer
+            .take()
+            .ok_or_else(|| zip_error("archive already finished"))?;
+        let file = writer.finish().map_err(zip_error)?;
+        Ok(file.metadata()?.len())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ArchiveFile {
+    fn drop(&mut self) {
+        self.writer.take();
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::warn!("Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// The name without path separators or control characters.
+pub fn sanitize(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(80)
+        .collect::<String>();
+    match name.trim() {
+        "" => "untitled".to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// A file name of the title, unique among the names already given.
+pub fn file_name(title: &str, extension: &str, taken: &mut Vec<String>) -> String {
+    let stem = sanitize(title);
+    let mut name = format!("{}.{}", stem, extension);
+    let mut n = 1;
+    while taken.contains(&name) {
+        n += 1;
+        name = format!("{} ({}).{}", stem, n, extension);
+    }
+    taken.push(name.clone());
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        let mut taken = Vec::new();
+        assert_eq!(file_name("A/B: c?", "md", &mut taken), "A_B_ c_.md");
+        assert_eq!(file_name("A/B: c?", "md", &mut taken), "A_B_ c_ (2).md");
+        assert_eq!(file_name("  ", "md", &mut taken), "untitled.md");
+    }
+}
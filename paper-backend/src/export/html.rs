@@ -0,0 +1,82 @@
+use crate::model::paper::Paper;
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Paragraphs of the text, separated by blank lines, line breaks kept.
+fn paragraphs(text: &str) -> String {
+    text.split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| format!("<p>{}</p>\n", escape_html(p).replace('\n', "<br>\n")))
+        .collect()
+}
+
+/// Render the paper metadata, summary and notes as a standalone html page.
+pub fn export_paper(paper: &Paper) -> String {
+    let title = escape_html(&paper.title);
+    let mut body = format!("<h1>{}</h1>\n", title);
+    if !paper.authors.is_empty() {
+        body.push_str(&format!(
+            "<p class=\"authors\">{}</p>\n",
+            escape_html(&paper.authors.join(", "))
+        ));
+    }
+    if let Some(doi) = &paper.doi {
+        let doi = escape_html(doi);
+        body.push_str(&format!(
+            "<p>DOI: <a href=\"https://doi.org/{}\">{}</a></p>\n",
+            doi, doi
+        ));
+    }
+    if !paper.tags.is_empty() {
+        body.push_str(&format!(
+            "<p class=\"tags\">{}</p>\n",
+            escape_html(&paper.tags.join(", "))
+        ));
+    }
+    for (heading, text) in [
+        ("Abstract", &paper.r#abstract),
+        ("Summary", &paper.summary),
+        ("Notes", &paper.content),
+    ] {
+        if let Some(text) = text {
+            body.push_str(&format!("<h2>{}</h2>\n{}", heading, paragraphs(text)));
+        }
+    }
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n\
+        <body>\n{}</body>\n</html>\n",
+        title, body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_paper_html() {
+        let mut paper = Paper::new("user", "folder", "Attention <Is> All You Need".to_string());
+        paper.summary = Some("First \"point\".\nSame paragraph.\n\nSecond & last.".to_string());
+        let html = export_paper(&paper);
+        assert!(html.contains("<title>Attention &lt;Is&gt; All You Need</title>"));
+        assert!(html.contains(
+            "<h2>Summary</h2>\n<p>First &quot;point&quot;.<br>\nSame paragraph.</p>\n\
+            <p>Second &amp; last.</p>\n"
+        ));
+        assert!(!html.contains("<h2>Notes</h2>"));
+    }
+}
@@ -0,0 +1,25 @@
+use crate::model::paper::Paper;
+
+/// Render the paper metadata, summary and notes as a markdown document.
+pub fn export_paper(paper: &Paper) -> String {
+    let mut markdown = format!("# {}\n", paper.title);
+    if !paper.authors.is_empty() {
+        markdown.push_str(&format!("\n{}\n", paper.authors.join(", ")));
+    }
+    if let Some(doi) = &paper.doi {
+        markdown.push_str(&format!("\nDOI: [{}](https://doi.org/{})\n", doi, doi));
+    }
+    if !paper.tags.is_empty() {
+        markdown.push_str(&format!("\nTags: {}\n", paper.tags.join(", ")));
+    }
+    for (heading, body) in [
+        ("Abstract", &paper.r#abstract),
+        ("Summary", &paper.summary),
+        ("Notes", &paper.content),
+    ] {
+        if let Some(body) = body {
+            markdown.push_str(&format!("\n## {}\n\n{}\n", heading, body.trim_end()));
+        }
+    }
+    markdown
+}
@@ -0,0 +1,48 @@
+pub mod archive;
+pub mod html;
+pub mod markdown;
+pub mod pdf;
+
+pub use paper_schema::export::ExportFormat;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::ServiceResult, model::paper::Paper};
+
+// watermark text longer than this is truncated
+const WATERMARK_MAX_CHARS: usize = 64;
+
+/// Options applied when a paper is exported.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportOptions {
+    /// Text stamped diagonally on every page, e.g. "DRAFT — do not distribute"
+    /// or the recipient email of a shared export.
+    pub watermark: Option<String>,
+}
+
+impl ExportOptions {
+    pub fn with_watermark(mut self, watermark: Option<String>) -> Self {
+        self.watermark = watermark
+            .map(|w| {
+                w.trim()
+                    .chars()
+                    .take(WATERMARK_MAX_CHARS)
+                    .collect::<String>()
+            })
+            .filter(|w| !w.is_empty());
+        self
+    }
+}
+
+/// Render the paper metadata, summary and notes in the format. The watermark
+/// only applies to pdf documents.
+pub fn export_paper_as(
+    paper: &Paper,
+    format: ExportFormat,
+    options: &ExportOptions,
+) -> ServiceResult<Vec<u8>> {
+    match format {
+        ExportFormat::Md => Ok(markdown::export_paper(paper).into_bytes()),
+        ExportFormat::Html => Ok(html::export_paper(paper).into_bytes()),
+        ExportFormat::Pdf => pdf::export_paper(paper, options),
+    }
+}
@@ -0,0 +1,125 @@
+use printpdf::{Color, Greyscale, IndirectFontRef, Mm, PdfDocument, PdfLayerReference, TextMatrix};
+
+use crate::{
+    error::{ServiceError, ServiceResult},
+    export::ExportOptions,
+    model::paper::Paper,
+};
+
+// A4 page
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+const FONT_SIZE: f32 = 11.0;
+const LINE_HEIGHT: f32 = 6.0;
+// rough number of characters per line for the font
+const LINE_CHARS: usize = 90;
+// the builtin fonts only cover latin-1, titles and notes in greek, cyrillic
+// or with math symbols need an embedded font
+const FONT: &[u8] = include_bytes!("fonts/DejaVuSans.ttf");
+
+/// Render the paper metadata, summary and notes into a simple pdf document.
+pub fn export_paper(paper: &Paper, options: &ExportOptions) -> ServiceResult<Vec<u8>> {
+    export_papers(&paper.title, std::slice::from_ref(paper), options)
+}
+
+/// Render several papers into one pdf document, each paper starting on a new page.
+pub fn export_papers(
+    title: &str,
+    papers: &[Paper],
+    options: &ExportOptions,
+) -> ServiceResult<Vec<u8>> {
+    let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "content");
+    let font = doc
+        .add_external_font(FONT)
+        .map_err(|e| ServiceError::InternalServerError(format!("PDF font error: {}", e)))?;
+
+    let mut layer = doc.get_page(page).get_layer(layer);
+    if let Some(watermark) = &options.watermark {
+        draw_watermark(&layer, &font, watermark);
+    }
+    for (i, paper) in papers.iter().enumerate() {
+        let mut y = PAGE_HEIGHT - MARGIN;
+        for (j, line) in paper_lines(paper).into_iter().enumerate() {
+            if y < MARGIN || (i > 0 && j == 0) {
+                let (page, new_layer) = doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "content");
+                layer = doc.get_page(page).get_layer(new_layer);
+                if let Some(watermark) = &options.watermark {
+                    draw_watermark(&layer, &font, watermark);
+                }
+                y = PAGE_HEIGHT - MARGIN;
+            }
+            layer.use_text(line, FONT_SIZE, Mm(MARGIN), Mm(y), &font);
+            y -= LINE_HEIGHT;
+        }
+    }
+
+    doc.save_to_bytes()
+        .map_err(|e| ServiceError::InternalServerError(format!("PDF export error: {}", e)))
+}
+
+fn draw_watermark(layer: &PdfLayerReference, font: &IndirectFontRef, text: &str) {
+    layer.begin_text_section();
+    layer.set_fill_color(Color::Greyscale(Greyscale::new(0.85, None)));
+    layer.set_font(font, 42.0);
+    layer.set_text_matrix(TextMatrix::TranslateRotate(
+        Mm(MARGIN + 10.0).into(),
+        Mm(PAGE_HEIGHT / 3.0).into(),
+        45.0,
+    ));
+    layer.write_text(text, font);
+    layer.end_text_section();
+    // restore the text color for the content
+    layer.set_fill_color(Color::Greyscale(Greyscale::new(0.0, None)));
+}
+
+fn paper_lines(paper: &Paper) -> Vec<String> {
+    let mut sections = vec![paper.title.clone()];
+    if !paper.authors.is_empty() {
+        sections.push(paper.authors.join(", "));
+    }
+    if let Some(doi) = &paper.doi {
+        sections.push(format!("DOI: {}", doi));
+    }
+    for (heading, body) in [
+        ("Abstract", &paper.r#abstract),
+        ("Summary", &paper.summary),
+        ("Notes", &paper.content),
+    ] {
+        if let Some(body) = body {
+            sections.push(String::new());
+            sections.push(heading.to_string());
+            sections.push(body.clone());
+        }
+    }
+
+    sections
+        .iter()
+        .flat_map(|section| section.lines())
+        .flat_map(wrap_line)
+        .collect()
+}
+
+fn wrap_line(line: &str) -> Vec<String> {
+    if line.is_empty() {
+        return vec![String::new()];
+    }
+    let chars = line.chars().collect::<Vec<_>>();
+    chars
+        .chunks(LINE_CHARS)
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_paper_pdf() {
+        let mut paper = Paper::new("user", "folder", "Σ-attention для всех".to_string());
+        paper.summary = Some("Bounds of ∑ and ≤ over ℝ.".to_string());
+        let pdf = export_paper(&paper, &ExportOptions::default()).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+}
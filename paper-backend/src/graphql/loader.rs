@@ -0,0 +1,119 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_graphql::dataloader::Loader;
+use bson::doc;
+use futures::TryStreamExt;
+
+use crate::{
+    app_data::AppDataRef,
+    error::ServiceError,
+    model::{
+        folder::Folder,
+        paper::{Paper, PaperRepository},
+    },
+};
+
+// parent key of the top level folders
+pub const ROOT: &str = "";
+
+/// The folders of the user by id, from the cached folders.
+pub struct FolderLoader {
+    pub state: AppDataRef,
+    pub user_id: String,
+}
+
+impl Loader<String> for FolderLoader {
+    type Value = Folder;
+    type Error = Arc<ServiceError>;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Folder>, Self::Error> {
+        let folders = self.state.cached_folders(&self.user_id).await?;
+        Ok(folders
+            .into_iter()
+            .filter(|folder| keys.contains(&folder.id))
+            .map(|folder| (folder.id.clone(), folder))
+            .collect())
+    }
+}
+
+/// The subfolders of folders of the user by parent, `ROOT` for the top level
+/// ones, in their order.
+pub struct FolderChildrenLoader {
+    pub state: AppDataRef,
+    pub user_id: String,
+}
+
+impl Loader<String> for FolderChildrenLoader {
+    type Value = Vec<Folder>;
+    type Error = Arc<ServiceError>;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Vec<Folder>>, Self::Error> {
+        let folders = self.state.cached_folders(&self.user_id).await?;
+        let mut children = keys
+            .iter()
+            .map(|key| (key.clone(), Vec::new()))
+            .collect::<HashMap<_, _>>();
+        for folder in folders {
+            let parent = folder.parent_id.as_deref().unwrap_or(ROOT);
+            if let Some(siblings) = children.get_mut(parent) {
+                siblings.push(folder);
+            }
+        }
+        children
+            .values_mut()
+            .for_each(|siblings| siblings.sort_by_key(|f| f.sort_order));
+        Ok(children)
+    }
+}
+
+/// The papers of the user by id.
+pub struct PaperLoader {
+    pub state: AppDataRef,
+    pub user_id: String,
+}
+
+impl Loader<String> for PaperLoader {
+    type Value = Paper;
+    type Error = Arc<ServiceError>;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Paper>, Self::Error> {
+        let papers = self.state.db.get_papers_by_ids(&self.user_id, keys).await?;
+        Ok(papers
+            .into_iter()
+            .map(|paper| (paper.id.clone(), paper))
+            .collect())
+    }
+}
+
+/// The papers of folders of the user by folder, newest first, in one query
+/// for all the folders of a response.
+pub struct FolderPapersLoader {
+    pub state: AppDataRef,
+    pub user_id: String,
+}
+
+impl Loader<String> for FolderPapersLoader {
+    type Value = Vec<Paper>;
+    type Error = Arc<ServiceError>;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Vec<Paper>>, Self::Error> {
+        let filter = doc! { "folder_id": { "$in": keys } };
+        let papers = self
+            .state
+            .db
+            .find_papers(&self.user_id, None, Some(filter), None)
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        let mut by_folder = keys
+            .iter()
+            .map(|key| (key.clone(), Vec::new()))
+            .collect::<HashMap<_, _>>();
+        for paper in papers {
+            if let Some(folder_papers) = by_folder.get_mut(&paper.folder_id) {
+                folder_papers.push(paper);
+            }
+        }
+        Ok(by_folder)
+    }
+}
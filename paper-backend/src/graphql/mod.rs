@@ -0,0 +1,357 @@
+mod loader;
+
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, ID, Object, Result, Schema, dataloader::DataLoader,
+};
+use bson::doc;
+use futures::{StreamExt, TryStreamExt};
+
+use crate::{
+    app_data::AppDataRef,
+    model::{
+        folder::Folder,
+        paper::{Paper, PaperRepository},
+        user::User,
+    },
+};
+use loader::{FolderChildrenLoader, FolderLoader, FolderPapersLoader, PaperLoader, ROOT};
+
+// nesting of a query, deep enough for a folder tree of the default depth quota
+const MAX_QUERY_DEPTH: usize = 16;
+const MAX_QUERY_COMPLEXITY: usize = 2000;
+const PAPERS_DEFAULT_LIMIT: usize = 50;
+const PAPERS_MAX_LIMIT: usize = 200;
+
+pub type PaperSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub fn build_schema() -> PaperSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .limit_complexity(MAX_QUERY_COMPLEXITY)
+        .finish()
+}
+
+/// Run the query for the user, the loaders batch the reads of a response.
+pub async fn execute(
+    schema: &PaperSchema,
+    state: &AppDataRef,
+    user: User,
+    request: async_graphql::Request,
+) -> async_graphql::Response {
+    let user_id = user.uid.clone();
+    let request = request
+        .data(state.clone())
+        .data(DataLoader::new(
+            FolderLoader {
+                state: state.clone(),
+                user_id: user_id.clone(),
+            },
+            tokio::spawn,
+        ))
+        .data(DataLoader::new(
+            FolderChildrenLoader {
+                state: state.clone(),
+                user_id: user_id.clone(),
+            },
+            tokio::spawn,
+        ))
+        .data(DataLoader::new(
+            FolderPapersLoader {
+                state: state.clone(),
+                user_id: user_id.clone(),
+            },
+            tokio::spawn,
+        ))
+        .data(DataLoader::new(
+            PaperLoader {
+                state: state.clone(),
+                user_id,
+            },
+            tokio::spawn,
+        ))
+        .data(user);
+    schema.execute(request).await
+}
+
+fn user<'a>(ctx: &Context<'a>) -> &'a User {
+    ctx.data_unchecked::<User>()
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// The signed in user.
+    async fn me(&self, ctx: &Context<'_>) -> UserNode {
+        UserNode(user(ctx).clone())
+    }
+
+    /// The top level folders of the user, in their order.
+    async fn folders(&self, ctx: &Context<'_>) -> Result<Vec<FolderNode>> {
+        let loader = ctx.data_unchecked::<DataLoader<FolderChildrenLoader>>();
+        let folders = loader.load_one(ROOT.to_string()).await?;
+        Ok(folders
+            .unwrap_or_default()
+            .into_iter()
+            .map(FolderNode)
+            .collect())
+    }
+
+    async fn folder(&self, ctx: &Context<'_>, id: ID) -> Result<Option<FolderNode>> {
+        let loader = ctx.data_unchecked::<DataLoader<FolderLoader>>();
+        Ok(loader.load_one(id.to_string()).await?.map(FolderNode))
+    }
+
+    /// The papers of the user, newest first, optionally in one folder or with
+    /// one tag.
+    async fn papers(
+        &self,
+        ctx: &Context<'_>,
+        folder_id: Option<ID>,
+        tag: Option<String>,
+        first: Option<usize>,
+    ) -> Result<Vec<PaperNode>> {
+        let state = ctx.data_unchecked::<AppDataRef>();
+        let limit = first.unwrap_or(PAPERS_DEFAULT_LIMIT).min(PAPERS_MAX_LIMIT);
+        let filter = tag.map(|tag| doc! { "tags": tag });
+        let papers = state
+            .db
+            .find_papers(
+                &user(ctx).uid,
+                folder_id.as_ref().map(|id| id.as_str()),
+                filter,
+                None,
+            )
+            .await?
+            .take(limit)
+            .try_collect::<Vec<_>>()
+            .await?;
+        Ok(papers.into_iter().map(PaperNode).collect())
+    }
+
+    async fn paper(&self, ctx: &Context<'_>, id: ID) -> Result<Option<PaperNode>> {
+        let loader = ctx.data_unchecked::<DataLoader<PaperLoader>>();
+        Ok(loader.load_one(id.to_string()).await?.map(PaperNode))
+    }
+
+    /// The distinct tags of the papers of the user.
+    async fn tags(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
+        let state = ctx.data_unchecked::<AppDataRef>();
+        Ok(state.db.get_user_tags(&user(ctx).uid).await?)
+    }
+}
+
+pub struct UserNode(User);
+
+#[Object(name = "User")]
+impl UserNode {
+    async fn id(&self) -> ID {
+        ID(self.0.uid.clone())
+    }
+
+    async fn username(&self) -> Option<&str> {
+        self.0.username.as_deref()
+    }
+
+    async fn email(&self) -> Option<&str> {
+        self.0.email.as_deref()
+    }
+
+    async fn org_id(&self) -> Option<ID> {
+        self.0.org_id.clone().map(ID)
+    }
+
+    /// Timestamp in milliseconds
+    async fn created_at(&self) -> i64 {
+        self.0.created_at.timestamp_millis()
+    }
+}
+
+pub struct FolderNode(Folder);
+
+#[Object(name = "Folder")]
+impl FolderNode {
+    async fn id(&self) -> ID {
+        ID(self.0.id.clone())
+    }
+
+    async fn parent_id(&self) -> Option<ID> {
+        self.0.parent_id.clone().map(ID)
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.0.description.as_deref()
+    }
+
+    async fn color(&self) -> Option<&str> {
+        self.0.color.as_deref()
+    }
+
+    async fn icon(&self) -> Option<&str> {
+        self.0.icon.as_deref()
+    }
+
+    async fn archived(&self) -> bool {
+        self.0.archived
+    }
+
+    /// Whether the folder lists the papers of a search, holding none itself
+    async fn smart(&self) -> bool {
+        self.0.is_smart()
+    }
+
+    /// Timestamp in milliseconds
+    async fn created_at(&self) -> i64 {
+        self.0.created_at.timestamp_millis()
+    }
+
+    /// Timestamp in milliseconds
+    async fn updated_at(&self) -> i64 {
+        self.0.updated_at.timestamp_millis()
+    }
+
+    /// The subfolders, in their order.
+    async fn children(&self, ctx: &Context<'_>) -> Result<Vec<FolderNode>> {
+        let loader = ctx.data_unchecked::<DataLoader<FolderChildrenLoader>>();
+        let children = loader.load_one(self.0.id.clone()).await?;
+        Ok(children
+            .unwrap_or_default()
+            .into_iter()
+            .map(FolderNode)
+            .collect())
+    }
+
+    /// The papers directly in the folder, newest first.
+    async fn papers(&self, ctx: &Context<'_>) -> Result<Vec<PaperNode>> {
+        let loader = ctx.data_unchecked::<DataLoader<FolderPapersLoader>>();
+        let papers = loader.load_one(self.0.id.clone()).await?;
+        Ok(papers
+            .unwrap_or_default()
+            .into_iter()
+            .map(PaperNode)
+            .collect())
+    }
+
+    async fn paper_count(&self, ctx: &Context<'_>) -> Result<usize> {
+        let loader = ctx.data_unchecked::<DataLoader<FolderPapersLoader>>();
+        let papers = loader.load_one(self.0.id.clone()).await?;
+        Ok(papers.map_or(0, |papers| papers.len()))
+    }
+}
+
+pub struct PaperNode(Paper);
+
+#[Object(name = "Paper")]
+impl PaperNode {
+    async fn id(&self) -> ID {
+        ID(self.0.id.clone())
+    }
+
+    async fn folder_id(&self) -> ID {
+        ID(self.0.folder_id.clone())
+    }
+
+    async fn folder(&self, ctx: &Context<'_>) -> Result<Option<FolderNode>> {
+        let loader = ctx.data_unchecked::<DataLoader<FolderLoader>>();
+        Ok(loader
+            .load_one(self.0.folder_id.clone())
+            .await?
+            .map(FolderNode))
+    }
+
+    async fn title(&self) -> &str {
+        &self.0.title
+    }
+
+    async fn authors(&self) -> &[String] {
+        &self.0.authors
+    }
+
+    #[graphql(name = "abstract")]
+    async fn abstract_text(&self) -> Option<&str> {
+        self.0.r#abstract.as_deref()
+    }
+
+    async fn doi(&self) -> Option<&str> {
+        self.0.doi.as_deref()
+    }
+
+    async fn tags(&self) -> &[String] {
+        &self.0.tags
+    }
+
+    async fn starred(&self) -> bool {
+        self.0.starred
+    }
+
+    /// The markdown notes of the user
+    async fn notes(&self) -> Option<&str> {
+        self.0.content.as_deref()
+    }
+
+    /// The summary generated by the model
+    async fn summary(&self) -> Option<&str> {
+        self.0.summary.as_deref()
+    }
+
+    /// Timestamp in milliseconds
+    async fn created_at(&self) -> i64 {
+        self.0.created_at.timestamp_millis()
+    }
+
+    /// Timestamp in milliseconds
+    async fn updated_at(&self) -> i64 {
+        self.0.updated_at.timestamp_millis()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app_data::AppData,
+        model::{folder::FolderRepository, organization::FolderTemplate, txn::TxnContext},
+    };
+
+    fn template(name: &str) -> FolderTemplate {
+        FolderTemplate {
+            name: name.to_string(),
+            description: None,
+            children: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dashboard_query() {
+        let state = AppData::for_tests().await;
+        let user = User::new_by_email("a@example.com".to_string(), None, String::new());
+        let root = Folder::new_from_template(&user.uid, None, &template("Thesis"));
+        let child =
+            Folder::new_from_template(&user.uid, Some(root.id.clone()), &template("Drafts"));
+        for folder in [root.clone(), child.clone()] {
+            state
+                .db
+                .create_folder(&mut TxnContext::none(), folder)
+                .await
+                .unwrap();
+        }
+        let mut paper = Paper::new(&user.uid, &child.id, "BERT".to_string());
+        paper.tags = vec!["nlp".to_string()];
+        state.db.create_paper(paper).await.unwrap();
+
+        let query = "{ me { email } tags folders { name children { name paperCount papers { title folder { name } } } } }";
+        let response = execute(&build_schema(), &state, user, query.into()).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(data["me"]["email"], "a@example.com");
+        assert_eq!(data["tags"], serde_json::json!(["nlp"]));
+        let drafts = &data["folders"][0]["children"][0];
+        assert_eq!(drafts["name"], "Drafts");
+        assert_eq!(drafts["paperCount"], 1);
+        assert_eq!(drafts["papers"][0]["folder"]["name"], "Drafts");
+    }
+}
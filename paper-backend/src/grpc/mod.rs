@@ -0,0 +1,274 @@
+pub mod proto {
+    tonic::include_proto!("paper.v1");
+}
+
+use bson::doc;
+use futures::{StreamExt, stream::BoxStream};
+use salvo::http::StatusCode;
+use tonic::{Code, Request, Response, Status, transport::Server};
+
+use crate::{
+    app_data::AppDataRef,
+    authz::{Principal, Resource, assert_can_read},
+    config::GrpcConfig,
+    error::ServiceError,
+    model::{
+        consent::pending_consents,
+        folder::{Folder, FolderRepository},
+        paper::{Paper, PaperRepository},
+        user::User,
+    },
+    tenant::{TENANT_HEADER, Tenants},
+    utils::jwt::verify_access_token,
+};
+use proto::{
+    GetFolderRequest, GetPaperRequest, GetPapersRequest, GetPapersResponse, ListFoldersRequest,
+    ListFoldersResponse, ListPapersRequest,
+    folder_service_server::{FolderService, FolderServiceServer},
+    paper_service_server::{PaperService, PaperServiceServer},
+};
+
+/// Serve the folder and paper services of every tenant until the process stops.
+pub async fn serve(tenants: Tenants, config: GrpcConfig) {
+    let address = match config.address.parse() {
+        Ok(address) => address,
+        Err(e) => {
+            tracing::error!("Invalid grpc address {}: {}", config.address, e);
+            return;
+        }
+    };
+    tracing::info!("gRPC server listening on {}", address);
+    let result = Server::builder()
+        .add_service(FolderServiceServer::new(FolderApi {
+            tenants: tenants.clone(),
+        }))
+        .add_service(PaperServiceServer::new(PaperApi { tenants }))
+        .serve(address)
+        .await;
+    if let Err(e) = result {
+        tracing::error!("gRPC server failed: {}", e);
+    }
+}
+
+fn to_status(error: ServiceError) -> Status {
+    let code = match error.status_code() {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => Code::InvalidArgument,
+        StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+        StatusCode::FORBIDDEN => Code::PermissionDenied,
+        StatusCode::NOT_FOUND => Code::NotFound,
+        StatusCode::CONFLICT => Code::Aborted,
+        StatusCode::PRECONDITION_FAILED | StatusCode::PRECONDITION_REQUIRED => {
+            Code::FailedPrecondition
+        }
+        StatusCode::PAYLOAD_TOO_LARGE | StatusCode::TOO_MANY_REQUESTS => Code::ResourceExhausted,
+        StatusCode::SERVICE_UNAVAILABLE | StatusCode::BAD_GATEWAY => Code::Unavailable,
+        StatusCode::GATEWAY_TIMEOUT => Code::DeadlineExceeded,
+        _ => Code::Internal,
+    };
+    Status::new(code, error.message())
+}
+
+/// The tenant named by the `x-tenant` metadata, or else issuing the token, the
+/// default one otherwise, as [`Tenants`] resolves the http requests.
+fn resolve_tenant<'a, T>(
+    tenants: &'a Tenants,
+    request: &Request<T>,
+    token_tenant: Option<&str>,
+) -> Result<&'a AppDataRef, Status> {
+    let named = request
+        .metadata()
+        .get(TENANT_HEADER)
+        .map(|id| id.to_str().unwrap_or_default());
+    if let Some(id) = named {
+        return tenants
+            .get(id)
+            .ok_or_else(|| to_status(ServiceError::NotFound(format!("Tenant {}", id))));
+    }
+    Ok(token_tenant
+        .and_then(|id| tenants.get(id))
+        .unwrap_or(&tenants.all()[0]))
+}
+
+/// The tenant and the user of the access token in the `authorization`
+/// metadata, checked as the http api does.
+async fn authenticate<T>(
+    tenants: &Tenants,
+    request: &Request<T>,
+) -> Result<(AppDataRef, User), Status> {
+    let token = request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| Status::unauthenticated("JWT is not provided"))?;
+    let claims = verify_access_token(token).map_err(to_status)?;
+    let state = resolve_tenant(tenants, request, claims.tid.as_deref())?.clone();
+    claims.check_tenant(&state.tenant.id).map_err(to_status)?;
+    let user = state
+        .cached_user(&claims.sub)
+        .await
+        .map_err(to_status)?
+        .ok_or_else(|| Status::unauthenticated("User not found"))?;
+    if user.is_token_revoked(claims.iat) {
+        return Err(Status::unauthenticated("JWT is revoked"));
+    }
+    let pending = pending_consents(&user, &state.legal_config);
+    if !pending.is_empty() {
+        let message = format!("Accept the current {} first", pending.join(", "));
+        return Err(to_status(ServiceError::ConsentRequired(message)));
+    }
+    Ok((state, user))
+}
+
+impl From<Folder> for proto::Folder {
+    fn from(folder: Folder) -> Self {
+        proto::Folder {
+            id: folder.id,
+            parent_id: folder.parent_id,
+            name: folder.name,
+            description: folder.description,
+            color: folder.color,
+            icon: folder.icon,
+            archived: folder.archived,
+            created_at: folder.created_at.timestamp_millis(),
+            updated_at: folder.updated_at.timestamp_millis(),
+            version: folder.version,
+        }
+    }
+}
+
+impl From<Paper> for proto::Paper {
+    fn from(paper: Paper) -> Self {
+        proto::Paper {
+            id: paper.id,
+            folder_id: paper.folder_id,
+            title: paper.title,
+            authors: paper.authors,
+            r#abstract: paper.r#abstract,
+            doi: paper.doi,
+            tags: paper.tags,
+            content: paper.content,
+            summary: paper.summary,
+            starred: paper.starred,
+            created_at: paper.created_at.timestamp_millis(),
+            updated_at: paper.updated_at.timestamp_millis(),
+            version: paper.version,
+        }
+    }
+}
+
+struct FolderApi {
+    tenants: Tenants,
+}
+
+#[tonic::async_trait]
+impl FolderService for FolderApi {
+    async fn list_folders(
+        &self,
+        request: Request<ListFoldersRequest>,
+    ) -> Result<Response<ListFoldersResponse>, Status> {
+        let (state, user) = authenticate(&self.tenants, &request).await?;
+        let folders = state.cached_folders(&user.uid).await.map_err(to_status)?;
+        Ok(Response::new(ListFoldersResponse {
+            folders: folders.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn get_folder(
+        &self,
+        request: Request<GetFolderRequest>,
+    ) -> Result<Response<proto::Folder>, Status> {
+        let (state, user) = authenticate(&self.tenants, &request).await?;
+        let id = request.into_inner().id;
+        let folder = state
+            .db
+            .get_folder_by_id(&id)
+            .await
+            .map_err(to_status)?
+            .ok_or_else(|| to_status(ServiceError::FolderNotFound(id)))?;
+        let principal = Principal::of(&state, &user);
+        assert_can_read(Resource::Folder(&folder), principal).map_err(to_status)?;
+        Ok(Response::new(folder.into()))
+    }
+}
+
+struct PaperApi {
+    tenants: Tenants,
+}
+
+#[tonic::async_trait]
+impl PaperService for PaperApi {
+    type ListPapersStream = BoxStream<'static, Result<proto::Paper, Status>>;
+
+    async fn list_papers(
+        &self,
+        request: Request<ListPapersRequest>,
+    ) -> Result<Response<Self::ListPapersStream>, Status> {
+        let (state, user) = authenticate(&self.tenants, &request).await?;
+        let request = request.into_inner();
+        let filter = request.tag.map(|tag| doc! { "tags": tag });
+        let papers = state
+            .db
+            .find_papers(&user.uid, request.folder_id.as_deref(), filter, None)
+            .await
+            .map_err(to_status)?;
+        let limit = match request.limit {
+            0 => usize::MAX,
+            limit => limit as usize,
+        };
+        let papers = papers
+            .take(limit)
+            .map(|paper| paper.map(Into::into).map_err(to_status));
+        Ok(Response::new(papers.boxed()))
+    }
+
+    async fn get_paper(
+        &self,
+        request: Request<GetPaperRequest>,
+    ) -> Result<Response<proto::Paper>, Status> {
+        let (state, user) = authenticate(&self.tenants, &request).await?;
+        let id = request.into_inner().id;
+        let paper = state
+            .cached_paper(&id)
+            .await
+            .map_err(to_status)?
+            .ok_or_else(|| to_status(ServiceError::PaperNotFound(id)))?;
+        let principal = Principal::of(&state, &user);
+        assert_can_read(Resource::Paper(&paper), principal).map_err(to_status)?;
+        Ok(Response::new(paper.into()))
+    }
+
+    async fn get_papers(
+        &self,
+        request: Request<GetPapersRequest>,
+    ) -> Result<Response<GetPapersResponse>, Status> {
+        let (state, user) = authenticate(&self.tenants, &request).await?;
+        let ids = request.into_inner().ids;
+        let papers = state
+            .db
+            .get_papers_by_ids(&user.uid, &ids)
+            .await
+            .map_err(to_status)?;
+        Ok(Response::new(GetPapersResponse {
+            papers: papers.into_iter().map(Into::into).collect(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_status() {
+        let status = to_status(ServiceError::PaperNotFound("p1".to_string()));
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(status.message(), "Paper p1 not found");
+        let status = to_status(ServiceError::Unauthorized("JWT is revoked".to_string()));
+        assert_eq!(status.code(), Code::Unauthenticated);
+        let status = to_status(ServiceError::VersionConflict("stale".to_string()));
+        assert_eq!(status.code(), Code::Aborted);
+        let status = to_status(ServiceError::InternalServerError("boom".to_string()));
+        assert_eq!(status.code(), Code::Internal);
+    }
+}
@@ -0,0 +1,51 @@
+use super::*;
+
+pub(super) const MESSAGES: &[(&str, &str)] = &[
+    (DEFAULT_FOLDER_NAME, "Default"),
+    (DEFAULT_FOLDER_DESCRIPTION, "System-defined folder."),
+    (STARRED_FOLDER_NAME, "Starred"),
+    (STARRED_FOLDER_DESCRIPTION, "Starred papers."),
+    (GREETING_NAME, "there"),
+    (VERIFY_EMAIL_SUBJECT, "Verify your email"),
+    (
+        VERIFY_EMAIL_BODY,
+        "Welcome to Paper!\n\nPlease open the link below to activate your account, \
+        it expires in 24 hours:\n\n{{link}}\n",
+    ),
+    (RESET_PASSWORD_SUBJECT, "Reset your password"),
+    (
+        RESET_PASSWORD_BODY,
+        "A password reset was requested for your account.\n\n\
+        Use the token below within 30 minutes to set a new password, \
+        or ignore this email if it wasn't you:\n\n{{token}}\n",
+    ),
+    (
+        DELETE_ACCOUNT_SUBJECT,
+        "Confirm the deletion of your account",
+    ),
+    (
+        DELETE_ACCOUNT_BODY,
+        "The deletion of your account and all its data was requested.\n\n\
+        Use the token below within 15 minutes to confirm it, \
+        or ignore this email if it wasn't you:\n\n{{token}}\n",
+    ),
+    (LOGIN_LOCKED_SUBJECT, "Your account was locked"),
+    (
+        LOGIN_LOCKED_BODY,
+        "Too many failed logins to your account, the last one from {{ip}}, \
+        it is locked for {{minutes}} minutes.\n\n\
+        If it wasn't you, reset your password once it is unlocked, \
+        or contact the support to unlock it sooner.\n",
+    ),
+    (SUMMARY_READY_SUBJECT, "Summary ready: {{title}}"),
+    (
+        SUMMARY_READY_BODY,
+        include_str!("../templates/en/summary_ready.txt"),
+    ),
+    (WEEKLY_DIGEST_SUBJECT, "Your week on Paper"),
+    (
+        WEEKLY_DIGEST_BODY,
+        include_str!("../templates/en/weekly_digest.txt"),
+    ),
+    (WEEKLY_DIGEST_MORE, "  and {{count}} more\n"),
+];
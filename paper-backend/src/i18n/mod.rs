@@ -0,0 +1,224 @@
+use salvo::{
+    Depot, Request, Response, handler,
+    http::header::{ACCEPT_LANGUAGE, CONTENT_LANGUAGE, HeaderValue, VARY},
+};
+
+use crate::{app_data::AppDataRef, config::SettingsConfig, error::ServiceError, model::user::User};
+
+mod en;
+mod zh_cn;
+
+pub const DEFAULT_FOLDER_NAME: &str = "folder.default.name";
+pub const DEFAULT_FOLDER_DESCRIPTION: &str = "folder.default.description";
+pub const STARRED_FOLDER_NAME: &str = "folder.starred.name";
+pub const STARRED_FOLDER_DESCRIPTION: &str = "folder.starred.description";
+// the name greeting the users without one in the emails
+pub const GREETING_NAME: &str = "email.greeting_name";
+pub const VERIFY_EMAIL_SUBJECT: &str = "email.verify.subject";
+pub const VERIFY_EMAIL_BODY: &str = "email.verify.body";
+pub const RESET_PASSWORD_SUBJECT: &str = "email.reset_password.subject";
+pub const RESET_PASSWORD_BODY: &str = "email.reset_password.body";
+pub const DELETE_ACCOUNT_SUBJECT: &str = "email.delete_account.subject";
+pub const DELETE_ACCOUNT_BODY: &str = "email.delete_account.body";
+pub const LOGIN_LOCKED_SUBJECT: &str = "email.login_locked.subject";
+pub const LOGIN_LOCKED_BODY: &str = "email.login_locked.body";
+pub const SUMMARY_READY_SUBJECT: &str = "email.summary_ready.subject";
+pub const SUMMARY_READY_BODY: &str = "email.summary_ready.body";
+pub const WEEKLY_DIGEST_SUBJECT: &str = "email.weekly_digest.subject";
+pub const WEEKLY_DIGEST_BODY: &str = "email.weekly_digest.body";
+// the new papers of the digest left out of the list
+pub const WEEKLY_DIGEST_MORE: &str = "email.weekly_digest.more";
+
+/// A language the messages of the server are written in. English is the
+/// language of the code, the catalogs of the others translate the messages by
+/// key, the errors and the rejected fields by code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    ZhCn,
+}
+
+impl Locale {
+    pub const ALL: &'static [Locale] = &[Locale::En, Locale::ZhCn];
+
+    /// The language tag, as stored in the settings of the users.
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::ZhCn => "zh-CN",
+        }
+    }
+
+    /// Name of the language, as the prompts ask for it.
+    pub fn english_name(self) -> &'static str {
+        match self {
+            Locale::En => "English",
+            Locale::ZhCn => "Simplified Chinese",
+        }
+    }
+
+    /// The locale of a language tag by its primary language, e.g. `zh-Hans-CN`.
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let primary = tag.split(['-', '_']).next().unwrap_or_default();
+        match primary.to_ascii_lowercase().as_str() {
+            "en" => Some(Locale::En),
+            "zh" => Some(Locale::ZhCn),
+            _ => None,
+        }
+    }
+
+    /// The supported locale the `Accept-Language` header prefers, by weight
+    /// then by order.
+    pub fn from_accept_language(header: &str) -> Option<Locale> {
+        let mut tags = header
+            .split(',')
+            .filter_map(|tag| {
+                let mut parts = tag.split(';');
+                let locale = Locale::from_tag(parts.next()?.trim())?;
+                let weight = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                Some((locale, weight))
+            })
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect::<Vec<_>>();
+        // stable, the tags of the same weight keep their order
+        tags.sort_by(|a, b| b.1.total_cmp(&a.1));
+        tags.first().map(|(locale, _)| *locale)
+    }
+
+    /// The language the user chose, else the fallback, e.g. the one of the
+    /// request or the default of the server.
+    pub fn of_user(user: &User, fallback: Locale) -> Locale {
+        user.settings
+            .language
+            .as_deref()
+            .and_then(Locale::from_tag)
+            .unwrap_or(fallback)
+    }
+
+    /// The locale of the messages of the response, English before it is known.
+    pub fn of_response(res: &Response) -> Locale {
+        res.headers()
+            .get(CONTENT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Locale::from_tag)
+            .unwrap_or_default()
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::En => en::MESSAGES,
+            Locale::ZhCn => zh_cn::MESSAGES,
+        }
+    }
+
+    /// The message of the key, in English when the catalog misses it.
+    pub fn text(self, key: &'static str) -> &'static str {
+        let find = |catalog: &'static [(&'static str, &'static str)]| {
+            catalog
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, message)| *message)
+        };
+        find(self.catalog())
+            .or_else(|| find(en::MESSAGES))
+            .unwrap_or(key)
+    }
+
+    /// The message of the key, its `{{name}}` placeholders filled with the values.
+    pub fn render(self, key: &'static str, values: &[(&str, &str)]) -> String {
+        crate::utils::template::render_template(self.text(key), values)
+    }
+
+    /// The translated message of the error, none for English and for the
+    /// free text messages of the routes, which are answered as they are.
+    pub fn error_message(self, err: &ServiceError) -> Option<String> {
+        match self {
+            Locale::En => None,
+            Locale::ZhCn => zh_cn::error_message(err),
+        }
+    }
+
+    /// The translated message of a field rejected with the code.
+    pub fn field_message(self, code: &str, field: &str) -> Option<String> {
+        match self {
+            Locale::En => None,
+            Locale::ZhCn => Some(zh_cn::field_message(code).replace("{{field}}", field)),
+        }
+    }
+}
+
+/// The default locale of the server, of the users who chose none.
+pub fn default_locale(config: &SettingsConfig) -> Locale {
+    Locale::from_tag(&config.language).unwrap_or_default()
+}
+
+/// The locale of the request, see `negotiate_locale`.
+pub fn request_locale(depot: &Depot) -> Locale {
+    depot.obtain::<Locale>().copied().unwrap_or_default()
+}
+
+/// Inject the locale into the depot and name it on the response, the errors
+/// are rendered in it.
+pub fn set_locale(depot: &mut Depot, res: &mut Response, locale: Locale) {
+    depot.inject(locale);
+    res.headers_mut()
+        .insert(CONTENT_LANGUAGE, HeaderValue::from_static(locale.code()));
+}
+
+/// Hoop picking the locale of the request from its `Accept-Language` header,
+/// else the default of the server. The language the user chose in their
+/// settings replaces it once they are authenticated.
+#[handler]
+pub async fn negotiate_locale(req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    let default = depot
+        .obtain::<AppDataRef>()
+        .map(|state| default_locale(&state.settings_config))
+        .unwrap_or_default();
+    let locale = req
+        .header::<String>(ACCEPT_LANGUAGE)
+        .and_then(|header| Locale::from_accept_language(&header))
+        .unwrap_or(default);
+    set_locale(depot, res, locale);
+    res.headers_mut()
+        .append(VARY, HeaderValue::from_static("accept-language"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_language() {
+        assert_eq!(Locale::from_tag("zh-Hans-CN"), Some(Locale::ZhCn));
+        assert_eq!(Locale::from_tag("EN_us"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("fr"), None);
+
+        let locale = Locale::from_accept_language("fr-FR, zh-CN;q=0.8, en;q=0.9");
+        assert_eq!(locale, Some(Locale::En));
+        let locale = Locale::from_accept_language("zh;q=0.5, en;q=0");
+        assert_eq!(locale, Some(Locale::ZhCn));
+        assert_eq!(Locale::from_accept_language("fr, de;q=0.9"), None);
+        assert_eq!(Locale::from_accept_language("*"), None);
+    }
+
+    #[test]
+    fn test_catalogs() {
+        for (key, _) in en::MESSAGES {
+            assert!(
+                zh_cn::MESSAGES.iter().any(|(k, _)| k == key),
+                "{} is not translated",
+                key
+            );
+        }
+        assert_eq!(Locale::ZhCn.text(DEFAULT_FOLDER_NAME), "默认");
+        assert_eq!(Locale::En.text(DEFAULT_FOLDER_NAME), "Default");
+        assert_eq!(
+            Locale::ZhCn.field_message("length", "name").as_deref(),
+            Some("name 的长度不符合要求")
+        );
+        assert_eq!(Locale::En.field_message("length", "name"), None);
+    }
+}
@@ -0,0 +1,105 @@
+use super::*;
+use crate::error::is_db_outage;
+
+pub(super) const MESSAGES: &[(&str, &str)] = &[
+    (DEFAULT_FOLDER_NAME, "默认"),
+    (DEFAULT_FOLDER_DESCRIPTION, "系统文件夹。"),
+    (STARRED_FOLDER_NAME, "收藏"),
+    (STARRED_FOLDER_DESCRIPTION, "收藏的论文。"),
+    (GREETING_NAME, "用户"),
+    (VERIFY_EMAIL_SUBJECT, "验证你的邮箱"),
+    (
+        VERIFY_EMAIL_BODY,
+        "欢迎使用 Paper！\n\n请打开下面的链接激活你的账号，链接 24 小时内有效：\n\n{{link}}\n",
+    ),
+    (RESET_PASSWORD_SUBJECT, "重置你的密码"),
+    (
+        RESET_PASSWORD_BODY,
+        "你的账号申请了重置密码。\n\n\
+        请在 30 分钟内使用下面的令牌设置新密码，如果不是你本人操作，请忽略这封邮件：\n\n{{token}}\n",
+    ),
+    (DELETE_ACCOUNT_SUBJECT, "确认删除你的账号"),
+    (
+        DELETE_ACCOUNT_BODY,
+        "你的账号申请了删除账号及其全部数据。\n\n\
+        请在 15 分钟内使用下面的令牌确认删除，如果不是你本人操作，请忽略这封邮件：\n\n{{token}}\n",
+    ),
+    (LOGIN_LOCKED_SUBJECT, "你的账号已被锁定"),
+    (
+        LOGIN_LOCKED_BODY,
+        "你的账号登录失败次数过多，最近一次来自 {{ip}}，账号已锁定 {{minutes}} 分钟。\n\n\
+        如果不是你本人操作，请在解锁后重置密码，或联系客服提前解锁。\n",
+    ),
+    (SUMMARY_READY_SUBJECT, "摘要已生成：{{title}}"),
+    (
+        SUMMARY_READY_BODY,
+        include_str!("../templates/zh-CN/summary_ready.txt"),
+    ),
+    (WEEKLY_DIGEST_SUBJECT, "你在 Paper 的一周"),
+    (
+        WEEKLY_DIGEST_BODY,
+        include_str!("../templates/zh-CN/weekly_digest.txt"),
+    ),
+    (WEEKLY_DIGEST_MORE, "  以及另外 {{count}} 篇\n"),
+];
+
+pub(super) fn error_message(err: &ServiceError) -> Option<String> {
+    let message = match err {
+        ServiceError::Unauthorized(_) | ServiceError::JwtError(_) => {
+            "未登录或登录已失效".to_string()
+        }
+        ServiceError::ConsentRequired(_) => "请先同意最新的用户协议和隐私政策".to_string(),
+        ServiceError::CsrfRejected(_) => "缺少或无效的 CSRF 令牌".to_string(),
+        ServiceError::NotFound(_) => "资源不存在".to_string(),
+        ServiceError::FolderNotFound(id) => format!("文件夹 {} 不存在", id),
+        ServiceError::PaperNotFound(id) => format!("论文 {} 不存在", id),
+        ServiceError::PayloadTooLarge(limit) => format!("请求体超过了 {} 字节的上限", limit),
+        ServiceError::RateLimited(secs) => format!("请求过于频繁，请在 {} 秒后重试", secs),
+        ServiceError::LoginLocked(secs) => {
+            format!("登录失败次数过多，已锁定 {} 秒", secs)
+        }
+        ServiceError::QuotaExceeded { used, quota, .. } => {
+            format!("本月的 AI 额度已用完，已使用 {} / {} tokens", used, quota)
+        }
+        ServiceError::ResourceQuotaExceeded { .. } => "超出了资源配额".to_string(),
+        ServiceError::Validation(_) => "校验失败".to_string(),
+        ServiceError::MongoClientError(err) if is_db_outage(err) => {
+            "数据库暂时不可用，请稍后重试".to_string()
+        }
+        ServiceError::InternalServerError(_)
+        | ServiceError::MongoClientError(_)
+        | ServiceError::BsonDeError(_)
+        | ServiceError::BsonSerError(_)
+        | ServiceError::IoError(_) => "服务器内部错误".to_string(),
+        ServiceError::MailError(_) => "邮件发送失败".to_string(),
+        ServiceError::LLMError(_) => "AI 服务调用失败".to_string(),
+        ServiceError::EmbeddingError(_) => "向量服务调用失败".to_string(),
+        ServiceError::Timeout(secs) => format!("请求未能在 {} 秒内完成", secs),
+        ServiceError::CircuitOpen {
+            service,
+            retry_after,
+        } => format!("{} 暂时不可用，请在 {} 秒后重试", service, retry_after),
+        ServiceError::RestoreRunning => "正在恢复备份，请在恢复完成后重试".to_string(),
+        _ => return None,
+    };
+    Some(message)
+}
+
+pub(super) fn field_message(code: &str) -> &'static str {
+    match code {
+        "length" => "{{field}} 的长度不符合要求",
+        "range" => "{{field}} 超出了允许的范围",
+        "required" => "{{field}} 不能为空",
+        "email" => "{{field}} 不是有效的邮箱地址",
+        "url" => "{{field}} 不是有效的链接",
+        "must_match" => "{{field}} 不一致",
+        "color" => "{{field}} 须为 #rrggbb 格式的颜色",
+        "tag_length" => "{{field}} 中的标签过长",
+        "language" => "{{field}} 不是支持的语言",
+        "timezone" => "{{field}} 不是有效的时区",
+        "unsupported" => "{{field}} 不受支持",
+        "not_found" => "{{field}} 不存在",
+        "duplicate" => "{{field}} 已存在",
+        _ => "{{field}} 无效",
+    }
+}
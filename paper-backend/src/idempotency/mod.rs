@@ -0,0 +1,296 @@
+use salvo::{
+    Depot, FlowCtrl, Request, Response,
+    http::{
+        Method, ResBody, StatusCode,
+        header::{CONTENT_TYPE, HeaderValue},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult},
+    model::{
+        content::content_hash,
+        idempotency::{IdempotencyRecord, IdempotencyRepository},
+        user::User,
+    },
+    utils::crypto::{open_with_secret, seal_with_secret},
+};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+const MAX_KEY_CHARS: usize = 255;
+// larger responses are not kept, their retries run again
+const MAX_STORED_BODY_BYTES: usize = 1024 * 1024;
+// a first request still running after this is taken as lost
+const IN_FLIGHT_TIMEOUT_MS: i64 = 10 * 60 * 1000;
+
+/// Claim the key, taking over from a first request lost before completing.
+async fn claim(
+    state: &AppDataRef,
+    record: IdempotencyRecord,
+) -> ServiceResult<Option<IdempotencyRecord>> {
+    let existing = state.db.claim_idempotency_key(record.clone()).await?;
+    match existing {
+        Some(existing)
+            if existing.status.is_none()
+                && record.created_at.timestamp_millis()
+                    - existing.created_at.timestamp_millis()
+                    > IN_FLIGHT_TIMEOUT_MS =>
+        {
+            state.db.release_idempotency_key(&existing.id).await?;
+            state.db.claim_idempotency_key(record).await
+        }
+        existing => Ok(existing),
+    }
+}
+
+/// Sha256 of the method, the path with its query and the body of the request.
+fn fingerprint(req: &Request, body: &[u8]) -> String {
+    let path = req
+        .uri()
+        .path_and_query()
+        .map_or(req.uri().path(), |path| path.as_str());
+    content_hash(
+        &[
+            req.method().as_str().as_bytes(),
+            b"\n",
+            path.as_bytes(),
+            b"\n",
+            body,
+        ]
+        .concat(),
+    )
+}
+
+/// Answer with the stored response of the first request sent with the key,
+/// opened with the key.
+fn replay(
+    existing: IdempotencyRecord,
+    fingerprint: &str,
+    secret: &str,
+    res: &mut Response,
+) -> ServiceResult<()> {
+    if existing.fingerprint != fingerprint {
+        return Err(ServiceError::invalid_field(
+            "Idempotency-Key",
+            "reused",
+            "The key was already used for another request",
+        ));
+    }
+    let Some(status) = existing.status else {
+        return Err(ServiceError::VersionConflict(
+            "A request with the same idempotency key is still running".to_string(),
+        ));
+    };
+    let body = match &existing.body {
+        Some(body) => open_with_secret(secret, body)?,
+        None => Vec::new(),
+    };
+    res.status_code(StatusCode::from_u16(status).unwrap_or(StatusCode::OK));
+    if let Some(content_type) = existing
+        .content_type
+        .and_then(|content_type| HeaderValue::from_str(&content_type).ok())
+    {
+        res.headers_mut().insert(CONTENT_TYPE, content_type);
+    }
+    res.headers_mut()
+        .insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+    res.replace_body(ResBody::Once(body.into()));
+    Ok(())
+}
+
+/// Store the response for the retries, sealed under the key as it may hold
+/// tokens, or release the key when the request failed or the response cannot
+/// be kept, so a retry runs it again.
+async fn complete(state: &AppDataRef, id: &str, secret: &str, res: &Response) {
+    let status = res.status_code.unwrap_or(StatusCode::OK);
+    let body = match &res.body {
+        ResBody::Once(body) if status.is_success() && body.len() <= MAX_STORED_BODY_BYTES => {
+            Some(body.as_ref())
+        }
+        ResBody::None if status.is_success() => Some([].as_slice()),
+        _ => None,
+    };
+    let result = match body.map(|body| seal_with_secret(secret, body)) {
+        Some(Ok(body)) => {
+            let content_type = res
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            state
+                .db
+                .complete_idempotency_key(id, status.as_u16(), content_type, body)
+                .await
+        }
+        Some(Err(e)) => {
+            tracing::error!(
+                "Failed to seal the response of idempotency key {}: {}",
+                id,
+                e
+            );
+            state.db.release_idempotency_key(id).await
+        }
+        None => state.db.release_idempotency_key(id).await,
+    };
+    if let Err(e) = result {
+        tracing::error!(
+            "Failed to store the response of idempotency key {}: {}",
+            id,
+            e
+        );
+    }
+}
+
+/// Run a POST sent with an `Idempotency-Key` header once per key and user: the
+/// retries get the stored response of the first request for 24 hours, so they
+/// neither create duplicates nor call the llm again. Reusing the key for
+/// another request, even with another body, is refused.
+#[salvo::handler]
+pub async fn idempotent(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+    ctrl: &mut FlowCtrl,
+) {
+    if req.method() != Method::POST {
+        return;
+    }
+    let Some(key) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return;
+    };
+    let key = key.to_str().unwrap_or_default().trim().to_string();
+    if key.is_empty() || key.chars().count() > MAX_KEY_CHARS {
+        res.render(ServiceError::invalid_field(
+            "Idempotency-Key",
+            "length",
+            format!("The key must have 1 to {} characters", MAX_KEY_CHARS),
+        ));
+        ctrl.skip_rest();
+        return;
+    }
+    let (state, user_id) = match (depot.obtain::<AppDataRef>(), depot.obtain::<User>()) {
+        (Ok(state), Ok(user)) => (state.clone(), user.uid.clone()),
+        _ => return,
+    };
+
+    // cached by the request, the handlers read it again
+    let body = match req.payload().await {
+        Ok(body) => body.clone(),
+        Err(e) => {
+            res.render(ServiceError::BadRequest(format!("Invalid body: {}", e)));
+            ctrl.skip_rest();
+            return;
+        }
+    };
+    let fingerprint = fingerprint(req, &body);
+    let secret = format!("{}:{}", user_id, key);
+
+    let record = IdempotencyRecord::new(
+        &user_id,
+        &key,
+        req.method().as_str(),
+        req.uri().path(),
+        fingerprint.clone(),
+    );
+    let id = record.id.clone();
+    let claimed = match claim(&state, record).await {
+        Ok(None) => true,
+        Ok(Some(existing)) => {
+            if let Err(e) = replay(existing, &fingerprint, &secret, res) {
+                res.render(e);
+            }
+            false
+        }
+        Err(e) => {
+            res.render(e);
+            false
+        }
+    };
+    if !claimed {
+        ctrl.skip_rest();
+        return;
+    }
+
+    ctrl.call_next(req, depot, res).await;
+    complete(&state, &id, &secret, res).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use salvo::{
+        Router, Service, affix_state,
+        test::{ResponseExt, TestClient},
+        writing::Text,
+    };
+
+    use super::*;
+    use crate::app_data::AppData;
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    #[salvo::handler]
+    async fn create(req: &mut Request, res: &mut Response) {
+        let calls = CALLS.fetch_add(1, Ordering::SeqCst) + 1;
+        let body = req.payload().await.unwrap().clone();
+        res.status_code(StatusCode::CREATED);
+        res.render(Text::Json(format!(
+            "{{\"token\":\"secret-{}\",\"echo\":{}}}",
+            calls,
+            String::from_utf8_lossy(&body)
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_replay() {
+        let state = AppData::for_tests().await;
+        let user = User::new_by_email("reader@example.com".to_string(), None, String::new());
+        let router = Router::new()
+            .hoop(affix_state::inject(state.clone()).inject(user.clone()))
+            .hoop(idempotent)
+            .push(Router::with_path("papers").post(create));
+        let service = Service::new(router);
+        let send = |key: &'static str, title: &'static str| {
+            TestClient::post("http://127.0.0.1/papers")
+                .add_header(IDEMPOTENCY_KEY_HEADER, key, true)
+                .json(&serde_json::json!({ "title": title }))
+                .send(&service)
+        };
+
+        let mut resp = send("retry-key-1", "a").await;
+        assert_eq!(resp.status_code, Some(StatusCode::CREATED));
+        let first = resp.take_string().await.unwrap();
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+        // the retry gets the stored response without running again
+        let mut resp = send("retry-key-1", "a").await;
+        assert_eq!(resp.status_code, Some(StatusCode::CREATED));
+        assert!(resp.headers().contains_key(REPLAYED_HEADER));
+        assert_eq!(resp.take_string().await.unwrap(), first);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+        // the key cannot be reused with another body
+        let resp = send("retry-key-1", "b").await;
+        assert_eq!(resp.status_code, Some(StatusCode::UNPROCESSABLE_ENTITY));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+        let resp = send("retry-key-2", "b").await;
+        assert_eq!(resp.status_code, Some(StatusCode::CREATED));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+
+        // neither the key nor the response are stored in the clear
+        let stored =
+            IdempotencyRecord::new(&user.uid, "retry-key-1", "POST", "/papers", String::new());
+        let stored = state
+            .db
+            .claim_idempotency_key(stored)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!stored.id.contains("retry-key-1"));
+        assert!(!stored.body.unwrap().contains("secret-1"));
+    }
+}
@@ -0,0 +1,36 @@
+pub mod app_data;
+pub mod authz;
+pub mod backup;
+pub mod citation;
+pub mod classify;
+pub mod collab;
+pub mod comparison;
+pub mod config;
+pub mod dedup;
+pub mod digest;
+pub mod embedding;
+pub mod error;
+pub mod events;
+pub mod export;
+pub mod graphql;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod i18n;
+pub mod idempotency;
+pub mod llm;
+pub mod math;
+pub mod migrations;
+pub mod model;
+pub mod pdf;
+pub mod qa;
+pub mod rate_limit;
+pub mod reload;
+pub mod resilience;
+pub mod router;
+pub mod scan;
+pub mod search;
+pub mod seed;
+pub mod tenant;
+pub mod timed_task;
+pub mod tls;
+pub mod utils;
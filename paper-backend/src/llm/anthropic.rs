@@ -0,0 +1,125 @@
+use ai_flow_synth::llm::model::{ChatMessage, ChatMessageRole};
+use futures::{TryStreamExt, future::ready};
+use serde::Deserialize;
+
+use crate::{
+    error::{ServiceError, ServiceResult},
+    llm::{
+        LlmProvider, LlmStream,
+        stream::{response_lines, sse_data},
+    },
+};
+
+const API_VERSION: &str = "2023-06-01";
+// the messages api requires a bound on the answer
+const MAX_OUTPUT_TOKENS: u32 = 4096;
+
+/// Anthropic `/v1/messages` api.
+pub struct AnthropicProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl std::fmt::Debug for AnthropicProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnthropicProvider")
+            .field("base_url", &self.base_url)
+            .finish()
+    }
+}
+
+impl AnthropicProvider {
+    pub fn new(client: reqwest::Client, base_url: String, api_key: String) -> Self {
+        AnthropicProvider {
+            client,
+            base_url,
+            api_key,
+        }
+    }
+}
+
+/// The system prompt goes apart, the other messages alternate user and assistant.
+fn request_body(model: &str, messages: &[ChatMessage]) -> serde_json::Value {
+    let system = messages
+        .iter()
+        .filter(|m| matches!(m.role, ChatMessageRole::System))
+        .map(|m| m.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let messages = messages
+        .iter()
+        .filter_map(|m| match m.role {
+            ChatMessageRole::System => None,
+            ChatMessageRole::Assistant => Some(("assistant", &m.content)),
+            ChatMessageRole::User | ChatMessageRole::Tool => Some(("user", &m.content)),
+        })
+        .map(|(role, content)| serde_json::json!({ "role": role, "content": content }))
+        .collect::<Vec<_>>();
+    serde_json::json!({
+        "model": model,
+        "system": system,
+        "messages": messages,
+        "max_tokens": MAX_OUTPUT_TOKENS,
+        "stream": true,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    ContentBlockDelta {
+        delta: Delta,
+    },
+    Error {
+        error: ApiError,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct Delta {
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    message: String,
+}
+
+fn event_content(data: &str) -> ServiceResult<Option<String>> {
+    match serde_json::from_str::<StreamEvent>(data) {
+        Ok(StreamEvent::ContentBlockDelta { delta }) => Ok(delta.text),
+        Ok(StreamEvent::Error { error }) => Err(ServiceError::LLMError(error.message)),
+        Ok(StreamEvent::Other) | Err(_) => Ok(None),
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmProvider for AnthropicProvider {
+    async fn chat_stream(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        api_key: Option<&str>,
+    ) -> ServiceResult<LlmStream> {
+        let response = self
+            .client
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", api_key.unwrap_or(&self.api_key))
+            .header("anthropic-version", API_VERSION)
+            .json(&request_body(model, messages))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ServiceError::LLMError(e.to_string()))?;
+        let stream = response_lines(response).try_filter_map(|line| {
+            ready(match sse_data(&line) {
+                Some(data) => event_content(data),
+                None => Ok(None),
+            })
+        });
+        Ok(Box::pin(stream))
+    }
+}
@@ -0,0 +1,41 @@
+use ai_flow_synth::llm::model::{ChatMessage, ChatMessageRole};
+
+use crate::{
+    error::ServiceResult,
+    llm::{LlmProvider, LlmStream},
+};
+
+// characters of the question echoed in the answer
+const ECHO_MAX_CHARS: usize = 200;
+
+/// Answers without any model, echoing the last user message, for development
+/// and tests.
+#[derive(Debug)]
+pub struct MockProvider;
+
+pub fn mock_answer(model: &str, messages: &[ChatMessage]) -> String {
+    let question = messages
+        .iter()
+        .rev()
+        .find(|m| matches!(m.role, ChatMessageRole::User))
+        .map(|m| m.content.chars().take(ECHO_MAX_CHARS).collect::<String>())
+        .unwrap_or_default();
+    format!("[{}] {}", model, question)
+}
+
+#[async_trait::async_trait]
+impl LlmProvider for MockProvider {
+    async fn chat_stream(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        _api_key: Option<&str>,
+    ) -> ServiceResult<LlmStream> {
+        // streamed word by word, like a real model
+        let deltas = mock_answer(model, messages)
+            .split_inclusive(' ')
+            .map(|word| Ok(word.to_string()))
+            .collect::<Vec<_>>();
+        Ok(Box::pin(futures::stream::iter(deltas)))
+    }
+}
@@ -0,0 +1,316 @@
+pub mod anthropic;
+pub mod mock;
+pub mod ollama;
+pub mod openai;
+pub mod prompt;
+pub mod stream;
+
+use std::{pin::Pin, sync::Arc, time::Duration};
+
+use ai_flow_synth::llm::model::ChatMessage;
+use futures::{Stream, StreamExt};
+
+use crate::{
+    config::{CircuitBreakerConfig, LlmConfig},
+    error::{ServiceError, ServiceResult},
+    resilience::breaker::CircuitBreaker,
+    utils::cost::estimate_tokens,
+};
+
+/// Deltas of the answer, as the model writes it.
+pub type LlmStream = Pin<Box<dyn Stream<Item = ServiceResult<String>> + Send>>;
+
+#[async_trait::async_trait]
+pub trait LlmProvider: Send + Sync + std::fmt::Debug {
+    /// Start the chat with the model, the stream yields the answer. `api_key`
+    /// replaces the key of the config, e.g. by the own key of the user.
+    async fn chat_stream(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        api_key: Option<&str>,
+    ) -> ServiceResult<LlmStream>;
+}
+
+// waits between the attempts to start a chat, doubled every retry
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+const DEFAULT_MAX_RETRIES: u32 = 2;
+
+/// A configured provider and the models it serves.
+#[derive(Debug)]
+struct Backend {
+    // `provider` of the config, e.g. `openai`
+    kind: String,
+    provider: Arc<dyn LlmProvider>,
+    model: String,
+    // requests for these models are routed to this provider, besides `model`
+    models: Vec<String>,
+    base_url: String,
+    // bound on starting a chat and on a whole completion
+    timeout: Duration,
+    max_retries: u32,
+    // one per provider, the others serve their models during its outage
+    breaker: CircuitBreaker,
+}
+
+impl Backend {
+    fn serves(&self, model: &str) -> bool {
+        self.model == model || self.models.iter().any(|m| m == model)
+    }
+}
+
+fn create_backend(
+    config: &LlmConfig,
+    breaker_config: &CircuitBreakerConfig,
+) -> anyhow::Result<Backend> {
+    let (default_model, default_url, default_timeout) = match config.provider.as_str() {
+        "openai" => ("gpt-4o-mini", "https://api.openai.com", 60),
+        "deepseek" => ("deepseek-chat", "https://api.deepseek.com", 60),
+        "anthropic" => ("claude-3-5-haiku-latest", "https://api.anthropic.com", 120),
+        // local models are slow on small machines
+        "ollama" => ("llama3.1", "http://localhost:11434", 300),
+        "mock" => ("mock", "", 5),
+        other => anyhow::bail!("Unknown llm provider: {}", other),
+    };
+    let model = config.model.clone().unwrap_or(default_model.to_string());
+    let base_url = config.base_url.clone().unwrap_or(default_url.to_string());
+    // streams run long, only the connection is bounded here
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .build()?;
+    let api_key = config.api_key.clone();
+    let provider: Arc<dyn LlmProvider> = match config.provider.as_str() {
+        "anthropic" => Arc::new(anthropic::AnthropicProvider::new(
+            client,
+            base_url.clone(),
+            api_key,
+        )),
+        "ollama" => Arc::new(ollama::OllamaProvider::new(client, base_url.clone())),
+        "mock" => Arc::new(mock::MockProvider),
+        _ => Arc::new(openai::OpenAiProvider::new(
+            client,
+            base_url.clone(),
+            api_key,
+        )),
+    };
+    Ok(Backend {
+        kind: config.provider.clone(),
+        provider,
+        model,
+        models: config.models.clone(),
+        base_url,
+        timeout: Duration::from_secs(config.timeout_secs.unwrap_or(default_timeout)),
+        max_retries: config.max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+        breaker: CircuitBreaker::new(format!("llm {}", config.provider), breaker_config),
+    })
+}
+
+/// Entry point of every llm call: routes the requested model to the provider
+/// serving it, the default provider otherwise.
+pub struct LlmClient {
+    // the default provider first
+    backends: Vec<Backend>,
+    pub model: String,
+    pub base_url: String,
+}
+
+impl std::fmt::Debug for LlmClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LlmClient")
+            .field("model", &self.model)
+            .field("base_url", &self.base_url)
+            .field("providers", &self.backends.len())
+            .finish()
+    }
+}
+
+impl LlmClient {
+    pub fn new(
+        config: &LlmConfig,
+        providers: &[LlmConfig],
+        breaker_config: &CircuitBreakerConfig,
+    ) -> anyhow::Result<Self> {
+        let backends = std::iter::once(config)
+            .chain(providers)
+            .map(|config| create_backend(config, breaker_config))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(LlmClient {
+            model: backends[0].model.clone(),
+            base_url: backends[0].base_url.clone(),
+            backends,
+        })
+    }
+
+    /// Whether a configured provider serves the model.
+    pub fn supports(&self, model: &str) -> bool {
+        self.backends.iter().any(|b| b.serves(model))
+    }
+
+    fn backend(&self, model: &str) -> &Backend {
+        self.backends
+            .iter()
+            .find(|b| b.serves(model))
+            .unwrap_or(&self.backends[0])
+    }
+
+    /// The provider serving the model, e.g. `openai`.
+    pub fn provider_of(&self, model: &str) -> &str {
+        &self.backend(model).kind
+    }
+
+    /// Start the chat with the model, the default one when `None`. Failures to
+    /// start are retried with backoff, unless the circuit of the provider is
+    /// open.
+    pub async fn stream(
+        &self,
+        model: Option<&str>,
+        messages: &[ChatMessage],
+    ) -> ServiceResult<LlmStream> {
+        self.stream_with_key(model, messages, None).await
+    }
+
+    /// [`LlmClient::stream`] with the api key of a user instead of the one of
+    /// the config. Its failures, e.g. a revoked key, leave the circuit of the
+    /// provider alone.
+    pub async fn stream_with_key(
+        &self,
+        model: Option<&str>,
+        messages: &[ChatMessage],
+        api_key: Option<&str>,
+    ) -> ServiceResult<LlmStream> {
+        let model = model.unwrap_or(&self.model);
+        let backend = self.backend(model);
+        let mut attempt = 0;
+        loop {
+            let start = async {
+                tokio::time::timeout(
+                    backend.timeout,
+                    backend.provider.chat_stream(model, messages, api_key),
+                )
+                .await
+                .unwrap_or_else(|_| Err(ServiceError::LLMError("Timed out".to_string())))
+            };
+            let started = match api_key {
+                Some(_) => start.await,
+                None => backend.breaker.call(start).await,
+            };
+            match started {
+                Ok(stream) => return Ok(stream),
+                Err(e @ ServiceError::CircuitOpen { .. }) => return Err(e),
+                Err(e) if attempt >= backend.max_retries => return Err(e),
+                Err(e) => {
+                    tracing::warn!("Llm call to {} failed, retrying: {}", model, e);
+                    tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Runs the chat with the default model and collects the answer.
+    pub async fn complete(&self, messages: &[ChatMessage]) -> ServiceResult<String> {
+        self.complete_with(None, messages).await
+    }
+
+    /// Runs the chat with the model, the default one when `None`, and collects
+    /// the streamed deltas into the full answer.
+    pub async fn complete_with(
+        &self,
+        model: Option<&str>,
+        messages: &[ChatMessage],
+    ) -> ServiceResult<String> {
+        self.complete_with_key(model, messages, None).await
+    }
+
+    /// [`LlmClient::complete_with`] with the api key of a user instead of the
+    /// one of the config.
+    pub async fn complete_with_key(
+        &self,
+        model: Option<&str>,
+        messages: &[ChatMessage],
+        api_key: Option<&str>,
+    ) -> ServiceResult<String> {
+        let timeout = self.backend(model.unwrap_or(&self.model)).timeout;
+        let collect = async {
+            let mut stream = self.stream_with_key(model, messages, api_key).await?;
+            let mut content = String::new();
+            while let Some(delta) = stream.next().await {
+                content.push_str(&delta?);
+            }
+            Ok(content)
+        };
+        tokio::time::timeout(timeout, collect)
+            .await
+            .map_err(|_| ServiceError::LLMError("Timed out".to_string()))?
+    }
+
+    /// Check an api key for the provider with a minimal chat with its default
+    /// model, failing with the error of the provider when it refuses the key.
+    pub async fn validate_key(&self, provider: &str, api_key: &str) -> ServiceResult<()> {
+        let backend = self
+            .backends
+            .iter()
+            .find(|b| b.kind == provider)
+            .ok_or_else(|| {
+                ServiceError::BadRequest(format!("No {} provider is configured", provider))
+            })?;
+        let messages = [ChatMessage::user("ping")];
+        tokio::time::timeout(
+            backend.timeout,
+            backend
+                .provider
+                .chat_stream(&backend.model, &messages, Some(api_key)),
+        )
+        .await
+        .map_err(|_| ServiceError::LLMError("Timed out".to_string()))??;
+        Ok(())
+    }
+
+    /// Estimated input and output tokens of a completed chat, the streamed
+    /// responses carry no usage.
+    pub fn estimate_usage(messages: &[ChatMessage], answer: &str) -> (u64, u64) {
+        let input = messages.iter().map(|m| estimate_tokens(&m.content)).sum();
+        (input, estimate_tokens(answer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_config(model: &str, models: &[&str]) -> LlmConfig {
+        LlmConfig {
+            provider: "mock".to_string(),
+            api_key: String::new(),
+            base_url: None,
+            model: Some(model.to_string()),
+            models: models.iter().map(|m| m.to_string()).collect(),
+            timeout_secs: None,
+            max_retries: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routes_models_to_providers() {
+        let client = LlmClient::new(
+            &mock_config("default-model", &[]),
+            &[mock_config("other-model", &["third-model"])],
+            &CircuitBreakerConfig::default(),
+        )
+        .unwrap();
+        assert!(client.supports("third-model"));
+        assert!(!client.supports("unknown-model"));
+
+        let messages = [
+            ChatMessage::system("Be brief"),
+            ChatMessage::user("hello there"),
+        ];
+        let answer = client.complete(&messages).await.unwrap();
+        assert_eq!(answer, "[default-model] hello there");
+        let answer = client
+            .complete_with(Some("third-model"), &messages)
+            .await
+            .unwrap();
+        assert_eq!(answer, "[third-model] hello there");
+    }
+}
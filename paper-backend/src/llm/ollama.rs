@@ -0,0 +1,72 @@
+use ai_flow_synth::llm::model::ChatMessage;
+use futures::{StreamExt, TryStreamExt, future::ready};
+use serde::Deserialize;
+
+use crate::{
+    error::{ServiceError, ServiceResult},
+    llm::{LlmProvider, LlmStream, stream::response_lines},
+};
+
+/// Ollama `/api/chat` api of a local model server.
+#[derive(Debug)]
+pub struct OllamaProvider {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl OllamaProvider {
+    pub fn new(client: reqwest::Client, base_url: String) -> Self {
+        OllamaProvider { client, base_url }
+    }
+}
+
+/// A line of the streamed ndjson answer.
+#[derive(Debug, Deserialize)]
+struct Chunk {
+    message: Option<ChunkMessage>,
+    #[serde(default)]
+    done: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkMessage {
+    content: String,
+}
+
+fn parse_chunk(line: &str) -> ServiceResult<Chunk> {
+    let chunk = serde_json::from_str::<Chunk>(line)
+        .map_err(|e| ServiceError::LLMError(format!("Invalid ollama chunk: {}", e)))?;
+    match chunk.error {
+        Some(error) => Err(ServiceError::LLMError(error)),
+        None => Ok(chunk),
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmProvider for OllamaProvider {
+    async fn chat_stream(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        _api_key: Option<&str>,
+    ) -> ServiceResult<LlmStream> {
+        let response = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&serde_json::json!({
+                "model": model,
+                "messages": messages,
+                "stream": true,
+            }))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ServiceError::LLMError(e.to_string()))?;
+        let stream = response_lines(response)
+            .and_then(|line| ready(parse_chunk(&line)))
+            .take_while(|chunk| ready(!matches!(chunk, Ok(Chunk { done: true, .. }))))
+            .map_ok(|chunk| chunk.message.map(|m| m.content).unwrap_or_default());
+        Ok(Box::pin(stream))
+    }
+}
@@ -0,0 +1,88 @@
+use ai_flow_synth::llm::model::ChatMessage;
+use futures::{StreamExt, TryStreamExt, future::ready};
+use serde::Deserialize;
+
+use crate::{
+    error::{ServiceError, ServiceResult},
+    llm::{
+        LlmProvider, LlmStream,
+        stream::{response_lines, sse_data},
+    },
+};
+
+/// OpenAI `/v1/chat/completions` api, also served by DeepSeek, vLLM, LM Studio...
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl std::fmt::Debug for OpenAiProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenAiProvider")
+            .field("base_url", &self.base_url)
+            .finish()
+    }
+}
+
+impl OpenAiProvider {
+    pub fn new(client: reqwest::Client, base_url: String, api_key: String) -> Self {
+        OpenAiProvider {
+            client,
+            base_url,
+            api_key,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Chunk {
+    choices: Vec<ChunkChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    delta: ChunkDelta,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkDelta {
+    content: Option<String>,
+}
+
+/// Content delta of a streamed chunk, tool calls and keep-alives carry none.
+fn chunk_content(data: &str) -> Option<String> {
+    let chunk = serde_json::from_str::<Chunk>(data).ok()?;
+    chunk.choices.into_iter().next()?.delta.content
+}
+
+#[async_trait::async_trait]
+impl LlmProvider for OpenAiProvider {
+    async fn chat_stream(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        api_key: Option<&str>,
+    ) -> ServiceResult<LlmStream> {
+        let response = self
+            .client
+            .post(format!("{}/v1/chat/completions", self.base_url))
+            .bearer_auth(api_key.unwrap_or(&self.api_key))
+            .json(&serde_json::json!({
+                "model": model,
+                "messages": messages,
+                "stream": true,
+            }))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ServiceError::LLMError(e.to_string()))?;
+        let stream = response_lines(response)
+            .take_while(|line| {
+                let done = matches!(line, Ok(line) if sse_data(line) == Some("[DONE]"));
+                ready(!done)
+            })
+            .try_filter_map(|line| ready(Ok(sse_data(&line).and_then(chunk_content))));
+        Ok(Box::pin(stream))
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, RwLock},
+};
+
+use crate::{
+    model::{database::Database, prompt::PromptTemplateRepository},
+    utils::template::render_template,
+};
+
+// summary of the papers of a folder, written when it is wrapped up
+pub const FOLDER_WRAP_UP_PROMPT: &str = "folder_wrap_up";
+// system prompt of the conversations with the model
+pub const CHAT_PROMPT: &str = "chat";
+// tags and folder suggested for an imported paper, answered as json
+pub const PAPER_CLASSIFY_PROMPT: &str = "paper_classify";
+// question about a paper answered from excerpts of its text, with quotes
+pub const PAPER_ASK_PROMPT: &str = "paper_ask";
+// structured comparison of papers, answered as json
+pub const PAPER_COMPARE_PROMPT: &str = "paper_compare";
+
+/// A prompt shipped with the service, used until an operator stores a template
+/// of the same name.
+#[derive(Debug, Clone, Copy)]
+pub struct DefaultPrompt {
+    pub name: &'static str,
+    pub body: &'static str,
+}
+
+pub const DEFAULT_PROMPTS: &[DefaultPrompt] = &[
+    DefaultPrompt {
+        name: FOLDER_WRAP_UP_PROMPT,
+        body: "Write a concise project summary in markdown covering the goal, the main \
+            findings of each paper, how they relate, and open questions.\n\n\
+            Project: {{folder_name}}\n{{folder_description}}\n\n{{materials}}",
+    },
+    DefaultPrompt {
+        name: CHAT_PROMPT,
+        body: "You are a research assistant helping the user read and organize \
+            academic papers. Answer concisely in markdown, and say so when you are not sure.",
+    },
+    DefaultPrompt {
+        name: PAPER_CLASSIFY_PROMPT,
+        body: "Suggest tags and a folder for a paper added to the library of a researcher.\n\n\
+            Title: {{title}}\nAbstract: {{abstract}}\n\n\
+            Existing tags: {{tags}}\n\nFolders:\n{{folders}}\n\n\
+            Reuse the existing tags when they fit and suggest at most 5 tags. Pick the number \
+            of the folder the paper belongs in, or null when none fits. Answer with json only: \
+            {\"tags\": [\"tag\"], \"folder\": 1}",
+    },
+    DefaultPrompt {
+        name: PAPER_ASK_PROMPT,
+        body: "Answer the question about the paper \"{{title}}\" using only the numbered \
+            excerpts of its text below. When they do not answer it, say that the paper does not \
+            cover it.\n\nExcerpts:\n{{excerpts}}\n\nQuestion: {{question}}\n\n\
+            Write the answer, then a line QUOTES: followed by the sentences of the excerpts \
+            supporting it, one per line, copied verbatim and prefixed by the number of the \
+            excerpt, like: [2] quoted sentence",
+    },
+    DefaultPrompt {
+        name: PAPER_COMPARE_PROMPT,
+        body: "Compare the numbered papers below: the methods, the datasets and the results \
+            of each, and the findings on which they contradict each other.\n\n{{papers}}\n\n\
+            Answer with json only: {\"summary\": \"how the papers relate\", \"papers\": \
+            [{\"paper\": 1, \"methods\": \"\", \"datasets\": \"\", \"results\": \"\"}], \
+            \"contradictions\": [\"\"]}",
+    },
+];
+
+// bodies from `prompt_config.defaults`, replaced when the config is reloaded
+static CONFIGURED_PROMPTS: LazyLock<RwLock<HashMap<String, String>>> =
+    LazyLock::new(Default::default);
+
+pub fn set_default_prompts(defaults: &HashMap<String, String>) {
+    let mut prompts = CONFIGURED_PROMPTS
+        .write()
+        .unwrap_or_else(|e| e.into_inner());
+    *prompts = defaults.clone();
+}
+
+/// The default body of the prompt, the configured one before the shipped one.
+pub fn default_prompt(name: &str) -> Option<String> {
+    let prompts = CONFIGURED_PROMPTS.read().unwrap_or_else(|e| e.into_inner());
+    if let Some(body) = prompts.get(name) {
+        return Some(body.clone());
+    }
+    DEFAULT_PROMPTS
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| p.body.to_string())
+}
+
+/// Body of the prompt: the stored version of the template, the latest one when
+/// `None`, falling back to the default when there is none or it cannot be read.
+pub async fn resolve_prompt(db: &dyn Database, name: &str, version: Option<u32>) -> String {
+    match db.find_prompt_template(name, version).await {
+        Ok(Some(template)) => return template.body,
+        Ok(None) => {}
+        Err(e) => tracing::error!("Failed to read prompt template {}: {}", name, e),
+    }
+    if let Some(version) = version {
+        tracing::warn!(
+            "Prompt template {} v{} not found, using the default",
+            name,
+            version
+        );
+    }
+    default_prompt(name).unwrap_or_default()
+}
+
+/// Resolve the prompt and fill its variables.
+pub async fn render_prompt(
+    db: &dyn Database,
+    name: &str,
+    version: Option<u32>,
+    values: &[(&str, &str)],
+) -> String {
+    render_template(&resolve_prompt(db, name, version).await, values)
+}
@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+
+use futures::{Stream, StreamExt};
+
+use crate::error::{ServiceError, ServiceResult};
+
+/// Splits a byte stream into lines, across chunk boundaries.
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    buffer: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// The lines completed by the bytes, without their line break.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line = self.buffer.drain(..=end).collect::<Vec<_>>();
+            let line = String::from_utf8_lossy(&line);
+            lines.push(line.trim_end_matches(['\r', '\n']).to_string());
+        }
+        lines
+    }
+
+    /// The last line, when the stream does not end with a line break.
+    pub fn finish(&mut self) -> Option<String> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let line = String::from_utf8_lossy(&self.buffer).to_string();
+        self.buffer.clear();
+        Some(line)
+    }
+}
+
+/// The non-empty lines of the body of the response, as they arrive.
+pub fn response_lines(
+    response: reqwest::Response,
+) -> impl Stream<Item = ServiceResult<String>> + Send {
+    let bytes = Box::pin(response.bytes_stream());
+    let state = (bytes, LineBuffer::default(), VecDeque::new(), false);
+    futures::stream::unfold(
+        state,
+        |(mut bytes, mut buffer, mut lines, mut ended)| async move {
+            loop {
+                if let Some(line) = lines.pop_front() {
+                    return Some((Ok(line), (bytes, buffer, lines, ended)));
+                }
+                if ended {
+                    return None;
+                }
+                match bytes.next().await {
+                    Some(Ok(chunk)) => lines.extend(buffer.push(&chunk)),
+                    Some(Err(e)) => {
+                        let error = ServiceError::LLMError(e.to_string());
+                        return Some((Err(error), (bytes, buffer, lines, true)));
+                    }
+                    None => {
+                        lines.extend(buffer.finish());
+                        ended = true;
+                    }
+                }
+                lines.retain(|line: &String| !line.is_empty());
+            }
+        },
+    )
+}
+
+/// Payload of a server-sent event `data:` line.
+pub fn sse_data(line: &str) -> Option<&str> {
+    line.strip_prefix("data:").map(str::trim_start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_buffer_joins_chunks() {
+        let mut buffer = LineBuffer::default();
+        assert!(buffer.push(b"data: {\"a\"").is_empty());
+        assert_eq!(
+            buffer.push(b":1}\r\n\r\ndata: [DONE]\n"),
+            ["data: {\"a\":1}", "", "data: [DONE]"]
+        );
+        assert_eq!(buffer.push(b"{\"done\":true}"), Vec::<String>::new());
+        assert_eq!(buffer.finish().as_deref(), Some("{\"done\":true}"));
+        assert_eq!(sse_data("data: [DONE]"), Some("[DONE]"));
+        assert_eq!(sse_data("event: ping"), None);
+    }
+}
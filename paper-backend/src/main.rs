@@ -1,12 +1,13 @@
-mod app_data;
-mod config;
-mod error;
-mod model;
-mod router;
-// mod timed_task;
-mod utils;
+use std::sync::Arc;
 
+use clap::Parser;
+use futures::FutureExt;
+#[cfg(unix)]
+use salvo::conn::UnixListener;
 use salvo::{
+    compression::{Compression, CompressionLevel},
+    conn::Acceptor,
+    cors::{AllowOrigin, Cors, CorsHandler},
     http::Method,
     oapi::{
         SecurityScheme,
@@ -14,59 +15,417 @@ use salvo::{
     },
     prelude::*,
 };
-// use timed_task::register_timed_task;
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::utils::jwt::set_jwt_config;
+#[cfg(feature = "grpc")]
+use paper_backend::grpc;
+use paper_backend::{
+    app_data,
+    config::{self, FrontendConfig, ListenAddress},
+    error, events, i18n, migrations, model,
+    reload::{self, LiveSettings},
+    resilience, router, seed,
+    tenant::{self, Tenants},
+    timed_task::register_timed_task,
+    tls,
+    utils::{
+        self, api_version::ApiVersion, jwt::set_jwt_config, security_headers::SecurityHeaders,
+    },
+};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let opt = std::env::args().collect::<Vec<_>>();
+    let cli = config::Cli::parse();
 
-    let config = config::Config::from_path(opt.get(1).unwrap_or(&"config.toml".into()))
-        .expect("Failed to load config");
-    let _g = ai_flow_synth::utils::enable_log(&config.log_config).unwrap();
+    let config = config::Config::load(&cli).expect("Failed to load config");
+    let (_g, log_level) = ai_flow_synth::utils::enable_reloadable_log(&config.log_config).unwrap();
+    if cli.migrations_dry_run {
+        return print_pending_migrations(&config).await;
+    }
+    if cli.seed {
+        return seed_demo(&config).await;
+    }
+    if cli.openapi {
+        return print_openapi(&config);
+    }
     set_jwt_config(&config.backend_config.jwt);
     let app_data = app_data::AppData::new(&config).await;
+    let tenants = Tenants::new(&config, app_data.clone()).await;
+    for state in tenants.all() {
+        // the migrations first, they fix the documents a new unique index rejects
+        migrations::run_migrations(state.db.as_ref(), false)
+            .await
+            .expect("Failed to run migrations");
+        let sealed = migrations::reencrypt_fields(state.db.as_ref(), state.cipher.as_ref(), false)
+            .await
+            .expect("Failed to re-encrypt fields");
+        if sealed > 0 {
+            info!("Sealed {} fields under the current encryption key", sealed);
+        }
+        state
+            .db
+            .ensure_indexes()
+            .await
+            .expect("Failed to create indexes");
+    }
+    let live_settings = reload::LiveSettings::new(&config, log_level);
+    if !cli.no_watch {
+        tokio::spawn(reload::watch_config(
+            cli.clone(),
+            tenants.clone(),
+            live_settings.clone(),
+        ));
+    }
+
+    for state in tenants.all() {
+        register_timed_task(state.clone()).await;
+        tokio::spawn(resilience::replay_writes(state.clone()));
+        events::register_subscribers(state);
+    }
+    if let Some(grpc_config) = &config.grpc_config {
+        #[cfg(feature = "grpc")]
+        tokio::spawn(grpc::serve(tenants.clone(), grpc_config.clone()));
+        #[cfg(not(feature = "grpc"))]
+        anyhow::bail!(
+            "`grpc_config` needs the `grpc` feature, got {}",
+            grpc_config.address
+        );
+    }
+
+    // the unversioned `/api` keeps answering as v1, for the clients predating
+    // the versions
+    let legacy_api = Router::with_path("api")
+        .hoop(ApiVersion::V1)
+        .push(router::create_router(&config.backend_config));
+    let legacy_router = Router::new()
+        .push(router::health::create_router())
+        .push(legacy_api);
+    let mut router = Router::new()
+        .hoop(affix_state::inject(tenants.clone()))
+        .hoop(tenant::resolve_tenant)
+        .hoop(i18n::negotiate_locale)
+        .hoop(utils::body_limit::limit_body)
+        .hoop(utils::timeout::limit_time);
+    let mut docs = Router::new().push(
+        api_doc("0.0.1", ApiVersion::V1, &legacy_router).into_router("/api-doc/openapi.json"),
+    );
+    for version in ApiVersion::ALL {
+        let versioned_router = Router::with_path(format!("api/{}", version.path()))
+            .hoop(*version)
+            .push(router::create_router(&config.backend_config));
+        let doc_path = format!("/api-doc/{}/openapi.json", version.path());
+        docs =
+            docs.push(api_doc(version.path(), *version, &versioned_router).into_router(&doc_path));
+        router = router.push(versioned_router);
+    }
+    let latest_doc = format!("/api-doc/{}/openapi.json", ApiVersion::LATEST.path());
+    let docs = router::create_docs_router(
+        &config.backend_config,
+        config.docs_config.access,
+        docs.push(SwaggerUi::new(latest_doc).into_router("/swagger-ui")),
+    );
+    let mut router = router.push(legacy_router);
+    // on the internal address, the docs get a server of their own below
+    let mut internal_docs = None;
+    match (docs, &config.docs_config.address) {
+        (Some(docs), None) => router = router.unshift(docs),
+        (Some(docs), Some(address)) => {
+            internal_docs = Some((
+                address.clone(),
+                Router::new()
+                    .hoop(affix_state::inject(tenants.clone()))
+                    .hoop(tenant::resolve_tenant)
+                    .push(docs),
+            ))
+        }
+        (None, _) => info!("The api docs are disabled"),
+    }
+    let router = Arc::new(router);
+    let security_headers = config
+        .security_headers_config
+        .enabled
+        .then(|| SecurityHeaders::new(&config.security_headers_config))
+        .transpose()
+        .expect("Invalid security headers config");
+    // a server per address, sharing the router
+    let create_service = || {
+        let mut service = Service::new(router.clone())
+            .catcher(Catcher::default().hoop(error::catch_parse_error))
+            .hoop(create_cors(&config.frontend_config, &live_settings))
+            .hoop(utils::request_id::request_id);
+        if let Some(security_headers) = &security_headers {
+            service = service.hoop(security_headers.clone());
+        }
+        match config.compression_config.enabled {
+            true => service.hoop(
+                Compression::new()
+                    .disable_all()
+                    .enable_gzip(CompressionLevel::Default)
+                    .enable_brotli(CompressionLevel::Default)
+                    .min_length(config.compression_config.min_bytes),
+            ),
+            false => service,
+        }
+    };
+
+    let backend_config = &config.backend_config;
+    let shutdown_timeout = backend_config.shutdown_timeout();
+    let mut servers = Vec::new();
+    for address in backend_config.listen_addresses() {
+        let server = match (address, &backend_config.tls) {
+            (ListenAddress::Tcp(address), Some(tls_config)) => {
+                let tls = tls::rustls_configs(tls_config.clone())
+                    .await
+                    .expect("Failed to load tls certificate");
+                let acceptor = TcpListener::new(&address).rustls(tls).bind().await;
+                info!("Server started on https://{}", address);
+                serve(acceptor, create_service(), shutdown_timeout).boxed()
+            }
+            (ListenAddress::Tcp(address), None) => {
+                let acceptor = TcpListener::new(&address).bind().await;
+                info!("Server started on {}", address);
+                serve(acceptor, create_service(), shutdown_timeout).boxed()
+            }
+            #[cfg(unix)]
+            (ListenAddress::Unix(path), _) => {
+                remove_stale_socket(&path)?;
+                let acceptor = UnixListener::new(&path).bind().await;
+                info!("Server started on unix:{}", path.display());
+                serve(acceptor, create_service(), shutdown_timeout).boxed()
+            }
+            #[cfg(not(unix))]
+            (ListenAddress::Unix(_), _) => {
+                panic!("Unix sockets are not supported on this platform")
+            }
+        };
+        servers.push(server);
+    }
+    if let Some((address, docs)) = internal_docs {
+        let mut service = Service::new(docs);
+        if let Some(security_headers) = &security_headers {
+            service = service.hoop(security_headers.clone());
+        }
+        let acceptor = TcpListener::new(&address).bind().await;
+        info!("Api docs served on {}", address);
+        servers.push(serve(acceptor, service, shutdown_timeout).boxed());
+    }
+    futures::future::join_all(servers).await;
+    for address in backend_config.listen_addresses() {
+        if let ListenAddress::Unix(path) = address {
+            std::fs::remove_file(path).ok();
+        }
+    }
 
-    // register_timed_task(app_data.clone()).await;
+    // the server is stopped, finish the background work before closing the databases
+    for state in tenants.all() {
+        if tokio::time::timeout(shutdown_timeout, state.jobs.wait())
+            .await
+            .is_err()
+        {
+            warn!(
+                "{} background jobs of tenant {} still running, abandoned",
+                state.jobs.running(),
+                state.tenant.id
+            );
+        }
+        resilience::flush_writes(state).await;
+        if tokio::time::timeout(shutdown_timeout, state.db.shutdown())
+            .await
+            .is_err()
+        {
+            warn!(
+                "Timed out closing the database of tenant {}",
+                state.tenant.id
+            );
+        }
+    }
+    info!("Server stopped");
 
-    let cors = salvo::cors::Cors::new()
-        .allow_origin(
-            config
-                .frontend_config
-                .cors
+    Ok(())
+}
+
+/// Cors of the frontend, the allowed origins are reloaded with the config.
+fn create_cors(frontend_config: &FrontendConfig, live_settings: &LiveSettings) -> CorsHandler {
+    let live_settings = live_settings.clone();
+    let mut cors = Cors::new()
+        .allow_origin(AllowOrigin::judge(move |origin, _, _| {
+            live_settings.allows_origin(origin)
+        }))
+        .allow_methods(vec![
+            Method::GET,
+            Method::POST,
+            Method::DELETE,
+            Method::PUT,
+            Method::PATCH,
+            Method::HEAD,
+        ])
+        .allow_headers(vec![
+            "authorization",
+            "content-type",
+            "idempotency-key",
+            "if-match",
+            "if-none-match",
+            "x-tenant",
+            "x-csrf-token",
+            "x-request-id",
+            "tus-resumable",
+            "upload-length",
+            "upload-metadata",
+            "upload-offset",
+            "upload-checksum",
+        ])
+        .expose_headers(
+            frontend_config
+                .cors_expose_headers
                 .iter()
                 .map(String::as_str)
                 .collect::<Vec<_>>(),
         )
-        .allow_methods(vec![Method::GET, Method::POST, Method::DELETE, Method::PUT])
-        .allow_headers(vec!["authorization", "content-type"])
-        .into_handler();
-
-    let router = Router::new().push(
-        Router::with_path("api")
-            .hoop(affix_state::inject(app_data))
-            .push(router::create_router(&config.backend_config)),
-    );
-    let doc = OpenApi::new("Paper Api", "0.0.1")
-        .add_security_scheme(
-            "bearer",
-            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer).bearer_format("JWT")),
-        )
-        .merge_router(&router);
+        .allow_credentials(frontend_config.cors_allow_credentials);
+    if let Some(max_age) = frontend_config.cors_max_age {
+        cors = cors.max_age(max_age);
+    }
+    cors.into_handler()
+}
 
-    let router = router
-        .unshift(doc.into_router("/api-doc/openapi.json"))
-        .unshift(SwaggerUi::new("/api-doc/openapi.json").into_router("/swagger-ui"));
+/// List the migrations the next start would apply, with the documents they would change.
+async fn print_pending_migrations(config: &config::Config) -> anyhow::Result<()> {
+    let db = model::database::connect(config)
+        .await
+        .expect("Failed to connect to the database");
+    let pending = migrations::run_migrations(db.as_ref(), true).await?;
+    if pending.is_empty() {
+        println!("No pending migration");
+    }
+    for (migration, affected) in pending {
+        println!(
+            "{}: {} ({} documents to change)",
+            migration.id, migration.description, affected
+        );
+    }
+    let cipher = config
+        .encryption_config
+        .as_ref()
+        .map(utils::crypto::Cipher::new)
+        .transpose()?;
+    let sealed = migrations::reencrypt_fields(db.as_ref(), cipher.as_ref(), true).await?;
+    if sealed > 0 {
+        println!("{} fields to seal under the current encryption key", sealed);
+    }
+    Ok(())
+}
 
-    let service = Service::new(router).hoop(cors);
+/// The spec of the router, whose timestamps are written as `version` writes them.
+fn api_doc(name: &str, version: ApiVersion, router: &Router) -> OpenApi {
+    version.document(
+        OpenApi::new("Paper Api", name)
+            .add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer).bearer_format("JWT")),
+            )
+            .merge_router(router),
+    )
+}
 
-    let acceptor = TcpListener::new(&config.backend_config.address)
-        .bind()
-        .await;
-    Server::new(acceptor).serve(service).await;
-    info!("Server started on {}", &config.backend_config.address);
+/// Print the spec of the latest api version, whatever the docs config, for the
+/// generation of the clients. Needs no database.
+fn print_openapi(config: &config::Config) -> anyhow::Result<()> {
+    let version = ApiVersion::LATEST;
+    let router = Router::with_path(format!("api/{}", version.path()))
+        .hoop(version)
+        .push(router::create_router(&config.backend_config));
+    println!(
+        "{}",
+        api_doc(version.path(), version, &router).to_pretty_json()?
+    );
+    Ok(())
+}
 
+/// Seed the demo account, on a database brought up to date like on startup.
+async fn seed_demo(config: &config::Config) -> anyhow::Result<()> {
+    let db = model::database::connect(config)
+        .await
+        .expect("Failed to connect to the database");
+    migrations::run_migrations(db.as_ref(), false).await?;
+    db.ensure_indexes().await?;
+    let seeded = seed::seed_demo(db.as_ref()).await?;
+    println!(
+        "Seeded {} folders and {} papers for {} (password {})",
+        seeded.folders,
+        seeded.papers,
+        seed::DEMO_EMAIL,
+        seed::DEMO_PASSWORD
+    );
+    db.shutdown().await;
     Ok(())
 }
+
+/// Remove the socket left behind by a server which did not stop cleanly,
+/// refusing to remove a file which is not a socket.
+#[cfg(unix)]
+fn remove_stale_socket(path: &std::path::Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            std::fs::remove_file(path).map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to remove stale unix socket {}: {}",
+                    path.display(),
+                    e
+                )
+            })
+        }
+        Ok(_) => anyhow::bail!(
+            "Cannot listen on {}, it exists and is not a unix socket",
+            path.display()
+        ),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => anyhow::bail!("Failed to inspect {}: {}", path.display(), e),
+    }
+}
+
+/// Serve until a shutdown signal, then drain the in-flight requests.
+async fn serve<A: Acceptor + Send + 'static>(
+    acceptor: A,
+    service: Service,
+    shutdown_timeout: std::time::Duration,
+) {
+    let server = Server::new(acceptor);
+    let handle = server.handle();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Shutting down, draining in-flight requests");
+        handle.stop_graceful(shutdown_timeout);
+    });
+    server.serve(service).await;
+}
+
+/// Resolves on SIGINT (ctrl-c) or SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for ctrl-c: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
@@ -0,0 +1,145 @@
+//! Server side rendering of the LaTeX math of abstracts and notes into MathML,
+//! which the browsers display natively, so the frontend needs no math library.
+
+use std::time::Duration;
+
+use latex2mathml::{DisplayStyle, latex_to_mathml};
+use sha2::{Digest, Sha256};
+
+use crate::{export::html::escape_html, utils::cache::TtlCache};
+
+// texts are rendered again when edited, the cache only spares the repeated reads
+const RENDER_CACHE_TTL: Duration = Duration::from_secs(600);
+
+/// Delimiters of the formulas, the longest first so `$$` is not read as two `$`.
+const DELIMITERS: [(&str, &str, bool); 4] = [
+    ("$$", "$$", true),
+    ("\\[", "\\]", true),
+    ("\\(", "\\)", false),
+    ("$", "$", false),
+];
+
+/// Renders texts with their rendering cached by content.
+#[derive(Debug)]
+pub struct MathRenderer {
+    cache: TtlCache<String>,
+}
+
+impl Default for MathRenderer {
+    fn default() -> Self {
+        MathRenderer {
+            cache: TtlCache::new(RENDER_CACHE_TTL),
+        }
+    }
+}
+
+impl MathRenderer {
+    pub fn render(&self, text: &str) -> String {
+        let key = Sha256::digest(text.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        if let Some(html) = self.cache.get(&key) {
+            return html;
+        }
+        let html = render_math(text);
+        self.cache.insert(&key, html.clone());
+        html
+    }
+}
+
+/// The text with its formulas replaced by MathML. `$...$` and `\(...\)` are
+/// inline, `$$...$$` and `\[...\]` are displayed as blocks, `\$` is a dollar.
+/// The rest of the text is only escaped, so markdown notes stay markdown with
+/// inline MathML. A formula which fails to parse is kept as its source.
+pub fn render_math(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some((start, open, close, block)) = next_formula(rest) {
+        html.push_str(&escape_html(&rest[..start]));
+        let body = &rest[start + open.len()..];
+        match formula_end(body, close, block) {
+            Some(end) => {
+                let source = &rest[start..start + open.len() + end + close.len()];
+                html.push_str(&render_formula(&body[..end], block, source));
+                rest = &body[end + close.len()..];
+            }
+            // not a formula, e.g. a price
+            None => {
+                html.push_str(&escape_html(open));
+                rest = body;
+            }
+        }
+    }
+    html.push_str(&escape_html(rest));
+    html
+}
+
+/// The first opening delimiter of the text, skipping the escaped dollars.
+fn next_formula(text: &str) -> Option<(usize, &'static str, &'static str, bool)> {
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        if rest.starts_with("\\$") {
+            i += 2;
+            continue;
+        }
+        for (open, close, block) in DELIMITERS {
+            if rest.starts_with(open) {
+                return Some((i, open, close, block));
+            }
+        }
+        i += rest.chars().next().map_or(1, char::len_utf8);
+    }
+    None
+}
+
+/// The length of the formula before its closing delimiter. An inline formula
+/// neither starts nor ends with a space and stays in its paragraph, like in
+/// pandoc, so `$5 and $10` is no formula.
+fn formula_end(body: &str, close: &str, block: bool) -> Option<usize> {
+    let end = body.find(close).filter(|&end| end > 0)?;
+    let formula = &body[..end];
+    if !block
+        && (formula.starts_with(char::is_whitespace)
+            || formula.ends_with(char::is_whitespace)
+            || formula.contains("\n\n"))
+    {
+        return None;
+    }
+    Some(end)
+}
+
+fn render_formula(latex: &str, block: bool, source: &str) -> String {
+    let style = if block {
+        DisplayStyle::Block
+    } else {
+        DisplayStyle::Inline
+    };
+    match latex_to_mathml(latex.trim(), style) {
+        Ok(mathml) => mathml,
+        Err(e) => {
+            tracing::debug!("Failed to render formula {}: {}", latex, e);
+            escape_html(source)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_math() {
+        let html = render_math("Energy $E = mc^2$ and\n$$\\frac{a}{b}$$");
+        assert!(html.starts_with("Energy <math"));
+        assert_eq!(html.matches("<math").count(), 2);
+        assert!(html.contains("display=\"block\""));
+        assert!(html.contains("<mfrac>"));
+
+        assert_eq!(render_math("costs $5 and $10"), "costs $5 and $10");
+        assert_eq!(render_math("a \\$ b $ c"), "a \\$ b $ c");
+        assert_eq!(render_math("x < y & $$"), "x &lt; y &amp; $$");
+        assert!(render_math("\\(x_1\\)").starts_with("<math"));
+    }
+}
@@ -0,0 +1,254 @@
+use std::{collections::HashMap, time::Duration};
+
+use futures::future::BoxFuture;
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        blob::{BlobRepository, paper_file_key},
+        content::store_file,
+        database::Database,
+        llm_key::LlmKeyRepository,
+        migration::{
+            MigrationRecord, MigrationRepository,
+            schema::{ListMigrationsResponse, MigrationResponse},
+        },
+        note::paper_note_key,
+        paper::PaperRepository,
+        webhook::WebhookRepository,
+    },
+    utils::crypto::Cipher,
+};
+
+type MigrationFn = for<'a> fn(&'a dyn Database, bool) -> BoxFuture<'a, ServiceResult<u64>>;
+
+// a lock older than this is taken over, its holder having died while migrating
+const MIGRATION_LOCK_STALE_MS: i64 = 60 * 60 * 1000;
+// the wait between the attempts to take the lock held by another instance
+const MIGRATION_LOCK_POLL: Duration = Duration::from_secs(2);
+
+/// A change of the stored documents, applied once on startup. Runs may be
+/// interrupted or race between instances, so each only touches the documents
+/// it has not changed yet.
+pub struct Migration {
+    pub id: &'static str,
+    pub description: &'static str,
+    run: MigrationFn,
+}
+
+// applied in this order, new ones are appended
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        id: "0001_backfill_versions",
+        description: "Give a version to the folders and papers stored before versioning",
+        run: backfill_versions,
+    },
+    Migration {
+        id: "0002_backfill_folder_sort_order",
+        description: "Rank the folders stored before manual ordering in creation order",
+        run: backfill_folder_sort_order,
+    },
+    Migration {
+        id: "0003_rename_duplicate_folders",
+        description: "Number the folders named like a sibling, before their names are made unique",
+        run: rename_duplicate_folders,
+    },
+    Migration {
+        id: "0004_store_files_by_content",
+        description: "Move the files of the papers to blobs shared by content and counted",
+        run: store_files_by_content,
+    },
+    Migration {
+        id: "0005_drop_plain_email_index",
+        description: "Drop the index of the user emails, created again unique",
+        run: drop_plain_email_index,
+    },
+];
+
+fn backfill_versions(db: &dyn Database, dry_run: bool) -> BoxFuture<'_, ServiceResult<u64>> {
+    db.backfill_versions(dry_run)
+}
+
+fn backfill_folder_sort_order(
+    db: &dyn Database,
+    dry_run: bool,
+) -> BoxFuture<'_, ServiceResult<u64>> {
+    db.backfill_folder_sort_order(dry_run)
+}
+
+fn rename_duplicate_folders(db: &dyn Database, dry_run: bool) -> BoxFuture<'_, ServiceResult<u64>> {
+    db.rename_duplicate_folders(dry_run)
+}
+
+fn drop_plain_email_index(db: &dyn Database, dry_run: bool) -> BoxFuture<'_, ServiceResult<u64>> {
+    db.drop_plain_email_index(dry_run)
+}
+
+/// Move every `paper/{id}` blob to the blob of its content, counting a
+/// reference for its paper. The blobs of deleted papers are dropped. A run
+/// interrupted between both counts the file once more, which only keeps it.
+fn store_files_by_content(db: &dyn Database, dry_run: bool) -> BoxFuture<'_, ServiceResult<u64>> {
+    Box::pin(async move {
+        let prefix = paper_file_key("");
+        let keys = db
+            .list_blobs()
+            .await?
+            .into_iter()
+            .filter(|key| key.starts_with(&prefix))
+            .collect::<Vec<_>>();
+        if dry_run {
+            return Ok(keys.len() as u64);
+        }
+        for key in &keys {
+            let paper_id = &key[prefix.len()..];
+            let holds_file = db
+                .get_paper_by_id(paper_id)
+                .await?
+                .is_some_and(|paper| paper.file_hash.is_some());
+            if let Some(bytes) = db.get_blob(key).await?.filter(|_| holds_file) {
+                store_file(db, &bytes).await?;
+            }
+            db.delete_blob(key).await?;
+        }
+        Ok(keys.len() as u64)
+    })
+}
+
+/// Run the migrations not applied yet, in order, and the documents each changed.
+/// A dry run changes and records nothing, it counts the documents to change.
+/// The migrations run under a lock, the instances started meanwhile wait for
+/// it and find them applied.
+pub async fn run_migrations(
+    db: &dyn Database,
+    dry_run: bool,
+) -> ServiceResult<Vec<(&'static Migration, u64)>> {
+    if dry_run {
+        return apply_migrations(db, true).await;
+    }
+    let owner = uuid::Uuid::new_v4().to_string();
+    loop {
+        let stale_before = bson::DateTime::from_millis(
+            bson::DateTime::now().timestamp_millis() - MIGRATION_LOCK_STALE_MS,
+        );
+        if db.acquire_migration_lock(&owner, stale_before).await? {
+            break;
+        }
+        tracing::info!("Waiting for another instance to run the migrations");
+        tokio::time::sleep(MIGRATION_LOCK_POLL).await;
+    }
+    let report = apply_migrations(db, false).await;
+    let released = db.release_migration_lock(&owner).await;
+    let report = report?;
+    released?;
+    Ok(report)
+}
+
+async fn apply_migrations(
+    db: &dyn Database,
+    dry_run: bool,
+) -> ServiceResult<Vec<(&'static Migration, u64)>> {
+    let applied = db.get_applied_migrations().await?;
+    let mut report = Vec::new();
+    for migration in MIGRATIONS {
+        if applied.iter().any(|record| record.id == migration.id) {
+            continue;
+        }
+        let affected = (migration.run)(db, dry_run).await?;
+        if !dry_run {
+            tracing::info!(
+                "Applied migration {}, {} documents changed",
+                migration.id,
+                affected
+            );
+            db.record_migration(MigrationRecord {
+                id: migration.id.to_string(),
+                description: migration.description.to_string(),
+                applied_at: bson::DateTime::now(),
+                affected,
+            })
+            .await?;
+        }
+        report.push((migration, affected));
+    }
+    Ok(report)
+}
+
+/// Seal again under the current key the secrets sealed under a retired one,
+/// without the key id by the first versions, or stored before encryption, and
+/// the notes when they are to be sealed. Unlike the migrations it runs on every
+/// start, a key may be rotated again later. A dry run changes nothing, it
+/// counts the values to seal.
+pub async fn reencrypt_fields(
+    db: &dyn Database,
+    cipher: Option<&Cipher>,
+    dry_run: bool,
+) -> ServiceResult<u64> {
+    let Some(cipher) = cipher else {
+        return Ok(0);
+    };
+    let mut sealed = 0;
+    for mut key in db.get_all_llm_keys().await? {
+        if cipher.is_current(&key.encrypted_key) {
+            continue;
+        }
+        if !dry_run {
+            key.encrypted_key = cipher.encrypt(&cipher.decrypt_sealed(&key.encrypted_key)?)?;
+            db.upsert_llm_key(key).await?;
+        }
+        sealed += 1;
+    }
+    for webhook in db.get_all_webhooks().await? {
+        if cipher.is_current(&webhook.secret) {
+            continue;
+        }
+        if !dry_run {
+            let secret = cipher.encrypt(&cipher.decrypt(&webhook.secret)?)?;
+            db.set_webhook_secret(&webhook.id, &secret).await?;
+        }
+        sealed += 1;
+    }
+    if cipher.encrypt_notes {
+        let prefix = paper_note_key("");
+        for key in db.list_blobs().await? {
+            if !key.starts_with(&prefix) {
+                continue;
+            }
+            let Some(bytes) = db.get_blob(&key).await? else {
+                continue;
+            };
+            if cipher.is_current_bytes(&bytes) {
+                continue;
+            }
+            if !dry_run {
+                let note = cipher.encrypt_bytes(&cipher.decrypt_bytes(&bytes)?)?;
+                db.put_blob(&key, &note).await?;
+            }
+            sealed += 1;
+        }
+    }
+    Ok(sealed)
+}
+
+/// Every migration with when it was applied.
+pub async fn migration_status(db: &dyn Database) -> ServiceResult<ListMigrationsResponse> {
+    let applied: HashMap<String, MigrationRecord> = db
+        .get_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|record| (record.id.clone(), record))
+        .collect();
+    let migrations = MIGRATIONS
+        .iter()
+        .map(|migration| {
+            let record = applied.get(migration.id);
+            MigrationResponse {
+                id: migration.id.to_string(),
+                description: migration.description.to_string(),
+                applied: record.is_some(),
+                applied_at: record.map(|record| record.applied_at.timestamp_millis()),
+                affected: record.map(|record| record.affected),
+            }
+        })
+        .collect();
+    Ok(ListMigrationsResponse(migrations))
+}
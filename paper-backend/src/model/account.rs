@@ -0,0 +1,273 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::{Document, doc};
+use futures::TryStreamExt;
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        blob::{BlobRepository, paper_thumbnail_key, quarantine_file_key},
+        constant::*,
+        content::release_file,
+        document::{DocumentDatabase, Query},
+        export::export_file_key,
+        note::paper_note_key,
+        txn::{TxnContext, in_session},
+    },
+    pdf::thumbnail::ThumbnailSize,
+};
+
+// collections holding data of a user, with the field of the user id
+const USER_COLLECTIONS: &[(&str, &str)] = &[
+    (ACTIVITY_COLLECTION_NAME, "user_id"),
+    (AUDIT_LOG_COLLECTION_NAME, "user_id"),
+    (BLOCK_COLLECTION_NAME, "user_id"),
+    (CITATION_COLLECTION_NAME, "user_id"),
+    (COMPARISON_COLLECTION_NAME, "user_id"),
+    (CONSENT_COLLECTION_NAME, "user_id"),
+    (CONVERSATION_COLLECTION_NAME, "user_id"),
+    (CONVERSATION_MESSAGE_COLLECTION_NAME, "user_id"),
+    (CUSTOM_FIELD_COLLECTION_NAME, "owner_id"),
+    (EXPORT_COLLECTION_NAME, "user_id"),
+    (FOLDER_COLLECTION_NAME, "user_id"),
+    (JOB_COLLECTION_NAME, "user_id"),
+    (LLM_KEY_COLLECTION_NAME, "user_id"),
+    (NOTIFICATION_COLLECTION_NAME, "user_id"),
+    (PAPER_COLLECTION_NAME, "user_id"),
+    (PAPER_EMBEDDING_COLLECTION_NAME, "user_id"),
+    (QUARANTINE_COLLECTION_NAME, "user_id"),
+    (READING_LIST_COLLECTION_NAME, "user_id"),
+    (UPLOAD_CHUNK_COLLECTION_NAME, "user_id"),
+    (UPLOAD_COLLECTION_NAME, "user_id"),
+    (SHARE_LINK_COLLECTION_NAME, "owner_id"),
+    (USAGE_EVENT_COLLECTION_NAME, "user_id"),
+    (WEBHOOK_COLLECTION_NAME, "user_id"),
+    (WEBHOOK_DELIVERY_COLLECTION_NAME, "user_id"),
+    (USER_COLLECTION_NAME, "uid"),
+];
+
+// collections holding data of the papers of a user
+const PAPER_COLLECTIONS: &[&str] = &[
+    COMMENT_COLLECTION_NAME,
+    PAPER_CHUNK_COLLECTION_NAME,
+    PAPER_PAGE_COLLECTION_NAME,
+    PAPER_REVISION_COLLECTION_NAME,
+];
+
+pub mod schema {
+    pub use paper_schema::account::*;
+
+    use crate::utils::validate::ValidatedRequest;
+
+    impl ValidatedRequest for DeleteAccountRequest {}
+}
+
+/// Cascading deletion of everything stored for a user.
+#[async_trait::async_trait]
+pub trait AccountRepository: Send + Sync {
+    /// Delete the user with its papers, files, folders and every other record.
+    /// The files are deleted first and outside of the transaction, the blobs
+    /// are not transactional.
+    async fn delete_account(&self, txn: &mut TxnContext, user_id: &str) -> ServiceResult<()>;
+    /// Collections still holding records of the user, with their count. Empty
+    /// once the account is fully deleted.
+    async fn remaining_account_data(&self, user_id: &str) -> ServiceResult<Vec<(String, u64)>>;
+}
+
+async fn user_document_ids(
+    client: &MongoClient,
+    collection: &str,
+    user_id: &str,
+) -> ServiceResult<Vec<String>> {
+    let ids = client
+        .collection::<Document>(collection)
+        .find(doc! { "user_id": user_id })
+        .projection(doc! { "_id": 1 })
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?
+        .into_iter()
+        .filter_map(|d| d.get_str("_id").ok().map(str::to_string))
+        .collect();
+    Ok(ids)
+}
+
+/// Release the files of the papers of a user, detaching each from its paper
+/// first so a retried deletion does not release it twice.
+async fn release_user_files(client: &MongoClient, user_id: &str) -> ServiceResult<()> {
+    let papers = client.collection::<Document>(PAPER_COLLECTION_NAME);
+    let files = papers
+        .find(doc! { "user_id": user_id, "file_hash": { NE_OP: null } })
+        .projection(doc! { "file_hash": 1 })
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?;
+    for file in files {
+        let (Ok(id), Ok(hash)) = (file.get_str("_id"), file.get_str("file_hash")) else {
+            continue;
+        };
+        let detached = papers
+            .update_one(
+                doc! { "_id": id, "file_hash": hash },
+                doc! { SET_OP: { "file_hash": null } },
+            )
+            .await?;
+        if detached.matched_count > 0 {
+            release_file(client, hash).await?;
+        }
+    }
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl AccountRepository for MongoClient {
+    async fn delete_account(&self, txn: &mut TxnContext, user_id: &str) -> ServiceResult<()> {
+        let paper_ids = user_document_ids(self, PAPER_COLLECTION_NAME, user_id).await?;
+        let export_ids = user_document_ids(self, EXPORT_COLLECTION_NAME, user_id).await?;
+        let quarantine_ids = user_document_ids(self, QUARANTINE_COLLECTION_NAME, user_id).await?;
+        release_user_files(self, user_id).await?;
+        for paper_id in &paper_ids {
+            self.delete_blob(&paper_note_key(paper_id)).await?;
+            for size in ThumbnailSize::ALL {
+                self.delete_blob(&paper_thumbnail_key(paper_id, size))
+                    .await?;
+            }
+        }
+        for export_id in &export_ids {
+            self.delete_blob(&export_file_key(export_id)).await?;
+        }
+        for quarantine_id in &quarantine_ids {
+            self.delete_blob(&quarantine_file_key(quarantine_id))
+                .await?;
+        }
+        for collection in PAPER_COLLECTIONS {
+            let collection = self.collection::<Document>(collection);
+            let filter = doc! { "paper_id": { IN_OP: &paper_ids } };
+            in_session!(collection.delete_many(filter), txn)?;
+        }
+        // the other admins keep managing the organization
+        let organizations = self.collection::<Document>(ORGANIZATION_COLLECTION_NAME);
+        in_session!(
+            organizations.update_many(
+                doc! { "admin_ids": user_id },
+                doc! { PULL_OP: { "admin_ids": user_id } },
+            ),
+            txn
+        )?;
+        // the user goes last, a failure outside of a transaction leaves an
+        // account that can retry
+        for &(collection, field) in USER_COLLECTIONS {
+            let collection = self.collection::<Document>(collection);
+            in_session!(collection.delete_many(doc! { field: user_id }), txn)?;
+        }
+        Ok(())
+    }
+
+    async fn remaining_account_data(&self, user_id: &str) -> ServiceResult<Vec<(String, u64)>> {
+        let mut remaining = Vec::new();
+        for &(collection, field) in USER_COLLECTIONS {
+            let count = self
+                .collection::<Document>(collection)
+                .count_documents(doc! { field: user_id })
+                .await?;
+            if count > 0 {
+                remaining.push((collection.to_string(), count));
+            }
+        }
+        let admin_of = self
+            .collection::<Document>(ORGANIZATION_COLLECTION_NAME)
+            .count_documents(doc! { "admin_ids": user_id })
+            .await?;
+        if admin_of > 0 {
+            remaining.push((ORGANIZATION_COLLECTION_NAME.to_string(), admin_of));
+        }
+        Ok(remaining)
+    }
+}
+
+async fn stored_document_ids(
+    db: &DocumentDatabase,
+    collection: &str,
+    user_id: &str,
+) -> ServiceResult<Vec<String>> {
+    let ids = db
+        .find_documents(collection, Query::new(doc! { "user_id": user_id }))
+        .await?
+        .into_iter()
+        .filter_map(|d| d.get_str("_id").ok().map(str::to_string))
+        .collect();
+    Ok(ids)
+}
+
+async fn release_stored_files(db: &DocumentDatabase, user_id: &str) -> ServiceResult<()> {
+    let query = Query::new(doc! { "user_id": user_id, "file_hash": { NE_OP: null } });
+    for file in db.find_documents(PAPER_COLLECTION_NAME, query).await? {
+        let (Ok(id), Ok(hash)) = (file.get_str("_id"), file.get_str("file_hash")) else {
+            continue;
+        };
+        let detached = db
+            .update_one(
+                PAPER_COLLECTION_NAME,
+                doc! { "_id": id, "file_hash": hash },
+                doc! { SET_OP: { "file_hash": null } },
+            )
+            .await?;
+        if detached > 0 {
+            release_file(db, hash).await?;
+        }
+    }
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl AccountRepository for DocumentDatabase {
+    async fn delete_account(&self, txn: &mut TxnContext, user_id: &str) -> ServiceResult<()> {
+        let db = txn.documents(self);
+        let paper_ids = stored_document_ids(db, PAPER_COLLECTION_NAME, user_id).await?;
+        let export_ids = stored_document_ids(db, EXPORT_COLLECTION_NAME, user_id).await?;
+        let quarantine_ids = stored_document_ids(db, QUARANTINE_COLLECTION_NAME, user_id).await?;
+        release_stored_files(db, user_id).await?;
+        for paper_id in &paper_ids {
+            db.delete_blob(&paper_note_key(paper_id)).await?;
+            for size in ThumbnailSize::ALL {
+                db.delete_blob(&paper_thumbnail_key(paper_id, size)).await?;
+            }
+        }
+        for export_id in &export_ids {
+            db.delete_blob(&export_file_key(export_id)).await?;
+        }
+        for quarantine_id in &quarantine_ids {
+            db.delete_blob(&quarantine_file_key(quarantine_id)).await?;
+        }
+        for collection in PAPER_COLLECTIONS {
+            db.delete_many(collection, doc! { "paper_id": { IN_OP: &paper_ids } })
+                .await?;
+        }
+        db.update_many(
+            ORGANIZATION_COLLECTION_NAME,
+            doc! { "admin_ids": user_id },
+            doc! { PULL_OP: { "admin_ids": user_id } },
+        )
+        .await?;
+        for &(collection, field) in USER_COLLECTIONS {
+            db.delete_many(collection, doc! { field: user_id }).await?;
+        }
+        Ok(())
+    }
+
+    async fn remaining_account_data(&self, user_id: &str) -> ServiceResult<Vec<(String, u64)>> {
+        let mut remaining = Vec::new();
+        for &(collection, field) in USER_COLLECTIONS {
+            let count = self.count(collection, doc! { field: user_id }).await?;
+            if count > 0 {
+                remaining.push((collection.to_string(), count));
+            }
+        }
+        let admin_of = self
+            .count(ORGANIZATION_COLLECTION_NAME, doc! { "admin_ids": user_id })
+            .await?;
+        if admin_of > 0 {
+            remaining.push((ORGANIZATION_COLLECTION_NAME.to_string(), admin_of));
+        }
+        Ok(remaining)
+    }
+}
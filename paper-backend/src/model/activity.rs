@@ -0,0 +1,103 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+pub use paper_schema::activity::{ActivityAction, ActivityKind};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+pub use paper_schema::activity as schema;
+
+/// Last time a user opened a paper or folder, one record per resource.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Activity {
+    #[serde(rename = "_id")]
+    pub id: String, // `{user_id}/{resource_id}`, so opening again replaces it
+    pub user_id: String,
+    pub kind: ActivityKind,
+    pub resource_id: String,
+    pub action: ActivityAction,
+    pub at: bson::DateTime,
+}
+
+impl Activity {
+    pub fn new(
+        user_id: &str,
+        kind: ActivityKind,
+        resource_id: &str,
+        action: ActivityAction,
+    ) -> Self {
+        Activity {
+            id: format!("{}/{}", user_id, resource_id),
+            user_id: user_id.to_string(),
+            kind,
+            resource_id: resource_id.to_string(),
+            action,
+            at: bson::DateTime::now(),
+        }
+    }
+    /// The response of the activity, with the title of its resource.
+    pub fn into_response(self, title: String) -> schema::ActivityResponse {
+        schema::ActivityResponse {
+            kind: self.kind,
+            resource_id: self.resource_id,
+            title,
+            action: self.action,
+            at: self.at.timestamp_millis(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait ActivityRepository: Send + Sync {
+    /// Record the activity, replacing the previous one on the same resource.
+    async fn record_activity(&self, activity: Activity) -> ServiceResult<()>;
+    /// The latest activities of the user, most recent first.
+    async fn get_recent_activity(&self, user_id: &str, limit: i64) -> ServiceResult<Vec<Activity>>;
+}
+
+#[async_trait::async_trait]
+impl ActivityRepository for MongoClient {
+    async fn record_activity(&self, activity: Activity) -> ServiceResult<()> {
+        let filter = doc! { "_id": &activity.id };
+        self.collection::<Activity>(ACTIVITY_COLLECTION_NAME)
+            .replace_one(filter, activity)
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_recent_activity(&self, user_id: &str, limit: i64) -> ServiceResult<Vec<Activity>> {
+        let cursor = self
+            .collection::<Activity>(ACTIVITY_COLLECTION_NAME)
+            .find(doc! { "user_id": user_id })
+            .sort(doc! { "at": -1 })
+            .limit(limit)
+            .await?;
+        let activities = cursor.try_collect().await?;
+        Ok(activities)
+    }
+}
+
+#[async_trait::async_trait]
+impl ActivityRepository for DocumentDatabase {
+    async fn record_activity(&self, activity: Activity) -> ServiceResult<()> {
+        let filter = doc! { "_id": &activity.id };
+        self.replace_one(ACTIVITY_COLLECTION_NAME, filter, &activity, true)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_recent_activity(&self, user_id: &str, limit: i64) -> ServiceResult<Vec<Activity>> {
+        let query = Query::new(doc! { "user_id": user_id })
+            .sort(doc! { "at": -1 })
+            .limit(limit);
+        self.find(ACTIVITY_COLLECTION_NAME, query).await
+    }
+}
@@ -0,0 +1,12 @@
+pub mod schema {
+    pub use paper_schema::ai::*;
+
+    use crate::utils::validate::{ValidatedRequest, trim_all, trim_option};
+
+    impl ValidatedRequest for EstimateRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.model);
+            trim_all(&mut self.paper_ids);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use ai_flow_synth::utils::MongoClient;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::DocumentDatabase,
+        txn::{TxnContext, in_session},
+    },
+    utils::request_id::current_request_id,
+};
+
+/// Security relevant events, kept for later investigation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLog {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String, // uuid of the user the event belongs to
+    pub created_at: bson::DateTime,
+
+    pub action: AuditAction,
+    pub ip: Option<String>,
+    pub detail: Option<String>,
+    // the request which caused the event
+    #[serde(default)]
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AuditAction {
+    #[serde(rename = "password_reset_requested")]
+    PasswordResetRequested,
+    #[serde(rename = "password_reset")]
+    PasswordReset,
+    #[serde(rename = "paper_deleted")]
+    PaperDeleted,
+    #[serde(rename = "folder_created")]
+    FolderCreated,
+    #[serde(rename = "login_locked")]
+    LoginLocked,
+}
+
+impl AuditLog {
+    pub fn new(user_id: &str, action: AuditAction, ip: Option<String>) -> Self {
+        AuditLog {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+
+            action,
+            ip,
+            detail: None,
+            request_id: current_request_id(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait AuditLogRepository: Send + Sync {
+    async fn create_audit_log(&self, txn: &mut TxnContext, log: AuditLog) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl AuditLogRepository for MongoClient {
+    async fn create_audit_log(&self, txn: &mut TxnContext, log: AuditLog) -> ServiceResult<()> {
+        let collection = self.collection::<AuditLog>(AUDIT_LOG_COLLECTION_NAME);
+        in_session!(collection.insert_one(log), txn)?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLogRepository for DocumentDatabase {
+    async fn create_audit_log(&self, txn: &mut TxnContext, log: AuditLog) -> ServiceResult<()> {
+        txn.documents(self)
+            .insert(AUDIT_LOG_COLLECTION_NAME, &log)
+            .await
+    }
+}
@@ -0,0 +1,42 @@
+pub mod schema {
+    pub use paper_schema::auth::*;
+
+    use crate::utils::validate::{ValidatedRequest, trim};
+
+    impl ValidatedRequest for PhoneLogin {
+        fn normalize(&mut self) {
+            trim(&mut self.phone);
+            trim(&mut self.code);
+        }
+    }
+
+    impl ValidatedRequest for EmailRegister {
+        fn normalize(&mut self) {
+            trim(&mut self.email);
+            self.email = self.email.to_lowercase();
+            if let Some(username) = self.username.as_mut() {
+                trim(username);
+            }
+        }
+    }
+
+    impl ValidatedRequest for EmailLogin {
+        fn normalize(&mut self) {
+            trim(&mut self.email);
+            self.email = self.email.to_lowercase();
+        }
+    }
+
+    impl ValidatedRequest for ForgotPassword {
+        fn normalize(&mut self) {
+            trim(&mut self.email);
+            self.email = self.email.to_lowercase();
+        }
+    }
+
+    impl ValidatedRequest for ResetPassword {
+        fn normalize(&mut self) {
+            trim(&mut self.token);
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::{Document, doc};
+use futures::TryStreamExt;
+pub use paper_schema::backup::{BackupKind, BackupStatus};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+        indexes::declared_indexes,
+        paper::Progress,
+    },
+    utils::request_id::current_request_id,
+};
+
+pub mod schema {
+    pub use paper_schema::backup::*;
+
+    use crate::model::backup::BackupJob;
+
+    impl From<BackupJob> for BackupJobResponse {
+        fn from(job: BackupJob) -> Self {
+            BackupJobResponse {
+                id: job.id,
+                kind: job.kind,
+                backup_id: job.backup_id,
+                status: job.status,
+                progress: job.progress,
+                size: job.size,
+                error: job.error,
+                created_at: job.created_at.timestamp_millis(),
+                finished_at: job.finished_at.map(|t| t.timestamp_millis()),
+            }
+        }
+    }
+}
+
+/// Key of the snapshot written by a backup.
+pub fn backup_file_key(backup_id: &str) -> String {
+    format!("backup/{}", backup_id)
+}
+
+// where a restore writes a collection before it replaces the live one
+fn staging_name(collection: &str) -> String {
+    format!("{}_restore", collection)
+}
+
+/// The collections copied by a backup. The backups themselves are left out,
+/// a restore does not forget the snapshots taken since.
+pub const BACKED_UP_COLLECTIONS: &[&str] = &[
+    USER_COLLECTION_NAME,
+    FOLDER_COLLECTION_NAME,
+    PAPER_COLLECTION_NAME,
+    NOTIFICATION_COLLECTION_NAME,
+    AUDIT_LOG_COLLECTION_NAME,
+    BLOCK_COLLECTION_NAME,
+    SHARE_LINK_COLLECTION_NAME,
+    COMMENT_COLLECTION_NAME,
+    PAPER_PAGE_COLLECTION_NAME,
+    PAPER_REVISION_COLLECTION_NAME,
+    PAPER_CHUNK_COLLECTION_NAME,
+    ORGANIZATION_COLLECTION_NAME,
+    CITATION_COLLECTION_NAME,
+    READING_LIST_COLLECTION_NAME,
+    USAGE_EVENT_COLLECTION_NAME,
+    CONSENT_COLLECTION_NAME,
+    PAPER_EMBEDDING_COLLECTION_NAME,
+    WEBHOOK_COLLECTION_NAME,
+    WEBHOOK_DELIVERY_COLLECTION_NAME,
+    PROMPT_TEMPLATE_COLLECTION_NAME,
+    CONVERSATION_COLLECTION_NAME,
+    CONVERSATION_MESSAGE_COLLECTION_NAME,
+    COMPARISON_COLLECTION_NAME,
+    EXPORT_COLLECTION_NAME,
+    JOB_COLLECTION_NAME,
+    CUSTOM_FIELD_COLLECTION_NAME,
+    ACTIVITY_COLLECTION_NAME,
+    IDEMPOTENCY_COLLECTION_NAME,
+    MIGRATION_COLLECTION_NAME,
+    UPLOAD_COLLECTION_NAME,
+    UPLOAD_CHUNK_COLLECTION_NAME,
+    QUARANTINE_COLLECTION_NAME,
+    CONTENT_COLLECTION_NAME,
+    LLM_KEY_COLLECTION_NAME,
+];
+
+/// A snapshot of the database taken, or restored, by an operator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupJob {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String, // uuid of the operator
+    pub created_at: bson::DateTime,
+    pub finished_at: Option<bson::DateTime>,
+
+    pub kind: BackupKind,
+    // uuid of the backup loaded by a restore
+    pub backup_id: Option<String>,
+    pub status: BackupStatus,
+    pub progress: Option<Progress>,
+    pub size: Option<u64>,
+    pub error: Option<String>,
+    // the request which started the job, to trace a failure
+    #[serde(default)]
+    pub request_id: Option<String>,
+}
+
+impl BackupJob {
+    pub fn new(user_id: &str, kind: BackupKind, backup_id: Option<String>) -> Self {
+        BackupJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+            finished_at: None,
+
+            kind,
+            backup_id,
+            status: BackupStatus::Running,
+            progress: None,
+            size: None,
+            error: None,
+            request_id: current_request_id(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait BackupRepository: Send + Sync {
+    async fn create_backup_job(&self, job: BackupJob) -> ServiceResult<()>;
+    async fn get_backup_job(&self, id: &str) -> ServiceResult<Option<BackupJob>>;
+    /// The latest backups and restores first.
+    async fn get_backup_jobs(&self, limit: i64) -> ServiceResult<Vec<BackupJob>>;
+    async fn set_backup_progress(&self, id: &str, progress: Progress) -> ServiceResult<()>;
+    /// Record the end of the job, with the size of the snapshot or the error.
+    async fn finish_backup_job(
+        &self,
+        id: &str,
+        status: BackupStatus,
+        size: Option<u64>,
+        error: Option<String>,
+    ) -> ServiceResult<()>;
+
+    /// Every document of the collections, as of the same point in time.
+    async fn dump_collections(
+        &self,
+        collections: &[&str],
+    ) -> ServiceResult<Vec<(String, Vec<Document>)>>;
+    /// Replace every document of the collections with the ones given, the
+    /// collections are left as they were unless every one is written.
+    async fn replace_collections(
+        &self,
+        collections: Vec<(String, Vec<Document>)>,
+    ) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl BackupRepository for MongoClient {
+    async fn create_backup_job(&self, job: BackupJob) -> ServiceResult<()> {
+        self.collection::<BackupJob>(BACKUP_COLLECTION_NAME)
+            .insert_one(job)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_backup_job(&self, id: &str) -> ServiceResult<Option<BackupJob>> {
+        let job = self
+            .collection::<BackupJob>(BACKUP_COLLECTION_NAME)
+            .find_one(doc! { "_id": id })
+            .await?;
+        Ok(job)
+    }
+
+    async fn get_backup_jobs(&self, limit: i64) -> ServiceResult<Vec<BackupJob>> {
+        let cursor = self
+            .collection::<BackupJob>(BACKUP_COLLECTION_NAME)
+            .find(doc! {})
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await?;
+        let jobs = cursor.try_collect().await?;
+        Ok(jobs)
+    }
+
+    async fn set_backup_progress(&self, id: &str, progress: Progress) -> ServiceResult<()> {
+        self.collection::<BackupJob>(BACKUP_COLLECTION_NAME)
+            .update_one(
+                doc! { "_id": id },
+                doc! { SET_OP: { "progress": bson::to_bson(&progress)? } },
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn finish_backup_job(
+        &self,
+        id: &str,
+        status: BackupStatus,
+        size: Option<u64>,
+        error: Option<String>,
+    ) -> ServiceResult<()> {
+        let update = doc! {
+            SET_OP: {
+                "status": bson::to_bson(&status)?,
+                "size": size.map(|s| s as i64),
+                "error": error,
+                "finished_at": bson::DateTime::now(),
+            }
+        };
+        self.collection::<BackupJob>(BACKUP_COLLECTION_NAME)
+            .update_one(doc! { "_id": id }, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn dump_collections(
+        &self,
+        collections: &[&str],
+    ) -> ServiceResult<Vec<(String, Vec<Document>)>> {
+        // the reads of a snapshot session all see the same majority commit
+        let mut session = self.start_snapshot_session().await?;
+        let mut dumped = Vec::new();
+        for collection in collections {
+            let mut cursor = self
+                .collection::<Document>(collection)
+                .find(doc! {})
+                .session(&mut session)
+                .await?;
+            let docs = cursor.stream(&mut session).try_collect().await?;
+            dumped.push((collection.to_string(), docs));
+        }
+        Ok(dumped)
+    }
+
+    // each collection is written to a staging collection with its indexes,
+    // which are only renamed over the live ones once they are all written
+    async fn replace_collections(
+        &self,
+        collections: Vec<(String, Vec<Document>)>,
+    ) -> ServiceResult<()> {
+        let indexes = declared_indexes();
+        for (name, docs) in &collections {
+            let staging = self.collection::<Document>(&staging_name(name));
+            // left over by a restore which failed
+            staging.drop().await?;
+            if !docs.is_empty() {
+                staging.insert_many(docs).await?;
+            }
+            let models = indexes
+                .iter()
+                .find(|(collection, _)| *collection == name.as_str())
+                .map(|(_, models)| models.clone())
+                .unwrap_or_default();
+            if !models.is_empty() {
+                staging.create_indexes(models).await?;
+            }
+        }
+        for (name, _) in &collections {
+            self.rename_collection(&staging_name(name), name).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl BackupRepository for DocumentDatabase {
+    async fn create_backup_job(&self, job: BackupJob) -> ServiceResult<()> {
+        self.insert(BACKUP_COLLECTION_NAME, &job).await
+    }
+
+    async fn get_backup_job(&self, id: &str) -> ServiceResult<Option<BackupJob>> {
+        self.find_one(BACKUP_COLLECTION_NAME, doc! { "_id": id })
+            .await
+    }
+
+    async fn get_backup_jobs(&self, limit: i64) -> ServiceResult<Vec<BackupJob>> {
+        let query = Query::new(doc! {})
+            .sort(doc! { "created_at": -1 })
+            .limit(limit);
+        self.find(BACKUP_COLLECTION_NAME, query).await
+    }
+
+    async fn set_backup_progress(&self, id: &str, progress: Progress) -> ServiceResult<()> {
+        self.update_one(
+            BACKUP_COLLECTION_NAME,
+            doc! { "_id": id },
+            doc! { SET_OP: { "progress": bson::to_bson(&progress)? } },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn finish_backup_job(
+        &self,
+        id: &str,
+        status: BackupStatus,
+        size: Option<u64>,
+        error: Option<String>,
+    ) -> ServiceResult<()> {
+        let update = doc! {
+            SET_OP: {
+                "status": bson::to_bson(&status)?,
+                "size": size.map(|s| s as i64),
+                "error": error,
+                "finished_at": bson::DateTime::now(),
+            }
+        };
+        self.update_one(BACKUP_COLLECTION_NAME, doc! { "_id": id }, update)
+            .await?;
+        Ok(())
+    }
+
+    // the store has no snapshots, the collections are read one after another
+    async fn dump_collections(
+        &self,
+        collections: &[&str],
+    ) -> ServiceResult<Vec<(String, Vec<Document>)>> {
+        let mut dumped = Vec::new();
+        for collection in collections {
+            let docs = self.find_documents(collection, Query::new(doc! {})).await?;
+            dumped.push((collection.to_string(), docs));
+        }
+        Ok(dumped)
+    }
+
+    async fn replace_collections(
+        &self,
+        collections: Vec<(String, Vec<Document>)>,
+    ) -> ServiceResult<()> {
+        self.replace_all(collections).await
+    }
+}
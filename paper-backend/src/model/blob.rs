@@ -0,0 +1,154 @@
+use std::path::Path;
+
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::{AsyncReadExt, AsyncWriteExt, TryStreamExt};
+use tokio::io::AsyncReadExt as _;
+
+use crate::{
+    error::ServiceResult,
+    model::{constant::*, document::DocumentDatabase},
+    pdf::thumbnail::ThumbnailSize,
+};
+
+// parts of a file read at once when storing it as a blob
+const BLOB_FILE_BUFFER: usize = 256 * 1024;
+
+/// Key of the original file uploaded for a paper, before files were stored
+/// by their content.
+pub fn paper_file_key(paper_id: &str) -> String {
+    format!("paper/{}", paper_id)
+}
+
+/// Key of an uploaded file stored once by its sha256, shared by every paper
+/// holding the same file.
+pub fn content_blob_key(hash: &str) -> String {
+    format!("content/{}", hash)
+}
+
+/// Key of the png of the first page of the file of a paper, in a size.
+pub fn paper_thumbnail_key(paper_id: &str, size: ThumbnailSize) -> String {
+    format!("thumbnail/{}/{}", paper_id, size.as_str())
+}
+
+/// Key of an uploaded file refused by the content scan.
+pub fn quarantine_file_key(quarantine_id: &str) -> String {
+    format!("quarantine/{}", quarantine_id)
+}
+
+/// Binary files (uploaded pdfs...) stored by key in GridFS.
+#[async_trait::async_trait]
+pub trait BlobRepository: Send + Sync {
+    /// Store the blob, replacing any blob with the same key.
+    async fn put_blob(&self, key: &str, bytes: &[u8]) -> ServiceResult<()>;
+    /// Store the file as the blob, read by parts where the store allows it.
+    async fn put_blob_file(&self, key: &str, path: &Path) -> ServiceResult<()>;
+    async fn get_blob(&self, key: &str) -> ServiceResult<Option<Vec<u8>>>;
+    async fn delete_blob(&self, key: &str) -> ServiceResult<()>;
+    /// The keys of every blob stored.
+    async fn list_blobs(&self) -> ServiceResult<Vec<String>>;
+    /// Fails when the store cannot be read.
+    async fn check_blob_store(&self) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl BlobRepository for MongoClient {
+    async fn put_blob(&self, key: &str, bytes: &[u8]) -> ServiceResult<()> {
+        self.delete_blob(key).await?;
+        let bucket = self.gridfs_bucket(BLOB_BUCKET_NAME);
+        let mut stream = bucket.open_upload_stream(key).await?;
+        stream.write_all(bytes).await?;
+        stream.close().await?;
+        Ok(())
+    }
+
+    async fn put_blob_file(&self, key: &str, path: &Path) -> ServiceResult<()> {
+        self.delete_blob(key).await?;
+        let bucket = self.gridfs_bucket(BLOB_BUCKET_NAME);
+        let mut stream = bucket.open_upload_stream(key).await?;
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut buffer = vec![0; BLOB_FILE_BUFFER];
+        loop {
+            let read = file.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            stream.write_all(&buffer[..read]).await?;
+        }
+        stream.close().await?;
+        Ok(())
+    }
+
+    async fn get_blob(&self, key: &str) -> ServiceResult<Option<Vec<u8>>> {
+        let bucket = self.gridfs_bucket(BLOB_BUCKET_NAME);
+        let exists = bucket.find_one(doc! { "filename": key }).await?.is_some();
+        if !exists {
+            return Ok(None);
+        }
+        let mut stream = bucket.open_download_stream_by_name(key).await?;
+        let mut bytes = Vec::new();
+        stream.read_to_end(&mut bytes).await?;
+        Ok(Some(bytes))
+    }
+
+    async fn delete_blob(&self, key: &str) -> ServiceResult<()> {
+        let bucket = self.gridfs_bucket(BLOB_BUCKET_NAME);
+        let files = bucket
+            .find(doc! { "filename": key })
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        for file in files {
+            bucket.delete(file.id).await?;
+        }
+        Ok(())
+    }
+
+    async fn list_blobs(&self) -> ServiceResult<Vec<String>> {
+        let bucket = self.gridfs_bucket(BLOB_BUCKET_NAME);
+        let files = bucket.find(doc! {}).await?.try_collect::<Vec<_>>().await?;
+        let mut keys = files
+            .into_iter()
+            .filter_map(|file| file.filename)
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+
+    async fn check_blob_store(&self) -> ServiceResult<()> {
+        let bucket = self.gridfs_bucket(BLOB_BUCKET_NAME);
+        bucket.find_one(doc! {}).await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl BlobRepository for DocumentDatabase {
+    async fn put_blob(&self, key: &str, bytes: &[u8]) -> ServiceResult<()> {
+        self.store().put_blob(key, bytes).await
+    }
+
+    // the stores keep a blob in a single row
+    async fn put_blob_file(&self, key: &str, path: &Path) -> ServiceResult<()> {
+        let bytes = tokio::fs::read(path).await?;
+        self.store().put_blob(key, &bytes).await
+    }
+
+    async fn get_blob(&self, key: &str) -> ServiceResult<Option<Vec<u8>>> {
+        self.store().get_blob(key).await
+    }
+
+    async fn delete_blob(&self, key: &str) -> ServiceResult<()> {
+        self.store().delete_blob(key).await
+    }
+
+    async fn list_blobs(&self) -> ServiceResult<Vec<String>> {
+        self.store().list_blobs().await
+    }
+
+    async fn check_blob_store(&self) -> ServiceResult<()> {
+        self.store().get_blob("").await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::block::*;
+
+    use crate::{
+        model::block::Block,
+        utils::validate::{ValidatedRequest, trim},
+    };
+
+    impl From<Block> for BlockResponse {
+        fn from(block: Block) -> Self {
+            BlockResponse {
+                reference: block.reference(),
+                id: block.id,
+                name: block.name,
+                content: block.content,
+            }
+        }
+    }
+
+    impl ValidatedRequest for CreateBlockRequest {
+        fn normalize(&mut self) {
+            trim(&mut self.name);
+        }
+    }
+
+    impl ValidatedRequest for UpdateBlockRequest {
+        fn normalize(&mut self) {
+            if let Some(name) = self.name.as_mut() {
+                trim(name);
+            }
+        }
+    }
+}
+
+/// A reusable content snippet (author bio, method boilerplate, acknowledgments...),
+/// referenced from papers as `{{block:<id>}}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Block {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String, // uuid of the user who owns this block
+    pub created_at: bson::DateTime,
+    pub updated_at: bson::DateTime,
+
+    pub name: String,
+    pub content: String,
+}
+
+impl Block {
+    pub fn new_from_request(user_id: &str, request: schema::CreateBlockRequest) -> Self {
+        Block {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+            updated_at: bson::DateTime::now(),
+
+            name: request.name,
+            content: request.content,
+        }
+    }
+
+    pub fn reference(&self) -> String {
+        format!("{}{}{}", BLOCK_REF_PREFIX, self.id, BLOCK_REF_SUFFIX)
+    }
+}
+
+pub const BLOCK_REF_PREFIX: &str = "{{block:";
+pub const BLOCK_REF_SUFFIX: &str = "}}";
+
+/// Ids of all blocks referenced in the text.
+pub fn referenced_block_ids(text: &str) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(BLOCK_REF_PREFIX) {
+        rest = &rest[start + BLOCK_REF_PREFIX.len()..];
+        let Some(end) = rest.find(BLOCK_REF_SUFFIX) else {
+            break;
+        };
+        let id = rest[..end].trim().to_string();
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+        rest = &rest[end + BLOCK_REF_SUFFIX.len()..];
+    }
+    ids
+}
+
+/// Replace block references with their content, unknown references are kept as is.
+pub fn expand_blocks(text: &str, blocks: &[Block]) -> String {
+    let mut text = text.to_string();
+    for block in blocks {
+        text = text.replace(&block.reference(), &block.content);
+    }
+    text
+}
+
+#[async_trait::async_trait]
+pub trait BlockRepository: Send + Sync {
+    async fn create_block(&self, block: Block) -> ServiceResult<()>;
+    async fn get_block_by_id(&self, id: &str) -> ServiceResult<Option<Block>>;
+    async fn get_blocks_by_user_id(&self, user_id: &str) -> ServiceResult<Vec<Block>>;
+    async fn get_blocks_by_ids(&self, user_id: &str, ids: &[String]) -> ServiceResult<Vec<Block>>;
+    async fn update_block(&self, block: Block) -> ServiceResult<Block>;
+    async fn delete_block(&self, id: &str) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl BlockRepository for MongoClient {
+    async fn create_block(&self, block: Block) -> ServiceResult<()> {
+        self.collection::<Block>(BLOCK_COLLECTION_NAME)
+            .insert_one(block)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_block_by_id(&self, id: &str) -> ServiceResult<Option<Block>> {
+        let filter = doc! { "_id": id };
+        let result = self
+            .collection::<Block>(BLOCK_COLLECTION_NAME)
+            .find_one(filter)
+            .await?;
+        Ok(result)
+    }
+
+    async fn get_blocks_by_user_id(&self, user_id: &str) -> ServiceResult<Vec<Block>> {
+        let filter = doc! { "user_id": user_id };
+        let cursor = self
+            .collection::<Block>(BLOCK_COLLECTION_NAME)
+            .find(filter)
+            .await?;
+        let blocks = cursor.try_collect().await?;
+        Ok(blocks)
+    }
+
+    async fn get_blocks_by_ids(&self, user_id: &str, ids: &[String]) -> ServiceResult<Vec<Block>> {
+        // only the blocks of the user can be transcluded
+        let filter = doc! { "user_id": user_id, "_id": { IN_OP: ids } };
+        let cursor = self
+            .collection::<Block>(BLOCK_COLLECTION_NAME)
+            .find(filter)
+            .await?;
+        let blocks = cursor.try_collect().await?;
+        Ok(blocks)
+    }
+
+    async fn update_block(&self, block: Block) -> ServiceResult<Block> {
+        let filter = doc! { "_id": &block.id };
+        let update = doc! {
+            SET_OP: bson::to_bson(&block)?,
+        };
+        self.collection::<Block>(BLOCK_COLLECTION_NAME)
+            .update_one(filter, update)
+            .await?;
+        Ok(block)
+    }
+
+    async fn delete_block(&self, id: &str) -> ServiceResult<()> {
+        let filter = doc! { "_id": id };
+        self.collection::<Block>(BLOCK_COLLECTION_NAME)
+            .delete_one(filter)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockRepository for DocumentDatabase {
+    async fn create_block(&self, block: Block) -> ServiceResult<()> {
+        self.insert(BLOCK_COLLECTION_NAME, &block).await
+    }
+
+    async fn get_block_by_id(&self, id: &str) -> ServiceResult<Option<Block>> {
+        self.find_one(BLOCK_COLLECTION_NAME, doc! { "_id": id })
+            .await
+    }
+
+    async fn get_blocks_by_user_id(&self, user_id: &str) -> ServiceResult<Vec<Block>> {
+        let query = Query::new(doc! { "user_id": user_id });
+        self.find(BLOCK_COLLECTION_NAME, query).await
+    }
+
+    async fn get_blocks_by_ids(&self, user_id: &str, ids: &[String]) -> ServiceResult<Vec<Block>> {
+        let query = Query::new(doc! { "user_id": user_id, "_id": { IN_OP: ids } });
+        self.find(BLOCK_COLLECTION_NAME, query).await
+    }
+
+    async fn update_block(&self, block: Block) -> ServiceResult<Block> {
+        let filter = doc! { "_id": &block.id };
+        let update = doc! {
+            SET_OP: bson::to_bson(&block)?,
+        };
+        self.update_one(BLOCK_COLLECTION_NAME, filter, update)
+            .await?;
+        Ok(block)
+    }
+
+    async fn delete_block(&self, id: &str) -> ServiceResult<()> {
+        self.delete_one(BLOCK_COLLECTION_NAME, doc! { "_id": id })
+            .await?;
+        Ok(())
+    }
+}
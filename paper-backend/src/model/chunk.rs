@@ -0,0 +1,171 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ServiceResult, is_duplicate_key},
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::chunk::*;
+
+    use crate::utils::validate::{ValidatedRequest, trim, trim_option};
+
+    impl ValidatedRequest for AskPaperRequest {
+        fn normalize(&mut self) {
+            trim(&mut self.question);
+            trim_option(&mut self.model);
+        }
+    }
+}
+
+/// A passage of the extracted text of a paper, never spanning two pages, with
+/// its embedding when an embedder is configured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperChunk {
+    #[serde(rename = "_id")]
+    pub id: String, // `{paper_id}:{index}`
+    pub paper_id: String,
+    // hash of the file the text was extracted from, the chunks of a replaced
+    // file are stale
+    pub file_hash: String,
+    pub index: u32,
+    pub page: u32, // 1 based
+
+    pub text: String,
+    // empty when computed without embedder
+    #[serde(default)]
+    pub vector: Vec<f32>,
+}
+
+impl PaperChunk {
+    pub fn new(paper_id: &str, file_hash: &str, index: u32, page: u32, text: String) -> Self {
+        PaperChunk {
+            id: format!("{}:{}", paper_id, index),
+            paper_id: paper_id.to_string(),
+            file_hash: file_hash.to_string(),
+            index,
+            page,
+
+            text,
+            vector: Vec::new(),
+        }
+    }
+}
+
+/// Claim of the computation of the chunks of a paper, so concurrent requests
+/// don't embed and write them twice. Expires when its holder died.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkClaim {
+    #[serde(rename = "_id")]
+    pub paper_id: String,
+    pub file_hash: String,
+    pub claimed_at: bson::DateTime,
+}
+
+impl ChunkClaim {
+    pub fn new(paper_id: &str, file_hash: &str) -> Self {
+        ChunkClaim {
+            paper_id: paper_id.to_string(),
+            file_hash: file_hash.to_string(),
+            claimed_at: bson::DateTime::now(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait PaperChunkRepository: Send + Sync {
+    /// Claim the computation of the chunks of the paper, false when another
+    /// request holds the claim.
+    async fn claim_paper_chunks(&self, claim: ChunkClaim) -> ServiceResult<bool>;
+    /// Release the claim once the chunks are stored, or their computation failed.
+    async fn release_paper_chunks(&self, paper_id: &str) -> ServiceResult<()>;
+    /// Replace all the chunks of the paper.
+    async fn replace_paper_chunks(
+        &self,
+        paper_id: &str,
+        chunks: Vec<PaperChunk>,
+    ) -> ServiceResult<()>;
+    /// The chunks of the paper, in reading order.
+    async fn get_paper_chunks(&self, paper_id: &str) -> ServiceResult<Vec<PaperChunk>>;
+}
+
+#[async_trait::async_trait]
+impl PaperChunkRepository for MongoClient {
+    async fn claim_paper_chunks(&self, claim: ChunkClaim) -> ServiceResult<bool> {
+        match self
+            .collection::<ChunkClaim>(CHUNK_CLAIM_COLLECTION_NAME)
+            .insert_one(claim)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn release_paper_chunks(&self, paper_id: &str) -> ServiceResult<()> {
+        self.collection::<ChunkClaim>(CHUNK_CLAIM_COLLECTION_NAME)
+            .delete_one(doc! { "_id": paper_id })
+            .await?;
+        Ok(())
+    }
+
+    async fn replace_paper_chunks(
+        &self,
+        paper_id: &str,
+        chunks: Vec<PaperChunk>,
+    ) -> ServiceResult<()> {
+        let collection = self.collection::<PaperChunk>(PAPER_CHUNK_COLLECTION_NAME);
+        collection
+            .delete_many(doc! { "paper_id": paper_id })
+            .await?;
+        if !chunks.is_empty() {
+            collection.insert_many(chunks).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_paper_chunks(&self, paper_id: &str) -> ServiceResult<Vec<PaperChunk>> {
+        let cursor = self
+            .collection::<PaperChunk>(PAPER_CHUNK_COLLECTION_NAME)
+            .find(doc! { "paper_id": paper_id })
+            .sort(doc! { "index": 1 })
+            .await?;
+        let chunks = cursor.try_collect().await?;
+        Ok(chunks)
+    }
+}
+
+#[async_trait::async_trait]
+impl PaperChunkRepository for DocumentDatabase {
+    async fn claim_paper_chunks(&self, claim: ChunkClaim) -> ServiceResult<bool> {
+        self.try_insert(CHUNK_CLAIM_COLLECTION_NAME, &claim).await
+    }
+
+    async fn release_paper_chunks(&self, paper_id: &str) -> ServiceResult<()> {
+        self.delete_one(CHUNK_CLAIM_COLLECTION_NAME, doc! { "_id": paper_id })
+            .await?;
+        Ok(())
+    }
+
+    async fn replace_paper_chunks(
+        &self,
+        paper_id: &str,
+        chunks: Vec<PaperChunk>,
+    ) -> ServiceResult<()> {
+        self.delete_many(PAPER_CHUNK_COLLECTION_NAME, doc! { "paper_id": paper_id })
+            .await?;
+        self.insert_many(PAPER_CHUNK_COLLECTION_NAME, &chunks).await
+    }
+
+    async fn get_paper_chunks(&self, paper_id: &str) -> ServiceResult<Vec<PaperChunk>> {
+        let query = Query::new(doc! { "paper_id": paper_id }).sort(doc! { "index": 1 });
+        self.find(PAPER_CHUNK_COLLECTION_NAME, query).await
+    }
+}
@@ -0,0 +1,243 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+        txn::{TxnContext, in_session},
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::citation::*;
+
+    use crate::model::citation::Citation;
+
+    impl From<Citation> for CitationResponse {
+        fn from(citation: Citation) -> Self {
+            CitationResponse {
+                citing_id: citation.citing_id,
+                cited_id: citation.cited_id,
+                raw: citation.raw,
+                doi: citation.doi,
+            }
+        }
+    }
+}
+
+/// A reference from one paper to another, the cited paper is only known
+/// when it is in the library of the user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Citation {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String,
+    pub citing_id: String, // uuid of the paper containing the reference
+    pub cited_id: Option<String>,
+    pub created_at: bson::DateTime,
+
+    pub raw: String,
+    pub doi: Option<String>,
+}
+
+impl Citation {
+    pub fn new(user_id: &str, citing_id: &str, raw: String, doi: Option<String>) -> Self {
+        Citation {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            citing_id: citing_id.to_string(),
+            cited_id: None,
+            created_at: bson::DateTime::now(),
+
+            raw,
+            doi,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait CitationRepository: Send + Sync {
+    /// Replace all the references of the citing paper.
+    async fn replace_citations(
+        &self,
+        citing_id: &str,
+        citations: Vec<Citation>,
+    ) -> ServiceResult<()>;
+    async fn get_citations_by_citing_id(&self, citing_id: &str) -> ServiceResult<Vec<Citation>>;
+    async fn get_citations_by_cited_id(&self, cited_id: &str) -> ServiceResult<Vec<Citation>>;
+    /// The citations between papers of the library of the user.
+    async fn get_library_citations(&self, user_id: &str) -> ServiceResult<Vec<Citation>>;
+    /// Delete the references of the deleted paper, the references to it are
+    /// kept as references outside of the library.
+    async fn delete_paper_citations(&self, paper_id: &str) -> ServiceResult<()>;
+    /// Move the references of the merged papers, and to them, over to the
+    /// surviving paper.
+    async fn reassign_citations(
+        &self,
+        txn: &mut TxnContext,
+        paper_ids: &[String],
+        survivor_id: &str,
+    ) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl CitationRepository for MongoClient {
+    async fn replace_citations(
+        &self,
+        citing_id: &str,
+        citations: Vec<Citation>,
+    ) -> ServiceResult<()> {
+        let collection = self.collection::<Citation>(CITATION_COLLECTION_NAME);
+        collection
+            .delete_many(doc! { "citing_id": citing_id })
+            .await?;
+        if !citations.is_empty() {
+            collection.insert_many(citations).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_citations_by_citing_id(&self, citing_id: &str) -> ServiceResult<Vec<Citation>> {
+        let filter = doc! { "citing_id": citing_id };
+        let cursor = self
+            .collection::<Citation>(CITATION_COLLECTION_NAME)
+            .find(filter)
+            .await?;
+        let citations = cursor.try_collect().await?;
+        Ok(citations)
+    }
+
+    async fn get_citations_by_cited_id(&self, cited_id: &str) -> ServiceResult<Vec<Citation>> {
+        let filter = doc! { "cited_id": cited_id };
+        let cursor = self
+            .collection::<Citation>(CITATION_COLLECTION_NAME)
+            .find(filter)
+            .await?;
+        let citations = cursor.try_collect().await?;
+        Ok(citations)
+    }
+
+    async fn get_library_citations(&self, user_id: &str) -> ServiceResult<Vec<Citation>> {
+        let filter = doc! { "user_id": user_id, "cited_id": { NE_OP: null } };
+        let cursor = self
+            .collection::<Citation>(CITATION_COLLECTION_NAME)
+            .find(filter)
+            .await?;
+        let citations = cursor.try_collect().await?;
+        Ok(citations)
+    }
+
+    async fn delete_paper_citations(&self, paper_id: &str) -> ServiceResult<()> {
+        let collection = self.collection::<Citation>(CITATION_COLLECTION_NAME);
+        collection
+            .delete_many(doc! { "citing_id": paper_id })
+            .await?;
+        collection
+            .update_many(
+                doc! { "cited_id": paper_id },
+                doc! { SET_OP: { "cited_id": null } },
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn reassign_citations(
+        &self,
+        txn: &mut TxnContext,
+        paper_ids: &[String],
+        survivor_id: &str,
+    ) -> ServiceResult<()> {
+        let collection = self.collection::<Citation>(CITATION_COLLECTION_NAME);
+        in_session!(
+            collection.update_many(
+                doc! { "citing_id": { IN_OP: paper_ids } },
+                doc! { SET_OP: { "citing_id": survivor_id } },
+            ),
+            txn
+        )?;
+        in_session!(
+            collection.update_many(
+                doc! { "cited_id": { IN_OP: paper_ids } },
+                doc! { SET_OP: { "cited_id": survivor_id } },
+            ),
+            txn
+        )?;
+        // a duplicate citing another one now cites itself
+        in_session!(
+            collection.delete_many(doc! { "citing_id": survivor_id, "cited_id": survivor_id }),
+            txn
+        )?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl CitationRepository for DocumentDatabase {
+    async fn replace_citations(
+        &self,
+        citing_id: &str,
+        citations: Vec<Citation>,
+    ) -> ServiceResult<()> {
+        self.delete_many(CITATION_COLLECTION_NAME, doc! { "citing_id": citing_id })
+            .await?;
+        self.insert_many(CITATION_COLLECTION_NAME, &citations).await
+    }
+
+    async fn get_citations_by_citing_id(&self, citing_id: &str) -> ServiceResult<Vec<Citation>> {
+        let query = Query::new(doc! { "citing_id": citing_id });
+        self.find(CITATION_COLLECTION_NAME, query).await
+    }
+
+    async fn get_citations_by_cited_id(&self, cited_id: &str) -> ServiceResult<Vec<Citation>> {
+        let query = Query::new(doc! { "cited_id": cited_id });
+        self.find(CITATION_COLLECTION_NAME, query).await
+    }
+
+    async fn get_library_citations(&self, user_id: &str) -> ServiceResult<Vec<Citation>> {
+        let query = Query::new(doc! { "user_id": user_id, "cited_id": { NE_OP: null } });
+        self.find(CITATION_COLLECTION_NAME, query).await
+    }
+
+    async fn delete_paper_citations(&self, paper_id: &str) -> ServiceResult<()> {
+        self.delete_many(CITATION_COLLECTION_NAME, doc! { "citing_id": paper_id })
+            .await?;
+        self.update_many(
+            CITATION_COLLECTION_NAME,
+            doc! { "cited_id": paper_id },
+            doc! { SET_OP: { "cited_id": null } },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn reassign_citations(
+        &self,
+        txn: &mut TxnContext,
+        paper_ids: &[String],
+        survivor_id: &str,
+    ) -> ServiceResult<()> {
+        let db = txn.documents(self);
+        db.update_many(
+            CITATION_COLLECTION_NAME,
+            doc! { "citing_id": { IN_OP: paper_ids } },
+            doc! { SET_OP: { "citing_id": survivor_id } },
+        )
+        .await?;
+        db.update_many(
+            CITATION_COLLECTION_NAME,
+            doc! { "cited_id": { IN_OP: paper_ids } },
+            doc! { SET_OP: { "cited_id": survivor_id } },
+        )
+        .await?;
+        db.delete_many(
+            CITATION_COLLECTION_NAME,
+            doc! { "citing_id": survivor_id, "cited_id": survivor_id },
+        )
+        .await?;
+        Ok(())
+    }
+}
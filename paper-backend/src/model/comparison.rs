@@ -0,0 +1,175 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+pub use paper_schema::comparison::ComparedPaper;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::comparison::*;
+
+    use crate::{
+        model::comparison::Comparison,
+        utils::validate::{ValidatedRequest, trim_all, trim_option},
+    };
+
+    impl ValidatedRequest for ComparePapersRequest {
+        fn normalize(&mut self) {
+            trim_all(&mut self.paper_ids);
+            trim_option(&mut self.model);
+        }
+    }
+
+    impl From<Comparison> for ComparisonResponse {
+        fn from(comparison: Comparison) -> Self {
+            ComparisonResponse {
+                id: comparison.id,
+                paper_ids: comparison.paper_ids,
+                summary: comparison.summary,
+                papers: comparison.papers,
+                contradictions: comparison.contradictions,
+                model: comparison.model,
+                created_at: comparison.created_at.timestamp_millis(),
+                generated_at: comparison.generated_at.timestamp_millis(),
+            }
+        }
+    }
+}
+
+/// A comparison of 2 to 5 papers written by the model, kept to be read again,
+/// regenerated or exported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comparison {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String,
+    pub created_at: bson::DateTime,
+    // last time the model wrote the comparison
+    pub generated_at: bson::DateTime,
+
+    pub paper_ids: Vec<String>,
+    pub model: String,
+    pub summary: String,
+    pub papers: Vec<ComparedPaper>,
+    pub contradictions: Vec<String>,
+}
+
+impl Comparison {
+    pub fn new(user_id: &str, paper_ids: Vec<String>, model: String) -> Self {
+        let now = bson::DateTime::now();
+        Comparison {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            created_at: now,
+            generated_at: now,
+
+            paper_ids,
+            model,
+            summary: String::new(),
+            papers: Vec::new(),
+            contradictions: Vec::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait ComparisonRepository: Send + Sync {
+    async fn create_comparison(&self, comparison: Comparison) -> ServiceResult<()>;
+    async fn get_comparison(&self, user_id: &str, id: &str) -> ServiceResult<Option<Comparison>>;
+    /// The latest comparisons of the user, newest first.
+    async fn get_comparisons(&self, user_id: &str, limit: i64) -> ServiceResult<Vec<Comparison>>;
+    async fn update_comparison(&self, comparison: Comparison) -> ServiceResult<()>;
+    /// Delete the comparison, false when the user has no such comparison.
+    async fn delete_comparison(&self, user_id: &str, id: &str) -> ServiceResult<bool>;
+}
+
+#[async_trait::async_trait]
+impl ComparisonRepository for MongoClient {
+    async fn create_comparison(&self, comparison: Comparison) -> ServiceResult<()> {
+        self.collection::<Comparison>(COMPARISON_COLLECTION_NAME)
+            .insert_one(comparison)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_comparison(&self, user_id: &str, id: &str) -> ServiceResult<Option<Comparison>> {
+        let comparison = self
+            .collection::<Comparison>(COMPARISON_COLLECTION_NAME)
+            .find_one(doc! { "_id": id, "user_id": user_id })
+            .await?;
+        Ok(comparison)
+    }
+
+    async fn get_comparisons(&self, user_id: &str, limit: i64) -> ServiceResult<Vec<Comparison>> {
+        let cursor = self
+            .collection::<Comparison>(COMPARISON_COLLECTION_NAME)
+            .find(doc! { "user_id": user_id })
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await?;
+        let comparisons = cursor.try_collect().await?;
+        Ok(comparisons)
+    }
+
+    async fn update_comparison(&self, comparison: Comparison) -> ServiceResult<()> {
+        let filter = doc! { "_id": &comparison.id, "user_id": &comparison.user_id };
+        self.collection::<Comparison>(COMPARISON_COLLECTION_NAME)
+            .replace_one(filter, comparison)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_comparison(&self, user_id: &str, id: &str) -> ServiceResult<bool> {
+        let result = self
+            .collection::<Comparison>(COMPARISON_COLLECTION_NAME)
+            .delete_one(doc! { "_id": id, "user_id": user_id })
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+}
+
+#[async_trait::async_trait]
+impl ComparisonRepository for DocumentDatabase {
+    async fn create_comparison(&self, comparison: Comparison) -> ServiceResult<()> {
+        self.insert(COMPARISON_COLLECTION_NAME, &comparison).await
+    }
+
+    async fn get_comparison(&self, user_id: &str, id: &str) -> ServiceResult<Option<Comparison>> {
+        self.find_one(
+            COMPARISON_COLLECTION_NAME,
+            doc! { "_id": id, "user_id": user_id },
+        )
+        .await
+    }
+
+    async fn get_comparisons(&self, user_id: &str, limit: i64) -> ServiceResult<Vec<Comparison>> {
+        let query = Query::new(doc! { "user_id": user_id })
+            .sort(doc! { "created_at": -1 })
+            .limit(limit);
+        self.find(COMPARISON_COLLECTION_NAME, query).await
+    }
+
+    async fn update_comparison(&self, comparison: Comparison) -> ServiceResult<()> {
+        let filter = doc! { "_id": &comparison.id, "user_id": &comparison.user_id };
+        self.replace_one(COMPARISON_COLLECTION_NAME, filter, &comparison, false)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_comparison(&self, user_id: &str, id: &str) -> ServiceResult<bool> {
+        let deleted = self
+            .delete_one(
+                COMPARISON_COLLECTION_NAME,
+                doc! { "_id": id, "user_id": user_id },
+            )
+            .await?;
+        Ok(deleted > 0)
+    }
+}
@@ -0,0 +1,99 @@
+use ai_flow_synth::utils::MongoClient;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{LegalConfig, LegalDocument},
+    error::ServiceResult,
+    model::{constant::*, document::DocumentDatabase, user::User},
+};
+
+pub mod schema {
+    pub use paper_schema::consent::*;
+
+    use crate::{
+        config::LegalDocument,
+        utils::validate::{ValidatedRequest, trim_option},
+    };
+
+    impl From<&LegalDocument> for LegalDocumentResponse {
+        fn from(document: &LegalDocument) -> Self {
+            LegalDocumentResponse {
+                version: document.version.clone(),
+                url: document.url.clone(),
+            }
+        }
+    }
+
+    impl ValidatedRequest for ConsentRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.terms_version);
+            trim_option(&mut self.privacy_version);
+        }
+    }
+}
+
+/// A consent given by a user, kept as a trail for compliance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentRecord {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String,
+    pub created_at: bson::DateTime,
+
+    pub terms_version: Option<String>,
+    pub privacy_version: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl ConsentRecord {
+    pub fn new(user: &User, ip: Option<String>) -> Self {
+        ConsentRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user.uid.clone(),
+            created_at: bson::DateTime::now(),
+
+            terms_version: user.terms_version.clone(),
+            privacy_version: user.privacy_version.clone(),
+            ip,
+        }
+    }
+}
+
+/// Documents whose current version the user has not accepted yet.
+pub fn pending_consents(user: &User, config: &LegalConfig) -> Vec<String> {
+    let outdated = |accepted: &Option<String>, document: &Option<LegalDocument>| {
+        document
+            .as_ref()
+            .is_some_and(|d| accepted.as_deref() != Some(d.version.as_str()))
+    };
+    let mut pending = Vec::new();
+    if outdated(&user.terms_version, &config.terms) {
+        pending.push("terms".to_string());
+    }
+    if outdated(&user.privacy_version, &config.privacy_policy) {
+        pending.push("privacyPolicy".to_string());
+    }
+    pending
+}
+
+#[async_trait::async_trait]
+pub trait ConsentRepository: Send + Sync {
+    async fn create_consent_record(&self, record: ConsentRecord) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl ConsentRepository for MongoClient {
+    async fn create_consent_record(&self, record: ConsentRecord) -> ServiceResult<()> {
+        self.collection::<ConsentRecord>(CONSENT_COLLECTION_NAME)
+            .insert_one(record)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ConsentRepository for DocumentDatabase {
+    async fn create_consent_record(&self, record: ConsentRecord) -> ServiceResult<()> {
+        self.insert(CONSENT_COLLECTION_NAME, &record).await
+    }
+}
@@ -1,8 +1,69 @@
 // collection names
 pub const USER_COLLECTION_NAME: &str = "users";
 pub const FOLDER_COLLECTION_NAME: &str = "folders";
+pub const PAPER_COLLECTION_NAME: &str = "papers";
+pub const NOTIFICATION_COLLECTION_NAME: &str = "notifications";
+pub const AUDIT_LOG_COLLECTION_NAME: &str = "audit_logs";
+pub const BLOCK_COLLECTION_NAME: &str = "blocks";
+pub const SHARE_LINK_COLLECTION_NAME: &str = "share_links";
+pub const COMMENT_COLLECTION_NAME: &str = "comments";
+pub const PAPER_PAGE_COLLECTION_NAME: &str = "paper_pages";
+pub const PAPER_CHUNK_COLLECTION_NAME: &str = "paper_chunks";
+pub const CHUNK_CLAIM_COLLECTION_NAME: &str = "paper_chunk_claims";
+pub const ORGANIZATION_COLLECTION_NAME: &str = "organizations";
+pub const CITATION_COLLECTION_NAME: &str = "citations";
+pub const READING_LIST_COLLECTION_NAME: &str = "reading_list";
+pub const USAGE_EVENT_COLLECTION_NAME: &str = "usage_events";
+pub const CONSENT_COLLECTION_NAME: &str = "consents";
+pub const PAPER_EMBEDDING_COLLECTION_NAME: &str = "paper_embeddings";
+pub const WEBHOOK_COLLECTION_NAME: &str = "webhooks";
+pub const WEBHOOK_DELIVERY_COLLECTION_NAME: &str = "webhook_deliveries";
+pub const PROMPT_TEMPLATE_COLLECTION_NAME: &str = "prompt_templates";
+pub const CONVERSATION_COLLECTION_NAME: &str = "conversations";
+pub const CONVERSATION_MESSAGE_COLLECTION_NAME: &str = "conversation_messages";
+pub const COMPARISON_COLLECTION_NAME: &str = "comparisons";
+pub const EXPORT_COLLECTION_NAME: &str = "exports";
+pub const CUSTOM_FIELD_COLLECTION_NAME: &str = "custom_fields";
+pub const ACTIVITY_COLLECTION_NAME: &str = "activities";
+pub const IDEMPOTENCY_COLLECTION_NAME: &str = "idempotency_keys";
+pub const MIGRATION_COLLECTION_NAME: &str = "_migrations";
+pub const MIGRATION_LOCK_COLLECTION_NAME: &str = "_migration_lock";
+pub const BACKUP_COLLECTION_NAME: &str = "backups";
+pub const JOB_COLLECTION_NAME: &str = "jobs";
+pub const PAPER_REVISION_COLLECTION_NAME: &str = "paper_revisions";
+pub const UPLOAD_COLLECTION_NAME: &str = "uploads";
+pub const QUARANTINE_COLLECTION_NAME: &str = "quarantine";
+pub const UPLOAD_CHUNK_COLLECTION_NAME: &str = "upload_chunks";
+pub const CONTENT_COLLECTION_NAME: &str = "file_contents";
+pub const LLM_KEY_COLLECTION_NAME: &str = "llm_keys";
+pub const LOGIN_ATTEMPT_COLLECTION_NAME: &str = "login_attempts";
+// gridfs bucket
+pub const BLOB_BUCKET_NAME: &str = "blobs";
 
 // OPERATIONS
 pub const SET_OP: &str = "$set";
+pub const UNSET_OP: &str = "$unset";
 pub const LTE_OP: &str = "$lte";
+pub const LT_OP: &str = "$lt";
 pub const GTE_OP: &str = "$gte";
+pub const IN_OP: &str = "$in";
+pub const NE_OP: &str = "$ne";
+pub const TEXT_OP: &str = "$text";
+pub const NIN_OP: &str = "$nin";
+pub const SUM_OP: &str = "$sum";
+pub const ADD_TO_SET_OP: &str = "$addToSet";
+pub const EACH_OP: &str = "$each";
+pub const INC_OP: &str = "$inc";
+pub const OR_OP: &str = "$or";
+pub const PULL_OP: &str = "$pull";
+pub const AND_OP: &str = "$and";
+pub const ALL_OP: &str = "$all";
+pub const EXISTS_OP: &str = "$exists";
+pub const TYPE_OP: &str = "$type";
+pub const SET_ON_INSERT_OP: &str = "$setOnInsert";
+
+// aggregation stages
+pub const MATCH_STAGE: &str = "$match";
+pub const GROUP_STAGE: &str = "$group";
+pub const SORT_STAGE: &str = "$sort";
+pub const LIMIT_STAGE: &str = "$limit";
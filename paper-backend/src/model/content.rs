@@ -0,0 +1,203 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use mongodb::options::ReturnDocument;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        blob::{BlobRepository, content_blob_key},
+        constant::*,
+        database::Database,
+        document::DocumentDatabase,
+    },
+};
+
+/// Hex sha256 of a file, the key of its content.
+pub fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// The references to a file stored once by its content, whichever papers and
+/// users uploaded it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentRef {
+    #[serde(rename = "_id")]
+    pub id: String, // sha256, the bytes are in the `content/{id}` blob
+    // papers holding the file
+    pub refs: i64,
+    pub size: u64,
+    pub created_at: bson::DateTime,
+}
+
+#[async_trait::async_trait]
+pub trait ContentRepository: Send + Sync {
+    /// Count one more reference to the content, true when it is the first one
+    /// and the blob is to be stored.
+    async fn acquire_content(&self, hash: &str, size: u64) -> ServiceResult<bool>;
+    /// Count one reference less to the content, true when it was the last one
+    /// and the blob is to be deleted. The last reference deletes the count in
+    /// the same write, an upload of the content meanwhile counts from scratch.
+    async fn release_content(&self, hash: &str) -> ServiceResult<bool>;
+}
+
+#[async_trait::async_trait]
+impl ContentRepository for MongoClient {
+    async fn acquire_content(&self, hash: &str, size: u64) -> ServiceResult<bool> {
+        let content = self
+            .collection::<ContentRef>(CONTENT_COLLECTION_NAME)
+            .find_one_and_update(
+                doc! { "_id": hash },
+                doc! {
+                    INC_OP: { "refs": 1 },
+                    SET_ON_INSERT_OP: {
+                        "size": size as i64,
+                        "created_at": bson::DateTime::now(),
+                    },
+                },
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await?;
+        Ok(content.is_none_or(|content| content.refs <= 1))
+    }
+
+    async fn release_content(&self, hash: &str) -> ServiceResult<bool> {
+        let collection = self.collection::<ContentRef>(CONTENT_COLLECTION_NAME);
+        loop {
+            let result = collection
+                .delete_one(doc! { "_id": hash, "refs": { LTE_OP: 1 } })
+                .await?;
+            if result.deleted_count > 0 {
+                return Ok(true);
+            }
+            let result = collection
+                .update_one(
+                    doc! { "_id": hash, "refs": { GTE_OP: 2 } },
+                    doc! { INC_OP: { "refs": -1 } },
+                )
+                .await?;
+            if result.matched_count > 0 {
+                return Ok(false);
+            }
+            // released by others down to the last reference in between
+            let content = collection.find_one(doc! { "_id": hash }).await?;
+            if content.is_none() {
+                return Ok(false);
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ContentRepository for DocumentDatabase {
+    async fn acquire_content(&self, hash: &str, size: u64) -> ServiceResult<bool> {
+        let content = ContentRef {
+            id: hash.to_string(),
+            refs: 1,
+            size,
+            created_at: bson::DateTime::now(),
+        };
+        if self.try_insert(CONTENT_COLLECTION_NAME, &content).await? {
+            return Ok(true);
+        }
+        self.update_one(
+            CONTENT_COLLECTION_NAME,
+            doc! { "_id": hash },
+            doc! { INC_OP: { "refs": 1 } },
+        )
+        .await?;
+        let content = self
+            .find_one::<ContentRef>(CONTENT_COLLECTION_NAME, doc! { "_id": hash })
+            .await?;
+        Ok(content.is_none_or(|content| content.refs <= 1))
+    }
+
+    async fn release_content(&self, hash: &str) -> ServiceResult<bool> {
+        loop {
+            let deleted = self
+                .delete_one(
+                    CONTENT_COLLECTION_NAME,
+                    doc! { "_id": hash, "refs": { LTE_OP: 1 } },
+                )
+                .await?;
+            if deleted > 0 {
+                return Ok(true);
+            }
+            let matched = self
+                .update_one(
+                    CONTENT_COLLECTION_NAME,
+                    doc! { "_id": hash, "refs": { GTE_OP: 2 } },
+                    doc! { INC_OP: { "refs": -1 } },
+                )
+                .await?;
+            if matched > 0 {
+                return Ok(false);
+            }
+            // released by others down to the last reference in between
+            let content = self
+                .find_one::<ContentRef>(CONTENT_COLLECTION_NAME, doc! { "_id": hash })
+                .await?;
+            if content.is_none() {
+                return Ok(false);
+            }
+        }
+    }
+}
+
+/// Store the file by its content, only once whoever uploads it, and return its
+/// hash. Every call is a reference released with `release_file`.
+pub async fn store_file(db: &dyn Database, bytes: &[u8]) -> ServiceResult<String> {
+    let hash = content_hash(bytes);
+    if db.acquire_content(&hash, bytes.len() as u64).await? {
+        if let Err(e) = db.put_blob(&content_blob_key(&hash), bytes).await {
+            // not stored, the next upload of the content is the first again
+            if let Err(e) = release_file(db, &hash).await {
+                tracing::warn!("Failed to release the content {}: {}", hash, e);
+            }
+            return Err(e);
+        }
+    }
+    Ok(hash)
+}
+
+/// Take one more reference to a stored file for a copy, without storing it
+/// again. False when the file is not stored.
+pub async fn share_file(db: &dyn Database, hash: &str) -> ServiceResult<bool> {
+    if db.acquire_content(hash, 0).await? {
+        // nobody held it
+        release_file(db, hash).await?;
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Release a reference to the file, deleting it with the last one.
+pub async fn release_file(db: &dyn Database, hash: &str) -> ServiceResult<()> {
+    if db.release_content(hash).await? {
+        db.delete_blob(&content_blob_key(hash)).await?;
+    }
+    Ok(())
+}
+
+/// The bytes of the file stored by its content.
+pub async fn get_file(db: &dyn Database, hash: &str) -> ServiceResult<Option<Vec<u8>>> {
+    db.get_blob(&content_blob_key(hash)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(
+            content_hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,452 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+pub use paper_schema::conversation::MessageRole;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ServiceError, ServiceResult},
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+        version_filter,
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::conversation::*;
+
+    use crate::{
+        model::conversation::{Conversation, ConversationMessage},
+        utils::validate::{ValidatedRequest, trim, trim_option},
+    };
+
+    impl From<Conversation> for ConversationResponse {
+        fn from(conversation: Conversation) -> Self {
+            ConversationResponse {
+                id: conversation.id,
+                title: conversation.title,
+                pinned: conversation.pinned,
+                message_count: conversation.message_count,
+                created_at: conversation.created_at.timestamp_millis(),
+                updated_at: conversation.updated_at.timestamp_millis(),
+            }
+        }
+    }
+
+    impl From<ConversationMessage> for MessageResponse {
+        fn from(message: ConversationMessage) -> Self {
+            MessageResponse {
+                id: message.id,
+                conversation_id: message.conversation_id,
+                role: message.role,
+                content: message.content,
+                model: message.model,
+                created_at: message.created_at.timestamp_millis(),
+            }
+        }
+    }
+
+    impl ValidatedRequest for CreateConversationRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.title);
+        }
+    }
+
+    impl ValidatedRequest for UpdateConversationRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.title);
+        }
+    }
+
+    impl ValidatedRequest for SendMessageRequest {
+        fn normalize(&mut self) {
+            trim(&mut self.content);
+            trim_option(&mut self.model);
+        }
+    }
+}
+
+// auto generated titles are cut at a word boundary around this length
+const TITLE_MAX_CHARS: usize = 60;
+
+/// A chat of a user with the model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String,
+    pub created_at: bson::DateTime,
+    // time of the last message, conversations are listed by it
+    pub updated_at: bson::DateTime,
+
+    pub title: Option<String>,
+    pub pinned: bool,
+    pub message_count: u32,
+    // incremented on every update, guards against concurrent writes
+    #[serde(default)]
+    pub version: u32,
+}
+
+/// A message of a conversation, stored apart so the history can be paged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationMessage {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub conversation_id: String,
+    pub user_id: String,
+    pub created_at: bson::DateTime,
+
+    pub role: MessageRole,
+    pub content: String,
+    // model which wrote the message, for assistant messages
+    pub model: Option<String>,
+}
+
+/// A message matching a full text search, with its text relevance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoredMessage {
+    #[serde(flatten)]
+    pub message: ConversationMessage,
+    pub score: f64,
+}
+
+impl Conversation {
+    pub fn new(user_id: &str, title: Option<String>) -> Self {
+        Conversation {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+            updated_at: bson::DateTime::now(),
+
+            title,
+            pinned: false,
+            message_count: 0,
+            version: 0,
+        }
+    }
+}
+
+impl ConversationMessage {
+    pub fn new(conversation: &Conversation, role: MessageRole, content: String) -> Self {
+        ConversationMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            conversation_id: conversation.id.clone(),
+            user_id: conversation.user_id.clone(),
+            created_at: bson::DateTime::now(),
+
+            role,
+            content,
+            model: None,
+        }
+    }
+}
+
+/// Title of a conversation from its first message: the first line, cut at a
+/// word boundary when too long.
+pub fn title_from_message(content: &str) -> String {
+    let line = content
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .unwrap_or_default();
+    let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= TITLE_MAX_CHARS {
+        return line;
+    }
+    let cut: String = line.chars().take(TITLE_MAX_CHARS).collect();
+    let cut = match cut.rfind(' ') {
+        Some(end) if end > TITLE_MAX_CHARS / 2 => &cut[..end],
+        _ => cut.as_str(),
+    };
+    format!("{}…", cut.trim_end_matches([',', '.', ';', ':']))
+}
+
+fn version_conflict(conversation: &Conversation) -> ServiceError {
+    ServiceError::VersionConflict(format!(
+        "Conversation {} was modified concurrently",
+        conversation.id
+    ))
+}
+
+#[async_trait::async_trait]
+pub trait ConversationRepository: Send + Sync {
+    async fn create_conversation(&self, conversation: Conversation) -> ServiceResult<()>;
+    async fn get_conversation(
+        &self,
+        user_id: &str,
+        id: &str,
+    ) -> ServiceResult<Option<Conversation>>;
+    /// Conversations of the user, pinned first, then the most recently active.
+    async fn get_conversations(
+        &self,
+        user_id: &str,
+        limit: i64,
+    ) -> ServiceResult<Vec<Conversation>>;
+    async fn get_conversations_by_ids(
+        &self,
+        user_id: &str,
+        ids: &[String],
+    ) -> ServiceResult<Vec<Conversation>>;
+    /// Fails with a version conflict when the conversation changed since it was read.
+    async fn update_conversation(&self, conversation: Conversation) -> ServiceResult<Conversation>;
+    /// Delete the conversation with its messages.
+    async fn delete_conversation(&self, user_id: &str, id: &str) -> ServiceResult<()>;
+    async fn create_messages(&self, messages: Vec<ConversationMessage>) -> ServiceResult<()>;
+    /// The latest messages of the conversation sent before the time, newest first.
+    async fn get_messages(
+        &self,
+        conversation_id: &str,
+        before: Option<bson::DateTime>,
+        limit: i64,
+    ) -> ServiceResult<Vec<ConversationMessage>>;
+    async fn search_messages(
+        &self,
+        user_id: &str,
+        query: &str,
+        limit: i64,
+    ) -> ServiceResult<Vec<ScoredMessage>>;
+}
+
+#[async_trait::async_trait]
+impl ConversationRepository for MongoClient {
+    async fn create_conversation(&self, conversation: Conversation) -> ServiceResult<()> {
+        self.collection::<Conversation>(CONVERSATION_COLLECTION_NAME)
+            .insert_one(conversation)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_conversation(
+        &self,
+        user_id: &str,
+        id: &str,
+    ) -> ServiceResult<Option<Conversation>> {
+        let filter = doc! { "_id": id, "user_id": user_id };
+        let result = self
+            .collection::<Conversation>(CONVERSATION_COLLECTION_NAME)
+            .find_one(filter)
+            .await?;
+        Ok(result)
+    }
+
+    async fn get_conversations(
+        &self,
+        user_id: &str,
+        limit: i64,
+    ) -> ServiceResult<Vec<Conversation>> {
+        let cursor = self
+            .collection::<Conversation>(CONVERSATION_COLLECTION_NAME)
+            .find(doc! { "user_id": user_id })
+            .sort(doc! { "pinned": -1, "updated_at": -1 })
+            .limit(limit)
+            .await?;
+        let conversations = cursor.try_collect().await?;
+        Ok(conversations)
+    }
+
+    async fn get_conversations_by_ids(
+        &self,
+        user_id: &str,
+        ids: &[String],
+    ) -> ServiceResult<Vec<Conversation>> {
+        let filter = doc! { "user_id": user_id, "_id": { IN_OP: ids } };
+        let cursor = self
+            .collection::<Conversation>(CONVERSATION_COLLECTION_NAME)
+            .find(filter)
+            .await?;
+        let conversations = cursor.try_collect().await?;
+        Ok(conversations)
+    }
+
+    async fn update_conversation(
+        &self,
+        mut conversation: Conversation,
+    ) -> ServiceResult<Conversation> {
+        let mut filter = version_filter(&conversation.id, conversation.version);
+        filter.insert("user_id", &conversation.user_id);
+        conversation.version += 1;
+        let result = self
+            .collection::<Conversation>(CONVERSATION_COLLECTION_NAME)
+            .replace_one(filter, &conversation)
+            .await?;
+        if result.matched_count == 0 {
+            return Err(version_conflict(&conversation));
+        }
+        Ok(conversation)
+    }
+
+    async fn delete_conversation(&self, user_id: &str, id: &str) -> ServiceResult<()> {
+        let filter = doc! { "_id": id, "user_id": user_id };
+        self.collection::<Conversation>(CONVERSATION_COLLECTION_NAME)
+            .delete_one(filter)
+            .await?;
+        self.collection::<ConversationMessage>(CONVERSATION_MESSAGE_COLLECTION_NAME)
+            .delete_many(doc! { "conversation_id": id, "user_id": user_id })
+            .await?;
+        Ok(())
+    }
+
+    async fn create_messages(&self, messages: Vec<ConversationMessage>) -> ServiceResult<()> {
+        self.collection::<ConversationMessage>(CONVERSATION_MESSAGE_COLLECTION_NAME)
+            .insert_many(messages)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_messages(
+        &self,
+        conversation_id: &str,
+        before: Option<bson::DateTime>,
+        limit: i64,
+    ) -> ServiceResult<Vec<ConversationMessage>> {
+        let mut filter = doc! { "conversation_id": conversation_id };
+        if let Some(before) = before {
+            filter.insert("created_at", doc! { LT_OP: before });
+        }
+        let cursor = self
+            .collection::<ConversationMessage>(CONVERSATION_MESSAGE_COLLECTION_NAME)
+            .find(filter)
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .limit(limit)
+            .await?;
+        let messages = cursor.try_collect().await?;
+        Ok(messages)
+    }
+
+    async fn search_messages(
+        &self,
+        user_id: &str,
+        query: &str,
+        limit: i64,
+    ) -> ServiceResult<Vec<ScoredMessage>> {
+        let filter = doc! { "user_id": user_id, TEXT_OP: { "$search": query } };
+        let score = doc! { "score": { "$meta": "textScore" } };
+        let cursor = self
+            .collection::<ScoredMessage>(CONVERSATION_MESSAGE_COLLECTION_NAME)
+            .find(filter)
+            .projection(score.clone())
+            .sort(score)
+            .limit(limit)
+            .await?;
+        let messages = cursor.try_collect().await?;
+        Ok(messages)
+    }
+}
+
+#[async_trait::async_trait]
+impl ConversationRepository for DocumentDatabase {
+    async fn create_conversation(&self, conversation: Conversation) -> ServiceResult<()> {
+        self.insert(CONVERSATION_COLLECTION_NAME, &conversation)
+            .await
+    }
+
+    async fn get_conversation(
+        &self,
+        user_id: &str,
+        id: &str,
+    ) -> ServiceResult<Option<Conversation>> {
+        let filter = doc! { "_id": id, "user_id": user_id };
+        self.find_one(CONVERSATION_COLLECTION_NAME, filter).await
+    }
+
+    async fn get_conversations(
+        &self,
+        user_id: &str,
+        limit: i64,
+    ) -> ServiceResult<Vec<Conversation>> {
+        let query = Query::new(doc! { "user_id": user_id })
+            .sort(doc! { "pinned": -1, "updated_at": -1 })
+            .limit(limit);
+        self.find(CONVERSATION_COLLECTION_NAME, query).await
+    }
+
+    async fn get_conversations_by_ids(
+        &self,
+        user_id: &str,
+        ids: &[String],
+    ) -> ServiceResult<Vec<Conversation>> {
+        let query = Query::new(doc! { "user_id": user_id, "_id": { IN_OP: ids } });
+        self.find(CONVERSATION_COLLECTION_NAME, query).await
+    }
+
+    async fn update_conversation(
+        &self,
+        mut conversation: Conversation,
+    ) -> ServiceResult<Conversation> {
+        let mut filter = version_filter(&conversation.id, conversation.version);
+        filter.insert("user_id", &conversation.user_id);
+        conversation.version += 1;
+        let matched = self
+            .replace_one(CONVERSATION_COLLECTION_NAME, filter, &conversation, false)
+            .await?;
+        if matched == 0 {
+            return Err(version_conflict(&conversation));
+        }
+        Ok(conversation)
+    }
+
+    async fn delete_conversation(&self, user_id: &str, id: &str) -> ServiceResult<()> {
+        let filter = doc! { "_id": id, "user_id": user_id };
+        self.delete_one(CONVERSATION_COLLECTION_NAME, filter)
+            .await?;
+        self.delete_many(
+            CONVERSATION_MESSAGE_COLLECTION_NAME,
+            doc! { "conversation_id": id, "user_id": user_id },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn create_messages(&self, messages: Vec<ConversationMessage>) -> ServiceResult<()> {
+        self.insert_many(CONVERSATION_MESSAGE_COLLECTION_NAME, &messages)
+            .await
+    }
+
+    async fn get_messages(
+        &self,
+        conversation_id: &str,
+        before: Option<bson::DateTime>,
+        limit: i64,
+    ) -> ServiceResult<Vec<ConversationMessage>> {
+        let mut filter = doc! { "conversation_id": conversation_id };
+        if let Some(before) = before {
+            filter.insert("created_at", doc! { LT_OP: before });
+        }
+        let query = Query::new(filter)
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .limit(limit);
+        self.find(CONVERSATION_MESSAGE_COLLECTION_NAME, query).await
+    }
+
+    async fn search_messages(
+        &self,
+        user_id: &str,
+        query: &str,
+        limit: i64,
+    ) -> ServiceResult<Vec<ScoredMessage>> {
+        let filter = doc! { "user_id": user_id, TEXT_OP: { "$search": query } };
+        let search = Query::new(filter).text_score().limit(limit);
+        self.find(CONVERSATION_MESSAGE_COLLECTION_NAME, search)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_from_message() {
+        assert_eq!(
+            title_from_message("\n  What is   RAG?\nMore"),
+            "What is RAG?"
+        );
+        let long = "Summarize the main findings of the attention paper, and compare them \
+            with the earlier recurrent models";
+        let title = title_from_message(long);
+        assert_eq!(
+            title,
+            "Summarize the main findings of the attention paper, and…"
+        );
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::BTreeMap;
+
+use ai_flow_synth::utils::MongoClient;
+use bson::{Bson, Document, doc};
+use futures::TryStreamExt;
+pub use paper_schema::custom_field::{FieldType, FieldValue};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ServiceError, ServiceResult},
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+// max characters of a text value
+const MAX_TEXT_CHARS: usize = 1000;
+
+pub mod schema {
+    pub use paper_schema::custom_field::*;
+
+    use std::collections::HashSet;
+
+    use crate::{
+        model::custom_field::CustomField,
+        utils::validate::{ValidatedRequest, trim, trim_all},
+    };
+
+    impl ValidatedRequest for CreateCustomFieldRequest {
+        fn normalize(&mut self) {
+            trim(&mut self.key);
+            trim(&mut self.name);
+            trim_all(&mut self.options);
+            let mut seen = HashSet::new();
+            self.options
+                .retain(|option| !option.is_empty() && seen.insert(option.clone()));
+        }
+    }
+
+    impl From<CustomField> for CustomFieldResponse {
+        fn from(field: CustomField) -> Self {
+            CustomFieldResponse {
+                id: field.id,
+                key: field.key,
+                name: field.name,
+                r#type: field.field_type,
+                options: field.options,
+                team: field.team,
+                created_at: field.created_at.timestamp_millis(),
+            }
+        }
+    }
+}
+
+/// A field the papers of the user, or of the organization, can be given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomField {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    // uuid of the user, or of the organization for team fields
+    pub owner_id: String,
+    pub team: bool,
+    pub created_at: bson::DateTime,
+
+    // name in the map of the papers
+    pub key: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+impl CustomField {
+    pub fn new_from_request(owner_id: &str, request: schema::CreateCustomFieldRequest) -> Self {
+        CustomField {
+            id: uuid::Uuid::new_v4().to_string(),
+            owner_id: owner_id.to_string(),
+            team: request.team,
+            created_at: bson::DateTime::now(),
+
+            key: request.key,
+            name: request.name,
+            field_type: request.r#type,
+            options: request.options,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait CustomFieldRepository: Send + Sync {
+    async fn create_custom_field(&self, field: CustomField) -> ServiceResult<()>;
+    /// Fields of the user and of their organization, by key.
+    async fn get_custom_fields(
+        &self,
+        user_id: &str,
+        org_id: Option<&str>,
+    ) -> ServiceResult<Vec<CustomField>>;
+    async fn get_custom_field(&self, id: &str) -> ServiceResult<Option<CustomField>>;
+    /// Delete the field with its values on the papers of its owner, of every
+    /// member of the organization for a team field.
+    async fn delete_custom_field(&self, field: &CustomField) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl CustomFieldRepository for MongoClient {
+    async fn create_custom_field(&self, field: CustomField) -> ServiceResult<()> {
+        self.collection::<CustomField>(CUSTOM_FIELD_COLLECTION_NAME)
+            .insert_one(field)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_custom_fields(
+        &self,
+        user_id: &str,
+        org_id: Option<&str>,
+    ) -> ServiceResult<Vec<CustomField>> {
+        let owners = std::iter::once(user_id).chain(org_id).collect::<Vec<_>>();
+        let fields = self
+            .collection::<CustomField>(CUSTOM_FIELD_COLLECTION_NAME)
+            .find(doc! { "owner_id": { IN_OP: owners } })
+            .sort(doc! { "key": 1 })
+            .await?
+            .try_collect()
+            .await?;
+        Ok(fields)
+    }
+
+    async fn get_custom_field(&self, id: &str) -> ServiceResult<Option<CustomField>> {
+        let field = self
+            .collection::<CustomField>(CUSTOM_FIELD_COLLECTION_NAME)
+            .find_one(doc! { "_id": id })
+            .await?;
+        Ok(field)
+    }
+
+    async fn delete_custom_field(&self, field: &CustomField) -> ServiceResult<()> {
+        let user_ids = match field.team {
+            true => self
+                .collection::<Document>(USER_COLLECTION_NAME)
+                .find(doc! { "org_id": &field.owner_id })
+                .projection(doc! { "_id": 1 })
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?
+                .iter()
+                .filter_map(|user| user.get_str("_id").ok().map(str::to_string))
+                .collect(),
+            false => vec![field.owner_id.clone()],
+        };
+        let (filter, update) = unset_field_values(field, &user_ids);
+        self.collection::<Document>(PAPER_COLLECTION_NAME)
+            .update_many(filter, update)
+            .await?;
+        self.collection::<CustomField>(CUSTOM_FIELD_COLLECTION_NAME)
+            .delete_one(doc! { "_id": &field.id })
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl CustomFieldRepository for DocumentDatabase {
+    async fn create_custom_field(&self, field: CustomField) -> ServiceResult<()> {
+        self.insert(CUSTOM_FIELD_COLLECTION_NAME, &field).await
+    }
+
+    async fn get_custom_fields(
+        &self,
+        user_id: &str,
+        org_id: Option<&str>,
+    ) -> ServiceResult<Vec<CustomField>> {
+        let owners = std::iter::once(user_id).chain(org_id).collect::<Vec<_>>();
+        let query = Query::new(doc! { "owner_id": { IN_OP: owners } }).sort(doc! { "key": 1 });
+        self.find(CUSTOM_FIELD_COLLECTION_NAME, query).await
+    }
+
+    async fn get_custom_field(&self, id: &str) -> ServiceResult<Option<CustomField>> {
+        self.find_one(CUSTOM_FIELD_COLLECTION_NAME, doc! { "_id": id })
+            .await
+    }
+
+    async fn delete_custom_field(&self, field: &CustomField) -> ServiceResult<()> {
+        let user_ids = match field.team {
+            true => self
+                .find_documents(
+                    USER_COLLECTION_NAME,
+                    Query::new(doc! { "org_id": &field.owner_id }),
+                )
+                .await?
+                .iter()
+                .filter_map(|user| user.get_str("_id").ok().map(str::to_string))
+                .collect(),
+            false => vec![field.owner_id.clone()],
+        };
+        let (filter, update) = unset_field_values(field, &user_ids);
+        self.update_many(PAPER_COLLECTION_NAME, filter, update)
+            .await?;
+        self.delete_one(CUSTOM_FIELD_COLLECTION_NAME, doc! { "_id": &field.id })
+            .await?;
+        Ok(())
+    }
+}
+
+/// Filter and update removing the values of the field from the papers of the users.
+fn unset_field_values(field: &CustomField, user_ids: &[String]) -> (Document, Document) {
+    let path = format!("custom_fields.{}", field.key);
+    let filter = doc! {
+        "user_id": { IN_OP: user_ids },
+        path.as_str(): { EXISTS_OP: true },
+    };
+    (filter, doc! { UNSET_OP: { path: "" } })
+}
+
+/// Why the value does not fit the type of the field.
+fn check_value(field: &CustomField, value: &FieldValue) -> Result<(), String> {
+    match (field.field_type, value) {
+        (FieldType::Number, FieldValue::Number(n)) if n.is_finite() => Ok(()),
+        (FieldType::Number, _) => Err("must be a number".to_string()),
+        (_, FieldValue::Number(_)) => Err("must be a string".to_string()),
+        (FieldType::Text, FieldValue::Text(text)) => match text.chars().count() {
+            n if n > MAX_TEXT_CHARS => {
+                Err(format!("must be at most {} characters", MAX_TEXT_CHARS))
+            }
+            _ => Ok(()),
+        },
+        (FieldType::Date, FieldValue::Text(date)) => {
+            chrono::NaiveDate::parse_from_str(date, DATE_FORMAT)
+                .map(|_| ())
+                .map_err(|_| "must be a date as YYYY-MM-DD".to_string())
+        }
+        (FieldType::Select, FieldValue::Text(option)) => match field.options.contains(option) {
+            true => Ok(()),
+            false => Err(format!("must be one of {}", field.options.join(", "))),
+        },
+    }
+}
+
+fn find_field<'a>(
+    fields: &'a [CustomField],
+    key: &str,
+    param: &str,
+) -> ServiceResult<&'a CustomField> {
+    fields.iter().find(|field| field.key == key).ok_or_else(|| {
+        ServiceError::invalid_field(
+            param,
+            "unknown_field",
+            format!("Unknown custom field {}", key),
+        )
+    })
+}
+
+/// Apply the changes to the custom field values of a paper, a null value
+/// removes the field. Fails on unknown fields and values not fitting their type.
+pub fn apply_values(
+    values: &mut BTreeMap<String, FieldValue>,
+    changes: BTreeMap<String, Option<FieldValue>>,
+    fields: &[CustomField],
+) -> ServiceResult<()> {
+    for (key, value) in changes {
+        match value {
+            Some(value) => {
+                let field = find_field(fields, &key, "customFields")?;
+                check_value(field, &value).map_err(|message| {
+                    ServiceError::invalid_field(
+                        "customFields",
+                        "invalid_value",
+                        format!("{} {}", key, message),
+                    )
+                })?;
+                values.insert(key, value);
+            }
+            None => {
+                values.remove(&key);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Filter on the custom fields of the papers from comma separated conditions
+/// `key=value`, `key>=value` or `key<=value`, all of which must match.
+pub fn field_filter(expr: &str, fields: &[CustomField]) -> ServiceResult<Option<Document>> {
+    let invalid = |message: String| ServiceError::invalid_field("field", "invalid_filter", message);
+    let mut conditions = Vec::new();
+    for condition in expr.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+        let Some(i) = condition.find(['<', '>', '=']) else {
+            return Err(invalid(format!("{} has no operator", condition)));
+        };
+        let (key, rest) = condition.split_at(i);
+        let (op, value) = if let Some(value) = rest.strip_prefix(">=") {
+            (Some(GTE_OP), value)
+        } else if let Some(value) = rest.strip_prefix("<=") {
+            (Some(LTE_OP), value)
+        } else if let Some(value) = rest.strip_prefix('=') {
+            (None, value)
+        } else {
+            return Err(invalid(format!("{} has an unknown operator", condition)));
+        };
+        let field = find_field(fields, key.trim(), "field")?;
+        let value = value.trim();
+        let value = match field.field_type {
+            FieldType::Number => value
+                .parse::<f64>()
+                .map(FieldValue::Number)
+                .map_err(|_| invalid(format!("{} must be a number", field.key)))?,
+            _ => FieldValue::Text(value.to_string()),
+        };
+        check_value(field, &value)
+            .map_err(|message| invalid(format!("{} {}", field.key, message)))?;
+        let value = match value {
+            FieldValue::Number(n) => Bson::Double(n),
+            FieldValue::Text(text) => Bson::String(text),
+        };
+        let path = format!("custom_fields.{}", field.key);
+        conditions.push(match op {
+            Some(op) => doc! { path: { op: value } },
+            None => doc! { path: value },
+        });
+    }
+    Ok((!conditions.is_empty()).then(|| doc! { AND_OP: conditions }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::validate::ValidatedRequest;
+
+    fn field(key: &str, field_type: FieldType, options: &[&str]) -> CustomField {
+        CustomField {
+            id: key.to_string(),
+            owner_id: "user".to_string(),
+            team: false,
+            created_at: bson::DateTime::now(),
+            key: key.to_string(),
+            name: key.to_string(),
+            field_type,
+            options: options.iter().map(|o| o.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_apply_values() {
+        let fields = vec![
+            field("pages", FieldType::Number, &[]),
+            field("read_on", FieldType::Date, &[]),
+            field("status", FieldType::Select, &["todo", "done"]),
+        ];
+        let mut values = BTreeMap::from([("pages".to_string(), FieldValue::Number(12.0))]);
+        let changes = BTreeMap::from([
+            ("pages".to_string(), None),
+            (
+                "read_on".to_string(),
+                Some(FieldValue::Text("2024-02-29".to_string())),
+            ),
+            (
+                "status".to_string(),
+                Some(FieldValue::Text("done".to_string())),
+            ),
+        ]);
+        apply_values(&mut values, changes, &fields).unwrap();
+        assert_eq!(values.len(), 2);
+        assert!(!values.contains_key("pages"));
+
+        for (key, value) in [
+            ("status", FieldValue::Text("later".to_string())),
+            ("read_on", FieldValue::Text("2023-02-29".to_string())),
+            ("pages", FieldValue::Text("12".to_string())),
+            ("unknown", FieldValue::Number(1.0)),
+        ] {
+            let changes = BTreeMap::from([(key.to_string(), Some(value))]);
+            assert!(apply_values(&mut values, changes, &fields).is_err());
+        }
+    }
+
+    #[test]
+    fn test_field_filter() {
+        let fields = vec![
+            field("pages", FieldType::Number, &[]),
+            field("status", FieldType::Select, &["todo", "done"]),
+        ];
+        let filter = field_filter("status=done, pages>=10", &fields)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            filter,
+            doc! { AND_OP: [
+                { "custom_fields.status": "done" },
+                { "custom_fields.pages": { GTE_OP: 10.0 } },
+            ] }
+        );
+        assert!(field_filter("", &fields).unwrap().is_none());
+        assert!(field_filter("pages>=many", &fields).is_err());
+        assert!(field_filter("status", &fields).is_err());
+        assert!(field_filter("other=1", &fields).is_err());
+    }
+
+    #[test]
+    fn test_options_are_deduplicated() {
+        let mut request = schema::CreateCustomFieldRequest {
+            key: "status".to_string(),
+            name: "Status".to_string(),
+            r#type: FieldType::Select,
+            options: ["todo", "done ", "", "todo", "done"]
+                .map(String::from)
+                .to_vec(),
+            team: false,
+        };
+        request.normalize();
+        assert_eq!(request.options, vec!["todo", "done"]);
+    }
+}
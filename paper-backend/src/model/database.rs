@@ -0,0 +1,296 @@
+use std::sync::Arc;
+
+use ai_flow_synth::utils::{MongoClient, MongoConfig};
+
+use crate::{
+    config::{Config, TenantConfig},
+    error::{ServiceError, ServiceResult},
+    model::{
+        account::AccountRepository, activity::ActivityRepository, audit::AuditLogRepository,
+        backup::BackupRepository, blob::BlobRepository, block::BlockRepository,
+        chunk::PaperChunkRepository, citation::CitationRepository,
+        comparison::ComparisonRepository, consent::ConsentRepository, content::ContentRepository,
+        conversation::ConversationRepository, custom_field::CustomFieldRepository,
+        document::DocumentDatabase, embedding::PaperEmbeddingRepository, export::ExportRepository,
+        folder::FolderRepository, idempotency::IdempotencyRepository, indexes, job::JobRepository,
+        llm_key::LlmKeyRepository, login_attempt::LoginAttemptRepository,
+        migration::MigrationRepository, notification::NotificationRepository,
+        organization::OrganizationRepository, page::PaperPageRepository, paper::PaperRepository,
+        prompt::PromptTemplateRepository, quarantine::QuarantineRepository,
+        reading_list::ReadingListRepository, revision::PaperRevisionRepository,
+        share::ShareRepository, stats::StatsRepository, txn::TxnContext, upload::UploadRepository,
+        usage::UsageRepository, user::UserRepository, webhook::WebhookRepository,
+    },
+};
+
+/// Every repository, implemented by each database backend: mongo, or a
+/// [`DocumentDatabase`] over postgres.
+#[async_trait::async_trait]
+pub trait Database:
+    AccountRepository
+    + ActivityRepository
+    + AuditLogRepository
+    + BackupRepository
+    + BlobRepository
+    + BlockRepository
+    + PaperChunkRepository
+    + CitationRepository
+    + ComparisonRepository
+    + ConsentRepository
+    + ContentRepository
+    + ConversationRepository
+    + CustomFieldRepository
+    + PaperEmbeddingRepository
+    + ExportRepository
+    + FolderRepository
+    + IdempotencyRepository
+    + JobRepository
+    + LlmKeyRepository
+    + LoginAttemptRepository
+    + MigrationRepository
+    + NotificationRepository
+    + OrganizationRepository
+    + PaperPageRepository
+    + PaperRepository
+    + PromptTemplateRepository
+    + QuarantineRepository
+    + ReadingListRepository
+    + PaperRevisionRepository
+    + ShareRepository
+    + StatsRepository
+    + UploadRepository
+    + UsageRepository
+    + UserRepository
+    + WebhookRepository
+    + std::fmt::Debug
+    + Send
+    + Sync
+{
+    /// Fails when the database cannot be reached.
+    async fn ping(&self) -> ServiceResult<()>;
+    /// Create the declared indexes missing from the database.
+    async fn ensure_indexes(&self) -> ServiceResult<()>;
+    /// Start a transaction, see [`in_transaction`](super::txn::in_transaction).
+    async fn begin_txn(&self) -> ServiceResult<TxnContext>;
+    /// Close the connections, waiting for the running operations.
+    async fn shutdown(&self);
+}
+
+#[async_trait::async_trait]
+impl Database for MongoClient {
+    async fn ping(&self) -> ServiceResult<()> {
+        Ok(MongoClient::ping(self).await?)
+    }
+
+    async fn ensure_indexes(&self) -> ServiceResult<()> {
+        indexes::ensure_all(self).await
+    }
+
+    async fn begin_txn(&self) -> ServiceResult<TxnContext> {
+        let mut session = self.start_session().await?;
+        session.start_transaction().await?;
+        Ok(TxnContext::started(session))
+    }
+
+    async fn shutdown(&self) {
+        MongoClient::shutdown(self).await;
+    }
+}
+
+#[async_trait::async_trait]
+impl Database for DocumentDatabase {
+    async fn ping(&self) -> ServiceResult<()> {
+        self.store().ping().await
+    }
+
+    async fn ensure_indexes(&self) -> ServiceResult<()> {
+        DocumentDatabase::ensure_indexes(self).await
+    }
+
+    // the writes apply one by one on a store without transactions
+    async fn begin_txn(&self) -> ServiceResult<TxnContext> {
+        match self.store().begin().await? {
+            Some(store) => Ok(TxnContext::in_store(DocumentDatabase::new(store))),
+            None => Ok(TxnContext::none()),
+        }
+    }
+
+    async fn shutdown(&self) {
+        self.store().shutdown().await;
+    }
+}
+
+/// Connect to the database of the config: postgres when `postgres_config` is
+/// set, mongo otherwise.
+pub async fn connect(config: &Config) -> ServiceResult<Arc<dyn Database>> {
+    connect_database(config, None).await
+}
+
+/// Connect to the database of the tenant, on the server of the config.
+pub async fn connect_tenant(
+    config: &Config,
+    tenant: &TenantConfig,
+) -> ServiceResult<Arc<dyn Database>> {
+    connect_database(config, Some(&tenant.database())).await
+}
+
+/// The postgres url with its database replaced.
+#[cfg(feature = "postgres")]
+fn with_database(url: &str, database: &str) -> String {
+    let (url, params) = match url.split_once('?') {
+        Some((url, params)) => (url, format!("?{}", params)),
+        None => (url, String::new()),
+    };
+    let authority = url.find("://").map_or(0, |i| i + 3);
+    let path = url[authority..]
+        .find('/')
+        .map_or(url.len(), |i| authority + i);
+    format!("{}/{}{}", &url[..path], database, params)
+}
+
+async fn connect_database(
+    config: &Config,
+    database: Option<&str>,
+) -> ServiceResult<Arc<dyn Database>> {
+    match (&config.postgres_config, &config.mongo_config) {
+        #[cfg(feature = "postgres")]
+        (Some(postgres_config), _) => {
+            let postgres_config = crate::config::PostgresConfig {
+                url: match database {
+                    Some(database) => with_database(&postgres_config.url, database),
+                    None => postgres_config.url.clone(),
+                },
+                ..postgres_config.clone()
+            };
+            let store = super::document::postgres::PostgresStore::new(&postgres_config).await?;
+            Ok(Arc::new(DocumentDatabase::new(Arc::new(store))))
+        }
+        #[cfg(not(feature = "postgres"))]
+        (Some(_), _) => Err(ServiceError::InternalServerError(
+            "`postgres_config` needs the `postgres` feature".to_string(),
+        )),
+        (None, Some(mongo_config)) => {
+            let mongo_config = MongoConfig {
+                db_name: database.unwrap_or(&mongo_config.db_name).to_string(),
+                ..mongo_config.clone()
+            };
+            let client = MongoClient::new(&mongo_config)
+                .await
+                .map_err(|e| ServiceError::InternalServerError(format!("MongoDB error: {}", e)))?;
+            Ok(Arc::new(client))
+        }
+        (None, None) => Err(ServiceError::InternalServerError(
+            "Neither `mongo_config` nor `postgres_config` is set".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        i18n::Locale,
+        model::{
+            audit::{AuditAction, AuditLog},
+            folder::Folder,
+            paper::Paper,
+            txn::in_transaction,
+        },
+    };
+
+    // the backends given by the environment, the others are skipped
+    async fn backends() -> Vec<Arc<dyn Database>> {
+        let mut backends: Vec<Arc<dyn Database>> = Vec::new();
+        if let Ok(uri) = std::env::var("PAPER_TEST_MONGO_URI") {
+            let config = MongoConfig {
+                uri,
+                db_name: "paper_test".to_string(),
+                ..Default::default()
+            };
+            backends.push(Arc::new(MongoClient::new(&config).await.unwrap()));
+        }
+        #[cfg(feature = "postgres")]
+        if let Ok(url) = std::env::var("PAPER_TEST_POSTGRES_URL") {
+            let config = crate::config::PostgresConfig {
+                url,
+                max_connections: 2,
+            };
+            let store = crate::model::document::postgres::PostgresStore::new(&config)
+                .await
+                .unwrap();
+            backends.push(Arc::new(DocumentDatabase::new(Arc::new(store))));
+        }
+        backends
+    }
+
+    #[tokio::test]
+    async fn test_backends() {
+        for db in backends().await {
+            let user_id = uuid::Uuid::new_v4().to_string();
+            let folder = Folder::default_system_folder(&user_id, Locale::default());
+            let (created, log) = (
+                folder.clone(),
+                AuditLog::new(&user_id, AuditAction::FolderCreated, None),
+            );
+            in_transaction(db.as_ref(), |db, txn| {
+                let (created, log) = (created.clone(), log.clone());
+                Box::pin(async move {
+                    db.create_folder(txn, created).await?;
+                    db.create_audit_log(txn, log).await
+                })
+            })
+            .await
+            .unwrap();
+            // nothing is written when the transaction fails
+            let mut aborted = folder.clone();
+            aborted.id = uuid::Uuid::new_v4().to_string();
+            aborted.name = "Aborted".to_string();
+            let result: ServiceResult<()> = in_transaction(db.as_ref(), |db, txn| {
+                let aborted = aborted.clone();
+                Box::pin(async move {
+                    db.create_folder(txn, aborted).await?;
+                    Err(ServiceError::BadRequest("abort".to_string()))
+                })
+            })
+            .await;
+            assert!(result.is_err());
+            assert!(db.get_folder_by_id(&aborted.id).await.unwrap().is_none());
+            let stale = folder.clone();
+            let mut folder = db.update_folder(folder).await.unwrap();
+            assert_eq!(folder.version, 1);
+            assert!(matches!(
+                db.update_folder(stale).await,
+                Err(ServiceError::VersionConflict(_))
+            ));
+
+            let paper = Paper::new(&user_id, &folder.id, "Title".to_string());
+            db.create_paper(paper.clone()).await.unwrap();
+            db.set_paper_starred(&paper.id, true).await.unwrap();
+            let papers = db.get_papers_by_folder_id(&folder.id).await.unwrap();
+            assert_eq!(papers.len(), 1);
+            assert!(papers[0].starred);
+
+            db.delete_paper(&paper.id).await.unwrap();
+            assert!(db.get_paper_by_id(&paper.id).await.unwrap().is_none());
+            folder.name = "Renamed".to_string();
+            db.update_folder(folder.clone()).await.unwrap();
+            let folders = db.get_folders_by_user_id(&user_id).await.unwrap();
+            assert_eq!(folders[0].name, "Renamed");
+            db.delete_folder(&folder.id).await.unwrap();
+            assert!(db.get_folder_by_id(&folder.id).await.unwrap().is_none());
+        }
+    }
+
+    #[cfg(feature = "postgres")]
+    #[test]
+    fn test_with_database() {
+        assert_eq!(
+            with_database("postgres://user@localhost/paper", "paper_acme"),
+            "postgres://user@localhost/paper_acme"
+        );
+        assert_eq!(
+            with_database("postgres://localhost:5432?sslmode=require", "paper_acme"),
+            "postgres://localhost:5432/paper_acme?sslmode=require"
+        );
+    }
+}
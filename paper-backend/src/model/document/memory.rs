@@ -0,0 +1,323 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use bson::{Bson, Document};
+use mongodb::IndexModel;
+
+use crate::{
+    error::{ServiceError, ServiceResult},
+    model::document::{Change, DocumentStore, Query, Scan, Select, query},
+};
+
+/// The fields of a unique index, among the documents matching its partial
+/// filter if any.
+#[derive(Debug, Clone)]
+struct UniqueIndex {
+    fields: Vec<String>,
+    partial: Option<Document>,
+}
+
+impl UniqueIndex {
+    fn covers(&self, doc: &Document) -> bool {
+        match &self.partial {
+            Some(filter) => query::matches(doc, filter, None).unwrap_or(false),
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Collection {
+    docs: Vec<Document>,
+    // `_id` is always unique
+    unique: Vec<UniqueIndex>,
+}
+
+impl Collection {
+    fn key(doc: &Document, fields: &[String]) -> Vec<Bson> {
+        fields
+            .iter()
+            .map(|field| match query::values(doc, field).last() {
+                Some(value) => (*value).clone(),
+                None => Bson::Null,
+            })
+            .collect()
+    }
+
+    /// Whether the document breaks a unique index against the documents but
+    /// the one at `skip`.
+    fn conflicts(&self, doc: &Document, skip: Option<usize>) -> bool {
+        let id = UniqueIndex {
+            fields: vec!["_id".to_string()],
+            partial: None,
+        };
+        let mut indexes = self.unique.iter().chain([&id]);
+        indexes.any(|index| {
+            if !index.covers(doc) {
+                return false;
+            }
+            let key = Self::key(doc, &index.fields);
+            self.docs.iter().enumerate().any(|(i, other)| {
+                Some(i) != skip && index.covers(other) && Self::key(other, &index.fields) == key
+            })
+        })
+    }
+}
+
+/// Documents kept in memory, lost with the process, so that the tests run
+/// without a database.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    collections: Mutex<HashMap<String, Collection>>,
+    blobs: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryStore {
+    fn with_collection<R>(&self, collection: &str, f: impl FnOnce(&mut Collection) -> R) -> R {
+        let mut collections = self.collections.lock().unwrap_or_else(|e| e.into_inner());
+        f(collections.entry(collection.to_string()).or_default())
+    }
+}
+
+#[async_trait::async_trait]
+impl DocumentStore for MemoryStore {
+    async fn scan(&self, collection: &str, _query: &Query) -> ServiceResult<Scan> {
+        let docs = self.with_collection(collection, |c| c.docs.clone());
+        Ok(Scan { docs, exact: false })
+    }
+
+    async fn count(&self, _collection: &str, _filter: &Document) -> ServiceResult<Option<u64>> {
+        Ok(None)
+    }
+
+    async fn insert(&self, collection: &str, docs: Vec<Document>) -> ServiceResult<bool> {
+        self.with_collection(collection, |c| {
+            let len = c.docs.len();
+            for doc in docs {
+                if c.conflicts(&doc, None) {
+                    c.docs.truncate(len);
+                    return Ok(false);
+                }
+                c.docs.push(doc);
+            }
+            Ok(true)
+        })
+    }
+
+    async fn modify(
+        &self,
+        collection: &str,
+        _filter: &Document,
+        many: bool,
+        change: &Change,
+    ) -> ServiceResult<u64> {
+        self.with_collection(collection, |c| {
+            let mut matched = 0;
+            for i in 0..c.docs.len() {
+                let mut doc = c.docs[i].clone();
+                if !change(&mut doc)? {
+                    continue;
+                }
+                if c.conflicts(&doc, Some(i)) {
+                    return Err(ServiceError::InternalServerError(format!(
+                        "Duplicate key in {}",
+                        collection
+                    )));
+                }
+                c.docs[i] = doc;
+                matched += 1;
+                if !many {
+                    break;
+                }
+            }
+            Ok(matched)
+        })
+    }
+
+    async fn remove(
+        &self,
+        collection: &str,
+        _filter: &Document,
+        many: bool,
+        select: &Select,
+    ) -> ServiceResult<u64> {
+        self.with_collection(collection, |c| {
+            let mut deleted = 0;
+            let mut i = 0;
+            while i < c.docs.len() && (many || deleted == 0) {
+                match select(&c.docs[i])? {
+                    true => {
+                        c.docs.remove(i);
+                        deleted += 1;
+                    }
+                    false => i += 1,
+                }
+            }
+            Ok(deleted)
+        })
+    }
+
+    async fn replace(&self, collections: Vec<(String, Vec<Document>)>) -> ServiceResult<()> {
+        let mut stored = self.collections.lock().unwrap_or_else(|e| e.into_inner());
+        let mut replaced = Vec::new();
+        for (name, docs) in collections {
+            let mut collection = Collection {
+                docs: Vec::new(),
+                unique: stored
+                    .get(&name)
+                    .map(|c| c.unique.clone())
+                    .unwrap_or_default(),
+            };
+            for doc in docs {
+                if collection.conflicts(&doc, None) {
+                    return Err(ServiceError::InternalServerError(format!(
+                        "Duplicate key in {}",
+                        name
+                    )));
+                }
+                collection.docs.push(doc);
+            }
+            replaced.push((name, collection));
+        }
+        stored.extend(replaced);
+        Ok(())
+    }
+
+    async fn begin(&self) -> ServiceResult<Option<Arc<dyn DocumentStore>>> {
+        Ok(None)
+    }
+
+    async fn commit(&self) -> ServiceResult<()> {
+        Ok(())
+    }
+
+    async fn rollback(&self) {}
+
+    async fn put_blob(&self, key: &str, bytes: &[u8]) -> ServiceResult<()> {
+        let mut blobs = self.blobs.lock().unwrap_or_else(|e| e.into_inner());
+        blobs.insert(key.to_string(), bytes.to_vec());
+        Ok(())
+    }
+
+    async fn get_blob(&self, key: &str) -> ServiceResult<Option<Vec<u8>>> {
+        let blobs = self.blobs.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(blobs.get(key).cloned())
+    }
+
+    async fn delete_blob(&self, key: &str) -> ServiceResult<()> {
+        let mut blobs = self.blobs.lock().unwrap_or_else(|e| e.into_inner());
+        blobs.remove(key);
+        Ok(())
+    }
+
+    async fn list_blobs(&self) -> ServiceResult<Vec<String>> {
+        let blobs = self.blobs.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(blobs.keys().cloned().collect())
+    }
+
+    async fn ensure_indexes(
+        &self,
+        indexes: &[(&'static str, Vec<IndexModel>)],
+    ) -> ServiceResult<()> {
+        for (collection, indexes) in indexes {
+            let unique = indexes
+                .iter()
+                .filter(|index| index.options.as_ref().and_then(|o| o.unique) == Some(true))
+                .map(|index| UniqueIndex {
+                    fields: index.keys.keys().cloned().collect(),
+                    partial: index
+                        .options
+                        .as_ref()
+                        .and_then(|o| o.partial_filter_expression.clone()),
+                })
+                .collect();
+            self.with_collection(collection, |c| c.unique = unique);
+        }
+        Ok(())
+    }
+
+    async fn ping(&self) -> ServiceResult<()> {
+        Ok(())
+    }
+
+    async fn shutdown(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::doc;
+
+    use super::*;
+    use crate::model::constant::TYPE_OP;
+
+    #[tokio::test]
+    async fn test_unique_indexes() {
+        let store = MemoryStore::default();
+        let options = mongodb::options::IndexOptions::builder()
+            .unique(true)
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! { "token": 1 })
+            .options(options)
+            .build();
+        let indexes = [("shares", vec![index])];
+        store.ensure_indexes(&indexes).await.unwrap();
+
+        let share = |id: &str, token: &str| doc! { "_id": id, "token": token };
+        let insert = |docs| store.insert("shares", docs);
+        assert!(insert(vec![share("s1", "a")]).await.unwrap());
+        // all or none
+        let docs = vec![share("s2", "b"), share("s3", "a")];
+        assert!(!insert(docs).await.unwrap());
+        assert!(!insert(vec![share("s1", "c")]).await.unwrap());
+        let scan = store.scan("shares", &Query::default()).await.unwrap();
+        assert_eq!(scan.docs.len(), 1);
+
+        assert!(insert(vec![share("s2", "b")]).await.unwrap());
+        let change = |doc: &mut Document| {
+            let matched = doc.get_str("_id") == Ok("s2");
+            doc.insert("token", "a");
+            Ok(matched)
+        };
+        let modified = store.modify("shares", &doc! {}, false, &change).await;
+        assert!(modified.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_partial_unique_indexes() {
+        let store = MemoryStore::default();
+        let options = mongodb::options::IndexOptions::builder()
+            .unique(true)
+            .partial_filter_expression(doc! { "email": { TYPE_OP: "string" } })
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! { "email": 1 })
+            .options(options)
+            .build();
+        store
+            .ensure_indexes(&[("users", vec![index])])
+            .await
+            .unwrap();
+
+        let user = |id: &str, email: Option<&str>| doc! { "_id": id, "email": email };
+        let insert = |docs| store.insert("users", docs);
+        // the users without an email are not indexed
+        assert!(
+            insert(vec![user("u1", None), user("u2", None)])
+                .await
+                .unwrap()
+        );
+        assert!(
+            insert(vec![user("u3", Some("a@example.com"))])
+                .await
+                .unwrap()
+        );
+        assert!(
+            !insert(vec![user("u4", Some("a@example.com"))])
+                .await
+                .unwrap()
+        );
+    }
+}
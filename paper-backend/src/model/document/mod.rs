@@ -0,0 +1,369 @@
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+use bson::{Bson, Document, doc, oid::ObjectId};
+use mongodb::IndexModel;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    error::{ServiceError, ServiceResult},
+    model::{constant::*, indexes::declared_indexes},
+};
+
+#[cfg(test)]
+pub mod memory;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod query;
+
+pub use query::Query;
+
+// how often the documents past an expiring index are deleted
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Changes a candidate document in place, returning whether it matched.
+pub type Change = dyn Fn(&mut Document) -> ServiceResult<bool> + Send + Sync;
+/// Returns whether a candidate document matched.
+pub type Select = dyn Fn(&Document) -> ServiceResult<bool> + Send + Sync;
+
+/// The documents a store found for a query.
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub docs: Vec<Document>,
+    // the documents are the result of the query, matched exactly, sorted,
+    // skipped and limited by the store, instead of candidates
+    pub exact: bool,
+}
+
+/// Storage of schemaless documents by collection, for the databases without
+/// the query language of mongo. The store narrows down the documents to the
+/// candidates of a filter, the filter itself is evaluated by the caller unless
+/// the store tells it ran the whole query.
+#[async_trait::async_trait]
+pub trait DocumentStore: Send + Sync + Debug {
+    /// Documents of the collection which may match the filter of the query, or
+    /// the result of the query when the store can run it exactly.
+    async fn scan(&self, collection: &str, query: &Query) -> ServiceResult<Scan>;
+    /// How many documents match the filter, none when the store cannot tell
+    /// without the caller evaluating it.
+    async fn count(&self, collection: &str, filter: &Document) -> ServiceResult<Option<u64>>;
+    /// Insert every document or none, false when one breaks a unique index.
+    async fn insert(&self, collection: &str, docs: Vec<Document>) -> ServiceResult<bool>;
+    /// Change the candidates of the filter (the first match only unless
+    /// `many`) atomically, returning how many matched.
+    async fn modify(
+        &self,
+        collection: &str,
+        filter: &Document,
+        many: bool,
+        change: &Change,
+    ) -> ServiceResult<u64>;
+    /// Delete the candidates of the filter selected (the first one only unless
+    /// `many`), returning how many were deleted.
+    async fn remove(
+        &self,
+        collection: &str,
+        filter: &Document,
+        many: bool,
+        select: &Select,
+    ) -> ServiceResult<u64>;
+    /// Replace every document of the collections with the ones given, in one
+    /// write: every collection is replaced or none, failing when a document
+    /// breaks a unique index.
+    async fn replace(&self, collections: Vec<(String, Vec<Document>)>) -> ServiceResult<()>;
+    /// Start a transaction: the store returned runs its reads and writes in
+    /// it, until they are committed or rolled back. None when the store has
+    /// no transactions and applies the writes one by one.
+    async fn begin(&self) -> ServiceResult<Option<Arc<dyn DocumentStore>>>;
+    /// Apply the writes of the transaction of the store, see `begin`.
+    async fn commit(&self) -> ServiceResult<()>;
+    /// Discard the writes of the transaction of the store, see `begin`.
+    async fn rollback(&self);
+
+    /// Store the blob, replacing any blob with the same key.
+    async fn put_blob(&self, key: &str, bytes: &[u8]) -> ServiceResult<()>;
+    async fn get_blob(&self, key: &str) -> ServiceResult<Option<Vec<u8>>>;
+    async fn delete_blob(&self, key: &str) -> ServiceResult<()>;
+    /// The keys of every blob.
+    async fn list_blobs(&self) -> ServiceResult<Vec<String>>;
+
+    /// Enforce the unique indexes, the others are a matter of performance.
+    async fn ensure_indexes(
+        &self,
+        indexes: &[(&'static str, Vec<IndexModel>)],
+    ) -> ServiceResult<()>;
+    async fn ping(&self) -> ServiceResult<()>;
+    async fn shutdown(&self);
+}
+
+/// The repositories over a [`DocumentStore`], running the queries of the mongo
+/// implementations with the same semantics. Aggregations are computed here,
+/// the writes given a [`TxnContext`](super::txn::TxnContext) run on the store
+/// of its transaction.
+#[derive(Debug, Clone)]
+pub struct DocumentDatabase {
+    store: Arc<dyn DocumentStore>,
+}
+
+impl DocumentDatabase {
+    pub fn new(store: Arc<dyn DocumentStore>) -> Self {
+        DocumentDatabase { store }
+    }
+
+    pub(crate) fn store(&self) -> &dyn DocumentStore {
+        self.store.as_ref()
+    }
+
+    /// Insert the document, failing when it breaks a unique index.
+    pub(crate) async fn insert<T: Serialize>(
+        &self,
+        collection: &str,
+        value: &T,
+    ) -> ServiceResult<()> {
+        match self.try_insert(collection, value).await? {
+            true => Ok(()),
+            false => Err(ServiceError::InternalServerError(format!(
+                "Duplicate key in {}",
+                collection
+            ))),
+        }
+    }
+
+    /// Insert the document, false when it breaks a unique index.
+    pub(crate) async fn try_insert<T: Serialize>(
+        &self,
+        collection: &str,
+        value: &T,
+    ) -> ServiceResult<bool> {
+        self.store
+            .insert(collection, vec![with_id(bson::to_document(value)?)])
+            .await
+    }
+
+    pub(crate) async fn insert_many<T: Serialize>(
+        &self,
+        collection: &str,
+        values: &[T],
+    ) -> ServiceResult<()> {
+        let docs = values
+            .iter()
+            .map(|value| Ok(with_id(bson::to_document(value)?)))
+            .collect::<ServiceResult<Vec<_>>>()?;
+        if docs.is_empty() {
+            return Ok(());
+        }
+        match self.store.insert(collection, docs).await? {
+            true => Ok(()),
+            false => Err(ServiceError::InternalServerError(format!(
+                "Duplicate key in {}",
+                collection
+            ))),
+        }
+    }
+
+    /// The documents matching the query, as raw documents.
+    pub(crate) async fn find_documents(
+        &self,
+        collection: &str,
+        query: Query,
+    ) -> ServiceResult<Vec<Document>> {
+        let scan = self.store.scan(collection, &query).await?;
+        if scan.exact {
+            return Ok(scan.docs);
+        }
+        query::select(scan.docs, &query, query::text_index(collection))
+    }
+
+    pub(crate) async fn find<T: DeserializeOwned>(
+        &self,
+        collection: &str,
+        query: Query,
+    ) -> ServiceResult<Vec<T>> {
+        self.find_documents(collection, query)
+            .await?
+            .into_iter()
+            .map(|doc| Ok(bson::from_document(doc)?))
+            .collect()
+    }
+
+    /// The first document of the query.
+    pub(crate) async fn find_first<T: DeserializeOwned>(
+        &self,
+        collection: &str,
+        query: Query,
+    ) -> ServiceResult<Option<T>> {
+        Ok(self.find(collection, query.limit(1)).await?.pop())
+    }
+
+    pub(crate) async fn find_one<T: DeserializeOwned>(
+        &self,
+        collection: &str,
+        filter: Document,
+    ) -> ServiceResult<Option<T>> {
+        self.find_first(collection, Query::new(filter)).await
+    }
+
+    pub(crate) async fn count(&self, collection: &str, filter: Document) -> ServiceResult<u64> {
+        if let Some(count) = self.store.count(collection, &filter).await? {
+            return Ok(count);
+        }
+        let docs = self.find_documents(collection, Query::new(filter)).await?;
+        Ok(docs.len() as u64)
+    }
+
+    async fn update(
+        &self,
+        collection: &str,
+        filter: Document,
+        update: Document,
+        many: bool,
+    ) -> ServiceResult<u64> {
+        let text = query::text_index(collection);
+        let change = |doc: &mut Document| {
+            if !query::matches(doc, &filter, text)? {
+                return Ok(false);
+            }
+            query::apply_update(doc, &update)?;
+            Ok(true)
+        };
+        self.store.modify(collection, &filter, many, &change).await
+    }
+
+    /// Update the first match of the filter, returning how many matched.
+    pub(crate) async fn update_one(
+        &self,
+        collection: &str,
+        filter: Document,
+        update: Document,
+    ) -> ServiceResult<u64> {
+        self.update(collection, filter, update, false).await
+    }
+
+    pub(crate) async fn update_many(
+        &self,
+        collection: &str,
+        filter: Document,
+        update: Document,
+    ) -> ServiceResult<u64> {
+        self.update(collection, filter, update, true).await
+    }
+
+    /// Replace the first match of the filter, or insert the document when
+    /// none matches and `upsert`. Returns how many matched.
+    pub(crate) async fn replace_one<T: Serialize>(
+        &self,
+        collection: &str,
+        filter: Document,
+        value: &T,
+        upsert: bool,
+    ) -> ServiceResult<u64> {
+        let replacement = bson::to_document(value)?;
+        let text = query::text_index(collection);
+        let change = |doc: &mut Document| {
+            if !query::matches(doc, &filter, text)? {
+                return Ok(false);
+            }
+            let mut replaced = doc! { "_id": doc.get("_id").cloned().unwrap_or(Bson::Null) };
+            replaced.extend(replacement.clone());
+            *doc = replaced;
+            Ok(true)
+        };
+        let matched = self
+            .store
+            .modify(collection, &filter, false, &change)
+            .await?;
+        if matched > 0 || !upsert {
+            return Ok(matched);
+        }
+        let mut inserted = query::upserted(&filter, &doc! {})?;
+        inserted.extend(replacement.clone());
+        match self
+            .store
+            .insert(collection, vec![with_id(inserted)])
+            .await?
+        {
+            true => Ok(0),
+            // inserted concurrently, replace it
+            false => self.store.modify(collection, &filter, false, &change).await,
+        }
+    }
+
+    async fn delete(&self, collection: &str, filter: Document, many: bool) -> ServiceResult<u64> {
+        let text = query::text_index(collection);
+        let select = |doc: &Document| query::matches(doc, &filter, text);
+        self.store.remove(collection, &filter, many, &select).await
+    }
+
+    pub(crate) async fn delete_one(
+        &self,
+        collection: &str,
+        filter: Document,
+    ) -> ServiceResult<u64> {
+        self.delete(collection, filter, false).await
+    }
+
+    pub(crate) async fn delete_many(
+        &self,
+        collection: &str,
+        filter: Document,
+    ) -> ServiceResult<u64> {
+        self.delete(collection, filter, true).await
+    }
+
+    /// Enforce the declared unique indexes, and delete the documents past the
+    /// expiring ones from now on.
+    /// Replace every document of the collections, all at once.
+    pub(crate) async fn replace_all(
+        &self,
+        collections: Vec<(String, Vec<Document>)>,
+    ) -> ServiceResult<()> {
+        let collections = collections
+            .into_iter()
+            .map(|(collection, docs)| (collection, docs.into_iter().map(with_id).collect()))
+            .collect();
+        self.store.replace(collections).await
+    }
+
+    pub async fn ensure_indexes(&self) -> ServiceResult<()> {
+        let indexes = declared_indexes();
+        self.store.ensure_indexes(&indexes).await?;
+
+        let expiring = indexes
+            .into_iter()
+            .flat_map(|(collection, indexes)| {
+                indexes.into_iter().filter_map(move |index| {
+                    let expire_after = index.options.as_ref()?.expire_after?;
+                    let field = index.keys.keys().next()?.clone();
+                    Some((collection, field, expire_after))
+                })
+            })
+            .collect::<Vec<_>>();
+        if expiring.is_empty() {
+            return Ok(());
+        }
+        let db = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+            loop {
+                interval.tick().await;
+                for (collection, field, expire_after) in &expiring {
+                    let expired_at = bson::DateTime::from_system_time(
+                        std::time::SystemTime::now() - *expire_after,
+                    );
+                    let filter = doc! { field.as_str(): { LT_OP: expired_at } };
+                    if let Err(e) = db.delete_many(collection, filter).await {
+                        tracing::warn!("Failed to delete expired {}: {}", collection, e);
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+// mongo gives the documents inserted without an id one
+fn with_id(mut doc: Document) -> Document {
+    if !doc.contains_key("_id") {
+        doc.insert("_id", Bson::ObjectId(ObjectId::new()));
+    }
+    doc
+}
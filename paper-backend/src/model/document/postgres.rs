@@ -0,0 +1,1064 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use bson::{Bson, Document};
+use mongodb::IndexModel;
+use sqlx::{
+    Connection, PgConnection, PgPool, Postgres, Row, Transaction,
+    pool::PoolConnection,
+    postgres::{PgArguments, PgPoolOptions},
+    query::Query as SqlQuery,
+};
+use tokio::sync::{MappedMutexGuard, MutexGuard};
+
+use crate::{
+    config::PostgresConfig,
+    error::{ServiceError, ServiceResult},
+    model::{
+        constant::{AND_OP, GTE_OP, IN_OP, LT_OP, LTE_OP, OR_OP, TYPE_OP},
+        document::{
+            Change, DocumentStore, Query, Scan, Select,
+            query::{EQ_OP, GT_OP, operators},
+        },
+        indexes::index_name,
+    },
+};
+
+const BLOB_TABLE: &str = "_blobs";
+
+fn pg_error(e: sqlx::Error) -> ServiceError {
+    ServiceError::InternalServerError(format!("PostgreSQL error: {}", e))
+}
+
+fn is_unique_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error()
+        .is_some_and(|e| e.is_unique_violation())
+}
+
+fn to_json(doc: Document) -> serde_json::Value {
+    Bson::Document(doc).into_relaxed_extjson()
+}
+
+fn from_json(value: serde_json::Value) -> ServiceResult<Document> {
+    match Bson::try_from(value) {
+        Ok(Bson::Document(doc)) => Ok(doc),
+        Ok(_) => Err(ServiceError::InternalServerError(
+            "PostgreSQL error: a stored document is not an object".to_string(),
+        )),
+        Err(e) => Err(ServiceError::InternalServerError(format!(
+            "PostgreSQL error: {}",
+            e
+        ))),
+    }
+}
+
+// the primary key of the row of a document
+fn id_key(id: &Bson) -> String {
+    match id {
+        Bson::String(id) => id.clone(),
+        Bson::ObjectId(id) => id.to_hex(),
+        id => id.clone().into_relaxed_extjson().to_string(),
+    }
+}
+
+enum Param {
+    Text(String),
+    Texts(Vec<String>),
+    Json(serde_json::Value),
+}
+
+fn bind_all<'q>(
+    mut query: SqlQuery<'q, Postgres, PgArguments>,
+    params: Vec<Param>,
+) -> SqlQuery<'q, Postgres, PgArguments> {
+    for param in params {
+        query = match param {
+            Param::Text(text) => query.bind(text),
+            Param::Texts(texts) => query.bind(texts),
+            Param::Json(json) => query.bind(json),
+        };
+    }
+    query
+}
+
+/// Whether an equality to the value translates to the containment of its
+/// extended json, which is the same for equal values.
+fn containable(value: &Bson) -> bool {
+    match value {
+        Bson::String(_)
+        | Bson::Boolean(_)
+        | Bson::Int32(_)
+        | Bson::Int64(_)
+        | Bson::DateTime(_)
+        | Bson::ObjectId(_) => true,
+        Bson::Double(n) => n.is_finite(),
+        _ => false,
+    }
+}
+
+/// The where clause of a filter and its parameters. It narrows a table down to
+/// the candidates of the filter through the gin index of the table, and
+/// matches exactly the documents of the filter when every clause translates
+/// with the semantics of mongo.
+struct Where {
+    params: Vec<Param>,
+    exact: bool,
+}
+
+impl Where {
+    fn new() -> Self {
+        Where {
+            params: Vec::new(),
+            exact: true,
+        }
+    }
+
+    fn param(&mut self, param: Param) -> String {
+        self.params.push(param);
+        format!("${}", self.params.len())
+    }
+
+    /// The condition of the filter, `TRUE` when no clause narrows it. A clause
+    /// is translated in full or leaves no parameter behind.
+    fn filter(&mut self, filter: &Document) -> String {
+        let mut conditions = Vec::new();
+        for (key, condition) in filter {
+            let translated = match key.as_str() {
+                AND_OP | OR_OP => self.clauses(key, condition),
+                "_id" => self.id(condition).or_else(|| self.field(key, condition)),
+                operator if operator.starts_with('$') => None,
+                path => self.field(path, condition),
+            };
+            match translated {
+                Some(translated) => conditions.push(translated),
+                None => self.exact = false,
+            }
+        }
+        match conditions.is_empty() {
+            true => "TRUE".to_string(),
+            false => conditions.join(" AND "),
+        }
+    }
+
+    fn clauses(&mut self, operator: &str, condition: &Bson) -> Option<String> {
+        let clauses = condition
+            .as_array()?
+            .iter()
+            .map(Bson::as_document)
+            .collect::<Option<Vec<_>>>()?;
+        if clauses.is_empty() {
+            return None;
+        }
+        let conditions = clauses
+            .into_iter()
+            .map(|clause| format!("({})", self.filter(clause)))
+            .collect::<Vec<_>>();
+        let separator = match operator {
+            AND_OP => " AND ",
+            _ => " OR ",
+        };
+        Some(format!("({})", conditions.join(separator)))
+    }
+
+    // the id column, for an id or a list of ids
+    fn id(&mut self, condition: &Bson) -> Option<String> {
+        let is_id = |id: &Bson| matches!(id, Bson::String(_) | Bson::ObjectId(_));
+        match operators(condition) {
+            None if is_id(condition) => {
+                let id = self.param(Param::Text(id_key(condition)));
+                Some(format!("id = {}", id))
+            }
+            Some(operators) if operators.len() == 1 => {
+                let ids = operators.get_array(IN_OP).ok()?;
+                if !ids.iter().all(is_id) {
+                    return None;
+                }
+                let ids = self.param(Param::Texts(ids.iter().map(id_key).collect()));
+                Some(format!("id = ANY({})", ids))
+            }
+            _ => None,
+        }
+    }
+
+    fn field(&mut self, path: &str, condition: &Bson) -> Option<String> {
+        let Some(operators) = operators(condition) else {
+            return self.equals(path, condition);
+        };
+        let mut conditions = Vec::new();
+        for (operator, operand) in operators {
+            let translated = match operator.as_str() {
+                EQ_OP => self.equals(path, operand),
+                IN_OP => self.one_of(path, operand),
+                GT_OP | GTE_OP | LT_OP | LTE_OP => {
+                    // the items of an array field compare too, the field is a
+                    // candidate
+                    self.exact = false;
+                    self.compares(path, operator, operand)
+                }
+                _ => None,
+            };
+            match translated {
+                Some(translated) => conditions.push(translated),
+                None => self.exact = false,
+            }
+        }
+        match conditions.is_empty() {
+            true => None,
+            false => Some(conditions.join(" AND ")),
+        }
+    }
+
+    /// The top level field is the value, or an array holding it.
+    fn equals(&mut self, path: &str, value: &Bson) -> Option<String> {
+        if path.contains('.') || !containable(value) {
+            return None;
+        }
+        let value = value.clone().into_relaxed_extjson();
+        let scalar = self.param(Param::Json(serde_json::json!({ path: value })));
+        let item = self.param(Param::Json(serde_json::json!({ path: [value] })));
+        Some(format!("(doc @> {} OR doc @> {})", scalar, item))
+    }
+
+    fn one_of(&mut self, path: &str, operand: &Bson) -> Option<String> {
+        let items = operand.as_array()?;
+        if items.is_empty() {
+            return Some("FALSE".to_string());
+        }
+        if path.contains('.') || !items.iter().all(containable) {
+            return None;
+        }
+        let conditions = items
+            .iter()
+            .map(|item| self.equals(path, item))
+            .collect::<Option<Vec<_>>>()?;
+        Some(format!("({})", conditions.join(" OR ")))
+    }
+
+    /// The field compares to the operand when of the same type, as mongo only
+    /// compares values of the same type. An array or an unusual value is a
+    /// candidate.
+    fn compares(&mut self, path: &str, operator: &str, operand: &Bson) -> Option<String> {
+        let compare = match operator {
+            GT_OP => ">",
+            GTE_OP => ">=",
+            LT_OP => "<",
+            _ => "<=",
+        };
+        let param = match operand {
+            Bson::Int32(_) | Bson::Int64(_) => Param::Json(operand.clone().into_relaxed_extjson()),
+            Bson::Double(n) if n.is_finite() => Param::Json(operand.clone().into_relaxed_extjson()),
+            Bson::String(value) => Param::Text(value.clone()),
+            Bson::DateTime(value) => Param::Text(value.try_to_rfc3339_string().ok()?),
+            _ => return None,
+        };
+        let path = path.split('.').map(str::to_string).collect();
+        let field = format!("(doc #> {})", self.param(Param::Texts(path)));
+        let value = self.param(param);
+        let (typed, left, right) = match operand {
+            Bson::String(_) => (
+                format!("jsonb_typeof({}) = 'string'", field),
+                format!("({} #>> '{{}}') COLLATE \"C\"", field),
+                format!("{} COLLATE \"C\"", value),
+            ),
+            Bson::DateTime(_) => (
+                format!("jsonb_typeof({} -> '$date') = 'string'", field),
+                format!("({} ->> '$date')::timestamptz", field),
+                format!("{}::timestamptz", value),
+            ),
+            _ => (
+                format!("jsonb_typeof({}) = 'number'", field),
+                field.clone(),
+                value,
+            ),
+        };
+        Some(format!(
+            "CASE WHEN {} THEN {} {} {} \
+            WHEN jsonb_typeof({}) IN ('array', 'object') THEN TRUE ELSE FALSE END",
+            typed, left, compare, right, field
+        ))
+    }
+}
+
+/// The where clause of the partial filter of an index, which only checks the
+/// types of the fields, e.g. `{ email: { $type: "string" } }`.
+fn partial_condition(filter: &Document) -> ServiceResult<String> {
+    let mut conditions = Vec::new();
+    for (path, condition) in filter {
+        let json_type = match operators(condition).and_then(|o| o.get_str(TYPE_OP).ok()) {
+            Some("string") => "string",
+            Some("object") => "object",
+            Some("array") => "array",
+            Some("bool") => "boolean",
+            Some("double" | "int" | "long" | "decimal") => "number",
+            _ => {
+                return Err(ServiceError::InternalServerError(format!(
+                    "Unsupported partial index filter {}",
+                    filter
+                )));
+            }
+        };
+        conditions.push(format!(
+            "jsonb_typeof(doc #> '{{{}}}') = '{}'",
+            path.replace('.', ",").replace('\'', "''"),
+            json_type
+        ));
+    }
+    Ok(conditions.join(" AND "))
+}
+
+/// The order by of a sort on top level fields, by the rank of the type of the
+/// values first then by the values, as [`compare`](super::query::compare)
+/// orders them, the id breaking the ties. None when a field is nested.
+fn order_by(sort: &Document) -> Option<String> {
+    let mut keys = Vec::new();
+    for (path, direction) in sort {
+        if path.contains('.') {
+            return None;
+        }
+        let direction = match direction {
+            Bson::Int32(d) if *d < 0 => "DESC",
+            Bson::Int64(d) if *d < 0 => "DESC",
+            Bson::Double(d) if *d < 0.0 => "DESC",
+            _ => "ASC",
+        };
+        let field = format!("(doc -> '{}')", path.replace('\'', "''"));
+        let expressions = [
+            format!(
+                "CASE WHEN {f} IS NULL OR jsonb_typeof({f}) = 'null' THEN 1 \
+                WHEN jsonb_typeof({f}) = 'number' THEN 2 \
+                WHEN jsonb_typeof({f}) = 'string' THEN 3 \
+                WHEN jsonb_typeof({f}) = 'array' THEN 5 \
+                WHEN jsonb_typeof({f}) = 'boolean' THEN 8 \
+                WHEN {f} ? '$oid' THEN 7 \
+                WHEN {f} ? '$date' THEN 9 \
+                ELSE 4 END",
+                f = field
+            ),
+            format!(
+                "CASE WHEN jsonb_typeof({f}) = 'number' THEN ({f})::numeric END",
+                f = field
+            ),
+            format!(
+                "(CASE WHEN jsonb_typeof({f}) = 'string' THEN {f} #>> '{{}}' \
+                WHEN jsonb_typeof({f} -> '$oid') = 'string' THEN {f} ->> '$oid' END) COLLATE \"C\"",
+                f = field
+            ),
+            format!(
+                "CASE WHEN jsonb_typeof({f} -> '$date') = 'string' \
+                THEN ({f} ->> '$date')::timestamptz END",
+                f = field
+            ),
+            format!(
+                "CASE WHEN jsonb_typeof({f}) = 'boolean' THEN ({f})::boolean END",
+                f = field
+            ),
+            field,
+        ];
+        keys.extend(
+            expressions
+                .into_iter()
+                .map(|expression| format!("{} {}", expression, direction)),
+        );
+    }
+    keys.push("id".to_string());
+    Some(keys.join(", "))
+}
+
+/// Documents stored as jsonb in PostgreSQL, a table by collection created on
+/// first use, for the deployments which cannot run mongo.
+#[derive(Clone)]
+pub struct PostgresStore {
+    pool: PgPool,
+    // the tables known to exist
+    tables: Arc<Mutex<HashSet<String>>>,
+}
+
+impl std::fmt::Debug for PostgresStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostgresStore").finish()
+    }
+}
+
+impl PostgresStore {
+    pub async fn new(config: &PostgresConfig) -> ServiceResult<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(config.max_connections)
+            .connect(&config.url)
+            .await
+            .map_err(pg_error)?;
+        sqlx::query(&format!(
+            r#"CREATE TABLE IF NOT EXISTS "{}" (key TEXT PRIMARY KEY, bytes BYTEA NOT NULL)"#,
+            BLOB_TABLE
+        ))
+        .execute(&pool)
+        .await
+        .map_err(pg_error)?;
+        Ok(PostgresStore {
+            pool,
+            tables: Arc::new(Mutex::new(HashSet::new())),
+        })
+    }
+
+    // created outside of any transaction, to be known to exist once created
+    async fn table(&self, collection: &str) -> ServiceResult<String> {
+        let table = format!(r#""{}""#, collection.replace('"', ""));
+        let exists = self
+            .tables
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(collection);
+        if !exists {
+            sqlx::query(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, doc JSONB NOT NULL)",
+                table
+            ))
+            .execute(&self.pool)
+            .await
+            .map_err(pg_error)?;
+            sqlx::query(&format!(
+                r#"CREATE INDEX IF NOT EXISTS "{}_doc" ON {} USING GIN (doc jsonb_path_ops)"#,
+                collection, table
+            ))
+            .execute(&self.pool)
+            .await
+            .map_err(pg_error)?;
+            self.tables
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(collection.to_string());
+        }
+        Ok(table)
+    }
+
+    async fn connection(&self) -> ServiceResult<PoolConnection<Postgres>> {
+        self.pool.acquire().await.map_err(pg_error)
+    }
+
+    // the operations of the store run on the connection given, from the pool
+    // or in a transaction; the writes of several statements run in a nested
+    // transaction, a savepoint within a transaction
+
+    async fn scan_on(
+        &self,
+        conn: &mut PgConnection,
+        collection: &str,
+        query: &Query,
+    ) -> ServiceResult<Scan> {
+        let table = self.table(collection).await?;
+        let mut clause = Where::new();
+        let condition = clause.filter(&query.filter);
+        let order = match &query.sort {
+            Some(sort) => order_by(sort),
+            None => Some("id".to_string()),
+        };
+        // the query runs here in full when it translates exactly, the text
+        // searches are scored by the caller
+        let mut sql = format!("SELECT doc FROM {} WHERE {}", table, condition);
+        let exact = match order {
+            Some(order) if clause.exact && !query.text_score => {
+                sql.push_str(&format!(" ORDER BY {}", order));
+                if let Some(limit) = query.limit.filter(|limit| *limit > 0) {
+                    sql.push_str(&format!(" LIMIT {}", limit));
+                }
+                if let Some(skip) = query.skip {
+                    sql.push_str(&format!(" OFFSET {}", skip));
+                }
+                true
+            }
+            _ => false,
+        };
+        let rows = bind_all(sqlx::query(&sql), clause.params)
+            .fetch_all(conn)
+            .await
+            .map_err(pg_error)?;
+        let docs = rows
+            .into_iter()
+            .map(|row| from_json(row.try_get("doc").map_err(pg_error)?))
+            .collect::<ServiceResult<_>>()?;
+        Ok(Scan { docs, exact })
+    }
+
+    async fn count_on(
+        &self,
+        conn: &mut PgConnection,
+        collection: &str,
+        filter: &Document,
+    ) -> ServiceResult<Option<u64>> {
+        let table = self.table(collection).await?;
+        let mut clause = Where::new();
+        let condition = clause.filter(filter);
+        if !clause.exact {
+            return Ok(None);
+        }
+        let sql = format!(
+            "SELECT COUNT(*) AS count FROM {} WHERE {}",
+            table, condition
+        );
+        let row = bind_all(sqlx::query(&sql), clause.params)
+            .fetch_one(conn)
+            .await
+            .map_err(pg_error)?;
+        let count: i64 = row.try_get("count").map_err(pg_error)?;
+        Ok(Some(count as u64))
+    }
+
+    async fn insert_on(
+        &self,
+        conn: &mut PgConnection,
+        collection: &str,
+        docs: Vec<Document>,
+    ) -> ServiceResult<bool> {
+        let table = self.table(collection).await?;
+        let sql = format!("INSERT INTO {} (id, doc) VALUES ($1, $2)", table);
+        let mut tx = conn.begin().await.map_err(pg_error)?;
+        for doc in docs {
+            let id = id_key(doc.get("_id").unwrap_or(&Bson::Null));
+            let inserted = sqlx::query(&sql)
+                .bind(id)
+                .bind(to_json(doc))
+                .execute(&mut *tx)
+                .await;
+            match inserted {
+                Ok(_) => {}
+                // the transaction is rolled back on drop
+                Err(e) if is_unique_violation(&e) => return Ok(false),
+                Err(e) => return Err(pg_error(e)),
+            }
+        }
+        tx.commit().await.map_err(pg_error)?;
+        Ok(true)
+    }
+
+    async fn modify_on(
+        &self,
+        conn: &mut PgConnection,
+        collection: &str,
+        filter: &Document,
+        many: bool,
+        change: &Change,
+    ) -> ServiceResult<u64> {
+        let table = self.table(collection).await?;
+        let mut clause = Where::new();
+        let condition = clause.filter(filter);
+        let select = format!(
+            "SELECT id, doc FROM {} WHERE {} ORDER BY id",
+            table, condition
+        );
+        let lock = format!("SELECT doc FROM {} WHERE id = $1 FOR UPDATE", table);
+        let update = format!("UPDATE {} SET doc = $2 WHERE id = $1", table);
+        let mut tx = conn.begin().await.map_err(pg_error)?;
+        let rows = bind_all(sqlx::query(&select), clause.params)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(pg_error)?;
+        let mut matched = 0;
+        for row in rows {
+            let id: String = row.try_get("id").map_err(pg_error)?;
+            let mut doc = from_json(row.try_get("doc").map_err(pg_error)?)?;
+            if !change(&mut doc)? {
+                continue;
+            }
+            // only the rows to change are locked, then changed as they are now
+            let Some(row) = sqlx::query(&lock)
+                .bind(&id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(pg_error)?
+            else {
+                continue;
+            };
+            let mut doc = from_json(row.try_get("doc").map_err(pg_error)?)?;
+            if !change(&mut doc)? {
+                continue;
+            }
+            sqlx::query(&update)
+                .bind(id)
+                .bind(to_json(doc))
+                .execute(&mut *tx)
+                .await
+                .map_err(pg_error)?;
+            matched += 1;
+            if !many {
+                break;
+            }
+        }
+        tx.commit().await.map_err(pg_error)?;
+        Ok(matched)
+    }
+
+    async fn remove_on(
+        &self,
+        conn: &mut PgConnection,
+        collection: &str,
+        filter: &Document,
+        many: bool,
+        select: &Select,
+    ) -> ServiceResult<u64> {
+        let table = self.table(collection).await?;
+        let mut clause = Where::new();
+        let condition = clause.filter(filter);
+        let candidates = format!(
+            "SELECT id, doc FROM {} WHERE {} ORDER BY id",
+            table, condition
+        );
+        let lock = format!("SELECT doc FROM {} WHERE id = $1 FOR UPDATE", table);
+        let delete = format!("DELETE FROM {} WHERE id = $1", table);
+        let mut tx = conn.begin().await.map_err(pg_error)?;
+        let rows = bind_all(sqlx::query(&candidates), clause.params)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(pg_error)?;
+        let mut deleted = 0;
+        for row in rows {
+            let id: String = row.try_get("id").map_err(pg_error)?;
+            let doc = from_json(row.try_get("doc").map_err(pg_error)?)?;
+            if !select(&doc)? {
+                continue;
+            }
+            // only the rows to delete are locked, then selected as they are now
+            let Some(row) = sqlx::query(&lock)
+                .bind(&id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(pg_error)?
+            else {
+                continue;
+            };
+            if !select(&from_json(row.try_get("doc").map_err(pg_error)?)?)? {
+                continue;
+            }
+            sqlx::query(&delete)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(pg_error)?;
+            deleted += 1;
+            if !many {
+                break;
+            }
+        }
+        tx.commit().await.map_err(pg_error)?;
+        Ok(deleted)
+    }
+
+    async fn replace_on(
+        &self,
+        conn: &mut PgConnection,
+        collections: Vec<(String, Vec<Document>)>,
+    ) -> ServiceResult<()> {
+        let mut tables = Vec::new();
+        for (collection, _) in &collections {
+            tables.push(self.table(collection).await?);
+        }
+        // the readers see the collections as they were until the commit
+        let mut tx = conn.begin().await.map_err(pg_error)?;
+        for (table, (collection, docs)) in tables.into_iter().zip(collections) {
+            sqlx::query(&format!("DELETE FROM {}", table))
+                .execute(&mut *tx)
+                .await
+                .map_err(pg_error)?;
+            let sql = format!("INSERT INTO {} (id, doc) VALUES ($1, $2)", table);
+            for doc in docs {
+                let id = id_key(doc.get("_id").unwrap_or(&Bson::Null));
+                let inserted = sqlx::query(&sql)
+                    .bind(id)
+                    .bind(to_json(doc))
+                    .execute(&mut *tx)
+                    .await;
+                match inserted {
+                    Ok(_) => {}
+                    Err(e) if is_unique_violation(&e) => {
+                        return Err(ServiceError::InternalServerError(format!(
+                            "Duplicate key in {}",
+                            collection
+                        )));
+                    }
+                    Err(e) => return Err(pg_error(e)),
+                }
+            }
+        }
+        tx.commit().await.map_err(pg_error)?;
+        Ok(())
+    }
+
+    async fn put_blob_on(
+        &self,
+        conn: &mut PgConnection,
+        key: &str,
+        bytes: &[u8],
+    ) -> ServiceResult<()> {
+        sqlx::query(&format!(
+            r#"INSERT INTO "{}" (key, bytes) VALUES ($1, $2)
+            ON CONFLICT (key) DO UPDATE SET bytes = EXCLUDED.bytes"#,
+            BLOB_TABLE
+        ))
+        .bind(key)
+        .bind(bytes)
+        .execute(conn)
+        .await
+        .map_err(pg_error)?;
+        Ok(())
+    }
+
+    async fn get_blob_on(
+        &self,
+        conn: &mut PgConnection,
+        key: &str,
+    ) -> ServiceResult<Option<Vec<u8>>> {
+        let row = sqlx::query(&format!(
+            r#"SELECT bytes FROM "{}" WHERE key = $1"#,
+            BLOB_TABLE
+        ))
+        .bind(key)
+        .fetch_optional(conn)
+        .await
+        .map_err(pg_error)?;
+        row.map(|row| row.try_get("bytes").map_err(pg_error))
+            .transpose()
+    }
+
+    async fn delete_blob_on(&self, conn: &mut PgConnection, key: &str) -> ServiceResult<()> {
+        sqlx::query(&format!(r#"DELETE FROM "{}" WHERE key = $1"#, BLOB_TABLE))
+            .bind(key)
+            .execute(conn)
+            .await
+            .map_err(pg_error)?;
+        Ok(())
+    }
+
+    async fn list_blobs_on(&self, conn: &mut PgConnection) -> ServiceResult<Vec<String>> {
+        let rows = sqlx::query(&format!(r#"SELECT key FROM "{}" ORDER BY key"#, BLOB_TABLE))
+            .fetch_all(conn)
+            .await
+            .map_err(pg_error)?;
+        rows.into_iter()
+            .map(|row| row.try_get("key").map_err(pg_error))
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl DocumentStore for PostgresStore {
+    async fn scan(&self, collection: &str, query: &Query) -> ServiceResult<Scan> {
+        let mut conn = self.connection().await?;
+        self.scan_on(&mut conn, collection, query).await
+    }
+
+    async fn count(&self, collection: &str, filter: &Document) -> ServiceResult<Option<u64>> {
+        let mut conn = self.connection().await?;
+        self.count_on(&mut conn, collection, filter).await
+    }
+
+    async fn insert(&self, collection: &str, docs: Vec<Document>) -> ServiceResult<bool> {
+        let mut conn = self.connection().await?;
+        self.insert_on(&mut conn, collection, docs).await
+    }
+
+    async fn modify(
+        &self,
+        collection: &str,
+        filter: &Document,
+        many: bool,
+        change: &Change,
+    ) -> ServiceResult<u64> {
+        let mut conn = self.connection().await?;
+        self.modify_on(&mut conn, collection, filter, many, change)
+            .await
+    }
+
+    async fn remove(
+        &self,
+        collection: &str,
+        filter: &Document,
+        many: bool,
+        select: &Select,
+    ) -> ServiceResult<u64> {
+        let mut conn = self.connection().await?;
+        self.remove_on(&mut conn, collection, filter, many, select)
+            .await
+    }
+
+    async fn replace(&self, collections: Vec<(String, Vec<Document>)>) -> ServiceResult<()> {
+        let mut conn = self.connection().await?;
+        self.replace_on(&mut conn, collections).await
+    }
+
+    async fn begin(&self) -> ServiceResult<Option<Arc<dyn DocumentStore>>> {
+        let tx = self.pool.begin().await.map_err(pg_error)?;
+        Ok(Some(Arc::new(PostgresTxn {
+            store: self.clone(),
+            tx: tokio::sync::Mutex::new(Some(tx)),
+        })))
+    }
+
+    async fn commit(&self) -> ServiceResult<()> {
+        Ok(())
+    }
+
+    async fn rollback(&self) {}
+
+    async fn put_blob(&self, key: &str, bytes: &[u8]) -> ServiceResult<()> {
+        let mut conn = self.connection().await?;
+        self.put_blob_on(&mut conn, key, bytes).await
+    }
+
+    async fn get_blob(&self, key: &str) -> ServiceResult<Option<Vec<u8>>> {
+        let mut conn = self.connection().await?;
+        self.get_blob_on(&mut conn, key).await
+    }
+
+    async fn delete_blob(&self, key: &str) -> ServiceResult<()> {
+        let mut conn = self.connection().await?;
+        self.delete_blob_on(&mut conn, key).await
+    }
+
+    async fn list_blobs(&self) -> ServiceResult<Vec<String>> {
+        let mut conn = self.connection().await?;
+        self.list_blobs_on(&mut conn).await
+    }
+
+    async fn ensure_indexes(
+        &self,
+        indexes: &[(&'static str, Vec<IndexModel>)],
+    ) -> ServiceResult<()> {
+        for (collection, indexes) in indexes {
+            let table = self.table(collection).await?;
+            let unique = indexes
+                .iter()
+                .filter(|index| index.options.as_ref().and_then(|o| o.unique) == Some(true));
+            for index in unique {
+                // a missing field equals a null one, as on mongo, where a sql
+                // null would never conflict
+                let columns = index
+                    .keys
+                    .keys()
+                    .map(|path| {
+                        format!(
+                            "(COALESCE(doc #> '{{{}}}', 'null'::jsonb))",
+                            path.replace('.', ",")
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let partial = match index
+                    .options
+                    .as_ref()
+                    .and_then(|o| o.partial_filter_expression.as_ref())
+                {
+                    Some(filter) => format!(" WHERE {}", partial_condition(filter)?),
+                    None => String::new(),
+                };
+                sqlx::query(&format!(
+                    r#"CREATE UNIQUE INDEX IF NOT EXISTS "{}_{}" ON {} ({}){}"#,
+                    collection,
+                    index_name(index),
+                    table,
+                    columns.join(", "),
+                    partial
+                ))
+                .execute(&self.pool)
+                .await
+                .map_err(pg_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn ping(&self) -> ServiceResult<()> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(pg_error)?;
+        Ok(())
+    }
+
+    async fn shutdown(&self) {
+        self.pool.close().await;
+    }
+}
+
+/// The store within a transaction of [`PostgresStore::begin`], its operations
+/// run one after another on the connection of the transaction. Rolled back
+/// when dropped before the commit.
+struct PostgresTxn {
+    store: PostgresStore,
+    // none once committed or rolled back
+    tx: tokio::sync::Mutex<Option<Transaction<'static, Postgres>>>,
+}
+
+impl std::fmt::Debug for PostgresTxn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostgresTxn").finish()
+    }
+}
+
+impl PostgresTxn {
+    async fn connection(
+        &self,
+    ) -> ServiceResult<MappedMutexGuard<'_, Transaction<'static, Postgres>>> {
+        MutexGuard::try_map(self.tx.lock().await, Option::as_mut)
+            .map_err(|_| ServiceError::InternalServerError("The transaction has ended".to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl DocumentStore for PostgresTxn {
+    async fn scan(&self, collection: &str, query: &Query) -> ServiceResult<Scan> {
+        let mut conn = self.connection().await?;
+        self.store.scan_on(&mut conn, collection, query).await
+    }
+
+    async fn count(&self, collection: &str, filter: &Document) -> ServiceResult<Option<u64>> {
+        let mut conn = self.connection().await?;
+        self.store.count_on(&mut conn, collection, filter).await
+    }
+
+    async fn insert(&self, collection: &str, docs: Vec<Document>) -> ServiceResult<bool> {
+        let mut conn = self.connection().await?;
+        self.store.insert_on(&mut conn, collection, docs).await
+    }
+
+    async fn modify(
+        &self,
+        collection: &str,
+        filter: &Document,
+        many: bool,
+        change: &Change,
+    ) -> ServiceResult<u64> {
+        let mut conn = self.connection().await?;
+        self.store
+            .modify_on(&mut conn, collection, filter, many, change)
+            .await
+    }
+
+    async fn remove(
+        &self,
+        collection: &str,
+        filter: &Document,
+        many: bool,
+        select: &Select,
+    ) -> ServiceResult<u64> {
+        let mut conn = self.connection().await?;
+        self.store
+            .remove_on(&mut conn, collection, filter, many, select)
+            .await
+    }
+
+    async fn replace(&self, collections: Vec<(String, Vec<Document>)>) -> ServiceResult<()> {
+        let mut conn = self.connection().await?;
+        self.store.replace_on(&mut conn, collections).await
+    }
+
+    async fn begin(&self) -> ServiceResult<Option<Arc<dyn DocumentStore>>> {
+        Err(ServiceError::InternalServerError(
+            "A transaction is already running".to_string(),
+        ))
+    }
+
+    async fn commit(&self) -> ServiceResult<()> {
+        match self.tx.lock().await.take() {
+            Some(tx) => tx.commit().await.map_err(pg_error),
+            None => Ok(()),
+        }
+    }
+
+    async fn rollback(&self) {
+        let Some(tx) = self.tx.lock().await.take() else {
+            return;
+        };
+        if let Err(e) = tx.rollback().await {
+            tracing::warn!("Failed to roll back transaction: {}", e);
+        }
+    }
+
+    async fn put_blob(&self, key: &str, bytes: &[u8]) -> ServiceResult<()> {
+        let mut conn = self.connection().await?;
+        self.store.put_blob_on(&mut conn, key, bytes).await
+    }
+
+    async fn get_blob(&self, key: &str) -> ServiceResult<Option<Vec<u8>>> {
+        let mut conn = self.connection().await?;
+        self.store.get_blob_on(&mut conn, key).await
+    }
+
+    async fn delete_blob(&self, key: &str) -> ServiceResult<()> {
+        let mut conn = self.connection().await?;
+        self.store.delete_blob_on(&mut conn, key).await
+    }
+
+    async fn list_blobs(&self) -> ServiceResult<Vec<String>> {
+        let mut conn = self.connection().await?;
+        self.store.list_blobs_on(&mut conn).await
+    }
+
+    async fn ensure_indexes(
+        &self,
+        indexes: &[(&'static str, Vec<IndexModel>)],
+    ) -> ServiceResult<()> {
+        self.store.ensure_indexes(indexes).await
+    }
+
+    async fn ping(&self) -> ServiceResult<()> {
+        self.store.ping().await
+    }
+
+    // the pool belongs to the store
+    async fn shutdown(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::doc;
+
+    use super::*;
+    use crate::model::constant::{EXISTS_OP, TEXT_OP};
+
+    fn translate(filter: Document) -> (String, usize, bool) {
+        let mut clause = Where::new();
+        let condition = clause.filter(&filter);
+        (condition, clause.params.len(), clause.exact)
+    }
+
+    #[test]
+    fn test_where() {
+        let (condition, params, exact) = translate(doc! { "_id": "p1", "user_id": "u1" });
+        assert_eq!(condition, "id = $1 AND (doc @> $2 OR doc @> $3)");
+        assert_eq!((params, exact), (3, true));
+
+        let (condition, params, exact) =
+            translate(doc! { "folder_id": { IN_OP: ["f1", "f2"] }, "starred": true });
+        assert!(condition.contains(" OR "));
+        assert_eq!((params, exact), (6, true));
+        assert_eq!(translate(doc! { "folder_id": { IN_OP: [] } }).0, "FALSE");
+
+        // the items of an array compare too, the page is taken by the caller
+        let now = bson::DateTime::now();
+        let (condition, params, exact) = translate(doc! { "created_at": { LT_OP: now } });
+        assert!(condition.contains("timestamptz"));
+        assert_eq!((params, exact), (2, false));
+
+        let (condition, params, exact) = translate(doc! {
+            OR_OP: [{ "user_id": "u1" }, { "shared": { EXISTS_OP: true } }],
+            TEXT_OP: { "$search": "attention" },
+        });
+        assert_eq!(condition, "(((doc @> $1 OR doc @> $2)) OR (TRUE))");
+        assert_eq!((params, exact), (2, false));
+    }
+
+    #[test]
+    fn test_order_by() {
+        let order = order_by(&doc! { "created_at": -1, "_id": -1 }).unwrap();
+        assert!(order.starts_with("CASE WHEN (doc -> 'created_at') IS NULL"));
+        assert!(order.contains("(doc -> '_id') DESC"));
+        assert!(order.ends_with(", id"));
+        assert!(order_by(&doc! { "author.name": 1 }).is_none());
+    }
+
+    #[test]
+    fn test_partial_condition() {
+        let condition = partial_condition(&doc! { "email": { TYPE_OP: "string" } }).unwrap();
+        assert_eq!(condition, "jsonb_typeof(doc #> '{email}') = 'string'");
+        assert!(partial_condition(&doc! { "email": { EXISTS_OP: true } }).is_err());
+    }
+}
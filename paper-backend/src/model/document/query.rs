@@ -0,0 +1,612 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    sync::LazyLock,
+};
+
+use bson::{Bson, Document};
+
+use crate::{
+    error::{ServiceError, ServiceResult},
+    model::{constant::*, indexes::declared_indexes},
+};
+
+pub(crate) const EQ_OP: &str = "$eq";
+pub(crate) const GT_OP: &str = "$gt";
+const SEARCH_OP: &str = "$search";
+// textual value of the fields of a text index
+const TEXT_INDEX_KEY: &str = "text";
+
+/// Fields of the text index of a collection, with their weights.
+#[derive(Debug, Clone, Default)]
+pub struct TextIndex {
+    fields: Vec<(String, f64)>,
+}
+
+static TEXT_INDEXES: LazyLock<HashMap<&'static str, TextIndex>> = LazyLock::new(|| {
+    declared_indexes()
+        .into_iter()
+        .filter_map(|(collection, indexes)| {
+            let index = indexes.into_iter().find(|index| {
+                index
+                    .keys
+                    .values()
+                    .any(|kind| kind.as_str() == Some(TEXT_INDEX_KEY))
+            })?;
+            let weights = index.options.and_then(|o| o.weights).unwrap_or_default();
+            let fields = index
+                .keys
+                .iter()
+                .filter(|(_, kind)| kind.as_str() == Some(TEXT_INDEX_KEY))
+                .map(|(field, _)| {
+                    let weight = weights.get(field).and_then(number).unwrap_or(1.0);
+                    (field.clone(), weight)
+                })
+                .collect();
+            Some((collection, TextIndex { fields }))
+        })
+        .collect()
+});
+
+/// The text index declared on the collection, searched by `$text`.
+pub fn text_index(collection: &str) -> Option<&'static TextIndex> {
+    TEXT_INDEXES.get(collection)
+}
+
+/// A find on a collection, run with the semantics of mongo for the operators
+/// the repositories use.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub filter: Document,
+    pub sort: Option<Document>,
+    pub skip: Option<u64>,
+    pub limit: Option<i64>,
+    // best matches of the `$text` search first, their relevance set as `score`
+    pub text_score: bool,
+}
+
+impl Query {
+    pub fn new(filter: Document) -> Self {
+        Query {
+            filter,
+            ..Default::default()
+        }
+    }
+
+    pub fn sort(mut self, sort: Document) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+
+    pub fn skip(mut self, skip: u64) -> Self {
+        self.skip = Some(skip);
+        self
+    }
+
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn text_score(mut self) -> Self {
+        self.text_score = true;
+        self
+    }
+}
+
+fn unsupported(operator: &str) -> ServiceError {
+    ServiceError::InternalServerError(format!("Unsupported query operator {}", operator))
+}
+
+fn number(value: &Bson) -> Option<f64> {
+    match value {
+        Bson::Int32(n) => Some(f64::from(*n)),
+        Bson::Int64(n) => Some(*n as f64),
+        Bson::Double(n) => Some(*n),
+        _ => None,
+    }
+}
+
+// the order of the types when sorting, as in mongo
+fn type_rank(value: &Bson) -> u8 {
+    match value {
+        Bson::Null | Bson::Undefined => 1,
+        Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_) | Bson::Decimal128(_) => 2,
+        Bson::String(_) | Bson::Symbol(_) => 3,
+        Bson::Document(_) => 4,
+        Bson::Array(_) => 5,
+        Bson::Binary(_) => 6,
+        Bson::ObjectId(_) => 7,
+        Bson::Boolean(_) => 8,
+        Bson::DateTime(_) => 9,
+        Bson::Timestamp(_) => 10,
+        _ => 11,
+    }
+}
+
+/// The alias of the type of the value given to `$type`, e.g. `string`.
+fn type_alias(value: &Bson) -> &'static str {
+    match value {
+        Bson::Double(_) => "double",
+        Bson::String(_) => "string",
+        Bson::Document(_) => "object",
+        Bson::Array(_) => "array",
+        Bson::Binary(_) => "binData",
+        Bson::ObjectId(_) => "objectId",
+        Bson::Boolean(_) => "bool",
+        Bson::DateTime(_) => "date",
+        Bson::Null => "null",
+        Bson::Int32(_) => "int",
+        Bson::Int64(_) => "long",
+        Bson::Decimal128(_) => "decimal",
+        Bson::Timestamp(_) => "timestamp",
+        _ => "other",
+    }
+}
+
+/// Order of two values, those of different types by the rank of their type.
+pub fn compare(a: &Bson, b: &Bson) -> Ordering {
+    let (rank_a, rank_b) = (type_rank(a), type_rank(b));
+    if rank_a != rank_b {
+        return rank_a.cmp(&rank_b);
+    }
+    match (a, b) {
+        (Bson::String(a), Bson::String(b)) => a.cmp(b),
+        (Bson::Boolean(a), Bson::Boolean(b)) => a.cmp(b),
+        (Bson::DateTime(a), Bson::DateTime(b)) => a.cmp(b),
+        (Bson::ObjectId(a), Bson::ObjectId(b)) => a.bytes().cmp(&b.bytes()),
+        (Bson::Binary(a), Bson::Binary(b)) => a.bytes.cmp(&b.bytes),
+        (Bson::Array(a), Bson::Array(b)) => a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| compare(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        (Bson::Document(a), Bson::Document(b)) => a
+            .iter()
+            .zip(b)
+            .map(|((key_a, a), (key_b, b))| key_a.cmp(key_b).then_with(|| compare(a, b)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        (a, b) => match (number(a), number(b)) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            _ => Ordering::Equal,
+        },
+    }
+}
+
+fn lookup<'a>(value: &'a Bson, path: &[&str], found: &mut Vec<&'a Bson>) {
+    let Some((field, rest)) = path.split_first() else {
+        // an array matches by itself and by each of its items
+        if let Bson::Array(items) = value {
+            found.extend(items);
+        }
+        found.push(value);
+        return;
+    };
+    match value {
+        Bson::Document(doc) => {
+            if let Some(value) = doc.get(*field) {
+                lookup(value, rest, found);
+            }
+        }
+        Bson::Array(items) => match field.parse::<usize>() {
+            Ok(index) => {
+                if let Some(item) = items.get(index) {
+                    lookup(item, rest, found);
+                }
+            }
+            Err(_) => {
+                for item in items
+                    .iter()
+                    .filter(|item| matches!(item, Bson::Document(_)))
+                {
+                    lookup(item, path, found);
+                }
+            }
+        },
+        _ => {}
+    }
+}
+
+/// The values at the dotted path, through the arrays on the way. Empty when
+/// the document has no such field.
+pub fn values<'a>(doc: &'a Document, path: &str) -> Vec<&'a Bson> {
+    let path = path.split('.').collect::<Vec<_>>();
+    let mut found = Vec::new();
+    if let Some(value) = doc.get(path[0]) {
+        lookup(value, &path[1..], &mut found);
+    }
+    found
+}
+
+/// The operators of a condition, none for a value to equal.
+pub(crate) fn operators(condition: &Bson) -> Option<&Document> {
+    match condition {
+        Bson::Document(doc) if doc.keys().next().is_some_and(|key| key.starts_with('$')) => {
+            Some(doc)
+        }
+        _ => None,
+    }
+}
+
+fn list<'a>(operator: &str, operand: &'a Bson) -> ServiceResult<&'a Vec<Bson>> {
+    operand.as_array().ok_or_else(|| unsupported(operator))
+}
+
+// null matches a missing field too
+fn equals(values: &[&Bson], target: &Bson) -> bool {
+    match target {
+        Bson::Null => values.is_empty() || values.iter().any(|v| matches!(v, Bson::Null)),
+        target => values
+            .iter()
+            .any(|v| type_rank(v) == type_rank(target) && compare(v, target).is_eq()),
+    }
+}
+
+// only values of the same type compare
+fn any_compares(values: &[&Bson], target: &Bson, accept: fn(Ordering) -> bool) -> bool {
+    values
+        .iter()
+        .any(|v| type_rank(v) == type_rank(target) && accept(compare(v, target)))
+}
+
+fn matches_condition(values: &[&Bson], condition: &Bson) -> ServiceResult<bool> {
+    let Some(operators) = operators(condition) else {
+        return Ok(equals(values, condition));
+    };
+    for (operator, operand) in operators {
+        let matched = match operator.as_str() {
+            EQ_OP => equals(values, operand),
+            NE_OP => !equals(values, operand),
+            GT_OP => any_compares(values, operand, Ordering::is_gt),
+            GTE_OP => any_compares(values, operand, Ordering::is_ge),
+            LT_OP => any_compares(values, operand, Ordering::is_lt),
+            LTE_OP => any_compares(values, operand, Ordering::is_le),
+            IN_OP => list(operator, operand)?
+                .iter()
+                .any(|item| equals(values, item)),
+            NIN_OP => !list(operator, operand)?
+                .iter()
+                .any(|item| equals(values, item)),
+            ALL_OP => list(operator, operand)?
+                .iter()
+                .all(|item| equals(values, item)),
+            EXISTS_OP => !values.is_empty() == operand.as_bool().unwrap_or(true),
+            TYPE_OP => {
+                let alias = operand.as_str().ok_or_else(|| unsupported(operator))?;
+                values.iter().any(|value| type_alias(value) == alias)
+            }
+            operator => return Err(unsupported(operator)),
+        };
+        if !matched {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn clauses<'a>(operator: &str, condition: &'a Bson) -> ServiceResult<Vec<&'a Document>> {
+    list(operator, condition)?
+        .iter()
+        .map(|clause| clause.as_document().ok_or_else(|| unsupported(operator)))
+        .collect()
+}
+
+fn search_terms<'a>(condition: &'a Bson) -> ServiceResult<&'a str> {
+    condition
+        .as_document()
+        .and_then(|search| search.get_str(SEARCH_OP).ok())
+        .ok_or_else(|| unsupported(TEXT_OP))
+}
+
+/// Whether the document matches the filter, `text` being the text index of
+/// its collection for the `$text` searches.
+pub fn matches(doc: &Document, filter: &Document, text: Option<&TextIndex>) -> ServiceResult<bool> {
+    for (key, condition) in filter {
+        let matched = match key.as_str() {
+            AND_OP => {
+                let mut all = true;
+                for clause in clauses(key, condition)? {
+                    if !matches(doc, clause, text)? {
+                        all = false;
+                        break;
+                    }
+                }
+                all
+            }
+            OR_OP => {
+                let mut any = false;
+                for clause in clauses(key, condition)? {
+                    if matches(doc, clause, text)? {
+                        any = true;
+                        break;
+                    }
+                }
+                any
+            }
+            TEXT_OP => text_score(doc, text, search_terms(condition)?) > 0.0,
+            operator if operator.starts_with('$') => return Err(unsupported(operator)),
+            path => matches_condition(&values(doc, path), condition)?,
+        };
+        if !matched {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Relevance of the document to the search, by the weights of the fields its
+/// terms appear in. 0 when none appears.
+pub fn text_score(doc: &Document, text: Option<&TextIndex>, search: &str) -> f64 {
+    let Some(text) = text else {
+        return 0.0;
+    };
+    let searched = terms(search).collect::<HashSet<_>>();
+    let mut score = 0.0;
+    for (field, weight) in &text.fields {
+        for value in values(doc, field) {
+            let Bson::String(value) = value else {
+                continue;
+            };
+            let hits = terms(value).filter(|term| searched.contains(term)).count();
+            if hits > 0 {
+                score += weight * (1.0 + (hits as f64).ln());
+            }
+        }
+    }
+    score
+}
+
+fn sort_key<'a>(doc: &'a Document, path: &str) -> &'a Bson {
+    // the whole value comes last, an array sorts as itself
+    values(doc, path).last().copied().unwrap_or(&Bson::Null)
+}
+
+/// Order of two documents for a sort, e.g. `{ "created_at": -1 }`.
+pub fn compare_by(a: &Document, b: &Document, sort: &Document) -> Ordering {
+    for (path, direction) in sort {
+        let ordering = compare(sort_key(a, path), sort_key(b, path));
+        let ordering = match number(direction).is_some_and(|d| d < 0.0) {
+            true => ordering.reverse(),
+            false => ordering,
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Run the query on documents of a collection, `text` being its text index.
+pub fn select(
+    docs: Vec<Document>,
+    query: &Query,
+    text: Option<&TextIndex>,
+) -> ServiceResult<Vec<Document>> {
+    let mut selected = Vec::new();
+    for doc in docs {
+        if matches(&doc, &query.filter, text)? {
+            selected.push(doc);
+        }
+    }
+    if query.text_score {
+        let search = match query.filter.get(TEXT_OP) {
+            Some(condition) => search_terms(condition)?,
+            None => "",
+        };
+        for doc in &mut selected {
+            let score = text_score(doc, text, search);
+            doc.insert("score", score);
+        }
+        let score = |doc: &Document| doc.get_f64("score").unwrap_or_default();
+        selected.sort_by(|a, b| score(b).total_cmp(&score(a)));
+    } else if let Some(sort) = &query.sort {
+        selected.sort_by(|a, b| compare_by(a, b, sort));
+    }
+    if let Some(skip) = query.skip {
+        selected.drain(..selected.len().min(skip as usize));
+    }
+    if let Some(limit) = query.limit.filter(|limit| *limit > 0) {
+        selected.truncate(limit as usize);
+    }
+    Ok(selected)
+}
+
+// the document holding the last field of the path, created on the way
+fn parent_mut<'a>(
+    doc: &'a mut Document,
+    path: &'a str,
+) -> ServiceResult<(&'a mut Document, &'a str)> {
+    let (parents, field) = match path.rsplit_once('.') {
+        Some((parents, field)) => (Some(parents), field),
+        None => (None, path),
+    };
+    let mut current = doc;
+    for part in parents.into_iter().flat_map(|parents| parents.split('.')) {
+        if !matches!(current.get(part), Some(Bson::Document(_))) {
+            current.insert(part, Document::new());
+        }
+        current = current
+            .get_document_mut(part)
+            .map_err(|e| ServiceError::InternalServerError(e.to_string()))?;
+    }
+    Ok((current, field))
+}
+
+fn set_path(doc: &mut Document, path: &str, value: Bson) -> ServiceResult<()> {
+    let (parent, field) = parent_mut(doc, path)?;
+    parent.insert(field, value);
+    Ok(())
+}
+
+fn add(current: Option<&Bson>, increment: &Bson) -> ServiceResult<Bson> {
+    match (current, increment) {
+        (None | Some(Bson::Null), increment) => Ok(increment.clone()),
+        (Some(Bson::Int32(a)), Bson::Int32(b)) => Ok(a
+            .checked_add(*b)
+            .map_or(Bson::Int64(i64::from(*a) + i64::from(*b)), Bson::Int32)),
+        (Some(Bson::Int32(_) | Bson::Int64(_)), Bson::Int32(_) | Bson::Int64(_)) => {
+            let int = |value: &Bson| value.as_i64().or(value.as_i32().map(i64::from));
+            Ok(Bson::Int64(
+                int(current.unwrap_or(&Bson::Null)).unwrap_or_default()
+                    + int(increment).unwrap_or_default(),
+            ))
+        }
+        (Some(current), increment) => match (number(current), number(increment)) {
+            (Some(a), Some(b)) => Ok(Bson::Double(a + b)),
+            _ => Err(unsupported(INC_OP)),
+        },
+    }
+}
+
+fn array_mut<'a>(doc: &'a mut Document, path: &'a str) -> ServiceResult<&'a mut Vec<Bson>> {
+    let (parent, field) = parent_mut(doc, path)?;
+    if !matches!(parent.get(field), Some(Bson::Array(_))) {
+        parent.insert(field, Bson::Array(Vec::new()));
+    }
+    parent
+        .get_array_mut(field)
+        .map_err(|e| ServiceError::InternalServerError(e.to_string()))
+}
+
+/// Apply the update operators to the document, e.g. `{ "$set": { "read": true } }`.
+pub fn apply_update(doc: &mut Document, update: &Document) -> ServiceResult<()> {
+    for (operator, fields) in update {
+        let fields = fields.as_document().ok_or_else(|| unsupported(operator))?;
+        for (path, value) in fields {
+            match operator.as_str() {
+                SET_OP => set_path(doc, path, value.clone())?,
+                UNSET_OP => {
+                    let (parent, field) = parent_mut(doc, path)?;
+                    parent.remove(field);
+                }
+                INC_OP => {
+                    let sum = add(values(doc, path).last().copied(), value)?;
+                    set_path(doc, path, sum)?;
+                }
+                ADD_TO_SET_OP => {
+                    let items = match value {
+                        Bson::Document(each) if each.contains_key(EACH_OP) => {
+                            list(EACH_OP, each.get(EACH_OP).unwrap_or(&Bson::Null))?.clone()
+                        }
+                        value => vec![value.clone()],
+                    };
+                    let array = array_mut(doc, path)?;
+                    for item in items {
+                        if !array
+                            .iter()
+                            .any(|existing| compare(existing, &item).is_eq())
+                        {
+                            array.push(item);
+                        }
+                    }
+                }
+                PULL_OP => {
+                    if operators(value).is_some() {
+                        return Err(unsupported(PULL_OP));
+                    }
+                    array_mut(doc, path)?.retain(|item| !equals(&[item], value));
+                }
+                operator => return Err(unsupported(operator)),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The document inserted by an upsert matching none: the equalities of the
+/// filter, with the update applied.
+pub fn upserted(filter: &Document, update: &Document) -> ServiceResult<Document> {
+    let mut doc = Document::new();
+    for (path, value) in filter {
+        if !path.starts_with('$') && operators(value).is_none() {
+            set_path(&mut doc, path, value.clone())?;
+        }
+    }
+    apply_update(&mut doc, update)?;
+    Ok(doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::doc;
+
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let now = bson::DateTime::now();
+        let paper = doc! {
+            "_id": "p1",
+            "user_id": "u1",
+            "tags": ["ml", "nlp"],
+            "starred": true,
+            "created_at": now,
+            "custom_fields": { "pages": 12.0 },
+            "title": "Attention is all you need",
+        };
+        let check = |filter: Document| matches(&paper, &filter, None).unwrap();
+        assert!(check(doc! { "user_id": "u1", "tags": "ml" }));
+        assert!(!check(doc! { "user_id": "u2" }));
+        assert!(check(doc! { "tags": { ALL_OP: ["ml", "nlp"] } }));
+        assert!(!check(doc! { "tags": { ALL_OP: ["ml", "cv"] } }));
+        assert!(check(doc! { "_id": { IN_OP: ["p0", "p1"] } }));
+        assert!(check(
+            doc! { "folder_id": null, "starred": { NE_OP: false } }
+        ));
+        assert!(check(
+            doc! { "custom_fields.pages": { GTE_OP: 10, LTE_OP: 12 } }
+        ));
+        assert!(!check(doc! { "created_at": { LT_OP: now } }));
+        assert!(check(
+            doc! { OR_OP: [{ "user_id": "u2" }, { "version": { EXISTS_OP: false } }] }
+        ));
+        assert!(check(doc! { "title": { TYPE_OP: "string" } }));
+        assert!(!check(doc! { "folder_id": { TYPE_OP: "string" } }));
+
+        let text = TextIndex {
+            fields: vec![("title".to_string(), 10.0)],
+        };
+        let search = |q: &str| doc! { TEXT_OP: { SEARCH_OP: q } };
+        assert!(matches(&paper, &search("attention models"), Some(&text)).unwrap());
+        assert!(!matches(&paper, &search("diffusion"), Some(&text)).unwrap());
+        assert!(matches(&paper, &doc! { "$where": "true" }, None).is_err());
+    }
+
+    #[test]
+    fn test_apply_update() {
+        let mut folder = doc! { "_id": "f1", "version": 1, "admin_ids": ["a", "b"] };
+        let update = doc! {
+            SET_OP: { "name": "papers", "style.color": "#fff" },
+            INC_OP: { "version": 1 },
+            ADD_TO_SET_OP: { "tags": { EACH_OP: ["x", "x", "y"] } },
+            PULL_OP: { "admin_ids": "a" },
+        };
+        apply_update(&mut folder, &update).unwrap();
+        apply_update(&mut folder, &doc! { UNSET_OP: { "style.color": "" } }).unwrap();
+        assert_eq!(
+            folder,
+            doc! {
+                "_id": "f1",
+                "version": 2,
+                "admin_ids": ["b"],
+                "name": "papers",
+                "style": {},
+                "tags": ["x", "y"],
+            }
+        );
+
+        let upsert = upserted(
+            &doc! { "_id": "a1", "at": { GTE_OP: 1 } },
+            &doc! { SET_OP: { "n": 1 } },
+        );
+        assert_eq!(upsert.unwrap(), doc! { "_id": "a1", "n": 1 });
+    }
+}
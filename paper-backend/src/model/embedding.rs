@@ -0,0 +1,95 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+/// Embedding of the text of a paper, for semantic search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperEmbedding {
+    #[serde(rename = "_id")]
+    pub paper_id: String,
+    pub user_id: String,
+    pub updated_at: bson::DateTime,
+
+    pub vector: Vec<f32>,
+}
+
+#[async_trait::async_trait]
+pub trait PaperEmbeddingRepository: Send + Sync {
+    async fn upsert_paper_embedding(&self, embedding: PaperEmbedding) -> ServiceResult<()>;
+    async fn delete_paper_embedding(&self, paper_id: &str) -> ServiceResult<()>;
+    async fn get_paper_embedding(&self, paper_id: &str) -> ServiceResult<Option<PaperEmbedding>>;
+    /// The embeddings of all the papers of the user.
+    async fn get_user_embeddings(&self, user_id: &str) -> ServiceResult<Vec<PaperEmbedding>>;
+}
+
+#[async_trait::async_trait]
+impl PaperEmbeddingRepository for MongoClient {
+    async fn upsert_paper_embedding(&self, embedding: PaperEmbedding) -> ServiceResult<()> {
+        let filter = doc! { "_id": &embedding.paper_id };
+        self.collection::<PaperEmbedding>(PAPER_EMBEDDING_COLLECTION_NAME)
+            .replace_one(filter, embedding)
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_paper_embedding(&self, paper_id: &str) -> ServiceResult<()> {
+        let filter = doc! { "_id": paper_id };
+        self.collection::<PaperEmbedding>(PAPER_EMBEDDING_COLLECTION_NAME)
+            .delete_one(filter)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_paper_embedding(&self, paper_id: &str) -> ServiceResult<Option<PaperEmbedding>> {
+        let embedding = self
+            .collection::<PaperEmbedding>(PAPER_EMBEDDING_COLLECTION_NAME)
+            .find_one(doc! { "_id": paper_id })
+            .await?;
+        Ok(embedding)
+    }
+
+    async fn get_user_embeddings(&self, user_id: &str) -> ServiceResult<Vec<PaperEmbedding>> {
+        let cursor = self
+            .collection::<PaperEmbedding>(PAPER_EMBEDDING_COLLECTION_NAME)
+            .find(doc! { "user_id": user_id })
+            .await?;
+        let embeddings = cursor.try_collect().await?;
+        Ok(embeddings)
+    }
+}
+
+#[async_trait::async_trait]
+impl PaperEmbeddingRepository for DocumentDatabase {
+    async fn upsert_paper_embedding(&self, embedding: PaperEmbedding) -> ServiceResult<()> {
+        let filter = doc! { "_id": &embedding.paper_id };
+        self.replace_one(PAPER_EMBEDDING_COLLECTION_NAME, filter, &embedding, true)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_paper_embedding(&self, paper_id: &str) -> ServiceResult<()> {
+        self.delete_one(PAPER_EMBEDDING_COLLECTION_NAME, doc! { "_id": paper_id })
+            .await?;
+        Ok(())
+    }
+
+    async fn get_paper_embedding(&self, paper_id: &str) -> ServiceResult<Option<PaperEmbedding>> {
+        self.find_one(PAPER_EMBEDDING_COLLECTION_NAME, doc! { "_id": paper_id })
+            .await
+    }
+
+    async fn get_user_embeddings(&self, user_id: &str) -> ServiceResult<Vec<PaperEmbedding>> {
+        let query = Query::new(doc! { "user_id": user_id });
+        self.find(PAPER_EMBEDDING_COLLECTION_NAME, query).await
+    }
+}
@@ -0,0 +1,212 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+pub use paper_schema::export::{ExportKind, ExportStatus};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    export::ExportFormat,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+    utils::request_id::current_request_id,
+};
+
+pub mod schema {
+    pub use paper_schema::export::*;
+
+    use crate::model::export::{ExportJob, ExportStatus};
+
+    impl From<ExportJob> for ExportJobResponse {
+        fn from(job: ExportJob) -> Self {
+            ExportJobResponse {
+                download_url: (job.status == ExportStatus::Ready)
+                    .then(|| format!("/api/exports/{}/download", job.id)),
+                id: job.id,
+                kind: job.kind,
+                resource_id: job.resource_id,
+                format: job.format,
+                status: job.status,
+                file_name: job.file_name,
+                size: job.size,
+                error: job.error,
+                created_at: job.created_at.timestamp_millis(),
+                finished_at: job.finished_at.map(|t| t.timestamp_millis()),
+            }
+        }
+    }
+}
+
+/// Key of the archive built by an export.
+pub fn export_file_key(export_id: &str) -> String {
+    format!("export/{}", export_id)
+}
+
+/// An archive built in the background for the user to download.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportJob {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String,
+    pub created_at: bson::DateTime,
+    pub finished_at: Option<bson::DateTime>,
+
+    #[serde(default)]
+    pub kind: ExportKind,
+    // uuid of the folder exported, or of the user for an account export
+    pub resource_id: String,
+    pub format: ExportFormat,
+    pub status: ExportStatus,
+    pub file_name: String,
+    pub size: Option<u64>,
+    pub error: Option<String>,
+    // the request which started the export, to trace a failure
+    #[serde(default)]
+    pub request_id: Option<String>,
+}
+
+impl ExportJob {
+    pub fn new(
+        user_id: &str,
+        kind: ExportKind,
+        resource_id: &str,
+        format: ExportFormat,
+        file_name: String,
+    ) -> Self {
+        ExportJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+            finished_at: None,
+
+            kind,
+            resource_id: resource_id.to_string(),
+            format,
+            status: ExportStatus::Pending,
+            file_name,
+            size: None,
+            error: None,
+            request_id: current_request_id(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait ExportRepository: Send + Sync {
+    async fn create_export(&self, job: ExportJob) -> ServiceResult<()>;
+    async fn get_export(&self, user_id: &str, id: &str) -> ServiceResult<Option<ExportJob>>;
+    /// Record the end of the export, with the size of the archive or the error.
+    async fn finish_export(
+        &self,
+        id: &str,
+        status: ExportStatus,
+        size: Option<u64>,
+        error: Option<String>,
+    ) -> ServiceResult<()>;
+    /// The exports started before the date, whatever their status.
+    async fn get_expired_exports(&self, before: bson::DateTime) -> ServiceResult<Vec<ExportJob>>;
+    async fn delete_export(&self, id: &str) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl ExportRepository for MongoClient {
+    async fn create_export(&self, job: ExportJob) -> ServiceResult<()> {
+        self.collection::<ExportJob>(EXPORT_COLLECTION_NAME)
+            .insert_one(job)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_export(&self, user_id: &str, id: &str) -> ServiceResult<Option<ExportJob>> {
+        let job = self
+            .collection::<ExportJob>(EXPORT_COLLECTION_NAME)
+            .find_one(doc! { "_id": id, "user_id": user_id })
+            .await?;
+        Ok(job)
+    }
+
+    async fn finish_export(
+        &self,
+        id: &str,
+        status: ExportStatus,
+        size: Option<u64>,
+        error: Option<String>,
+    ) -> ServiceResult<()> {
+        let update = doc! {
+            SET_OP: {
+                "status": bson::to_bson(&status)?,
+                "size": size.map(|s| s as i64),
+                "error": error,
+                "finished_at": bson::DateTime::now(),
+            }
+        };
+        self.collection::<ExportJob>(EXPORT_COLLECTION_NAME)
+            .update_one(doc! { "_id": id }, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_expired_exports(&self, before: bson::DateTime) -> ServiceResult<Vec<ExportJob>> {
+        let cursor = self
+            .collection::<ExportJob>(EXPORT_COLLECTION_NAME)
+            .find(doc! { "created_at": { LT_OP: before } })
+            .await?;
+        let jobs = cursor.try_collect().await?;
+        Ok(jobs)
+    }
+
+    async fn delete_export(&self, id: &str) -> ServiceResult<()> {
+        self.collection::<ExportJob>(EXPORT_COLLECTION_NAME)
+            .delete_one(doc! { "_id": id })
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ExportRepository for DocumentDatabase {
+    async fn create_export(&self, job: ExportJob) -> ServiceResult<()> {
+        self.insert(EXPORT_COLLECTION_NAME, &job).await
+    }
+
+    async fn get_export(&self, user_id: &str, id: &str) -> ServiceResult<Option<ExportJob>> {
+        self.find_one(
+            EXPORT_COLLECTION_NAME,
+            doc! { "_id": id, "user_id": user_id },
+        )
+        .await
+    }
+
+    async fn finish_export(
+        &self,
+        id: &str,
+        status: ExportStatus,
+        size: Option<u64>,
+        error: Option<String>,
+    ) -> ServiceResult<()> {
+        let update = doc! {
+            SET_OP: {
+                "status": bson::to_bson(&status)?,
+                "size": size.map(|s| s as i64),
+                "error": error,
+                "finished_at": bson::DateTime::now(),
+            }
+        };
+        self.update_one(EXPORT_COLLECTION_NAME, doc! { "_id": id }, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_expired_exports(&self, before: bson::DateTime) -> ServiceResult<Vec<ExportJob>> {
+        let query = Query::new(doc! { "created_at": { LT_OP: before } });
+        self.find(EXPORT_COLLECTION_NAME, query).await
+    }
+
+    async fn delete_export(&self, id: &str) -> ServiceResult<()> {
+        self.delete_one(EXPORT_COLLECTION_NAME, doc! { "_id": id })
+            .await?;
+        Ok(())
+    }
+}
@@ -1,46 +1,65 @@
+use std::collections::HashSet;
+
 use ai_flow_synth::utils::MongoClient;
-use bson::doc;
+use bson::{Document, doc};
 use futures::TryStreamExt;
-use salvo::oapi::ToSchema;
+pub use paper_schema::folder::{FolderType, SmartQuery};
 use serde::{Deserialize, Serialize};
 
-use crate::{error::ServiceResult, model::constant::*};
-
-pub mod schema {
-    use salvo::{
-        Response, Scribe,
-        oapi::{ToResponse, ToSchema},
-        writing::Json,
-    };
-    use serde::{Deserialize, Serialize};
-
-    use crate::model::folder::{Folder, FolderType};
+use crate::{
+    error::{ServiceError, ServiceResult, is_duplicate_key},
+    i18n::{
+        DEFAULT_FOLDER_DESCRIPTION, DEFAULT_FOLDER_NAME, Locale, STARRED_FOLDER_DESCRIPTION,
+        STARRED_FOLDER_NAME,
+    },
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+        organization::FolderTemplate,
+        txn::{TxnContext, in_session},
+        version_filter,
+    },
+};
 
-    /// Response schema for a folder.
-    #[derive(Debug, Serialize, Deserialize, ToSchema, ToResponse)]
-    #[serde(rename_all = "camelCase")]
-    pub struct FolderResponse {
-        pub id: String,
-        pub parent_id: Option<String>,
+// id of the virtual system folder listing the starred papers
+pub const STARRED_FOLDER_ID: &str = "starred";
 
-        pub name: String,
-        pub description: Option<String>,
-        pub r#type: FolderType,
-    }
-
-    impl Scribe for FolderResponse {
-        fn render(self, res: &mut Response) {
-            res.render(Json(self));
-        }
-    }
+pub mod schema {
+    pub use paper_schema::folder::*;
 
-    #[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
-    pub struct ListFoldersResponse(pub Vec<FolderResponse>);
+    use crate::{
+        model::folder::Folder,
+        utils::{
+            fields::SparseFields,
+            validate::{ValidatedRequest, trim, trim_all, trim_option},
+        },
+    };
 
-    impl Scribe for ListFoldersResponse {
-        fn render(self, res: &mut Response) {
-            res.render(Json(self));
-        }
+    impl SparseFields for FolderResponse {
+        const FIELDS: &'static [(&'static str, &'static [&'static str])] = &[
+            ("id", &[]),
+            ("parentId", &["parent_id"]),
+            ("name", &["name"]),
+            ("description", &["description"]),
+            ("type", &["type"]),
+            ("query", &["query"]),
+            ("color", &["color"]),
+            ("icon", &["icon"]),
+            ("sortOrder", &["sort_order"]),
+            ("archived", &["archived"]),
+            ("version", &["version"]),
+            ("paperCount", &[]),
+            ("childCount", &["parent_id"]),
+        ];
+        const REQUIRED: &'static [&'static str] = &[
+            "user_id",
+            "created_at",
+            "updated_at",
+            "name",
+            "type",
+            "sort_order",
+            "version",
+        ];
     }
 
     impl From<Folder> for FolderResponse {
@@ -52,34 +71,86 @@ pub mod schema {
                 name: folder.name,
                 description: folder.description,
                 r#type: folder.r#type,
+                query: folder.query,
+                color: folder.color,
+                icon: folder.icon,
+                sort_order: folder.sort_order,
+                archived: folder.archived,
+                version: folder.version,
+                paper_count: None,
+                child_count: None,
             }
         }
     }
 
-    /// Create Folder Request schema.
-    /// default type is `user`
-    /// if parent_id is None, it will be created in the root folder.
-    #[derive(Debug, Serialize, Deserialize, ToSchema)]
-    #[serde(rename_all = "camelCase")]
-    pub struct CreateFolderRequest {
-        #[salvo(schema(example = "parent-folder-uuid"))]
-        pub parent_id: Option<String>, // uuid of parent folder
-        #[salvo(schema(example = "folder-name"))]
-        pub name: String,
-        #[salvo(schema(example = "This is a folder description."))]
-        pub description: Option<String>,
-    }
-
-    /// Update Folder Request schema.
-    #[derive(Debug, Serialize, Deserialize, ToSchema)]
-    #[serde(rename_all = "camelCase")]
-    pub struct UpdateFolderRequest {
-        #[salvo(schema(example = "parent-folder-uuid"))]
-        pub parent_id: Option<String>, // uuid of parent folder
-        #[salvo(schema(example = "folder-name"))]
-        pub name: Option<String>,
-        #[salvo(schema(example = "This is a folder description."))]
-        pub description: Option<String>,
+    impl ValidatedRequest for CreateFolderRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.parent_id);
+            trim(&mut self.name);
+            trim_option(&mut self.description);
+            trim_option(&mut self.color);
+            trim_option(&mut self.icon);
+            if let Some(query) = self.query.as_mut() {
+                normalize_query(query);
+            }
+        }
+    }
+
+    impl ValidatedRequest for MoveFolderRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.parent_id);
+        }
+    }
+
+    impl ValidatedRequest for CopyFolderRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.parent_id);
+        }
+    }
+
+    impl ValidatedRequest for UpdateFolderRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.parent_id);
+            if let Some(name) = self.name.as_mut() {
+                trim(name);
+            }
+            trim_option(&mut self.description);
+            trim_option(&mut self.color);
+            trim_option(&mut self.icon);
+            if let Some(query) = self.query.as_mut() {
+                normalize_query(query);
+            }
+        }
+    }
+
+    impl ValidatedRequest for ReorderFoldersRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.parent_id);
+            trim_all(&mut self.folder_ids);
+        }
+    }
+
+    fn normalize_node(node: &mut ImportFolderNode) {
+        trim(&mut node.key);
+        trim(&mut node.name);
+        trim_option(&mut node.description);
+        trim_option(&mut node.color);
+        trim_option(&mut node.icon);
+        node.children.iter_mut().for_each(normalize_node);
+    }
+
+    fn normalize_query(query: &mut SmartQuery) {
+        trim_option(&mut query.q);
+        trim_option(&mut query.folder_id);
+        trim_all(&mut query.tags);
+        trim_option(&mut query.field);
+    }
+
+    impl ValidatedRequest for ImportFoldersRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.parent_id);
+            self.folders.iter_mut().for_each(normalize_node);
+        }
     }
 }
 
@@ -95,10 +166,26 @@ pub struct Folder {
     pub name: String,
     pub description: Option<String>,
     pub r#type: FolderType,
+    // search listing the papers of a smart folder, which holds none itself
+    #[serde(default)]
+    pub query: Option<SmartQuery>,
+    #[serde(default)]
+    pub color: Option<String>, // #rrggbb
+    #[serde(default)]
+    pub icon: Option<String>,
+    // rank among the sibling folders, lowest first
+    #[serde(default)]
+    pub sort_order: u32,
+    // set once the project in this folder is wrapped up
+    #[serde(default)]
+    pub archived: bool,
+    // incremented on every update, guards against concurrent writes
+    #[serde(default)]
+    pub version: u32,
 }
 
 impl Folder {
-    pub fn default_system_folder(user_id: &str) -> Self {
+    pub fn default_system_folder(user_id: &str, locale: Locale) -> Self {
         Folder {
             id: uuid::Uuid::new_v4().to_string(),
             parent_id: None,
@@ -106,9 +193,104 @@ impl Folder {
             created_at: bson::DateTime::now(),
             updated_at: bson::DateTime::now(),
 
-            name: "默认".to_string(),
-            description: Some("System-defined folder.".to_string()),
+            name: locale.text(DEFAULT_FOLDER_NAME).to_string(),
+            description: Some(locale.text(DEFAULT_FOLDER_DESCRIPTION).to_string()),
             r#type: FolderType::SystemDefined,
+            query: None,
+            color: None,
+            icon: None,
+            sort_order: 0,
+            archived: false,
+            version: 0,
+        }
+    }
+
+    /// Virtual system folder listing the starred papers of the user, never stored.
+    pub fn starred_folder(user_id: &str, locale: Locale) -> Self {
+        Folder {
+            id: STARRED_FOLDER_ID.to_string(),
+            parent_id: None,
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::from_millis(0),
+            updated_at: bson::DateTime::from_millis(0),
+
+            name: locale.text(STARRED_FOLDER_NAME).to_string(),
+            description: Some(locale.text(STARRED_FOLDER_DESCRIPTION).to_string()),
+            r#type: FolderType::SystemDefined,
+            query: Some(SmartQuery::starred()),
+            color: None,
+            icon: Some("star".to_string()),
+            sort_order: 0,
+            archived: false,
+            version: 0,
+        }
+    }
+
+    /// Folder provisioned from the folder template of an organization.
+    pub fn new_from_template(
+        user_id: &str,
+        parent_id: Option<String>,
+        template: &FolderTemplate,
+    ) -> Self {
+        Folder {
+            id: uuid::Uuid::new_v4().to_string(),
+            parent_id,
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+            updated_at: bson::DateTime::now(),
+
+            name: template.name.clone(),
+            description: template.description.clone(),
+            r#type: FolderType::SystemDefined,
+            query: None,
+            color: None,
+            icon: None,
+            sort_order: 0,
+            archived: false,
+            version: 0,
+        }
+    }
+
+    /// Folder of an imported tree, created without its subfolders.
+    pub fn new_from_import(
+        user_id: &str,
+        parent_id: Option<String>,
+        node: &schema::ImportFolderNode,
+    ) -> Self {
+        Folder {
+            id: uuid::Uuid::new_v4().to_string(),
+            parent_id,
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+            updated_at: bson::DateTime::now(),
+
+            name: node.name.clone(),
+            description: node.description.clone(),
+            r#type: FolderType::UserDefined,
+            query: None,
+            color: node.color.clone(),
+            icon: node.icon.clone(),
+            sort_order: 0,
+            archived: false,
+            version: 0,
+        }
+    }
+
+    /// Copy of the folder under the parent, without its subfolders and papers.
+    /// Copies of the system folder are user defined.
+    pub fn copy_under(&self, parent_id: Option<String>) -> Self {
+        let r#type = match self.r#type {
+            FolderType::SystemDefined => FolderType::UserDefined,
+            ref other => other.clone(),
+        };
+        Folder {
+            id: uuid::Uuid::new_v4().to_string(),
+            parent_id,
+            r#type,
+            created_at: bson::DateTime::now(),
+            updated_at: bson::DateTime::now(),
+            version: 0,
+            ..self.clone()
         }
     }
 
@@ -122,44 +304,95 @@ impl Folder {
 
             name: request.name,
             description: request.description,
-            r#type: FolderType::UserDefined,
+            r#type: match request.query {
+                Some(_) => FolderType::Smart,
+                None => FolderType::UserDefined,
+            },
+            query: request.query,
+            color: request.color,
+            icon: request.icon,
+            sort_order: 0,
+            archived: false,
+            version: 0,
         }
     }
+
+    pub fn is_smart(&self) -> bool {
+        matches!(self.r#type, FolderType::Smart)
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
-pub enum FolderType {
-    #[serde(rename = "system")]
-    SystemDefined,
-    #[serde(rename = "user")]
-    UserDefined,
+/// Filter on the papers of the user matching the query, `scope` being the
+/// folders searched and `fields` the filter on custom fields.
+pub fn smart_query_filter(
+    query: &SmartQuery,
+    scope: Option<Vec<String>>,
+    fields: Option<Document>,
+) -> Document {
+    let mut filter = fields.unwrap_or_default();
+    if let Some(q) = query.q.as_deref() {
+        filter.insert(TEXT_OP, doc! { "$search": q });
+    }
+    if !query.tags.is_empty() {
+        filter.insert("tags", doc! { ALL_OP: &query.tags });
+    }
+    if let Some(starred) = query.starred {
+        filter.insert("starred", starred);
+    }
+    if let Some(scope) = scope {
+        filter.insert("folder_id", doc! { IN_OP: scope });
+    }
+    filter
 }
 
-pub async fn create_index(client: &MongoClient) -> ServiceResult<()> {
-    let collection = client.collection::<Folder>(FOLDER_COLLECTION_NAME);
-    let index = mongodb::IndexModel::builder()
-        .keys(doc! { "user_id": 1, "name": 1 })
-        .build();
-    collection.create_index(index).await?;
-    Ok(())
+/// The name, or the first of "name (2)", "name (3)"... not taken yet.
+pub fn free_folder_name<'a>(taken: impl IntoIterator<Item = &'a str>, name: &str) -> String {
+    let taken = taken.into_iter().collect::<HashSet<_>>();
+    let mut candidate = name.to_string();
+    let mut n = 1;
+    while taken.contains(candidate.as_str()) {
+        n += 1;
+        candidate = format!("{} ({})", name, n);
+    }
+    candidate
+}
+
+// the folder names are unique among the siblings
+pub fn name_conflict(folder: &Folder) -> ServiceError {
+    ServiceError::NameConflict(format!(
+        "A folder named \"{}\" already exists there",
+        folder.name
+    ))
 }
 
 #[async_trait::async_trait]
 pub trait FolderRepository: Send + Sync {
-    async fn create_folder(&self, folder: Folder) -> ServiceResult<()>;
+    async fn create_folder(&self, txn: &mut TxnContext, folder: Folder) -> ServiceResult<()>;
     async fn get_folder_by_id(&self, id: &str) -> ServiceResult<Option<Folder>>;
     async fn get_folders_by_user_id(&self, user_id: &str) -> ServiceResult<Vec<Folder>>;
+    /// Like `get_folders_by_user_id`, loading only the projected fields.
+    async fn get_folders_projected(
+        &self,
+        user_id: &str,
+        projection: bson::Document,
+    ) -> ServiceResult<Vec<Folder>>;
     async fn update_folder(&self, folder: Folder) -> ServiceResult<Folder>;
+    /// Set the ranks of sibling folders, given in their new order.
+    async fn reorder_folders(&self, user_id: &str, folder_ids: &[String]) -> ServiceResult<()>;
     async fn delete_folder(&self, id: &str) -> ServiceResult<()>;
 }
 
 #[async_trait::async_trait]
 impl FolderRepository for MongoClient {
-    async fn create_folder(&self, folder: Folder) -> ServiceResult<()> {
-        self.collection::<Folder>(FOLDER_COLLECTION_NAME)
-            .insert_one(folder.clone())
-            .await?;
-        Ok(())
+    async fn create_folder(&self, txn: &mut TxnContext, folder: Folder) -> ServiceResult<()> {
+        let collection = self.collection::<Folder>(FOLDER_COLLECTION_NAME);
+        match in_session!(collection.insert_one(&folder), txn) {
+            Err(e) if is_duplicate_key(&e) => Err(name_conflict(&folder)),
+            result => {
+                result?;
+                Ok(())
+            }
+        }
     }
 
     async fn get_folder_by_id(&self, id: &str) -> ServiceResult<Option<Folder>> {
@@ -181,17 +414,65 @@ impl FolderRepository for MongoClient {
         Ok(folders)
     }
 
-    async fn update_folder(&self, folder: Folder) -> ServiceResult<Folder> {
-        let filter = doc! { "_id": &folder.id };
+    async fn get_folders_projected(
+        &self,
+        user_id: &str,
+        projection: bson::Document,
+    ) -> ServiceResult<Vec<Folder>> {
+        let filter = doc! { "user_id": user_id };
+        let cursor = self
+            .collection::<Folder>(FOLDER_COLLECTION_NAME)
+            .find(filter)
+            .projection(projection)
+            .await?;
+        let folders = cursor.try_collect().await?;
+        Ok(folders)
+    }
+
+    async fn update_folder(&self, mut folder: Folder) -> ServiceResult<Folder> {
+        let filter = version_filter(&folder.id, folder.version);
+        folder.version += 1;
         let update = doc! {
             SET_OP: bson::to_bson(&folder)?,
         };
-        self.collection::<Folder>(FOLDER_COLLECTION_NAME)
+        let result = match self
+            .collection::<Folder>(FOLDER_COLLECTION_NAME)
             .update_one(filter, update)
-            .await?;
+            .await
+        {
+            Err(e) if is_duplicate_key(&e) => return Err(name_conflict(&folder)),
+            result => result?,
+        };
+        if result.matched_count == 0 {
+            return Err(ServiceError::VersionConflict(format!(
+                "Folder {} was modified concurrently",
+                folder.id
+            )));
+        }
         Ok(folder)
     }
 
+    async fn reorder_folders(&self, user_id: &str, folder_ids: &[String]) -> ServiceResult<()> {
+        let collection = self.collection::<Folder>(FOLDER_COLLECTION_NAME);
+        let now = bson::DateTime::now();
+
+        let mut session = self.start_session().await?;
+        session.start_transaction().await?;
+        for (sort_order, folder_id) in folder_ids.iter().enumerate() {
+            let filter = doc! { "_id": folder_id, "user_id": user_id };
+            let update = doc! {
+                SET_OP: { "sort_order": sort_order as u32, "updated_at": now },
+                INC_OP: { "version": 1 },
+            };
+            collection
+                .update_one(filter, update)
+                .session(&mut session)
+                .await?;
+        }
+        session.commit_transaction().await?;
+        Ok(())
+    }
+
     async fn delete_folder(&self, id: &str) -> ServiceResult<()> {
         let filter = doc! { "_id": id };
         self.collection::<Folder>(FOLDER_COLLECTION_NAME)
@@ -200,3 +481,84 @@ impl FolderRepository for MongoClient {
         Ok(())
     }
 }
+
+#[async_trait::async_trait]
+impl FolderRepository for DocumentDatabase {
+    async fn create_folder(&self, txn: &mut TxnContext, folder: Folder) -> ServiceResult<()> {
+        match txn
+            .documents(self)
+            .try_insert(FOLDER_COLLECTION_NAME, &folder)
+            .await?
+        {
+            true => Ok(()),
+            false => Err(name_conflict(&folder)),
+        }
+    }
+
+    async fn get_folder_by_id(&self, id: &str) -> ServiceResult<Option<Folder>> {
+        self.find_one(FOLDER_COLLECTION_NAME, doc! { "_id": id })
+            .await
+    }
+
+    async fn get_folders_by_user_id(&self, user_id: &str) -> ServiceResult<Vec<Folder>> {
+        let query = Query::new(doc! { "user_id": user_id });
+        self.find(FOLDER_COLLECTION_NAME, query).await
+    }
+
+    async fn get_folders_projected(
+        &self,
+        user_id: &str,
+        _projection: bson::Document,
+    ) -> ServiceResult<Vec<Folder>> {
+        // the whole folders are loaded, a projection only saves the transfer
+        self.get_folders_by_user_id(user_id).await
+    }
+
+    async fn update_folder(&self, mut folder: Folder) -> ServiceResult<Folder> {
+        // the store rejects a duplicate as any other error, it is told apart first
+        let sibling = doc! {
+            "_id": { NE_OP: &folder.id },
+            "user_id": &folder.user_id,
+            "parent_id": folder.parent_id.clone(),
+            "name": &folder.name,
+        };
+        if self.count(FOLDER_COLLECTION_NAME, sibling).await? > 0 {
+            return Err(name_conflict(&folder));
+        }
+        let filter = version_filter(&folder.id, folder.version);
+        folder.version += 1;
+        let update = doc! {
+            SET_OP: bson::to_bson(&folder)?,
+        };
+        let matched = self
+            .update_one(FOLDER_COLLECTION_NAME, filter, update)
+            .await?;
+        if matched == 0 {
+            return Err(ServiceError::VersionConflict(format!(
+                "Folder {} was modified concurrently",
+                folder.id
+            )));
+        }
+        Ok(folder)
+    }
+
+    async fn reorder_folders(&self, user_id: &str, folder_ids: &[String]) -> ServiceResult<()> {
+        let now = bson::DateTime::now();
+        for (sort_order, folder_id) in folder_ids.iter().enumerate() {
+            let filter = doc! { "_id": folder_id, "user_id": user_id };
+            let update = doc! {
+                SET_OP: { "sort_order": sort_order as u32, "updated_at": now },
+                INC_OP: { "version": 1 },
+            };
+            self.update_one(FOLDER_COLLECTION_NAME, filter, update)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_folder(&self, id: &str) -> ServiceResult<()> {
+        self.delete_one(FOLDER_COLLECTION_NAME, doc! { "_id": id })
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1 @@
+pub use paper_schema::health as schema;
@@ -0,0 +1,165 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ServiceError, ServiceResult, is_duplicate_key},
+    model::{constant::*, content::content_hash, document::DocumentDatabase},
+};
+
+/// The response of a request sent with an `Idempotency-Key`, replayed to the
+/// retries of the request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdempotencyRecord {
+    #[serde(rename = "_id")]
+    pub id: String, // `{user_id}:{sha256 of the key}`
+    pub user_id: String,
+    pub created_at: bson::DateTime,
+
+    pub method: String,
+    pub path: String,
+    // sha256 of the method, path and body, the key can only be reused for the
+    // same request
+    #[serde(default)]
+    pub fingerprint: String,
+    // none while the first request is running
+    pub status: Option<u16>,
+    pub content_type: Option<String>,
+    // sealed under the key, which is not stored
+    pub body: Option<String>,
+}
+
+impl IdempotencyRecord {
+    pub fn new(user_id: &str, key: &str, method: &str, path: &str, fingerprint: String) -> Self {
+        IdempotencyRecord {
+            id: format!("{}:{}", user_id, content_hash(key.as_bytes())),
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+
+            method: method.to_string(),
+            path: path.to_string(),
+            fingerprint,
+            status: None,
+            content_type: None,
+            body: None,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait IdempotencyRepository: Send + Sync {
+    /// Claim the key for a first request, or the record of the key when it was
+    /// already claimed.
+    async fn claim_idempotency_key(
+        &self,
+        record: IdempotencyRecord,
+    ) -> ServiceResult<Option<IdempotencyRecord>>;
+    /// Store the response of the request which claimed the key.
+    async fn complete_idempotency_key(
+        &self,
+        id: &str,
+        status: u16,
+        content_type: Option<String>,
+        body: String,
+    ) -> ServiceResult<()>;
+    /// Release the key, so a retry runs the request again.
+    async fn release_idempotency_key(&self, id: &str) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl IdempotencyRepository for MongoClient {
+    async fn claim_idempotency_key(
+        &self,
+        record: IdempotencyRecord,
+    ) -> ServiceResult<Option<IdempotencyRecord>> {
+        let collection = self.collection::<IdempotencyRecord>(IDEMPOTENCY_COLLECTION_NAME);
+        let id = record.id.clone();
+        match collection.insert_one(record).await {
+            Ok(_) => Ok(None),
+            Err(e) if is_duplicate_key(&e) => {
+                let existing = collection.find_one(doc! { "_id": &id }).await?;
+                // expired between the insert and the read
+                existing
+                    .map(Some)
+                    .ok_or_else(|| ServiceError::VersionConflict(format!("Idempotency key {}", id)))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn complete_idempotency_key(
+        &self,
+        id: &str,
+        status: u16,
+        content_type: Option<String>,
+        body: String,
+    ) -> ServiceResult<()> {
+        let filter = doc! { "_id": id };
+        let update = doc! {
+            SET_OP: {
+                "status": i32::from(status),
+                "content_type": content_type,
+                "body": body,
+            },
+        };
+        self.collection::<IdempotencyRecord>(IDEMPOTENCY_COLLECTION_NAME)
+            .update_one(filter, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn release_idempotency_key(&self, id: &str) -> ServiceResult<()> {
+        self.collection::<IdempotencyRecord>(IDEMPOTENCY_COLLECTION_NAME)
+            .delete_one(doc! { "_id": id })
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl IdempotencyRepository for DocumentDatabase {
+    async fn claim_idempotency_key(
+        &self,
+        record: IdempotencyRecord,
+    ) -> ServiceResult<Option<IdempotencyRecord>> {
+        if self
+            .try_insert(IDEMPOTENCY_COLLECTION_NAME, &record)
+            .await?
+        {
+            return Ok(None);
+        }
+        let existing = self
+            .find_one(IDEMPOTENCY_COLLECTION_NAME, doc! { "_id": &record.id })
+            .await?;
+        // expired between the insert and the read
+        existing
+            .map(Some)
+            .ok_or_else(|| ServiceError::VersionConflict(format!("Idempotency key {}", record.id)))
+    }
+
+    async fn complete_idempotency_key(
+        &self,
+        id: &str,
+        status: u16,
+        content_type: Option<String>,
+        body: String,
+    ) -> ServiceResult<()> {
+        let filter = doc! { "_id": id };
+        let update = doc! {
+            SET_OP: {
+                "status": i32::from(status),
+                "content_type": content_type,
+                "body": body,
+            },
+        };
+        self.update_one(IDEMPOTENCY_COLLECTION_NAME, filter, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn release_idempotency_key(&self, id: &str) -> ServiceResult<()> {
+        self.delete_one(IDEMPOTENCY_COLLECTION_NAME, doc! { "_id": id })
+            .await?;
+        Ok(())
+    }
+}
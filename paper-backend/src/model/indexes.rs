@@ -0,0 +1,449 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use ai_flow_synth::utils::MongoClient;
+use bson::{Bson, Document, doc};
+use futures::TryStreamExt;
+use mongodb::{IndexModel, options::IndexOptions};
+
+use crate::{error::ServiceResult, model::constant::*};
+
+// idempotent responses are replayed for a day
+const IDEMPOTENCY_RETENTION: Duration = Duration::from_secs(24 * 3600);
+// resumable uploads can be completed for a day
+const UPLOAD_RETENTION: Duration = Duration::from_secs(24 * 3600);
+// webhook deliveries are kept for a month
+const DELIVERY_RETENTION: Duration = Duration::from_secs(30 * 24 * 3600);
+// a chunking request which died is taken over after ten minutes
+const CHUNK_CLAIM_RETENTION: Duration = Duration::from_secs(10 * 60);
+// failed logins are forgotten a day after the last one
+const LOGIN_ATTEMPT_RETENTION: Duration = Duration::from_secs(24 * 3600);
+
+fn index(keys: Document) -> IndexModel {
+    IndexModel::builder().keys(keys).build()
+}
+
+fn unique_index(keys: Document) -> IndexModel {
+    IndexModel::builder()
+        .keys(keys)
+        .options(IndexOptions::builder().unique(true).build())
+        .build()
+}
+
+// unique among the documents matching the filter, e.g. those holding the field
+fn partial_unique_index(keys: Document, filter: Document) -> IndexModel {
+    IndexModel::builder()
+        .keys(keys)
+        .options(
+            IndexOptions::builder()
+                .unique(true)
+                .partial_filter_expression(filter)
+                .build(),
+        )
+        .build()
+}
+
+fn expiring_index(keys: Document, expire_after: Duration) -> IndexModel {
+    IndexModel::builder()
+        .keys(keys)
+        .options(IndexOptions::builder().expire_after(expire_after).build())
+        .build()
+}
+
+/// The indexes of every collection, new ones are created on startup.
+pub(crate) fn declared_indexes() -> Vec<(&'static str, Vec<IndexModel>)> {
+    vec![
+        (
+            ACTIVITY_COLLECTION_NAME,
+            vec![index(doc! { "user_id": 1, "at": -1 })],
+        ),
+        (
+            AUDIT_LOG_COLLECTION_NAME,
+            vec![index(doc! { "user_id": 1, "created_at": -1 })],
+        ),
+        (
+            BACKUP_COLLECTION_NAME,
+            vec![index(doc! { "created_at": -1 })],
+        ),
+        (
+            BLOCK_COLLECTION_NAME,
+            vec![index(doc! { "user_id": 1, "name": 1 })],
+        ),
+        (
+            PAPER_CHUNK_COLLECTION_NAME,
+            vec![index(doc! { "paper_id": 1, "index": 1 })],
+        ),
+        (
+            CHUNK_CLAIM_COLLECTION_NAME,
+            vec![expiring_index(
+                doc! { "claimed_at": 1 },
+                CHUNK_CLAIM_RETENTION,
+            )],
+        ),
+        (
+            CITATION_COLLECTION_NAME,
+            vec![
+                index(doc! { "citing_id": 1 }),
+                index(doc! { "cited_id": 1 }),
+                index(doc! { "user_id": 1 }),
+            ],
+        ),
+        (
+            COMPARISON_COLLECTION_NAME,
+            vec![index(doc! { "user_id": 1, "created_at": -1 })],
+        ),
+        (
+            CONSENT_COLLECTION_NAME,
+            vec![index(doc! { "user_id": 1, "created_at": -1 })],
+        ),
+        (
+            CONVERSATION_COLLECTION_NAME,
+            vec![index(doc! { "user_id": 1, "pinned": -1, "updated_at": -1 })],
+        ),
+        (
+            CONVERSATION_MESSAGE_COLLECTION_NAME,
+            vec![
+                index(doc! { "conversation_id": 1, "created_at": -1 }),
+                // search within the conversations of a user
+                index(doc! { "user_id": 1, "content": "text" }),
+            ],
+        ),
+        (
+            CUSTOM_FIELD_COLLECTION_NAME,
+            vec![unique_index(doc! { "owner_id": 1, "key": 1 })],
+        ),
+        (
+            PAPER_EMBEDDING_COLLECTION_NAME,
+            vec![index(doc! { "user_id": 1 })],
+        ),
+        (
+            EXPORT_COLLECTION_NAME,
+            vec![index(doc! { "user_id": 1, "created_at": -1 })],
+        ),
+        (
+            FOLDER_COLLECTION_NAME,
+            vec![unique_index(
+                doc! { "user_id": 1, "parent_id": 1, "name": 1 },
+            )],
+        ),
+        (
+            IDEMPOTENCY_COLLECTION_NAME,
+            vec![expiring_index(
+                doc! { "created_at": 1 },
+                IDEMPOTENCY_RETENTION,
+            )],
+        ),
+        (
+            JOB_COLLECTION_NAME,
+            vec![index(doc! { "user_id": 1, "created_at": -1 })],
+        ),
+        (LLM_KEY_COLLECTION_NAME, vec![index(doc! { "user_id": 1 })]),
+        (
+            LOGIN_ATTEMPT_COLLECTION_NAME,
+            vec![expiring_index(
+                doc! { "last_failed_at": 1 },
+                LOGIN_ATTEMPT_RETENTION,
+            )],
+        ),
+        (
+            NOTIFICATION_COLLECTION_NAME,
+            vec![
+                index(doc! { "user_id": 1, "created_at": -1 }),
+                index(doc! { "user_id": 1, "read": 1 }),
+            ],
+        ),
+        (
+            PAPER_PAGE_COLLECTION_NAME,
+            vec![index(doc! { "paper_id": 1, "page": 1 })],
+        ),
+        (
+            PAPER_REVISION_COLLECTION_NAME,
+            vec![
+                index(doc! { "paper_id": 1, "revision": -1 }),
+                index(doc! { "created_at": 1 }),
+            ],
+        ),
+        (
+            PAPER_COLLECTION_NAME,
+            vec![
+                index(doc! { "user_id": 1, "folder_id": 1 }),
+                index(doc! { "user_id": 1, "starred": 1 }),
+                // full text search, the title weighs most
+                IndexModel::builder()
+                    .keys(doc! {
+                        "title": "text",
+                        "abstract": "text",
+                        "summary": "text",
+                        "content": "text",
+                        "tags": "text",
+                    })
+                    .options(
+                        IndexOptions::builder()
+                            .weights(doc! {
+                                "title": 10,
+                                "tags": 5,
+                                "abstract": 3,
+                                "summary": 2,
+                                "content": 1,
+                            })
+                            .build(),
+                    )
+                    .build(),
+            ],
+        ),
+        (
+            PROMPT_TEMPLATE_COLLECTION_NAME,
+            vec![unique_index(doc! { "name": 1, "version": -1 })],
+        ),
+        (
+            QUARANTINE_COLLECTION_NAME,
+            vec![index(doc! { "created_at": -1 })],
+        ),
+        (
+            READING_LIST_COLLECTION_NAME,
+            vec![unique_index(doc! { "user_id": 1, "paper_id": 1 })],
+        ),
+        (
+            SHARE_LINK_COLLECTION_NAME,
+            vec![
+                unique_index(doc! { "token": 1 }),
+                index(doc! { "paper_id": 1 }),
+            ],
+        ),
+        (
+            COMMENT_COLLECTION_NAME,
+            vec![index(doc! { "paper_id": 1, "created_at": 1 })],
+        ),
+        (
+            UPLOAD_CHUNK_COLLECTION_NAME,
+            vec![
+                index(doc! { "upload_id": 1, "offset": 1 }),
+                expiring_index(doc! { "created_at": 1 }, UPLOAD_RETENTION),
+            ],
+        ),
+        (
+            UPLOAD_COLLECTION_NAME,
+            vec![expiring_index(doc! { "created_at": 1 }, UPLOAD_RETENTION)],
+        ),
+        (
+            USAGE_EVENT_COLLECTION_NAME,
+            vec![
+                index(doc! { "user_id": 1, "created_at": -1 }),
+                // the operator summary spans every user
+                index(doc! { "created_at": -1 }),
+            ],
+        ),
+        (
+            USER_COLLECTION_NAME,
+            vec![
+                index(doc! { "uid": 1 }),
+                index(doc! { "phone_hash": 1 }),
+                // the users signed in by phone or wechat have a null email
+                partial_unique_index(doc! { "email": 1 }, doc! { "email": { TYPE_OP: "string" } }),
+            ],
+        ),
+        (WEBHOOK_COLLECTION_NAME, vec![index(doc! { "user_id": 1 })]),
+        (
+            WEBHOOK_DELIVERY_COLLECTION_NAME,
+            vec![
+                index(doc! { "webhook_id": 1, "created_at": -1 }),
+                expiring_index(doc! { "created_at": 1 }, DELIVERY_RETENTION),
+            ],
+        ),
+    ]
+}
+
+/// Name mongo gives an index created without one, e.g. `user_id_1_created_at_-1`.
+pub(crate) fn index_name(index: &IndexModel) -> String {
+    if let Some(name) = index.options.as_ref().and_then(|o| o.name.clone()) {
+        return name;
+    }
+    index
+        .keys
+        .iter()
+        .map(|(key, value)| match value {
+            Bson::String(kind) => format!("{}_{}", key, kind),
+            value => format!("{}_{}", key, value),
+        })
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// What an index is besides its name, to tell a declared index from the one
+/// found in the database.
+#[derive(Debug, PartialEq)]
+struct IndexShape {
+    keys: Vec<(String, String)>,
+    // the text fields with their weights
+    text: BTreeMap<String, f64>,
+    unique: bool,
+    sparse: bool,
+    expire_after: Option<Duration>,
+    partial_filter: Option<Document>,
+}
+
+fn number(value: &Bson) -> Option<f64> {
+    match value {
+        Bson::Int32(n) => Some(f64::from(*n)),
+        Bson::Int64(n) => Some(*n as f64),
+        Bson::Double(n) => Some(*n),
+        _ => None,
+    }
+}
+
+/// The shape of the index, as declared or as listed by mongo, which gives the
+/// text fields of a text index in its weights, e.g. `{ _fts: "text", _ftsx: 1 }`.
+fn index_shape(index: &IndexModel) -> IndexShape {
+    let options = index.options.clone().unwrap_or_default();
+    let weights = options.weights.unwrap_or_default();
+    let weight = |field: &str| weights.get(field).and_then(number).unwrap_or(1.0);
+    let mut keys = Vec::new();
+    let mut text = BTreeMap::new();
+    for (key, value) in &index.keys {
+        match value {
+            _ if key == "_fts" || key == "_ftsx" => {}
+            Bson::String(kind) if kind == "text" => {
+                text.insert(key.clone(), weight(key));
+            }
+            value => keys.push((
+                key.clone(),
+                number(value).map_or_else(|| value.to_string(), |n| n.to_string()),
+            )),
+        }
+    }
+    if index.keys.contains_key("_fts") {
+        for field in weights.keys() {
+            text.insert(field.clone(), weight(field));
+        }
+    }
+    IndexShape {
+        keys,
+        text,
+        unique: options.unique.unwrap_or(false),
+        sparse: options.sparse.unwrap_or(false),
+        expire_after: options.expire_after,
+        partial_filter: options.partial_filter_expression,
+    }
+}
+
+// listing the indexes of a collection not created yet fails
+pub(crate) fn is_namespace_not_found(err: &mongodb::error::Error) -> bool {
+    matches!(
+        *err.kind,
+        mongodb::error::ErrorKind::Command(mongodb::error::CommandError { code: 26, .. })
+    )
+}
+
+/// Create the declared indexes missing from the database, and warn about the
+/// indexes found there but no longer declared, or whose keys or options differ
+/// from their declaration, which are left to the operator to drop. Indexes are
+/// matched by name, or by keys when renamed.
+pub async fn ensure_all(client: &MongoClient) -> ServiceResult<()> {
+    for (name, indexes) in declared_indexes() {
+        let collection = client.collection::<Document>(name);
+        let existing: Vec<IndexModel> = match collection.list_indexes().await {
+            Ok(cursor) => cursor.try_collect().await?,
+            Err(e) if is_namespace_not_found(&e) => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let existing = existing
+            .iter()
+            .map(|index| (index_name(index), index_shape(index)))
+            .collect::<Vec<_>>();
+
+        let mut matched = Vec::new();
+        let mut missing = Vec::new();
+        for index in indexes {
+            let (declared, shape) = (index_name(&index), index_shape(&index));
+            let found = existing.iter().find(|(name, found)| {
+                *name == declared || (found.keys == shape.keys && found.text == shape.text)
+            });
+            match found {
+                Some((found, found_shape)) => {
+                    if *found_shape != shape {
+                        tracing::warn!(
+                            "Index {} on {} differs from its declaration, found {:?}, declared {:?}",
+                            found,
+                            name,
+                            found_shape,
+                            shape
+                        );
+                    }
+                    matched.push(found.clone());
+                }
+                None => missing.push(index),
+            }
+        }
+        if !missing.is_empty() {
+            let created = missing.iter().map(index_name).collect::<Vec<_>>();
+            collection.create_indexes(missing).await?;
+            tracing::info!("Created indexes {} on {}", created.join(", "), name);
+        }
+        for (extra, _) in existing
+            .iter()
+            .filter(|(index, _)| index != "_id_" && !matched.contains(index))
+        {
+            tracing::warn!("Index {} on {} is not declared anymore", extra, name);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_name() {
+        assert_eq!(
+            index_name(&index(doc! { "user_id": 1, "created_at": -1 })),
+            "user_id_1_created_at_-1"
+        );
+        assert_eq!(
+            index_name(&index(doc! { "user_id": 1, "content": "text" })),
+            "user_id_1_content_text"
+        );
+    }
+
+    #[test]
+    fn test_index_shape() {
+        let listed = |keys: Document, options: IndexOptions| {
+            IndexModel::builder().keys(keys).options(options).build()
+        };
+
+        // a text index is listed with its fields in the weights
+        let declared = index(doc! { "user_id": 1, "content": "text" });
+        let found = listed(
+            doc! { "user_id": 1, "_fts": "text", "_ftsx": 1 },
+            IndexOptions::builder()
+                .name("user_id_1_content_text".to_string())
+                .weights(doc! { "content": 1 })
+                .build(),
+        );
+        assert_eq!(index_shape(&found), index_shape(&declared));
+        let reweighted = listed(
+            doc! { "user_id": 1, "_fts": "text", "_ftsx": 1 },
+            IndexOptions::builder()
+                .weights(doc! { "content": 2 })
+                .build(),
+        );
+        assert_ne!(index_shape(&reweighted), index_shape(&declared));
+
+        let declared = unique_index(doc! { "token": 1 });
+        let found = listed(
+            doc! { "token": 1.0 },
+            IndexOptions::builder().unique(true).build(),
+        );
+        assert_eq!(index_shape(&found), index_shape(&declared));
+        assert_ne!(
+            index_shape(&index(doc! { "token": 1 })),
+            index_shape(&declared)
+        );
+        assert_ne!(
+            index_shape(&expiring_index(doc! { "created_at": 1 }, UPLOAD_RETENTION)),
+            index_shape(&expiring_index(
+                doc! { "created_at": 1 },
+                DELIVERY_RETENTION
+            ))
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+pub use paper_schema::job::{JobKind, JobStatus};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+        paper::Progress,
+    },
+    utils::request_id::current_request_id,
+};
+
+pub mod schema {
+    pub use paper_schema::job::*;
+
+    use crate::model::job::Job;
+
+    impl From<Job> for JobResponse {
+        fn from(job: Job) -> Self {
+            JobResponse {
+                id: job.id,
+                kind: job.kind,
+                resource_id: job.resource_id,
+                status: job.status,
+                progress: job.progress,
+                result_id: job.result_id,
+                error: job.error,
+                created_at: job.created_at.timestamp_millis(),
+                finished_at: job.finished_at.map(|t| t.timestamp_millis()),
+            }
+        }
+    }
+}
+
+/// Long running work started by a user, tracked until it finishes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String,
+    pub created_at: bson::DateTime,
+    pub finished_at: Option<bson::DateTime>,
+
+    pub kind: JobKind,
+    // uuid of the resource the job works on
+    pub resource_id: String,
+    pub status: JobStatus,
+    pub progress: Option<Progress>,
+    // uuid of the resource created by the job, once done
+    pub result_id: Option<String>,
+    pub error: Option<String>,
+    // the request which started the job, to trace a failure
+    #[serde(default)]
+    pub request_id: Option<String>,
+}
+
+impl Job {
+    pub fn new(user_id: &str, kind: JobKind, resource_id: &str) -> Self {
+        Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+            finished_at: None,
+
+            kind,
+            resource_id: resource_id.to_string(),
+            status: JobStatus::Running,
+            progress: None,
+            result_id: None,
+            error: None,
+            request_id: current_request_id(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait JobRepository: Send + Sync {
+    async fn create_job(&self, job: Job) -> ServiceResult<()>;
+    async fn get_job(&self, user_id: &str, id: &str) -> ServiceResult<Option<Job>>;
+    /// The latest jobs of the user first.
+    async fn get_jobs(&self, user_id: &str, limit: i64) -> ServiceResult<Vec<Job>>;
+    async fn set_job_progress(&self, id: &str, progress: Progress) -> ServiceResult<()>;
+    /// Record the end of the job, with the resource it created or the error.
+    async fn finish_job(
+        &self,
+        id: &str,
+        status: JobStatus,
+        result_id: Option<String>,
+        error: Option<String>,
+    ) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl JobRepository for MongoClient {
+    async fn create_job(&self, job: Job) -> ServiceResult<()> {
+        self.collection::<Job>(JOB_COLLECTION_NAME)
+            .insert_one(job)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_job(&self, user_id: &str, id: &str) -> ServiceResult<Option<Job>> {
+        let job = self
+            .collection::<Job>(JOB_COLLECTION_NAME)
+            .find_one(doc! { "_id": id, "user_id": user_id })
+            .await?;
+        Ok(job)
+    }
+
+    async fn get_jobs(&self, user_id: &str, limit: i64) -> ServiceResult<Vec<Job>> {
+        let cursor = self
+            .collection::<Job>(JOB_COLLECTION_NAME)
+            .find(doc! { "user_id": user_id })
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await?;
+        let jobs = cursor.try_collect().await?;
+        Ok(jobs)
+    }
+
+    async fn set_job_progress(&self, id: &str, progress: Progress) -> ServiceResult<()> {
+        self.collection::<Job>(JOB_COLLECTION_NAME)
+            .update_one(
+                doc! { "_id": id },
+                doc! { SET_OP: { "progress": bson::to_bson(&progress)? } },
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn finish_job(
+        &self,
+        id: &str,
+        status: JobStatus,
+        result_id: Option<String>,
+        error: Option<String>,
+    ) -> ServiceResult<()> {
+        let update = doc! {
+            SET_OP: {
+                "status": bson::to_bson(&status)?,
+                "result_id": result_id,
+                "error": error,
+                "finished_at": bson::DateTime::now(),
+            }
+        };
+        self.collection::<Job>(JOB_COLLECTION_NAME)
+            .update_one(doc! { "_id": id }, update)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl JobRepository for DocumentDatabase {
+    async fn create_job(&self, job: Job) -> ServiceResult<()> {
+        self.insert(JOB_COLLECTION_NAME, &job).await
+    }
+
+    async fn get_job(&self, user_id: &str, id: &str) -> ServiceResult<Option<Job>> {
+        self.find_one(JOB_COLLECTION_NAME, doc! { "_id": id, "user_id": user_id })
+            .await
+    }
+
+    async fn get_jobs(&self, user_id: &str, limit: i64) -> ServiceResult<Vec<Job>> {
+        let query = Query::new(doc! { "user_id": user_id })
+            .sort(doc! { "created_at": -1 })
+            .limit(limit);
+        self.find(JOB_COLLECTION_NAME, query).await
+    }
+
+    async fn set_job_progress(&self, id: &str, progress: Progress) -> ServiceResult<()> {
+        self.update_one(
+            JOB_COLLECTION_NAME,
+            doc! { "_id": id },
+            doc! { SET_OP: { "progress": bson::to_bson(&progress)? } },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn finish_job(
+        &self,
+        id: &str,
+        status: JobStatus,
+        result_id: Option<String>,
+        error: Option<String>,
+    ) -> ServiceResult<()> {
+        let update = doc! {
+            SET_OP: {
+                "status": bson::to_bson(&status)?,
+                "result_id": result_id,
+                "error": error,
+                "finished_at": bson::DateTime::now(),
+            }
+        };
+        self.update_one(JOB_COLLECTION_NAME, doc! { "_id": id }, update)
+            .await?;
+        Ok(())
+    }
+}
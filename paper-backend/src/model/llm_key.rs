@@ -0,0 +1,219 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+/// Providers the users can bring their own api key for.
+pub const KEY_PROVIDERS: [&str; 2] = ["openai", "anthropic"];
+// characters of the key shown back to the user
+const KEY_HINT_CHARS: usize = 4;
+
+pub mod schema {
+    pub use paper_schema::llm_key::*;
+
+    use crate::{
+        model::llm_key::UserLlmKey,
+        utils::validate::{ValidatedRequest, trim},
+    };
+
+    impl From<UserLlmKey> for LlmKeyResponse {
+        fn from(key: UserLlmKey) -> Self {
+            LlmKeyResponse {
+                provider: key.provider,
+                key_hint: key.key_hint,
+                created_at: key.created_at.timestamp_millis(),
+                validated_at: key.validated_at.map(|at| at.timestamp_millis()),
+            }
+        }
+    }
+
+    impl ValidatedRequest for StoreLlmKeyRequest {
+        fn normalize(&mut self) {
+            trim(&mut self.api_key);
+        }
+    }
+}
+
+/// An api key of the user for a llm provider, sealed with the master key. The
+/// calls made for the user to the provider use it, and are billed to them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserLlmKey {
+    #[serde(rename = "_id")]
+    pub id: String, // `{user_id}:{provider}`
+    pub user_id: String,
+    pub created_at: bson::DateTime,
+
+    pub provider: String,
+    pub encrypted_key: String,
+    pub key_hint: String,
+    pub validated_at: Option<bson::DateTime>,
+}
+
+impl UserLlmKey {
+    pub fn new(user_id: &str, provider: &str, api_key: &str, encrypted_key: String) -> Self {
+        let hint = api_key
+            .chars()
+            .rev()
+            .take(KEY_HINT_CHARS)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect::<String>();
+        UserLlmKey {
+            id: llm_key_id(user_id, provider),
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+
+            provider: provider.to_string(),
+            encrypted_key,
+            key_hint: format!("…{}", hint),
+            validated_at: None,
+        }
+    }
+}
+
+fn llm_key_id(user_id: &str, provider: &str) -> String {
+    format!("{}:{}", user_id, provider)
+}
+
+#[async_trait::async_trait]
+pub trait LlmKeyRepository: Send + Sync {
+    /// Store the key, replacing the previous key of the user for the provider.
+    async fn upsert_llm_key(&self, key: UserLlmKey) -> ServiceResult<()>;
+    async fn get_llm_key(&self, user_id: &str, provider: &str)
+    -> ServiceResult<Option<UserLlmKey>>;
+    async fn get_llm_keys(&self, user_id: &str) -> ServiceResult<Vec<UserLlmKey>>;
+    /// The keys of every user, to seal them again after a key rotation.
+    async fn get_all_llm_keys(&self) -> ServiceResult<Vec<UserLlmKey>>;
+    async fn set_llm_key_validated(
+        &self,
+        user_id: &str,
+        provider: &str,
+        at: bson::DateTime,
+    ) -> ServiceResult<()>;
+    /// Whether the user had a key for the provider.
+    async fn delete_llm_key(&self, user_id: &str, provider: &str) -> ServiceResult<bool>;
+}
+
+#[async_trait::async_trait]
+impl LlmKeyRepository for MongoClient {
+    async fn upsert_llm_key(&self, key: UserLlmKey) -> ServiceResult<()> {
+        let filter = doc! { "_id": &key.id };
+        self.collection::<UserLlmKey>(LLM_KEY_COLLECTION_NAME)
+            .replace_one(filter, key)
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_llm_key(
+        &self,
+        user_id: &str,
+        provider: &str,
+    ) -> ServiceResult<Option<UserLlmKey>> {
+        let key = self
+            .collection::<UserLlmKey>(LLM_KEY_COLLECTION_NAME)
+            .find_one(doc! { "_id": llm_key_id(user_id, provider) })
+            .await?;
+        Ok(key)
+    }
+
+    async fn get_llm_keys(&self, user_id: &str) -> ServiceResult<Vec<UserLlmKey>> {
+        let cursor = self
+            .collection::<UserLlmKey>(LLM_KEY_COLLECTION_NAME)
+            .find(doc! { "user_id": user_id })
+            .sort(doc! { "provider": 1 })
+            .await?;
+        let keys = cursor.try_collect().await?;
+        Ok(keys)
+    }
+
+    async fn get_all_llm_keys(&self) -> ServiceResult<Vec<UserLlmKey>> {
+        let cursor = self
+            .collection::<UserLlmKey>(LLM_KEY_COLLECTION_NAME)
+            .find(doc! {})
+            .await?;
+        let keys = cursor.try_collect().await?;
+        Ok(keys)
+    }
+
+    async fn set_llm_key_validated(
+        &self,
+        user_id: &str,
+        provider: &str,
+        at: bson::DateTime,
+    ) -> ServiceResult<()> {
+        self.collection::<UserLlmKey>(LLM_KEY_COLLECTION_NAME)
+            .update_one(
+                doc! { "_id": llm_key_id(user_id, provider) },
+                doc! { SET_OP: { "validated_at": at } },
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_llm_key(&self, user_id: &str, provider: &str) -> ServiceResult<bool> {
+        let result = self
+            .collection::<UserLlmKey>(LLM_KEY_COLLECTION_NAME)
+            .delete_one(doc! { "_id": llm_key_id(user_id, provider) })
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmKeyRepository for DocumentDatabase {
+    async fn upsert_llm_key(&self, key: UserLlmKey) -> ServiceResult<()> {
+        let filter = doc! { "_id": &key.id };
+        self.replace_one(LLM_KEY_COLLECTION_NAME, filter, &key, true)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_llm_key(
+        &self,
+        user_id: &str,
+        provider: &str,
+    ) -> ServiceResult<Option<UserLlmKey>> {
+        let filter = doc! { "_id": llm_key_id(user_id, provider) };
+        self.find_one(LLM_KEY_COLLECTION_NAME, filter).await
+    }
+
+    async fn get_llm_keys(&self, user_id: &str) -> ServiceResult<Vec<UserLlmKey>> {
+        let query = Query::new(doc! { "user_id": user_id }).sort(doc! { "provider": 1 });
+        self.find(LLM_KEY_COLLECTION_NAME, query).await
+    }
+
+    async fn get_all_llm_keys(&self) -> ServiceResult<Vec<UserLlmKey>> {
+        self.find(LLM_KEY_COLLECTION_NAME, Query::new(doc! {}))
+            .await
+    }
+
+    async fn set_llm_key_validated(
+        &self,
+        user_id: &str,
+        provider: &str,
+        at: bson::DateTime,
+    ) -> ServiceResult<()> {
+        self.update_one(
+            LLM_KEY_COLLECTION_NAME,
+            doc! { "_id": llm_key_id(user_id, provider) },
+            doc! { SET_OP: { "validated_at": at } },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_llm_key(&self, user_id: &str, provider: &str) -> ServiceResult<bool> {
+        let filter = doc! { "_id": llm_key_id(user_id, provider) };
+        Ok(self.delete_one(LLM_KEY_COLLECTION_NAME, filter).await? > 0)
+    }
+}
@@ -0,0 +1,259 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+use mongodb::options::ReturnDocument;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::login_attempt::*;
+
+    use crate::model::login_attempt::LoginAttempt;
+
+    impl From<LoginAttempt> for LoginLockResponse {
+        fn from(attempt: LoginAttempt) -> Self {
+            LoginLockResponse {
+                key: attempt.id,
+                failures: attempt.failures,
+                last_failed_at: attempt.last_failed_at.timestamp_millis(),
+                locked_until: attempt
+                    .locked_until
+                    .map(|at| at.timestamp_millis())
+                    .unwrap_or_default(),
+            }
+        }
+    }
+}
+
+/// The failed logins of an account or of an ip, which delay then lock the next
+/// ones. Forgotten a day after the last failure.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginAttempt {
+    #[serde(rename = "_id")]
+    pub id: String, // `account:{email}` or `ip:{ip}`
+    // since the last lock, with the logins being checked
+    pub failures: i64,
+    pub last_failed_at: bson::DateTime,
+    pub locked_until: Option<bson::DateTime>,
+}
+
+impl LoginAttempt {
+    pub fn is_locked(&self, now: bson::DateTime) -> bool {
+        self.locked_until.is_some_and(|until| until > now)
+    }
+}
+
+pub fn account_attempt_key(email: &str) -> String {
+    format!("account:{}", email.to_lowercase())
+}
+
+pub fn ip_attempt_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+#[async_trait::async_trait]
+pub trait LoginAttemptRepository: Send + Sync {
+    async fn get_login_attempt(&self, key: &str) -> ServiceResult<Option<LoginAttempt>>;
+    /// Count one more attempt as failed before it is checked, dated now. The
+    /// attempt as it was before, none for the first one.
+    async fn reserve_login_attempt(&self, key: &str) -> ServiceResult<Option<LoginAttempt>>;
+    /// Count one attempt less, a reserved one which did not fail.
+    async fn release_login_attempt(&self, key: &str) -> ServiceResult<()>;
+    /// Date the failure of a reserved attempt, the attempt with it.
+    async fn record_login_failure(&self, key: &str) -> ServiceResult<Option<LoginAttempt>>;
+    /// Lock the logins until then, the failures are counted from zero again.
+    async fn lock_login(&self, key: &str, until: bson::DateTime) -> ServiceResult<()>;
+    /// Whether failures were counted.
+    async fn clear_login_attempt(&self, key: &str) -> ServiceResult<bool>;
+    /// The accounts and ips locked now, the latest failures first.
+    async fn get_locked_logins(
+        &self,
+        now: bson::DateTime,
+        limit: i64,
+    ) -> ServiceResult<Vec<LoginAttempt>>;
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptRepository for MongoClient {
+    async fn get_login_attempt(&self, key: &str) -> ServiceResult<Option<LoginAttempt>> {
+        let attempt = self
+            .collection::<LoginAttempt>(LOGIN_ATTEMPT_COLLECTION_NAME)
+            .find_one(doc! { "_id": key })
+            .await?;
+        Ok(attempt)
+    }
+
+    async fn reserve_login_attempt(&self, key: &str) -> ServiceResult<Option<LoginAttempt>> {
+        let attempt = self
+            .collection::<LoginAttempt>(LOGIN_ATTEMPT_COLLECTION_NAME)
+            .find_one_and_update(
+                doc! { "_id": key },
+                doc! {
+                    INC_OP: { "failures": 1 },
+                    SET_OP: { "last_failed_at": bson::DateTime::now() },
+                },
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::Before)
+            .await?;
+        Ok(attempt)
+    }
+
+    async fn release_login_attempt(&self, key: &str) -> ServiceResult<()> {
+        // counted from zero again by a lock meanwhile
+        self.collection::<LoginAttempt>(LOGIN_ATTEMPT_COLLECTION_NAME)
+            .update_one(
+                doc! { "_id": key, "failures": { GTE_OP: 1 } },
+                doc! { INC_OP: { "failures": -1 } },
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn record_login_failure(&self, key: &str) -> ServiceResult<Option<LoginAttempt>> {
+        let attempt = self
+            .collection::<LoginAttempt>(LOGIN_ATTEMPT_COLLECTION_NAME)
+            .find_one_and_update(
+                doc! { "_id": key },
+                doc! { SET_OP: { "last_failed_at": bson::DateTime::now() } },
+            )
+            .return_document(ReturnDocument::After)
+            .await?;
+        Ok(attempt)
+    }
+
+    async fn lock_login(&self, key: &str, until: bson::DateTime) -> ServiceResult<()> {
+        self.collection::<LoginAttempt>(LOGIN_ATTEMPT_COLLECTION_NAME)
+            .update_one(
+                doc! { "_id": key },
+                doc! { SET_OP: { "failures": 0, "locked_until": until } },
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn clear_login_attempt(&self, key: &str) -> ServiceResult<bool> {
+        let result = self
+            .collection::<LoginAttempt>(LOGIN_ATTEMPT_COLLECTION_NAME)
+            .delete_one(doc! { "_id": key })
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn get_locked_logins(
+        &self,
+        now: bson::DateTime,
+        limit: i64,
+    ) -> ServiceResult<Vec<LoginAttempt>> {
+        let cursor = self
+            .collection::<LoginAttempt>(LOGIN_ATTEMPT_COLLECTION_NAME)
+            .find(doc! { "locked_until": { GTE_OP: now } })
+            .sort(doc! { "last_failed_at": -1 })
+            .limit(limit)
+            .await?;
+        let attempts = cursor.try_collect().await?;
+        Ok(attempts)
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptRepository for DocumentDatabase {
+    async fn get_login_attempt(&self, key: &str) -> ServiceResult<Option<LoginAttempt>> {
+        self.find_one(LOGIN_ATTEMPT_COLLECTION_NAME, doc! { "_id": key })
+            .await
+    }
+
+    async fn reserve_login_attempt(&self, key: &str) -> ServiceResult<Option<LoginAttempt>> {
+        let attempt = LoginAttempt {
+            id: key.to_string(),
+            failures: 1,
+            last_failed_at: bson::DateTime::now(),
+            locked_until: None,
+        };
+        loop {
+            if self
+                .try_insert(LOGIN_ATTEMPT_COLLECTION_NAME, &attempt)
+                .await?
+            {
+                return Ok(None);
+            }
+            let Some(before) = self
+                .find_one::<LoginAttempt>(LOGIN_ATTEMPT_COLLECTION_NAME, doc! { "_id": key })
+                .await?
+            else {
+                continue;
+            };
+            // counted only from the attempt read, else read it again
+            let matched = self
+                .update_one(
+                    LOGIN_ATTEMPT_COLLECTION_NAME,
+                    doc! { "_id": key, "failures": before.failures },
+                    doc! {
+                        INC_OP: { "failures": 1 },
+                        SET_OP: { "last_failed_at": attempt.last_failed_at },
+                    },
+                )
+                .await?;
+            if matched > 0 {
+                return Ok(Some(before));
+            }
+        }
+    }
+
+    async fn release_login_attempt(&self, key: &str) -> ServiceResult<()> {
+        // counted from zero again by a lock meanwhile
+        self.update_one(
+            LOGIN_ATTEMPT_COLLECTION_NAME,
+            doc! { "_id": key, "failures": { GTE_OP: 1 } },
+            doc! { INC_OP: { "failures": -1 } },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn record_login_failure(&self, key: &str) -> ServiceResult<Option<LoginAttempt>> {
+        self.update_one(
+            LOGIN_ATTEMPT_COLLECTION_NAME,
+            doc! { "_id": key },
+            doc! { SET_OP: { "last_failed_at": bson::DateTime::now() } },
+        )
+        .await?;
+        self.find_one(LOGIN_ATTEMPT_COLLECTION_NAME, doc! { "_id": key })
+            .await
+    }
+
+    async fn lock_login(&self, key: &str, until: bson::DateTime) -> ServiceResult<()> {
+        self.update_one(
+            LOGIN_ATTEMPT_COLLECTION_NAME,
+            doc! { "_id": key },
+            doc! { SET_OP: { "failures": 0, "locked_until": until } },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn clear_login_attempt(&self, key: &str) -> ServiceResult<bool> {
+        let deleted = self
+            .delete_one(LOGIN_ATTEMPT_COLLECTION_NAME, doc! { "_id": key })
+            .await?;
+        Ok(deleted > 0)
+    }
+
+    async fn get_locked_logins(
+        &self,
+        now: bson::DateTime,
+        limit: i64,
+    ) -> ServiceResult<Vec<LoginAttempt>> {
+        let query = Query::new(doc! { "locked_until": { GTE_OP: now } })
+            .sort(doc! { "last_failed_at": -1 })
+            .limit(limit);
+        self.find(LOGIN_ATTEMPT_COLLECTION_NAME, query).await
+    }
+}
@@ -0,0 +1,7 @@
+pub mod schema {
+    pub use paper_schema::math::*;
+
+    use crate::utils::validate::ValidatedRequest;
+
+    impl ValidatedRequest for RenderMathRequest {}
+}
@@ -0,0 +1,392 @@
+use std::collections::{HashMap, HashSet};
+
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+use mongodb::IndexModel;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ServiceResult, is_duplicate_key},
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+        folder::{Folder, free_folder_name},
+        indexes::{index_name, is_namespace_not_found},
+    },
+};
+
+pub use paper_schema::migration as schema;
+
+/// A migration applied to the database, recorded so it runs once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationRecord {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub description: String,
+    pub applied_at: bson::DateTime,
+    pub affected: u64,
+}
+
+// the id of the single lock document
+const MIGRATION_LOCK_ID: &str = "migrations";
+
+/// The lock held by the instance running the migrations, so instances started
+/// together don't run them twice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationLock {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub owner: String,
+    pub acquired_at: bson::DateTime,
+}
+
+impl MigrationLock {
+    fn new(owner: &str) -> Self {
+        MigrationLock {
+            id: MIGRATION_LOCK_ID.to_string(),
+            owner: owner.to_string(),
+            acquired_at: bson::DateTime::now(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait MigrationRepository: Send + Sync {
+    async fn get_applied_migrations(&self) -> ServiceResult<Vec<MigrationRecord>>;
+    /// Record the migration, already recorded by another instance is fine.
+    async fn record_migration(&self, record: MigrationRecord) -> ServiceResult<()>;
+    /// Take the lock of the migrations for the owner, taking over a lock
+    /// acquired before `stale_before` as its holder died. False when another
+    /// instance holds it.
+    async fn acquire_migration_lock(
+        &self,
+        owner: &str,
+        stale_before: bson::DateTime,
+    ) -> ServiceResult<bool>;
+    /// Release the lock of the migrations held by the owner.
+    async fn release_migration_lock(&self, owner: &str) -> ServiceResult<()>;
+
+    /// Give the folders and papers stored before versioning a `version`, the
+    /// documents to change are only counted in a dry run.
+    async fn backfill_versions(&self, dry_run: bool) -> ServiceResult<u64>;
+    /// Rank the folders stored before manual ordering among their siblings, in
+    /// the order they were created.
+    async fn backfill_folder_sort_order(&self, dry_run: bool) -> ServiceResult<u64>;
+    /// Number the folders named like an older sibling, "name (2)"..., before
+    /// the names are made unique among siblings.
+    async fn rename_duplicate_folders(&self, dry_run: bool) -> ServiceResult<u64>;
+    /// Drop the index of the emails of the users created before it was made
+    /// unique, so that it is created again unique.
+    async fn drop_plain_email_index(&self, dry_run: bool) -> ServiceResult<u64>;
+}
+
+// the name of the index of the emails of the users
+const EMAIL_INDEX: &str = "email_1";
+
+/// The new name of each folder named like an older sibling, the folders given
+/// oldest first.
+fn duplicate_folder_renames(folders: &[Folder]) -> Vec<(String, String)> {
+    let mut taken: HashMap<(&str, Option<&str>), HashSet<String>> = HashMap::new();
+    for folder in folders {
+        taken
+            .entry((&folder.user_id, folder.parent_id.as_deref()))
+            .or_default()
+            .insert(folder.name.clone());
+    }
+    let mut seen = HashSet::new();
+    let mut renames = Vec::new();
+    for folder in folders {
+        let siblings = (folder.user_id.as_str(), folder.parent_id.as_deref());
+        if seen.insert((siblings, folder.name.as_str())) {
+            continue;
+        }
+        let names = taken.entry(siblings).or_default();
+        let name = free_folder_name(names.iter().map(String::as_str), &folder.name);
+        names.insert(name.clone());
+        renames.push((folder.id.clone(), name));
+    }
+    renames
+}
+
+// the update of a renamed folder, its etag changes
+fn rename_update(name: &str) -> bson::Document {
+    doc! {
+        SET_OP: { "name": name, "updated_at": bson::DateTime::now() },
+        INC_OP: { "version": 1 },
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationRepository for MongoClient {
+    async fn get_applied_migrations(&self) -> ServiceResult<Vec<MigrationRecord>> {
+        let cursor = self
+            .collection::<MigrationRecord>(MIGRATION_COLLECTION_NAME)
+            .find(doc! {})
+            .sort(doc! { "_id": 1 })
+            .await?;
+        let records = cursor.try_collect().await?;
+        Ok(records)
+    }
+
+    async fn record_migration(&self, record: MigrationRecord) -> ServiceResult<()> {
+        match self
+            .collection::<MigrationRecord>(MIGRATION_COLLECTION_NAME)
+            .insert_one(record)
+            .await
+        {
+            Err(e) if !is_duplicate_key(&e) => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn acquire_migration_lock(
+        &self,
+        owner: &str,
+        stale_before: bson::DateTime,
+    ) -> ServiceResult<bool> {
+        let collection = self.collection::<MigrationLock>(MIGRATION_LOCK_COLLECTION_NAME);
+        collection
+            .delete_one(doc! { "_id": MIGRATION_LOCK_ID, "acquired_at": { LT_OP: stale_before } })
+            .await?;
+        match collection.insert_one(MigrationLock::new(owner)).await {
+            Ok(_) => Ok(true),
+            Err(e) if is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn release_migration_lock(&self, owner: &str) -> ServiceResult<()> {
+        self.collection::<MigrationLock>(MIGRATION_LOCK_COLLECTION_NAME)
+            .delete_one(doc! { "_id": MIGRATION_LOCK_ID, "owner": owner })
+            .await?;
+        Ok(())
+    }
+
+    async fn backfill_versions(&self, dry_run: bool) -> ServiceResult<u64> {
+        let filter = doc! { "version": { EXISTS_OP: false } };
+        let mut affected = 0;
+        for name in [FOLDER_COLLECTION_NAME, PAPER_COLLECTION_NAME] {
+            let collection = self.collection::<bson::Document>(name);
+            affected += match dry_run {
+                true => collection.count_documents(filter.clone()).await?,
+                false => {
+                    collection
+                        .update_many(filter.clone(), doc! { SET_OP: { "version": 0 } })
+                        .await?
+                        .modified_count
+                }
+            };
+        }
+        Ok(affected)
+    }
+
+    async fn backfill_folder_sort_order(&self, dry_run: bool) -> ServiceResult<u64> {
+        let collection = self.collection::<Folder>(FOLDER_COLLECTION_NAME);
+        let filter = doc! { "sort_order": { EXISTS_OP: false } };
+        if dry_run {
+            return Ok(collection.count_documents(filter).await?);
+        }
+        let folders: Vec<Folder> = collection
+            .find(filter)
+            .sort(doc! { "created_at": 1 })
+            .await?
+            .try_collect()
+            .await?;
+        let mut next_ranks: HashMap<(&str, Option<&str>), u32> = HashMap::new();
+        for folder in &folders {
+            let rank = next_ranks
+                .entry((&folder.user_id, folder.parent_id.as_deref()))
+                .or_default();
+            collection
+                .update_one(
+                    doc! { "_id": &folder.id },
+                    doc! { SET_OP: { "sort_order": *rank } },
+                )
+                .await?;
+            *rank += 1;
+        }
+        Ok(folders.len() as u64)
+    }
+
+    async fn rename_duplicate_folders(&self, dry_run: bool) -> ServiceResult<u64> {
+        let collection = self.collection::<Folder>(FOLDER_COLLECTION_NAME);
+        let folders: Vec<Folder> = collection
+            .find(doc! {})
+            .sort(doc! { "created_at": 1 })
+            .await?
+            .try_collect()
+            .await?;
+        let renames = duplicate_folder_renames(&folders);
+        if !dry_run {
+            for (id, name) in &renames {
+                collection
+                    .update_one(doc! { "_id": id }, rename_update(name))
+                    .await?;
+            }
+        }
+        Ok(renames.len() as u64)
+    }
+
+    async fn drop_plain_email_index(&self, dry_run: bool) -> ServiceResult<u64> {
+        let collection = self.collection::<bson::Document>(USER_COLLECTION_NAME);
+        let indexes: Vec<IndexModel> = match collection.list_indexes().await {
+            Ok(cursor) => cursor.try_collect().await?,
+            Err(e) if is_namespace_not_found(&e) => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let plain = indexes.iter().any(|index| {
+            index_name(index) == EMAIL_INDEX
+                && index.options.as_ref().and_then(|o| o.unique) != Some(true)
+        });
+        if !plain {
+            return Ok(0);
+        }
+        if !dry_run {
+            collection.drop_index(EMAIL_INDEX).await?;
+        }
+        Ok(1)
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationRepository for DocumentDatabase {
+    async fn get_applied_migrations(&self) -> ServiceResult<Vec<MigrationRecord>> {
+        let query = Query::new(doc! {}).sort(doc! { "_id": 1 });
+        self.find(MIGRATION_COLLECTION_NAME, query).await
+    }
+
+    async fn record_migration(&self, record: MigrationRecord) -> ServiceResult<()> {
+        self.try_insert(MIGRATION_COLLECTION_NAME, &record).await?;
+        Ok(())
+    }
+
+    async fn acquire_migration_lock(
+        &self,
+        owner: &str,
+        stale_before: bson::DateTime,
+    ) -> ServiceResult<bool> {
+        let stale = doc! { "_id": MIGRATION_LOCK_ID, "acquired_at": { LT_OP: stale_before } };
+        self.delete_one(MIGRATION_LOCK_COLLECTION_NAME, stale)
+            .await?;
+        self.try_insert(MIGRATION_LOCK_COLLECTION_NAME, &MigrationLock::new(owner))
+            .await
+    }
+
+    async fn release_migration_lock(&self, owner: &str) -> ServiceResult<()> {
+        let filter = doc! { "_id": MIGRATION_LOCK_ID, "owner": owner };
+        self.delete_one(MIGRATION_LOCK_COLLECTION_NAME, filter)
+            .await?;
+        Ok(())
+    }
+
+    async fn backfill_versions(&self, dry_run: bool) -> ServiceResult<u64> {
+        let filter = doc! { "version": { EXISTS_OP: false } };
+        let mut affected = 0;
+        for name in [FOLDER_COLLECTION_NAME, PAPER_COLLECTION_NAME] {
+            affected += match dry_run {
+                true => self.count(name, filter.clone()).await?,
+                false => {
+                    self.update_many(name, filter.clone(), doc! { SET_OP: { "version": 0 } })
+                        .await?
+                }
+            };
+        }
+        Ok(affected)
+    }
+
+    async fn backfill_folder_sort_order(&self, dry_run: bool) -> ServiceResult<u64> {
+        let filter = doc! { "sort_order": { EXISTS_OP: false } };
+        if dry_run {
+            return self.count(FOLDER_COLLECTION_NAME, filter).await;
+        }
+        let query = Query::new(filter).sort(doc! { "created_at": 1 });
+        let folders: Vec<Folder> = self.find(FOLDER_COLLECTION_NAME, query).await?;
+        let mut next_ranks: HashMap<(&str, Option<&str>), u32> = HashMap::new();
+        for folder in &folders {
+            let rank = next_ranks
+                .entry((&folder.user_id, folder.parent_id.as_deref()))
+                .or_default();
+            self.update_one(
+                FOLDER_COLLECTION_NAME,
+                doc! { "_id": &folder.id },
+                doc! { SET_OP: { "sort_order": *rank } },
+            )
+            .await?;
+            *rank += 1;
+        }
+        Ok(folders.len() as u64)
+    }
+
+    async fn rename_duplicate_folders(&self, dry_run: bool) -> ServiceResult<u64> {
+        let query = Query::new(doc! {}).sort(doc! { "created_at": 1 });
+        let folders: Vec<Folder> = self.find(FOLDER_COLLECTION_NAME, query).await?;
+        let renames = duplicate_folder_renames(&folders);
+        if !dry_run {
+            for (id, name) in &renames {
+                self.update_one(
+                    FOLDER_COLLECTION_NAME,
+                    doc! { "_id": id },
+                    rename_update(name),
+                )
+                .await?;
+            }
+        }
+        Ok(renames.len() as u64)
+    }
+
+    // the document stores only keep the unique indexes
+    async fn drop_plain_email_index(&self, _dry_run: bool) -> ServiceResult<u64> {
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_folder_renames() {
+        let folder = |id: &str, parent_id: Option<&str>, name: &str| {
+            let mut folder = Folder::default_system_folder("u1", crate::i18n::Locale::En);
+            folder.id = id.to_string();
+            folder.parent_id = parent_id.map(str::to_string);
+            folder.name = name.to_string();
+            folder
+        };
+        let folders = [
+            folder("f1", None, "Reading"),
+            folder("f2", None, "Reading (2)"),
+            folder("f3", Some("f1"), "Reading"),
+            folder("f4", None, "Reading"),
+            folder("f5", None, "Reading"),
+        ];
+        assert_eq!(
+            duplicate_folder_renames(&folders),
+            vec![
+                ("f4".to_string(), "Reading (3)".to_string()),
+                ("f5".to_string(), "Reading (4)".to_string()),
+            ]
+        );
+        assert_eq!(free_folder_name(["Notes"], "Drafts"), "Drafts");
+    }
+
+    #[tokio::test]
+    async fn test_migration_lock() {
+        use crate::model::document::memory::MemoryStore;
+
+        let db = DocumentDatabase::new(std::sync::Arc::new(MemoryStore::default()));
+        let long_ago = bson::DateTime::from_millis(0);
+        assert!(db.acquire_migration_lock("a", long_ago).await.unwrap());
+        assert!(!db.acquire_migration_lock("b", long_ago).await.unwrap());
+        // only its holder releases it
+        db.release_migration_lock("b").await.unwrap();
+        assert!(!db.acquire_migration_lock("b", long_ago).await.unwrap());
+
+        // taken over once stale
+        let later = bson::DateTime::from_millis(bson::DateTime::now().timestamp_millis() + 1000);
+        assert!(db.acquire_migration_lock("b", later).await.unwrap());
+        db.release_migration_lock("b").await.unwrap();
+        assert!(db.acquire_migration_lock("a", long_ago).await.unwrap());
+    }
+}
@@ -1,9 +1,67 @@
+pub mod account;
+pub mod activity;
+pub mod ai;
+pub mod audit;
+pub mod auth;
+pub mod backup;
+pub mod blob;
+pub mod block;
+pub mod chunk;
+pub mod citation;
+pub mod comparison;
+pub mod consent;
 mod constant;
+pub mod content;
+pub mod conversation;
+pub mod custom_field;
+pub mod database;
+pub mod document;
+pub mod embedding;
+pub mod export;
 pub mod folder;
+pub mod health;
+pub mod idempotency;
+pub mod indexes;
+pub mod job;
+pub mod llm_key;
+pub mod login_attempt;
+pub mod math;
+pub mod migration;
+pub mod note;
+pub mod notification;
+pub mod organization;
+pub mod page;
+pub mod paper;
+pub mod prompt;
+pub mod quarantine;
+pub mod quota;
+pub mod reading_list;
+pub mod retry;
+pub mod revision;
+pub mod share;
+pub mod stats;
+pub mod txn;
+pub mod upload;
+pub mod usage;
 pub mod user;
+pub mod webhook;
 
-pub async fn create_all_index(client: &ai_flow_synth::utils::MongoClient) -> anyhow::Result<()> {
-    folder::create_index(client).await?;
-    user::create_index(client).await?;
-    Ok(())
+/// Filter matching the document only at the expected version. Documents stored
+/// before versioning have no version, which counts as 0.
+pub(crate) fn version_filter(id: &str, version: u32) -> bson::Document {
+    if version == 0 {
+        bson::doc! { "_id": id, "version": { constant::IN_OP: [0, bson::Bson::Null] } }
+    } else {
+        bson::doc! { "_id": id, "version": version }
+    }
+}
+
+/// Numeric field of an aggregation result, whatever numeric type mongo picked.
+pub(crate) fn count_field(doc: &bson::Document, key: &str) -> u64 {
+    match doc.get(key) {
+        Some(bson::Bson::Int32(n)) => (*n).max(0) as u64,
+        Some(bson::Bson::Int64(n)) => (*n).max(0) as u64,
+        Some(bson::Bson::Double(n)) => n.max(0.0) as u64,
+        _ => 0,
+    }
 }
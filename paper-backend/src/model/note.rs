@@ -0,0 +1,13 @@
+/// Key of the CRDT state of the notes of a paper, while they are edited
+/// together.
+pub fn paper_note_key(paper_id: &str) -> String {
+    format!("note/{}", paper_id)
+}
+
+pub mod schema {
+    pub use paper_schema::note::*;
+
+    use crate::utils::validate::ValidatedRequest;
+
+    impl ValidatedRequest for UpdateNotesRequest {}
+}
@@ -0,0 +1,194 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+pub use paper_schema::notification::NotificationKind;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::notification::*;
+
+    use crate::model::notification::Notification;
+
+    impl From<Notification> for NotificationResponse {
+        fn from(notification: Notification) -> Self {
+            NotificationResponse {
+                id: notification.id,
+                kind: notification.kind,
+                title: notification.title,
+                content: notification.content,
+                resource_id: notification.resource_id,
+                read: notification.read,
+                created_at: notification.created_at.timestamp_millis(),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String, // uuid of the user to notify
+    pub created_at: bson::DateTime,
+
+    pub kind: NotificationKind,
+    pub title: String,
+    pub content: Option<String>,
+    // id of the folder / paper this notification refers to
+    pub resource_id: Option<String>,
+    pub read: bool,
+}
+
+impl Notification {
+    pub fn new(user_id: &str, kind: NotificationKind, title: String) -> Self {
+        Notification {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+
+            kind,
+            title,
+            content: None,
+            resource_id: None,
+            read: false,
+        }
+    }
+
+    pub fn with_content(mut self, content: impl ToString) -> Self {
+        self.content = Some(content.to_string());
+        self
+    }
+
+    pub fn with_resource(mut self, resource_id: impl ToString) -> Self {
+        self.resource_id = Some(resource_id.to_string());
+        self
+    }
+}
+
+#[async_trait::async_trait]
+pub trait NotificationRepository: Send + Sync {
+    async fn create_notification(&self, notification: Notification) -> ServiceResult<()>;
+    /// The latest notifications of the user, newest first.
+    async fn get_notifications(
+        &self,
+        user_id: &str,
+        unread_only: bool,
+        limit: i64,
+    ) -> ServiceResult<Vec<Notification>>;
+    async fn count_unread_notifications(&self, user_id: &str) -> ServiceResult<u64>;
+    /// Mark the notification read, false when the user has no such notification.
+    async fn mark_notification_read(&self, user_id: &str, id: &str) -> ServiceResult<bool>;
+    async fn mark_all_notifications_read(&self, user_id: &str) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl NotificationRepository for MongoClient {
+    async fn create_notification(&self, notification: Notification) -> ServiceResult<()> {
+        self.collection::<Notification>(NOTIFICATION_COLLECTION_NAME)
+            .insert_one(notification)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_notifications(
+        &self,
+        user_id: &str,
+        unread_only: bool,
+        limit: i64,
+    ) -> ServiceResult<Vec<Notification>> {
+        let mut filter = doc! { "user_id": user_id };
+        if unread_only {
+            filter.insert("read", false);
+        }
+        let cursor = self
+            .collection::<Notification>(NOTIFICATION_COLLECTION_NAME)
+            .find(filter)
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await?;
+        let notifications = cursor.try_collect().await?;
+        Ok(notifications)
+    }
+
+    async fn count_unread_notifications(&self, user_id: &str) -> ServiceResult<u64> {
+        let filter = doc! { "user_id": user_id, "read": false };
+        let count = self
+            .collection::<Notification>(NOTIFICATION_COLLECTION_NAME)
+            .count_documents(filter)
+            .await?;
+        Ok(count)
+    }
+
+    async fn mark_notification_read(&self, user_id: &str, id: &str) -> ServiceResult<bool> {
+        let filter = doc! { "_id": id, "user_id": user_id };
+        let update = doc! { SET_OP: { "read": true } };
+        let result = self
+            .collection::<Notification>(NOTIFICATION_COLLECTION_NAME)
+            .update_one(filter, update)
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn mark_all_notifications_read(&self, user_id: &str) -> ServiceResult<()> {
+        let filter = doc! { "user_id": user_id, "read": false };
+        let update = doc! { SET_OP: { "read": true } };
+        self.collection::<Notification>(NOTIFICATION_COLLECTION_NAME)
+            .update_many(filter, update)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl NotificationRepository for DocumentDatabase {
+    async fn create_notification(&self, notification: Notification) -> ServiceResult<()> {
+        self.insert(NOTIFICATION_COLLECTION_NAME, &notification)
+            .await
+    }
+
+    async fn get_notifications(
+        &self,
+        user_id: &str,
+        unread_only: bool,
+        limit: i64,
+    ) -> ServiceResult<Vec<Notification>> {
+        let mut filter = doc! { "user_id": user_id };
+        if unread_only {
+            filter.insert("read", false);
+        }
+        let query = Query::new(filter)
+            .sort(doc! { "created_at": -1 })
+            .limit(limit);
+        self.find(NOTIFICATION_COLLECTION_NAME, query).await
+    }
+
+    async fn count_unread_notifications(&self, user_id: &str) -> ServiceResult<u64> {
+        let filter = doc! { "user_id": user_id, "read": false };
+        self.count(NOTIFICATION_COLLECTION_NAME, filter).await
+    }
+
+    async fn mark_notification_read(&self, user_id: &str, id: &str) -> ServiceResult<bool> {
+        let filter = doc! { "_id": id, "user_id": user_id };
+        let update = doc! { SET_OP: { "read": true } };
+        let matched = self
+            .update_one(NOTIFICATION_COLLECTION_NAME, filter, update)
+            .await?;
+        Ok(matched > 0)
+    }
+
+    async fn mark_all_notifications_read(&self, user_id: &str) -> ServiceResult<()> {
+        let filter = doc! { "user_id": user_id, "read": false };
+        let update = doc! { SET_OP: { "read": true } };
+        self.update_many(NOTIFICATION_COLLECTION_NAME, filter, update)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+pub use paper_schema::organization::FolderTemplate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{constant::*, document::DocumentDatabase},
+};
+
+pub mod schema {
+    pub use paper_schema::organization::*;
+
+    use crate::{
+        model::organization::Organization,
+        utils::validate::{ValidatedRequest, trim, trim_option},
+    };
+
+    impl From<Organization> for OrganizationResponse {
+        fn from(org: Organization) -> Self {
+            OrganizationResponse {
+                id: org.id,
+                name: org.name,
+                admin_ids: org.admin_ids,
+                folder_template: org.folder_template,
+            }
+        }
+    }
+
+    impl ValidatedRequest for CreateOrganizationRequest {
+        fn normalize(&mut self) {
+            trim(&mut self.name);
+        }
+    }
+
+    fn normalize_template(template: &mut FolderTemplateRequest) {
+        trim(&mut template.name);
+        trim_option(&mut template.description);
+        template.children.iter_mut().for_each(normalize_template);
+    }
+
+    impl ValidatedRequest for UpdateFolderTemplateRequest {
+        fn normalize(&mut self) {
+            self.folders.iter_mut().for_each(normalize_template);
+        }
+    }
+
+    impl ValidatedRequest for AddMemberRequest {
+        fn normalize(&mut self) {
+            trim(&mut self.email);
+            // stored lowercased at registration
+            self.email = self.email.to_lowercase();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Organization {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub created_at: bson::DateTime,
+    pub updated_at: bson::DateTime,
+
+    pub name: String,
+    // uid of the users allowed to manage the organization
+    pub admin_ids: Vec<String>,
+    // folders provisioned for every new member, the system folder when empty
+    #[serde(default)]
+    pub folder_template: Vec<FolderTemplate>,
+}
+
+impl Organization {
+    pub fn new(name: String, admin_id: &str) -> Self {
+        Organization {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: bson::DateTime::now(),
+            updated_at: bson::DateTime::now(),
+
+            name,
+            admin_ids: vec![admin_id.to_string()],
+            folder_template: Vec::new(),
+        }
+    }
+
+    pub fn is_admin(&self, uid: &str) -> bool {
+        self.admin_ids.iter().any(|id| id == uid)
+    }
+}
+
+#[async_trait::async_trait]
+pub trait OrganizationRepository: Send + Sync {
+    async fn create_organization(&self, org: Organization) -> ServiceResult<()>;
+    async fn get_organization_by_id(&self, id: &str) -> ServiceResult<Option<Organization>>;
+    async fn update_organization(&self, org: Organization) -> ServiceResult<Organization>;
+}
+
+#[async_trait::async_trait]
+impl OrganizationRepository for MongoClient {
+    async fn create_organization(&self, org: Organization) -> ServiceResult<()> {
+        self.collection::<Organization>(ORGANIZATION_COLLECTION_NAME)
+            .insert_one(org)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_organization_by_id(&self, id: &str) -> ServiceResult<Option<Organization>> {
+        let filter = doc! { "_id": id };
+        let result = self
+            .collection::<Organization>(ORGANIZATION_COLLECTION_NAME)
+            .find_one(filter)
+            .await?;
+        Ok(result)
+    }
+
+    async fn update_organization(&self, org: Organization) -> ServiceResult<Organization> {
+        let filter = doc! { "_id": &org.id };
+        let update = doc! {
+            SET_OP: bson::to_bson(&org)?,
+        };
+        self.collection::<Organization>(ORGANIZATION_COLLECTION_NAME)
+            .update_one(filter, update)
+            .await?;
+        Ok(org)
+    }
+}
+
+#[async_trait::async_trait]
+impl OrganizationRepository for DocumentDatabase {
+    async fn create_organization(&self, org: Organization) -> ServiceResult<()> {
+        self.insert(ORGANIZATION_COLLECTION_NAME, &org).await
+    }
+
+    async fn get_organization_by_id(&self, id: &str) -> ServiceResult<Option<Organization>> {
+        self.find_one(ORGANIZATION_COLLECTION_NAME, doc! { "_id": id })
+            .await
+    }
+
+    async fn update_organization(&self, org: Organization) -> ServiceResult<Organization> {
+        let filter = doc! { "_id": &org.id };
+        let update = doc! {
+            SET_OP: bson::to_bson(&org)?,
+        };
+        self.update_one(ORGANIZATION_COLLECTION_NAME, filter, update)
+            .await?;
+        Ok(org)
+    }
+}
@@ -0,0 +1,125 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::page::*;
+
+    use crate::model::page::PaperPage;
+
+    impl From<PaperPage> for PageTextResponse {
+        fn from(page: PaperPage) -> Self {
+            PageTextResponse {
+                page: page.page,
+                text: page.text,
+            }
+        }
+    }
+}
+
+/// Text extracted from one page of the file of a paper.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperPage {
+    #[serde(rename = "_id")]
+    pub id: String, // `{paper_id}:{page}`
+    pub paper_id: String,
+    pub page: u32, // 1 based
+
+    pub text: String,
+}
+
+impl PaperPage {
+    pub fn new(paper_id: &str, page: u32, text: String) -> Self {
+        PaperPage {
+            id: format!("{}:{}", paper_id, page),
+            paper_id: paper_id.to_string(),
+            page,
+
+            text,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait PaperPageRepository: Send + Sync {
+    /// Replace all the pages of the paper.
+    async fn replace_paper_pages(&self, paper_id: &str, pages: Vec<PaperPage>)
+    -> ServiceResult<()>;
+    /// All the pages of the paper, or only the given one.
+    async fn get_paper_pages(
+        &self,
+        paper_id: &str,
+        page: Option<u32>,
+    ) -> ServiceResult<Vec<PaperPage>>;
+}
+
+#[async_trait::async_trait]
+impl PaperPageRepository for MongoClient {
+    async fn replace_paper_pages(
+        &self,
+        paper_id: &str,
+        pages: Vec<PaperPage>,
+    ) -> ServiceResult<()> {
+        let collection = self.collection::<PaperPage>(PAPER_PAGE_COLLECTION_NAME);
+        collection
+            .delete_many(doc! { "paper_id": paper_id })
+            .await?;
+        if !pages.is_empty() {
+            collection.insert_many(pages).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_paper_pages(
+        &self,
+        paper_id: &str,
+        page: Option<u32>,
+    ) -> ServiceResult<Vec<PaperPage>> {
+        let mut filter = doc! { "paper_id": paper_id };
+        if let Some(page) = page {
+            filter.insert("page", page);
+        }
+        let cursor = self
+            .collection::<PaperPage>(PAPER_PAGE_COLLECTION_NAME)
+            .find(filter)
+            .sort(doc! { "page": 1 })
+            .await?;
+        let pages = cursor.try_collect().await?;
+        Ok(pages)
+    }
+}
+
+#[async_trait::async_trait]
+impl PaperPageRepository for DocumentDatabase {
+    async fn replace_paper_pages(
+        &self,
+        paper_id: &str,
+        pages: Vec<PaperPage>,
+    ) -> ServiceResult<()> {
+        self.delete_many(PAPER_PAGE_COLLECTION_NAME, doc! { "paper_id": paper_id })
+            .await?;
+        self.insert_many(PAPER_PAGE_COLLECTION_NAME, &pages).await
+    }
+
+    async fn get_paper_pages(
+        &self,
+        paper_id: &str,
+        page: Option<u32>,
+    ) -> ServiceResult<Vec<PaperPage>> {
+        let mut filter = doc! { "paper_id": paper_id };
+        if let Some(page) = page {
+            filter.insert("page", page);
+        }
+        let query = Query::new(filter).sort(doc! { "page": 1 });
+        self.find(PAPER_PAGE_COLLECTION_NAME, query).await
+    }
+}
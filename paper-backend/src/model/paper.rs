@@ -0,0 +1,927 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use ai_flow_synth::utils::MongoClient;
+use bson::{Document, doc};
+use futures::{StreamExt, TryStreamExt, stream::BoxStream};
+pub use paper_schema::paper::{Progress, TextStatus};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ServiceError, ServiceResult},
+    model::{
+        constant::*,
+        count_field,
+        custom_field::FieldValue,
+        document::{DocumentDatabase, Query},
+        txn::{TxnContext, in_session},
+        version_filter,
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::paper::*;
+
+    use std::collections::HashSet;
+
+    use crate::{
+        model::paper::{Paper, PaperSuggestions},
+        utils::{
+            fields::SparseFields,
+            semantic_scholar::ExternalPaper,
+            validate::{ValidatedRequest, trim, trim_all, trim_option},
+        },
+    };
+
+    impl SparseFields for PaperResponse {
+        const FIELDS: &'static [(&'static str, &'static [&'static str])] = &[
+            ("id", &[]),
+            ("folderId", &["folder_id"]),
+            ("title", &["title"]),
+            ("authors", &["authors"]),
+            ("abstract", &["abstract"]),
+            ("doi", &["doi"]),
+            ("content", &["content"]),
+            ("summary", &["summary"]),
+            ("tags", &["tags"]),
+            ("hasFile", &["file_hash"]),
+            ("fileSize", &["file_size"]),
+            ("fileError", &["file_error"]),
+            ("textStatus", &["text_status"]),
+            ("pageCount", &["page_count"]),
+            ("ocrProgress", &["ocr_progress"]),
+            ("starred", &["starred"]),
+            ("suggestions", &["suggestions"]),
+            ("customFields", &["custom_fields"]),
+            ("version", &["version"]),
+        ];
+        const REQUIRED: &'static [&'static str] = &[
+            "folder_id",
+            "user_id",
+            "created_at",
+            "updated_at",
+            "title",
+            "version",
+        ];
+    }
+
+    impl From<Paper> for PaperResponse {
+        fn from(paper: Paper) -> Self {
+            PaperResponse {
+                id: paper.id,
+                folder_id: paper.folder_id,
+
+                title: paper.title,
+                authors: paper.authors,
+                r#abstract: paper.r#abstract,
+                doi: paper.doi,
+                content: paper.content,
+                summary: paper.summary,
+                tags: paper.tags,
+
+                has_file: paper.file_hash.is_some(),
+                file_size: paper.file_size,
+                file_error: paper.file_error,
+                text_status: paper.text_status,
+                page_count: paper.page_count,
+                ocr_progress: paper.ocr_progress,
+                starred: paper.starred,
+                suggestions: paper.suggestions.map(Into::into),
+                custom_fields: paper.custom_fields,
+                version: paper.version,
+            }
+        }
+    }
+
+    impl From<PaperSuggestions> for SuggestionsResponse {
+        fn from(suggestions: PaperSuggestions) -> Self {
+            SuggestionsResponse {
+                tags: suggestions.tags,
+                folder_id: suggestions.folder_id,
+                created_at: suggestions.created_at.timestamp_millis(),
+            }
+        }
+    }
+
+    impl ValidatedRequest for AcceptSuggestionsRequest {
+        fn normalize(&mut self) {
+            if let Some(tags) = self.tags.as_mut() {
+                trim_all(tags);
+            }
+        }
+    }
+
+    impl ValidatedRequest for CreatePaperRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.folder_id);
+            trim(&mut self.title);
+            trim_all(&mut self.authors);
+            trim_option(&mut self.r#abstract);
+            trim_option(&mut self.doi);
+            trim_all(&mut self.tags);
+        }
+    }
+
+    impl ValidatedRequest for BatchPaperRequest {
+        fn normalize(&mut self) {
+            trim_all(&mut self.paper_ids);
+            self.paper_ids.dedup();
+            trim_option(&mut self.folder_id);
+            trim_all(&mut self.tags);
+        }
+    }
+
+    impl From<ExternalPaper> for ExternalPaperResponse {
+        fn from(paper: ExternalPaper) -> Self {
+            ExternalPaperResponse {
+                doi: paper.doi(),
+                title: paper.title,
+                url: paper.url,
+                year: paper.year,
+                authors: paper.authors.into_iter().map(|a| a.name).collect(),
+            }
+        }
+    }
+
+    impl ValidatedRequest for MergePapersRequest {
+        fn normalize(&mut self) {
+            trim(&mut self.survivor_id);
+            trim_all(&mut self.paper_ids);
+            // in order, each paper is merged once
+            let mut seen = HashSet::from([self.survivor_id.clone()]);
+            self.paper_ids.retain(|id| seen.insert(id.clone()));
+        }
+    }
+
+    impl ValidatedRequest for UpdatePaperRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.folder_id);
+            if let Some(title) = self.title.as_mut() {
+                trim(title);
+            }
+            if let Some(authors) = self.authors.as_mut() {
+                trim_all(authors);
+            }
+            trim_option(&mut self.r#abstract);
+            trim_option(&mut self.doi);
+            if let Some(tags) = self.tags.as_mut() {
+                trim_all(tags);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Paper {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub folder_id: String, // uuid of the folder containing this paper
+    pub user_id: String,   // uuid of the user who owns this paper
+    pub created_at: bson::DateTime,
+    pub updated_at: bson::DateTime,
+
+    pub title: String,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    pub r#abstract: Option<String>,
+    pub doi: Option<String>,
+    pub content: Option<String>, // markdown body or user notes
+    pub summary: Option<String>, // AI generated summary
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // sha256 of the attached file, used to detect duplicates
+    #[serde(default)]
+    pub file_hash: Option<String>,
+    #[serde(default)]
+    pub file_size: Option<u64>,
+    // reason the last uploaded file was refused, it is not attached
+    #[serde(default)]
+    pub file_error: Option<String>,
+    // state of the text extraction of the attached file
+    #[serde(default)]
+    pub text_status: Option<TextStatus>,
+    #[serde(default)]
+    pub page_count: Option<u32>,
+    #[serde(default)]
+    pub ocr_progress: Option<Progress>,
+    #[serde(default)]
+    pub starred: bool,
+    // written by the classification of imported papers, cleared once accepted
+    #[serde(default)]
+    pub suggestions: Option<PaperSuggestions>,
+    // values of the custom fields by key, typed by their definition
+    #[serde(default)]
+    pub custom_fields: BTreeMap<String, FieldValue>,
+    // incremented on every update, guards against concurrent writes
+    #[serde(default)]
+    pub version: u32,
+}
+
+/// Tags and folder the model suggests for a paper.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperSuggestions {
+    pub tags: Vec<String>,
+    pub folder_id: Option<String>,
+    pub model: String,
+    pub created_at: bson::DateTime,
+}
+
+/// A paper matching a full text search, with its text relevance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoredPaper {
+    #[serde(flatten)]
+    pub paper: Paper,
+    pub score: f64,
+}
+
+impl Paper {
+    pub fn new(user_id: &str, folder_id: &str, title: String) -> Self {
+        Paper {
+            id: uuid::Uuid::new_v4().to_string(),
+            folder_id: folder_id.to_string(),
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+            updated_at: bson::DateTime::now(),
+
+            title,
+            authors: Vec::new(),
+            r#abstract: None,
+            doi: None,
+            content: None,
+            summary: None,
+            tags: Vec::new(),
+            file_hash: None,
+            file_size: None,
+            file_error: None,
+            text_status: None,
+            page_count: None,
+            ocr_progress: None,
+            starred: false,
+            suggestions: None,
+            custom_fields: BTreeMap::new(),
+            version: 0,
+        }
+    }
+
+    /// The paper of the request, in its folder or the default one of the user.
+    pub fn new_from_request(
+        user_id: &str,
+        folder_id: &str,
+        request: schema::CreatePaperRequest,
+    ) -> Self {
+        Paper {
+            authors: request.authors,
+            r#abstract: request.r#abstract,
+            doi: request.doi,
+            content: request.content,
+            tags: request.tags,
+            ..Paper::new(user_id, folder_id, request.title)
+        }
+    }
+
+    /// Copy of the metadata of the paper into the folder, without its file.
+    pub fn copy_into(&self, folder_id: &str) -> Self {
+        Paper {
+            authors: self.authors.clone(),
+            r#abstract: self.r#abstract.clone(),
+            doi: self.doi.clone(),
+            content: self.content.clone(),
+            summary: self.summary.clone(),
+            tags: self.tags.clone(),
+            custom_fields: self.custom_fields.clone(),
+            ..Paper::new(&self.user_id, folder_id, self.title.clone())
+        }
+    }
+}
+
+/// A write applied to many papers at once, see `PaperRepository::run_paper_batch`.
+#[derive(Debug, Clone)]
+pub enum PaperBatchOp {
+    Move { folder_id: String },
+    Tag { tags: Vec<String> },
+    Delete,
+}
+
+#[async_trait::async_trait]
+pub trait PaperRepository: Send + Sync {
+    async fn create_paper(&self, paper: Paper) -> ServiceResult<()>;
+    async fn get_paper_by_id(&self, id: &str) -> ServiceResult<Option<Paper>>;
+    /// Like `get_paper_by_id`, loading only the projected fields.
+    async fn get_paper_projected(
+        &self,
+        id: &str,
+        projection: bson::Document,
+    ) -> ServiceResult<Option<Paper>>;
+    async fn get_papers_by_folder_id(&self, folder_id: &str) -> ServiceResult<Vec<Paper>>;
+    async fn get_papers_by_ids(&self, user_id: &str, ids: &[String]) -> ServiceResult<Vec<Paper>>;
+    async fn get_papers_by_user_id(&self, user_id: &str) -> ServiceResult<Vec<Paper>>;
+    /// Full text search in the papers of the user, most relevant first.
+    /// Papers of the user matching the text and the filter, best first.
+    async fn search_papers(
+        &self,
+        user_id: &str,
+        query: &str,
+        filter: Option<bson::Document>,
+        limit: i64,
+    ) -> ServiceResult<Vec<ScoredPaper>>;
+    /// Stream of the papers of the user, optionally in one folder and matching
+    /// the filter, newest first.
+    async fn find_papers(
+        &self,
+        user_id: &str,
+        folder_id: Option<&str>,
+        filter: Option<bson::Document>,
+        projection: Option<bson::Document>,
+    ) -> ServiceResult<BoxStream<'static, ServiceResult<Paper>>>;
+    /// A page of the papers matching the filter, newest first, with how many
+    /// match in all.
+    async fn find_papers_page(
+        &self,
+        filter: bson::Document,
+        offset: u64,
+        limit: i64,
+    ) -> ServiceResult<(Vec<Paper>, u64)>;
+    /// How many papers of the user each folder holds, by folder id.
+    async fn count_papers_by_folder(&self, user_id: &str) -> ServiceResult<HashMap<String, u64>>;
+    /// Apply the op to all the papers, atomically when run in a transaction.
+    async fn run_paper_batch(
+        &self,
+        txn: &mut TxnContext,
+        ids: &[String],
+        op: PaperBatchOp,
+    ) -> ServiceResult<()>;
+    async fn update_paper(&self, paper: Paper) -> ServiceResult<Paper>;
+    async fn set_paper_text_status(
+        &self,
+        id: &str,
+        status: TextStatus,
+        page_count: Option<u32>,
+    ) -> ServiceResult<()>;
+    async fn set_paper_ocr_progress(&self, id: &str, progress: Progress) -> ServiceResult<()>;
+    /// Detach the file refused by the content scan from the paper, with the
+    /// reason. False when the paper holds another file by now.
+    async fn set_paper_file_refused(
+        &self,
+        id: &str,
+        file_hash: &str,
+        reason: &str,
+    ) -> ServiceResult<bool>;
+    async fn set_paper_starred(&self, id: &str, starred: bool) -> ServiceResult<()>;
+    async fn set_paper_suggestions(
+        &self,
+        id: &str,
+        suggestions: &PaperSuggestions,
+    ) -> ServiceResult<()>;
+    /// Distinct tags of the papers of the user.
+    async fn get_user_tags(&self, user_id: &str) -> ServiceResult<Vec<String>>;
+    async fn delete_paper(&self, id: &str) -> ServiceResult<()>;
+}
+
+fn file_refused_update(reason: &str) -> ServiceResult<Document> {
+    Ok(doc! {
+        SET_OP: {
+            "file_hash": null,
+            "file_size": null,
+            "file_error": reason,
+            "text_status": bson::to_bson(&TextStatus::Quarantined)?,
+            "page_count": null,
+            "ocr_progress": null,
+        },
+        INC_OP: { "version": 1 },
+    })
+}
+
+#[async_trait::async_trait]
+impl PaperRepository for MongoClient {
+    async fn create_paper(&self, paper: Paper) -> ServiceResult<()> {
+        self.collection::<Paper>(PAPER_COLLECTION_NAME)
+            .insert_one(paper)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_paper_by_id(&self, id: &str) -> ServiceResult<Option<Paper>> {
+        let filter = doc! { "_id": id };
+        let result = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .find_one(filter)
+            .await?;
+        Ok(result)
+    }
+
+    async fn get_paper_projected(
+        &self,
+        id: &str,
+        projection: bson::Document,
+    ) -> ServiceResult<Option<Paper>> {
+        let filter = doc! { "_id": id };
+        let result = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .find_one(filter)
+            .projection(projection)
+            .await?;
+        Ok(result)
+    }
+
+    async fn get_papers_by_folder_id(&self, folder_id: &str) -> ServiceResult<Vec<Paper>> {
+        let filter = doc! { "folder_id": folder_id };
+        let cursor = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .find(filter)
+            .await?;
+        let papers = cursor.try_collect().await?;
+        Ok(papers)
+    }
+
+    async fn get_papers_by_ids(&self, user_id: &str, ids: &[String]) -> ServiceResult<Vec<Paper>> {
+        let filter = doc! { "user_id": user_id, "_id": { IN_OP: ids } };
+        let cursor = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .find(filter)
+            .await?;
+        let papers = cursor.try_collect().await?;
+        Ok(papers)
+    }
+
+    async fn get_papers_by_user_id(&self, user_id: &str) -> ServiceResult<Vec<Paper>> {
+        let filter = doc! { "user_id": user_id };
+        let cursor = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .find(filter)
+            .await?;
+        let papers = cursor.try_collect().await?;
+        Ok(papers)
+    }
+
+    async fn search_papers(
+        &self,
+        user_id: &str,
+        query: &str,
+        filter: Option<bson::Document>,
+        limit: i64,
+    ) -> ServiceResult<Vec<ScoredPaper>> {
+        let mut filter = filter.unwrap_or_default();
+        filter.insert("user_id", user_id);
+        filter.insert(TEXT_OP, doc! { "$search": query });
+        let score = doc! { "score": { "$meta": "textScore" } };
+        let cursor = self
+            .collection::<ScoredPaper>(PAPER_COLLECTION_NAME)
+            .find(filter)
+            .projection(score.clone())
+            .sort(score)
+            .limit(limit)
+            .await?;
+        let papers = cursor.try_collect().await?;
+        Ok(papers)
+    }
+
+    async fn find_papers(
+        &self,
+        user_id: &str,
+        folder_id: Option<&str>,
+        filter: Option<bson::Document>,
+        projection: Option<bson::Document>,
+    ) -> ServiceResult<BoxStream<'static, ServiceResult<Paper>>> {
+        let mut filter = filter.unwrap_or_default();
+        filter.insert("user_id", user_id);
+        if let Some(folder_id) = folder_id {
+            filter.insert("folder_id", folder_id);
+        }
+        let cursor = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .find(filter)
+            .with_options(
+                mongodb::options::FindOptions::builder()
+                    .projection(projection)
+                    .sort(doc! { "created_at": -1 })
+                    .build(),
+            )
+            .await?;
+        Ok(cursor.map_err(ServiceError::from).boxed())
+    }
+
+    async fn find_papers_page(
+        &self,
+        filter: bson::Document,
+        offset: u64,
+        limit: i64,
+    ) -> ServiceResult<(Vec<Paper>, u64)> {
+        let collection = self.collection::<Paper>(PAPER_COLLECTION_NAME);
+        let total = collection.count_documents(filter.clone()).await?;
+        let cursor = collection
+            .find(filter)
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .skip(offset)
+            .limit(limit)
+            .await?;
+        let papers = cursor.try_collect().await?;
+        Ok((papers, total))
+    }
+
+    async fn count_papers_by_folder(&self, user_id: &str) -> ServiceResult<HashMap<String, u64>> {
+        let pipeline = vec![
+            doc! { MATCH_STAGE: { "user_id": user_id } },
+            doc! { GROUP_STAGE: { "_id": "$folder_id", "papers": { SUM_OP: 1 } } },
+        ];
+        let cursor = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .aggregate(pipeline)
+            .await?;
+        let groups: Vec<Document> = cursor.try_collect().await?;
+        Ok(groups
+            .iter()
+            .filter_map(|group| {
+                let folder_id = group.get_str("_id").ok()?;
+                Some((folder_id.to_string(), count_field(group, "papers")))
+            })
+            .collect())
+    }
+
+    async fn run_paper_batch(
+        &self,
+        txn: &mut TxnContext,
+        ids: &[String],
+        op: PaperBatchOp,
+    ) -> ServiceResult<()> {
+        let collection = self.collection::<Paper>(PAPER_COLLECTION_NAME);
+        let filter = doc! { "_id": { IN_OP: ids } };
+        let now = bson::DateTime::now();
+        match op {
+            PaperBatchOp::Move { folder_id } => {
+                let update = doc! {
+                    SET_OP: { "folder_id": folder_id, "updated_at": now },
+                    INC_OP: { "version": 1 },
+                };
+                in_session!(collection.update_many(filter, update), txn)?;
+            }
+            PaperBatchOp::Tag { tags } => {
+                let update = doc! {
+                    ADD_TO_SET_OP: { "tags": { EACH_OP: tags } },
+                    SET_OP: { "updated_at": now },
+                    INC_OP: { "version": 1 },
+                };
+                in_session!(collection.update_many(filter, update), txn)?;
+            }
+            PaperBatchOp::Delete => {
+                in_session!(collection.delete_many(filter), txn)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn update_paper(&self, mut paper: Paper) -> ServiceResult<Paper> {
+        let filter = version_filter(&paper.id, paper.version);
+        paper.version += 1;
+        let update = doc! {
+            SET_OP: bson::to_bson(&paper)?,
+        };
+        let result = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .update_one(filter, update)
+            .await?;
+        if result.matched_count == 0 {
+            return Err(ServiceError::VersionConflict(format!(
+                "Paper {} was modified concurrently",
+                paper.id
+            )));
+        }
+        Ok(paper)
+    }
+
+    async fn set_paper_text_status(
+        &self,
+        id: &str,
+        status: TextStatus,
+        page_count: Option<u32>,
+    ) -> ServiceResult<()> {
+        let filter = doc! { "_id": id };
+        let update = doc! {
+            SET_OP: {
+                "text_status": bson::to_bson(&status)?,
+                "page_count": page_count,
+            },
+            INC_OP: { "version": 1 },
+        };
+        self.collection::<Paper>(PAPER_COLLECTION_NAME)
+            .update_one(filter, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_paper_file_refused(
+        &self,
+        id: &str,
+        file_hash: &str,
+        reason: &str,
+    ) -> ServiceResult<bool> {
+        let filter = doc! { "_id": id, "file_hash": file_hash };
+        let update = file_refused_update(reason)?;
+        let result = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .update_one(filter, update)
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn set_paper_ocr_progress(&self, id: &str, progress: Progress) -> ServiceResult<()> {
+        let filter = doc! { "_id": id };
+        let update = doc! {
+            SET_OP: { "ocr_progress": bson::to_bson(&progress)? },
+            INC_OP: { "version": 1 },
+        };
+        self.collection::<Paper>(PAPER_COLLECTION_NAME)
+            .update_one(filter, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_paper_starred(&self, id: &str, starred: bool) -> ServiceResult<()> {
+        let filter = doc! { "_id": id };
+        let update = doc! {
+            SET_OP: { "starred": starred },
+            INC_OP: { "version": 1 },
+        };
+        self.collection::<Paper>(PAPER_COLLECTION_NAME)
+            .update_one(filter, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_paper_suggestions(
+        &self,
+        id: &str,
+        suggestions: &PaperSuggestions,
+    ) -> ServiceResult<()> {
+        let filter = doc! { "_id": id };
+        let update = doc! {
+            SET_OP: { "suggestions": bson::to_bson(suggestions)? },
+            INC_OP: { "version": 1 },
+        };
+        self.collection::<Paper>(PAPER_COLLECTION_NAME)
+            .update_one(filter, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_user_tags(&self, user_id: &str) -> ServiceResult<Vec<String>> {
+        let tags = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .distinct("tags", doc! { "user_id": user_id })
+            .await?;
+        Ok(tags
+            .into_iter()
+            .filter_map(|tag| tag.as_str().map(String::from))
+            .collect())
+    }
+
+    async fn delete_paper(&self, id: &str) -> ServiceResult<()> {
+        let filter = doc! { "_id": id };
+        self.collection::<Paper>(PAPER_COLLECTION_NAME)
+            .delete_one(filter)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl PaperRepository for DocumentDatabase {
+    async fn create_paper(&self, paper: Paper) -> ServiceResult<()> {
+        self.insert(PAPER_COLLECTION_NAME, &paper).await
+    }
+
+    async fn get_paper_by_id(&self, id: &str) -> ServiceResult<Option<Paper>> {
+        self.find_one(PAPER_COLLECTION_NAME, doc! { "_id": id })
+            .await
+    }
+
+    async fn get_paper_projected(
+        &self,
+        id: &str,
+        _projection: bson::Document,
+    ) -> ServiceResult<Option<Paper>> {
+        // the whole paper is loaded, a projection only saves the transfer
+        self.get_paper_by_id(id).await
+    }
+
+    async fn get_papers_by_folder_id(&self, folder_id: &str) -> ServiceResult<Vec<Paper>> {
+        let query = Query::new(doc! { "folder_id": folder_id });
+        self.find(PAPER_COLLECTION_NAME, query).await
+    }
+
+    async fn get_papers_by_ids(&self, user_id: &str, ids: &[String]) -> ServiceResult<Vec<Paper>> {
+        let query = Query::new(doc! { "user_id": user_id, "_id": { IN_OP: ids } });
+        self.find(PAPER_COLLECTION_NAME, query).await
+    }
+
+    async fn get_papers_by_user_id(&self, user_id: &str) -> ServiceResult<Vec<Paper>> {
+        let query = Query::new(doc! { "user_id": user_id });
+        self.find(PAPER_COLLECTION_NAME, query).await
+    }
+
+    async fn search_papers(
+        &self,
+        user_id: &str,
+        query: &str,
+        filter: Option<bson::Document>,
+        limit: i64,
+    ) -> ServiceResult<Vec<ScoredPaper>> {
+        let mut filter = filter.unwrap_or_default();
+        filter.insert("user_id", user_id);
+        filter.insert(TEXT_OP, doc! { "$search": query });
+        let search = Query::new(filter).text_score().limit(limit);
+        self.find(PAPER_COLLECTION_NAME, search).await
+    }
+
+    async fn find_papers(
+        &self,
+        user_id: &str,
+        folder_id: Option<&str>,
+        filter: Option<bson::Document>,
+        _projection: Option<bson::Document>,
+    ) -> ServiceResult<BoxStream<'static, ServiceResult<Paper>>> {
+        let mut filter = filter.unwrap_or_default();
+        filter.insert("user_id", user_id);
+        if let Some(folder_id) = folder_id {
+            filter.insert("folder_id", folder_id);
+        }
+        let query = Query::new(filter).sort(doc! { "created_at": -1 });
+        let papers: Vec<Paper> = self.find(PAPER_COLLECTION_NAME, query).await?;
+        Ok(futures::stream::iter(papers.into_iter().map(Ok)).boxed())
+    }
+
+    async fn find_papers_page(
+        &self,
+        filter: bson::Document,
+        offset: u64,
+        limit: i64,
+    ) -> ServiceResult<(Vec<Paper>, u64)> {
+        let total = self.count(PAPER_COLLECTION_NAME, filter.clone()).await?;
+        let query = Query::new(filter)
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .skip(offset)
+            .limit(limit);
+        let papers = self.find(PAPER_COLLECTION_NAME, query).await?;
+        Ok((papers, total))
+    }
+
+    async fn count_papers_by_folder(&self, user_id: &str) -> ServiceResult<HashMap<String, u64>> {
+        let query = Query::new(doc! { "user_id": user_id });
+        let papers = self.find_documents(PAPER_COLLECTION_NAME, query).await?;
+        let mut counts = HashMap::new();
+        for paper in &papers {
+            if let Ok(folder_id) = paper.get_str("folder_id") {
+                *counts.entry(folder_id.to_string()).or_default() += 1;
+            }
+        }
+        Ok(counts)
+    }
+
+    async fn run_paper_batch(
+        &self,
+        txn: &mut TxnContext,
+        ids: &[String],
+        op: PaperBatchOp,
+    ) -> ServiceResult<()> {
+        let db = txn.documents(self);
+        let filter = doc! { "_id": { IN_OP: ids } };
+        let now = bson::DateTime::now();
+        match op {
+            PaperBatchOp::Move { folder_id } => {
+                let update = doc! {
+                    SET_OP: { "folder_id": folder_id, "updated_at": now },
+                    INC_OP: { "version": 1 },
+                };
+                db.update_many(PAPER_COLLECTION_NAME, filter, update)
+                    .await?;
+            }
+            PaperBatchOp::Tag { tags } => {
+                let update = doc! {
+                    ADD_TO_SET_OP: { "tags": { EACH_OP: tags } },
+                    SET_OP: { "updated_at": now },
+                    INC_OP: { "version": 1 },
+                };
+                db.update_many(PAPER_COLLECTION_NAME, filter, update)
+                    .await?;
+            }
+            PaperBatchOp::Delete => {
+                db.delete_many(PAPER_COLLECTION_NAME, filter).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn update_paper(&self, mut paper: Paper) -> ServiceResult<Paper> {
+        let filter = version_filter(&paper.id, paper.version);
+        paper.version += 1;
+        let update = doc! {
+            SET_OP: bson::to_bson(&paper)?,
+        };
+        let matched = self
+            .update_one(PAPER_COLLECTION_NAME, filter, update)
+            .await?;
+        if matched == 0 {
+            return Err(ServiceError::VersionConflict(format!(
+                "Paper {} was modified concurrently",
+                paper.id
+            )));
+        }
+        Ok(paper)
+    }
+
+    async fn set_paper_text_status(
+        &self,
+        id: &str,
+        status: TextStatus,
+        page_count: Option<u32>,
+    ) -> ServiceResult<()> {
+        let update = doc! {
+            SET_OP: {
+                "text_status": bson::to_bson(&status)?,
+                "page_count": page_count,
+            },
+            INC_OP: { "version": 1 },
+        };
+        self.update_one(PAPER_COLLECTION_NAME, doc! { "_id": id }, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_paper_file_refused(
+        &self,
+        id: &str,
+        file_hash: &str,
+        reason: &str,
+    ) -> ServiceResult<bool> {
+        let filter = doc! { "_id": id, "file_hash": file_hash };
+        let update = file_refused_update(reason)?;
+        let matched = self
+            .update_one(PAPER_COLLECTION_NAME, filter, update)
+            .await?;
+        Ok(matched > 0)
+    }
+
+    async fn set_paper_ocr_progress(&self, id: &str, progress: Progress) -> ServiceResult<()> {
+        let update = doc! {
+            SET_OP: { "ocr_progress": bson::to_bson(&progress)? },
+            INC_OP: { "version": 1 },
+        };
+        self.update_one(PAPER_COLLECTION_NAME, doc! { "_id": id }, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_paper_starred(&self, id: &str, starred: bool) -> ServiceResult<()> {
+        let update = doc! {
+            SET_OP: { "starred": starred },
+            INC_OP: { "version": 1 },
+        };
+        self.update_one(PAPER_COLLECTION_NAME, doc! { "_id": id }, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_paper_suggestions(
+        &self,
+        id: &str,
+        suggestions: &PaperSuggestions,
+    ) -> ServiceResult<()> {
+        let update = doc! {
+            SET_OP: { "suggestions": bson::to_bson(suggestions)? },
+            INC_OP: { "version": 1 },
+        };
+        self.update_one(PAPER_COLLECTION_NAME, doc! { "_id": id }, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_user_tags(&self, user_id: &str) -> ServiceResult<Vec<String>> {
+        let papers: Vec<Paper> = self.get_papers_by_user_id(user_id).await?;
+        let tags = papers
+            .into_iter()
+            .flat_map(|paper| paper.tags)
+            .collect::<BTreeSet<_>>();
+        Ok(tags.into_iter().collect())
+    }
+
+    async fn delete_paper(&self, id: &str) -> ServiceResult<()> {
+        self.delete_one(PAPER_COLLECTION_NAME, doc! { "_id": id })
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::schema::MergePapersRequest;
+    use crate::utils::validate::ValidatedRequest;
+
+    #[test]
+    fn test_merge_papers_normalize() {
+        let mut request = MergePapersRequest {
+            survivor_id: " s ".to_string(),
+            paper_ids: ["a", "b", "s", "a", " b"].map(str::to_string).to_vec(),
+        };
+        request.normalize();
+        assert_eq!(request.survivor_id, "s");
+        assert_eq!(request.paper_ids, ["a", "b"]);
+    }
+}
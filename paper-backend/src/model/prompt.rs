@@ -0,0 +1,226 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::prompt::*;
+
+    use crate::{
+        model::prompt::PromptTemplate,
+        utils::validate::{ValidatedRequest, trim, trim_all, trim_option},
+    };
+
+    impl From<PromptTemplate> for PromptTemplateResponse {
+        fn from(template: PromptTemplate) -> Self {
+            PromptTemplateResponse {
+                id: template.id,
+                name: template.name,
+                version: template.version,
+                description: template.description,
+                variables: template.variables,
+                body: template.body,
+                created_by: template.created_by,
+                created_at: template.created_at.timestamp_millis(),
+                updated_at: template.updated_at.timestamp_millis(),
+            }
+        }
+    }
+
+    impl ValidatedRequest for CreatePromptTemplateRequest {
+        fn normalize(&mut self) {
+            trim(&mut self.name);
+            trim_option(&mut self.description);
+            trim_all(&mut self.variables);
+            self.variables.dedup();
+        }
+    }
+
+    impl ValidatedRequest for UpdatePromptTemplateRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.description);
+            trim_all(&mut self.variables);
+            self.variables.dedup();
+        }
+    }
+}
+
+/// A version of the prompt sent to the model by an ai feature, tunable by the
+/// operators without a redeploy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub name: String,
+    // the name and version are unique together
+    pub version: u32,
+    pub created_by: String, // uid of the operator
+    pub created_at: bson::DateTime,
+    pub updated_at: bson::DateTime,
+
+    pub description: Option<String>,
+    pub variables: Vec<String>,
+    // `{{variable}}` placeholders are filled at call time
+    pub body: String,
+}
+
+impl PromptTemplate {
+    pub fn new(
+        created_by: &str,
+        version: u32,
+        request: schema::CreatePromptTemplateRequest,
+    ) -> Self {
+        PromptTemplate {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: request.name,
+            version,
+            created_by: created_by.to_string(),
+            created_at: bson::DateTime::now(),
+            updated_at: bson::DateTime::now(),
+
+            description: request.description,
+            variables: request.variables,
+            body: request.body,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait PromptTemplateRepository: Send + Sync {
+    async fn create_prompt_template(&self, template: PromptTemplate) -> ServiceResult<()>;
+    async fn get_prompt_template(&self, id: &str) -> ServiceResult<Option<PromptTemplate>>;
+    /// The templates, of the name when given, by name then newest version first.
+    async fn get_prompt_templates(&self, name: Option<&str>) -> ServiceResult<Vec<PromptTemplate>>;
+    /// The version of the template, the latest one when `None`.
+    async fn find_prompt_template(
+        &self,
+        name: &str,
+        version: Option<u32>,
+    ) -> ServiceResult<Option<PromptTemplate>>;
+    async fn update_prompt_template(&self, template: PromptTemplate) -> ServiceResult<()>;
+    /// Delete the template, false when there is no such template.
+    async fn delete_prompt_template(&self, id: &str) -> ServiceResult<bool>;
+}
+
+#[async_trait::async_trait]
+impl PromptTemplateRepository for MongoClient {
+    async fn create_prompt_template(&self, template: PromptTemplate) -> ServiceResult<()> {
+        self.collection::<PromptTemplate>(PROMPT_TEMPLATE_COLLECTION_NAME)
+            .insert_one(template)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_prompt_template(&self, id: &str) -> ServiceResult<Option<PromptTemplate>> {
+        let result = self
+            .collection::<PromptTemplate>(PROMPT_TEMPLATE_COLLECTION_NAME)
+            .find_one(doc! { "_id": id })
+            .await?;
+        Ok(result)
+    }
+
+    async fn get_prompt_templates(&self, name: Option<&str>) -> ServiceResult<Vec<PromptTemplate>> {
+        let filter = match name {
+            Some(name) => doc! { "name": name },
+            None => doc! {},
+        };
+        let cursor = self
+            .collection::<PromptTemplate>(PROMPT_TEMPLATE_COLLECTION_NAME)
+            .find(filter)
+            .sort(doc! { "name": 1, "version": -1 })
+            .await?;
+        let templates = cursor.try_collect().await?;
+        Ok(templates)
+    }
+
+    async fn find_prompt_template(
+        &self,
+        name: &str,
+        version: Option<u32>,
+    ) -> ServiceResult<Option<PromptTemplate>> {
+        let mut filter = doc! { "name": name };
+        if let Some(version) = version {
+            filter.insert("version", version);
+        }
+        let result = self
+            .collection::<PromptTemplate>(PROMPT_TEMPLATE_COLLECTION_NAME)
+            .find_one(filter)
+            .sort(doc! { "version": -1 })
+            .await?;
+        Ok(result)
+    }
+
+    async fn update_prompt_template(&self, template: PromptTemplate) -> ServiceResult<()> {
+        let filter = doc! { "_id": &template.id };
+        self.collection::<PromptTemplate>(PROMPT_TEMPLATE_COLLECTION_NAME)
+            .replace_one(filter, template)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_prompt_template(&self, id: &str) -> ServiceResult<bool> {
+        let result = self
+            .collection::<PromptTemplate>(PROMPT_TEMPLATE_COLLECTION_NAME)
+            .delete_one(doc! { "_id": id })
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+}
+
+#[async_trait::async_trait]
+impl PromptTemplateRepository for DocumentDatabase {
+    async fn create_prompt_template(&self, template: PromptTemplate) -> ServiceResult<()> {
+        self.insert(PROMPT_TEMPLATE_COLLECTION_NAME, &template)
+            .await
+    }
+
+    async fn get_prompt_template(&self, id: &str) -> ServiceResult<Option<PromptTemplate>> {
+        self.find_one(PROMPT_TEMPLATE_COLLECTION_NAME, doc! { "_id": id })
+            .await
+    }
+
+    async fn get_prompt_templates(&self, name: Option<&str>) -> ServiceResult<Vec<PromptTemplate>> {
+        let filter = match name {
+            Some(name) => doc! { "name": name },
+            None => doc! {},
+        };
+        let query = Query::new(filter).sort(doc! { "name": 1, "version": -1 });
+        self.find(PROMPT_TEMPLATE_COLLECTION_NAME, query).await
+    }
+
+    async fn find_prompt_template(
+        &self,
+        name: &str,
+        version: Option<u32>,
+    ) -> ServiceResult<Option<PromptTemplate>> {
+        let mut filter = doc! { "name": name };
+        if let Some(version) = version {
+            filter.insert("version", version);
+        }
+        let query = Query::new(filter).sort(doc! { "version": -1 });
+        self.find_first(PROMPT_TEMPLATE_COLLECTION_NAME, query)
+            .await
+    }
+
+    async fn update_prompt_template(&self, template: PromptTemplate) -> ServiceResult<()> {
+        let filter = doc! { "_id": &template.id };
+        self.replace_one(PROMPT_TEMPLATE_COLLECTION_NAME, filter, &template, false)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_prompt_template(&self, id: &str) -> ServiceResult<bool> {
+        let deleted = self
+            .delete_one(PROMPT_TEMPLATE_COLLECTION_NAME, doc! { "_id": id })
+            .await?;
+        Ok(deleted > 0)
+    }
+}
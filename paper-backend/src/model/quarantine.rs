@@ -0,0 +1,134 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        content::content_hash,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::quarantine::*;
+
+    use crate::model::quarantine::QuarantinedFile;
+
+    impl From<QuarantinedFile> for QuarantinedFileResponse {
+        fn from(file: QuarantinedFile) -> Self {
+            QuarantinedFileResponse {
+                id: file.id,
+                user_id: file.user_id,
+                paper_id: file.paper_id,
+                file_hash: file.file_hash,
+                file_size: file.file_size,
+                reason: file.reason,
+                scanner: file.scanner,
+                created_at: file.created_at.timestamp_millis(),
+            }
+        }
+    }
+}
+
+/// An uploaded file refused by the content scan, kept aside for the operators
+/// instead of being served or processed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantinedFile {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid, the bytes are in the `quarantine/{id}` blob
+    pub user_id: String,
+    pub paper_id: String,
+    pub file_hash: String, // sha256
+    pub file_size: u64,
+    pub reason: String,
+    pub scanner: String,
+    pub created_at: bson::DateTime,
+}
+
+impl QuarantinedFile {
+    pub fn new(user_id: &str, paper_id: &str, bytes: &[u8], reason: &str, scanner: &str) -> Self {
+        QuarantinedFile {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            paper_id: paper_id.to_string(),
+            file_hash: content_hash(bytes),
+            file_size: bytes.len() as u64,
+            reason: reason.to_string(),
+            scanner: scanner.to_string(),
+            created_at: bson::DateTime::now(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait QuarantineRepository: Send + Sync {
+    async fn create_quarantined_file(&self, file: QuarantinedFile) -> ServiceResult<()>;
+    async fn get_quarantined_file(&self, id: &str) -> ServiceResult<Option<QuarantinedFile>>;
+    /// The latest quarantined files of every user first.
+    async fn get_quarantined_files(&self, limit: i64) -> ServiceResult<Vec<QuarantinedFile>>;
+    async fn delete_quarantined_file(&self, id: &str) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl QuarantineRepository for MongoClient {
+    async fn create_quarantined_file(&self, file: QuarantinedFile) -> ServiceResult<()> {
+        self.collection::<QuarantinedFile>(QUARANTINE_COLLECTION_NAME)
+            .insert_one(file)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_quarantined_file(&self, id: &str) -> ServiceResult<Option<QuarantinedFile>> {
+        let file = self
+            .collection::<QuarantinedFile>(QUARANTINE_COLLECTION_NAME)
+            .find_one(doc! { "_id": id })
+            .await?;
+        Ok(file)
+    }
+
+    async fn get_quarantined_files(&self, limit: i64) -> ServiceResult<Vec<QuarantinedFile>> {
+        let cursor = self
+            .collection::<QuarantinedFile>(QUARANTINE_COLLECTION_NAME)
+            .find(doc! {})
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await?;
+        let files = cursor.try_collect().await?;
+        Ok(files)
+    }
+
+    async fn delete_quarantined_file(&self, id: &str) -> ServiceResult<()> {
+        self.collection::<QuarantinedFile>(QUARANTINE_COLLECTION_NAME)
+            .delete_one(doc! { "_id": id })
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl QuarantineRepository for DocumentDatabase {
+    async fn create_quarantined_file(&self, file: QuarantinedFile) -> ServiceResult<()> {
+        self.insert(QUARANTINE_COLLECTION_NAME, &file).await
+    }
+
+    async fn get_quarantined_file(&self, id: &str) -> ServiceResult<Option<QuarantinedFile>> {
+        self.find_one(QUARANTINE_COLLECTION_NAME, doc! { "_id": id })
+            .await
+    }
+
+    async fn get_quarantined_files(&self, limit: i64) -> ServiceResult<Vec<QuarantinedFile>> {
+        let query = Query::new(doc! {})
+            .sort(doc! { "created_at": -1 })
+            .limit(limit);
+        self.find(QUARANTINE_COLLECTION_NAME, query).await
+    }
+
+    async fn delete_quarantined_file(&self, id: &str) -> ServiceResult<()> {
+        self.delete_one(QUARANTINE_COLLECTION_NAME, doc! { "_id": id })
+            .await?;
+        Ok(())
+    }
+}
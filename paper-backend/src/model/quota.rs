@@ -0,0 +1,173 @@
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::QuotaConfig,
+    error::{ServiceError, ServiceResult},
+};
+
+pub mod schema {
+    pub use paper_schema::quota::*;
+
+    use crate::{
+        model::quota::{QuotaOverrides, Quotas},
+        utils::validate::ValidatedRequest,
+    };
+
+    impl From<Quotas> for QuotaLimitsResponse {
+        fn from(quotas: Quotas) -> Self {
+            QuotaLimitsResponse {
+                max_folder_depth: Some(quotas.max_folder_depth),
+                max_folders: quotas.max_folders,
+                max_papers_per_folder: quotas.max_papers_per_folder,
+                max_storage_bytes: quotas.max_storage_bytes,
+            }
+        }
+    }
+
+    impl From<QuotaOverrides> for QuotaLimitsResponse {
+        fn from(overrides: QuotaOverrides) -> Self {
+            QuotaLimitsResponse {
+                max_folder_depth: overrides.max_folder_depth,
+                max_folders: overrides.max_folders,
+                max_papers_per_folder: overrides.max_papers_per_folder,
+                max_storage_bytes: overrides.max_storage_bytes,
+            }
+        }
+    }
+
+    impl ValidatedRequest for UpdateUserQuotasRequest {}
+
+    impl From<UpdateUserQuotasRequest> for QuotaOverrides {
+        fn from(request: UpdateUserQuotasRequest) -> Self {
+            QuotaOverrides {
+                max_folder_depth: request.max_folder_depth,
+                max_folders: request.max_folders,
+                max_papers_per_folder: request.max_papers_per_folder,
+                max_storage_bytes: request.max_storage_bytes,
+            }
+        }
+    }
+}
+
+/// What a library quota limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaResource {
+    FolderDepth,
+    Folders,
+    PapersPerFolder,
+    StorageBytes,
+}
+
+impl std::fmt::Display for QuotaResource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            QuotaResource::FolderDepth => "folder_depth",
+            QuotaResource::Folders => "folders",
+            QuotaResource::PapersPerFolder => "papers_per_folder",
+            QuotaResource::StorageBytes => "storage_bytes",
+        };
+        f.write_str(name)
+    }
+}
+
+impl QuotaResource {
+    /// The limit in words, for the error message.
+    pub fn describe(&self, limit: u64) -> String {
+        match self {
+            QuotaResource::FolderDepth => format!("folders can be nested at most {} levels", limit),
+            QuotaResource::Folders => format!("at most {} folders", limit),
+            QuotaResource::PapersPerFolder => format!("at most {} papers per folder", limit),
+            QuotaResource::StorageBytes => format!("at most {} bytes of files", limit),
+        }
+    }
+}
+
+/// Limits set by an operator for a single user, over the ones of the config.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuotaOverrides {
+    pub max_folder_depth: Option<u64>,
+    pub max_folders: Option<u64>,
+    pub max_papers_per_folder: Option<u64>,
+    pub max_storage_bytes: Option<u64>,
+}
+
+/// The limits enforced on the library of a user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quotas {
+    // levels of folders, the root level is 1
+    pub max_folder_depth: u64,
+    pub max_folders: Option<u64>,
+    pub max_papers_per_folder: Option<u64>,
+    pub max_storage_bytes: Option<u64>,
+}
+
+impl Quotas {
+    pub fn of(config: &QuotaConfig, overrides: &QuotaOverrides) -> Self {
+        Quotas {
+            max_folder_depth: overrides
+                .max_folder_depth
+                .unwrap_or(config.max_folder_depth),
+            max_folders: overrides.max_folders.or(config.max_folders),
+            max_papers_per_folder: overrides
+                .max_papers_per_folder
+                .or(config.max_papers_per_folder),
+            max_storage_bytes: overrides.max_storage_bytes.or(config.max_storage_bytes),
+        }
+    }
+}
+
+/// Fail with `ResourceQuotaExceeded` when adding to what is used goes over the limit.
+pub fn check_quota(
+    resource: QuotaResource,
+    limit: Option<u64>,
+    used: u64,
+    added: u64,
+) -> ServiceResult<()> {
+    match limit {
+        Some(limit) if used.saturating_add(added) > limit => {
+            Err(ServiceError::ResourceQuotaExceeded {
+                resource,
+                used,
+                limit,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    #[test]
+    fn test_quotas() {
+        let config = QuotaConfig {
+            max_folders: Some(10),
+            max_storage_bytes: Some(100),
+            ..QuotaConfig::default()
+        };
+        let overrides = QuotaOverrides {
+            max_folder_depth: Some(3),
+            max_storage_bytes: Some(1000),
+            ..QuotaOverrides::default()
+        };
+        let quotas = Quotas::of(&config, &overrides);
+        assert_eq!(quotas.max_folder_depth, 3);
+        assert_eq!(quotas.max_folders, Some(10));
+        assert_eq!(quotas.max_papers_per_folder, None);
+        assert_eq!(quotas.max_storage_bytes, Some(1000));
+        assert_eq!(
+            Quotas::of(&config, &QuotaOverrides::default()).max_folder_depth,
+            8
+        );
+
+        assert!(check_quota(QuotaResource::Folders, quotas.max_folders, 9, 1).is_ok());
+        let err = check_quota(QuotaResource::Folders, quotas.max_folders, 10, 1).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ResourceQuotaExceeded);
+        assert!(check_quota(QuotaResource::PapersPerFolder, None, u64::MAX, 1).is_ok());
+    }
+}
@@ -0,0 +1,260 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+pub use paper_schema::reading_list::{ReadingPriority, ReadingStatus};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::reading_list::*;
+
+    use crate::{
+        model::reading_list::ReadingListItem,
+        utils::validate::{ValidatedRequest, trim, trim_all},
+    };
+
+    impl From<ReadingListItem> for ReadingListItemResponse {
+        fn from(item: ReadingListItem) -> Self {
+            ReadingListItemResponse {
+                paper_id: item.paper_id,
+                title: item.title,
+                priority: item.priority,
+                status: item.status,
+                position: item.position,
+                added_at: item.created_at.timestamp_millis(),
+                started_at: item.started_at.map(|t| t.timestamp_millis()),
+                finished_at: item.finished_at.map(|t| t.timestamp_millis()),
+            }
+        }
+    }
+
+    impl ValidatedRequest for AddReadingListItemRequest {
+        fn normalize(&mut self) {
+            trim(&mut self.paper_id);
+        }
+    }
+
+    impl ValidatedRequest for UpdateReadingListItemRequest {}
+
+    impl ValidatedRequest for ReorderReadingListRequest {
+        fn normalize(&mut self) {
+            trim_all(&mut self.paper_ids);
+        }
+    }
+}
+
+/// A paper queued for reading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadingListItem {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String,
+    pub paper_id: String,
+    pub created_at: bson::DateTime,
+    pub updated_at: bson::DateTime,
+
+    // copied from the paper so the queue renders without loading papers
+    pub title: String,
+    pub priority: ReadingPriority,
+    pub status: ReadingStatus,
+    pub position: u32,
+    pub started_at: Option<bson::DateTime>,
+    pub finished_at: Option<bson::DateTime>,
+}
+
+impl ReadingListItem {
+    pub fn new(user_id: &str, paper_id: &str, title: String, position: u32) -> Self {
+        ReadingListItem {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            paper_id: paper_id.to_string(),
+            created_at: bson::DateTime::now(),
+            updated_at: bson::DateTime::now(),
+
+            title,
+            priority: ReadingPriority::Normal,
+            status: ReadingStatus::ToRead,
+            position,
+            started_at: None,
+            finished_at: None,
+        }
+    }
+
+    /// Move to the status, recording when reading started and finished.
+    pub fn set_status(&mut self, status: ReadingStatus) {
+        let now = bson::DateTime::now();
+        match status {
+            ReadingStatus::ToRead => {
+                self.started_at = None;
+                self.finished_at = None;
+            }
+            ReadingStatus::Reading => {
+                self.started_at.get_or_insert(now);
+                self.finished_at = None;
+            }
+            ReadingStatus::Done => {
+                self.started_at.get_or_insert(now);
+                if self.status != ReadingStatus::Done {
+                    self.finished_at = Some(now);
+                }
+            }
+        }
+        self.status = status;
+    }
+}
+
+#[async_trait::async_trait]
+pub trait ReadingListRepository: Send + Sync {
+    async fn create_reading_list_item(&self, item: ReadingListItem) -> ServiceResult<()>;
+    async fn get_reading_list_item(
+        &self,
+        user_id: &str,
+        paper_id: &str,
+    ) -> ServiceResult<Option<ReadingListItem>>;
+    /// The queue of the user, in order.
+    async fn get_reading_list(&self, user_id: &str) -> ServiceResult<Vec<ReadingListItem>>;
+    async fn update_reading_list_item(&self, item: ReadingListItem) -> ServiceResult<()>;
+    /// Set the positions of the queued papers, given in their new order.
+    async fn reorder_reading_list(&self, user_id: &str, paper_ids: &[String]) -> ServiceResult<()>;
+    async fn delete_reading_list_item(&self, user_id: &str, paper_id: &str) -> ServiceResult<()>;
+    /// Take the deleted paper off the queue of every user.
+    async fn delete_paper_reading_list_items(&self, paper_id: &str) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl ReadingListRepository for MongoClient {
+    async fn create_reading_list_item(&self, item: ReadingListItem) -> ServiceResult<()> {
+        self.collection::<ReadingListItem>(READING_LIST_COLLECTION_NAME)
+            .insert_one(item)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_reading_list_item(
+        &self,
+        user_id: &str,
+        paper_id: &str,
+    ) -> ServiceResult<Option<ReadingListItem>> {
+        let filter = doc! { "user_id": user_id, "paper_id": paper_id };
+        let result = self
+            .collection::<ReadingListItem>(READING_LIST_COLLECTION_NAME)
+            .find_one(filter)
+            .await?;
+        Ok(result)
+    }
+
+    async fn get_reading_list(&self, user_id: &str) -> ServiceResult<Vec<ReadingListItem>> {
+        let filter = doc! { "user_id": user_id };
+        let cursor = self
+            .collection::<ReadingListItem>(READING_LIST_COLLECTION_NAME)
+            .find(filter)
+            .sort(doc! { "position": 1 })
+            .await?;
+        let items = cursor.try_collect().await?;
+        Ok(items)
+    }
+
+    async fn update_reading_list_item(&self, item: ReadingListItem) -> ServiceResult<()> {
+        let filter = doc! { "_id": &item.id };
+        self.collection::<ReadingListItem>(READING_LIST_COLLECTION_NAME)
+            .replace_one(filter, item)
+            .await?;
+        Ok(())
+    }
+
+    async fn reorder_reading_list(&self, user_id: &str, paper_ids: &[String]) -> ServiceResult<()> {
+        let collection = self.collection::<ReadingListItem>(READING_LIST_COLLECTION_NAME);
+        let now = bson::DateTime::now();
+
+        let mut session = self.start_session().await?;
+        session.start_transaction().await?;
+        for (position, paper_id) in paper_ids.iter().enumerate() {
+            let filter = doc! { "user_id": user_id, "paper_id": paper_id };
+            let update = doc! {
+                SET_OP: { "position": position as u32, "updated_at": now },
+            };
+            collection
+                .update_one(filter, update)
+                .session(&mut session)
+                .await?;
+        }
+        session.commit_transaction().await?;
+        Ok(())
+    }
+
+    async fn delete_reading_list_item(&self, user_id: &str, paper_id: &str) -> ServiceResult<()> {
+        let filter = doc! { "user_id": user_id, "paper_id": paper_id };
+        self.collection::<ReadingListItem>(READING_LIST_COLLECTION_NAME)
+            .delete_one(filter)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_paper_reading_list_items(&self, paper_id: &str) -> ServiceResult<()> {
+        self.collection::<ReadingListItem>(READING_LIST_COLLECTION_NAME)
+            .delete_many(doc! { "paper_id": paper_id })
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ReadingListRepository for DocumentDatabase {
+    async fn create_reading_list_item(&self, item: ReadingListItem) -> ServiceResult<()> {
+        self.insert(READING_LIST_COLLECTION_NAME, &item).await
+    }
+
+    async fn get_reading_list_item(
+        &self,
+        user_id: &str,
+        paper_id: &str,
+    ) -> ServiceResult<Option<ReadingListItem>> {
+        let filter = doc! { "user_id": user_id, "paper_id": paper_id };
+        self.find_one(READING_LIST_COLLECTION_NAME, filter).await
+    }
+
+    async fn get_reading_list(&self, user_id: &str) -> ServiceResult<Vec<ReadingListItem>> {
+        let query = Query::new(doc! { "user_id": user_id }).sort(doc! { "position": 1 });
+        self.find(READING_LIST_COLLECTION_NAME, query).await
+    }
+
+    async fn update_reading_list_item(&self, item: ReadingListItem) -> ServiceResult<()> {
+        let filter = doc! { "_id": &item.id };
+        self.replace_one(READING_LIST_COLLECTION_NAME, filter, &item, false)
+            .await?;
+        Ok(())
+    }
+
+    async fn reorder_reading_list(&self, user_id: &str, paper_ids: &[String]) -> ServiceResult<()> {
+        let now = bson::DateTime::now();
+        for (position, paper_id) in paper_ids.iter().enumerate() {
+            let filter = doc! { "user_id": user_id, "paper_id": paper_id };
+            let update = doc! {
+                SET_OP: { "position": position as u32, "updated_at": now },
+            };
+            self.update_one(READING_LIST_COLLECTION_NAME, filter, update)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_reading_list_item(&self, user_id: &str, paper_id: &str) -> ServiceResult<()> {
+        let filter = doc! { "user_id": user_id, "paper_id": paper_id };
+        self.delete_one(READING_LIST_COLLECTION_NAME, filter)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_paper_reading_list_items(&self, paper_id: &str) -> ServiceResult<()> {
+        self.delete_many(READING_LIST_COLLECTION_NAME, doc! { "paper_id": paper_id })
+            .await?;
+        Ok(())
+    }
+}
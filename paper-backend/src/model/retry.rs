@@ -0,0 +1,582 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use bson::Document;
+use futures::stream::BoxStream;
+
+use crate::{
+    config::DbRetryConfig,
+    error::{ServiceError, ServiceResult, is_transient},
+    model::{
+        account::AccountRepository,
+        activity::{Activity, ActivityRepository},
+        audit::{AuditLog, AuditLogRepository},
+        backup::{BackupJob, BackupRepository, BackupStatus},
+        blob::BlobRepository,
+        block::{Block, BlockRepository},
+        chunk::{ChunkClaim, PaperChunk, PaperChunkRepository},
+        citation::{Citation, CitationRepository},
+        comparison::{Comparison, ComparisonRepository},
+        consent::{ConsentRecord, ConsentRepository},
+        content::ContentRepository,
+        conversation::{Conversation, ConversationMessage, ConversationRepository, ScoredMessage},
+        custom_field::{CustomField, CustomFieldRepository},
+        database::Database,
+        embedding::{PaperEmbedding, PaperEmbeddingRepository},
+        export::{ExportJob, ExportRepository, ExportStatus},
+        folder::{Folder, FolderRepository},
+        idempotency::{IdempotencyRecord, IdempotencyRepository},
+        job::{Job, JobRepository, JobStatus},
+        llm_key::{LlmKeyRepository, UserLlmKey},
+        login_attempt::{LoginAttempt, LoginAttemptRepository},
+        migration::{MigrationRecord, MigrationRepository},
+        notification::{Notification, NotificationRepository},
+        organization::{Organization, OrganizationRepository},
+        page::{PaperPage, PaperPageRepository},
+        paper::{
+            Paper, PaperBatchOp, PaperRepository, PaperSuggestions, Progress, ScoredPaper,
+            TextStatus,
+        },
+        prompt::{PromptTemplate, PromptTemplateRepository},
+        quarantine::{QuarantineRepository, QuarantinedFile},
+        reading_list::{ReadingListItem, ReadingListRepository},
+        revision::{PaperRevision, PaperRevisionRepository},
+        share::{Comment, ShareLink, ShareRepository},
+        stats::StatsRepository,
+        txn::TxnContext,
+        upload::{UploadChunk, UploadRepository, UploadSession},
+        usage::{UsageEvent, UsageRepository, UsageTotal},
+        user::{User, UserRepository},
+        webhook::{Webhook, WebhookDelivery, WebhookRepository},
+    },
+};
+
+pub use paper_schema::retry as schema;
+
+/// Counters of the retries of the database calls since the start.
+#[derive(Debug, Default)]
+pub struct RetryMetrics {
+    retries: AtomicU64,
+    recovered: AtomicU64,
+    failed: AtomicU64,
+    by_operation: Mutex<HashMap<&'static str, u64>>,
+}
+
+impl RetryMetrics {
+    fn record_retry(&self, operation: &'static str) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+        let mut by_operation = self.by_operation.lock().unwrap_or_else(|e| e.into_inner());
+        *by_operation.entry(operation).or_default() += 1;
+    }
+
+    fn record_outcome(&self, succeeded: bool) {
+        let counter = match succeeded {
+            true => &self.recovered,
+            false => &self.failed,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> schema::DbRetriesResponse {
+        let by_operation = self.by_operation.lock().unwrap_or_else(|e| e.into_inner());
+        let mut operations = by_operation
+            .iter()
+            .map(|(operation, retries)| schema::OperationRetriesResponse {
+                operation: operation.to_string(),
+                retries: *retries,
+            })
+            .collect::<Vec<_>>();
+        operations.sort_by(|a, b| b.retries.cmp(&a.retries));
+        schema::DbRetriesResponse {
+            retries: self.retries.load(Ordering::Relaxed),
+            recovered: self.recovered.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            operations,
+        }
+    }
+}
+
+/// The repositories of another database, every call retried on a transient
+/// error (primary stepdown, connection pool cleared...) after a jittered
+/// backoff. Only the mongo errors are told transient.
+#[derive(Debug)]
+pub struct RetryingDatabase {
+    inner: Arc<dyn Database>,
+    config: DbRetryConfig,
+    metrics: Arc<RetryMetrics>,
+}
+
+impl RetryingDatabase {
+    pub fn new(
+        inner: Arc<dyn Database>,
+        config: DbRetryConfig,
+        metrics: Arc<RetryMetrics>,
+    ) -> Self {
+        RetryingDatabase {
+            inner,
+            config,
+            metrics,
+        }
+    }
+
+    async fn retry<T, F, Fut>(&self, operation: &'static str, call: F) -> ServiceResult<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = ServiceResult<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let result = call().await;
+            let error = match &result {
+                Err(ServiceError::MongoClientError(e))
+                    if is_transient(e) && attempt < self.config.max_attempts =>
+                {
+                    e
+                }
+                _ => {
+                    if attempt > 1 {
+                        self.metrics.record_outcome(result.is_ok());
+                    }
+                    return result;
+                }
+            };
+            // full jitter, the callers failing together do not retry together
+            let max_delay = self.config.max_delay(attempt).as_millis() as u64;
+            let delay = Duration::from_millis(rand::random_range(0..=max_delay));
+            tracing::warn!(
+                "Database call {} failed on attempt {}, retrying in {}ms: {}",
+                operation,
+                attempt,
+                delay.as_millis(),
+                error
+            );
+            self.metrics.record_retry(operation);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Database for RetryingDatabase {
+    // the health checks report the failures as they are
+    async fn ping(&self) -> ServiceResult<()> {
+        self.inner.ping().await
+    }
+
+    async fn ensure_indexes(&self) -> ServiceResult<()> {
+        self.retry("ensure_indexes", || self.inner.ensure_indexes())
+            .await
+    }
+
+    async fn begin_txn(&self) -> ServiceResult<TxnContext> {
+        self.retry("begin_txn", || self.inner.begin_txn()).await
+    }
+
+    async fn shutdown(&self) {
+        self.inner.shutdown().await;
+    }
+}
+
+/// Implement the repositories for [`RetryingDatabase`] from their methods, the
+/// arguments are cloned for every attempt. The `txn fn` methods take a
+/// [`TxnContext`] first, and are not retried inside of a transaction: a
+/// transaction failing is run again whole, by `in_transaction`.
+macro_rules! retrying {
+    ($($repository:ident {
+        $(fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty;)*
+        $(txn fn $txn_name:ident($($txn_arg:ident: $txn_ty:ty),* $(,)?) -> $txn_ret:ty;)*
+    })*) => {
+        $(
+            #[async_trait::async_trait]
+            #[allow(clippy::clone_on_copy)]
+            impl $repository for RetryingDatabase {
+                $(
+                    async fn $name(&self, $($arg: $ty),*) -> ServiceResult<$ret> {
+                        self.retry(stringify!($name), move || {
+                            $repository::$name(self.inner.as_ref(), $($arg.clone()),*)
+                        })
+                        .await
+                    }
+                )*
+                $(
+                    async fn $txn_name(
+                        &self,
+                        txn: &mut TxnContext,
+                        $($txn_arg: $txn_ty),*
+                    ) -> ServiceResult<$txn_ret> {
+                        if txn.is_active() {
+                            return $repository::$txn_name(
+                                self.inner.as_ref(),
+                                txn,
+                                $($txn_arg),*
+                            )
+                            .await;
+                        }
+                        self.retry(stringify!($txn_name), move || {
+                            $(let $txn_arg = $txn_arg.clone();)*
+                            async move {
+                                let mut txn = TxnContext::none();
+                                $repository::$txn_name(self.inner.as_ref(), &mut txn, $($txn_arg),*)
+                                    .await
+                            }
+                        })
+                        .await
+                    }
+                )*
+            }
+        )*
+    };
+}
+
+retrying! {
+    AccountRepository {
+        fn remaining_account_data(user_id: &str) -> Vec<(String, u64)>;
+        txn fn delete_account(user_id: &str) -> ();
+    }
+
+    ActivityRepository {
+        fn record_activity(activity: Activity) -> ();
+        fn get_recent_activity(user_id: &str, limit: i64) -> Vec<Activity>;
+    }
+
+    AuditLogRepository {
+        txn fn create_audit_log(log: AuditLog) -> ();
+    }
+
+    BackupRepository {
+        fn create_backup_job(job: BackupJob) -> ();
+        fn get_backup_job(id: &str) -> Option<BackupJob>;
+        fn get_backup_jobs(limit: i64) -> Vec<BackupJob>;
+        fn set_backup_progress(id: &str, progress: Progress) -> ();
+        fn finish_backup_job(
+            id: &str,
+            status: BackupStatus,
+            size: Option<u64>,
+            error: Option<String>,
+        ) -> ();
+        fn dump_collections(collections: &[&str]) -> Vec<(String, Vec<Document>)>;
+        fn replace_collections(collections: Vec<(String, Vec<Document>)>) -> ();
+    }
+
+    BlobRepository {
+        fn put_blob(key: &str, bytes: &[u8]) -> ();
+        fn put_blob_file(key: &str, path: &std::path::Path) -> ();
+        fn get_blob(key: &str) -> Option<Vec<u8>>;
+        fn delete_blob(key: &str) -> ();
+        fn list_blobs() -> Vec<String>;
+        fn check_blob_store() -> ();
+    }
+
+    BlockRepository {
+        fn create_block(block: Block) -> ();
+        fn get_block_by_id(id: &str) -> Option<Block>;
+        fn get_blocks_by_user_id(user_id: &str) -> Vec<Block>;
+        fn get_blocks_by_ids(user_id: &str, ids: &[String]) -> Vec<Block>;
+        fn update_block(block: Block) -> Block;
+        fn delete_block(id: &str) -> ();
+    }
+
+    PaperChunkRepository {
+        fn claim_paper_chunks(claim: ChunkClaim) -> bool;
+        fn release_paper_chunks(paper_id: &str) -> ();
+        fn replace_paper_chunks(paper_id: &str, chunks: Vec<PaperChunk>) -> ();
+        fn get_paper_chunks(paper_id: &str) -> Vec<PaperChunk>;
+    }
+
+    CitationRepository {
+        fn replace_citations(citing_id: &str, citations: Vec<Citation>) -> ();
+        fn get_citations_by_citing_id(citing_id: &str) -> Vec<Citation>;
+        fn get_citations_by_cited_id(cited_id: &str) -> Vec<Citation>;
+        fn get_library_citations(user_id: &str) -> Vec<Citation>;
+        fn delete_paper_citations(paper_id: &str) -> ();
+        txn fn reassign_citations(paper_ids: &[String], survivor_id: &str) -> ();
+    }
+
+    ComparisonRepository {
+        fn create_comparison(comparison: Comparison) -> ();
+        fn get_comparison(user_id: &str, id: &str) -> Option<Comparison>;
+        fn get_comparisons(user_id: &str, limit: i64) -> Vec<Comparison>;
+        fn update_comparison(comparison: Comparison) -> ();
+        fn delete_comparison(user_id: &str, id: &str) -> bool;
+    }
+
+    ConsentRepository {
+        fn create_consent_record(record: ConsentRecord) -> ();
+    }
+
+    ContentRepository {
+        fn acquire_content(hash: &str, size: u64) -> bool;
+        fn release_content(hash: &str) -> bool;
+    }
+
+    ConversationRepository {
+        fn create_conversation(conversation: Conversation) -> ();
+        fn get_conversation(user_id: &str, id: &str) -> Option<Conversation>;
+        fn get_conversations(user_id: &str, limit: i64) -> Vec<Conversation>;
+        fn get_conversations_by_ids(user_id: &str, ids: &[String]) -> Vec<Conversation>;
+        fn update_conversation(conversation: Conversation) -> Conversation;
+        fn delete_conversation(user_id: &str, id: &str) -> ();
+        fn create_messages(messages: Vec<ConversationMessage>) -> ();
+        fn get_messages(
+            conversation_id: &str,
+            before: Option<bson::DateTime>,
+            limit: i64,
+        ) -> Vec<ConversationMessage>;
+        fn search_messages(user_id: &str, query: &str, limit: i64) -> Vec<ScoredMessage>;
+    }
+
+    CustomFieldRepository {
+        fn create_custom_field(field: CustomField) -> ();
+        fn get_custom_fields(user_id: &str, org_id: Option<&str>) -> Vec<CustomField>;
+        fn get_custom_field(id: &str) -> Option<CustomField>;
+        fn delete_custom_field(field: &CustomField) -> ();
+    }
+
+    PaperEmbeddingRepository {
+        fn upsert_paper_embedding(embedding: PaperEmbedding) -> ();
+        fn delete_paper_embedding(paper_id: &str) -> ();
+        fn get_paper_embedding(paper_id: &str) -> Option<PaperEmbedding>;
+        fn get_user_embeddings(user_id: &str) -> Vec<PaperEmbedding>;
+    }
+
+    ExportRepository {
+        fn create_export(job: ExportJob) -> ();
+        fn get_export(user_id: &str, id: &str) -> Option<ExportJob>;
+        fn finish_export(
+            id: &str,
+            status: ExportStatus,
+            size: Option<u64>,
+            error: Option<String>,
+        ) -> ();
+        fn get_expired_exports(before: bson::DateTime) -> Vec<ExportJob>;
+        fn delete_export(id: &str) -> ();
+    }
+
+    FolderRepository {
+        fn get_folder_by_id(id: &str) -> Option<Folder>;
+        fn get_folders_by_user_id(user_id: &str) -> Vec<Folder>;
+        fn get_folders_projected(user_id: &str, projection: bson::Document) -> Vec<Folder>;
+        fn update_folder(folder: Folder) -> Folder;
+        fn reorder_folders(user_id: &str, folder_ids: &[String]) -> ();
+        fn delete_folder(id: &str) -> ();
+        txn fn create_folder(folder: Folder) -> ();
+    }
+
+    IdempotencyRepository {
+        fn claim_idempotency_key(record: IdempotencyRecord) -> Option<IdempotencyRecord>;
+        fn complete_idempotency_key(
+            id: &str,
+            status: u16,
+            content_type: Option<String>,
+            body: String,
+        ) -> ();
+        fn release_idempotency_key(id: &str) -> ();
+    }
+
+    JobRepository {
+        fn create_job(job: Job) -> ();
+        fn get_job(user_id: &str, id: &str) -> Option<Job>;
+        fn get_jobs(user_id: &str, limit: i64) -> Vec<Job>;
+        fn set_job_progress(id: &str, progress: Progress) -> ();
+        fn finish_job(
+            id: &str,
+            status: JobStatus,
+            result_id: Option<String>,
+            error: Option<String>,
+        ) -> ();
+    }
+
+    LlmKeyRepository {
+        fn upsert_llm_key(key: UserLlmKey) -> ();
+        fn get_llm_key(user_id: &str, provider: &str) -> Option<UserLlmKey>;
+        fn get_llm_keys(user_id: &str) -> Vec<UserLlmKey>;
+        fn get_all_llm_keys() -> Vec<UserLlmKey>;
+        fn set_llm_key_validated(user_id: &str, provider: &str, at: bson::DateTime) -> ();
+        fn delete_llm_key(user_id: &str, provider: &str) -> bool;
+    }
+
+    LoginAttemptRepository {
+        fn get_login_attempt(key: &str) -> Option<LoginAttempt>;
+        fn reserve_login_attempt(key: &str) -> Option<LoginAttempt>;
+        fn release_login_attempt(key: &str) -> ();
+        fn record_login_failure(key: &str) -> Option<LoginAttempt>;
+        fn lock_login(key: &str, until: bson::DateTime) -> ();
+        fn clear_login_attempt(key: &str) -> bool;
+        fn get_locked_logins(now: bson::DateTime, limit: i64) -> Vec<LoginAttempt>;
+    }
+
+    MigrationRepository {
+        fn get_applied_migrations() -> Vec<MigrationRecord>;
+        fn record_migration(record: MigrationRecord) -> ();
+        fn acquire_migration_lock(owner: &str, stale_before: bson::DateTime) -> bool;
+        fn release_migration_lock(owner: &str) -> ();
+        fn backfill_versions(dry_run: bool) -> u64;
+        fn backfill_folder_sort_order(dry_run: bool) -> u64;
+        fn rename_duplicate_folders(dry_run: bool) -> u64;
+        fn drop_plain_email_index(dry_run: bool) -> u64;
+    }
+
+    NotificationRepository {
+        fn create_notification(notification: Notification) -> ();
+        fn get_notifications(user_id: &str, unread_only: bool, limit: i64) -> Vec<Notification>;
+        fn count_unread_notifications(user_id: &str) -> u64;
+        fn mark_notification_read(user_id: &str, id: &str) -> bool;
+        fn mark_all_notifications_read(user_id: &str) -> ();
+    }
+
+    OrganizationRepository {
+        fn create_organization(org: Organization) -> ();
+        fn get_organization_by_id(id: &str) -> Option<Organization>;
+        fn update_organization(org: Organization) -> Organization;
+    }
+
+    PaperPageRepository {
+        fn replace_paper_pages(paper_id: &str, pages: Vec<PaperPage>) -> ();
+        fn get_paper_pages(paper_id: &str, page: Option<u32>) -> Vec<PaperPage>;
+    }
+
+    PaperRepository {
+        fn create_paper(paper: Paper) -> ();
+        fn get_paper_by_id(id: &str) -> Option<Paper>;
+        fn get_paper_projected(id: &str, projection: bson::Document) -> Option<Paper>;
+        fn get_papers_by_folder_id(folder_id: &str) -> Vec<Paper>;
+        fn get_papers_by_ids(user_id: &str, ids: &[String]) -> Vec<Paper>;
+        fn get_papers_by_user_id(user_id: &str) -> Vec<Paper>;
+        fn search_papers(
+            user_id: &str,
+            query: &str,
+            filter: Option<bson::Document>,
+            limit: i64,
+        ) -> Vec<ScoredPaper>;
+        fn find_papers(
+            user_id: &str,
+            folder_id: Option<&str>,
+            filter: Option<bson::Document>,
+            projection: Option<bson::Document>,
+        ) -> BoxStream<'static, ServiceResult<Paper>>;
+        fn find_papers_page(filter: bson::Document, offset: u64, limit: i64) -> (Vec<Paper>, u64);
+        fn count_papers_by_folder(user_id: &str) -> HashMap<String, u64>;
+        fn update_paper(paper: Paper) -> Paper;
+        fn set_paper_text_status(id: &str, status: TextStatus, page_count: Option<u32>) -> ();
+        fn set_paper_ocr_progress(id: &str, progress: Progress) -> ();
+        fn set_paper_file_refused(id: &str, file_hash: &str, reason: &str) -> bool;
+        fn set_paper_starred(id: &str, starred: bool) -> ();
+        fn set_paper_suggestions(id: &str, suggestions: &PaperSuggestions) -> ();
+        fn get_user_tags(user_id: &str) -> Vec<String>;
+        fn delete_paper(id: &str) -> ();
+        txn fn run_paper_batch(ids: &[String], op: PaperBatchOp) -> ();
+    }
+
+    PromptTemplateRepository {
+        fn create_prompt_template(template: PromptTemplate) -> ();
+        fn get_prompt_template(id: &str) -> Option<PromptTemplate>;
+        fn get_prompt_templates(name: Option<&str>) -> Vec<PromptTemplate>;
+        fn find_prompt_template(name: &str, version: Option<u32>) -> Option<PromptTemplate>;
+        fn update_prompt_template(template: PromptTemplate) -> ();
+        fn delete_prompt_template(id: &str) -> bool;
+    }
+
+    QuarantineRepository {
+        fn create_quarantined_file(file: QuarantinedFile) -> ();
+        fn get_quarantined_file(id: &str) -> Option<QuarantinedFile>;
+        fn get_quarantined_files(limit: i64) -> Vec<QuarantinedFile>;
+        fn delete_quarantined_file(id: &str) -> ();
+    }
+
+    ReadingListRepository {
+        fn create_reading_list_item(item: ReadingListItem) -> ();
+        fn get_reading_list_item(user_id: &str, paper_id: &str) -> Option<ReadingListItem>;
+        fn get_reading_list(user_id: &str) -> Vec<ReadingListItem>;
+        fn update_reading_list_item(item: ReadingListItem) -> ();
+        fn reorder_reading_list(user_id: &str, paper_ids: &[String]) -> ();
+        fn delete_reading_list_item(user_id: &str, paper_id: &str) -> ();
+        fn delete_paper_reading_list_items(paper_id: &str) -> ();
+    }
+
+    PaperRevisionRepository {
+        fn save_paper_revision(revision: PaperRevision) -> ();
+        fn get_paper_revisions(paper_id: &str) -> Vec<PaperRevision>;
+        fn get_paper_revision(paper_id: &str, revision: u32) -> Option<PaperRevision>;
+        fn delete_paper_revisions_before(paper_id: &str, revision: u32) -> ();
+        fn delete_paper_revisions(paper_id: &str) -> ();
+        fn delete_expired_paper_revisions(before: bson::DateTime) -> u64;
+    }
+
+    ShareRepository {
+        fn create_share_link(link: ShareLink) -> ();
+        fn get_share_link_by_id(id: &str) -> Option<ShareLink>;
+        fn get_share_link_by_token(token: &str) -> Option<ShareLink>;
+        fn get_share_links_by_paper_id(paper_id: &str) -> Vec<ShareLink>;
+        fn count_share_links_by_paper_id(paper_id: &str) -> u64;
+        fn revoke_share_link(id: &str) -> ();
+        fn create_comment(comment: Comment) -> ();
+        fn get_comments_by_paper_id(paper_id: &str) -> Vec<Comment>;
+        fn delete_comment(paper_id: &str, id: &str) -> ();
+        txn fn reassign_paper(from_ids: &[String], to_id: &str) -> ();
+    }
+
+    StatsRepository {
+        fn count_papers(user_id: &str) -> u64;
+        fn count_folders(user_id: &str) -> u64;
+        fn count_folder_papers(folder_id: &str) -> u64;
+        fn count_notes(user_id: &str) -> u64;
+        fn sum_storage_bytes(user_id: &str) -> u64;
+        fn papers_added_per_week(
+            user_id: &str,
+            since: bson::DateTime,
+        ) -> Vec<(bson::DateTime, u64)>;
+    }
+
+    UploadRepository {
+        fn create_upload(upload: UploadSession) -> ();
+        fn get_upload(user_id: &str, id: &str) -> Option<UploadSession>;
+        fn put_upload_chunk(chunk: UploadChunk) -> ();
+        fn advance_upload(id: &str, offset: u64, new_offset: u64) -> bool;
+        fn get_upload_chunks(upload_id: &str) -> Vec<UploadChunk>;
+        fn delete_upload(id: &str) -> ();
+    }
+
+    UsageRepository {
+        fn record_usage(event: UsageEvent) -> ();
+        fn sum_tokens_since(user_id: &str, since: bson::DateTime) -> u64;
+        fn count_usage_since(user_id: &str, feature: &str, since: bson::DateTime) -> u64;
+        fn sum_usage_since(
+            user_id: Option<&str>,
+            group_by: Option<&str>,
+            since: bson::DateTime,
+            limit: i64,
+        ) -> Vec<UsageTotal>;
+    }
+
+    UserRepository {
+        fn create_user(user: User) -> ();
+        fn update_user(user: User) -> ();
+        fn delete_user(id: String) -> ();
+        fn get_user_by_phone(phone: &str) -> Option<User>;
+        fn get_user_by_uid(uid: &str) -> Option<User>;
+        fn get_user_by_email(email: &str) -> Option<User>;
+        fn check_non_duplicate(phone: Option<String>) -> ();
+        fn check_non_duplicate_email(email: &str) -> ();
+        fn get_users_due_for_digest(before: bson::DateTime) -> Vec<User>;
+        fn claim_digest(uid: &str, before: bson::DateTime) -> bool;
+    }
+
+    WebhookRepository {
+        fn create_webhook(webhook: Webhook) -> ();
+        fn get_webhook(user_id: &str, id: &str) -> Option<Webhook>;
+        fn get_webhooks(user_id: &str) -> Vec<Webhook>;
+        fn get_all_webhooks() -> Vec<Webhook>;
+        fn set_webhook_secret(id: &str, secret: &str) -> ();
+        fn delete_webhook(user_id: &str, id: &str) -> ();
+        fn create_webhook_delivery(delivery: WebhookDelivery) -> ();
+        fn get_webhook_deliveries(webhook_id: &str, limit: i64) -> Vec<WebhookDelivery>;
+    }
+}
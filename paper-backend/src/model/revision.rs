@@ -0,0 +1,293 @@
+use std::collections::BTreeMap;
+
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        custom_field::FieldValue,
+        document::{DocumentDatabase, Query},
+        paper::Paper,
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::revision::*;
+
+    use crate::model::revision::PaperRevision;
+
+    impl From<PaperRevision> for PaperRevisionResponse {
+        fn from(revision: PaperRevision) -> Self {
+            PaperRevisionResponse {
+                paper_id: revision.paper_id,
+                revision: revision.revision,
+                edited_at: revision.edited_at.timestamp_millis(),
+                replaced_at: revision.created_at.timestamp_millis(),
+                title: revision.title,
+                authors: revision.authors,
+                r#abstract: revision.r#abstract,
+                doi: revision.doi,
+                content: revision.content,
+                tags: revision.tags,
+                custom_fields: revision.custom_fields,
+            }
+        }
+    }
+
+    impl From<PaperRevision> for PaperRevisionItem {
+        fn from(revision: PaperRevision) -> Self {
+            PaperRevisionItem {
+                revision: revision.revision,
+                edited_at: revision.edited_at.timestamp_millis(),
+                replaced_at: revision.created_at.timestamp_millis(),
+                title: revision.title,
+            }
+        }
+    }
+}
+
+/// The metadata and notes of a paper as they were at one version, kept when
+/// an update replaced them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperRevision {
+    #[serde(rename = "_id")]
+    pub id: String, // `{paper_id}:{revision}`
+    pub paper_id: String,
+    pub user_id: String,
+    // the version of the paper
+    pub revision: u32,
+    // when the paper was edited to this revision
+    pub edited_at: bson::DateTime,
+    // when it was replaced, the revisions expire from then on
+    pub created_at: bson::DateTime,
+
+    pub title: String,
+    pub authors: Vec<String>,
+    pub r#abstract: Option<String>,
+    pub doi: Option<String>,
+    pub content: Option<String>,
+    pub tags: Vec<String>,
+    pub custom_fields: BTreeMap<String, FieldValue>,
+}
+
+impl PaperRevision {
+    pub fn of(paper: &Paper) -> Self {
+        PaperRevision {
+            id: format!("{}:{}", paper.id, paper.version),
+            paper_id: paper.id.clone(),
+            user_id: paper.user_id.clone(),
+            revision: paper.version,
+            edited_at: paper.updated_at,
+            created_at: bson::DateTime::now(),
+
+            title: paper.title.clone(),
+            authors: paper.authors.clone(),
+            r#abstract: paper.r#abstract.clone(),
+            doi: paper.doi.clone(),
+            content: paper.content.clone(),
+            tags: paper.tags.clone(),
+            custom_fields: paper.custom_fields.clone(),
+        }
+    }
+
+    /// Whether the paper has the metadata and notes of the revision.
+    pub fn matches(&self, paper: &Paper) -> bool {
+        self.fields() == PaperRevision::of(paper).fields()
+    }
+
+    /// Give the paper back the metadata and notes of the revision.
+    pub fn restore_into(&self, paper: &mut Paper) {
+        paper.title = self.title.clone();
+        paper.authors = self.authors.clone();
+        paper.r#abstract = self.r#abstract.clone();
+        paper.doi = self.doi.clone();
+        paper.content = self.content.clone();
+        paper.tags = self.tags.clone();
+        paper.custom_fields = self.custom_fields.clone();
+    }
+
+    /// The kept fields as text, one item per line, for the diff views.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let custom_fields = self
+            .custom_fields
+            .iter()
+            .map(|(key, value)| {
+                let value = serde_json::to_string(value).unwrap_or_default();
+                format!("{}: {}", key, value)
+            })
+            .collect::<Vec<_>>();
+        vec![
+            ("title", self.title.clone()),
+            ("authors", self.authors.join("\n")),
+            ("abstract", self.r#abstract.clone().unwrap_or_default()),
+            ("doi", self.doi.clone().unwrap_or_default()),
+            ("content", self.content.clone().unwrap_or_default()),
+            ("tags", self.tags.join("\n")),
+            ("customFields", custom_fields.join("\n")),
+        ]
+    }
+}
+
+#[async_trait::async_trait]
+pub trait PaperRevisionRepository: Send + Sync {
+    /// Store the revision, replacing one stored for the same version.
+    async fn save_paper_revision(&self, revision: PaperRevision) -> ServiceResult<()>;
+    /// The revisions of the paper, the latest first.
+    async fn get_paper_revisions(&self, paper_id: &str) -> ServiceResult<Vec<PaperRevision>>;
+    async fn get_paper_revision(
+        &self,
+        paper_id: &str,
+        revision: u32,
+    ) -> ServiceResult<Option<PaperRevision>>;
+    /// Delete the revisions of the paper older than the given one.
+    async fn delete_paper_revisions_before(
+        &self,
+        paper_id: &str,
+        revision: u32,
+    ) -> ServiceResult<()>;
+    async fn delete_paper_revisions(&self, paper_id: &str) -> ServiceResult<()>;
+    /// Delete the revisions of every paper replaced before the given time.
+    async fn delete_expired_paper_revisions(&self, before: bson::DateTime) -> ServiceResult<u64>;
+}
+
+#[async_trait::async_trait]
+impl PaperRevisionRepository for MongoClient {
+    async fn save_paper_revision(&self, revision: PaperRevision) -> ServiceResult<()> {
+        let filter = doc! { "_id": &revision.id };
+        self.collection::<PaperRevision>(PAPER_REVISION_COLLECTION_NAME)
+            .replace_one(filter, revision)
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_paper_revisions(&self, paper_id: &str) -> ServiceResult<Vec<PaperRevision>> {
+        let cursor = self
+            .collection::<PaperRevision>(PAPER_REVISION_COLLECTION_NAME)
+            .find(doc! { "paper_id": paper_id })
+            .sort(doc! { "revision": -1 })
+            .await?;
+        let revisions = cursor.try_collect().await?;
+        Ok(revisions)
+    }
+
+    async fn get_paper_revision(
+        &self,
+        paper_id: &str,
+        revision: u32,
+    ) -> ServiceResult<Option<PaperRevision>> {
+        let revision = self
+            .collection::<PaperRevision>(PAPER_REVISION_COLLECTION_NAME)
+            .find_one(doc! { "paper_id": paper_id, "revision": revision })
+            .await?;
+        Ok(revision)
+    }
+
+    async fn delete_paper_revisions_before(
+        &self,
+        paper_id: &str,
+        revision: u32,
+    ) -> ServiceResult<()> {
+        let filter = doc! { "paper_id": paper_id, "revision": { LT_OP: revision } };
+        self.collection::<PaperRevision>(PAPER_REVISION_COLLECTION_NAME)
+            .delete_many(filter)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_paper_revisions(&self, paper_id: &str) -> ServiceResult<()> {
+        self.collection::<PaperRevision>(PAPER_REVISION_COLLECTION_NAME)
+            .delete_many(doc! { "paper_id": paper_id })
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_expired_paper_revisions(&self, before: bson::DateTime) -> ServiceResult<u64> {
+        let result = self
+            .collection::<PaperRevision>(PAPER_REVISION_COLLECTION_NAME)
+            .delete_many(doc! { "created_at": { LT_OP: before } })
+            .await?;
+        Ok(result.deleted_count)
+    }
+}
+
+#[async_trait::async_trait]
+impl PaperRevisionRepository for DocumentDatabase {
+    async fn save_paper_revision(&self, revision: PaperRevision) -> ServiceResult<()> {
+        let filter = doc! { "_id": &revision.id };
+        self.replace_one(PAPER_REVISION_COLLECTION_NAME, filter, &revision, true)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_paper_revisions(&self, paper_id: &str) -> ServiceResult<Vec<PaperRevision>> {
+        let query = Query::new(doc! { "paper_id": paper_id }).sort(doc! { "revision": -1 });
+        self.find(PAPER_REVISION_COLLECTION_NAME, query).await
+    }
+
+    async fn get_paper_revision(
+        &self,
+        paper_id: &str,
+        revision: u32,
+    ) -> ServiceResult<Option<PaperRevision>> {
+        self.find_one(
+            PAPER_REVISION_COLLECTION_NAME,
+            doc! { "paper_id": paper_id, "revision": revision },
+        )
+        .await
+    }
+
+    async fn delete_paper_revisions_before(
+        &self,
+        paper_id: &str,
+        revision: u32,
+    ) -> ServiceResult<()> {
+        let filter = doc! { "paper_id": paper_id, "revision": { LT_OP: revision } };
+        self.delete_many(PAPER_REVISION_COLLECTION_NAME, filter)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_paper_revisions(&self, paper_id: &str) -> ServiceResult<()> {
+        self.delete_many(
+            PAPER_REVISION_COLLECTION_NAME,
+            doc! { "paper_id": paper_id },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_expired_paper_revisions(&self, before: bson::DateTime) -> ServiceResult<u64> {
+        self.delete_many(
+            PAPER_REVISION_COLLECTION_NAME,
+            doc! { "created_at": { LT_OP: before } },
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restore_revision() {
+        let mut paper = Paper::new("user", "folder", "Draft".to_string());
+        paper.content = Some("first notes".to_string());
+        let revision = PaperRevision::of(&paper);
+        assert_eq!(revision.id, format!("{}:{}", paper.id, paper.version));
+        assert!(revision.matches(&paper));
+
+        paper.title = "Final".to_string();
+        paper.content = None;
+        assert!(!revision.matches(&paper));
+        revision.restore_into(&mut paper);
+        assert_eq!(paper.title, "Draft");
+        assert_eq!(paper.content.as_deref(), Some("first notes"));
+        assert!(revision.matches(&paper));
+    }
+}
@@ -0,0 +1,357 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+pub use paper_schema::share::SharePermission;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+        paper::Paper,
+        txn::{TxnContext, in_session},
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::share::*;
+
+    use crate::{
+        model::share::{Comment, ShareLink},
+        utils::validate::{ValidatedRequest, trim, trim_option},
+    };
+
+    impl From<ShareLink> for ShareLinkResponse {
+        fn from(link: ShareLink) -> Self {
+            ShareLinkResponse {
+                id: link.id,
+                paper_id: link.paper_id,
+                token: link.token,
+                handle: link.handle,
+                permission: link.permission,
+                watermark: link.watermark,
+                revoked: link.revoked,
+                expires_at: link.expires_at.map(|t| t.timestamp_millis()),
+            }
+        }
+    }
+
+    impl ValidatedRequest for CreateShareLinkRequest {
+        fn normalize(&mut self) {
+            trim_option(&mut self.watermark);
+        }
+    }
+
+    impl From<Comment> for CommentResponse {
+        fn from(comment: Comment) -> Self {
+            CommentResponse {
+                id: comment.id,
+                paper_id: comment.paper_id,
+                author_handle: comment.author_handle,
+                content: comment.content,
+                anchor: comment.anchor,
+                created_at: comment.created_at.timestamp_millis(),
+            }
+        }
+    }
+
+    impl ValidatedRequest for CreateCommentRequest {
+        fn normalize(&mut self) {
+            trim(&mut self.content);
+            trim_option(&mut self.anchor);
+        }
+    }
+}
+
+/// A secret link granting an anonymous reviewer access to a single paper.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareLink {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub token: String,
+    pub paper_id: String,
+    pub owner_id: String, // uuid of the user who owns the paper
+    pub created_at: bson::DateTime,
+    pub expires_at: Option<bson::DateTime>,
+
+    pub handle: String,
+    pub permission: SharePermission,
+    pub watermark: Option<String>,
+    pub revoked: bool,
+}
+
+impl ShareLink {
+    pub fn new(
+        paper_id: &str,
+        owner_id: &str,
+        handle: String,
+        request: schema::CreateShareLinkRequest,
+    ) -> Self {
+        let now = bson::DateTime::now();
+        ShareLink {
+            id: uuid::Uuid::new_v4().to_string(),
+            token: format!(
+                "{}{}",
+                uuid::Uuid::new_v4().simple(),
+                uuid::Uuid::new_v4().simple()
+            ),
+            paper_id: paper_id.to_string(),
+            owner_id: owner_id.to_string(),
+            created_at: now,
+            expires_at: request.expires_in_days.map(|days| {
+                bson::DateTime::from_millis(now.timestamp_millis() + days * 24 * 3600 * 1000)
+            }),
+
+            handle,
+            permission: request.permission.unwrap_or(SharePermission::Comment),
+            watermark: request.watermark,
+            revoked: false,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.revoked
+            && self
+                .expires_at
+                .is_none_or(|t| t.timestamp_millis() > bson::DateTime::now().timestamp_millis())
+    }
+    /// What the reviewers of the link see: the paper and the comments on it.
+    pub fn review(&self, paper: Paper, comments: Vec<Comment>) -> schema::ReviewResponse {
+        schema::ReviewResponse {
+            handle: self.handle.clone(),
+            title: paper.title,
+            authors: paper.authors,
+            r#abstract: paper.r#abstract,
+            content: paper.content,
+            comments: comments.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Pseudonym of the n-th (0 based) link of a paper: Reviewer A, B, ... Z, AA, AB...
+pub fn reviewer_handle(n: u64) -> String {
+    let mut n = n;
+    let mut letters = Vec::new();
+    loop {
+        letters.push((b'A' + (n % 26) as u8) as char);
+        if n < 26 {
+            break;
+        }
+        n = n / 26 - 1;
+    }
+    format!("Reviewer {}", letters.iter().rev().collect::<String>())
+}
+
+/// A comment on a paper, made by the owner or through a share link.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub paper_id: String,
+    // the share link used, reviewers are never linked to an account
+    pub share_link_id: Option<String>,
+    pub created_at: bson::DateTime,
+
+    pub author_handle: String,
+    pub content: String,
+    pub anchor: Option<String>,
+}
+
+impl Comment {
+    pub fn new_from_link(link: &ShareLink, request: schema::CreateCommentRequest) -> Self {
+        Comment {
+            id: uuid::Uuid::new_v4().to_string(),
+            paper_id: link.paper_id.clone(),
+            share_link_id: Some(link.id.clone()),
+            created_at: bson::DateTime::now(),
+
+            author_handle: link.handle.clone(),
+            content: request.content,
+            anchor: request.anchor,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait ShareRepository: Send + Sync {
+    async fn create_share_link(&self, link: ShareLink) -> ServiceResult<()>;
+    async fn get_share_link_by_id(&self, id: &str) -> ServiceResult<Option<ShareLink>>;
+    async fn get_share_link_by_token(&self, token: &str) -> ServiceResult<Option<ShareLink>>;
+    async fn get_share_links_by_paper_id(&self, paper_id: &str) -> ServiceResult<Vec<ShareLink>>;
+    async fn count_share_links_by_paper_id(&self, paper_id: &str) -> ServiceResult<u64>;
+    async fn revoke_share_link(&self, id: &str) -> ServiceResult<()>;
+
+    async fn create_comment(&self, comment: Comment) -> ServiceResult<()>;
+    async fn get_comments_by_paper_id(&self, paper_id: &str) -> ServiceResult<Vec<Comment>>;
+    async fn delete_comment(&self, paper_id: &str, id: &str) -> ServiceResult<()>;
+    /// Move the links and comments of the papers over to another paper, e.g. on merge.
+    async fn reassign_paper(
+        &self,
+        txn: &mut TxnContext,
+        from_ids: &[String],
+        to_id: &str,
+    ) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl ShareRepository for MongoClient {
+    async fn create_share_link(&self, link: ShareLink) -> ServiceResult<()> {
+        self.collection::<ShareLink>(SHARE_LINK_COLLECTION_NAME)
+            .insert_one(link)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_share_link_by_id(&self, id: &str) -> ServiceResult<Option<ShareLink>> {
+        let filter = doc! { "_id": id };
+        let result = self
+            .collection::<ShareLink>(SHARE_LINK_COLLECTION_NAME)
+            .find_one(filter)
+            .await?;
+        Ok(result)
+    }
+
+    async fn get_share_link_by_token(&self, token: &str) -> ServiceResult<Option<ShareLink>> {
+        let filter = doc! { "token": token };
+        let result = self
+            .collection::<ShareLink>(SHARE_LINK_COLLECTION_NAME)
+            .find_one(filter)
+            .await?;
+        Ok(result)
+    }
+
+    async fn get_share_links_by_paper_id(&self, paper_id: &str) -> ServiceResult<Vec<ShareLink>> {
+        let filter = doc! { "paper_id": paper_id };
+        let cursor = self
+            .collection::<ShareLink>(SHARE_LINK_COLLECTION_NAME)
+            .find(filter)
+            .await?;
+        let links = cursor.try_collect().await?;
+        Ok(links)
+    }
+
+    async fn count_share_links_by_paper_id(&self, paper_id: &str) -> ServiceResult<u64> {
+        let filter = doc! { "paper_id": paper_id };
+        let count = self
+            .collection::<ShareLink>(SHARE_LINK_COLLECTION_NAME)
+            .count_documents(filter)
+            .await?;
+        Ok(count)
+    }
+
+    async fn revoke_share_link(&self, id: &str) -> ServiceResult<()> {
+        let filter = doc! { "_id": id };
+        let update = doc! { SET_OP: { "revoked": true } };
+        self.collection::<ShareLink>(SHARE_LINK_COLLECTION_NAME)
+            .update_one(filter, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn create_comment(&self, comment: Comment) -> ServiceResult<()> {
+        self.collection::<Comment>(COMMENT_COLLECTION_NAME)
+            .insert_one(comment)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_comments_by_paper_id(&self, paper_id: &str) -> ServiceResult<Vec<Comment>> {
+        let filter = doc! { "paper_id": paper_id };
+        let cursor = self
+            .collection::<Comment>(COMMENT_COLLECTION_NAME)
+            .find(filter)
+            .sort(doc! { "created_at": 1 })
+            .await?;
+        let comments = cursor.try_collect().await?;
+        Ok(comments)
+    }
+
+    async fn delete_comment(&self, paper_id: &str, id: &str) -> ServiceResult<()> {
+        let filter = doc! { "_id": id, "paper_id": paper_id };
+        self.collection::<Comment>(COMMENT_COLLECTION_NAME)
+            .delete_one(filter)
+            .await?;
+        Ok(())
+    }
+
+    async fn reassign_paper(
+        &self,
+        txn: &mut TxnContext,
+        from_ids: &[String],
+        to_id: &str,
+    ) -> ServiceResult<()> {
+        let filter = doc! { "paper_id": { IN_OP: from_ids } };
+        let update = doc! { SET_OP: { "paper_id": to_id } };
+        let links = self.collection::<ShareLink>(SHARE_LINK_COLLECTION_NAME);
+        in_session!(links.update_many(filter.clone(), update.clone()), txn)?;
+        let comments = self.collection::<Comment>(COMMENT_COLLECTION_NAME);
+        in_session!(comments.update_many(filter, update), txn)?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ShareRepository for DocumentDatabase {
+    async fn create_share_link(&self, link: ShareLink) -> ServiceResult<()> {
+        self.insert(SHARE_LINK_COLLECTION_NAME, &link).await
+    }
+
+    async fn get_share_link_by_id(&self, id: &str) -> ServiceResult<Option<ShareLink>> {
+        self.find_one(SHARE_LINK_COLLECTION_NAME, doc! { "_id": id })
+            .await
+    }
+
+    async fn get_share_link_by_token(&self, token: &str) -> ServiceResult<Option<ShareLink>> {
+        self.find_one(SHARE_LINK_COLLECTION_NAME, doc! { "token": token })
+            .await
+    }
+
+    async fn get_share_links_by_paper_id(&self, paper_id: &str) -> ServiceResult<Vec<ShareLink>> {
+        let query = Query::new(doc! { "paper_id": paper_id });
+        self.find(SHARE_LINK_COLLECTION_NAME, query).await
+    }
+
+    async fn count_share_links_by_paper_id(&self, paper_id: &str) -> ServiceResult<u64> {
+        self.count(SHARE_LINK_COLLECTION_NAME, doc! { "paper_id": paper_id })
+            .await
+    }
+
+    async fn revoke_share_link(&self, id: &str) -> ServiceResult<()> {
+        let update = doc! { SET_OP: { "revoked": true } };
+        self.update_one(SHARE_LINK_COLLECTION_NAME, doc! { "_id": id }, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn create_comment(&self, comment: Comment) -> ServiceResult<()> {
+        self.insert(COMMENT_COLLECTION_NAME, &comment).await
+    }
+
+    async fn get_comments_by_paper_id(&self, paper_id: &str) -> ServiceResult<Vec<Comment>> {
+        let query = Query::new(doc! { "paper_id": paper_id }).sort(doc! { "created_at": 1 });
+        self.find(COMMENT_COLLECTION_NAME, query).await
+    }
+
+    async fn delete_comment(&self, paper_id: &str, id: &str) -> ServiceResult<()> {
+        let filter = doc! { "_id": id, "paper_id": paper_id };
+        self.delete_one(COMMENT_COLLECTION_NAME, filter).await?;
+        Ok(())
+    }
+
+    async fn reassign_paper(
+        &self,
+        txn: &mut TxnContext,
+        from_ids: &[String],
+        to_id: &str,
+    ) -> ServiceResult<()> {
+        let db = txn.documents(self);
+        let filter = doc! { "paper_id": { IN_OP: from_ids } };
+        let update = doc! { SET_OP: { "paper_id": to_id } };
+        db.update_many(SHARE_LINK_COLLECTION_NAME, filter.clone(), update.clone())
+            .await?;
+        db.update_many(COMMENT_COLLECTION_NAME, filter, update)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use chrono::{Datelike, TimeDelta};
+use futures::TryStreamExt;
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        count_field,
+        document::{DocumentDatabase, Query},
+        folder::Folder,
+        paper::Paper,
+    },
+};
+
+pub use paper_schema::stats as schema;
+
+#[async_trait::async_trait]
+pub trait StatsRepository: Send + Sync {
+    async fn count_papers(&self, user_id: &str) -> ServiceResult<u64>;
+    async fn count_folders(&self, user_id: &str) -> ServiceResult<u64>;
+    /// Papers filed in the folder.
+    async fn count_folder_papers(&self, folder_id: &str) -> ServiceResult<u64>;
+    /// Papers of the user with non empty notes.
+    async fn count_notes(&self, user_id: &str) -> ServiceResult<u64>;
+    /// Total size of the files attached to the papers of the user.
+    async fn sum_storage_bytes(&self, user_id: &str) -> ServiceResult<u64>;
+    /// Papers added per week since the time, as (week start, count), oldest first.
+    async fn papers_added_per_week(
+        &self,
+        user_id: &str,
+        since: bson::DateTime,
+    ) -> ServiceResult<Vec<(bson::DateTime, u64)>>;
+}
+
+#[async_trait::async_trait]
+impl StatsRepository for MongoClient {
+    async fn count_papers(&self, user_id: &str) -> ServiceResult<u64> {
+        let count = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .count_documents(doc! { "user_id": user_id })
+            .await?;
+        Ok(count)
+    }
+
+    async fn count_folders(&self, user_id: &str) -> ServiceResult<u64> {
+        let count = self
+            .collection::<Folder>(FOLDER_COLLECTION_NAME)
+            .count_documents(doc! { "user_id": user_id })
+            .await?;
+        Ok(count)
+    }
+
+    async fn count_folder_papers(&self, folder_id: &str) -> ServiceResult<u64> {
+        let count = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .count_documents(doc! { "folder_id": folder_id })
+            .await?;
+        Ok(count)
+    }
+
+    async fn count_notes(&self, user_id: &str) -> ServiceResult<u64> {
+        let filter = doc! { "user_id": user_id, "content": { NIN_OP: [null, ""] } };
+        let count = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .count_documents(filter)
+            .await?;
+        Ok(count)
+    }
+
+    async fn sum_storage_bytes(&self, user_id: &str) -> ServiceResult<u64> {
+        let pipeline = vec![
+            doc! { MATCH_STAGE: { "user_id": user_id } },
+            doc! { GROUP_STAGE: { "_id": null, "bytes": { SUM_OP: "$file_size" } } },
+        ];
+        let mut cursor = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .aggregate(pipeline)
+            .await?;
+        let bytes = match cursor.try_next().await? {
+            Some(group) => count_field(&group, "bytes"),
+            None => 0,
+        };
+        Ok(bytes)
+    }
+
+    async fn papers_added_per_week(
+        &self,
+        user_id: &str,
+        since: bson::DateTime,
+    ) -> ServiceResult<Vec<(bson::DateTime, u64)>> {
+        let pipeline = vec![
+            doc! { MATCH_STAGE: { "user_id": user_id, "created_at": { GTE_OP: since } } },
+            doc! {
+                GROUP_STAGE: {
+                    "_id": { "$dateTrunc": { "date": "$created_at", "unit": "week" } },
+                    "count": { SUM_OP: 1 },
+                },
+            },
+            doc! { SORT_STAGE: { "_id": 1 } },
+        ];
+        let cursor = self
+            .collection::<Paper>(PAPER_COLLECTION_NAME)
+            .aggregate(pipeline)
+            .await?;
+        let groups: Vec<bson::Document> = cursor.try_collect().await?;
+        Ok(groups
+            .into_iter()
+            .filter_map(|group| {
+                let week = group.get_datetime("_id").ok().copied()?;
+                Some((week, count_field(&group, "count")))
+            })
+            .collect())
+    }
+}
+
+// the start of the week of the time, on sunday in utc as `$dateTrunc` does
+fn week_start(at: bson::DateTime) -> bson::DateTime {
+    let at = at.to_chrono();
+    let days = i64::from(at.weekday().num_days_from_sunday());
+    let start = at.date_naive() - TimeDelta::days(days);
+    bson::DateTime::from_chrono(start.and_time(chrono::NaiveTime::MIN).and_utc())
+}
+
+#[async_trait::async_trait]
+impl StatsRepository for DocumentDatabase {
+    async fn count_papers(&self, user_id: &str) -> ServiceResult<u64> {
+        self.count(PAPER_COLLECTION_NAME, doc! { "user_id": user_id })
+            .await
+    }
+
+    async fn count_folders(&self, user_id: &str) -> ServiceResult<u64> {
+        self.count(FOLDER_COLLECTION_NAME, doc! { "user_id": user_id })
+            .await
+    }
+
+    async fn count_folder_papers(&self, folder_id: &str) -> ServiceResult<u64> {
+        self.count(PAPER_COLLECTION_NAME, doc! { "folder_id": folder_id })
+            .await
+    }
+
+    async fn count_notes(&self, user_id: &str) -> ServiceResult<u64> {
+        let filter = doc! { "user_id": user_id, "content": { NIN_OP: [null, ""] } };
+        self.count(PAPER_COLLECTION_NAME, filter).await
+    }
+
+    async fn sum_storage_bytes(&self, user_id: &str) -> ServiceResult<u64> {
+        let papers = self
+            .find_documents(
+                PAPER_COLLECTION_NAME,
+                Query::new(doc! { "user_id": user_id }),
+            )
+            .await?;
+        Ok(papers
+            .iter()
+            .map(|paper| count_field(paper, "file_size"))
+            .sum())
+    }
+
+    async fn papers_added_per_week(
+        &self,
+        user_id: &str,
+        since: bson::DateTime,
+    ) -> ServiceResult<Vec<(bson::DateTime, u64)>> {
+        let filter = doc! { "user_id": user_id, "created_at": { GTE_OP: since } };
+        let papers: Vec<Paper> = self.find(PAPER_COLLECTION_NAME, Query::new(filter)).await?;
+        let mut weeks = BTreeMap::new();
+        for paper in papers {
+            *weeks.entry(week_start(paper.created_at)).or_default() += 1;
+        }
+        Ok(weeks.into_iter().collect())
+    }
+}
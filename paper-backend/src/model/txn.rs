@@ -0,0 +1,151 @@
+use futures::future::BoxFuture;
+use mongodb::{
+    ClientSession,
+    error::{TRANSIENT_TRANSACTION_ERROR, UNKNOWN_TRANSACTION_COMMIT_RESULT},
+};
+
+use crate::{
+    error::{ServiceError, ServiceResult},
+    model::{database::Database, document::DocumentDatabase},
+};
+
+// attempts of a transaction, and of its commit, before giving up
+const MAX_TXN_ATTEMPTS: u32 = 3;
+
+/// The transaction the writes of a repository method belong to, passed along
+/// so several methods commit or abort together: the session of mongo, or the
+/// database over the store of a transaction of a [`DocumentDatabase`]. Without
+/// either the writes apply one by one.
+#[derive(Default)]
+pub struct TxnContext {
+    session: Option<ClientSession>,
+    documents: Option<DocumentDatabase>,
+}
+
+impl std::fmt::Debug for TxnContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TxnContext")
+            .field("active", &self.is_active())
+            .finish()
+    }
+}
+
+impl TxnContext {
+    /// Outside of any transaction.
+    pub fn none() -> Self {
+        TxnContext::default()
+    }
+
+    /// In the transaction started on the session.
+    pub fn started(session: ClientSession) -> Self {
+        TxnContext {
+            session: Some(session),
+            documents: None,
+        }
+    }
+
+    /// In the transaction of the document store the database runs on.
+    pub fn in_store(documents: DocumentDatabase) -> Self {
+        TxnContext {
+            session: None,
+            documents: Some(documents),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.session.is_some() || self.documents.is_some()
+    }
+
+    pub fn session(&mut self) -> Option<&mut ClientSession> {
+        self.session.as_mut()
+    }
+
+    /// The database to run the writes of the transaction on, the one given
+    /// outside of a transaction.
+    pub fn documents<'a>(&'a self, outside: &'a DocumentDatabase) -> &'a DocumentDatabase {
+        self.documents.as_ref().unwrap_or(outside)
+    }
+
+    // a commit of unknown outcome is safe to send again
+    async fn commit(&mut self) -> ServiceResult<()> {
+        if let Some(documents) = &self.documents {
+            return documents.store().commit().await;
+        }
+        let Some(session) = self.session.as_mut() else {
+            return Ok(());
+        };
+        let mut attempt = 1;
+        loop {
+            match session.commit_transaction().await {
+                Err(e)
+                    if e.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT)
+                        && attempt < MAX_TXN_ATTEMPTS =>
+                {
+                    attempt += 1;
+                }
+                result => return Ok(result?),
+            }
+        }
+    }
+
+    async fn abort(&mut self) {
+        if let Some(documents) = &self.documents {
+            documents.store().rollback().await;
+            return;
+        }
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        if let Err(e) = session.abort_transaction().await {
+            tracing::warn!("Failed to abort transaction: {}", e);
+        }
+    }
+}
+
+/// Run the mongo action in the session of the transaction, when there is one.
+macro_rules! in_session {
+    ($action:expr, $txn:expr) => {
+        match $txn.session() {
+            Some(session) => $action.session(session).await,
+            None => $action.await,
+        }
+    };
+}
+
+pub(crate) use in_session;
+
+/// Run the work in a single transaction: committed when it succeeds, aborted
+/// when it fails. The work runs again from the start when the transaction
+/// fails on a transient error (write conflict, primary stepdown...), it must
+/// not have effects outside of the database.
+pub async fn in_transaction<T, F>(db: &dyn Database, mut work: F) -> ServiceResult<T>
+where
+    T: Send,
+    F: for<'t> FnMut(&'t dyn Database, &'t mut TxnContext) -> BoxFuture<'t, ServiceResult<T>>
+        + Send,
+{
+    let mut attempt = 1;
+    loop {
+        let mut txn = db.begin_txn().await?;
+        let result = match work(db, &mut txn).await {
+            Ok(value) => txn.commit().await.map(|()| value),
+            Err(e) => {
+                txn.abort().await;
+                Err(e)
+            }
+        };
+        match result {
+            Err(ServiceError::MongoClientError(e))
+                if e.contains_label(TRANSIENT_TRANSACTION_ERROR) && attempt < MAX_TXN_ATTEMPTS =>
+            {
+                tracing::warn!(
+                    "Transaction failed on attempt {}, running it again: {}",
+                    attempt,
+                    e
+                );
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+/// A resumable upload of the pdf of a paper, received in chunks over several
+/// requests and assembled once the last byte arrives.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadSession {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String,
+    pub paper_id: String,
+    // recognize the text of a scanned file once assembled
+    pub ocr: bool,
+    // in bytes, announced when the upload is created
+    pub length: u64,
+    // bytes received so far
+    pub offset: u64,
+    pub created_at: bson::DateTime,
+}
+
+impl UploadSession {
+    pub fn new(user_id: &str, paper_id: &str, length: u64, ocr: bool) -> Self {
+        UploadSession {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            paper_id: paper_id.to_string(),
+            ocr,
+            length,
+            offset: 0,
+            created_at: bson::DateTime::now(),
+        }
+    }
+}
+
+/// The bytes of an upload received by one request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadChunk {
+    #[serde(rename = "_id")]
+    pub id: String, // `{upload_id}:{offset}`
+    pub upload_id: String,
+    pub user_id: String,
+    // position of the first byte in the file
+    pub offset: u64,
+    pub data: bson::Binary,
+    // of the upload, so the chunks expire with it
+    pub created_at: bson::DateTime,
+}
+
+impl UploadChunk {
+    pub fn new(upload: &UploadSession, bytes: Vec<u8>) -> Self {
+        UploadChunk {
+            id: format!("{}:{}", upload.id, upload.offset),
+            upload_id: upload.id.clone(),
+            user_id: upload.user_id.clone(),
+            offset: upload.offset,
+            data: bson::Binary {
+                subtype: bson::spec::BinarySubtype::Generic,
+                bytes,
+            },
+            created_at: upload.created_at,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait UploadRepository: Send + Sync {
+    async fn create_upload(&self, upload: UploadSession) -> ServiceResult<()>;
+    async fn get_upload(&self, user_id: &str, id: &str) -> ServiceResult<Option<UploadSession>>;
+    /// Store the chunk, replacing the one left at its offset by a failed attempt.
+    async fn put_upload_chunk(&self, chunk: UploadChunk) -> ServiceResult<()>;
+    /// Move the offset of the upload past a stored chunk, false when another
+    /// request moved it first.
+    async fn advance_upload(&self, id: &str, offset: u64, new_offset: u64) -> ServiceResult<bool>;
+    /// The chunks of the upload, by offset.
+    async fn get_upload_chunks(&self, upload_id: &str) -> ServiceResult<Vec<UploadChunk>>;
+    /// Delete the upload with its chunks.
+    async fn delete_upload(&self, id: &str) -> ServiceResult<()>;
+}
+
+#[async_trait::async_trait]
+impl UploadRepository for MongoClient {
+    async fn create_upload(&self, upload: UploadSession) -> ServiceResult<()> {
+        self.collection::<UploadSession>(UPLOAD_COLLECTION_NAME)
+            .insert_one(upload)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_upload(&self, user_id: &str, id: &str) -> ServiceResult<Option<UploadSession>> {
+        let upload = self
+            .collection::<UploadSession>(UPLOAD_COLLECTION_NAME)
+            .find_one(doc! { "_id": id, "user_id": user_id })
+            .await?;
+        Ok(upload)
+    }
+
+    async fn put_upload_chunk(&self, chunk: UploadChunk) -> ServiceResult<()> {
+        self.collection::<UploadChunk>(UPLOAD_CHUNK_COLLECTION_NAME)
+            .replace_one(doc! { "_id": &chunk.id }, &chunk)
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    async fn advance_upload(&self, id: &str, offset: u64, new_offset: u64) -> ServiceResult<bool> {
+        let result = self
+            .collection::<UploadSession>(UPLOAD_COLLECTION_NAME)
+            .update_one(
+                doc! { "_id": id, "offset": offset as i64 },
+                doc! { SET_OP: { "offset": new_offset as i64 } },
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn get_upload_chunks(&self, upload_id: &str) -> ServiceResult<Vec<UploadChunk>> {
+        let cursor = self
+            .collection::<UploadChunk>(UPLOAD_CHUNK_COLLECTION_NAME)
+            .find(doc! { "upload_id": upload_id })
+            .sort(doc! { "offset": 1 })
+            .await?;
+        let chunks = cursor.try_collect().await?;
+        Ok(chunks)
+    }
+
+    async fn delete_upload(&self, id: &str) -> ServiceResult<()> {
+        self.collection::<UploadChunk>(UPLOAD_CHUNK_COLLECTION_NAME)
+            .delete_many(doc! { "upload_id": id })
+            .await?;
+        self.collection::<UploadSession>(UPLOAD_COLLECTION_NAME)
+            .delete_one(doc! { "_id": id })
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl UploadRepository for DocumentDatabase {
+    async fn create_upload(&self, upload: UploadSession) -> ServiceResult<()> {
+        self.insert(UPLOAD_COLLECTION_NAME, &upload).await
+    }
+
+    async fn get_upload(&self, user_id: &str, id: &str) -> ServiceResult<Option<UploadSession>> {
+        self.find_one(
+            UPLOAD_COLLECTION_NAME,
+            doc! { "_id": id, "user_id": user_id },
+        )
+        .await
+    }
+
+    async fn put_upload_chunk(&self, chunk: UploadChunk) -> ServiceResult<()> {
+        self.replace_one(
+            UPLOAD_CHUNK_COLLECTION_NAME,
+            doc! { "_id": &chunk.id },
+            &chunk,
+            true,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn advance_upload(&self, id: &str, offset: u64, new_offset: u64) -> ServiceResult<bool> {
+        let matched = self
+            .update_one(
+                UPLOAD_COLLECTION_NAME,
+                doc! { "_id": id, "offset": offset as i64 },
+                doc! { SET_OP: { "offset": new_offset as i64 } },
+            )
+            .await?;
+        Ok(matched > 0)
+    }
+
+    async fn get_upload_chunks(&self, upload_id: &str) -> ServiceResult<Vec<UploadChunk>> {
+        let query = Query::new(doc! { "upload_id": upload_id }).sort(doc! { "offset": 1 });
+        self.find(UPLOAD_CHUNK_COLLECTION_NAME, query).await
+    }
+
+    async fn delete_upload(&self, id: &str) -> ServiceResult<()> {
+        self.delete_many(UPLOAD_CHUNK_COLLECTION_NAME, doc! { "upload_id": id })
+            .await?;
+        self.delete_one(UPLOAD_COLLECTION_NAME, doc! { "_id": id })
+            .await?;
+        Ok(())
+    }
+}
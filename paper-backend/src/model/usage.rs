@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use chrono::{DateTime, Datelike, Months, TimeZone, Utc};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        count_field,
+        document::{DocumentDatabase, Query},
+    },
+    utils::cost::model_price,
+};
+
+pub mod schema {
+    pub use paper_schema::usage::*;
+
+    use crate::model::usage::UsageTotal;
+
+    impl From<UsageTotal> for UsageTotalResponse {
+        fn from(total: UsageTotal) -> Self {
+            UsageTotalResponse {
+                key: total.key,
+                requests: total.requests,
+                input_tokens: total.input_tokens,
+                output_tokens: total.output_tokens,
+                cost: total.cost,
+            }
+        }
+    }
+}
+
+/// Tokens consumed by a llm call made for a user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageEvent {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String,
+    pub created_at: bson::DateTime,
+
+    pub model: String,
+    // the feature the call was made by, e.g. `wrap_up`
+    pub feature: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    // estimated from the list price, none for models without a known price
+    #[serde(default)]
+    pub cost: Option<f64>,
+    // made with the api key of the user, billed to them and out of the quota
+    #[serde(default)]
+    pub own_key: bool,
+}
+
+/// Usage summed over a group of events.
+#[derive(Debug, Clone, Default)]
+pub struct UsageTotal {
+    pub key: Option<String>,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: f64,
+}
+
+impl UsageTotal {
+    pub fn tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+/// Start of the month containing the time, quotas are reset then.
+pub fn month_start(time: DateTime<Utc>) -> DateTime<Utc> {
+    let day = time.date_naive().with_day(1).unwrap_or(time.date_naive());
+    Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0).unwrap_or_default())
+}
+
+/// Start of the month following the one containing the time.
+pub fn next_month_start(time: DateTime<Utc>) -> DateTime<Utc> {
+    let start = month_start(time);
+    start.checked_add_months(Months::new(1)).unwrap_or(start)
+}
+
+impl UsageEvent {
+    pub fn new(
+        user_id: &str,
+        model: &str,
+        feature: &str,
+        input_tokens: u64,
+        output_tokens: u64,
+    ) -> Self {
+        UsageEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+
+            model: model.to_string(),
+            feature: feature.to_string(),
+            input_tokens,
+            output_tokens,
+            cost: model_price(model).map(|p| p.cost(input_tokens, output_tokens)),
+            own_key: false,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait UsageRepository: Send + Sync {
+    async fn record_usage(&self, event: UsageEvent) -> ServiceResult<()>;
+    /// Input and output tokens consumed by the user since the time, the calls
+    /// made with their own api key left out.
+    async fn sum_tokens_since(&self, user_id: &str, since: bson::DateTime) -> ServiceResult<u64>;
+    /// Llm calls made by the feature for the user since the time.
+    async fn count_usage_since(
+        &self,
+        user_id: &str,
+        feature: &str,
+        since: bson::DateTime,
+    ) -> ServiceResult<u64>;
+    /// Usage since the time grouped by the event field, e.g. `model`, or summed
+    /// over all events when `None`. Every user when `user_id` is `None`; the
+    /// largest groups, by tokens, first.
+    async fn sum_usage_since(
+        &self,
+        user_id: Option<&str>,
+        group_by: Option<&str>,
+        since: bson::DateTime,
+        limit: i64,
+    ) -> ServiceResult<Vec<UsageTotal>>;
+}
+
+#[async_trait::async_trait]
+impl UsageRepository for MongoClient {
+    async fn record_usage(&self, event: UsageEvent) -> ServiceResult<()> {
+        self.collection::<UsageEvent>(USAGE_EVENT_COLLECTION_NAME)
+            .insert_one(event)
+            .await?;
+        Ok(())
+    }
+
+    async fn sum_tokens_since(&self, user_id: &str, since: bson::DateTime) -> ServiceResult<u64> {
+        let pipeline = vec![
+            doc! {
+                MATCH_STAGE: {
+                    "user_id": user_id,
+                    "created_at": { GTE_OP: since },
+                    "own_key": { NE_OP: true },
+                },
+            },
+            doc! {
+                GROUP_STAGE: {
+                    "_id": null,
+                    "tokens": { SUM_OP: { "$add": ["$input_tokens", "$output_tokens"] } },
+                },
+            },
+        ];
+        let mut cursor = self
+            .collection::<UsageEvent>(USAGE_EVENT_COLLECTION_NAME)
+            .aggregate(pipeline)
+            .await?;
+        let tokens = match cursor.try_next().await? {
+            Some(group) => count_field(&group, "tokens"),
+            None => 0,
+        };
+        Ok(tokens)
+    }
+
+    async fn count_usage_since(
+        &self,
+        user_id: &str,
+        feature: &str,
+        since: bson::DateTime,
+    ) -> ServiceResult<u64> {
+        let filter = doc! {
+            "user_id": user_id,
+            "feature": feature,
+            "created_at": { GTE_OP: since },
+        };
+        let count = self
+            .collection::<UsageEvent>(USAGE_EVENT_COLLECTION_NAME)
+            .count_documents(filter)
+            .await?;
+        Ok(count)
+    }
+
+    async fn sum_usage_since(
+        &self,
+        user_id: Option<&str>,
+        group_by: Option<&str>,
+        since: bson::DateTime,
+        limit: i64,
+    ) -> ServiceResult<Vec<UsageTotal>> {
+        let mut filter = doc! { "created_at": { GTE_OP: since } };
+        if let Some(user_id) = user_id {
+            filter.insert("user_id", user_id);
+        }
+        let key = group_by.map_or(bson::Bson::Null, |field| format!("${}", field).into());
+        let pipeline = vec![
+            doc! { MATCH_STAGE: filter },
+            doc! {
+                GROUP_STAGE: {
+                    "_id": key,
+                    "requests": { SUM_OP: 1 },
+                    "input_tokens": { SUM_OP: "$input_tokens" },
+                    "output_tokens": { SUM_OP: "$output_tokens" },
+                    "cost": { SUM_OP: { "$ifNull": ["$cost", 0.0] } },
+                    "tokens": { SUM_OP: { "$add": ["$input_tokens", "$output_tokens"] } },
+                },
+            },
+            doc! { SORT_STAGE: { "tokens": -1 } },
+            doc! { LIMIT_STAGE: limit },
+        ];
+        let cursor = self
+            .collection::<UsageEvent>(USAGE_EVENT_COLLECTION_NAME)
+            .aggregate(pipeline)
+            .await?;
+        let groups: Vec<bson::Document> = cursor.try_collect().await?;
+        Ok(groups
+            .into_iter()
+            .map(|group| UsageTotal {
+                key: group.get_str("_id").ok().map(String::from),
+                requests: count_field(&group, "requests"),
+                input_tokens: count_field(&group, "input_tokens"),
+                output_tokens: count_field(&group, "output_tokens"),
+                cost: group.get_f64("cost").unwrap_or_default(),
+            })
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl UsageRepository for DocumentDatabase {
+    async fn record_usage(&self, event: UsageEvent) -> ServiceResult<()> {
+        self.insert(USAGE_EVENT_COLLECTION_NAME, &event).await
+    }
+
+    async fn sum_tokens_since(&self, user_id: &str, since: bson::DateTime) -> ServiceResult<u64> {
+        let filter = doc! {
+            "user_id": user_id,
+            "created_at": { GTE_OP: since },
+            "own_key": { NE_OP: true },
+        };
+        let events: Vec<UsageEvent> = self
+            .find(USAGE_EVENT_COLLECTION_NAME, Query::new(filter))
+            .await?;
+        Ok(events
+            .iter()
+            .map(|event| event.input_tokens + event.output_tokens)
+            .sum())
+    }
+
+    async fn count_usage_since(
+        &self,
+        user_id: &str,
+        feature: &str,
+        since: bson::DateTime,
+    ) -> ServiceResult<u64> {
+        let filter = doc! {
+            "user_id": user_id,
+            "feature": feature,
+            "created_at": { GTE_OP: since },
+        };
+        self.count(USAGE_EVENT_COLLECTION_NAME, filter).await
+    }
+
+    async fn sum_usage_since(
+        &self,
+        user_id: Option<&str>,
+        group_by: Option<&str>,
+        since: bson::DateTime,
+        limit: i64,
+    ) -> ServiceResult<Vec<UsageTotal>> {
+        let mut filter = doc! { "created_at": { GTE_OP: since } };
+        if let Some(user_id) = user_id {
+            filter.insert("user_id", user_id);
+        }
+        let events = self
+            .find_documents(USAGE_EVENT_COLLECTION_NAME, Query::new(filter))
+            .await?;
+        let mut groups: HashMap<Option<String>, UsageTotal> = HashMap::new();
+        for event in &events {
+            let key = group_by.and_then(|field| event.get_str(field).ok().map(String::from));
+            let total = groups.entry(key.clone()).or_insert_with(|| UsageTotal {
+                key,
+                requests: 0,
+                input_tokens: 0,
+                output_tokens: 0,
+                cost: 0.0,
+            });
+            total.requests += 1;
+            total.input_tokens += count_field(event, "input_tokens");
+            total.output_tokens += count_field(event, "output_tokens");
+            total.cost += event.get_f64("cost").unwrap_or_default();
+        }
+        let mut totals = groups.into_values().collect::<Vec<_>>();
+        totals.sort_by_key(|total| std::cmp::Reverse(total.input_tokens + total.output_tokens));
+        totals.truncate(limit.max(0) as usize);
+        Ok(totals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_month_bounds() {
+        let time = Utc.with_ymd_and_hms(2025, 12, 17, 8, 30, 0).unwrap();
+        assert_eq!(
+            month_start(time),
+            Utc.with_ymd_and_hms(2025, 12, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            next_month_start(time),
+            Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+        );
+    }
+}
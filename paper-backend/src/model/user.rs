@@ -1,34 +1,28 @@
 use ai_flow_synth::utils::MongoClient;
 use bson::doc;
+use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    error::{ServiceError, ServiceResult},
-    model::constant::*,
+    config::SettingsConfig,
+    error::{ServiceError, ServiceResult, is_duplicate_key},
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+        quota::QuotaOverrides,
+    },
 };
 
 pub mod schema {
-    use salvo::{Response, Scribe, macros::Extractible, writing::Json};
-    use serde::{Deserialize, Serialize};
+    pub use paper_schema::user::*;
 
-    use crate::model::user::User;
+    use validator::{ValidationError, ValidationErrors};
 
-    #[derive(Debug, Serialize, Deserialize)]
-    pub struct UserInfoResponse {
-        pub uid: String,
-        pub username: Option<String>,
-        pub phone: Option<String>,
-        pub email: Option<String>,
-        pub wechat_id: Option<String>,
-        pub created_at: bson::DateTime,
-        pub updated_at: bson::DateTime,
-    }
-
-    impl Scribe for UserInfoResponse {
-        fn render(self, res: &mut Response) {
-            res.render(Json(self));
-        }
-    }
+    use crate::{
+        i18n::Locale,
+        model::user::User,
+        utils::validate::{ValidatedRequest, trim},
+    };
 
     impl From<User> for UserInfoResponse {
         fn from(user: User) -> Self {
@@ -38,17 +32,75 @@ pub mod schema {
                 phone: user.phone,
                 email: user.email,
                 wechat_id: user.wechat_id,
+                org_id: user.org_id,
                 created_at: user.created_at,
                 updated_at: user.updated_at,
             }
         }
     }
 
-    #[derive(Debug, Deserialize, Extractible)]
-    #[salvo(extract(default_source(from = "body")))]
-    pub struct UpdateUserInfo {
-        pub username: Option<String>,
-        pub password: Option<String>,
+    impl From<&User> for NotificationPreferencesResponse {
+        fn from(user: &User) -> Self {
+            NotificationPreferencesResponse {
+                weekly_digest: user.notification_preferences.weekly_digest,
+                summary_ready_email: user.notification_preferences.summary_ready_email,
+                last_digest_at: user.last_digest_at.map(|t| t.timestamp_millis()),
+            }
+        }
+    }
+
+    impl ValidatedRequest for UpdateNotificationPreferencesRequest {}
+
+    fn validate_language(language: &str) -> Result<(), ValidationError> {
+        if !language.is_empty() && !Locale::ALL.iter().any(|l| l.code() == language) {
+            let codes = Locale::ALL.iter().map(|l| l.code()).collect::<Vec<_>>();
+            let mut error = ValidationError::new("language");
+            error.message = Some(format!("language must be one of {}", codes.join(", ")).into());
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    fn validate_timezone(timezone: &str) -> Result<(), ValidationError> {
+        if !timezone.is_empty() && timezone.parse::<chrono_tz::Tz>().is_err() {
+            let mut error = ValidationError::new("timezone");
+            error.message = Some("timezone must be an IANA name, e.g. Europe/Paris".into());
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    impl ValidatedRequest for UpdateSettingsRequest {
+        fn normalize(&mut self) {
+            for value in [
+                &mut self.language,
+                &mut self.default_folder_id,
+                &mut self.ai_model,
+                &mut self.summary_language,
+                &mut self.timezone,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                trim(value);
+            }
+        }
+
+        // the languages and the time zones known to the server
+        fn check(&self, errors: &mut ValidationErrors) {
+            let languages = [
+                ("language", &self.language),
+                ("summary_language", &self.summary_language),
+            ];
+            for (field, language) in languages {
+                if let Some(Err(error)) = language.as_deref().map(validate_language) {
+                    errors.add(field, error);
+                }
+            }
+            if let Some(Err(error)) = self.timezone.as_deref().map(validate_timezone) {
+                errors.add("timezone", error);
+            }
+        }
     }
 }
 
@@ -63,24 +115,110 @@ pub struct User {
     pub wechat_id: Option<String>, // 微信id
 
     pub email: Option<String>,
+    #[serde(default)]
+    pub status: UserStatus,
+    // organization the user is a member of
+    #[serde(default)]
+    pub org_id: Option<String>,
 
     pub created_at: bson::DateTime,
     pub updated_at: bson::DateTime,
     pub last_login: Option<bson::DateTime>,
+    // tokens issued before this time are rejected, set on password reset
+    pub sessions_invalidated_at: Option<bson::DateTime>,
+    // versions of the legal documents the user accepted last
+    #[serde(default)]
+    pub terms_version: Option<String>,
+    #[serde(default)]
+    pub privacy_version: Option<String>,
+    // emails the user opted in or out of
+    #[serde(default)]
+    pub notification_preferences: NotificationPreferences,
+    #[serde(default)]
+    pub last_digest_at: Option<bson::DateTime>,
+    #[serde(default)]
+    pub settings: UserSettings,
+    // limits set by an operator, the config applies to the others
+    #[serde(default)]
+    pub quota_overrides: QuotaOverrides,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationPreferences {
+    pub weekly_digest: bool,
+    pub summary_ready_email: bool,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        NotificationPreferences {
+            weekly_digest: true,
+            summary_ready_email: false,
+        }
+    }
+}
+
+impl NotificationPreferences {
+    /// Set the preferences given by the request, the others are kept.
+    pub fn update(&mut self, request: schema::UpdateNotificationPreferencesRequest) {
+        if let Some(weekly_digest) = request.weekly_digest {
+            self.weekly_digest = weekly_digest;
+        }
+        if let Some(summary_ready_email) = request.summary_ready_email {
+            self.summary_ready_email = summary_ready_email;
+        }
+    }
+}
+
+/// Preferences of the user, the unset ones follow the settings config.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    pub language: Option<String>,
+    pub default_folder_id: Option<String>,
+    pub ai_model: Option<String>,
+    // the ui language when unset
+    pub summary_language: Option<String>,
+    pub timezone: Option<String>,
+}
+
+impl UserSettings {
+    pub fn language<'a>(&'a self, defaults: &'a SettingsConfig) -> &'a str {
+        self.language.as_deref().unwrap_or(&defaults.language)
+    }
+
+    pub fn summary_language<'a>(&'a self, defaults: &'a SettingsConfig) -> &'a str {
+        self.summary_language
+            .as_deref()
+            .unwrap_or_else(|| self.language(defaults))
+    }
+
+    /// The timezone of the user, UTC when neither it nor the default is valid.
+    pub fn timezone(&self, defaults: &SettingsConfig) -> chrono_tz::Tz {
+        self.timezone
+            .as_deref()
+            .and_then(|timezone| timezone.parse().ok())
+            .or_else(|| defaults.timezone.parse().ok())
+            .unwrap_or(chrono_tz::UTC)
+    }
 }
 
-pub async fn create_index(client: &MongoClient) -> ServiceResult<()> {
-    let collection = client.collection::<User>(USER_COLLECTION_NAME);
-    let uid_index = mongodb::IndexModel::builder()
-        .keys(doc! { "uid": 1 })
-        .build();
-    let phone_hash_index = mongodb::IndexModel::builder()
-        .keys(doc! { "phone_hash": 1 })
-        .build();
-    collection
-        .create_indexes(vec![uid_index, phone_hash_index])
-        .await?;
-    Ok(())
+// a given empty value unsets the setting, back to the default
+fn set(setting: &mut Option<String>, value: Option<String>) {
+    if let Some(value) = value {
+        *setting = Some(value).filter(|value| !value.is_empty());
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum UserStatus {
+    // registered by email, waiting for verification
+    #[serde(rename = "pending")]
+    Pending,
+    #[default]
+    #[serde(rename = "active")]
+    Active,
 }
 
 impl User {
@@ -94,32 +232,113 @@ impl User {
             phone_hash: None, // todo hash phone
             wechat_id: None,
             email: None,
+            status: UserStatus::Active,
+            org_id: None,
             created_at: now,
             updated_at: now,
             last_login: None,
+            sessions_invalidated_at: None,
+            terms_version: None,
+            privacy_version: None,
+            notification_preferences: NotificationPreferences::default(),
+            last_digest_at: None,
+            settings: UserSettings::default(),
+            quota_overrides: QuotaOverrides::default(),
+        }
+    }
+
+    /// Set the settings given by the request, the others are kept.
+    pub fn update_settings(&mut self, request: schema::UpdateSettingsRequest) {
+        set(&mut self.settings.language, request.language);
+        set(
+            &mut self.settings.default_folder_id,
+            request.default_folder_id,
+        );
+        set(&mut self.settings.ai_model, request.ai_model);
+        set(
+            &mut self.settings.summary_language,
+            request.summary_language,
+        );
+        set(&mut self.settings.timezone, request.timezone);
+        if let Some(notifications) = request.notifications {
+            self.notification_preferences.update(notifications);
+        }
+    }
+
+    /// Whether a token issued at `iat` (in seconds) has been revoked. A token of
+    /// the second of the invalidation may predate it, and is revoked too.
+    pub fn is_token_revoked(&self, iat: i64) -> bool {
+        self.sessions_invalidated_at
+            .is_some_and(|t| iat <= t.timestamp_millis() / 1000)
+    }
+
+    pub fn new_by_email(email: String, username: Option<String>, password_hash: String) -> Self {
+        let now = bson::DateTime::now();
+        User {
+            uid: uuid::Uuid::new_v4().to_string(),
+            username,
+            password_hash: Some(password_hash),
+            phone: None,
+            phone_hash: None,
+            wechat_id: None,
+            email: Some(email),
+            status: UserStatus::Pending,
+            org_id: None,
+            created_at: now,
+            updated_at: now,
+            last_login: None,
+            sessions_invalidated_at: None,
+            terms_version: None,
+            privacy_version: None,
+            notification_preferences: NotificationPreferences::default(),
+            last_digest_at: None,
+            settings: UserSettings::default(),
+            quota_overrides: QuotaOverrides::default(),
         }
     }
 }
 
+pub fn email_taken(email: &str) -> ServiceError {
+    ServiceError::DuplicateUser(format!("Email {} already registered", email))
+}
+
 #[async_trait::async_trait]
 pub trait UserRepository {
+    /// Create the user, failing with `DuplicateUser` when its email is taken,
+    /// which the unique index of the emails settles between concurrent ones.
     async fn create_user(&self, user: User) -> ServiceResult<()>;
     async fn update_user(&self, user: User) -> ServiceResult<()>;
     async fn delete_user(&self, id: String) -> ServiceResult<()>;
 
     async fn get_user_by_phone(&self, phone: &str) -> ServiceResult<Option<User>>;
     async fn get_user_by_uid(&self, uid: &str) -> ServiceResult<Option<User>>;
+    async fn get_user_by_email(&self, email: &str) -> ServiceResult<Option<User>>;
 
     async fn check_non_duplicate(&self, phone: Option<String>) -> ServiceResult<()>;
+    async fn check_non_duplicate_email(&self, email: &str) -> ServiceResult<()>;
+
+    /// Active users with an email who opted in the digest and got none since `before`.
+    async fn get_users_due_for_digest(&self, before: bson::DateTime) -> ServiceResult<Vec<User>>;
+    /// Mark the digest of the user as sent, false when another instance did
+    /// since `before`, so every digest is sent once.
+    async fn claim_digest(&self, uid: &str, before: bson::DateTime) -> ServiceResult<bool>;
 }
 
 #[async_trait::async_trait]
 impl UserRepository for MongoClient {
     async fn create_user(&self, user: User) -> ServiceResult<()> {
-        self.collection::<User>(USER_COLLECTION_NAME)
+        let email = user.email.clone().unwrap_or_default();
+        match self
+            .collection::<User>(USER_COLLECTION_NAME)
             .insert_one(user)
-            .await?;
-        Ok(())
+            .await
+        {
+            Err(e) if is_duplicate_key(&e) => Err(email_taken(&email)),
+            result => {
+                result?;
+                Ok(())
+            }
+        }
     }
 
     async fn update_user(&self, user: User) -> ServiceResult<()> {
@@ -157,6 +376,15 @@ impl UserRepository for MongoClient {
         Ok(user)
     }
 
+    async fn get_user_by_email(&self, email: &str) -> ServiceResult<Option<User>> {
+        let filter = doc! { "email": email };
+        let user = self
+            .collection::<User>(USER_COLLECTION_NAME)
+            .find_one(filter)
+            .await?;
+        Ok(user)
+    }
+
     async fn check_non_duplicate(&self, phone: Option<String>) -> ServiceResult<()> {
         if let Some(phone) = phone {
             let filter = doc! { "phone": phone };
@@ -170,4 +398,177 @@ impl UserRepository for MongoClient {
         }
         Ok(())
     }
+
+    async fn check_non_duplicate_email(&self, email: &str) -> ServiceResult<()> {
+        let filter = doc! { "email": email };
+        let count = self
+            .collection::<User>(USER_COLLECTION_NAME)
+            .count_documents(filter)
+            .await?;
+        if count > 0 {
+            return Err(email_taken(email));
+        }
+        Ok(())
+    }
+
+    async fn get_users_due_for_digest(&self, before: bson::DateTime) -> ServiceResult<Vec<User>> {
+        let filter = doc! {
+            "email": { NE_OP: null },
+            "status": { NE_OP: "pending" },
+            "notification_preferences.weekly_digest": { NE_OP: false },
+            // registered for a week at least
+            "created_at": { LTE_OP: before },
+            OR_OP: [
+                { "last_digest_at": null },
+                { "last_digest_at": { LTE_OP: before } },
+            ],
+        };
+        let cursor = self
+            .collection::<User>(USER_COLLECTION_NAME)
+            .find(filter)
+            .await?;
+        let users = cursor.try_collect().await?;
+        Ok(users)
+    }
+
+    async fn claim_digest(&self, uid: &str, before: bson::DateTime) -> ServiceResult<bool> {
+        let filter = doc! {
+            "uid": uid,
+            OR_OP: [
+                { "last_digest_at": null },
+                { "last_digest_at": { LTE_OP: before } },
+            ],
+        };
+        let update = doc! { SET_OP: { "last_digest_at": bson::DateTime::now() } };
+        let result = self
+            .collection::<User>(USER_COLLECTION_NAME)
+            .update_one(filter, update)
+            .await?;
+        Ok(result.modified_count > 0)
+    }
+}
+
+#[async_trait::async_trait]
+impl UserRepository for DocumentDatabase {
+    async fn create_user(&self, user: User) -> ServiceResult<()> {
+        match self.try_insert(USER_COLLECTION_NAME, &user).await? {
+            true => Ok(()),
+            false => Err(email_taken(user.email.as_deref().unwrap_or_default())),
+        }
+    }
+
+    async fn update_user(&self, user: User) -> ServiceResult<()> {
+        let filter = doc! { "uid": &user.uid };
+        let update = doc! { SET_OP: bson::to_bson(&user)? };
+        self.update_one(USER_COLLECTION_NAME, filter, update)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_user(&self, uid: String) -> ServiceResult<()> {
+        self.delete_one(USER_COLLECTION_NAME, doc! { "uid": uid })
+            .await?;
+        Ok(())
+    }
+
+    async fn get_user_by_phone(&self, phone: &str) -> ServiceResult<Option<User>> {
+        self.find_one(USER_COLLECTION_NAME, doc! { "phone": phone })
+            .await
+    }
+
+    async fn get_user_by_uid(&self, uid: &str) -> ServiceResult<Option<User>> {
+        self.find_one(USER_COLLECTION_NAME, doc! { "uid": uid })
+            .await
+    }
+
+    async fn get_user_by_email(&self, email: &str) -> ServiceResult<Option<User>> {
+        self.find_one(USER_COLLECTION_NAME, doc! { "email": email })
+            .await
+    }
+
+    async fn check_non_duplicate(&self, phone: Option<String>) -> ServiceResult<()> {
+        if let Some(phone) = phone {
+            let count = self
+                .count(USER_COLLECTION_NAME, doc! { "phone": phone })
+                .await?;
+            if count > 0 {
+                return Err(ServiceError::DuplicateUser(format!("User already exists")));
+            }
+        }
+        Ok(())
+    }
+
+    async fn check_non_duplicate_email(&self, email: &str) -> ServiceResult<()> {
+        let count = self
+            .count(USER_COLLECTION_NAME, doc! { "email": email })
+            .await?;
+        if count > 0 {
+            return Err(email_taken(email));
+        }
+        Ok(())
+    }
+
+    async fn get_users_due_for_digest(&self, before: bson::DateTime) -> ServiceResult<Vec<User>> {
+        let filter = doc! {
+            "email": { NE_OP: null },
+            "status": { NE_OP: "pending" },
+            "notification_preferences.weekly_digest": { NE_OP: false },
+            "created_at": { LTE_OP: before },
+            OR_OP: [
+                { "last_digest_at": null },
+                { "last_digest_at": { LTE_OP: before } },
+            ],
+        };
+        self.find(USER_COLLECTION_NAME, Query::new(filter)).await
+    }
+
+    async fn claim_digest(&self, uid: &str, before: bson::DateTime) -> ServiceResult<bool> {
+        let filter = doc! {
+            "uid": uid,
+            OR_OP: [
+                { "last_digest_at": null },
+                { "last_digest_at": { LTE_OP: before } },
+            ],
+        };
+        let update = doc! { SET_OP: { "last_digest_at": bson::DateTime::now() } };
+        let matched = self
+            .update_one(USER_COLLECTION_NAME, filter, update)
+            .await?;
+        Ok(matched > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_settings() {
+        let defaults = SettingsConfig::default();
+        let mut settings = UserSettings::default();
+        assert_eq!(settings.language(&defaults), "en");
+        assert_eq!(settings.summary_language(&defaults), "en");
+        assert_eq!(settings.timezone(&defaults), chrono_tz::UTC);
+
+        settings.language = Some("zh-CN".to_string());
+        settings.timezone = Some("Asia/Shanghai".to_string());
+        assert_eq!(settings.summary_language(&defaults), "zh-CN");
+        assert_eq!(settings.timezone(&defaults), chrono_tz::Asia::Shanghai);
+
+        settings.summary_language = Some("en".to_string());
+        settings.timezone = Some("Mars/Olympus".to_string());
+        assert_eq!(settings.summary_language(&defaults), "en");
+        assert_eq!(settings.timezone(&defaults), chrono_tz::UTC);
+    }
+
+    #[test]
+    fn test_is_token_revoked() {
+        let mut user = User::new_by_email("reader@example.com".to_string(), None, String::new());
+        assert!(!user.is_token_revoked(1_700_000_000));
+        user.sessions_invalidated_at = Some(bson::DateTime::from_millis(1_700_000_000_500));
+        assert!(user.is_token_revoked(1_699_999_999));
+        // issued in the second of the reset, before or after it
+        assert!(user.is_token_revoked(1_700_000_000));
+        assert!(!user.is_token_revoked(1_700_000_001));
+    }
 }
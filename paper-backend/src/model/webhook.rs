@@ -0,0 +1,305 @@
+use ai_flow_synth::utils::MongoClient;
+use bson::doc;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ServiceResult,
+    model::{
+        constant::*,
+        document::{DocumentDatabase, Query},
+    },
+};
+
+pub mod schema {
+    pub use paper_schema::webhook::*;
+
+    use validator::{ValidationError, ValidationErrors};
+
+    use crate::{
+        events::{DomainEvent, webhook::check_host},
+        model::webhook::{Webhook, WebhookDelivery},
+        utils::validate::{ValidatedRequest, trim, trim_all},
+    };
+
+    impl From<Webhook> for WebhookResponse {
+        fn from(webhook: Webhook) -> Self {
+            WebhookResponse {
+                id: webhook.id,
+                url: webhook.url,
+                events: webhook.events,
+                secret: None,
+                created_at: webhook.created_at.timestamp_millis(),
+            }
+        }
+    }
+
+    fn validate_url(url: &str) -> Result<(), ValidationError> {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            let mut error = ValidationError::new("url");
+            error.message = Some("the url must be http or https".into());
+            return Err(error);
+        }
+        // the names are resolved and checked again on every delivery
+        if let Err(e) = check_host(url) {
+            let mut error = ValidationError::new("url");
+            error.message = Some(e.into());
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    fn validate_events(events: &[String]) -> Result<(), ValidationError> {
+        if let Some(event) = events
+            .iter()
+            .find(|event| !DomainEvent::KINDS.contains(&event.as_str()))
+        {
+            let mut error = ValidationError::new("event");
+            error.message = Some(format!("unknown event type {}", event).into());
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    impl ValidatedRequest for CreateWebhookRequest {
+        fn normalize(&mut self) {
+            trim(&mut self.url);
+            trim_all(&mut self.events);
+            self.events.sort();
+            self.events.dedup();
+        }
+
+        // the hosts and the event types the server delivers to
+        fn check(&self, errors: &mut ValidationErrors) {
+            if let Err(error) = validate_url(&self.url) {
+                errors.add("url", error);
+            }
+            if let Err(error) = validate_events(&self.events) {
+                errors.add("events", error);
+            }
+        }
+    }
+
+    impl From<WebhookDelivery> for WebhookDeliveryResponse {
+        fn from(delivery: WebhookDelivery) -> Self {
+            WebhookDeliveryResponse {
+                id: delivery.id,
+                event_id: delivery.event_id,
+                event_type: delivery.event_type,
+                success: delivery.success,
+                attempts: delivery.attempts,
+                status_code: delivery.status_code,
+                error: delivery.error,
+                created_at: delivery.created_at.timestamp_millis(),
+            }
+        }
+    }
+}
+
+/// An url the events of the user are posted to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub user_id: String,
+    pub created_at: bson::DateTime,
+
+    pub url: String,
+    // event types delivered, all when empty
+    pub events: Vec<String>,
+    pub secret: String,
+}
+
+impl Webhook {
+    pub fn new(user_id: &str, request: schema::CreateWebhookRequest) -> Self {
+        Webhook {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            created_at: bson::DateTime::now(),
+
+            url: request.url,
+            events: request.events,
+            secret: format!(
+                "whsec_{}{}",
+                uuid::Uuid::new_v4().simple(),
+                uuid::Uuid::new_v4().simple()
+            ),
+        }
+    }
+
+    pub fn accepts(&self, kind: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|event| event == kind)
+    }
+}
+
+/// Outcome of the delivery of an event to a webhook, after the retries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    #[serde(rename = "_id")]
+    pub id: String, // uuid
+    pub webhook_id: String,
+    pub user_id: String,
+    pub created_at: bson::DateTime,
+
+    pub event_id: String,
+    pub event_type: String,
+    pub success: bool,
+    pub attempts: u32,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+}
+
+#[async_trait::async_trait]
+pub trait WebhookRepository: Send + Sync {
+    async fn create_webhook(&self, webhook: Webhook) -> ServiceResult<()>;
+    async fn get_webhook(&self, user_id: &str, id: &str) -> ServiceResult<Option<Webhook>>;
+    async fn get_webhooks(&self, user_id: &str) -> ServiceResult<Vec<Webhook>>;
+    /// The webhooks of every user, to seal their secrets again after a key
+    /// rotation.
+    async fn get_all_webhooks(&self) -> ServiceResult<Vec<Webhook>>;
+    async fn set_webhook_secret(&self, id: &str, secret: &str) -> ServiceResult<()>;
+    async fn delete_webhook(&self, user_id: &str, id: &str) -> ServiceResult<()>;
+    async fn create_webhook_delivery(&self, delivery: WebhookDelivery) -> ServiceResult<()>;
+    /// The latest deliveries to the webhook, newest first.
+    async fn get_webhook_deliveries(
+        &self,
+        webhook_id: &str,
+        limit: i64,
+    ) -> ServiceResult<Vec<WebhookDelivery>>;
+}
+
+#[async_trait::async_trait]
+impl WebhookRepository for MongoClient {
+    async fn create_webhook(&self, webhook: Webhook) -> ServiceResult<()> {
+        self.collection::<Webhook>(WEBHOOK_COLLECTION_NAME)
+            .insert_one(webhook)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_webhook(&self, user_id: &str, id: &str) -> ServiceResult<Option<Webhook>> {
+        let filter = doc! { "_id": id, "user_id": user_id };
+        let result = self
+            .collection::<Webhook>(WEBHOOK_COLLECTION_NAME)
+            .find_one(filter)
+            .await?;
+        Ok(result)
+    }
+
+    async fn get_webhooks(&self, user_id: &str) -> ServiceResult<Vec<Webhook>> {
+        let filter = doc! { "user_id": user_id };
+        let cursor = self
+            .collection::<Webhook>(WEBHOOK_COLLECTION_NAME)
+            .find(filter)
+            .sort(doc! { "created_at": 1 })
+            .await?;
+        let webhooks = cursor.try_collect().await?;
+        Ok(webhooks)
+    }
+
+    async fn get_all_webhooks(&self) -> ServiceResult<Vec<Webhook>> {
+        let cursor = self
+            .collection::<Webhook>(WEBHOOK_COLLECTION_NAME)
+            .find(doc! {})
+            .await?;
+        let webhooks = cursor.try_collect().await?;
+        Ok(webhooks)
+    }
+
+    async fn set_webhook_secret(&self, id: &str, secret: &str) -> ServiceResult<()> {
+        self.collection::<Webhook>(WEBHOOK_COLLECTION_NAME)
+            .update_one(doc! { "_id": id }, doc! { SET_OP: { "secret": secret } })
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_webhook(&self, user_id: &str, id: &str) -> ServiceResult<()> {
+        let filter = doc! { "_id": id, "user_id": user_id };
+        self.collection::<Webhook>(WEBHOOK_COLLECTION_NAME)
+            .delete_one(filter)
+            .await?;
+        self.collection::<WebhookDelivery>(WEBHOOK_DELIVERY_COLLECTION_NAME)
+            .delete_many(doc! { "webhook_id": id })
+            .await?;
+        Ok(())
+    }
+
+    async fn create_webhook_delivery(&self, delivery: WebhookDelivery) -> ServiceResult<()> {
+        self.collection::<WebhookDelivery>(WEBHOOK_DELIVERY_COLLECTION_NAME)
+            .insert_one(delivery)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_webhook_deliveries(
+        &self,
+        webhook_id: &str,
+        limit: i64,
+    ) -> ServiceResult<Vec<WebhookDelivery>> {
+        let filter = doc! { "webhook_id": webhook_id };
+        let cursor = self
+            .collection::<WebhookDelivery>(WEBHOOK_DELIVERY_COLLECTION_NAME)
+            .find(filter)
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await?;
+        let deliveries = cursor.try_collect().await?;
+        Ok(deliveries)
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookRepository for DocumentDatabase {
+    async fn create_webhook(&self, webhook: Webhook) -> ServiceResult<()> {
+        self.insert(WEBHOOK_COLLECTION_NAME, &webhook).await
+    }
+
+    async fn get_webhook(&self, user_id: &str, id: &str) -> ServiceResult<Option<Webhook>> {
+        let filter = doc! { "_id": id, "user_id": user_id };
+        self.find_one(WEBHOOK_COLLECTION_NAME, filter).await
+    }
+
+    async fn get_webhooks(&self, user_id: &str) -> ServiceResult<Vec<Webhook>> {
+        let query = Query::new(doc! { "user_id": user_id }).sort(doc! { "created_at": 1 });
+        self.find(WEBHOOK_COLLECTION_NAME, query).await
+    }
+
+    async fn get_all_webhooks(&self) -> ServiceResult<Vec<Webhook>> {
+        self.find(WEBHOOK_COLLECTION_NAME, Query::new(doc! {}))
+            .await
+    }
+
+    async fn set_webhook_secret(&self, id: &str, secret: &str) -> ServiceResult<()> {
+        self.update_one(
+            WEBHOOK_COLLECTION_NAME,
+            doc! { "_id": id },
+            doc! { SET_OP: { "secret": secret } },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_webhook(&self, user_id: &str, id: &str) -> ServiceResult<()> {
+        let filter = doc! { "_id": id, "user_id": user_id };
+        self.delete_one(WEBHOOK_COLLECTION_NAME, filter).await?;
+        self.delete_many(WEBHOOK_DELIVERY_COLLECTION_NAME, doc! { "webhook_id": id })
+            .await?;
+        Ok(())
+    }
+
+    async fn create_webhook_delivery(&self, delivery: WebhookDelivery) -> ServiceResult<()> {
+        self.insert(WEBHOOK_DELIVERY_COLLECTION_NAME, &delivery)
+            .await
+    }
+
+    async fn get_webhook_deliveries(
+        &self,
+        webhook_id: &str,
+        limit: i64,
+    ) -> ServiceResult<Vec<WebhookDelivery>> {
+        let query = Query::new(doc! { "webhook_id": webhook_id })
+            .sort(doc! { "created_at": -1 })
+            .limit(limit);
+        self.find(WEBHOOK_DELIVERY_COLLECTION_NAME, query).await
+    }
+}
@@ -0,0 +1,92 @@
+use std::process::Stdio;
+
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    config::PdfConfig,
+    error::{ServiceError, ServiceResult},
+};
+
+// below this many characters per page on average the extraction is considered failed,
+// e.g. scanned documents without a text layer
+const MIN_CHARS_PER_PAGE: usize = 20;
+
+/// Extracts the text of a pdf page by page, with the builtin pure-Rust extractor
+/// and an optional external tool (`pdftotext`) as fallback.
+#[derive(Debug, Clone)]
+pub struct PdfTextExtractor {
+    external_extractor: Option<String>,
+}
+
+impl PdfTextExtractor {
+    pub fn new(config: &PdfConfig) -> Self {
+        PdfTextExtractor {
+            external_extractor: config.external_extractor.clone(),
+        }
+    }
+
+    /// Text of every page, in page order.
+    pub async fn extract(&self, bytes: Vec<u8>) -> ServiceResult<Vec<String>> {
+        let builtin = {
+            let bytes = bytes.clone();
+            tokio::task::spawn_blocking(move || extract_builtin(&bytes))
+                .await
+                .map_err(|e| ServiceError::PdfError(e.to_string()))?
+        };
+        let Some(tool) = &self.external_extractor else {
+            return builtin;
+        };
+        match builtin {
+            Ok(pages) if !is_near_empty(&pages) => Ok(pages),
+            Ok(_) => {
+                tracing::info!("Builtin pdf extraction is near empty, trying {}", tool);
+                extract_external(tool, &bytes).await
+            }
+            Err(e) => {
+                tracing::warn!("Builtin pdf extraction failed, trying {}: {}", tool, e);
+                extract_external(tool, &bytes).await
+            }
+        }
+    }
+}
+
+/// Whether the pages hold (almost) no text.
+pub fn is_near_empty(pages: &[String]) -> bool {
+    let chars = pages
+        .iter()
+        .map(|page| page.chars().filter(|c| !c.is_whitespace()).count())
+        .sum::<usize>();
+    chars < MIN_CHARS_PER_PAGE * pages.len().max(1)
+}
+
+fn extract_builtin(bytes: &[u8]) -> ServiceResult<Vec<String>> {
+    pdf_extract::extract_text_from_mem_by_pages(bytes)
+        .map_err(|e| ServiceError::PdfError(e.to_string()))
+}
+
+/// Run `pdftotext - -`, pages are separated by form feeds in its output.
+async fn extract_external(tool: &str, bytes: &[u8]) -> ServiceResult<Vec<String>> {
+    let mut child = tokio::process::Command::new(tool)
+        .args(["-enc", "UTF-8", "-", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(bytes).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(ServiceError::PdfError(format!(
+            "{} exited with {}",
+            tool, output.status
+        )));
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut pages = text.split('\u{c}').map(String::from).collect::<Vec<_>>();
+    // the output ends with a form feed after the last page
+    if pages.last().is_some_and(|page| page.trim().is_empty()) {
+        pages.pop();
+    }
+    Ok(pages)
+}
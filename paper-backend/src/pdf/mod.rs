@@ -0,0 +1,127 @@
+pub mod extract;
+pub mod ocr;
+pub mod references;
+pub mod thumbnail;
+
+use crate::{
+    app_data::AppDataRef,
+    citation::refresh_citations,
+    error::ServiceResult,
+    events::DomainEvent,
+    model::{
+        chunk::PaperChunkRepository,
+        page::{PaperPage, PaperPageRepository},
+        paper::{PaperRepository, Progress, TextStatus},
+    },
+    pdf::extract::is_near_empty,
+    utils::cache::CacheKey,
+};
+
+/// Background job run after upload: extract the text of every page of the file
+/// and store it for search, chunking and citation lookup. Scanned files without
+/// text layer go through OCR when enabled and `ocr` is not opted out.
+pub async fn run_extraction_job(
+    state: AppDataRef,
+    user_id: String,
+    paper_id: String,
+    bytes: Vec<u8>,
+    ocr: bool,
+) {
+    let status = match extract_paper_pages(&state, &paper_id, bytes, ocr).await {
+        Ok(page_count) => {
+            tracing::info!("Extracted {} pages of paper {}", page_count, paper_id);
+            (TextStatus::Ready, Some(page_count))
+        }
+        Err(e) => {
+            tracing::error!("Text extraction of paper {} failed: {}", paper_id, e);
+            (TextStatus::Failed, None)
+        }
+    };
+    if let Err(e) = state
+        .db
+        .set_paper_text_status(&paper_id, status.0, status.1)
+        .await
+    {
+        tracing::error!("Failed to update text status of paper {}: {}", paper_id, e);
+    }
+    state.invalidate(&[CacheKey::Paper(&paper_id)]).await;
+    state.events.publish(
+        &user_id,
+        DomainEvent::TextExtracted {
+            paper_id,
+            status: status.0,
+        },
+    );
+}
+
+async fn extract_paper_pages(
+    state: &AppDataRef,
+    paper_id: &str,
+    bytes: Vec<u8>,
+    ocr: bool,
+) -> ServiceResult<u32> {
+    let extracted = state.pdf_extractor.extract(bytes.clone()).await;
+    let pages = match (extracted, state.ocr.as_ref().filter(|_| ocr)) {
+        (Ok(pages), Some(engine)) if is_near_empty(&pages) => {
+            tracing::info!("No text layer in paper {}, running ocr", paper_id);
+            ocr_pages(state, engine, paper_id, &bytes).await?
+        }
+        (Err(e), Some(engine)) => {
+            tracing::warn!(
+                "Extraction of paper {} failed, running ocr: {}",
+                paper_id,
+                e
+            );
+            ocr_pages(state, engine, paper_id, &bytes).await?
+        }
+        (extracted, _) => extracted?,
+    };
+
+    // the references are a by-product, failing to get them does not fail the job
+    match state.db.get_paper_by_id(paper_id).await? {
+        Some(paper) => match refresh_citations(state, &paper, &pages).await {
+            Ok(count) => tracing::info!("Found {} references in paper {}", count, paper_id),
+            Err(e) => tracing::warn!("Citation extraction of paper {} failed: {}", paper_id, e),
+        },
+        None => tracing::warn!("Paper {} deleted during text extraction", paper_id),
+    }
+
+    let pages = pages
+        .into_iter()
+        .enumerate()
+        .map(|(i, text)| PaperPage::new(paper_id, i as u32 + 1, text))
+        .collect::<Vec<_>>();
+    let page_count = pages.len() as u32;
+    state.db.replace_paper_pages(paper_id, pages).await?;
+    // chunked again from the new text on the next question
+    state.db.replace_paper_chunks(paper_id, Vec::new()).await?;
+    Ok(page_count)
+}
+
+/// Recognize the pages one by one, reporting the progress on the paper.
+async fn ocr_pages(
+    state: &AppDataRef,
+    engine: &ocr::OcrEngine,
+    paper_id: &str,
+    bytes: &[u8],
+) -> ServiceResult<Vec<String>> {
+    let rendered = engine.render_pages(bytes).await?;
+    let total = rendered.pages.len() as u32;
+    state
+        .db
+        .set_paper_text_status(paper_id, TextStatus::Ocr, Some(total))
+        .await?;
+    state.invalidate(&[CacheKey::Paper(paper_id)]).await;
+
+    let mut pages = Vec::with_capacity(rendered.pages.len());
+    for (i, image) in rendered.pages.iter().enumerate() {
+        pages.push(engine.recognize(image).await?);
+        let progress = Progress {
+            done: i as u32 + 1,
+            total,
+        };
+        state.db.set_paper_ocr_progress(paper_id, progress).await?;
+        state.invalidate(&[CacheKey::Paper(paper_id)]).await;
+    }
+    Ok(pages)
+}
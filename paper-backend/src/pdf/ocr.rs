@@ -0,0 +1,101 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    config::OcrConfig,
+    error::{ServiceError, ServiceResult},
+};
+
+/// OCR of scanned pdfs: pages are rendered to images with `pdftoppm` (poppler)
+/// and recognized with `tesseract`.
+#[derive(Debug, Clone)]
+pub struct OcrEngine {
+    tesseract: String,
+    pdftoppm: String,
+    languages: String,
+    dpi: u32,
+}
+
+/// Page images of a pdf in a scratch directory, removed on drop.
+pub struct RenderedPages {
+    dir: PathBuf,
+    pub pages: Vec<PathBuf>,
+}
+
+impl Drop for RenderedPages {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            tracing::warn!("Failed to remove ocr scratch dir {:?}: {}", self.dir, e);
+        }
+    }
+}
+
+impl OcrEngine {
+    pub fn new(config: &OcrConfig) -> Self {
+        OcrEngine {
+            tesseract: config.tesseract.clone().unwrap_or("tesseract".to_string()),
+            pdftoppm: config.pdftoppm.clone().unwrap_or("pdftoppm".to_string()),
+            languages: if config.languages.is_empty() {
+                "eng".to_string()
+            } else {
+                config.languages.join("+")
+            },
+            dpi: config.dpi.unwrap_or(300),
+        }
+    }
+
+    /// Render every page of the pdf to a png image, in page order.
+    pub async fn render_pages(&self, bytes: &[u8]) -> ServiceResult<RenderedPages> {
+        let dir = std::env::temp_dir().join(format!("paper-ocr-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await?;
+        let mut rendered = RenderedPages {
+            dir: dir.clone(),
+            pages: Vec::new(),
+        };
+
+        let input = dir.join("input.pdf");
+        tokio::fs::write(&input, bytes).await?;
+        let output = tokio::process::Command::new(&self.pdftoppm)
+            .arg("-r")
+            .arg(self.dpi.to_string())
+            .arg("-png")
+            .arg(&input)
+            .arg(dir.join("page"))
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(ServiceError::PdfError(format!(
+                "{} exited with {}",
+                self.pdftoppm, output.status
+            )));
+        }
+
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "png") {
+                rendered.pages.push(path);
+            }
+        }
+        // `page-01.png`, `page-02.png`... zero padded, so sorting by name is page order
+        rendered.pages.sort();
+        Ok(rendered)
+    }
+
+    /// Recognize the text of a page image.
+    pub async fn recognize(&self, image: &Path) -> ServiceResult<String> {
+        let output = tokio::process::Command::new(&self.tesseract)
+            .arg(image)
+            .arg("stdout")
+            .arg("-l")
+            .arg(&self.languages)
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(ServiceError::PdfError(format!(
+                "{} exited with {}",
+                self.tesseract, output.status
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
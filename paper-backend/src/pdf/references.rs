@@ -0,0 +1,109 @@
+use crate::dedup::normalize_title;
+
+// headings starting the reference list, compared on the normalized line
+const REFERENCE_HEADINGS: &[&str] = &["references", "bibliography", "works cited", "参考文献"];
+// reference entries shorter than this are noise (page numbers, headers...)
+const MIN_REFERENCE_CHARS: usize = 20;
+
+/// A reference of a paper, as found in its reference list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedReference {
+    pub raw: String,
+    pub doi: Option<String>,
+}
+
+/// Find the reference list in the page texts and split it into entries.
+pub fn parse_references(pages: &[String]) -> Vec<ParsedReference> {
+    let lines = pages
+        .iter()
+        .flat_map(|page| page.lines())
+        .map(str::trim)
+        .collect::<Vec<_>>();
+    // the last heading wins, the word may appear in the body text as well
+    let Some(start) = lines
+        .iter()
+        .rposition(|line| REFERENCE_HEADINGS.contains(&normalize_title(line).as_str()))
+    else {
+        return Vec::new();
+    };
+
+    let mut entries: Vec<String> = Vec::new();
+    for line in &lines[start + 1..] {
+        if line.is_empty() {
+            continue;
+        }
+        match entries.last_mut() {
+            Some(entry) if !starts_entry(line) => {
+                // words hyphenated at the end of the line are joined back
+                if entry.ends_with('-') {
+                    entry.pop();
+                } else {
+                    entry.push(' ');
+                }
+                entry.push_str(line);
+            }
+            _ => entries.push(line.to_string()),
+        }
+    }
+    entries
+        .into_iter()
+        .filter(|entry| entry.chars().count() >= MIN_REFERENCE_CHARS)
+        .map(|raw| ParsedReference {
+            doi: find_doi(&raw),
+            raw,
+        })
+        .collect()
+}
+
+/// Whether the line starts a new entry: `[12]`, `12.` or `12 `.
+fn starts_entry(line: &str) -> bool {
+    let rest = line.strip_prefix('[').unwrap_or(line);
+    let digits = rest.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 || digits > 3 {
+        return false;
+    }
+    matches!(
+        rest[digits..].chars().next(),
+        Some(']') | Some('.') | Some(' ')
+    )
+}
+
+/// First DOI (`10.xxxx/...`) in the text, lowercased.
+pub fn find_doi(text: &str) -> Option<String> {
+    text.match_indices("10.").find_map(|(start, _)| {
+        let candidate = text[start..]
+            .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+            .next()?
+            .trim_end_matches(['.', ')', ']']);
+        let (prefix, suffix) = candidate.split_once('/')?;
+        let registrant = &prefix[3..];
+        if registrant.len() < 4
+            || suffix.is_empty()
+            || !registrant.chars().all(|c| c.is_ascii_digit())
+        {
+            return None;
+        }
+        Some(candidate.to_lowercase())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_references() {
+        let pages = vec![
+            "Introduction\nWe build on prior references.".to_string(),
+            "References\n[1] A. Vaswani et al. Attention is all you need.\nIn NeurIPS, 2017. doi:10.48550/arXiv.1706.03762.\n[2] K. He et al. Deep residual learn-\ning for image recognition. In CVPR, 2016.\n3".to_string(),
+        ];
+        let references = parse_references(&pages);
+        assert_eq!(references.len(), 2);
+        assert_eq!(
+            references[0].doi.as_deref(),
+            Some("10.48550/arxiv.1706.03762")
+        );
+        assert!(references[1].raw.contains("Deep residual learning"));
+        assert_eq!(references[1].doi, None);
+    }
+}
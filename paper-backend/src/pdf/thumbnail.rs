@@ -0,0 +1,158 @@
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_data::AppDataRef,
+    config::ThumbnailConfig,
+    error::{ServiceError, ServiceResult},
+    model::blob::{BlobRepository, paper_thumbnail_key},
+};
+
+/// Sizes of the thumbnails rendered for every uploaded file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl ThumbnailSize {
+    pub const ALL: [ThumbnailSize; 3] = [
+        ThumbnailSize::Small,
+        ThumbnailSize::Medium,
+        ThumbnailSize::Large,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ThumbnailSize::Small => "small",
+            ThumbnailSize::Medium => "medium",
+            ThumbnailSize::Large => "large",
+        }
+    }
+
+    /// Width in pixels, the height follows the ratio of the page.
+    pub fn width(self) -> u32 {
+        match self {
+            ThumbnailSize::Small => 160,
+            ThumbnailSize::Medium => 320,
+            ThumbnailSize::Large => 640,
+        }
+    }
+}
+
+/// Renders the first page of pdfs to png with `pdftoppm` (poppler).
+#[derive(Debug, Clone)]
+pub struct ThumbnailRenderer {
+    pdftoppm: String,
+}
+
+impl ThumbnailRenderer {
+    pub fn new(config: &ThumbnailConfig) -> Self {
+        ThumbnailRenderer {
+            pdftoppm: config.pdftoppm.clone().unwrap_or("pdftoppm".to_string()),
+        }
+    }
+
+    /// The png of the first page of the pdf in every size.
+    pub async fn render(&self, bytes: &[u8]) -> ServiceResult<Vec<(ThumbnailSize, Vec<u8>)>> {
+        let dir = std::env::temp_dir().join(format!("paper-thumbnail-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await?;
+        let rendered = self.render_in(&dir, bytes).await;
+        if let Err(e) = tokio::fs::remove_dir_all(&dir).await {
+            tracing::warn!("Failed to remove thumbnail scratch dir {:?}: {}", dir, e);
+        }
+        rendered
+    }
+
+    async fn render_in(
+        &self,
+        dir: &std::path::Path,
+        bytes: &[u8],
+    ) -> ServiceResult<Vec<(ThumbnailSize, Vec<u8>)>> {
+        let input = dir.join("input.pdf");
+        tokio::fs::write(&input, bytes).await?;
+        let mut thumbnails = Vec::with_capacity(ThumbnailSize::ALL.len());
+        for size in ThumbnailSize::ALL {
+            let output = dir.join(size.as_str());
+            let status = tokio::process::Command::new(&self.pdftoppm)
+                .args(["-f", "1", "-l", "1", "-singlefile", "-png"])
+                .arg("-scale-to-x")
+                .arg(size.width().to_string())
+                .args(["-scale-to-y", "-1"])
+                .arg(&input)
+                .arg(&output)
+                .status()
+                .await?;
+            if !status.success() {
+                return Err(ServiceError::PdfError(format!(
+                    "{} exited with {}",
+                    self.pdftoppm, status
+                )));
+            }
+            // `-singlefile` writes `{output}.png`
+            let png = tokio::fs::read(output.with_extension("png")).await?;
+            thumbnails.push((size, png));
+        }
+        Ok(thumbnails)
+    }
+}
+
+/// Background job run after upload: store the thumbnails of the first page of
+/// the file. Without thumbnail the file is only shown without preview.
+pub async fn run_thumbnail_job(state: AppDataRef, paper_id: String, bytes: Vec<u8>) {
+    let Some(renderer) = state.thumbnails.as_ref() else {
+        return;
+    };
+    match store_thumbnails(&state, renderer, &paper_id, &bytes).await {
+        Ok(()) => tracing::info!("Rendered the thumbnails of paper {}", paper_id),
+        Err(e) => {
+            tracing::error!("Thumbnails of paper {} failed: {}", paper_id, e);
+            // the ones of the previous file would not match the file anymore
+            if let Err(e) = delete_thumbnails(&state, &paper_id).await {
+                tracing::warn!("Failed to delete thumbnails of paper {}: {}", paper_id, e);
+            }
+        }
+    }
+}
+
+async fn store_thumbnails(
+    state: &AppDataRef,
+    renderer: &ThumbnailRenderer,
+    paper_id: &str,
+    bytes: &[u8],
+) -> ServiceResult<()> {
+    for (size, png) in renderer.render(bytes).await? {
+        state
+            .db
+            .put_blob(&paper_thumbnail_key(paper_id, size), &png)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn delete_thumbnails(state: &AppDataRef, paper_id: &str) -> ServiceResult<()> {
+    for size in ThumbnailSize::ALL {
+        state
+            .db
+            .delete_blob(&paper_thumbnail_key(paper_id, size))
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_size() {
+        for size in ThumbnailSize::ALL {
+            let name = serde_json::to_value(size).unwrap();
+            assert_eq!(name, size.as_str());
+        }
+        assert!(ThumbnailSize::Small.width() < ThumbnailSize::default().width());
+    }
+}
@@ -0,0 +1,334 @@
+use std::{collections::HashSet, time::Duration};
+
+use ai_flow_synth::llm::model::ChatMessage;
+
+use crate::{
+    app_data::AppDataRef,
+    embedding::cosine_similarity,
+    error::{ServiceError, ServiceResult},
+    llm::{
+        LlmClient,
+        prompt::{PAPER_ASK_PROMPT, render_prompt},
+    },
+    model::{
+        chunk::{
+            ChunkClaim, PaperChunk, PaperChunkRepository,
+            schema::{AskPaperResponse, QuoteResponse},
+        },
+        page::{PaperPage, PaperPageRepository},
+        paper::{Paper, TextStatus},
+        usage::UsageEvent,
+    },
+    resilience::record_usage,
+};
+
+// usage feature of the questions asked about a paper
+const ASK_FEATURE: &str = "ask";
+const CHUNK_CHARS: usize = 1200;
+// chunks most relevant to the question given to the model
+const CONTEXT_CHUNKS: usize = 6;
+const MAX_QUOTES: usize = 5;
+// marks the end of the answer and the start of the quotes in the model output
+const QUOTES_MARKER: &str = "QUOTES:";
+// how long to wait for the chunks computed by a concurrent request
+const CLAIM_WAIT: Duration = Duration::from_secs(1);
+const CLAIM_WAIT_ATTEMPTS: u32 = 30;
+
+/// Split the text of the pages in passages of about `CHUNK_CHARS`, on word
+/// boundaries and never across pages, as (page, text).
+fn chunk_pages(pages: &[PaperPage]) -> Vec<(u32, String)> {
+    let mut chunks = Vec::new();
+    for page in pages {
+        let mut current = String::new();
+        for word in page.text.split_whitespace() {
+            if !current.is_empty() && current.len() + word.len() + 1 > CHUNK_CHARS {
+                chunks.push((page.page, std::mem::take(&mut current)));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+        if !current.is_empty() {
+            chunks.push((page.page, current));
+        }
+    }
+    chunks
+}
+
+fn terms(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| term.chars().count() > 2)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Share of the terms of the question found in the text, when no embedder is
+/// configured.
+fn term_overlap(question: &HashSet<String>, text: &str) -> f32 {
+    if question.is_empty() {
+        return 0.0;
+    }
+    let text = terms(text);
+    question.iter().filter(|term| text.contains(*term)).count() as f32 / question.len() as f32
+}
+
+/// The `count` best scored chunks, in reading order.
+fn top_chunks(chunks: &[PaperChunk], scores: &[f32], count: usize) -> Vec<PaperChunk> {
+    let mut ranked = chunks.iter().zip(scores).collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1.total_cmp(a.1));
+    let mut top = ranked
+        .into_iter()
+        .take(count)
+        .map(|(chunk, _)| chunk.clone())
+        .collect::<Vec<_>>();
+    top.sort_by_key(|chunk| chunk.index);
+    top
+}
+
+/// The answer and its quotes from the output of the model. Quotes refer to the
+/// excerpts by number, only those found verbatim in the excerpt are kept.
+fn parse_answer(output: &str, excerpts: &[PaperChunk]) -> (String, Vec<QuoteResponse>) {
+    let (answer, quote_lines) = output.split_once(QUOTES_MARKER).unwrap_or((output, ""));
+    let mut quotes: Vec<QuoteResponse> = Vec::new();
+    for line in quote_lines.lines() {
+        let line = line.trim().trim_start_matches(['-', '*', ' ']);
+        let Some((number, quote)) = line.strip_prefix('[').and_then(|l| l.split_once(']')) else {
+            continue;
+        };
+        let Some(excerpt) = number
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| excerpts.get(n.checked_sub(1)?))
+        else {
+            continue;
+        };
+        let quote = quote
+            .trim()
+            .trim_matches(['"', '\u{201c}', '\u{201d}'])
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if quote.is_empty()
+            || !excerpt.text.to_lowercase().contains(&quote.to_lowercase())
+            || quotes.iter().any(|q| q.text == quote)
+        {
+            continue;
+        }
+        quotes.push(QuoteResponse {
+            page: excerpt.page,
+            text: quote,
+        });
+        if quotes.len() == MAX_QUOTES {
+            break;
+        }
+    }
+    (answer.trim().to_string(), quotes)
+}
+
+/// Whether the stored chunks are those of the file, embedded when an embedder is
+/// configured.
+fn is_fresh(state: &AppDataRef, chunks: &[PaperChunk], file_hash: &str) -> bool {
+    chunks.first().is_some_and(|chunk| {
+        chunk.file_hash == file_hash && (state.embedder.is_none() || !chunk.vector.is_empty())
+    })
+}
+
+/// The chunks of the extracted text of the paper, computed and embedded once per
+/// uploaded file, then served from the database. Only the request holding the
+/// claim computes them, the concurrent ones wait for its chunks.
+async fn paper_chunks(
+    state: &AppDataRef,
+    paper: &Paper,
+    file_hash: &str,
+) -> ServiceResult<Vec<PaperChunk>> {
+    let chunks = state.db.get_paper_chunks(&paper.id).await?;
+    if is_fresh(state, &chunks, file_hash) {
+        return Ok(chunks);
+    }
+
+    if !state
+        .db
+        .claim_paper_chunks(ChunkClaim::new(&paper.id, file_hash))
+        .await?
+    {
+        for _ in 0..CLAIM_WAIT_ATTEMPTS {
+            tokio::time::sleep(CLAIM_WAIT).await;
+            let chunks = state.db.get_paper_chunks(&paper.id).await?;
+            if is_fresh(state, &chunks, file_hash) {
+                return Ok(chunks);
+            }
+        }
+        return Err(ServiceError::VersionConflict(format!(
+            "Passages of paper {} are being computed",
+            paper.id
+        )));
+    }
+    let chunks = compute_chunks(state, paper, file_hash).await;
+    if let Err(e) = state.db.release_paper_chunks(&paper.id).await {
+        tracing::warn!("Failed to release the chunks of paper {}: {}", paper.id, e);
+    }
+    chunks
+}
+
+/// Split, embed and store the chunks of the paper.
+async fn compute_chunks(
+    state: &AppDataRef,
+    paper: &Paper,
+    file_hash: &str,
+) -> ServiceResult<Vec<PaperChunk>> {
+    let pages = state.db.get_paper_pages(&paper.id, None).await?;
+    let mut chunks = chunk_pages(&pages)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (page, text))| PaperChunk::new(&paper.id, file_hash, i as u32, page, text))
+        .collect::<Vec<_>>();
+    if let (Some(embedder), false) = (&state.embedder, chunks.is_empty()) {
+        let texts = chunks.iter().map(|c| c.text.clone()).collect::<Vec<_>>();
+        let vectors = embedder.embed(&texts).await?;
+        for (chunk, vector) in chunks.iter_mut().zip(vectors) {
+            chunk.vector = vector;
+        }
+    }
+    state
+        .db
+        .replace_paper_chunks(&paper.id, chunks.clone())
+        .await?;
+    tracing::info!("Chunked paper {} in {} passages", paper.id, chunks.len());
+    Ok(chunks)
+}
+
+/// Answer the question from the passages of the extracted text of the paper most
+/// relevant to it, with the quotes of the paper supporting the answer.
+pub async fn ask_paper(
+    state: &AppDataRef,
+    paper: &Paper,
+    question: &str,
+    model: &str,
+) -> ServiceResult<AskPaperResponse> {
+    let not_found = || ServiceError::NotFound(format!("Text of paper {}", paper.id));
+    let file_hash = paper
+        .file_hash
+        .as_deref()
+        .filter(|_| paper.text_status == Some(TextStatus::Ready))
+        .ok_or_else(not_found)?;
+    let chunks = paper_chunks(state, paper, file_hash).await?;
+    if chunks.is_empty() {
+        return Err(not_found());
+    }
+
+    let scores = match &state.embedder {
+        Some(embedder) if chunks.iter().all(|c| !c.vector.is_empty()) => {
+            let vector = embedder
+                .embed(&[question.to_string()])
+                .await?
+                .into_iter()
+                .next()
+                .unwrap_or_default();
+            chunks
+                .iter()
+                .map(|c| cosine_similarity(&vector, &c.vector))
+                .collect::<Vec<_>>()
+        }
+        _ => {
+            let terms = terms(question);
+            chunks
+                .iter()
+                .map(|c| term_overlap(&terms, &c.text))
+                .collect()
+        }
+    };
+    let excerpts = top_chunks(&chunks, &scores, CONTEXT_CHUNKS);
+    let excerpt_list = excerpts
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| format!("[{}] (page {}) {}", i + 1, chunk.page, chunk.text))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let prompt = render_prompt(
+        &state.db,
+        PAPER_ASK_PROMPT,
+        None,
+        &[
+            ("title", paper.title.as_str()),
+            ("excerpts", excerpt_list.as_str()),
+            ("question", question),
+        ],
+    )
+    .await;
+    let messages = vec![
+        ChatMessage::system("You answer questions about an academic paper from its text only."),
+        ChatMessage::user(prompt),
+    ];
+    let api_key = state.user_llm_key(&paper.user_id, model).await?;
+    let output = state
+        .llm
+        .complete_with_key(Some(model), &messages, api_key.as_deref())
+        .await?;
+    let (input_tokens, output_tokens) = LlmClient::estimate_usage(&messages, &output);
+    let mut usage = UsageEvent::new(
+        &paper.user_id,
+        model,
+        ASK_FEATURE,
+        input_tokens,
+        output_tokens,
+    );
+    usage.own_key = api_key.is_some();
+    record_usage(state, usage).await;
+
+    let (answer, quotes) = parse_answer(&output, &excerpts);
+    Ok(AskPaperResponse {
+        answer,
+        quotes,
+        model: model.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_pages() {
+        let pages = vec![
+            PaperPage::new("paper", 1, "word ".repeat(500)),
+            PaperPage::new("paper", 2, "  short\n page ".to_string()),
+        ];
+        let chunks = chunk_pages(&pages);
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks.iter().all(|(_, text)| text.len() <= CHUNK_CHARS));
+        assert_eq!(chunks[3], (2, "short page".to_string()));
+    }
+
+    #[test]
+    fn test_parse_answer() {
+        let excerpts = vec![
+            PaperChunk::new(
+                "paper",
+                "hash",
+                0,
+                3,
+                "We evaluate on the WMT 2014 English-German dataset.".to_string(),
+            ),
+            PaperChunk::new(
+                "paper",
+                "hash",
+                4,
+                7,
+                "Training took 3.5 days on 8 GPUs.".to_string(),
+            ),
+        ];
+        let output = "The model is evaluated on WMT 2014 [1].\n\nQUOTES:\n\
+            [1] \"We evaluate on the WMT 2014   English-German dataset.\"\n\
+            - [2] Training took a week.\n[3] Unknown excerpt.";
+        let (answer, quotes) = parse_answer(output, &excerpts);
+        assert_eq!(answer, "The model is evaluated on WMT 2014 [1].");
+        assert_eq!(quotes.len(), 1);
+        assert_eq!(quotes[0].page, 3);
+        assert_eq!(
+            quotes[0].text,
+            "We evaluate on the WMT 2014 English-German dataset."
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use crate::{
+    app_data::AppData,
+    config::LoginLimit,
+    error::{ServiceError, ServiceResult},
+    i18n::{LOGIN_LOCKED_BODY, LOGIN_LOCKED_SUBJECT, Locale, default_locale},
+    model::{
+        audit::{AuditAction, AuditLog, AuditLogRepository},
+        login_attempt::{
+            LoginAttempt, LoginAttemptRepository, account_attempt_key, ip_attempt_key,
+        },
+        txn::TxnContext,
+        user::User,
+    },
+    utils::mailer::Mail,
+};
+
+impl LoginLimit {
+    /// Seconds to wait after the last of `failures` failures of an account.
+    fn delay(&self, failures: i64) -> u64 {
+        let delayed = failures - i64::from(self.free_failures);
+        if delayed <= 0 {
+            return 0;
+        }
+        let doublings = (delayed - 1).min(32) as u32;
+        self.delay_secs
+            .saturating_mul(1 << doublings)
+            .min(self.max_delay_secs)
+    }
+}
+
+fn after_secs(at: bson::DateTime, secs: u64) -> bson::DateTime {
+    bson::DateTime::from_millis(at.timestamp_millis().saturating_add(secs as i64 * 1000))
+}
+
+/// Whole seconds until then, at least one.
+fn secs_until(at: bson::DateTime, now: bson::DateTime) -> u64 {
+    let millis = (at.timestamp_millis() - now.timestamp_millis()).max(0) as u64;
+    millis.div_ceil(1000).max(1)
+}
+
+/// Why a login is refused, given the attempts to the account and from the ip
+/// before it: either is locked, or the account waits out the delay of its last
+/// attempt. The ips are not delayed, the users behind a shared one would be.
+fn refusal(
+    limit: &LoginLimit,
+    account: Option<&LoginAttempt>,
+    from_ip: Option<&LoginAttempt>,
+    now: bson::DateTime,
+) -> Option<ServiceError> {
+    for attempt in account.iter().chain(from_ip.iter()) {
+        if let Some(until) = attempt.locked_until.filter(|until| *until > now) {
+            return Some(ServiceError::LoginLocked(secs_until(until, now)));
+        }
+    }
+    let account = account?;
+    let allowed_at = after_secs(account.last_failed_at, limit.delay(account.failures));
+    (allowed_at > now).then(|| ServiceError::RateLimited(secs_until(allowed_at, now)))
+}
+
+/// Count a login to the account from the ip as failed before its password is
+/// checked, so the logins tried at once each wait for the ones before them,
+/// and refuse it while either is locked or delayed. A refused login is not
+/// counted, but the delay runs again from it.
+pub async fn reserve_login(state: &AppData, email: &str, ip: &str) -> ServiceResult<()> {
+    if !state.rate_limiter.enabled() {
+        return Ok(());
+    }
+    let limit = state.rate_limiter.login_limit();
+    let now = bson::DateTime::now();
+    let (account_key, ip_key) = (account_attempt_key(email), ip_attempt_key(ip));
+    let (account, from_ip) = tokio::try_join!(
+        state.db.reserve_login_attempt(&account_key),
+        state.db.reserve_login_attempt(&ip_key),
+    )?;
+    if let Some(refused) = refusal(&limit, account.as_ref(), from_ip.as_ref(), now) {
+        tokio::try_join!(
+            state.db.release_login_attempt(&account_key),
+            state.db.release_login_attempt(&ip_key),
+        )?;
+        return Err(refused);
+    }
+    Ok(())
+}
+
+/// The reserved login to the account from the ip failed, lock either past its
+/// limit. The owner of the account, when it exists, is told by email.
+pub async fn login_failed(
+    state: &AppData,
+    email: &str,
+    ip: &str,
+    user: Option<&User>,
+) -> ServiceResult<()> {
+    if !state.rate_limiter.enabled() {
+        return Ok(());
+    }
+    let limit = state.rate_limiter.login_limit();
+    let until = after_secs(bson::DateTime::now(), limit.lock_secs);
+
+    let account = state
+        .db
+        .record_login_failure(&account_attempt_key(email))
+        .await?;
+    // unlocked by an operator meanwhile when gone
+    if let Some(account) = account.filter(|a| a.failures >= i64::from(limit.lock_after)) {
+        state.db.lock_login(&account.id, until).await?;
+        tracing::warn!(
+            "Locked the logins to {} after {} failures",
+            email,
+            account.failures
+        );
+        if let Some(user) = user {
+            notify_locked(state, user, email, ip, limit.lock_secs).await;
+        }
+    }
+
+    let from_ip = state.db.record_login_failure(&ip_attempt_key(ip)).await?;
+    if let Some(from_ip) = from_ip.filter(|a| a.failures >= i64::from(limit.ip_lock_after)) {
+        state.db.lock_login(&from_ip.id, until).await?;
+        tracing::warn!(
+            "Locked the logins from {} after {} failures",
+            ip,
+            from_ip.failures
+        );
+    }
+    Ok(())
+}
+
+/// The reserved login succeeded: forget the failed logins to the account, the
+/// ip keeps the count of its failures.
+pub async fn login_succeeded(state: &AppData, email: &str, ip: &str) -> ServiceResult<()> {
+    if !state.rate_limiter.enabled() {
+        return Ok(());
+    }
+    tokio::try_join!(
+        state.db.clear_login_attempt(&account_attempt_key(email)),
+        state.db.release_login_attempt(&ip_attempt_key(ip)),
+    )?;
+    Ok(())
+}
+
+// the login is refused either way, a failure to tell is only logged
+async fn notify_locked(state: &AppData, user: &User, email: &str, ip: &str, lock_secs: u64) {
+    let locale = Locale::of_user(user, default_locale(&state.settings_config));
+    let minutes = lock_secs.div_ceil(60).to_string();
+    let mail = Mail {
+        to: email.to_string(),
+        subject: locale.text(LOGIN_LOCKED_SUBJECT).to_string(),
+        body: locale.render(LOGIN_LOCKED_BODY, &[("ip", ip), ("minutes", &minutes)]),
+    };
+    if let Err(e) = state.mailer.send(mail).await {
+        tracing::warn!("Failed to send the lock email to {}: {}", email, e);
+    }
+    let log = AuditLog::new(&user.uid, AuditAction::LoginLocked, Some(ip.to_string()));
+    if let Err(e) = state
+        .db
+        .create_audit_log(&mut TxnContext::none(), log)
+        .await
+    {
+        tracing::warn!("Failed to audit the lock of {}: {}", user.uid, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_login_delay() {
+        let limit = LoginLimit::default();
+        assert_eq!(limit.delay(0), 0);
+        assert_eq!(limit.delay(3), 0);
+        assert_eq!(limit.delay(4), 2);
+        assert_eq!(limit.delay(5), 4);
+        assert_eq!(limit.delay(7), 16);
+        assert_eq!(limit.delay(9), 60);
+        assert_eq!(limit.delay(1_000), 60);
+
+        let now = bson::DateTime::from_millis(10_000);
+        assert_eq!(secs_until(after_secs(now, 2), now), 2);
+        assert_eq!(secs_until(bson::DateTime::from_millis(10_001), now), 1);
+    }
+
+    #[test]
+    fn test_login_refusal() {
+        let limit = LoginLimit::default();
+        let now = bson::DateTime::from_millis(100_000);
+        let attempt = |failures, last_failed_at, locked_until| LoginAttempt {
+            id: "account:a@b.c".to_string(),
+            failures,
+            last_failed_at: bson::DateTime::from_millis(last_failed_at),
+            locked_until: locked_until.map(bson::DateTime::from_millis),
+        };
+        assert!(refusal(&limit, None, None, now).is_none());
+        // free failures, then the logins tried at once wait for each other
+        assert!(refusal(&limit, Some(&attempt(3, 100_000, None)), None, now).is_none());
+        assert!(matches!(
+            refusal(&limit, Some(&attempt(4, 99_000, None)), None, now),
+            Some(ServiceError::RateLimited(1))
+        ));
+        assert!(refusal(&limit, Some(&attempt(4, 98_000, None)), None, now).is_none());
+        assert!(matches!(
+            refusal(&limit, None, Some(&attempt(0, 0, Some(160_000))), now),
+            Some(ServiceError::LoginLocked(60))
+        ));
+        assert!(refusal(&limit, None, Some(&attempt(0, 0, Some(90_000))), now).is_none());
+    }
+}
@@ -0,0 +1,454 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use salvo::{
+    Depot, FlowCtrl, Request, Response,
+    http::header::HeaderValue,
+    prelude::{JwtAuthDepotExt, JwtAuthState},
+};
+
+use crate::{
+    app_data::AppDataRef,
+    config::{LoginLimit, RateLimit, RateLimitConfig},
+    error::{ServiceError, ServiceResult},
+    utils::jwt::JwtClaims,
+};
+
+pub mod login;
+
+pub const LIMIT_HEADER: &str = "x-ratelimit-limit";
+pub const REMAINING_HEADER: &str = "x-ratelimit-remaining";
+pub const RESET_HEADER: &str = "x-ratelimit-reset";
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+// idle buckets are dropped beyond, they refill to full anyway
+const MAX_MEMORY_BUCKETS: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitScope {
+    Global,
+    Auth,
+    Ai,
+}
+
+impl LimitScope {
+    fn name(&self) -> &'static str {
+        match self {
+            LimitScope::Global => "global",
+            LimitScope::Auth => "auth",
+            LimitScope::Ai => "ai",
+        }
+    }
+}
+
+impl RateLimit {
+    fn tokens_per_sec(&self) -> f64 {
+        f64::from(self.per_minute.max(1)) / 60.0
+    }
+
+    /// Tokens in the bucket after `elapsed` seconds of refill.
+    fn refill(&self, tokens: f64, elapsed: f64) -> f64 {
+        (tokens + elapsed * self.tokens_per_sec()).min(f64::from(self.burst))
+    }
+}
+
+/// Outcome of taking a token from a bucket.
+#[derive(Debug, Clone, Copy)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    // seconds until the bucket is full again
+    pub reset: u64,
+    // seconds until the next token, when not allowed
+    pub retry_after: u64,
+}
+
+impl Decision {
+    fn new(limit: &RateLimit, tokens: f64, allowed: bool) -> Self {
+        let rate = limit.tokens_per_sec();
+        Decision {
+            allowed,
+            limit: limit.burst,
+            remaining: tokens.max(0.0).floor() as u32,
+            reset: ((f64::from(limit.burst) - tokens).max(0.0) / rate).ceil() as u64,
+            retry_after: if allowed {
+                0
+            } else {
+                ((1.0 - tokens) / rate).ceil().max(1.0) as u64
+            },
+        }
+    }
+
+    fn allow_all(limit: &RateLimit) -> Self {
+        Decision::new(limit, f64::from(limit.burst), true)
+    }
+}
+
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync + std::fmt::Debug {
+    /// Take a token from the bucket of the key, refilled at the rate of the limit.
+    async fn take(&self, key: &str, limit: &RateLimit) -> ServiceResult<Decision>;
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    counted_at: Instant,
+    full_at: Instant,
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn take(&self, key: &str, limit: &RateLimit) -> ServiceResult<Decision> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if buckets.len() >= MAX_MEMORY_BUCKETS && !buckets.contains_key(key) {
+            buckets.retain(|_, bucket| bucket.full_at > now);
+        }
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: f64::from(limit.burst),
+            counted_at: now,
+            full_at: now,
+        });
+        let refilled = limit.refill(
+            bucket.tokens,
+            now.duration_since(bucket.counted_at).as_secs_f64(),
+        );
+        let allowed = refilled >= 1.0;
+        bucket.tokens = if allowed { refilled - 1.0 } else { refilled };
+        let decision = Decision::new(limit, bucket.tokens, allowed);
+        bucket.counted_at = now;
+        bucket.full_at = now + Duration::from_secs(decision.reset);
+        Ok(decision)
+    }
+}
+
+/// Buckets shared by every instance, updated atomically by a script.
+#[cfg(feature = "redis")]
+pub struct RedisStore {
+    connection: redis::aio::ConnectionManager,
+    script: redis::Script,
+}
+
+#[cfg(feature = "redis")]
+impl std::fmt::Debug for RedisStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisStore").finish()
+    }
+}
+
+#[cfg(feature = "redis")]
+const TAKE_SCRIPT: &str = r#"
+local burst = tonumber(ARGV[1])
+local per_ms = tonumber(ARGV[2]) / 60000
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'at')
+local tokens = tonumber(bucket[1]) or burst
+local at = tonumber(bucket[2]) or now
+tokens = math.min(burst, tokens + math.max(0, now - at) * per_ms)
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'at', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil((burst - tokens) / per_ms) + 1000)
+return {allowed, tostring(tokens)}
+"#;
+
+#[cfg(feature = "redis")]
+impl RedisStore {
+    pub async fn new(url: &str) -> ServiceResult<Self> {
+        let client = redis::Client::open(url)
+            .map_err(|e| ServiceError::InternalServerError(format!("Invalid redis url: {}", e)))?;
+        let connection = redis::aio::ConnectionManager::new(client)
+            .await
+            .map_err(|e| ServiceError::InternalServerError(format!("Redis error: {}", e)))?;
+        Ok(RedisStore {
+            connection,
+            script: redis::Script::new(TAKE_SCRIPT),
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait::async_trait]
+impl RateLimitStore for RedisStore {
+    async fn take(&self, key: &str, limit: &RateLimit) -> ServiceResult<Decision> {
+        let mut connection = self.connection.clone();
+        let (allowed, tokens): (i64, String) = self
+            .script
+            .key(format!("ratelimit:{}", key))
+            .arg(limit.burst)
+            .arg(limit.per_minute.max(1))
+            .invoke_async(&mut connection)
+            .await
+            .map_err(|e| ServiceError::InternalServerError(format!("Redis error: {}", e)))?;
+        let tokens = tokens.parse::<f64>().unwrap_or(0.0);
+        Ok(Decision::new(limit, tokens, allowed == 1))
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    // the limits are reloaded with the config file, the store is kept
+    config: RwLock<RateLimitConfig>,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl RateLimiter {
+    pub async fn new(config: &RateLimitConfig) -> Self {
+        let store: Arc<dyn RateLimitStore> = match &config.redis_url {
+            #[cfg(feature = "redis")]
+            Some(url) => match RedisStore::new(url).await {
+                Ok(store) => Arc::new(store),
+                Err(e) => {
+                    tracing::error!("Failed to connect to redis, limiting in memory: {}", e);
+                    Arc::new(MemoryStore::default())
+                }
+            },
+            #[cfg(not(feature = "redis"))]
+            Some(_) => {
+                tracing::error!(
+                    "`rate_limit_config.redis_url` needs the `redis` feature, limiting in memory"
+                );
+                Arc::new(MemoryStore::default())
+            }
+            None => Arc::new(MemoryStore::default()),
+        };
+        RateLimiter {
+            config: RwLock::new(config.clone()),
+            store,
+        }
+    }
+
+    /// Apply the limits of a reloaded config, a change of `redis_url` needs a restart.
+    pub fn set_config(&self, config: &RateLimitConfig) {
+        let mut current = self.config.write().unwrap_or_else(|e| e.into_inner());
+        if current.redis_url != config.redis_url {
+            tracing::warn!("The rate limit store changes on restart only");
+        }
+        *current = RateLimitConfig {
+            redis_url: current.redis_url.clone(),
+            ..config.clone()
+        };
+    }
+
+    pub fn enabled(&self) -> bool {
+        let config = self.config.read().unwrap_or_else(|e| e.into_inner());
+        config.enabled
+    }
+
+    fn limit(&self, scope: LimitScope) -> RateLimit {
+        let config = self.config.read().unwrap_or_else(|e| e.into_inner());
+        match scope {
+            LimitScope::Global => config.global,
+            LimitScope::Auth => config.auth,
+            LimitScope::Ai => config.ai,
+        }
+    }
+
+    pub fn login_limit(&self) -> LoginLimit {
+        let config = self.config.read().unwrap_or_else(|e| e.into_inner());
+        config.login
+    }
+
+    /// The ip of the client, behind the trusted proxies.
+    pub fn client_ip(&self, req: &Request) -> String {
+        let config = self.config.read().unwrap_or_else(|e| e.into_inner());
+        let Some(peer) = req.remote_addr().clone().into_std() else {
+            return req.remote_addr().to_string();
+        };
+        let forwarded = req
+            .headers()
+            .get(FORWARDED_FOR_HEADER)
+            .and_then(|value| value.to_str().ok());
+        forwarded_client(peer.ip(), forwarded, &config.trusted_proxies).to_string()
+    }
+
+    /// Take a token for the client, requests are let through when the store fails.
+    pub async fn check(&self, scope: LimitScope, client: &str) -> Decision {
+        let limit = &self.limit(scope);
+        let key = format!("{}:{}", scope.name(), client);
+        match self.store.take(&key, limit).await {
+            Ok(decision) => decision,
+            Err(e) => {
+                tracing::error!("Failed to check the rate limit of {}: {}", key, e);
+                Decision::allow_all(limit)
+            }
+        }
+    }
+}
+
+/// The authenticated user, or the ip of the client.
+fn client_key(req: &Request, depot: &Depot, limiter: &RateLimiter) -> String {
+    if let (JwtAuthState::Authorized, Some(data)) =
+        (depot.jwt_auth_state(), depot.jwt_auth_data::<JwtClaims>())
+    {
+        return format!("user:{}", data.claims.sub);
+    }
+    format!("ip:{}", limiter.client_ip(req))
+}
+
+/// The client of a request from the peer, walking `X-Forwarded-For` from the
+/// right while the hops are trusted proxies: the entries on the left are set
+/// by the client and may be forged.
+fn forwarded_client(peer: IpAddr, forwarded: Option<&str>, trusted_proxies: &[String]) -> IpAddr {
+    let mut client = peer;
+    if !is_trusted(client, trusted_proxies) {
+        return client;
+    }
+    for hop in forwarded.unwrap_or_default().rsplit(',') {
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !is_trusted(client, trusted_proxies) {
+            break;
+        }
+    }
+    client
+}
+
+fn is_trusted(ip: IpAddr, trusted_proxies: &[String]) -> bool {
+    trusted_proxies
+        .iter()
+        .any(|proxy| match proxy.split_once('/') {
+            Some((network, bits)) => in_network(ip, network, bits),
+            None => proxy.parse::<IpAddr>().is_ok_and(|proxy| proxy == ip),
+        })
+}
+
+fn in_network(ip: IpAddr, network: &str, bits: &str) -> bool {
+    let (Ok(network), Ok(bits)) = (network.parse::<IpAddr>(), bits.parse::<u32>()) else {
+        return false;
+    };
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) if bits <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - bits).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) if bits <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - bits).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+async fn apply_limit(
+    scope: LimitScope,
+    req: &Request,
+    depot: &Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    let Ok(state) = depot.obtain::<AppDataRef>() else {
+        return;
+    };
+    if !state.rate_limiter.enabled() {
+        return;
+    }
+    let decision = state
+        .rate_limiter
+        .check(scope, &client_key(req, depot, &state.rate_limiter))
+        .await;
+
+    let headers = res.headers_mut();
+    headers.insert(LIMIT_HEADER, HeaderValue::from(decision.limit));
+    headers.insert(REMAINING_HEADER, HeaderValue::from(decision.remaining));
+    headers.insert(RESET_HEADER, HeaderValue::from(decision.reset));
+    if !decision.allowed {
+        res.render(ServiceError::RateLimited(decision.retry_after));
+        ctrl.skip_rest();
+    }
+}
+
+/// Limit every api request.
+#[salvo::handler]
+pub async fn limit_global(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+    ctrl: &mut FlowCtrl,
+) {
+    apply_limit(LimitScope::Global, req, depot, res, ctrl).await;
+}
+
+/// Stricter limit of login, registration and tokens, against credential stuffing.
+#[salvo::handler]
+pub async fn limit_auth(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+    ctrl: &mut FlowCtrl,
+) {
+    apply_limit(LimitScope::Auth, req, depot, res, ctrl).await;
+}
+
+/// Stricter limit of the llm backed endpoints, which are costly.
+#[salvo::handler]
+pub async fn limit_ai(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+    ctrl: &mut FlowCtrl,
+) {
+    apply_limit(LimitScope::Ai, req, depot, res, ctrl).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_store_empties_bucket() {
+        let store = MemoryStore::default();
+        let limit = RateLimit {
+            burst: 2,
+            per_minute: 1,
+        };
+        assert!(store.take("a", &limit).await.unwrap().allowed);
+        assert!(store.take("a", &limit).await.unwrap().allowed);
+        let decision = store.take("a", &limit).await.unwrap();
+        assert!(!decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert!(decision.retry_after > 0 && decision.retry_after <= 60);
+        // buckets are per key
+        assert!(store.take("b", &limit).await.unwrap().allowed);
+    }
+
+    #[test]
+    fn test_forwarded_client() {
+        let trusted = vec!["10.0.0.0/8".to_string(), "192.168.1.1".to_string()];
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+        let forwarded = Some("1.2.3.4, 5.6.7.8, 10.1.2.3");
+        // the header of an untrusted peer is ignored
+        assert_eq!(
+            forwarded_client(ip("8.8.8.8"), forwarded, &trusted),
+            ip("8.8.8.8")
+        );
+        // the first untrusted hop from the right, the left one may be forged
+        assert_eq!(
+            forwarded_client(ip("192.168.1.1"), forwarded, &trusted),
+            ip("5.6.7.8")
+        );
+        assert_eq!(
+            forwarded_client(ip("10.0.0.1"), None, &trusted),
+            ip("10.0.0.1")
+        );
+        assert!(!is_trusted(ip("11.0.0.1"), &trusted));
+        assert!(is_trusted(ip("::1"), &["::1/128".to_string()]));
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use ai_flow_synth::utils::LogLevelHandle;
+use salvo::http::HeaderValue;
+
+use crate::{
+    config::{Cli, Config},
+    llm::prompt::set_default_prompts,
+    tenant::Tenants,
+};
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The settings changed without a restart when the config file is edited: the
+/// log level, the rate limits, the CORS origins and the default prompts.
+#[derive(Debug, Clone)]
+pub struct LiveSettings {
+    cors_origins: Arc<RwLock<Vec<String>>>,
+    log_level: LogLevelHandle,
+}
+
+impl LiveSettings {
+    pub fn new(config: &Config, log_level: LogLevelHandle) -> Self {
+        set_default_prompts(&config.prompt_config.defaults);
+        LiveSettings {
+            cors_origins: Arc::new(RwLock::new(config.frontend_config.cors.clone())),
+            log_level,
+        }
+    }
+
+    pub fn allows_origin(&self, origin: &HeaderValue) -> bool {
+        let origins = self.cors_origins.read().unwrap_or_else(|e| e.into_inner());
+        origins
+            .iter()
+            .any(|allowed| allowed.as_bytes() == origin.as_bytes())
+    }
+
+    fn apply(&self, tenants: &Tenants, config: &Config) {
+        if let Err(e) = self.log_level.apply(&config.log_config) {
+            tracing::error!("{}", e);
+        }
+        for state in tenants.all() {
+            state.rate_limiter.set_config(&config.rate_limit_config);
+        }
+        set_default_prompts(&config.prompt_config.defaults);
+        let mut origins = self.cors_origins.write().unwrap_or_else(|e| e.into_inner());
+        *origins = config.frontend_config.cors.clone();
+    }
+}
+
+fn modified_at(cli: &Cli) -> Option<SystemTime> {
+    std::fs::metadata(&cli.config)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Reload the config when its file changes, the other settings keep their
+/// value until a restart. A config which fails to load is ignored.
+pub async fn watch_config(cli: Cli, tenants: Tenants, live: LiveSettings) {
+    let mut last_modified = modified_at(&cli);
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let modified = modified_at(&cli);
+        if modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+        match Config::load(&cli) {
+            Ok(config) => {
+                live.apply(&tenants, &config);
+                tracing::info!("Reloaded config from {}", cli.config.display());
+            }
+            Err(e) => tracing::error!(
+                "Failed to reload config from {}: {}",
+                cli.config.display(),
+                e
+            ),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::{future::Future, sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::{
+    config::CircuitBreakerConfig,
+    error::{ServiceError, ServiceResult},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BreakerState {
+    // consecutive failures
+    Closed(u32),
+    // calls fail fast until then
+    Open(Instant),
+    // a single call probes the dependency, since then
+    HalfOpen(Instant),
+}
+
+/// Stops calling an external dependency after consecutive failures, failing
+/// fast instead of waiting for its timeouts. Once `open_secs` passed, a single
+/// call probes it: closing the breaker when it succeeds, opening it again
+/// otherwise.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    name: String,
+    failure_threshold: u32,
+    open_for: Duration,
+    state: Mutex<BreakerState>,
+}
+
+/// Whether the error is a failure of the dependency, rather than of the call.
+fn is_failure(err: &ServiceError) -> bool {
+    matches!(
+        err,
+        ServiceError::LLMError(_)
+            | ServiceError::EmbeddingError(_)
+            | ServiceError::MailError(_)
+            | ServiceError::UpstreamError(_)
+            | ServiceError::Timeout(_)
+    )
+}
+
+impl CircuitBreaker {
+    pub fn new(name: impl ToString, config: &CircuitBreakerConfig) -> Self {
+        CircuitBreaker {
+            name: name.to_string(),
+            failure_threshold: config.failure_threshold.max(1),
+            open_for: Duration::from_secs(config.open_secs),
+            state: Mutex::new(BreakerState::Closed(0)),
+        }
+    }
+
+    /// Let the call through, or fail with the seconds until the next probe.
+    fn acquire(&self, now: Instant) -> Result<(), u64> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match *state {
+            BreakerState::Closed(_) => Ok(()),
+            BreakerState::Open(until) if now < until => Err((until - now).as_secs().max(1)),
+            // a probe never answered, e.g. its request was cancelled
+            BreakerState::HalfOpen(since) if now < since + self.open_for => {
+                Err((since + self.open_for - now).as_secs().max(1))
+            }
+            BreakerState::Open(_) | BreakerState::HalfOpen(_) => {
+                tracing::info!("Probing {} again", self.name);
+                *state = BreakerState::HalfOpen(now);
+                Ok(())
+            }
+        }
+    }
+
+    fn record(&self, failed: bool, now: Instant) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state = match (*state, failed) {
+            (BreakerState::Closed(_), false) => BreakerState::Closed(0),
+            (_, false) => {
+                tracing::info!("{} is back, closing its circuit", self.name);
+                BreakerState::Closed(0)
+            }
+            (BreakerState::Closed(failures), true) if failures + 1 < self.failure_threshold => {
+                BreakerState::Closed(failures + 1)
+            }
+            (_, true) => {
+                tracing::warn!(
+                    "{} is failing, calls fail fast for {}s",
+                    self.name,
+                    self.open_for.as_secs()
+                );
+                BreakerState::Open(now + self.open_for)
+            }
+        };
+    }
+
+    /// Run the call to the dependency unless its circuit is open, a 503 with
+    /// the seconds to wait then.
+    pub async fn call<T>(&self, call: impl Future<Output = ServiceResult<T>>) -> ServiceResult<T> {
+        self.acquire(Instant::now())
+            .map_err(|retry_after| ServiceError::CircuitOpen {
+                service: self.name.clone(),
+                retry_after,
+            })?;
+        let result = call.await;
+        self.record(result.as_ref().is_err_and(is_failure), Instant::now());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_and_probes() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 2,
+            open_secs: 30,
+        };
+        let breaker = CircuitBreaker::new("crossref", &config);
+        let now = Instant::now();
+
+        breaker.record(true, now);
+        assert!(breaker.acquire(now).is_ok());
+        breaker.record(true, now);
+        assert_eq!(breaker.acquire(now), Err(30));
+
+        // a single probe once open long enough
+        let later = now + Duration::from_secs(30);
+        assert!(breaker.acquire(later).is_ok());
+        assert!(breaker.acquire(later).is_err());
+        breaker.record(true, later);
+        assert!(breaker.acquire(later + Duration::from_secs(1)).is_err());
+
+        let probe = later + Duration::from_secs(30);
+        assert!(breaker.acquire(probe).is_ok());
+        breaker.record(false, probe);
+        assert!(breaker.acquire(probe).is_ok());
+        assert_eq!(*breaker.state.lock().unwrap(), BreakerState::Closed(0));
+    }
+}
@@ -0,0 +1,183 @@
+pub mod breaker;
+
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+use salvo::{
+    Depot, FlowCtrl, Request, Response,
+    http::{
+        Method, ResBody, StatusCode,
+        header::{CONTENT_TYPE, HeaderValue, RETRY_AFTER},
+    },
+    prelude::JwtAuthDepotExt,
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, is_db_outage},
+    model::usage::{UsageEvent, UsageRepository},
+    utils::{api_version::ApiVersion, cache::TtlCache, jwt::JwtClaims},
+};
+
+// how long a response stays servable during an outage
+const STALE_TTL: Duration = Duration::from_secs(60 * 60);
+// larger responses are not kept in memory
+const MAX_CACHED_BODY_BYTES: usize = 1024 * 1024;
+// responses kept at most, the oldest are dropped beyond
+const MAX_CACHED_RESPONSES: usize = 1_000;
+// writes kept for replay, the oldest are dropped beyond
+const MAX_QUEUED_WRITES: usize = 10_000;
+const REPLAY_INTERVAL: Duration = Duration::from_secs(30);
+// first path segment after `/api/` of the GET endpoints served from cache
+const CACHED_RESOURCES: &[&str] = &["folder", "paper", "reading-list", "user"];
+
+pub const DEGRADED_HEADER: &str = "x-degraded";
+
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    content_type: Option<HeaderValue>,
+    body: Vec<u8>,
+}
+
+/// A non critical write kept while the database is unreachable.
+#[derive(Debug, Clone)]
+pub enum PendingWrite {
+    Usage(UsageEvent),
+}
+
+/// Keeps the service partly usable while MongoDB is unreachable: the last
+/// responses of the key GET endpoints are served read-only, and non critical
+/// writes are queued to be replayed once the database is back.
+#[derive(Debug)]
+pub struct Resilience {
+    responses: TtlCache<CachedResponse>,
+    writes: Mutex<VecDeque<PendingWrite>>,
+}
+
+impl Default for Resilience {
+    fn default() -> Self {
+        Resilience {
+            responses: TtlCache::with_capacity(STALE_TTL, MAX_CACHED_RESPONSES),
+            writes: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+impl Resilience {
+    pub fn queue_write(&self, write: PendingWrite) {
+        let mut writes = self.writes.lock().unwrap_or_else(|e| e.into_inner());
+        if writes.len() >= MAX_QUEUED_WRITES {
+            writes.pop_front();
+        }
+        writes.push_back(write);
+    }
+
+    fn take_writes(&self) -> Vec<PendingWrite> {
+        let mut writes = self.writes.lock().unwrap_or_else(|e| e.into_inner());
+        writes.drain(..).collect()
+    }
+}
+
+/// Record the usage, queued for replay when the database is unreachable.
+pub async fn record_usage(state: &AppDataRef, event: UsageEvent) {
+    match state.db.record_usage(event.clone()).await {
+        Ok(()) => {}
+        Err(ServiceError::MongoClientError(e)) if is_db_outage(&e) => {
+            state.resilience.queue_write(PendingWrite::Usage(event));
+        }
+        Err(e) => tracing::error!(
+            "Failed to record llm usage of user {}: {}",
+            event.user_id,
+            e
+        ),
+    }
+}
+
+/// Replay the queued writes if the database is reachable again.
+pub async fn flush_writes(state: &AppDataRef) {
+    let writes = state.resilience.take_writes();
+    if writes.is_empty() {
+        return;
+    }
+    if state.db.ping().await.is_err() {
+        for write in writes {
+            state.resilience.queue_write(write);
+        }
+        return;
+    }
+    tracing::info!("Replaying {} writes queued during the outage", writes.len());
+    for write in writes {
+        match write {
+            PendingWrite::Usage(event) => record_usage(state, event).await,
+        }
+    }
+}
+
+/// Replay the queued writes, and send again the queued mails, periodically,
+/// for the lifetime of the service.
+pub async fn replay_writes(state: AppDataRef) {
+    let mut interval = tokio::time::interval(REPLAY_INTERVAL);
+    loop {
+        interval.tick().await;
+        flush_writes(&state).await;
+        state.mailer.retry_queued().await;
+    }
+}
+
+fn cache_key(req: &Request, depot: &Depot) -> Option<String> {
+    if req.method() != Method::GET {
+        return None;
+    }
+    let path = req.uri().path().trim_start_matches("/api/");
+    // the same resource under every version
+    let path = ApiVersion::ALL
+        .iter()
+        .find_map(|version| path.strip_prefix(version.path())?.strip_prefix('/'))
+        .unwrap_or(path);
+    let resource = path.split('/').next()?;
+    if !CACHED_RESOURCES.contains(&resource) {
+        return None;
+    }
+    let claims = depot.jwt_auth_data::<JwtClaims>()?;
+    Some(format!("{}:{}", claims.claims.sub, req.uri()))
+}
+
+/// Remember the successful responses of the key GET endpoints, and serve them
+/// marked as stale when the database is unavailable.
+#[salvo::handler]
+pub async fn serve_stale(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+    ctrl: &mut FlowCtrl,
+) {
+    let key = cache_key(req, depot);
+    ctrl.call_next(req, depot, res).await;
+    let (Some(key), Ok(state)) = (key, depot.obtain::<AppDataRef>()) else {
+        return;
+    };
+
+    let status = res.status_code.unwrap_or(StatusCode::OK);
+    if status.is_success() {
+        let body = match &res.body {
+            ResBody::Once(body) if body.len() <= MAX_CACHED_BODY_BYTES => body.to_vec(),
+            _ => return,
+        };
+        let cached = CachedResponse {
+            content_type: res.headers().get(CONTENT_TYPE).cloned(),
+            body,
+        };
+        state.resilience.responses.insert(&key, cached);
+    } else if status == StatusCode::SERVICE_UNAVAILABLE {
+        let Some(cached) = state.resilience.responses.get(&key) else {
+            return;
+        };
+        res.status_code(StatusCode::OK);
+        if let Some(content_type) = cached.content_type {
+            res.headers_mut().insert(CONTENT_TYPE, content_type);
+        }
+        res.headers_mut().remove(RETRY_AFTER);
+        res.headers_mut()
+            .insert(DEGRADED_HEADER, HeaderValue::from_static("stale"));
+        res.replace_body(ResBody::Once(cached.body.into()));
+    }
+}
@@ -0,0 +1,169 @@
+use salvo::{
+    Depot, Response, Router,
+    oapi::{RouterExt, endpoint, extract::JsonBody},
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    export::ExportFormat,
+    i18n::{DELETE_ACCOUNT_BODY, DELETE_ACCOUNT_SUBJECT, Locale, request_locale},
+    model::{
+        account::{AccountRepository, schema::DeleteAccountRequest},
+        export::{ExportJob, ExportKind, ExportRepository, schema::ExportJobResponse},
+        paper::PaperRepository,
+        txn::in_transaction,
+        user::User,
+    },
+    router::export::run_export,
+    utils::{
+        cache::CacheKey,
+        jwt::{generate_delete_token, verify_delete_token},
+        mailer::Mail,
+        password::verify_password,
+        validate::ValidatedRequest,
+    },
+};
+
+pub fn create_router() -> Router {
+    Router::new()
+        .delete(delete_account)
+        .push(Router::with_path("export").post(export_account))
+        .oapi_tag("account")
+}
+
+/// Export Account
+///
+/// Starts building a zip archive of everything stored for the authenticated user:
+/// folders, papers with their notes and summaries, note blocks, conversations and
+/// uploaded files, listed by a `manifest.json`. The status of the export links to
+/// the download once ready, and the user is notified.
+#[endpoint(
+    status_codes(202, 401),
+    responses(
+        (status_code = 202, body = ExportJobResponse, description = "Export started"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn export_account(
+    depot: &mut Depot,
+    resp: &mut Response,
+) -> ServiceResult<ExportJobResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let date = bson::DateTime::now()
+        .try_to_rfc3339_string()
+        .unwrap_or_default();
+    let job = ExportJob::new(
+        &user.uid,
+        ExportKind::Account,
+        &user.uid,
+        ExportFormat::Md,
+        format!("account-{}.zip", date.get(..10).unwrap_or_default()),
+    );
+    state.db.create_export(job.clone()).await?;
+    state.jobs.spawn(run_export(state.clone(), job.clone()));
+    resp.status_code(salvo::http::StatusCode::ACCEPTED);
+    Ok(job.into())
+}
+
+/// Email the user a token confirming the deletion of their account.
+async fn send_delete_confirmation(
+    state: &AppDataRef,
+    user: &User,
+    locale: Locale,
+) -> ServiceResult<()> {
+    let Some(email) = user.email.clone() else {
+        return Err(ServiceError::invalid_field(
+            "confirmationToken",
+            "no_email",
+            "The account has no email to send the confirmation to",
+        ));
+    };
+    let token = generate_delete_token(user.uid.clone(), &state.tenant.id)?;
+    let locale = Locale::of_user(user, locale);
+    state
+        .mailer
+        .send(Mail {
+            to: email,
+            subject: locale.text(DELETE_ACCOUNT_SUBJECT).to_string(),
+            body: locale.render(DELETE_ACCOUNT_BODY, &[("token", &token)]),
+        })
+        .await
+}
+
+/// Delete Account
+///
+/// Deletes the authenticated user with all their data: folders, papers, files,
+/// conversations and every other record. Accounts with a password must confirm
+/// it. Accounts without one are emailed a token on a first request, the
+/// deletion then happens once it is sent back as `confirmationToken`. The
+/// deletion is checked afterwards and fails when any record is left.
+#[endpoint(
+    status_codes(202, 204, 401, 422, 500),
+    responses(
+        (status_code = 202, description = "Confirmation token emailed"),
+        (status_code = 204, description = "Account deleted"),
+        (status_code = 401, description = "Unauthorized: User not authenticated, wrong password or invalid token"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error"),
+        (status_code = 500, description = "Internal Server Error: Some data could not be deleted")
+    )
+)]
+async fn delete_account(
+    depot: &mut Depot,
+    request: JsonBody<DeleteAccountRequest>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    match (user.password_hash.as_deref(), request.confirmation_token) {
+        (Some(hash), _) => {
+            let password = request.password.as_deref().unwrap_or_default();
+            if !verify_password(password, hash) {
+                return Err(ServiceError::Unauthorized("Invalid password".to_string()));
+            }
+        }
+        // a session alone is not enough, it could have been stolen
+        (None, Some(token)) => {
+            let claims = verify_delete_token(&token)?;
+            claims.check_tenant(&state.tenant.id)?;
+            if claims.sub != user.uid {
+                return Err(ServiceError::Unauthorized("Invalid token".to_string()));
+            }
+        }
+        (None, None) => {
+            send_delete_confirmation(state, user, request_locale(depot)).await?;
+            resp.status_code(salvo::http::StatusCode::ACCEPTED);
+            return Ok(());
+        }
+    }
+
+    let papers = state.db.get_papers_by_user_id(&user.uid).await?;
+    in_transaction(state.db.as_ref(), |db, txn| {
+        let user_id = user.uid.clone();
+        Box::pin(async move { db.delete_account(txn, &user_id).await })
+    })
+    .await?;
+    let mut keys = vec![CacheKey::User(&user.uid), CacheKey::Folders(&user.uid)];
+    keys.extend(papers.iter().map(|paper| CacheKey::Paper(&paper.id)));
+    state.invalidate(&keys).await;
+
+    let remaining = state.db.remaining_account_data(&user.uid).await?;
+    if !remaining.is_empty() {
+        let remaining = remaining
+            .iter()
+            .map(|(collection, count)| format!("{} {}", count, collection))
+            .collect::<Vec<_>>()
+            .join(", ");
+        tracing::error!("Deletion of account {} left {}", user.uid, remaining);
+        return Err(ServiceError::InternalServerError(
+            "Some data of the account could not be deleted, please retry".to_string(),
+        ));
+    }
+    tracing::info!("Deleted account {}", user.uid);
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
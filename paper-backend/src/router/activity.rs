@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use salvo::{
+    Depot, Router,
+    oapi::{RouterExt, endpoint, extract::QueryParam},
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::ServiceResult,
+    model::{
+        activity::{
+            Activity, ActivityAction, ActivityKind, ActivityRepository,
+            schema::ListActivityResponse,
+        },
+        paper::PaperRepository,
+        user::User,
+    },
+};
+
+const DEFAULT_ACTIVITY_LIMIT: i64 = 20;
+const MAX_ACTIVITY_LIMIT: i64 = 100;
+
+pub fn create_router() -> Router {
+    Router::new().get(list_activity).oapi_tag("activity")
+}
+
+/// Record in the background that the user opened the paper or folder.
+pub(super) fn record_activity(
+    state: &AppDataRef,
+    user_id: &str,
+    kind: ActivityKind,
+    resource_id: &str,
+    action: ActivityAction,
+) {
+    let activity = Activity::new(user_id, kind, resource_id, action);
+    state
+        .jobs
+        .spawn(run_record_activity(state.clone(), activity));
+}
+
+// the feed is best effort, a failure is only logged
+async fn run_record_activity(state: AppDataRef, activity: Activity) {
+    if let Err(e) = state.db.record_activity(activity).await {
+        tracing::warn!("Failed to record activity: {}", e);
+    }
+}
+
+/// List Activity
+///
+/// Lists the papers and folders the authenticated user recently viewed or edited,
+/// most recent first and each once. Deleted ones are left out, `limit` defaults to 20.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListActivityResponse, description = "Recent activity of the user"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn list_activity(
+    depot: &mut Depot,
+    limit: QueryParam<i64, false>,
+) -> ServiceResult<ListActivityResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let limit = limit
+        .into_inner()
+        .unwrap_or(DEFAULT_ACTIVITY_LIMIT)
+        .clamp(1, MAX_ACTIVITY_LIMIT);
+    let activities = state.db.get_recent_activity(&user.uid, limit).await?;
+
+    let paper_ids = activities
+        .iter()
+        .filter(|a| a.kind == ActivityKind::Paper)
+        .map(|a| a.resource_id.clone())
+        .collect::<Vec<_>>();
+    let mut titles: HashMap<String, String> = state
+        .db
+        .get_papers_by_ids(&user.uid, &paper_ids)
+        .await?
+        .into_iter()
+        .map(|paper| (paper.id, paper.title))
+        .collect();
+    titles.extend(
+        state
+            .cached_folders(&user.uid)
+            .await?
+            .into_iter()
+            .map(|folder| (folder.id, folder.name)),
+    );
+
+    let items = activities
+        .into_iter()
+        .filter_map(|activity| {
+            let title = titles.get(&activity.resource_id)?.clone();
+            Some(activity.into_response(title))
+        })
+        .collect();
+    Ok(ListActivityResponse(items))
+}
@@ -0,0 +1,482 @@
+use chrono::Utc;
+use salvo::{
+    Depot, FlowCtrl, Request, Response, Router,
+    http::StatusCode,
+    oapi::{
+        RouterExt, endpoint,
+        extract::{JsonBody, PathParam, QueryParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    backup::{RunningGuard, run_backup, run_restore},
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    migrations::migration_status,
+    model::{
+        backup::{
+            BackupJob, BackupKind, BackupRepository, BackupStatus,
+            schema::{BackupJobResponse, ListBackupJobsResponse, RestoreBackupRequest},
+        },
+        blob::{BlobRepository, quarantine_file_key},
+        login_attempt::{
+            LoginAttemptRepository,
+            schema::{ListLoginLocksResponse, LoginLockResponse},
+        },
+        migration::schema::ListMigrationsResponse,
+        quarantine::{
+            QuarantineRepository,
+            schema::{ListQuarantinedFilesResponse, QuarantinedFileResponse},
+        },
+        quota::schema::{QuotaLimitsResponse, UpdateUserQuotasRequest, UserQuotasResponse},
+        retry::schema::DbRetriesResponse,
+        stats::StatsRepository,
+        usage::{
+            UsageRepository, month_start,
+            schema::{AdminUsageResponse, UsageTotalResponse},
+        },
+        user::{User, UserRepository},
+    },
+    utils::{cache::CacheKey, validate::ValidatedRequest},
+};
+
+const MAX_USAGE_GROUPS: i64 = 100;
+const DEFAULT_USER_LIMIT: i64 = 50;
+const MAX_USER_LIMIT: i64 = 500;
+const MAX_BACKUP_JOBS: i64 = 100;
+const MAX_QUARANTINED_FILES: i64 = 100;
+const MAX_LOGIN_LOCKS: i64 = 100;
+
+pub fn create_router() -> Router {
+    Router::new()
+        .hoop(require_operator)
+        .push(Router::with_path("usage").get(get_all_usage))
+        .push(Router::with_path("migrations").get(list_migrations))
+        .push(Router::with_path("db-retries").get(get_db_retries))
+        .push(Router::with_path("backup").post(create_backup))
+        .push(Router::with_path("restore").post(restore_backup))
+        .push(
+            Router::with_path("backups")
+                .get(list_backup_jobs)
+                .push(Router::with_path("{job_id}").get(get_backup_job)),
+        )
+        .push(
+            Router::with_path("users/{user_id}/quotas")
+                .get(get_user_quotas)
+                .put(update_user_quotas),
+        )
+        .push(
+            Router::with_path("quarantine")
+                .get(list_quarantined_files)
+                .push(Router::with_path("{file_id}").delete(delete_quarantined_file)),
+        )
+        .push(
+            Router::with_path("login-locks")
+                .get(list_login_locks)
+                .push(Router::with_path("{key}").delete(unlock_login)),
+        )
+        .push(Router::with_path("prompts").push(super::prompt::create_router()))
+        .oapi_tag("admin")
+}
+
+/// Only the operators listed in the usage config get past.
+#[salvo::handler]
+pub(super) async fn require_operator(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+    ctrl: &mut FlowCtrl,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+    if !state.usage_config.is_operator(&user.uid) {
+        res.render(ServiceError::Unauthorized(
+            "Only operators can access this resource".to_string(),
+        ));
+        ctrl.skip_rest();
+        return Ok(());
+    }
+    ctrl.call_next(req, depot, res).await;
+    Ok(())
+}
+
+/// Get All Usage
+///
+/// Gets the llm usage of every user in the current month, in total, per model,
+/// per feature and per user, the heaviest users first. `limit` bounds the users
+/// listed, 50 by default. Operators only.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = AdminUsageResponse, description = "Llm usage of all users"),
+        (status_code = 401, description = "Unauthorized: User not an operator")
+    )
+)]
+async fn get_all_usage(
+    depot: &mut Depot,
+    limit: QueryParam<i64, false>,
+) -> ServiceResult<AdminUsageResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let limit = limit
+        .into_inner()
+        .unwrap_or(DEFAULT_USER_LIMIT)
+        .clamp(1, MAX_USER_LIMIT);
+    let db = &state.db;
+    let since = month_start(Utc::now());
+    let (total, by_model, by_feature, by_user) = tokio::try_join!(
+        db.sum_usage_since(None, None, since.into(), 1),
+        db.sum_usage_since(None, Some("model"), since.into(), MAX_USAGE_GROUPS),
+        db.sum_usage_since(None, Some("feature"), since.into(), MAX_USAGE_GROUPS),
+        db.sum_usage_since(None, Some("user_id"), since.into(), limit),
+    )?;
+
+    Ok(AdminUsageResponse {
+        period_start: since.timestamp_millis(),
+        total: total.into_iter().next().unwrap_or_default().into(),
+        by_model: by_model.into_iter().map(UsageTotalResponse::from).collect(),
+        by_feature: by_feature
+            .into_iter()
+            .map(UsageTotalResponse::from)
+            .collect(),
+        by_user: by_user.into_iter().map(UsageTotalResponse::from).collect(),
+    })
+}
+
+/// List Migrations
+///
+/// Lists the migrations of the stored documents, in the order they are applied
+/// on startup, with when each was applied. Operators only.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListMigrationsResponse, description = "Database migrations"),
+        (status_code = 401, description = "Unauthorized: User not an operator")
+    )
+)]
+async fn list_migrations(depot: &mut Depot) -> ServiceResult<ListMigrationsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    migration_status(&state.db).await
+}
+
+/// Get Database Retries
+///
+/// Gets how many database calls were retried after a transient error since the
+/// start of the instance, how many then succeeded, and the operations retried.
+/// Operators only.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = DbRetriesResponse, description = "Retries of the database calls"),
+        (status_code = 401, description = "Unauthorized: User not an operator")
+    )
+)]
+async fn get_db_retries(depot: &mut Depot) -> ServiceResult<DbRetriesResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    Ok(state.db_retries.snapshot())
+}
+
+fn acquire_running() -> ServiceResult<RunningGuard> {
+    RunningGuard::acquire().ok_or_else(|| {
+        ServiceError::BadRequest("A backup or restore is already running".to_string())
+    })
+}
+
+/// Create Backup
+///
+/// Starts a snapshot of every collection, read at one point in time, and of
+/// every blob, stored as a zip archive in the blob store. The status of the
+/// backup tracks its progress. Operators only.
+#[endpoint(
+    status_codes(202, 400, 401),
+    responses(
+        (status_code = 202, body = BackupJobResponse, description = "Backup started"),
+        (status_code = 400, description = "Bad Request: A backup or restore is already running"),
+        (status_code = 401, description = "Unauthorized: User not an operator")
+    )
+)]
+async fn create_backup(depot: &mut Depot, resp: &mut Response) -> ServiceResult<BackupJobResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let running = acquire_running()?;
+    let job = BackupJob::new(&user.uid, BackupKind::Backup, None);
+    state.db.create_backup_job(job.clone()).await?;
+    state
+        .jobs
+        .spawn(run_backup(state.clone(), job.clone(), running));
+    resp.status_code(StatusCode::ACCEPTED);
+    Ok(job.into())
+}
+
+/// Restore Backup
+///
+/// Starts replacing every collection and blob with the ones of a finished
+/// backup. The archive is checked whole before anything is written, then the
+/// migrations it predates are run. The other writes are refused with a 503
+/// meanwhile. The backups themselves are kept. Operators only.
+#[endpoint(
+    status_codes(202, 400, 401, 404),
+    responses(
+        (status_code = 202, body = BackupJobResponse, description = "Restore started"),
+        (status_code = 400, description = "Bad Request: Backup not ready, or a backup or restore is already running"),
+        (status_code = 401, description = "Unauthorized: User not an operator"),
+        (status_code = 404, description = "Not Found: Backup does not exist")
+    )
+)]
+async fn restore_backup(
+    depot: &mut Depot,
+    request: JsonBody<RestoreBackupRequest>,
+    resp: &mut Response,
+) -> ServiceResult<BackupJobResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let backup_id = request.into_inner().backup_id;
+    let backup = state
+        .db
+        .get_backup_job(&backup_id)
+        .await?
+        .filter(|job| job.kind == BackupKind::Backup)
+        .ok_or_else(|| ServiceError::NotFound("Backup".to_string()))?;
+    if backup.status != BackupStatus::Ready {
+        return Err(ServiceError::BadRequest(
+            "The backup is not ready to be restored".to_string(),
+        ));
+    }
+
+    let running = acquire_running()?;
+    let job = BackupJob::new(&user.uid, BackupKind::Restore, Some(backup.id.clone()));
+    state.db.create_backup_job(job.clone()).await?;
+    state
+        .jobs
+        .spawn(run_restore(state.clone(), job.clone(), backup.id, running));
+    resp.status_code(StatusCode::ACCEPTED);
+    Ok(job.into())
+}
+
+/// List Backup Jobs
+///
+/// Lists the latest backups and restores, the latest first. Operators only.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListBackupJobsResponse, description = "Backups and restores"),
+        (status_code = 401, description = "Unauthorized: User not an operator")
+    )
+)]
+async fn list_backup_jobs(depot: &mut Depot) -> ServiceResult<ListBackupJobsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let jobs = state.db.get_backup_jobs(MAX_BACKUP_JOBS).await?;
+    Ok(ListBackupJobsResponse(
+        jobs.into_iter().map(BackupJobResponse::from).collect(),
+    ))
+}
+
+/// Get Backup Job
+///
+/// Gets the status and progress of a backup or a restore. Operators only.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = BackupJobResponse, description = "Status of the backup or restore"),
+        (status_code = 401, description = "Unauthorized: User not an operator"),
+        (status_code = 404, description = "Not Found: Backup job does not exist")
+    )
+)]
+async fn get_backup_job(
+    depot: &mut Depot,
+    job_id: PathParam<String>,
+) -> ServiceResult<BackupJobResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let job = state
+        .db
+        .get_backup_job(&job_id)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound("Backup job".to_string()))?;
+    Ok(job.into())
+}
+
+async fn get_quota_user(state: &AppDataRef, user_id: &str) -> ServiceResult<User> {
+    state
+        .db
+        .get_user_by_uid(user_id)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound(format!("User {}", user_id)))
+}
+
+async fn user_quotas(state: &AppDataRef, user: User) -> ServiceResult<UserQuotasResponse> {
+    let (folders, storage_bytes) = tokio::try_join!(
+        state.db.count_folders(&user.uid),
+        state.db.sum_storage_bytes(&user.uid),
+    )?;
+    Ok(UserQuotasResponse {
+        limits: state.quotas(&user).into(),
+        overrides: QuotaLimitsResponse::from(user.quota_overrides),
+        user_id: user.uid,
+        folders,
+        storage_bytes,
+    })
+}
+
+/// Get User Quotas
+///
+/// Gets the library limits enforced on a user, the ones set for this user only,
+/// and the folders and bytes of files the user has. Operators only.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = UserQuotasResponse, description = "Quotas of the user"),
+        (status_code = 401, description = "Unauthorized: User not an operator"),
+        (status_code = 404, description = "Not Found: User does not exist")
+    )
+)]
+async fn get_user_quotas(
+    depot: &mut Depot,
+    user_id: PathParam<String>,
+) -> ServiceResult<UserQuotasResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let user = get_quota_user(state, &user_id).await?;
+    user_quotas(state, user).await
+}
+
+/// Update User Quotas
+///
+/// Replaces the limits set for a user only, the absent ones fall back to the
+/// config. Papers and folders already over a lowered limit are kept, only new
+/// ones are refused. Operators only.
+#[endpoint(
+    status_codes(200, 401, 404, 422),
+    responses(
+        (status_code = 200, body = UserQuotasResponse, description = "Quotas of the user updated"),
+        (status_code = 401, description = "Unauthorized: User not an operator"),
+        (status_code = 404, description = "Not Found: User does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn update_user_quotas(
+    depot: &mut Depot,
+    user_id: PathParam<String>,
+    request: JsonBody<UpdateUserQuotasRequest>,
+) -> ServiceResult<UserQuotasResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let request = request.into_inner().validated()?;
+    let mut user = get_quota_user(state, &user_id).await?;
+    user.quota_overrides = request.into();
+    user.updated_at = bson::DateTime::now();
+    state.db.update_user(user.clone()).await?;
+    state.invalidate(&[CacheKey::User(&user.uid)]).await;
+    user_quotas(state, user).await
+}
+
+/// List Quarantined Files
+///
+/// Lists the latest uploaded files refused by the content scan, of every user,
+/// with the reason and the scanner. Operators only.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListQuarantinedFilesResponse, description = "Quarantined files"),
+        (status_code = 401, description = "Unauthorized: User not an operator")
+    )
+)]
+async fn list_quarantined_files(depot: &mut Depot) -> ServiceResult<ListQuarantinedFilesResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let files = state
+        .db
+        .get_quarantined_files(MAX_QUARANTINED_FILES)
+        .await?;
+    Ok(ListQuarantinedFilesResponse(
+        files
+            .into_iter()
+            .map(QuarantinedFileResponse::from)
+            .collect(),
+    ))
+}
+
+/// Delete Quarantined File
+///
+/// Deletes a quarantined file for good, with its record. Operators only.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Quarantined file deleted"),
+        (status_code = 401, description = "Unauthorized: User not an operator"),
+        (status_code = 404, description = "Not Found: Quarantined file does not exist")
+    )
+)]
+async fn delete_quarantined_file(
+    depot: &mut Depot,
+    file_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let file = state
+        .db
+        .get_quarantined_file(&file_id)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound(format!("Quarantined file {}", file_id.as_str())))?;
+    state.db.delete_blob(&quarantine_file_key(&file.id)).await?;
+    state.db.delete_quarantined_file(&file.id).await?;
+    resp.status_code(StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+/// List Login Locks
+///
+/// Lists the accounts and ips locked after failed logins, the latest failures
+/// first. Operators only.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListLoginLocksResponse, description = "Current login locks"),
+        (status_code = 401, description = "Unauthorized: User not an operator")
+    )
+)]
+async fn list_login_locks(depot: &mut Depot) -> ServiceResult<ListLoginLocksResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let locks = state
+        .db
+        .get_locked_logins(bson::DateTime::now(), MAX_LOGIN_LOCKS)
+        .await?;
+    Ok(ListLoginLocksResponse(
+        locks.into_iter().map(LoginLockResponse::from).collect(),
+    ))
+}
+
+/// Unlock Login
+///
+/// Unlocks the account, `account:{email}`, or the ip, `ip:{ip}`, and forgets
+/// its failed logins. Operators only.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Logins unlocked"),
+        (status_code = 401, description = "Unauthorized: User not an operator"),
+        (status_code = 404, description = "Not Found: No failed logins counted for the key")
+    )
+)]
+async fn unlock_login(
+    depot: &mut Depot,
+    key: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    if !state.db.clear_login_attempt(&key).await? {
+        return Err(ServiceError::NotFound(format!(
+            "Failed logins of {}",
+            key.as_str()
+        )));
+    }
+    tracing::info!("Logins of {} unlocked", key.as_str());
+    resp.status_code(StatusCode::NO_CONTENT);
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use salvo::{
+    Depot, Router,
+    oapi::{RouterExt, endpoint, extract::JsonBody},
+};
+
+use crate::{
+    app_data::AppDataRef,
+    authz::{Access, Principal, Resource, can},
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    model::{
+        ai::schema::{EstimateRequest, EstimateResponse, ModelEstimate},
+        paper::PaperRepository,
+        user::User,
+    },
+    utils::{
+        cost::{MODEL_PRICES, ModelPrice, estimate_tokens, model_price},
+        validate::ValidatedRequest,
+    },
+};
+
+const DEFAULT_OUTPUT_TOKENS: u64 = 1024;
+
+pub fn create_router() -> Router {
+    Router::new()
+        .push(Router::with_path("estimate").post(estimate))
+        .oapi_tag("ai")
+}
+
+fn model_estimate(price: &ModelPrice, input_tokens: u64, output_tokens: u64) -> ModelEstimate {
+    let input_cost = price.cost(input_tokens, 0);
+    let output_cost = price.cost(0, output_tokens);
+    ModelEstimate {
+        model: price.model.to_string(),
+        input_tokens,
+        output_tokens,
+        input_cost,
+        output_cost,
+        total_cost: input_cost + output_cost,
+    }
+}
+
+/// Estimate Cost
+///
+/// Estimates the token usage and cost of a generation before running it,
+/// for the requested model and all other supported models.
+#[endpoint(
+    status_codes(200, 401, 404, 422),
+    responses(
+        (status_code = 200, body = EstimateResponse, description = "Estimated token usage and cost"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn estimate(
+    depot: &mut Depot,
+    request: JsonBody<EstimateRequest>,
+) -> ServiceResult<EstimateResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let model = state.user_model(user, request.model);
+    let price = model_price(&model).ok_or_else(|| {
+        ServiceError::invalid_field(
+            "model",
+            "unsupported",
+            format!("No pricing known for model {}", model),
+        )
+    })?;
+
+    let mut input_tokens = request.prompt.as_deref().map(estimate_tokens).unwrap_or(0);
+    let principal = Principal::of(state, user);
+    for paper_id in &request.paper_ids {
+        let paper = state
+            .db
+            .get_paper_by_id(paper_id)
+            .await?
+            .filter(|paper| can(Access::Read, Resource::Paper(paper), principal))
+            .ok_or_else(|| ServiceError::PaperNotFound(paper_id.clone()))?;
+        let text = [
+            Some(paper.title.as_str()),
+            paper.r#abstract.as_deref(),
+            paper.content.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("\n");
+        input_tokens += estimate_tokens(&text);
+    }
+    let output_tokens = request.max_output_tokens.unwrap_or(DEFAULT_OUTPUT_TOKENS);
+
+    let mut alternatives = MODEL_PRICES
+        .iter()
+        .filter(|p| p.model != price.model)
+        .map(|p| model_estimate(p, input_tokens, output_tokens))
+        .collect::<Vec<_>>();
+    alternatives.sort_by(|a, b| a.total_cost.total_cmp(&b.total_cost));
+
+    Ok(EstimateResponse {
+        estimate: model_estimate(&price, input_tokens, output_tokens),
+        alternatives,
+        currency: "USD".to_string(),
+    })
+}
@@ -1,18 +1,37 @@
 use salvo::{
-    Depot, Request, Response, Router, Scribe, Writer, handler,
-    http::cookie::{CookieBuilder, SameSite, time::Duration},
-    oapi::{RouterExt, ToResponse, ToSchema, endpoint, extract::*},
-    writing::Json,
+    Depot, Request, Response, Router, Writer, handler,
+    oapi::{RouterExt, endpoint, extract::*},
 };
-use serde::{Deserialize, Serialize};
 use tracing::info;
-use validator::Validate;
 
 use crate::{
     app_data::AppDataRef,
-    error::{ServiceError, ServiceResult},
-    model::user::{User, UserRepository},
-    utils::jwt::{generate_jwt_token, generate_refresh_token, verify_refresh_token},
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    i18n::{
+        Locale, RESET_PASSWORD_BODY, RESET_PASSWORD_SUBJECT, VERIFY_EMAIL_BODY,
+        VERIFY_EMAIL_SUBJECT, request_locale,
+    },
+    model::{
+        audit::{AuditAction, AuditLog, AuditLogRepository},
+        auth::schema::{
+            EmailLogin, EmailRegister, ForgotPassword, LoginResult, PhoneLogin, RegisterResult,
+            ResetPassword,
+        },
+        txn::TxnContext,
+        user::{User, UserRepository, UserStatus, email_taken},
+    },
+    rate_limit::login::{login_failed, login_succeeded, reserve_login},
+    utils::{
+        cache::CacheKey,
+        jwt::{
+            generate_jwt_token, generate_refresh_token, generate_reset_token,
+            generate_verify_token, verify_refresh_token, verify_reset_token, verify_verify_token,
+        },
+        mailer::Mail,
+        password::{hash_password, verify_password},
+        session::{REFRESH_COOKIE, clear_session_cookies, set_refresh_cookie, set_session_cookies},
+        validate::ValidatedRequest,
+    },
 };
 
 pub fn create_router() -> Router {
@@ -25,35 +44,49 @@ pub fn create_router() -> Router {
 pub fn create_non_auth_router() -> Router {
     Router::new()
         .push(Router::with_path("phone-login").post(phone_login))
+        .push(Router::with_path("email-login").post(email_login))
         .push(Router::with_path("refresh").post(refresh))
+        .push(Router::with_path("register").post(register))
+        .push(Router::with_path("verify").get(verify))
+        .push(Router::with_path("forgot").post(forgot_password))
+        .push(Router::with_path("reset").post(reset_password))
         .oapi_tag("auth")
-    // .push(Router::with_path("register").post(register))
+}
+
+/// The refresh token, and in cookie mode the session of the access token.
+fn set_login_cookies(
+    req: &Request,
+    resp: &mut Response,
+    state: &AppDataRef,
+    access_token: &str,
+    refresh_token: String,
+) {
+    set_refresh_cookie(resp, &state.session_config, refresh_token);
+    set_session_cookies(req, resp, &state.session_config, access_token);
 }
 
 /// Phone Login
 ///
 /// Authenticates a user using their phone number.
 #[endpoint(
-    status_codes(200, 201, 400, 401),
+    status_codes(200, 201, 401, 422),
     request_body(content = PhoneLogin, description = "login/register by phone"),
     responses(
         (status_code = 200, body = LoginResult, description = "Successful login"),
         (status_code = 201, body = LoginResult, description = "User created and logged in"),
-        (status_code = 400, description = "Bad Request: Validation error"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error"),
         (status_code = 401, description = "Unauthorized: Invalid phone number or code")
     )
 )]
 async fn phone_login(
     login: JsonBody<PhoneLogin>,
+    req: &mut Request,
     depot: &mut Depot,
     resp: &mut Response,
 ) -> ServiceResult<LoginResult> {
-    // todo better validation
-    login
-        .validate()
-        .map_err(|e| ServiceError::BadRequest(format!("Manually validation error: {}", e)))?;
+    let login = login.into_inner().validated()?;
     let state = depot.obtain::<AppDataRef>()?;
-    let exist_user = state.mongo_client.get_user_by_phone(&login.phone).await?;
+    let exist_user = state.db.get_user_by_phone(&login.phone).await?;
     let user_id = match exist_user {
         Some(user) => {
             // Here you would typically verify the code sent to the user's phone
@@ -65,22 +98,15 @@ async fn phone_login(
             tracing::info!("create new user found with phone: {}", login.phone);
             let new_user = User::new_by_phone(login.phone.clone());
             let user_id = new_user.uid.clone();
-            state.mongo_client.create_user(new_user).await?;
+            state.db.create_user(new_user).await?;
             resp.status_code(salvo::http::StatusCode::CREATED);
             user_id
         }
     };
 
-    let access_token = generate_jwt_token(user_id.clone())?;
-    let refresh_token = generate_refresh_token(user_id.clone())?;
-    resp.add_cookie(
-        CookieBuilder::new("refresh_token", refresh_token)
-            .max_age(Duration::days(30))
-            .same_site(SameSite::Lax)
-            .http_only(true)
-            .secure(false) // todo true in production
-            .build(),
-    );
+    let access_token = generate_jwt_token(user_id.clone(), &state.tenant.id)?;
+    let refresh_token = generate_refresh_token(user_id.clone(), &state.tenant.id)?;
+    set_login_cookies(req, resp, state, &access_token, refresh_token);
 
     Ok(LoginResult {
         access_token,
@@ -98,26 +124,36 @@ async fn phone_login(
         (status_code = 401, description = "Unauthorized: Refresh token not found or invalid")
     )
 )]
-async fn refresh(req: &mut Request, resp: &mut Response) -> ServiceResult<LoginResult> {
+async fn refresh(
+    req: &mut Request,
+    depot: &mut Depot,
+    resp: &mut Response,
+) -> ServiceResult<LoginResult> {
     let refresh_token = req
         .cookies()
-        .get("refresh_token")
+        .get(REFRESH_COOKIE)
         .ok_or_else(|| ServiceError::Unauthorized("Refresh token not found".to_string()))?
         .value();
-    let user_id = verify_refresh_token(refresh_token)?.sub;
+    let claims = verify_refresh_token(refresh_token)?;
+    let state = depot.obtain::<AppDataRef>()?;
+    claims.check_tenant(&state.tenant.id)?;
+    let user = state
+        .db
+        .get_user_by_uid(&claims.sub)
+        .await?
+        .ok_or_else(|| ServiceError::Unauthorized("User not found".to_string()))?;
+    if user.is_token_revoked(claims.iat) {
+        return Err(ServiceError::Unauthorized(
+            "Refresh token is revoked".to_string(),
+        ));
+    }
+    let user_id = user.uid;
 
     info!("Refreshing token for user: {:?}", user_id);
-    let access_token = generate_jwt_token(user_id.clone())?;
-    let refresh_token = generate_refresh_token(user_id.clone())?;
-
-    resp.add_cookie(
-        CookieBuilder::new("refresh_token", refresh_token)
-            .max_age(Duration::days(30))
-            .same_site(SameSite::Lax)
-            .http_only(true)
-            .secure(false) // todo true in production
-            .build(),
-    );
+    let access_token = generate_jwt_token(user_id.clone(), &state.tenant.id)?;
+    let refresh_token = generate_refresh_token(user_id.clone(), &state.tenant.id)?;
+
+    set_login_cookies(req, resp, state, &access_token, refresh_token);
 
     Ok(LoginResult {
         access_token,
@@ -137,63 +173,324 @@ async fn refresh(req: &mut Request, resp: &mut Response) -> ServiceResult<LoginR
 )]
 async fn logout(req: &mut Request, depot: &mut Depot, resp: &mut Response) -> ServiceResult<()> {
     // todo(nice to have), cache the deprecated refresh token to make sure it can't be used again
-    let _state = depot.obtain::<AppDataRef>()?;
+    let state = depot.obtain::<AppDataRef>()?;
     let user = depot.obtain::<User>()?;
     info!("Logging out user: {:?}", user);
 
     let refresh_token = req
         .cookies()
-        .get("refresh_token")
+        .get(REFRESH_COOKIE)
         .ok_or_else(|| ServiceError::Unauthorized("Refresh token not found".to_string()))?
         .value();
 
     info!("Logging out user with refresh token: {}", refresh_token);
-    resp.remove_cookie("refresh_token");
+    clear_session_cookies(resp, &state.session_config);
     resp.status_code(salvo::http::StatusCode::NO_CONTENT);
     Ok(())
 }
 
-#[handler]
-async fn register(req: &mut Request, depot: &mut Depot, resp: &mut Response) -> ServiceResult<()> {
+/// Register
+///
+/// Creates a pending account with email and password, and sends a verification email.
+/// The account can login after it is activated by the link in the email. The email of
+/// a pending account registered again gets the new password and a new link, so that an
+/// address registered by someone else is still claimed by its owner.
+#[endpoint(
+    status_codes(201, 400, 422),
+    request_body(content = EmailRegister, description = "register by email"),
+    responses(
+        (status_code = 201, body = RegisterResult, description = "Pending account created, verification email sent"),
+        (status_code = 400, description = "Bad Request: Email already registered"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn register(
+    register: JsonBody<EmailRegister>,
+    depot: &mut Depot,
+    resp: &mut Response,
+) -> ServiceResult<RegisterResult> {
+    let register = register.into_inner().validated()?;
     let state = depot.obtain::<AppDataRef>()?;
+    let password_hash = hash_password(&register.password)?;
+    // a fast path, the unique index of the emails settles concurrent registrations
+    let (user_id, created) = match state.db.get_user_by_email(&register.email).await? {
+        Some(mut user) if user.status == UserStatus::Pending => {
+            user.username = register.username;
+            user.password_hash = Some(password_hash);
+            user.updated_at = bson::DateTime::now();
+            let user_id = user.uid.clone();
+            state.db.update_user(user).await?;
+            state.invalidate(&[CacheKey::User(&user_id)]).await;
+            info!(
+                "Pending user registered again with email: {}",
+                register.email
+            );
+            (user_id, false)
+        }
+        Some(_) => return Err(email_taken(&register.email)),
+        None => {
+            let new_user =
+                User::new_by_email(register.email.clone(), register.username, password_hash);
+            let user_id = new_user.uid.clone();
+            state.db.create_user(new_user).await?;
+            info!("Pending user created with email: {}", register.email);
+            (user_id, true)
+        }
+    };
+
+    let token = generate_verify_token(user_id.clone(), &state.tenant.id)?;
+    let link = format!("{}/api/auth/verify?token={}", state.public_url, token);
+    let locale = request_locale(depot);
+    let sent = state
+        .mailer
+        .send(Mail {
+            to: register.email.clone(),
+            subject: locale.text(VERIFY_EMAIL_SUBJECT).to_string(),
+            body: locale.render(VERIFY_EMAIL_BODY, &[("link", &link)]),
+        })
+        .await;
+    if let Err(e) = sent {
+        // the pending account could never be verified, one registered again is
+        // left to the next attempt
+        if created {
+            let deleted = state.db.delete_user(user_id.clone()).await;
+            if let Err(e) = deleted {
+                tracing::error!("Failed to roll back pending user {}: {}", user_id, e);
+            }
+        }
+        return Err(e);
+    }
 
     resp.status_code(salvo::http::StatusCode::CREATED);
-    Ok(())
+    Ok(RegisterResult { user_id })
 }
 
-#[handler]
-async fn edit(req: &mut Request, depot: &mut Depot, resp: &mut Response) -> ServiceResult<()> {
+/// Verify Email
+///
+/// Activates a pending account with the token sent by the verification email.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Account activated"),
+        (status_code = 401, description = "Unauthorized: Invalid or expired token"),
+        (status_code = 404, description = "Not Found: User does not exist")
+    )
+)]
+async fn verify(
+    token: QueryParam<String, true>,
+    depot: &mut Depot,
+    resp: &mut Response,
+) -> ServiceResult<()> {
     let state = depot.obtain::<AppDataRef>()?;
-    let user = depot.obtain::<User>()?;
+    let claims = verify_verify_token(&token)?;
+    claims.check_tenant(&state.tenant.id)?;
+    let mut user = state
+        .db
+        .get_user_by_uid(&claims.sub)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound(format!("User {} not found", claims.sub)))?;
 
-    info!("Editing user: {:?}", user);
+    if user.status == UserStatus::Pending {
+        user.status = UserStatus::Active;
+        user.updated_at = bson::DateTime::now();
+        state.db.update_user(user).await?;
+        state.invalidate(&[CacheKey::User(&claims.sub)]).await;
+        info!("User activated: {}", claims.sub);
+    }
+
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
     Ok(())
 }
 
-/// Phone Login Request Body
-#[derive(Serialize, Deserialize, ToSchema, Validate)]
-#[serde(rename_all = "camelCase")]
-struct PhoneLogin {
-    #[validate(length(equal = 11))]
-    #[salvo(schema(max_length = 11, min_length = 11, example = "139xxxx1234"))]
-    phone: String,
-    #[validate(length(equal = 6))]
-    #[salvo(schema(max_length = 6, min_length = 6, example = "123456"))]
-    code: String,
+/// Email Login
+///
+/// Authenticates an activated user with email and password. After failed
+/// attempts the next ones to the account are delayed, then the account, or the
+/// ip they come from, is locked for a while and its owner told by email.
+#[endpoint(
+    status_codes(200, 401, 422, 429),
+    request_body(content = EmailLogin, description = "login by email"),
+    responses(
+        (status_code = 200, body = LoginResult, description = "Successful login"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error"),
+        (status_code = 401, description = "Unauthorized: Invalid email or password, or account not verified"),
+        (status_code = 429, description = "Too Many Requests: Delayed or locked after failed logins")
+    )
+)]
+async fn email_login(
+    login: JsonBody<EmailLogin>,
+    req: &mut Request,
+    depot: &mut Depot,
+    resp: &mut Response,
+) -> ServiceResult<LoginResult> {
+    let login = login.into_inner().validated()?;
+    let state = depot.obtain::<AppDataRef>()?;
+    let ip = state.rate_limiter.client_ip(req);
+    reserve_login(state, &login.email, &ip).await?;
+    let user = match state.db.get_user_by_email(&login.email).await? {
+        Some(user)
+            if user
+                .password_hash
+                .as_deref()
+                .is_some_and(|hash| verify_password(&login.password, hash)) =>
+        {
+            user
+        }
+        user => {
+            login_failed(state, &login.email, &ip, user.as_ref()).await?;
+            return Err(ServiceError::Unauthorized(
+                "Invalid email or password".to_string(),
+            ));
+        }
+    };
+    login_succeeded(state, &login.email, &ip).await?;
+    if user.status != UserStatus::Active {
+        return Err(ServiceError::Unauthorized("Email not verified".to_string()));
+    }
+
+    let user_id = user.uid;
+    let access_token = generate_jwt_token(user_id.clone(), &state.tenant.id)?;
+    let refresh_token = generate_refresh_token(user_id.clone(), &state.tenant.id)?;
+    set_login_cookies(req, resp, state, &access_token, refresh_token);
+
+    Ok(LoginResult {
+        access_token,
+        user_id,
+    })
 }
 
-/// Login Response Body
-#[derive(Serialize, Deserialize, ToResponse, ToSchema)]
-#[serde(rename_all = "camelCase")]
-struct LoginResult {
-    #[salvo(schema(example = "jwt.token.here"))]
-    access_token: String,
-    #[salvo(schema(example = "user-uuid"))]
-    user_id: String,
+/// Forgot Password
+///
+/// Sends a time-limited password reset link to the email if it belongs to an account.
+/// Always succeeds so that registered emails can't be probed.
+#[endpoint(
+    status_codes(204, 422),
+    request_body(content = ForgotPassword, description = "email of the account"),
+    responses(
+        (status_code = 204, description = "Reset email sent if the account exists"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn forgot_password(
+    forgot: JsonBody<ForgotPassword>,
+    req: &mut Request,
+    depot: &mut Depot,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let forgot = forgot.into_inner().validated()?;
+    let state = depot.obtain::<AppDataRef>()?;
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+
+    let Some(user) = state.db.get_user_by_email(&forgot.email).await? else {
+        info!(
+            "Password reset requested for unknown email: {}",
+            forgot.email
+        );
+        return Ok(());
+    };
+
+    let token = generate_reset_token(user.uid.clone(), &state.tenant.id)?;
+    let locale = Locale::of_user(&user, request_locale(depot));
+    let sent = state
+        .mailer
+        .send(Mail {
+            to: forgot.email.clone(),
+            subject: locale.text(RESET_PASSWORD_SUBJECT).to_string(),
+            body: locale.render(RESET_PASSWORD_BODY, &[("token", &token)]),
+        })
+        .await;
+    // a failure answered differently would tell the email is registered
+    if let Err(e) = sent {
+        tracing::error!(
+            "Failed to send the password reset of user {}: {}",
+            user.uid,
+            e
+        );
+        return Ok(());
+    }
+    state
+        .db
+        .create_audit_log(
+            &mut TxnContext::none(),
+            AuditLog::new(
+                &user.uid,
+                AuditAction::PasswordResetRequested,
+                Some(req.remote_addr().to_string()),
+            ),
+        )
+        .await?;
+    Ok(())
 }
 
-impl Scribe for LoginResult {
-    fn render(self, res: &mut Response) {
-        res.render(Json(self));
+/// Reset Password
+///
+/// Sets a new password with the token from the reset email,
+/// all existing sessions and refresh tokens of the user are invalidated.
+/// A pending account is activated, the email being proved by the link.
+#[endpoint(
+    status_codes(204, 401, 422),
+    request_body(content = ResetPassword, description = "reset token and new password"),
+    responses(
+        (status_code = 204, description = "Password reset successfully"),
+        (status_code = 401, description = "Unauthorized: Invalid or expired token"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn reset_password(
+    reset: JsonBody<ResetPassword>,
+    req: &mut Request,
+    depot: &mut Depot,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let reset = reset.into_inner().validated()?;
+    let state = depot.obtain::<AppDataRef>()?;
+    let claims = verify_reset_token(&reset.token)?;
+    claims.check_tenant(&state.tenant.id)?;
+    let mut user = state
+        .db
+        .get_user_by_uid(&claims.sub)
+        .await?
+        .ok_or_else(|| ServiceError::Unauthorized("User not found".to_string()))?;
+    // the reset token itself is revoked by a previous reset
+    if user.is_token_revoked(claims.iat) {
+        return Err(ServiceError::Unauthorized(
+            "Reset token already used".to_string(),
+        ));
+    }
+
+    let now = bson::DateTime::now();
+    user.password_hash = Some(hash_password(&reset.password)?);
+    if user.status == UserStatus::Pending {
+        user.status = UserStatus::Active;
     }
+    user.sessions_invalidated_at = Some(now);
+    user.updated_at = now;
+    let user_id = user.uid.clone();
+    state.db.update_user(user).await?;
+    state.invalidate(&[CacheKey::User(&user_id)]).await;
+    state
+        .db
+        .create_audit_log(
+            &mut TxnContext::none(),
+            AuditLog::new(
+                &user_id,
+                AuditAction::PasswordReset,
+                Some(req.remote_addr().to_string()),
+            ),
+        )
+        .await?;
+    info!("Password reset for user: {}", user_id);
+
+    clear_session_cookies(resp, &state.session_config);
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+#[handler]
+async fn edit(req: &mut Request, depot: &mut Depot, resp: &mut Response) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    info!("Editing user: {:?}", user);
+    Ok(())
 }
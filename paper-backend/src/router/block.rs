@@ -0,0 +1,173 @@
+use salvo::{
+    Depot, Response, Router, Writer,
+    oapi::{
+        RouterExt, endpoint,
+        extract::{JsonBody, PathParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    authz::{Access, Principal, Resource, assert_can},
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    model::{
+        block::{
+            Block, BlockRepository,
+            schema::{BlockResponse, CreateBlockRequest, ListBlocksResponse, UpdateBlockRequest},
+        },
+        user::User,
+    },
+    utils::validate::ValidatedRequest,
+};
+
+pub fn create_router() -> Router {
+    Router::new()
+        .push(Router::new().get(list_blocks).post(create_block))
+        .push(
+            Router::with_path("{block_id}")
+                .get(get_block)
+                .put(update_block)
+                .delete(delete_block),
+        )
+        .oapi_tag("block")
+}
+
+/// Fetch the block by ID and ensure the user may access it.
+async fn fetch_block(
+    state: &AppDataRef,
+    block_id: &str,
+    user: &User,
+    access: Access,
+) -> ServiceResult<Block> {
+    let block =
+        state.db.get_block_by_id(block_id).await?.ok_or_else(|| {
+            ServiceError::NotFound(format!("Block with ID {} not found", block_id))
+        })?;
+    assert_can(access, Resource::Block(&block), Principal::of(state, user))?;
+    Ok(block)
+}
+
+/// List Blocks
+///
+/// Lists the reusable content blocks of the authenticated user.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListBlocksResponse, description = "List of blocks"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn list_blocks(depot: &mut Depot) -> ServiceResult<ListBlocksResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let blocks = state.db.get_blocks_by_user_id(&user.uid).await?;
+    Ok(ListBlocksResponse(
+        blocks.into_iter().map(Into::into).collect(),
+    ))
+}
+
+/// Create Block
+///
+/// Creates a reusable content block, papers reference it with the returned `reference`.
+#[endpoint(
+    status_codes(201, 401, 422),
+    responses(
+        (status_code = 201, body = BlockResponse, description = "Block created successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn create_block(
+    depot: &mut Depot,
+    request: JsonBody<CreateBlockRequest>,
+    resp: &mut Response,
+) -> ServiceResult<BlockResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let block = Block::new_from_request(&user.uid, request);
+    state.db.create_block(block.clone()).await?;
+    resp.status_code(salvo::http::StatusCode::CREATED);
+    Ok(block.into())
+}
+
+/// Get Block
+///
+/// Gets a reusable content block of the authenticated user.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = BlockResponse, description = "Block details"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Block does not exist")
+    )
+)]
+async fn get_block(depot: &mut Depot, block_id: PathParam<String>) -> ServiceResult<BlockResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let block = fetch_block(state, &block_id, user, Access::Read).await?;
+    Ok(block.into())
+}
+
+/// Update Block
+///
+/// Updates a block, every paper referencing it picks up the change at export time.
+#[endpoint(
+    status_codes(200, 401, 404, 422),
+    request_body(content = UpdateBlockRequest, description = "Update block details"),
+    responses(
+        (status_code = 200, body = BlockResponse, description = "Block updated successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Block does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn update_block(
+    depot: &mut Depot,
+    block_id: PathParam<String>,
+    request: JsonBody<UpdateBlockRequest>,
+) -> ServiceResult<BlockResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let mut block = fetch_block(state, &block_id, user, Access::Write).await?;
+    if let Some(name) = request.name {
+        block.name = name;
+    }
+    if let Some(content) = request.content {
+        block.content = content;
+    }
+    block.updated_at = bson::DateTime::now();
+
+    let updated_block = state.db.update_block(block).await?;
+    Ok(updated_block.into())
+}
+
+/// Delete Block
+///
+/// Deletes a block, references left in papers are exported verbatim.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Block deleted successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Block does not exist")
+    )
+)]
+async fn delete_block(
+    depot: &mut Depot,
+    block_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let block = fetch_block(state, &block_id, user, Access::Write).await?;
+    state.db.delete_block(&block.id).await?;
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
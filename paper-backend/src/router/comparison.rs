@@ -0,0 +1,259 @@
+use salvo::{
+    Depot, Response, Router,
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE, HeaderValue},
+    oapi::{
+        RouterExt, endpoint,
+        extract::{JsonBody, PathParam, QueryParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    comparison::{compared_papers, comparison_markdown, generate_comparison},
+    error::{ErrorResponse, ServiceError, ServiceResult, ValidationErrorResponse},
+    model::{
+        comparison::{
+            Comparison, ComparisonRepository,
+            schema::{ComparePapersRequest, ComparisonResponse, ListComparisonsResponse},
+        },
+        user::User,
+    },
+    rate_limit::limit_ai,
+    utils::{timeout::extend_for_ai, validate::ValidatedRequest},
+};
+
+const DEFAULT_COMPARISON_LIMIT: i64 = 50;
+const MAX_COMPARISON_LIMIT: i64 = 200;
+
+pub fn create_router() -> Router {
+    Router::new()
+        .get(list_comparisons)
+        .push(
+            Router::with_path("{comparison_id}")
+                .get(get_comparison)
+                .delete(delete_comparison)
+                .push(
+                    Router::with_path("regenerate")
+                        .hoop(limit_ai)
+                        .hoop(extend_for_ai)
+                        .post(regenerate_comparison),
+                )
+                .push(Router::with_path("export").get(export_comparison)),
+        )
+        .oapi_tag("comparison")
+}
+
+async fn get_owned_comparison(
+    state: &AppDataRef,
+    user: &User,
+    comparison_id: &str,
+) -> ServiceResult<Comparison> {
+    state
+        .db
+        .get_comparison(&user.uid, comparison_id)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound(format!("Comparison {}", comparison_id)))
+}
+
+fn check_model(state: &AppDataRef, model: &str) -> ServiceResult<()> {
+    if !state.llm.supports(model) {
+        return Err(ServiceError::invalid_field(
+            "model",
+            "unsupported",
+            format!("Model {} is not available", model),
+        ));
+    }
+    Ok(())
+}
+
+/// Compare Papers With The Model
+///
+/// Has the model compare 2 to 5 papers of the authenticated user: their methods,
+/// datasets and results, and where they contradict each other. The comparison is
+/// kept, to be read again, regenerated or exported as markdown. `model` selects
+/// a configured model instead of the default one.
+#[endpoint(
+    status_codes(201, 401, 404, 422, 429, 502),
+    responses(
+        (status_code = 201, body = ComparisonResponse, description = "Comparison created"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error or model not available"),
+        (status_code = 429, body = ErrorResponse, description = "Too Many Requests: Rate limited or monthly llm quota exceeded"),
+        (status_code = 502, description = "Bad Gateway: The model answer could not be read")
+    )
+)]
+pub(super) async fn create_comparison(
+    depot: &mut Depot,
+    request: JsonBody<ComparePapersRequest>,
+    resp: &mut Response,
+) -> ServiceResult<ComparisonResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let model = state.user_model(user, request.model);
+    check_model(state, &model)?;
+    let papers = compared_papers(state, &user.uid, &request.paper_ids).await?;
+    state.ensure_quota(&user.uid, &model).await?;
+
+    let mut comparison = Comparison::new(&user.uid, request.paper_ids, model);
+    generate_comparison(state, &papers, &mut comparison).await?;
+    state.db.create_comparison(comparison.clone()).await?;
+    resp.status_code(salvo::http::StatusCode::CREATED);
+    Ok(comparison.into())
+}
+
+/// List Comparisons
+///
+/// Lists the comparisons of the authenticated user, newest first. `limit`
+/// defaults to 50.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListComparisonsResponse, description = "Comparisons of the user"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn list_comparisons(
+    depot: &mut Depot,
+    limit: QueryParam<i64, false>,
+) -> ServiceResult<ListComparisonsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let limit = limit
+        .into_inner()
+        .unwrap_or(DEFAULT_COMPARISON_LIMIT)
+        .clamp(1, MAX_COMPARISON_LIMIT);
+    let comparisons = state.db.get_comparisons(&user.uid, limit).await?;
+    Ok(ListComparisonsResponse(
+        comparisons.into_iter().map(Into::into).collect(),
+    ))
+}
+
+/// Get Comparison
+///
+/// Gets a comparison of the authenticated user.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = ComparisonResponse, description = "Comparison"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Comparison does not exist")
+    )
+)]
+async fn get_comparison(
+    depot: &mut Depot,
+    comparison_id: PathParam<String>,
+) -> ServiceResult<ComparisonResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let comparison = get_owned_comparison(state, user, &comparison_id).await?;
+    Ok(comparison.into())
+}
+
+/// Regenerate Comparison
+///
+/// Has the model write the comparison again from the current state of the
+/// papers, keeping its id. `model` defaults to the model of the previous
+/// generation.
+#[endpoint(
+    status_codes(200, 401, 404, 422, 429, 502),
+    responses(
+        (status_code = 200, body = ComparisonResponse, description = "Comparison regenerated"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Comparison or one of its papers does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Model not available"),
+        (status_code = 429, body = ErrorResponse, description = "Too Many Requests: Rate limited or monthly llm quota exceeded"),
+        (status_code = 502, description = "Bad Gateway: The model answer could not be read")
+    )
+)]
+async fn regenerate_comparison(
+    depot: &mut Depot,
+    comparison_id: PathParam<String>,
+    model: QueryParam<String, false>,
+) -> ServiceResult<ComparisonResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let mut comparison = get_owned_comparison(state, user, &comparison_id).await?;
+    if let Some(model) = model.into_inner() {
+        comparison.model = model;
+    }
+    check_model(state, &comparison.model)?;
+    let papers = compared_papers(state, &user.uid, &comparison.paper_ids).await?;
+    state.ensure_quota(&user.uid, &comparison.model).await?;
+
+    generate_comparison(state, &papers, &mut comparison).await?;
+    state.db.update_comparison(comparison.clone()).await?;
+    Ok(comparison.into())
+}
+
+/// Export Comparison
+///
+/// Downloads a comparison of the authenticated user as a markdown document.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, content_type = "text/markdown", description = "Markdown document"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Comparison does not exist")
+    )
+)]
+async fn export_comparison(
+    depot: &mut Depot,
+    comparison_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let comparison = get_owned_comparison(state, user, &comparison_id).await?;
+    resp.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("text/markdown; charset=utf-8"),
+    );
+    if let Ok(disposition) = HeaderValue::from_str(&format!(
+        "attachment; filename=\"comparison-{}.md\"",
+        comparison.id
+    )) {
+        resp.headers_mut().insert(CONTENT_DISPOSITION, disposition);
+    }
+    resp.body(comparison_markdown(&comparison));
+    Ok(())
+}
+
+/// Delete Comparison
+///
+/// Deletes a comparison of the authenticated user, the papers are kept.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Comparison deleted successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Comparison does not exist")
+    )
+)]
+async fn delete_comparison(
+    depot: &mut Depot,
+    comparison_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    if !state
+        .db
+        .delete_comparison(&user.uid, &comparison_id)
+        .await?
+    {
+        return Err(ServiceError::NotFound(format!(
+            "Comparison {}",
+            comparison_id.as_str()
+        )));
+    }
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
@@ -0,0 +1,440 @@
+use ai_flow_synth::llm::model::ChatMessage;
+use salvo::{
+    Depot, Response, Router,
+    oapi::{
+        RouterExt, endpoint,
+        extract::{JsonBody, PathParam, QueryParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ErrorResponse, ServiceError, ServiceResult, ValidationErrorResponse},
+    llm::{
+        LlmClient,
+        prompt::{CHAT_PROMPT, render_prompt},
+    },
+    model::{
+        conversation::{
+            Conversation, ConversationMessage, ConversationRepository, MessageRole,
+            schema::{
+                ConversationResponse, CreateConversationRequest, ListConversationsResponse,
+                ListMessagesResponse, MessageSearchResultResponse, SearchConversationsResponse,
+                SendMessageRequest, SendMessageResponse, UpdateConversationRequest,
+            },
+            title_from_message,
+        },
+        usage::UsageEvent,
+        user::User,
+    },
+    rate_limit::limit_ai,
+    resilience::record_usage,
+    utils::{time::parse_timestamp, timeout::extend_for_ai, validate::ValidatedRequest},
+};
+
+const DEFAULT_CONVERSATION_LIMIT: i64 = 50;
+const MAX_CONVERSATION_LIMIT: i64 = 200;
+const DEFAULT_MESSAGE_LIMIT: i64 = 50;
+const MAX_MESSAGE_LIMIT: i64 = 200;
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+const MAX_SEARCH_LIMIT: i64 = 100;
+// reloads of a conversation written to concurrently before giving up
+const UPDATE_ATTEMPTS: usize = 3;
+// latest messages sent back to the model as the context of the next answer
+const CHAT_HISTORY_MESSAGES: i64 = 20;
+// usage feature of the llm calls answering the messages
+const CHAT_FEATURE: &str = "chat";
+
+pub fn create_router() -> Router {
+    Router::new()
+        .push(
+            Router::new()
+                .get(list_conversations)
+                .post(create_conversation),
+        )
+        .push(Router::with_path("search").get(search_conversations))
+        .push(
+            Router::with_path("{conversation_id}")
+                .get(get_conversation)
+                .put(update_conversation)
+                .delete(delete_conversation)
+                .push(
+                    Router::with_path("messages").get(list_messages).push(
+                        Router::new()
+                            .hoop(limit_ai)
+                            .hoop(extend_for_ai)
+                            .post(send_message),
+                    ),
+                ),
+        )
+        .oapi_tag("conversation")
+}
+
+async fn get_owned_conversation(
+    state: &AppDataRef,
+    user: &User,
+    conversation_id: &str,
+) -> ServiceResult<Conversation> {
+    state
+        .db
+        .get_conversation(&user.uid, conversation_id)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound(format!("Conversation {}", conversation_id)))
+}
+
+/// Count a question and its answer on the conversation, reloading it when it
+/// was renamed or written to while the model was answering.
+async fn count_messages(
+    state: &AppDataRef,
+    user: &User,
+    mut conversation: Conversation,
+    message: &ConversationMessage,
+    reply: &ConversationMessage,
+) -> ServiceResult<Conversation> {
+    for _ in 0..UPDATE_ATTEMPTS {
+        if conversation.title.is_none() {
+            conversation.title = Some(title_from_message(&message.content));
+        }
+        conversation.message_count += 2;
+        conversation.updated_at = reply.created_at;
+        match state.db.update_conversation(conversation).await {
+            Err(ServiceError::VersionConflict(_)) => {
+                conversation =
+                    get_owned_conversation(state, user, &message.conversation_id).await?;
+            }
+            result => return result,
+        }
+    }
+    Err(ServiceError::VersionConflict(format!(
+        "Conversation {} was modified concurrently",
+        message.conversation_id
+    )))
+}
+
+/// List Conversations
+///
+/// Lists the conversations of the authenticated user, pinned ones first, then the
+/// most recently active. `limit` defaults to 50.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListConversationsResponse, description = "Conversations of the user"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn list_conversations(
+    depot: &mut Depot,
+    limit: QueryParam<i64, false>,
+) -> ServiceResult<ListConversationsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let limit = limit
+        .into_inner()
+        .unwrap_or(DEFAULT_CONVERSATION_LIMIT)
+        .clamp(1, MAX_CONVERSATION_LIMIT);
+    let conversations = state.db.get_conversations(&user.uid, limit).await?;
+    Ok(ListConversationsResponse(
+        conversations.into_iter().map(Into::into).collect(),
+    ))
+}
+
+/// Create Conversation
+///
+/// Starts an empty conversation, titled after its first message unless a title
+/// is given.
+#[endpoint(
+    status_codes(201, 401, 422),
+    responses(
+        (status_code = 201, body = ConversationResponse, description = "Conversation created"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn create_conversation(
+    depot: &mut Depot,
+    request: JsonBody<CreateConversationRequest>,
+    resp: &mut Response,
+) -> ServiceResult<ConversationResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let conversation = Conversation::new(&user.uid, request.title);
+    state.db.create_conversation(conversation.clone()).await?;
+    resp.status_code(salvo::http::StatusCode::CREATED);
+    Ok(conversation.into())
+}
+
+/// Get Conversation
+///
+/// Gets a conversation of the authenticated user, without its messages.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = ConversationResponse, description = "Conversation"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Conversation does not exist")
+    )
+)]
+async fn get_conversation(
+    depot: &mut Depot,
+    conversation_id: PathParam<String>,
+) -> ServiceResult<ConversationResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let conversation = get_owned_conversation(state, user, &conversation_id).await?;
+    Ok(conversation.into())
+}
+
+/// Update Conversation
+///
+/// Renames, pins or unpins a conversation of the authenticated user.
+#[endpoint(
+    status_codes(200, 401, 404, 409, 422),
+    responses(
+        (status_code = 200, body = ConversationResponse, description = "Conversation updated"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Conversation does not exist"),
+        (status_code = 409, description = "Conflict: The conversation was modified concurrently"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn update_conversation(
+    depot: &mut Depot,
+    conversation_id: PathParam<String>,
+    request: JsonBody<UpdateConversationRequest>,
+) -> ServiceResult<ConversationResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let mut conversation = get_owned_conversation(state, user, &conversation_id).await?;
+    if let Some(title) = request.title {
+        conversation.title = Some(title);
+    }
+    if let Some(pinned) = request.pinned {
+        conversation.pinned = pinned;
+    }
+    let conversation = state.db.update_conversation(conversation).await?;
+    Ok(conversation.into())
+}
+
+/// Delete Conversation
+///
+/// Deletes a conversation of the authenticated user with all its messages.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Conversation deleted successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Conversation does not exist")
+    )
+)]
+async fn delete_conversation(
+    depot: &mut Depot,
+    conversation_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let conversation = get_owned_conversation(state, user, &conversation_id).await?;
+    state
+        .db
+        .delete_conversation(&user.uid, &conversation.id)
+        .await?;
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+/// List Messages
+///
+/// Pages through the history of a conversation of the authenticated user, from
+/// the latest messages back: a page holds the `limit` (50 by default) messages
+/// sent before `before`, RFC 3339 or milliseconds since epoch, oldest first.
+#[endpoint(
+    status_codes(200, 401, 404, 422),
+    responses(
+        (status_code = 200, body = ListMessagesResponse, description = "Messages of the conversation"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Conversation does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Invalid timestamp")
+    )
+)]
+async fn list_messages(
+    depot: &mut Depot,
+    conversation_id: PathParam<String>,
+    before: QueryParam<String, false>,
+    limit: QueryParam<i64, false>,
+) -> ServiceResult<ListMessagesResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let conversation = get_owned_conversation(state, user, &conversation_id).await?;
+    let limit = limit
+        .into_inner()
+        .unwrap_or(DEFAULT_MESSAGE_LIMIT)
+        .clamp(1, MAX_MESSAGE_LIMIT);
+    let before = before
+        .into_inner()
+        .map(|before| {
+            parse_timestamp(&before)
+                .map(bson::DateTime::from_millis)
+                .ok_or_else(|| {
+                    ServiceError::invalid_field(
+                        "before",
+                        "invalid",
+                        format!("{} is not a RFC 3339 timestamp", before),
+                    )
+                })
+        })
+        .transpose()?;
+    // one more than the page tells whether older messages remain
+    let mut messages = state
+        .db
+        .get_messages(&conversation.id, before, limit + 1)
+        .await?;
+    let has_more = messages.len() as i64 > limit;
+    messages.truncate(limit as usize);
+    messages.reverse();
+    Ok(ListMessagesResponse {
+        items: messages.into_iter().map(Into::into).collect(),
+        has_more,
+    })
+}
+
+/// Send Message
+///
+/// Sends a message in a conversation of the authenticated user and waits for the
+/// answer of the model, which sees the latest messages of the conversation. The
+/// first message titles an untitled conversation. `model` selects a configured
+/// model instead of the default one.
+#[endpoint(
+    status_codes(200, 401, 404, 409, 422, 429),
+    responses(
+        (status_code = 200, body = SendMessageResponse, description = "The message and the answer"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Conversation does not exist"),
+        (status_code = 409, description = "Conflict: The conversation kept being modified concurrently"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error or model not available"),
+        (status_code = 429, body = ErrorResponse, description = "Too Many Requests: Rate limited or monthly llm quota exceeded")
+    )
+)]
+async fn send_message(
+    depot: &mut Depot,
+    conversation_id: PathParam<String>,
+    request: JsonBody<SendMessageRequest>,
+) -> ServiceResult<SendMessageResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let model = state.user_model(user, request.model);
+    if !state.llm.supports(&model) {
+        return Err(ServiceError::invalid_field(
+            "model",
+            "unsupported",
+            format!("Model {} is not available", model),
+        ));
+    }
+    let conversation = get_owned_conversation(state, user, &conversation_id).await?;
+    state.ensure_quota(&user.uid, &model).await?;
+
+    let mut history = state
+        .db
+        .get_messages(&conversation.id, None, CHAT_HISTORY_MESSAGES)
+        .await?;
+    history.reverse();
+    let system = render_prompt(&state.db, CHAT_PROMPT, None, &[]).await;
+    let messages = std::iter::once(ChatMessage::system(system))
+        .chain(history.into_iter().map(|message| match message.role {
+            MessageRole::User => ChatMessage::user(message.content),
+            MessageRole::Assistant => ChatMessage::assistant(message.content),
+        }))
+        .chain(std::iter::once(ChatMessage::user(&request.content)))
+        .collect::<Vec<_>>();
+    let message = ConversationMessage::new(&conversation, MessageRole::User, request.content);
+    let api_key = state.user_llm_key(&user.uid, &model).await?;
+    let answer = state
+        .llm
+        .complete_with_key(Some(&model), &messages, api_key.as_deref())
+        .await?;
+    let (input_tokens, output_tokens) = LlmClient::estimate_usage(&messages, &answer);
+    let mut usage = UsageEvent::new(&user.uid, &model, CHAT_FEATURE, input_tokens, output_tokens);
+    usage.own_key = api_key.is_some();
+    record_usage(state, usage).await;
+
+    let mut reply = ConversationMessage::new(&conversation, MessageRole::Assistant, answer);
+    reply.model = Some(model);
+    state
+        .db
+        .create_messages(vec![message.clone(), reply.clone()])
+        .await?;
+    let conversation = count_messages(state, user, conversation, &message, &reply).await?;
+
+    Ok(SendMessageResponse {
+        message: message.into(),
+        reply: reply.into(),
+        conversation: conversation.into(),
+    })
+}
+
+/// Search Conversations
+///
+/// Full text search within the messages of the conversations of the authenticated
+/// user, best match first. `limit` defaults to 20.
+#[endpoint(
+    status_codes(200, 401, 422),
+    responses(
+        (status_code = 200, body = SearchConversationsResponse, description = "Matching messages, best first"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Empty query")
+    )
+)]
+async fn search_conversations(
+    depot: &mut Depot,
+    q: QueryParam<String, true>,
+    limit: QueryParam<i64, false>,
+) -> ServiceResult<SearchConversationsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let query = q.trim();
+    if query.is_empty() {
+        return Err(ServiceError::invalid_field(
+            "q",
+            "required",
+            "Search query must not be empty",
+        ));
+    }
+    let limit = limit
+        .into_inner()
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+
+    let matches = state.db.search_messages(&user.uid, query, limit).await?;
+    let mut ids = matches
+        .iter()
+        .map(|scored| scored.message.conversation_id.clone())
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+    let conversations = state.db.get_conversations_by_ids(&user.uid, &ids).await?;
+    let results = matches
+        .into_iter()
+        .map(|scored| {
+            let conversation_title = conversations
+                .iter()
+                .find(|c| c.id == scored.message.conversation_id)
+                .and_then(|c| c.title.clone());
+            MessageSearchResultResponse {
+                conversation_id: scored.message.conversation_id.clone(),
+                conversation_title,
+                message: scored.message.into(),
+            }
+        })
+        .collect();
+    Ok(SearchConversationsResponse(results))
+}
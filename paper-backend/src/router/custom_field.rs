@@ -0,0 +1,185 @@
+use salvo::{
+    Depot, Response, Router,
+    oapi::{
+        RouterExt, endpoint,
+        extract::{JsonBody, PathParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    authz::{Access, Principal, Resource, assert_can_write, can},
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    model::{
+        custom_field::{
+            CustomField, CustomFieldRepository, FieldType,
+            schema::{CreateCustomFieldRequest, CustomFieldResponse, ListCustomFieldsResponse},
+        },
+        organization::OrganizationRepository,
+        user::User,
+    },
+    utils::validate::ValidatedRequest,
+};
+
+// fields a user, or an organization, can define
+const MAX_CUSTOM_FIELDS: usize = 50;
+
+pub fn create_router() -> Router {
+    Router::new()
+        .push(
+            Router::new()
+                .get(list_custom_fields)
+                .post(create_custom_field),
+        )
+        .push(Router::with_path("{field_id}").delete(delete_custom_field))
+        .oapi_tag("custom-field")
+}
+
+/// The organization of the user, who must administrate it to manage team fields.
+async fn check_team_admin(state: &AppDataRef, user: &User) -> ServiceResult<String> {
+    let Some(org_id) = user.org_id.as_deref() else {
+        return Err(ServiceError::invalid_field(
+            "team",
+            "no_organization",
+            "You are not a member of an organization",
+        ));
+    };
+    let org = state
+        .db
+        .get_organization_by_id(org_id)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound(format!("Organization {}", org_id)))?;
+    assert_can_write(Resource::Organization(&org), Principal::of(state, user))?;
+    Ok(org.id)
+}
+
+/// List Custom Fields
+///
+/// Lists the custom fields the papers of the authenticated user can be given:
+/// their own and those of their organization.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListCustomFieldsResponse, description = "Custom fields"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn list_custom_fields(depot: &mut Depot) -> ServiceResult<ListCustomFieldsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let fields = state
+        .db
+        .get_custom_fields(&user.uid, user.org_id.as_deref())
+        .await?;
+    Ok(ListCustomFieldsResponse(
+        fields.into_iter().map(Into::into).collect(),
+    ))
+}
+
+/// Create Custom Field
+///
+/// Defines a field the papers can be given a `text`, `number`, `date` or `select`
+/// value of, under `customFields` by its key. With `team` the field is defined for
+/// the organization of the user, who must administrate it.
+#[endpoint(
+    status_codes(201, 401, 409, 422),
+    responses(
+        (status_code = 201, body = CustomFieldResponse, description = "Custom field created"),
+        (status_code = 401, description = "Unauthorized: User not authenticated or not an organization admin"),
+        (status_code = 409, description = "Conflict: A field with the key exists"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error or too many fields")
+    )
+)]
+async fn create_custom_field(
+    depot: &mut Depot,
+    request: JsonBody<CreateCustomFieldRequest>,
+    resp: &mut Response,
+) -> ServiceResult<CustomFieldResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    if request.r#type == FieldType::Select && request.options.is_empty() {
+        return Err(ServiceError::invalid_field(
+            "options",
+            "required",
+            "A select field needs options",
+        ));
+    }
+    let owner_id = match request.team {
+        true => check_team_admin(state, user).await?,
+        false => user.uid.clone(),
+    };
+
+    let fields = state
+        .db
+        .get_custom_fields(&user.uid, user.org_id.as_deref())
+        .await?;
+    if fields.iter().any(|field| field.key == request.key) {
+        return Err(ServiceError::NameConflict(format!(
+            "A custom field {} already exists",
+            request.key
+        )));
+    }
+    if fields
+        .iter()
+        .filter(|field| field.owner_id == owner_id)
+        .count()
+        >= MAX_CUSTOM_FIELDS
+    {
+        return Err(ServiceError::invalid_field(
+            "key",
+            "limit",
+            format!("At most {} custom fields can be defined", MAX_CUSTOM_FIELDS),
+        ));
+    }
+
+    let field = CustomField::new_from_request(&owner_id, request);
+    state.db.create_custom_field(field.clone()).await?;
+    resp.status_code(salvo::http::StatusCode::CREATED);
+    Ok(field.into())
+}
+
+/// Delete Custom Field
+///
+/// Deletes a custom field of the authenticated user, or of their organization
+/// when they administrate it. The values already given to papers are kept until
+/// they are set to null.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Custom field deleted"),
+        (status_code = 401, description = "Unauthorized: User not authenticated or not an organization admin"),
+        (status_code = 404, description = "Not Found: Custom field does not exist")
+    )
+)]
+async fn delete_custom_field(
+    depot: &mut Depot,
+    field_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let principal = Principal::of(state, user);
+    let field = state
+        .db
+        .get_custom_field(&field_id)
+        .await?
+        .filter(|field| can(Access::Read, Resource::CustomField(field), principal))
+        .ok_or_else(|| ServiceError::NotFound(format!("Custom field {}", field_id)))?;
+    if field.team {
+        let org = state
+            .db
+            .get_organization_by_id(&field.owner_id)
+            .await?
+            .ok_or_else(|| ServiceError::NotFound(format!("Organization {}", field.owner_id)))?;
+        assert_can_write(Resource::Organization(&org), principal)?;
+    } else {
+        assert_can_write(Resource::CustomField(&field), principal)?;
+    }
+    state.db.delete_custom_field(&field).await?;
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
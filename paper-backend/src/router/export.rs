@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use salvo::{
+    Depot, Response, Router,
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE, HeaderValue},
+    oapi::{RouterExt, endpoint, extract::PathParam},
+};
+use serde::Serialize;
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult},
+    events::DomainEvent,
+    export::{
+        ExportOptions,
+        archive::{ArchiveFile, file_name, sanitize},
+        export_paper_as,
+    },
+    model::{
+        account::schema::{ManifestEntry, TakeoutConversation, TakeoutManifest},
+        blob::BlobRepository,
+        block::{BlockRepository, schema::BlockResponse},
+        content::get_file,
+        conversation::{ConversationRepository, schema::ConversationResponse},
+        export::{
+            ExportJob, ExportKind, ExportRepository, ExportStatus, export_file_key,
+            schema::ExportJobResponse,
+        },
+        folder::{Folder, FolderRepository, schema::FolderResponse},
+        paper::{PaperRepository, schema::PaperResponse},
+        user::{User, UserRepository, schema::UserInfoResponse},
+    },
+    router::paper::expand_paper_blocks,
+    search::folder_scope,
+};
+
+// conversations and messages per conversation put in an account archive
+const TAKEOUT_MAX_CONVERSATIONS: i64 = 10_000;
+const TAKEOUT_MAX_MESSAGES: i64 = 100_000;
+
+pub fn create_router() -> Router {
+    Router::with_path("{export_id}")
+        .get(get_export)
+        .push(Router::with_path("download").get(download_export))
+        .oapi_tag("export")
+}
+
+async fn get_owned_export(
+    state: &AppDataRef,
+    user: &User,
+    export_id: &str,
+) -> ServiceResult<ExportJob> {
+    state
+        .db
+        .get_export(&user.uid, export_id)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound("Export".to_string()))
+}
+
+/// Get Export
+///
+/// Gets the status of an export of the authenticated user, with the download
+/// link once the archive is ready.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = ExportJobResponse, description = "Status of the export"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Export does not exist")
+    )
+)]
+async fn get_export(
+    depot: &mut Depot,
+    export_id: PathParam<String>,
+) -> ServiceResult<ExportJobResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let job = get_owned_export(state, user, &export_id).await?;
+    Ok(job.into())
+}
+
+/// Download Export
+///
+/// Downloads the zip archive of a finished export of the authenticated user.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, content_type = "application/zip", description = "Zip archive"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Export does not exist or is not ready")
+    )
+)]
+async fn download_export(
+    depot: &mut Depot,
+    export_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let job = get_owned_export(state, user, &export_id).await?;
+    if job.status != ExportStatus::Ready {
+        return Err(ServiceError::NotFound("Export archive".to_string()));
+    }
+    let bytes = state
+        .db
+        .get_blob(&export_file_key(&job.id))
+        .await?
+        .ok_or_else(|| ServiceError::NotFound("Export archive".to_string()))?;
+
+    resp.headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/zip"));
+    if let Ok(disposition) =
+        HeaderValue::from_str(&format!("attachment; filename=\"{}\"", job.file_name))
+    {
+        resp.headers_mut().insert(CONTENT_DISPOSITION, disposition);
+    }
+    resp.body(bytes);
+    Ok(())
+}
+
+/// Directory of the folder inside the archive of the exported root folder,
+/// empty for the root itself.
+fn folder_dir(folders: &HashMap<&str, &Folder>, root_id: &str, folder_id: &str) -> String {
+    let mut names = Vec::new();
+    let mut current = folder_id;
+    while current != root_id {
+        let Some(folder) = folders.get(current) else {
+            break;
+        };
+        names.push(sanitize(&folder.name));
+        match folder.parent_id.as_deref() {
+            Some(parent_id) => current = parent_id,
+            None => break,
+        }
+    }
+    names.reverse();
+    names.join("/")
+}
+
+/// Add every paper of the folder and its subfolders to the archive.
+async fn build_folder_archive(
+    state: &AppDataRef,
+    job: &ExportJob,
+    archive: &mut ArchiveFile,
+) -> ServiceResult<()> {
+    let folders = state.cached_folders(&job.user_id).await?;
+    let by_id: HashMap<&str, &Folder> = folders.iter().map(|f| (f.id.as_str(), f)).collect();
+    let options = ExportOptions::default();
+
+    let mut scope = folder_scope(&folders, &job.resource_id)
+        .into_iter()
+        .collect::<Vec<_>>();
+    scope.sort();
+    let mut taken: HashMap<String, Vec<String>> = HashMap::new();
+    for folder_id in scope {
+        let dir = folder_dir(&by_id, &job.resource_id, &folder_id);
+        for mut paper in state.db.get_papers_by_folder_id(&folder_id).await? {
+            expand_paper_blocks(state, &mut paper).await?;
+            let name = file_name(
+                &paper.title,
+                job.format.extension(),
+                taken.entry(dir.clone()).or_default(),
+            );
+            let path = match dir.as_str() {
+                "" => name,
+                dir => format!("{}/{}", dir, name),
+            };
+            archive.add(&path, &export_paper_as(&paper, job.format, &options)?)?;
+        }
+    }
+    Ok(())
+}
+
+fn json_file<T: Serialize>(value: &T) -> ServiceResult<Vec<u8>> {
+    serde_json::to_vec_pretty(value).map_err(|e| ServiceError::InternalServerError(e.to_string()))
+}
+
+/// Add everything stored for the user to the archive: json files of the
+/// records, a document per paper, the uploaded files and a manifest listing
+/// them. The files are added as they are read, never all held in memory.
+async fn build_account_archive(
+    state: &AppDataRef,
+    job: &ExportJob,
+    archive: &mut ArchiveFile,
+) -> ServiceResult<()> {
+    let user_id = job.user_id.as_str();
+    let mut manifest = Vec::new();
+    let mut add_json = |path: &str,
+                        description: &str,
+                        count: Option<usize>,
+                        bytes: Vec<u8>|
+     -> ServiceResult<()> {
+        manifest.push(ManifestEntry {
+            path: path.to_string(),
+            description: description.to_string(),
+            count,
+        });
+        archive.add(path, &bytes)
+    };
+
+    if let Some(user) = state.db.get_user_by_uid(user_id).await? {
+        let user: UserInfoResponse = user.into();
+        add_json(
+            "account.json",
+            "Profile of the account",
+            None,
+            json_file(&user)?,
+        )?;
+    }
+
+    let folders = state.db.get_folders_by_user_id(user_id).await?;
+    let folders = folders
+        .into_iter()
+        .map(FolderResponse::from)
+        .collect::<Vec<_>>();
+    add_json(
+        "folders.json",
+        "Folders",
+        Some(folders.len()),
+        json_file(&folders)?,
+    )?;
+
+    let mut papers = state.db.get_papers_by_user_id(user_id).await?;
+    for paper in papers.iter_mut() {
+        expand_paper_blocks(state, paper).await?;
+    }
+    let records = papers
+        .iter()
+        .cloned()
+        .map(PaperResponse::from)
+        .collect::<Vec<_>>();
+    add_json(
+        "papers.json",
+        "Papers with their metadata, notes and summaries",
+        Some(records.len()),
+        json_file(&records)?,
+    )?;
+
+    let blocks = state.db.get_blocks_by_user_id(user_id).await?;
+    let blocks = blocks
+        .into_iter()
+        .map(BlockResponse::from)
+        .collect::<Vec<_>>();
+    add_json(
+        "blocks.json",
+        "Reusable note blocks",
+        Some(blocks.len()),
+        json_file(&blocks)?,
+    )?;
+
+    let mut conversations = Vec::new();
+    for conversation in state
+        .db
+        .get_conversations(user_id, TAKEOUT_MAX_CONVERSATIONS)
+        .await?
+    {
+        let mut messages = state
+            .db
+            .get_messages(&conversation.id, None, TAKEOUT_MAX_MESSAGES)
+            .await?;
+        messages.reverse();
+        conversations.push(TakeoutConversation {
+            conversation: ConversationResponse::from(conversation),
+            messages: messages.into_iter().map(Into::into).collect(),
+        });
+    }
+    add_json(
+        "conversations.json",
+        "Conversations with all their messages",
+        Some(conversations.len()),
+        json_file(&conversations)?,
+    )?;
+
+    let options = ExportOptions::default();
+    let mut documents = Vec::new();
+    let mut uploads = Vec::new();
+    for paper in &papers {
+        let name = file_name(&paper.title, job.format.extension(), &mut documents);
+        archive.add(
+            &format!("papers/{}", name),
+            &export_paper_as(paper, job.format, &options)?,
+        )?;
+        let Some(file_hash) = &paper.file_hash else {
+            continue;
+        };
+        if let Some(bytes) = get_file(state.db.as_ref(), file_hash).await? {
+            let name = file_name(&paper.title, "pdf", &mut uploads);
+            archive.add(&format!("files/{}", name), &bytes)?;
+        }
+    }
+    manifest.push(ManifestEntry {
+        path: "papers/".to_string(),
+        description: "A document per paper with its metadata, summary and notes".to_string(),
+        count: Some(documents.len()),
+    });
+    manifest.push(ManifestEntry {
+        path: "files/".to_string(),
+        description: "Files uploaded to the papers".to_string(),
+        count: Some(uploads.len()),
+    });
+
+    let manifest = TakeoutManifest {
+        user_id: user_id.to_string(),
+        exported_at: bson::DateTime::now().timestamp_millis(),
+        files: manifest,
+    };
+    archive.add("manifest.json", &json_file(&manifest)?)
+}
+
+/// Build the archive of an export, store it and notify the user.
+pub(super) async fn run_export(state: AppDataRef, job: ExportJob) {
+    let result = async {
+        let mut archive = ArchiveFile::create()?;
+        match job.kind {
+            ExportKind::Folder => build_folder_archive(&state, &job, &mut archive).await?,
+            ExportKind::Account => build_account_archive(&state, &job, &mut archive).await?,
+        }
+        let size = archive.finish()?;
+        state
+            .db
+            .put_blob_file(&export_file_key(&job.id), archive.path())
+            .await?;
+        Ok::<_, ServiceError>(size)
+    }
+    .await;
+    let (status, size, error) = match result {
+        Ok(size) => (ExportStatus::Ready, Some(size), None),
+        Err(e) => {
+            tracing::error!("Export {} failed: {}", job.id, e);
+            (ExportStatus::Failed, None, Some(e.to_string()))
+        }
+    };
+    if let Err(e) = state.db.finish_export(&job.id, status, size, error).await {
+        tracing::error!("Failed to update status of export {}: {}", job.id, e);
+    }
+    state.events.publish(
+        &job.user_id,
+        DomainEvent::ExportFinished {
+            export_id: job.id,
+            status,
+        },
+    );
+}
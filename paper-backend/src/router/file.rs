@@ -0,0 +1,64 @@
+use salvo::{
+    Depot, Request, Response, Router,
+    http::header::{CACHE_CONTROL, CONTENT_TYPE, HeaderValue},
+    oapi::{
+        RouterExt, endpoint,
+        extract::{PathParam, QueryParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult},
+    model::content::get_file,
+    utils::{etag::not_modified, signed_url::verify_file},
+};
+
+pub fn create_non_auth_router() -> Router {
+    Router::with_path("{file_hash}")
+        .get(download_file)
+        .oapi_tag("file")
+}
+
+/// Download File
+///
+/// Downloads an uploaded file with the signed url given by
+/// `GET /paper/{paper_id}/file/url`, without authentication. The file never
+/// changes under its url, which caches can keep until it expires.
+#[endpoint(
+    status_codes(200, 304, 401, 404),
+    responses(
+        (status_code = 200, description = "File pdf"),
+        (status_code = 304, description = "Not Modified: The file is cached already"),
+        (status_code = 401, description = "Unauthorized: Invalid or expired signature"),
+        (status_code = 404, description = "Not Found: File does not exist")
+    )
+)]
+async fn download_file(
+    req: &mut Request,
+    depot: &mut Depot,
+    file_hash: PathParam<String>,
+    expires: QueryParam<i64, true>,
+    signature: QueryParam<String, true>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let expires = expires.into_inner();
+    verify_file(&state.tenant.id, &file_hash, expires, &signature)?;
+    // the content is the hash, any copy is the right one
+    let etag = format!("\"{}\"", file_hash.as_str());
+    if !not_modified(req, resp, &etag) {
+        let bytes = get_file(state.db.as_ref(), &file_hash)
+            .await?
+            .ok_or_else(|| ServiceError::NotFound("File".to_string()))?;
+        resp.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/pdf"));
+        resp.body(bytes);
+    }
+    let max_age = (expires - chrono::Utc::now().timestamp()).max(0);
+    if let Ok(value) = HeaderValue::from_str(&format!("public, max-age={}, immutable", max_age)) {
+        resp.headers_mut().insert(CACHE_CONTROL, value);
+    }
+    Ok(())
+}
@@ -1,123 +1,502 @@
-use ai_flow_synth::utils::MongoClient;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use ai_flow_synth::llm::model::ChatMessage;
+use bson::doc;
 use salvo::{
-    Depot, Response, Router, Writer,
+    Depot, Request, Response, Router, Writer,
     oapi::{
         RouterExt, endpoint,
-        extract::{JsonBody, PathParam},
+        extract::{JsonBody, PathParam, QueryParam},
     },
 };
 
 use crate::{
     app_data::AppDataRef,
-    error::{ServiceError, ServiceResult},
+    authz::{Access, Principal, Resource, assert_can, can},
+    error::{ErrorResponse, ServiceError, ServiceResult, ValidationErrorResponse},
+    events::DomainEvent,
+    export::{ExportFormat, archive::sanitize},
+    i18n::{Locale, request_locale},
+    llm::{
+        LlmClient,
+        prompt::{FOLDER_WRAP_UP_PROMPT, render_prompt},
+    },
     model::{
+        activity::{ActivityAction, ActivityKind},
+        audit::{AuditAction, AuditLog, AuditLogRepository},
+        blob::{BlobRepository, paper_thumbnail_key},
+        content::share_file,
+        database::Database,
+        export::{ExportJob, ExportKind, ExportRepository, schema::ExportJobResponse},
         folder::{
-            Folder, FolderRepository,
+            Folder, FolderRepository, FolderType, STARRED_FOLDER_ID, SmartQuery, free_folder_name,
+            name_conflict,
             schema::{
-                CreateFolderRequest, FolderResponse, ListFoldersResponse, UpdateFolderRequest,
+                CopyFolderRequest, CreateFolderRequest, FolderPathItem, FolderResponse,
+                ImportFolderNode, ImportFoldersRequest, ImportFoldersResponse,
+                ListFolderPapersResponse, ListFoldersResponse, MoveFolderRequest,
+                MoveFolderResponse, ReorderFoldersRequest, UpdateFolderRequest,
+                WrapUpFolderResponse,
             },
         },
+        job::{Job, JobKind, JobRepository, JobStatus, schema::JobResponse},
+        organization::OrganizationRepository,
+        page::{PaperPage, PaperPageRepository},
+        paper::{Paper, PaperRepository, Progress, schema::PaperResponse},
+        quota::{QuotaResource, check_quota},
+        txn::{TxnContext, in_transaction},
+        usage::UsageEvent,
         user::User,
     },
+    pdf::thumbnail::ThumbnailSize,
+    rate_limit::limit_ai,
+    resilience::record_usage,
+    router::{
+        activity::record_activity,
+        export::run_export,
+        paper::{custom_field_filter, smart_folder_filter},
+    },
+    search::folder_scope,
+    utils::{
+        cache::CacheKey,
+        etag::{check_if_match, counted_list_etag, not_modified, set_etag, weak_etag},
+        fields::{FieldSelection, render_fields_list, selected_etag},
+        timeout::extend_for_ai,
+        validate::ValidatedRequest,
+    },
 };
 
+// max characters of a single paper sent to the llm for the wrap-up summary
+const WRAP_UP_PAPER_MAX_CHARS: usize = 4000;
+// max folders of a single imported tree
+const MAX_IMPORT_FOLDERS: usize = 500;
+// papers copied within the request, folders with more are copied in the background
+const COPY_INLINE_MAX_PAPERS: usize = 20;
+const DEFAULT_PAPER_LIMIT: i64 = 50;
+const MAX_PAPER_LIMIT: i64 = 200;
+
 pub fn create_router() -> Router {
     Router::new()
         .push(Router::new().get(list_folders).post(create_folder))
+        .push(Router::with_path("reorder").put(reorder_folders))
+        .push(Router::with_path("import").post(import_folders))
         .push(
             Router::with_path("{folder_id}")
                 .put(update_folder)
-                .push(Router::with_path("literatures").get(get_folder_literatures)),
+                .push(Router::with_path("move").post(move_folder))
+                .push(Router::with_path("copy").post(copy_folder))
+                .push(Router::with_path("literatures").get(get_folder_literatures))
+                .push(Router::with_path("papers").get(list_folder_papers))
+                .push(Router::with_path("export").get(export_folder))
+                .push(
+                    Router::with_path("wrap-up")
+                        .hoop(limit_ai)
+                        .hoop(extend_for_ai)
+                        .post(wrap_up_folder),
+                ),
         )
         .oapi_tag("folder")
 }
 
 /// List Folders
 ///
-/// Lists all folders for the authenticated user.
+/// Lists all folders for the authenticated user, `fields` selects the returned fields.
+/// Sibling folders come in their `sortOrder`, after the virtual `starred` folder
+/// listing the starred papers. Every folder comes with how many papers and subfolders
+/// it holds. Answers 304 when `If-None-Match` has the current `ETag` of the list.
 #[endpoint(
-    status_codes(200, 400, 401),
+    status_codes(200, 304, 400, 401, 422),
     responses(
         (status_code = 200, body = ListFoldersResponse, description = "List of folders"),
+        (status_code = 304, description = "Not Modified: No folder changed"),
         (status_code = 400, description = "Bad Request: Validation error"),
-        (status_code = 401, description = "Unauthorized: User not authenticated")
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Unknown field")
     )
 )]
 async fn list_folders(
+    req: &mut Request,
     depot: &mut Depot,
-    // resp: &mut Response,
-) -> ServiceResult<ListFoldersResponse> {
+    fields: QueryParam<String, false>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
     let state = depot.obtain::<AppDataRef>()?;
     let user = depot.obtain::<User>()?;
 
-    let mut folders: Vec<Folder> = state.mongo_client.get_folders_by_user_id(&user.uid).await?;
+    let selection = FieldSelection::parse::<FolderResponse>(fields.as_deref())?;
+    let mut folders = find_folders(state, &user.uid, selection.as_ref()).await?;
 
     if folders.is_empty() {
-        ensure_folder_initialized(&state.mongo_client, &user.uid).await?;
-        folders = state.mongo_client.get_folders_by_user_id(&user.uid).await?;
+        provision_folders(&state.db, user, request_locale(depot)).await?;
+        state.invalidate(&[CacheKey::Folders(&user.uid)]).await;
+        folders = find_folders(state, &user.uid, selection.as_ref()).await?;
     }
 
-    Ok(ListFoldersResponse(
-        folders.into_iter().map(Into::into).collect(),
-    ))
+    let paper_counts = state.db.count_papers_by_folder(&user.uid).await?;
+    let etag = counted_list_etag(folders.iter().map(|f| {
+        let papers = paper_counts.get(&f.id).copied().unwrap_or_default();
+        (f.id.as_str(), f.updated_at, f.version, papers)
+    }));
+    let etag = selected_etag(selection.as_ref(), etag);
+    if not_modified(req, resp, &etag) {
+        return Ok(());
+    }
+
+    // stable, folders of the same rank keep their creation order
+    folders.sort_by_key(|f| f.sort_order);
+    folders.insert(0, Folder::starred_folder(&user.uid, request_locale(depot)));
+    let folders = counted_folders(folders, &paper_counts);
+    render_fields_list(resp, selection.as_ref(), folders);
+    Ok(())
+}
+
+/// The responses of the folders with how many papers and subfolders each holds,
+/// the papers of the smart folders are searched and left uncounted.
+fn counted_folders(
+    folders: Vec<Folder>,
+    paper_counts: &HashMap<String, u64>,
+) -> Vec<FolderResponse> {
+    let mut child_counts: HashMap<String, u64> = HashMap::new();
+    for parent_id in folders.iter().filter_map(|f| f.parent_id.clone()) {
+        *child_counts.entry(parent_id).or_default() += 1;
+    }
+    folders
+        .into_iter()
+        .map(|folder| {
+            let paper_count = (!matches!(folder.r#type, FolderType::Smart))
+                .then(|| paper_counts.get(&folder.id).copied().unwrap_or_default());
+            let child_count = child_counts.get(&folder.id).copied().unwrap_or_default();
+            FolderResponse {
+                paper_count,
+                child_count: Some(child_count),
+                ..folder.into()
+            }
+        })
+        .collect()
+}
+
+/// Rank after the last of the folders under the parent.
+fn next_sort_order<'a>(
+    folders: impl IntoIterator<Item = &'a Folder>,
+    parent_id: Option<&str>,
+) -> u32 {
+    folders
+        .into_iter()
+        .filter(|f| f.parent_id.as_deref() == parent_id)
+        .map(|f| f.sort_order + 1)
+        .max()
+        .unwrap_or_default()
+}
+
+/// The name of the folder, numbered when a sibling has it already.
+fn free_sibling_name<'a>(folders: impl IntoIterator<Item = &'a Folder>, folder: &Folder) -> String {
+    let taken = folders
+        .into_iter()
+        .filter(|f| f.parent_id == folder.parent_id && f.id != folder.id)
+        .map(|f| f.name.as_str());
+    free_folder_name(taken, &folder.name)
+}
+
+async fn find_folders(
+    state: &AppDataRef,
+    user_id: &str,
+    selection: Option<&FieldSelection>,
+) -> ServiceResult<Vec<Folder>> {
+    match selection {
+        Some(selection) => {
+            state
+                .db
+                .get_folders_projected(user_id, selection.projection())
+                .await
+        }
+        None => state.cached_folders(user_id).await,
+    }
+}
+
+/// Check the scope and custom field filters of the query of a smart folder.
+async fn check_smart_query(
+    state: &AppDataRef,
+    user: &User,
+    query: &SmartQuery,
+) -> ServiceResult<()> {
+    if let Some(folder_id) = query.folder_id.as_deref() {
+        let folders = state.cached_folders(&user.uid).await?;
+        if !folders
+            .iter()
+            .any(|folder| folder.id == folder_id && !folder.is_smart())
+        {
+            return Err(ServiceError::invalid_field(
+                "query.folderId",
+                "not_found",
+                "Folder does not exist",
+            ));
+        }
+    }
+    custom_field_filter(state, user, query.field.as_deref()).await?;
+    Ok(())
 }
 
 /// Create Folder
 ///
-/// Creates a new user-defined folder for the authenticated user.
+/// Creates a new user-defined folder for the authenticated user. With a `query`
+/// the folder is a smart folder, listing the papers matching the saved search.
+/// The name must be free among the sibling folders, unless `auto_rename` numbers
+/// it: "name (2)". The folder must fit into the folder count and depth quotas.
 #[endpoint(
-    status_codes(201, 400, 401),
+    status_codes(201, 401, 403, 409, 422),
     responses(
         (status_code = 201, body = FolderResponse, description = "Folder created successfully"),
-        (status_code = 400, description = "Bad Request: Validation error"),
-        (status_code = 401, description = "Unauthorized: User not authenticated")
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 403, description = "Forbidden: Folder count or depth quota exceeded"),
+        (status_code = 409, description = "Conflict: A sibling folder has the same name"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
     )
 )]
 async fn create_folder(
+    req: &mut Request,
     depot: &mut Depot,
     request: JsonBody<CreateFolderRequest>,
+    auto_rename: QueryParam<bool, false>,
     resp: &mut Response,
 ) -> ServiceResult<FolderResponse> {
     let state = depot.obtain::<AppDataRef>()?;
     let user = depot.obtain::<User>()?;
 
     // Validate the request
+    let request = request.into_inner().validated()?;
+    state.ensure_folder_quota(user, 1).await?;
     if let Some(parent_id) = request.parent_id.as_ref() {
-        if state
-            .mongo_client
-            .get_folder_by_id(parent_id)
-            .await?
-            .is_none()
-        {
-            return Err(ServiceError::BadRequest(
-                "Parent folder does not exist".to_string(),
-            ));
-        }
+        let max_depth = state.quotas(user).max_folder_depth;
+        let principal = Principal::of(state, user);
+        check_parent_folder(&state.db, parent_id, principal, max_depth, 1).await?;
+    }
+    if let Some(query) = request.query.as_ref() {
+        check_smart_query(state, user, query).await?;
     }
 
-    let folder = Folder::new_from_request(&user.uid, request.0);
-    state.mongo_client.create_folder(folder.clone()).await?;
+    let mut folder = Folder::new_from_request(&user.uid, request);
+    let folders = state.cached_folders(&user.uid).await?;
+    folder.sort_order = next_sort_order(&folders, folder.parent_id.as_deref());
+    if auto_rename.into_inner().unwrap_or_default() {
+        folder.name = free_sibling_name(&folders, &folder);
+    }
+    let mut log = AuditLog::new(
+        &user.uid,
+        AuditAction::FolderCreated,
+        Some(req.remote_addr().to_string()),
+    );
+    log.detail = Some(folder.id.clone());
+    // the folder is not created without its audit log
+    in_transaction(state.db.as_ref(), |db, txn| {
+        let (folder, log) = (folder.clone(), log.clone());
+        Box::pin(async move {
+            db.create_folder(txn, folder).await?;
+            db.create_audit_log(txn, log).await
+        })
+    })
+    .await?;
+    state.invalidate(&[CacheKey::Folders(&user.uid)]).await;
+    state.events.publish(
+        &user.uid,
+        DomainEvent::FolderCreated {
+            folder_id: folder.id.clone(),
+            name: folder.name.clone(),
+        },
+    );
     resp.status_code(salvo::http::StatusCode::CREATED);
     Ok(folder.into())
 }
 
+/// Import Folders
+///
+/// Creates a whole tree of folders at once, under `parentId` or at the root.
+/// Every folder has a `key` chosen by the client, answered with the id of the
+/// created folder. Either every folder is created or none is. The names must be
+/// free among the sibling folders, unless `auto_rename` numbers them, and the tree
+/// must fit into the folder count and depth quotas.
+#[endpoint(
+    status_codes(201, 401, 403, 409, 422),
+    responses(
+        (status_code = 201, body = ImportFoldersResponse, description = "Folders created successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 403, description = "Forbidden: Folder count or depth quota exceeded"),
+        (status_code = 409, description = "Conflict: A sibling folder has the same name"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error, duplicate key or too many folders")
+    )
+)]
+async fn import_folders(
+    req: &mut Request,
+    depot: &mut Depot,
+    request: JsonBody<ImportFoldersRequest>,
+    auto_rename: QueryParam<bool, false>,
+    resp: &mut Response,
+) -> ServiceResult<ImportFoldersResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let existing = state.cached_folders(&user.uid).await?;
+    let (folders, ids) = plan_import(
+        &user.uid,
+        request.parent_id.as_deref(),
+        &request.folders,
+        &existing,
+        auto_rename.into_inner().unwrap_or_default(),
+    )?;
+    if folders.len() > MAX_IMPORT_FOLDERS {
+        return Err(ServiceError::invalid_field(
+            "folders",
+            "limit",
+            format!(
+                "At most {} folders can be imported at once",
+                MAX_IMPORT_FOLDERS
+            ),
+        ));
+    }
+    let count = folders.len() as u64;
+    state.ensure_folder_quota(user, count).await?;
+    let max_depth = state.quotas(user).max_folder_depth;
+    let height = tree_height(&folders);
+    match request.parent_id.as_deref() {
+        Some(parent_id) => {
+            let principal = Principal::of(state, user);
+            check_parent_folder(&state.db, parent_id, principal, max_depth, height).await?;
+        }
+        None => check_quota(QuotaResource::FolderDepth, Some(max_depth), 0, height)?,
+    }
+
+    let logs = folders
+        .iter()
+        .map(|folder| {
+            let mut log = AuditLog::new(
+                &user.uid,
+                AuditAction::FolderCreated,
+                Some(req.remote_addr().to_string()),
+            );
+            log.detail = Some(folder.id.clone());
+            log
+        })
+        .collect::<Vec<_>>();
+    // the tree is created as a whole, with the audit logs
+    in_transaction(state.db.as_ref(), |db, txn| {
+        let (folders, logs) = (folders.clone(), logs.clone());
+        Box::pin(async move {
+            for folder in folders {
+                db.create_folder(txn, folder).await?;
+            }
+            for log in logs {
+                db.create_audit_log(txn, log).await?;
+            }
+            Ok(())
+        })
+    })
+    .await?;
+    state.invalidate(&[CacheKey::Folders(&user.uid)]).await;
+    for folder in &folders {
+        state.events.publish(
+            &user.uid,
+            DomainEvent::FolderCreated {
+                folder_id: folder.id.clone(),
+                name: folder.name.clone(),
+            },
+        );
+    }
+    resp.status_code(salvo::http::StatusCode::CREATED);
+    Ok(ImportFoldersResponse {
+        ids,
+        folders: folders.into_iter().map(Into::into).collect(),
+    })
+}
+
+/// The folders of an imported tree, parents before their subfolders, and their
+/// ids by key. Names taken by a sibling are numbered with `auto_rename`, or fail.
+fn plan_import(
+    user_id: &str,
+    parent_id: Option<&str>,
+    nodes: &[ImportFolderNode],
+    existing: &[Folder],
+    auto_rename: bool,
+) -> ServiceResult<(Vec<Folder>, BTreeMap<String, String>)> {
+    let mut folders: Vec<Folder> = Vec::new();
+    let mut ids = BTreeMap::new();
+    let mut pending = nodes
+        .iter()
+        .rev()
+        .map(|node| (parent_id.map(String::from), node))
+        .collect::<Vec<_>>();
+    while let Some((parent_id, node)) = pending.pop() {
+        let mut folder = Folder::new_from_import(user_id, parent_id, node);
+        if ids.insert(node.key.clone(), folder.id.clone()).is_some() {
+            return Err(ServiceError::invalid_field(
+                "key",
+                "duplicate",
+                format!("Key {} is given twice", node.key),
+            ));
+        }
+        let siblings = existing
+            .iter()
+            .chain(&folders)
+            .filter(|f| f.parent_id == folder.parent_id)
+            .collect::<Vec<_>>();
+        if auto_rename {
+            folder.name = free_folder_name(siblings.iter().map(|f| f.name.as_str()), &folder.name);
+        } else if siblings.iter().any(|f| f.name == folder.name) {
+            return Err(name_conflict(&folder));
+        }
+        folder.sort_order = next_sort_order(siblings, folder.parent_id.as_deref());
+        pending.extend(
+            node.children
+                .iter()
+                .rev()
+                .map(|child| (Some(folder.id.clone()), child)),
+        );
+        folders.push(folder);
+    }
+    Ok((folders, ids))
+}
+
+/// Number of levels of a tree of new folders, listed parents first.
+fn tree_height(folders: &[Folder]) -> u64 {
+    let mut levels = HashMap::new();
+    for folder in folders {
+        let parent_level = folder
+            .parent_id
+            .as_ref()
+            .and_then(|id| levels.get(id))
+            .copied()
+            .unwrap_or(0);
+        levels.insert(folder.id.clone(), parent_level + 1);
+    }
+    levels.into_values().max().unwrap_or_default()
+}
+
 /// Update Folder
 ///
-/// Updates an existing folder for the authenticated user.
+/// Updates an existing folder for the authenticated user. Only smart folders
+/// can be given a new `query`. `auto_rename` numbers the name when a sibling
+/// folder has it already.
 #[endpoint(
-    status_codes(200, 400, 401, 404),
+    status_codes(200, 400, 401, 403, 404, 409, 412, 422, 428),
     request_body(content = UpdateFolderRequest, description = "Update folder details"),
     responses(
         (status_code = 200, body = FolderResponse, description = "Folder updated successfully"),
-        (status_code = 400, description = "Bad Request: Validation error"),
+        (status_code = 400, description = "Bad Request: Invalid folder ID"),
         (status_code = 401, description = "Unauthorized: User not authenticated"),
-        (status_code = 404, description = "Not Found: Folder does not exist")
+        (status_code = 403, description = "Forbidden: Folder depth quota exceeded"),
+        (status_code = 404, description = "Not Found: Folder does not exist"),
+        (status_code = 409, description = "Conflict: The folder was modified concurrently, or a sibling folder has the same name"),
+        (status_code = 412, description = "Precondition Failed: The folder was modified meanwhile"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error"),
+        (status_code = 428, description = "Precondition Required: If-Match header is missing")
     )
 )]
 async fn update_folder(
+    req: &mut Request,
     depot: &mut Depot,
     folder_id: PathParam<String>,
     request: JsonBody<UpdateFolderRequest>,
+    auto_rename: QueryParam<bool, false>,
+    resp: &mut Response,
 ) -> ServiceResult<FolderResponse> {
     let state = depot.obtain::<AppDataRef>()?;
     let user = depot.obtain::<User>()?;
@@ -129,67 +508,674 @@ async fn update_folder(
         ));
     }
 
-    // Fetch the folder by ID
-    let mut folder = state
-        .mongo_client
-        .get_folder_by_id(&folder_id)
+    let mut folder = get_folder(state, &folder_id, user, Access::Write).await?;
+    check_if_match(req, &weak_etag(folder.updated_at, folder.version))?;
+
+    // Update the folder details
+    let request = request.into_inner().validated()?;
+    if let Some(name) = request.name {
+        folder.name = name;
+    }
+    folder.description = request.description;
+    folder.color = request.color;
+    folder.icon = request.icon;
+    if let Some(parent_id) = request.parent_id {
+        let folders = user_folder_map(&state.db, &user.uid).await?;
+        let max_depth = state.quotas(user).max_folder_depth;
+        check_move_target(&folders, &folder.id, &parent_id, max_depth)?;
+        if folder.parent_id.as_ref() != Some(&parent_id) {
+            folder.sort_order = next_sort_order(folders.values(), Some(&parent_id));
+        }
+        folder.parent_id = Some(parent_id);
+    }
+    if let Some(query) = request.query {
+        if !folder.is_smart() {
+            return Err(ServiceError::invalid_field(
+                "query",
+                "not_smart",
+                "Only the query of a smart folder can be changed",
+            ));
+        }
+        check_smart_query(state, user, &query).await?;
+        folder.query = Some(query);
+    }
+    if auto_rename.into_inner().unwrap_or_default() {
+        let folders = state.db.get_folders_by_user_id(&user.uid).await?;
+        folder.name = free_sibling_name(&folders, &folder);
+    }
+    folder.updated_at = bson::DateTime::now();
+
+    let updated_folder = state.db.update_folder(folder).await?;
+    state.invalidate(&[CacheKey::Folders(&user.uid)]).await;
+    record_activity(
+        state,
+        &user.uid,
+        ActivityKind::Folder,
+        &updated_folder.id,
+        ActivityAction::Edited,
+    );
+    state
+        .events
+        .publish(&user.uid, folder_updated(&updated_folder));
+    set_etag(
+        resp,
+        &weak_etag(updated_folder.updated_at, updated_folder.version),
+    );
+    Ok(updated_folder.into())
+}
+
+/// Move Folder
+///
+/// Moves a folder with all its subfolders under another parent folder,
+/// or to the root when no parent is given. `If-Match` must have the `ETag` of the folder.
+/// `auto_rename` numbers the name when a folder under the new parent has it already.
+#[endpoint(
+    status_codes(200, 401, 403, 404, 409, 412, 422, 428),
+    responses(
+        (status_code = 200, body = MoveFolderResponse, description = "Folder moved successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 403, description = "Forbidden: Folder depth quota exceeded"),
+        (status_code = 404, description = "Not Found: Folder does not exist"),
+        (status_code = 409, description = "Conflict: The folder was modified concurrently, or a folder under the new parent has the same name"),
+        (status_code = 412, description = "Precondition Failed: The folder was modified meanwhile"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Invalid target folder"),
+        (status_code = 428, description = "Precondition Required: If-Match header is missing")
+    )
+)]
+async fn move_folder(
+    req: &mut Request,
+    depot: &mut Depot,
+    folder_id: PathParam<String>,
+    request: JsonBody<MoveFolderRequest>,
+    auto_rename: QueryParam<bool, false>,
+    resp: &mut Response,
+) -> ServiceResult<MoveFolderResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let mut folders = user_folder_map(&state.db, &user.uid).await?;
+    let Some(mut folder) = folders.get(folder_id.as_str()).cloned() else {
+        // folders of other users are reported as missing, too
+        return Err(ServiceError::FolderNotFound(folder_id.to_string()));
+    };
+    check_if_match(req, &weak_etag(folder.updated_at, folder.version))?;
+    if let Some(parent_id) = request.parent_id.as_deref() {
+        let max_depth = state.quotas(user).max_folder_depth;
+        check_move_target(&folders, &folder.id, parent_id, max_depth)?;
+    }
+
+    if folder.parent_id != request.parent_id {
+        folder.sort_order = next_sort_order(folders.values(), request.parent_id.as_deref());
+    }
+    folder.parent_id = request.parent_id;
+    if auto_rename.into_inner().unwrap_or_default() {
+        folder.name = free_sibling_name(folders.values(), &folder);
+    }
+    folder.updated_at = bson::DateTime::now();
+    let folder = state.db.update_folder(folder).await?;
+    state.invalidate(&[CacheKey::Folders(&user.uid)]).await;
+    state.events.publish(&user.uid, folder_updated(&folder));
+    set_etag(resp, &weak_etag(folder.updated_at, folder.version));
+    folders.insert(folder.id.clone(), folder.clone());
+
+    let path = folder_path(&folders, &folder.id);
+    Ok(MoveFolderResponse {
+        folder: folder.into(),
+        path,
+    })
+}
+
+/// Copy Folder
+///
+/// Copies a folder with all its subfolders and papers under another parent
+/// folder, or next to the folder when no parent is given. The copy is numbered
+/// when a folder under the parent has the name already. Papers are copied
+/// without their files, unless `includeFiles` is set. The folders are created
+/// at once, the papers by a job: answers 201 with the finished job for small
+/// folders, 202 with the running job otherwise, see `GET /api/jobs/{job_id}`.
+/// The copy must fit into the folder count, depth and storage quotas.
+#[endpoint(
+    status_codes(201, 202, 401, 403, 404, 422),
+    responses(
+        (status_code = 201, body = JobResponse, description = "Folder copied"),
+        (status_code = 202, body = JobResponse, description = "Folders copied, papers being copied"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 403, description = "Forbidden: Folder count, depth or storage quota exceeded"),
+        (status_code = 404, description = "Not Found: Folder does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Invalid target folder")
+    )
+)]
+async fn copy_folder(
+    req: &mut Request,
+    depot: &mut Depot,
+    folder_id: PathParam<String>,
+    request: JsonBody<CopyFolderRequest>,
+    resp: &mut Response,
+) -> ServiceResult<JobResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let folders = user_folder_map(&state.db, &user.uid).await?;
+    let Some(source) = folders.get(folder_id.as_str()) else {
+        // folders of other users are reported as missing, too
+        return Err(ServiceError::FolderNotFound(folder_id.to_string()));
+    };
+    let parent_id = request.parent_id.or_else(|| source.parent_id.clone());
+    let max_depth = state.quotas(user).max_folder_depth;
+    let height = subtree_height(&folders, &source.id, max_depth);
+    match parent_id.as_deref() {
+        Some(parent_id) => {
+            let principal = Principal::of(state, user);
+            check_parent_folder(&state.db, parent_id, principal, max_depth, height).await?;
+        }
+        None => check_quota(QuotaResource::FolderDepth, Some(max_depth), 0, height)?,
+    }
+
+    let (copies, copy_ids) = plan_copy(&folders, source, parent_id);
+    let count = copies.len() as u64;
+    state.ensure_folder_quota(user, count).await?;
+    let mut papers = Vec::new();
+    for folder in folders.values().filter(|f| copy_ids.contains_key(&f.id)) {
+        papers.extend(state.db.get_papers_by_folder_id(&folder.id).await?);
+    }
+    if request.include_files {
+        let bytes = papers.iter().filter_map(|p| p.file_size).sum();
+        state.ensure_storage_quota(user, bytes, 0).await?;
+    }
+
+    let root = copies[0].clone();
+    let mut log = AuditLog::new(
+        &user.uid,
+        AuditAction::FolderCreated,
+        Some(req.remote_addr().to_string()),
+    );
+    log.detail = Some(root.id.clone());
+    // the copied tree is created as a whole, the papers follow
+    in_transaction(state.db.as_ref(), |db, txn| {
+        let (copies, log) = (copies.clone(), log.clone());
+        Box::pin(async move {
+            for folder in copies {
+                db.create_folder(txn, folder).await?;
+            }
+            db.create_audit_log(txn, log).await
+        })
+    })
+    .await?;
+    state.invalidate(&[CacheKey::Folders(&user.uid)]).await;
+    for folder in &copies {
+        state.events.publish(
+            &user.uid,
+            DomainEvent::FolderCreated {
+                folder_id: folder.id.clone(),
+                name: folder.name.clone(),
+            },
+        );
+    }
+
+    let mut job = Job::new(&user.uid, JobKind::FolderCopy, &source.id);
+    job.result_id = Some(root.id.clone());
+    job.progress = Some(Progress {
+        done: 0,
+        total: papers.len() as u32,
+    });
+    state.db.create_job(job.clone()).await?;
+    let copy = PaperCopy {
+        papers,
+        folder_ids: copy_ids,
+        include_files: request.include_files,
+    };
+    if copy.papers.len() > COPY_INLINE_MAX_PAPERS {
+        state.jobs.spawn(run_copy(state.clone(), job.clone(), copy));
+        resp.status_code(salvo::http::StatusCode::ACCEPTED);
+        return Ok(job.into());
+    }
+    run_copy(state.clone(), job.clone(), copy).await;
+    let job = state
+        .db
+        .get_job(&user.uid, &job.id)
         .await?
-        .ok_or_else(|| ServiceError::NotFound(format!("Folder with ID {} not found", folder_id)))?;
+        .ok_or_else(|| ServiceError::NotFound("Job".to_string()))?;
+    resp.status_code(salvo::http::StatusCode::CREATED);
+    Ok(job.into())
+}
 
-    // Ensure the folder belongs to the authenticated user
-    if folder.user_id != user.uid {
-        return Err(ServiceError::Unauthorized(
-            "You do not have permission to update this folder".to_string(),
-        ));
+/// Copies of the folder and its subfolders under the parent, parents before
+/// their subfolders, and the id of each copy by id of the original. The copy of
+/// the folder goes last among its new siblings, numbered if its name is taken.
+fn plan_copy(
+    folders: &HashMap<String, Folder>,
+    source: &Folder,
+    parent_id: Option<String>,
+) -> (Vec<Folder>, HashMap<String, String>) {
+    let mut root = source.copy_under(parent_id);
+    root.sort_order = next_sort_order(folders.values(), root.parent_id.as_deref());
+    root.name = free_sibling_name(folders.values(), &root);
+    let mut copy_ids = HashMap::from([(source.id.clone(), root.id.clone())]);
+    let mut copies = vec![root];
+    let mut level = vec![source.id.clone()];
+    while !level.is_empty() {
+        let mut children = folders
+            .values()
+            .filter(|f| f.parent_id.as_ref().is_some_and(|p| level.contains(p)))
+            // guard against corrupted (cyclic) trees
+            .filter(|f| !copy_ids.contains_key(&f.id))
+            .collect::<Vec<_>>();
+        children.sort_by_key(|f| f.sort_order);
+        level = children.iter().map(|f| f.id.clone()).collect();
+        for child in children {
+            let parent_id = child.parent_id.as_ref().map(|p| copy_ids[p].clone());
+            let copy = child.copy_under(parent_id);
+            copy_ids.insert(child.id.clone(), copy.id.clone());
+            copies.push(copy);
+        }
     }
+    (copies, copy_ids)
+}
 
-    // Update the folder details
-    if let Some(name) = request.0.name {
-        folder.name = name;
+/// The papers of a copied folder, and the copies of their folders by id.
+struct PaperCopy {
+    papers: Vec<Paper>,
+    folder_ids: HashMap<String, String>,
+    include_files: bool,
+}
+
+/// Copy the papers into the copies of their folders, recording the progress
+/// on the job, then mark the job done.
+async fn run_copy(state: AppDataRef, job: Job, copy: PaperCopy) {
+    let total = copy.papers.len() as u32;
+    let result = async {
+        for (done, paper) in copy.papers.iter().enumerate() {
+            let folder_id = &copy.folder_ids[&paper.folder_id];
+            copy_paper(&state, paper, folder_id, copy.include_files).await?;
+            let progress = Progress {
+                done: done as u32 + 1,
+                total,
+            };
+            state.db.set_job_progress(&job.id, progress).await?;
+        }
+        Ok(())
+    }
+    .await;
+    let (status, error) = match result {
+        Ok(()) => (JobStatus::Done, None),
+        Err(e) => {
+            tracing::error!("Copy job {} failed: {}", job.id, e);
+            (JobStatus::Failed, Some(e.to_string()))
+        }
+    };
+    if let Err(e) = state
+        .db
+        .finish_job(&job.id, status, job.result_id.clone(), error)
+        .await
+    {
+        tracing::error!("Failed to update status of job {}: {}", job.id, e);
     }
-    folder.description = request.0.description;
-    if let Some(parent_id) = request.0.parent_id.as_ref() {
-        if state
-            .mongo_client
-            .get_folder_by_id(parent_id)
+}
+
+/// Copy the paper into the folder, with its file and the text of its pages.
+async fn copy_paper(
+    state: &AppDataRef,
+    paper: &Paper,
+    folder_id: &str,
+    include_files: bool,
+) -> ServiceResult<()> {
+    let mut copy = paper.copy_into(folder_id);
+    let file = match (include_files, &paper.file_hash) {
+        (true, Some(hash)) => share_file(state.db.as_ref(), hash).await?,
+        _ => false,
+    };
+    if file {
+        for size in ThumbnailSize::ALL {
+            if let Some(png) = state
+                .db
+                .get_blob(&paper_thumbnail_key(&paper.id, size))
+                .await?
+            {
+                state
+                    .db
+                    .put_blob(&paper_thumbnail_key(&copy.id, size), &png)
+                    .await?;
+            }
+        }
+        let pages = state
+            .db
+            .get_paper_pages(&paper.id, None)
             .await?
-            .is_none()
-        {
-            return Err(ServiceError::BadRequest(
-                "Parent folder does not exist".to_string(),
+            .into_iter()
+            .map(|page| PaperPage::new(&copy.id, page.page, page.text))
+            .collect();
+        state.db.replace_paper_pages(&copy.id, pages).await?;
+        copy.file_hash = paper.file_hash.clone();
+        copy.file_size = paper.file_size;
+        copy.text_status = paper.text_status;
+        copy.page_count = paper.page_count;
+    }
+    state.db.create_paper(copy.clone()).await?;
+    state.events.publish(
+        &copy.user_id,
+        DomainEvent::PaperCreated {
+            paper_id: copy.id,
+            folder_id: copy.folder_id,
+        },
+    );
+    Ok(())
+}
+
+/// Reorder Folders
+///
+/// Moves the given sibling folders to the front of their parent, or of the root
+/// when no parent is given, in the given order. The other folders keep their
+/// relative order after them. Answers the reordered siblings.
+#[endpoint(
+    status_codes(200, 401, 422),
+    responses(
+        (status_code = 200, body = ListFoldersResponse, description = "Reordered sibling folders"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Folder not under the parent or given twice")
+    )
+)]
+async fn reorder_folders(
+    depot: &mut Depot,
+    request: JsonBody<ReorderFoldersRequest>,
+) -> ServiceResult<ListFoldersResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let mut siblings = state
+        .db
+        .get_folders_by_user_id(&user.uid)
+        .await?
+        .into_iter()
+        .filter(|f| f.parent_id == request.parent_id)
+        .collect::<Vec<_>>();
+    siblings.sort_by_key(|f| f.sort_order);
+
+    let mut seen = HashSet::new();
+    for folder_id in &request.folder_ids {
+        if !seen.insert(folder_id.as_str()) {
+            return Err(ServiceError::invalid_field(
+                "folderIds",
+                "duplicate",
+                format!("Folder {} is given twice", folder_id),
+            ));
+        }
+        if !siblings.iter().any(|f| &f.id == folder_id) {
+            return Err(ServiceError::invalid_field(
+                "folderIds",
+                "not_sibling",
+                format!("Folder {} is not under the parent", folder_id),
             ));
         }
-        folder.parent_id = Some(parent_id.clone());
     }
+    let order = request
+        .folder_ids
+        .iter()
+        .cloned()
+        .chain(
+            siblings
+                .iter()
+                .filter(|f| !seen.contains(f.id.as_str()))
+                .map(|f| f.id.clone()),
+        )
+        .collect::<Vec<_>>();
+    state.db.reorder_folders(&user.uid, &order).await?;
+    state.invalidate(&[CacheKey::Folders(&user.uid)]).await;
 
-    let updated_folder = state.mongo_client.update_folder(folder).await?;
-    Ok(updated_folder.into())
+    let mut folders = state
+        .db
+        .get_folders_by_user_id(&user.uid)
+        .await?
+        .into_iter()
+        .filter(|f| f.parent_id == request.parent_id)
+        .collect::<Vec<_>>();
+    folders.sort_by_key(|f| f.sort_order);
+    Ok(ListFoldersResponse(
+        folders.into_iter().map(Into::into).collect(),
+    ))
+}
+
+fn folder_updated(folder: &Folder) -> DomainEvent {
+    DomainEvent::FolderUpdated {
+        folder_id: folder.id.clone(),
+        name: folder.name.clone(),
+    }
 }
 
-async fn ensure_folder_initialized(mongo_client: &MongoClient, user_id: &str) -> ServiceResult<()> {
-    let default_system_folder = Folder::default_system_folder(user_id);
-    mongo_client.create_folder(default_system_folder).await?;
-    // todo: initialize system defined folders for user at first login
-    // maybe move to user creation logic is more elegant
+/// Fetch the folder by ID and ensure the user may access it.
+async fn get_folder(
+    state: &AppDataRef,
+    folder_id: &str,
+    user: &User,
+    access: Access,
+) -> ServiceResult<Folder> {
+    let folder = state
+        .db
+        .get_folder_by_id(folder_id)
+        .await?
+        .ok_or_else(|| ServiceError::FolderNotFound(folder_id.to_string()))?;
+    let principal = Principal::of(state, user);
+    assert_can(access, Resource::Folder(&folder), principal)?;
+    Ok(folder)
+}
+
+/// All folders of the user, by id.
+async fn user_folder_map(
+    db: &dyn Database,
+    user_id: &str,
+) -> ServiceResult<HashMap<String, Folder>> {
+    let folders = db.get_folders_by_user_id(user_id).await?;
+    Ok(folders
+        .into_iter()
+        .map(|folder| (folder.id.clone(), folder))
+        .collect())
+}
+
+/// Ensure the folder can be placed under the parent: the parent exists, belongs
+/// to the user, is not the folder itself or one of its descendants, and the
+/// moved subtree still fits into the depth quota.
+fn check_move_target(
+    folders: &HashMap<String, Folder>,
+    folder_id: &str,
+    parent_id: &str,
+    max_depth: u64,
+) -> ServiceResult<()> {
+    let Some(parent) = folders.get(parent_id) else {
+        return Err(ServiceError::invalid_field(
+            "parentId",
+            "not_found",
+            "Parent folder does not exist",
+        ));
+    };
+    if parent.is_smart() {
+        return Err(ServiceError::invalid_field(
+            "parentId",
+            "smart_folder",
+            "Smart folders cannot have subfolders",
+        ));
+    }
+    let ancestors = folder_ancestors(folders, parent_id);
+    if ancestors.iter().any(|id| id == folder_id) {
+        return Err(ServiceError::invalid_field(
+            "parentId",
+            "cycle",
+            "A folder cannot be moved into itself or one of its subfolders",
+        ));
+    }
+    check_quota(
+        QuotaResource::FolderDepth,
+        Some(max_depth),
+        ancestors.len() as u64,
+        subtree_height(folders, folder_id, max_depth),
+    )
+}
+
+/// Ids from the folder up to the root, starting with the folder itself.
+fn folder_ancestors(folders: &HashMap<String, Folder>, folder_id: &str) -> Vec<String> {
+    let mut ancestors = vec![folder_id.to_string()];
+    let mut parent_id = folders.get(folder_id).and_then(|f| f.parent_id.as_ref());
+    while let Some(id) = parent_id {
+        // guard against corrupted (cyclic) trees
+        if ancestors.contains(id) {
+            break;
+        }
+        ancestors.push(id.clone());
+        parent_id = folders.get(id).and_then(|f| f.parent_id.as_ref());
+    }
+    ancestors
+}
+
+/// Number of levels of the subtree rooted at the folder, a leaf has height 1.
+/// Counted up to one level over the max depth.
+fn subtree_height(folders: &HashMap<String, Folder>, folder_id: &str, max_depth: u64) -> u64 {
+    let mut height = 0;
+    let mut level = vec![folder_id.to_string()];
+    while !level.is_empty() && height <= max_depth {
+        height += 1;
+        level = folders
+            .values()
+            .filter(|f| f.parent_id.as_ref().is_some_and(|p| level.contains(p)))
+            .map(|f| f.id.clone())
+            .collect();
+    }
+    height
+}
+
+/// Path from the root down to the folder.
+fn folder_path(folders: &HashMap<String, Folder>, folder_id: &str) -> Vec<FolderPathItem> {
+    folder_ancestors(folders, folder_id)
+        .into_iter()
+        .rev()
+        .filter_map(|id| folders.get(&id))
+        .map(|folder| FolderPathItem {
+            id: folder.id.clone(),
+            name: folder.name.clone(),
+        })
+        .collect()
+}
+
+/// Ensure the parent folder exists, can be written by the user and has room for
+/// the given levels of subfolders.
+async fn check_parent_folder(
+    db: &dyn Database,
+    parent_id: &str,
+    principal: Principal<'_>,
+    max_depth: u64,
+    levels: u64,
+) -> ServiceResult<()> {
+    let parent = db
+        .get_folder_by_id(parent_id)
+        .await?
+        .filter(|parent| can(Access::Write, Resource::Folder(parent), principal))
+        .ok_or_else(|| {
+            ServiceError::invalid_field("parentId", "not_found", "Parent folder does not exist")
+        })?;
+    if parent.is_smart() {
+        return Err(ServiceError::invalid_field(
+            "parentId",
+            "smart_folder",
+            "Smart folders cannot have subfolders",
+        ));
+    }
+    let depth = folder_depth(db, parent, max_depth).await?;
+    check_quota(QuotaResource::FolderDepth, Some(max_depth), depth, levels)
+}
+
+/// Level of the folder in the tree, folders without parent are at level 1.
+async fn folder_depth(db: &dyn Database, folder: Folder, max_depth: u64) -> ServiceResult<u64> {
+    let mut depth = 1;
+    let mut parent_id = folder.parent_id;
+    while let Some(id) = parent_id {
+        // guard against corrupted (cyclic) trees
+        if depth > max_depth {
+            break;
+        }
+        parent_id = db
+            .get_folder_by_id(&id)
+            .await?
+            .and_then(|parent| parent.parent_id);
+        depth += 1;
+    }
+    Ok(depth)
+}
+
+/// Create the initial folders of the user: the folder template of the organization,
+/// or the system folder when there is none, named in the language of the user or
+/// else the locale. Template folders already present at the root are skipped, so
+/// this can run again when the user joins an organization.
+pub(super) async fn provision_folders(
+    db: &dyn Database,
+    user: &User,
+    locale: Locale,
+) -> ServiceResult<()> {
+    let template = match &user.org_id {
+        Some(org_id) => db
+            .get_organization_by_id(org_id)
+            .await?
+            .map(|org| org.folder_template)
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let existing = db.get_folders_by_user_id(&user.uid).await?;
+    if template.is_empty() {
+        if existing.is_empty() {
+            let locale = Locale::of_user(user, locale);
+            let default_system_folder = Folder::default_system_folder(&user.uid, locale);
+            db.create_folder(&mut TxnContext::none(), default_system_folder)
+                .await?;
+        }
+        return Ok(());
+    }
+
+    let mut pending = template
+        .iter()
+        .rev()
+        .map(|t| (None, t))
+        .collect::<Vec<(Option<String>, _)>>();
+    while let Some((parent_id, template)) = pending.pop() {
+        let exists = existing
+            .iter()
+            .any(|f| f.parent_id.is_none() && f.name == template.name);
+        if parent_id.is_none() && exists {
+            continue;
+        }
+        let folder = Folder::new_from_template(&user.uid, parent_id, template);
+        pending.extend(
+            template
+                .children
+                .iter()
+                .rev()
+                .map(|child| (Some(folder.id.clone()), child)),
+        );
+        db.create_folder(&mut TxnContext::none(), folder).await?;
+    }
     Ok(())
 }
 
 /// Get Folder's Literatures
 ///
-/// Gets the details of a specific folder, including its literatures, for the authenticated user.
+/// Gets the details of a specific folder, including its literatures, for the authenticated user,
+/// with how many papers and subfolders it holds.
+/// Answers 304 when `If-None-Match` has the current `ETag` of the folder. The `ETag`, which
+/// `If-Match` gives back to update the folder, does not follow the counts.
 #[endpoint(
-    status_codes(200, 400, 401, 404),
+    status_codes(200, 304, 400, 401, 404),
     responses(
         (status_code = 200, body = FolderResponse, description = "Folder details retrieved successfully"),
+        (status_code = 304, description = "Not Modified: The folder did not change"),
         (status_code = 400, description = "Bad Request: Validation error"),
         (status_code = 401, description = "Unauthorized: User not authenticated"),
         (status_code = 404, description = "Not Found: Folder does not exist")
     )
 )]
 async fn get_folder_literatures(
+    req: &mut Request,
     depot: &mut Depot,
     folder_id: PathParam<String>,
-) -> ServiceResult<FolderResponse> {
+    resp: &mut Response,
+) -> ServiceResult<()> {
     // todo resp should be literature list.
     // todo add param limit and marker for pagination
     let state = depot.obtain::<AppDataRef>()?;
@@ -202,19 +1188,482 @@ async fn get_folder_literatures(
         ));
     }
 
-    // Fetch the folder by ID
-    let folder = state
-        .mongo_client
-        .get_folder_by_id(&folder_id)
-        .await?
-        .ok_or_else(|| ServiceError::NotFound(format!("Folder with ID {} not found", folder_id)))?;
+    let folder = get_folder(state, &folder_id, user, Access::Read).await?;
+    record_activity(
+        state,
+        &user.uid,
+        ActivityKind::Folder,
+        &folder.id,
+        ActivityAction::Viewed,
+    );
+
+    if not_modified(req, resp, &weak_etag(folder.updated_at, folder.version)) {
+        return Ok(());
+    }
+
+    // the counts are among the folders of the owner, for the shared folders too
+    let folders = state.cached_folders(&folder.user_id).await?;
+    let paper_counts = state.db.count_papers_by_folder(&folder.user_id).await?;
+    let response = counted_folders(folders, &paper_counts)
+        .into_iter()
+        .find(|f| f.id == folder.id)
+        .unwrap_or_else(|| folder.into());
+    resp.render(response);
+    Ok(())
+}
 
-    // Ensure the folder belongs to the authenticated user
-    if folder.user_id != user.uid {
-        return Err(ServiceError::Unauthorized(
-            "You do not have permission to access this folder".to_string(),
+/// List Folder Papers
+///
+/// Pages through the papers of a folder, newest first: a page holds the `limit`
+/// (50 by default) papers after the first `offset` ones, with how many the folder
+/// holds. The papers of a smart folder are those matching its query, `starred`
+/// pages through the starred papers.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = ListFolderPapersResponse, description = "Page of the papers of the folder"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Folder does not exist")
+    )
+)]
+async fn list_folder_papers(
+    depot: &mut Depot,
+    folder_id: PathParam<String>,
+    offset: QueryParam<u64, false>,
+    limit: QueryParam<i64, false>,
+) -> ServiceResult<ListFolderPapersResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let offset = offset.into_inner().unwrap_or_default();
+    let limit = limit
+        .into_inner()
+        .unwrap_or(DEFAULT_PAPER_LIMIT)
+        .clamp(1, MAX_PAPER_LIMIT);
+    let query = if folder_id.as_str() == STARRED_FOLDER_ID {
+        SmartQuery::starred()
+    } else {
+        let folder = get_folder(state, &folder_id, user, Access::Read).await?;
+        match folder.query {
+            Some(query) => query,
+            None => {
+                let filter = doc! { "user_id": &folder.user_id, "folder_id": &folder.id };
+                return folder_papers_page(state, filter, offset, limit).await;
+            }
+        }
+    };
+    // the smart folders search the papers of the user
+    let mut filter = smart_folder_filter(state, user, &query, None).await?;
+    filter.insert("user_id", &user.uid);
+    folder_papers_page(state, filter, offset, limit).await
+}
+
+async fn folder_papers_page(
+    state: &AppDataRef,
+    filter: bson::Document,
+    offset: u64,
+    limit: i64,
+) -> ServiceResult<ListFolderPapersResponse> {
+    let (papers, total) = state.db.find_papers_page(filter, offset, limit).await?;
+    Ok(ListFolderPapersResponse {
+        has_more: offset + (papers.len() as u64) < total,
+        items: papers.into_iter().map(PaperResponse::from).collect(),
+        total,
+    })
+}
+
+/// Export Folder
+///
+/// Starts building a zip archive of every paper of the folder and its subfolders,
+/// one `md`, `html` or `pdf` document (the default) per paper. The status of the
+/// export links to the download once ready, and the user is notified.
+#[endpoint(
+    status_codes(202, 401, 404),
+    responses(
+        (status_code = 202, body = ExportJobResponse, description = "Export started"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Folder does not exist")
+    )
+)]
+async fn export_folder(
+    depot: &mut Depot,
+    folder_id: PathParam<String>,
+    format: QueryParam<ExportFormat, false>,
+    resp: &mut Response,
+) -> ServiceResult<ExportJobResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let folder = get_folder(state, &folder_id, user, Access::Read).await?;
+
+    let format = format.into_inner().unwrap_or_default();
+    let job = ExportJob::new(
+        &user.uid,
+        ExportKind::Folder,
+        &folder.id,
+        format,
+        format!("{}.zip", sanitize(&folder.name)),
+    );
+    state.db.create_export(job.clone()).await?;
+    state.jobs.spawn(run_export(state.clone(), job.clone()));
+    resp.status_code(salvo::http::StatusCode::ACCEPTED);
+    Ok(job.into())
+}
+
+/// Wrap Up Folder
+///
+/// Summarizes all papers of a project folder into a new "project summary" paper,
+/// archives the folder and notifies the user. `model` selects a configured model
+/// instead of the default one. Fails with `QUOTA_EXCEEDED` once the monthly llm
+/// token quota of the user is used up.
+#[endpoint(
+    status_codes(200, 400, 401, 403, 404, 422, 429),
+    responses(
+        (status_code = 200, body = WrapUpFolderResponse, description = "Folder wrapped up successfully"),
+        (status_code = 400, description = "Bad Request: Folder is empty or already archived"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 403, description = "Forbidden: No room left in the folder for the summary paper"),
+        (status_code = 404, description = "Not Found: Folder does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Model not available"),
+        (status_code = 429, body = ErrorResponse, description = "Too Many Requests: Monthly llm quota exceeded")
+    )
+)]
+async fn wrap_up_folder(
+    depot: &mut Depot,
+    folder_id: PathParam<String>,
+    model: QueryParam<String, false>,
+) -> ServiceResult<WrapUpFolderResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let model = state.user_model(user, model.into_inner());
+    if !state.llm.supports(&model) {
+        return Err(ServiceError::invalid_field(
+            "model",
+            "unsupported",
+            format!("Model {} is not available", model),
         ));
     }
 
-    Ok(folder.into())
+    let mut folder = get_folder(state, &folder_id, user, Access::Write).await?;
+    if folder.archived {
+        return Err(ServiceError::BadRequest(
+            "Folder is already archived".to_string(),
+        ));
+    }
+
+    let papers = state.db.get_papers_by_folder_id(&folder.id).await?;
+    if papers.is_empty() {
+        return Err(ServiceError::BadRequest(
+            "Folder does not contain any paper".to_string(),
+        ));
+    }
+    state.ensure_quota(&user.uid, &model).await?;
+    state.ensure_folder_room(user, &folder.id, 1).await?;
+
+    let materials = papers
+        .iter()
+        .enumerate()
+        .map(|(i, paper)| {
+            let body = paper
+                .summary
+                .as_deref()
+                .or(paper.r#abstract.as_deref())
+                .or(paper.content.as_deref())
+                .unwrap_or_default();
+            let body: String = body.chars().take(WRAP_UP_PAPER_MAX_CHARS).collect();
+            format!("## [{}] {}\n{}", i + 1, paper.title, body)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let prompt = render_prompt(
+        &state.db,
+        FOLDER_WRAP_UP_PROMPT,
+        None,
+        &[
+            ("folder_name", folder.name.as_str()),
+            (
+                "folder_description",
+                folder.description.as_deref().unwrap_or_default(),
+            ),
+            ("materials", materials.as_str()),
+        ],
+    )
+    .await;
+    let language = Locale::from_tag(user.settings.summary_language(&state.settings_config))
+        .unwrap_or_default()
+        .english_name();
+    let messages = vec![
+        ChatMessage::system(format!(
+            "You are a research assistant. Write in {}.",
+            language
+        )),
+        ChatMessage::user(prompt),
+    ];
+    let api_key = state.user_llm_key(&user.uid, &model).await?;
+    let summary = state
+        .llm
+        .complete_with_key(Some(&model), &messages, api_key.as_deref())
+        .await?;
+    let (input_tokens, output_tokens) = LlmClient::estimate_usage(&messages, &summary);
+    let mut usage = UsageEvent::new(&user.uid, &model, "wrap_up", input_tokens, output_tokens);
+    usage.own_key = api_key.is_some();
+    record_usage(state, usage).await;
+
+    let mut summary_paper = Paper::new(
+        &user.uid,
+        &folder.id,
+        format!("Project Summary: {}", folder.name),
+    );
+    summary_paper.content = Some(summary.clone());
+    summary_paper.summary = Some(summary);
+    state.db.create_paper(summary_paper.clone()).await?;
+
+    folder.archived = true;
+    folder.updated_at = bson::DateTime::now();
+    let folder = state.db.update_folder(folder).await?;
+    state.invalidate(&[CacheKey::Folders(&user.uid)]).await;
+    state.events.publish(
+        &user.uid,
+        DomainEvent::PaperCreated {
+            paper_id: summary_paper.id.clone(),
+            folder_id: folder.id.clone(),
+        },
+    );
+    state.events.publish(
+        &user.uid,
+        DomainEvent::SummaryReady {
+            paper_id: summary_paper.id.clone(),
+            folder_id: folder.id.clone(),
+        },
+    );
+    state.events.publish(&user.uid, folder_updated(&folder));
+
+    Ok(WrapUpFolderResponse {
+        folder: folder.into(),
+        summary_paper: summary_paper.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo::{
+        Service, affix_state,
+        http::StatusCode,
+        test::{ResponseExt, TestClient},
+    };
+
+    use super::*;
+    use crate::{app_data::AppData, error::ErrorCode};
+
+    #[tokio::test]
+    async fn test_create_folder() {
+        let state = AppData::for_tests().await;
+        let user = User::new_by_email("reader@example.com".to_string(), None, String::new());
+        let router = Router::new()
+            .hoop(affix_state::inject(state).inject(user))
+            .push(Router::with_path("folder").push(create_router()));
+        let service = Service::new(router);
+        let list = || async {
+            let mut resp = TestClient::get("http://127.0.0.1/folder")
+                .send(&service)
+                .await;
+            resp.take_json::<Vec<FolderResponse>>().await.unwrap()
+        };
+
+        // the default folders are provisioned on the first listing
+        let folders = list().await;
+        assert_eq!(folders[0].id, STARRED_FOLDER_ID);
+        assert_eq!(folders.len(), 2);
+
+        let mut resp = TestClient::post("http://127.0.0.1/folder")
+            .json(&serde_json::json!({ "name": "To read" }))
+            .send(&service)
+            .await;
+        assert_eq!(resp.status_code, Some(StatusCode::CREATED));
+        let folder = resp.take_json::<FolderResponse>().await.unwrap();
+        assert_eq!(folder.sort_order, 1);
+
+        let folders = list().await;
+        assert!(folders.iter().any(|f| f.id == folder.id));
+
+        // the names are unique among siblings, unless numbered
+        let mut resp = TestClient::post("http://127.0.0.1/folder")
+            .json(&serde_json::json!({ "name": "To read" }))
+            .send(&service)
+            .await;
+        assert_eq!(resp.status_code, Some(StatusCode::CONFLICT));
+        let error = resp.take_json::<ErrorResponse>().await.unwrap();
+        assert_eq!(error.code, ErrorCode::NameConflict);
+        let mut resp = TestClient::post("http://127.0.0.1/folder?auto_rename=true")
+            .json(&serde_json::json!({ "name": "To read" }))
+            .send(&service)
+            .await;
+        assert_eq!(resp.status_code, Some(StatusCode::CREATED));
+        let renamed = resp.take_json::<FolderResponse>().await.unwrap();
+        assert_eq!(renamed.name, "To read (2)");
+    }
+
+    #[tokio::test]
+    async fn test_import_folders() {
+        let state = AppData::for_tests().await;
+        let user = User::new_by_email("reader@example.com".to_string(), None, String::new());
+        let router = Router::new()
+            .hoop(affix_state::inject(state).inject(user))
+            .push(Router::with_path("folder").push(create_router()));
+        let service = Service::new(router);
+        let tree = serde_json::json!({
+            "folders": [
+                { "key": "ml", "name": "ML", "children": [
+                    { "key": "nlp", "name": "NLP" },
+                    { "key": "cv", "name": "Vision" }
+                ] },
+                { "key": "bio", "name": "Biology" }
+            ]
+        });
+
+        let mut resp = TestClient::post("http://127.0.0.1/folder/import")
+            .json(&tree)
+            .send(&service)
+            .await;
+        assert_eq!(resp.status_code, Some(StatusCode::CREATED));
+        let imported = resp.take_json::<ImportFoldersResponse>().await.unwrap();
+        assert_eq!(imported.folders.len(), 4);
+        let nlp = imported.folders.iter().find(|f| f.name == "NLP").unwrap();
+        assert_eq!(imported.ids["nlp"], nlp.id);
+        assert_eq!(nlp.parent_id.as_ref(), Some(&imported.ids["ml"]));
+
+        // the whole tree conflicts with itself, unless numbered
+        let mut resp = TestClient::post("http://127.0.0.1/folder/import")
+            .json(&tree)
+            .send(&service)
+            .await;
+        assert_eq!(resp.status_code, Some(StatusCode::CONFLICT));
+        let mut resp = TestClient::post("http://127.0.0.1/folder/import?auto_rename=true")
+            .json(&tree)
+            .send(&service)
+            .await;
+        assert_eq!(resp.status_code, Some(StatusCode::CREATED));
+        let renamed = resp.take_json::<ImportFoldersResponse>().await.unwrap();
+        assert!(renamed.folders.iter().any(|f| f.name == "ML (2)"));
+        assert!(renamed.folders.iter().any(|f| f.name == "NLP"));
+
+        let mut resp = TestClient::post("http://127.0.0.1/folder/import")
+            .json(&serde_json::json!({
+                "folders": [{ "key": "a", "name": "A" }, { "key": "a", "name": "B" }]
+            }))
+            .send(&service)
+            .await;
+        assert_eq!(resp.status_code, Some(StatusCode::UNPROCESSABLE_ENTITY));
+        let error = resp.take_json::<ValidationErrorResponse>().await.unwrap();
+        assert_eq!(error.errors[0].code, "duplicate");
+    }
+
+    #[tokio::test]
+    async fn test_list_folder_papers() {
+        let state = AppData::for_tests().await;
+        let db = state.db.clone();
+        let user = User::new_by_email("reader@example.com".to_string(), None, String::new());
+        let mut project = Folder::default_system_folder(&user.uid, Locale::En);
+        project.r#type = FolderType::UserDefined;
+        project.name = "Project".to_string();
+        let drafts = project.copy_under(Some(project.id.clone()));
+        for folder in [&project, &drafts] {
+            db.create_folder(&mut TxnContext::none(), folder.clone())
+                .await
+                .unwrap();
+        }
+        for title in ["BERT", "GPT", "T5"] {
+            let paper = Paper::new(&user.uid, &project.id, title.to_string());
+            db.create_paper(paper).await.unwrap();
+        }
+
+        let router = Router::new()
+            .hoop(affix_state::inject(state).inject(user))
+            .push(Router::with_path("folder").push(create_router()));
+        let service = Service::new(router);
+        let page = |offset: u64| {
+            let url = format!(
+                "http://127.0.0.1/folder/{}/papers?offset={}&limit=2",
+                project.id, offset
+            );
+            let service = &service;
+            async move {
+                let mut resp = TestClient::get(url).send(service).await;
+                resp.take_json::<ListFolderPapersResponse>().await.unwrap()
+            }
+        };
+        let first = page(0).await;
+        assert_eq!(
+            (first.items.len(), first.total, first.has_more),
+            (2, 3, true)
+        );
+        let last = page(2).await;
+        assert_eq!((last.items.len(), last.total, last.has_more), (1, 3, false));
+
+        let mut resp = TestClient::get("http://127.0.0.1/folder")
+            .send(&service)
+            .await;
+        let folders = resp.take_json::<Vec<FolderResponse>>().await.unwrap();
+        let listed = folders.iter().find(|f| f.id == project.id).unwrap();
+        assert_eq!((listed.paper_count, listed.child_count), (Some(3), Some(1)));
+        // the papers of the smart folders are not counted
+        assert_eq!(folders[0].paper_count, None);
+
+        let url = format!("http://127.0.0.1/folder/{}/literatures", project.id);
+        let mut resp = TestClient::get(url).send(&service).await;
+        let details = resp.take_json::<FolderResponse>().await.unwrap();
+        assert_eq!(
+            (details.paper_count, details.child_count),
+            (Some(3), Some(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_copy_folder() {
+        let state = AppData::for_tests().await;
+        let db = state.db.clone();
+        let user = User::new_by_email("reader@example.com".to_string(), None, String::new());
+        let mut project = Folder::default_system_folder(&user.uid, Locale::En);
+        project.r#type = crate::model::folder::FolderType::UserDefined;
+        project.name = "Project".to_string();
+        let mut drafts = project.copy_under(Some(project.id.clone()));
+        drafts.name = "Drafts".to_string();
+        for folder in [&project, &drafts] {
+            db.create_folder(&mut TxnContext::none(), folder.clone())
+                .await
+                .unwrap();
+        }
+        let mut paper = Paper::new(&user.uid, &drafts.id, "BERT".to_string());
+        paper.tags = vec!["nlp".to_string()];
+        db.create_paper(paper).await.unwrap();
+
+        let router = Router::new()
+            .hoop(affix_state::inject(state).inject(user.clone()))
+            .push(Router::with_path("folder").push(create_router()));
+        let service = Service::new(router);
+        let mut resp = TestClient::post(format!("http://127.0.0.1/folder/{}/copy", project.id))
+            .json(&serde_json::json!({}))
+            .send(&service)
+            .await;
+        assert_eq!(resp.status_code, Some(StatusCode::CREATED));
+        let job = resp.take_json::<JobResponse>().await.unwrap();
+        assert_eq!(job.status, JobStatus::Done);
+        assert_eq!(job.progress.map(|p| (p.done, p.total)), Some((1, 1)));
+
+        // the copy is numbered next to the folder, its subfolders keep their names
+        let folders = db.get_folders_by_user_id(&user.uid).await.unwrap();
+        let copy_id = job.result_id.unwrap();
+        let copy = folders.iter().find(|f| f.id == copy_id).unwrap();
+        assert_eq!(copy.name, "Project (2)");
+        let copied_drafts = folders
+            .iter()
+            .find(|f| f.parent_id.as_ref() == Some(&copy_id) && f.name == "Drafts")
+            .unwrap();
+        let papers = db.get_papers_by_folder_id(&copied_drafts.id).await.unwrap();
+        assert_eq!(papers.len(), 1);
+        assert_eq!(papers[0].tags, vec!["nlp".to_string()]);
+        let originals = db.get_papers_by_folder_id(&drafts.id).await.unwrap();
+        assert_eq!(originals.len(), 1);
+    }
 }
@@ -0,0 +1,60 @@
+use std::collections::HashSet;
+
+use salvo::{
+    Depot, Router,
+    oapi::{RouterExt, endpoint},
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::ServiceResult,
+    model::{
+        citation::{
+            CitationRepository,
+            schema::{GraphEdge, GraphNode, GraphResponse},
+        },
+        paper::PaperRepository,
+        user::User,
+    },
+};
+
+pub fn create_router() -> Router {
+    Router::new().get(get_graph).oapi_tag("graph")
+}
+
+/// Get Citation Graph
+///
+/// Gets the citation graph of the library of the authenticated user:
+/// every paper is a node, every citation between two papers an edge.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = GraphResponse, description = "Nodes and edges of the citation graph"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn get_graph(depot: &mut Depot) -> ServiceResult<GraphResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let papers = state.db.get_papers_by_user_id(&user.uid).await?;
+    let citations = state.db.get_library_citations(&user.uid).await?;
+
+    let mut seen = HashSet::new();
+    let edges = citations
+        .into_iter()
+        .filter_map(|citation| Some((citation.citing_id, citation.cited_id?)))
+        // a paper cited twice in the same reference list is one edge
+        .filter(|edge| seen.insert(edge.clone()))
+        .map(|(source, target)| GraphEdge { source, target })
+        .collect();
+    let nodes = papers
+        .into_iter()
+        .map(|paper| GraphNode {
+            id: paper.id,
+            title: paper.title,
+            folder_id: paper.folder_id,
+        })
+        .collect();
+    Ok(GraphResponse { nodes, edges })
+}
@@ -0,0 +1,33 @@
+use std::sync::LazyLock;
+
+use salvo::{Depot, Request, Response, Router, handler, writing::Json};
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult},
+    graphql::{PaperSchema, build_schema, execute},
+    model::user::User,
+};
+
+static SCHEMA: LazyLock<PaperSchema> = LazyLock::new(build_schema);
+
+pub fn create_router() -> Router {
+    Router::new().post(graphql)
+}
+
+/// Run a GraphQL query over the folders, papers, tags and notes of the user,
+/// e.g. the whole dashboard in one request. Errors of the query are reported
+/// in the `errors` of the response, as GraphQL does.
+#[handler]
+async fn graphql(req: &mut Request, depot: &mut Depot, res: &mut Response) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?.clone();
+
+    let request = req
+        .parse_json::<async_graphql::Request>()
+        .await
+        .map_err(|e| ServiceError::BadRequest(format!("Invalid GraphQL request: {}", e)))?;
+    let response = execute(&SCHEMA, state, user, request).await;
+    res.render(Json(response));
+    Ok(())
+}
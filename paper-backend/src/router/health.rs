@@ -0,0 +1,132 @@
+use std::{future::Future, time::Duration};
+
+use salvo::{
+    Depot, Response, Router,
+    http::StatusCode,
+    oapi::{RouterExt, endpoint},
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::ServiceResult,
+    model::{
+        blob::BlobRepository,
+        health::schema::{HealthResponse, HealthStatus},
+    },
+};
+
+// max time a single dependency check may take
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Status of a dependency of the service.
+#[derive(Debug)]
+struct ComponentStatus {
+    name: String,
+    status: HealthStatus,
+    latency_ms: u64,
+    error: Option<String>,
+}
+
+pub fn create_router() -> Router {
+    Router::new()
+        .push(Router::with_path("healthz").get(healthz))
+        .push(Router::with_path("readyz").get(readyz))
+        .oapi_tag("health")
+}
+
+/// Run the check within the timeout, timing it.
+async fn check<F, E>(name: &str, critical: bool, check: F) -> ComponentStatus
+where
+    F: Future<Output = Result<(), E>>,
+    E: std::fmt::Display,
+{
+    let started = std::time::Instant::now();
+    let result = tokio::time::timeout(CHECK_TIMEOUT, check).await;
+    let error = match result {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("Timed out after {}s", CHECK_TIMEOUT.as_secs())),
+    };
+    let status = match (&error, critical) {
+        (None, _) => HealthStatus::Up,
+        (Some(_), true) => HealthStatus::Down,
+        (Some(_), false) => HealthStatus::Degraded,
+    };
+    ComponentStatus {
+        name: name.to_string(),
+        status,
+        latency_ms: started.elapsed().as_millis() as u64,
+        error,
+    }
+}
+
+/// Any http response means the provider is reachable, auth is not checked.
+async fn check_llm(base_url: &str) -> Result<(), reqwest::Error> {
+    // in process provider
+    if base_url.is_empty() {
+        return Ok(());
+    }
+    reqwest::Client::new().get(base_url).send().await?;
+    Ok(())
+}
+
+/// Liveness
+///
+/// Whether the process is alive, without checking its dependencies.
+#[endpoint(
+    status_codes(200),
+    responses(
+        (status_code = 200, body = HealthResponse, description = "Service is alive")
+    )
+)]
+async fn healthz() -> ServiceResult<HealthResponse> {
+    Ok(HealthResponse {
+        status: HealthStatus::Up,
+    })
+}
+
+/// Readiness
+///
+/// Whether the service can take traffic: the database and the blob store must be
+/// reachable, an unreachable llm provider only degrades the service. The failing
+/// dependencies are logged, not answered.
+#[endpoint(
+    status_codes(200, 503),
+    responses(
+        (status_code = 200, body = HealthResponse, description = "Service is ready, possibly degraded"),
+        (status_code = 503, body = HealthResponse, description = "Service Unavailable: A critical dependency is down")
+    )
+)]
+async fn readyz(depot: &mut Depot, resp: &mut Response) -> ServiceResult<HealthResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let (database, blob_store, llm) = tokio::join!(
+        check("database", true, state.db.ping()),
+        check("blob_store", true, state.db.check_blob_store()),
+        check("llm", false, check_llm(&state.llm.base_url)),
+    );
+    let components = vec![database, blob_store, llm];
+    let status = if components.iter().any(|c| c.status == HealthStatus::Down) {
+        HealthStatus::Down
+    } else if components
+        .iter()
+        .any(|c| c.status == HealthStatus::Degraded)
+    {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Up
+    };
+    for component in components.iter().filter(|c| c.status != HealthStatus::Up) {
+        tracing::warn!(
+            "Readiness check of {} is {:?} after {}ms: {}",
+            component.name,
+            component.status,
+            component.latency_ms,
+            component.error.as_deref().unwrap_or_default()
+        );
+    }
+    if status == HealthStatus::Down {
+        resp.status_code(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    Ok(HealthResponse { status })
+}
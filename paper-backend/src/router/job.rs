@@ -0,0 +1,70 @@
+use salvo::{
+    Depot, Router,
+    oapi::{RouterExt, endpoint, extract::PathParam},
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult},
+    model::{
+        job::{
+            JobRepository,
+            schema::{JobResponse, ListJobsResponse},
+        },
+        user::User,
+    },
+};
+
+// jobs listed, older ones are only available by id
+const MAX_LISTED_JOBS: i64 = 50;
+
+pub fn create_router() -> Router {
+    Router::new()
+        .get(list_jobs)
+        .push(Router::with_path("{job_id}").get(get_job))
+        .oapi_tag("job")
+}
+
+/// List Jobs
+///
+/// Lists the latest background jobs of the authenticated user, the latest first.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListJobsResponse, description = "Jobs of the user"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn list_jobs(depot: &mut Depot) -> ServiceResult<ListJobsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let jobs = state.db.get_jobs(&user.uid, MAX_LISTED_JOBS).await?;
+    Ok(ListJobsResponse(
+        jobs.into_iter().map(JobResponse::from).collect(),
+    ))
+}
+
+/// Get Job
+///
+/// Gets the status and progress of a background job of the authenticated user,
+/// with the resource it created once done.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = JobResponse, description = "Status of the job"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Job does not exist")
+    )
+)]
+async fn get_job(depot: &mut Depot, job_id: PathParam<String>) -> ServiceResult<JobResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let job = state
+        .db
+        .get_job(&user.uid, &job_id)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound("Job".to_string()))?;
+    Ok(job.into())
+}
@@ -0,0 +1,145 @@
+use salvo::{
+    Depot, Request, Router,
+    oapi::{RouterExt, endpoint, extract::JsonBody},
+};
+
+use crate::{
+    app_data::AppDataRef,
+    config::LegalDocument,
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    model::{
+        consent::{
+            ConsentRecord, ConsentRepository, pending_consents,
+            schema::{ConsentRequest, ConsentResponse, LegalDocumentsResponse},
+        },
+        user::{User, UserRepository},
+    },
+    utils::{cache::CacheKey, validate::ValidatedRequest},
+};
+
+pub fn create_router() -> Router {
+    Router::new()
+        .push(
+            Router::with_path("consent")
+                .get(get_consent)
+                .post(give_consent),
+        )
+        .oapi_tag("legal")
+}
+
+pub fn create_non_auth_router() -> Router {
+    Router::new().get(get_legal_documents).oapi_tag("legal")
+}
+
+fn consent_response(user: &User, state: &AppDataRef) -> ConsentResponse {
+    ConsentResponse {
+        terms_version: user.terms_version.clone(),
+        privacy_version: user.privacy_version.clone(),
+        pending: pending_consents(user, &state.legal_config),
+    }
+}
+
+/// The accepted version when it is the current one of the document.
+fn check_version(
+    field: &str,
+    given: Option<String>,
+    document: &Option<LegalDocument>,
+    accepted: Option<String>,
+) -> ServiceResult<Option<String>> {
+    match (given, document) {
+        (None, _) => Ok(accepted),
+        (Some(given), Some(document)) if given == document.version => Ok(Some(given)),
+        (Some(_), _) => Err(ServiceError::invalid_field(
+            field,
+            "outdated",
+            "Not the current version of the document",
+        )),
+    }
+}
+
+/// Get Legal Documents
+///
+/// Gets the current versions of the terms of service and the privacy policy,
+/// absent documents need no consent.
+#[endpoint(
+    status_codes(200),
+    responses(
+        (status_code = 200, body = LegalDocumentsResponse, description = "Current legal documents")
+    )
+)]
+async fn get_legal_documents(depot: &mut Depot) -> ServiceResult<LegalDocumentsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let config = &state.legal_config;
+    Ok(LegalDocumentsResponse {
+        terms: config.terms.as_ref().map(Into::into),
+        privacy_policy: config.privacy_policy.as_ref().map(Into::into),
+    })
+}
+
+/// Get Consent
+///
+/// Gets the document versions the authenticated user accepted, and the documents
+/// to accept before the rest of the api can be used.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ConsentResponse, description = "Consent of the user"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn get_consent(depot: &mut Depot) -> ServiceResult<ConsentResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    Ok(consent_response(user, state))
+}
+
+/// Give Consent
+///
+/// Accepts the current version of the terms of service and / or the privacy policy.
+/// Every consent is recorded with its time and origin.
+#[endpoint(
+    status_codes(200, 401, 422),
+    responses(
+        (status_code = 200, body = ConsentResponse, description = "Consent recorded"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Outdated document version")
+    )
+)]
+async fn give_consent(
+    req: &mut Request,
+    depot: &mut Depot,
+    request: JsonBody<ConsentRequest>,
+) -> ServiceResult<ConsentResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let config = &state.legal_config;
+    let mut user = user.clone();
+    user.terms_version = check_version(
+        "termsVersion",
+        request.terms_version,
+        &config.terms,
+        user.terms_version,
+    )?;
+    user.privacy_version = check_version(
+        "privacyVersion",
+        request.privacy_version,
+        &config.privacy_policy,
+        user.privacy_version,
+    )?;
+    user.updated_at = bson::DateTime::now();
+
+    state.db.update_user(user.clone()).await?;
+    state.invalidate(&[CacheKey::User(&user.uid)]).await;
+    state
+        .db
+        .create_consent_record(ConsentRecord::new(
+            &user,
+            Some(req.remote_addr().to_string()),
+        ))
+        .await?;
+    Ok(consent_response(&user, state))
+}
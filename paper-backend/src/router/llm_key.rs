@@ -0,0 +1,194 @@
+use salvo::{
+    Depot, Response, Router,
+    oapi::{
+        RouterExt, endpoint,
+        extract::{JsonBody, PathParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    model::{
+        llm_key::{
+            KEY_PROVIDERS, LlmKeyRepository, UserLlmKey,
+            schema::{ListLlmKeysResponse, LlmKeyResponse, StoreLlmKeyRequest},
+        },
+        user::User,
+    },
+    rate_limit::limit_ai,
+    utils::{crypto::Cipher, validate::ValidatedRequest},
+};
+
+pub fn create_router() -> Router {
+    Router::new()
+        .push(Router::new().get(list_llm_keys))
+        .push(
+            Router::with_path("{provider}")
+                .delete(delete_llm_key)
+                // both call the provider
+                .push(Router::new().hoop(limit_ai).put(store_llm_key))
+                .push(
+                    Router::with_path("validate")
+                        .hoop(limit_ai)
+                        .post(validate_llm_key),
+                ),
+        )
+        .oapi_tag("llm-key")
+}
+
+fn check_provider(provider: &str) -> ServiceResult<()> {
+    if !KEY_PROVIDERS.contains(&provider) {
+        return Err(ServiceError::invalid_field(
+            "provider",
+            "unsupported",
+            format!("Keys can only be stored for {}", KEY_PROVIDERS.join(", ")),
+        ));
+    }
+    Ok(())
+}
+
+fn cipher(state: &AppDataRef) -> ServiceResult<&Cipher> {
+    state.cipher.as_ref().ok_or_else(|| {
+        ServiceError::BadRequest("Api keys cannot be stored on this server".to_string())
+    })
+}
+
+/// Check the key with the provider, a 422 with the reason when refused.
+async fn validate_key(state: &AppDataRef, provider: &str, api_key: &str) -> ServiceResult<()> {
+    match state.llm.validate_key(provider, api_key).await {
+        Err(ServiceError::LLMError(e)) => Err(ServiceError::invalid_field(
+            "apiKey",
+            "refused",
+            format!("The key was refused by {}: {}", provider, e),
+        )),
+        result => result,
+    }
+}
+
+/// List Llm Keys
+///
+/// Lists the api keys the authenticated user stored, by provider, without the
+/// keys themselves.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListLlmKeysResponse, description = "Api keys of the user"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn list_llm_keys(depot: &mut Depot) -> ServiceResult<ListLlmKeysResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let keys = state.db.get_llm_keys(&user.uid).await?;
+    Ok(ListLlmKeysResponse(
+        keys.into_iter().map(Into::into).collect(),
+    ))
+}
+
+/// Store Llm Key
+///
+/// Stores an api key of the authenticated user for `openai` or `anthropic`,
+/// replacing the previous one, once the provider accepted it. The key is
+/// encrypted at rest. The llm calls made for the user to the provider use it
+/// from then on: they are billed to their own account, and do not count against
+/// the monthly token quota.
+#[endpoint(
+    status_codes(200, 400, 401, 422),
+    responses(
+        (status_code = 200, body = LlmKeyResponse, description = "Key stored"),
+        (status_code = 400, description = "Bad Request: Keys cannot be stored, or the provider is not configured"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Unsupported provider, or key refused by the provider")
+    )
+)]
+async fn store_llm_key(
+    depot: &mut Depot,
+    provider: PathParam<String>,
+    request: JsonBody<StoreLlmKeyRequest>,
+) -> ServiceResult<LlmKeyResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    check_provider(&provider)?;
+    let cipher = cipher(state)?;
+    let request = request.into_inner().validated()?;
+    validate_key(state, &provider, &request.api_key).await?;
+
+    let encrypted_key = cipher.encrypt(&request.api_key)?;
+    let mut key = UserLlmKey::new(&user.uid, &provider, &request.api_key, encrypted_key);
+    key.validated_at = Some(key.created_at);
+    state.db.upsert_llm_key(key.clone()).await?;
+    Ok(key.into())
+}
+
+/// Validate Llm Key
+///
+/// Checks that the provider still accepts the stored api key of the
+/// authenticated user, e.g. after it was rotated on the provider side.
+#[endpoint(
+    status_codes(200, 400, 401, 404, 422),
+    responses(
+        (status_code = 200, body = LlmKeyResponse, description = "Key accepted by the provider"),
+        (status_code = 400, description = "Bad Request: Keys cannot be stored, or the provider is not configured"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: No key stored for the provider"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Key refused by the provider")
+    )
+)]
+async fn validate_llm_key(
+    depot: &mut Depot,
+    provider: PathParam<String>,
+) -> ServiceResult<LlmKeyResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    check_provider(&provider)?;
+    let cipher = cipher(state)?;
+    let mut key = state
+        .db
+        .get_llm_key(&user.uid, &provider)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound(format!("Api key for {}", provider.as_str())))?;
+    let api_key = cipher.decrypt_sealed(&key.encrypted_key)?;
+    validate_key(state, &provider, &api_key).await?;
+
+    let now = bson::DateTime::now();
+    state
+        .db
+        .set_llm_key_validated(&user.uid, &provider, now)
+        .await?;
+    key.validated_at = Some(now);
+    Ok(key.into())
+}
+
+/// Delete Llm Key
+///
+/// Deletes the api key of the authenticated user for the provider, the calls
+/// made for them use the key of the server again.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Key deleted"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: No key stored for the provider")
+    )
+)]
+async fn delete_llm_key(
+    depot: &mut Depot,
+    provider: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    if !state.db.delete_llm_key(&user.uid, &provider).await? {
+        return Err(ServiceError::NotFound(format!(
+            "Api key for {}",
+            provider.as_str()
+        )));
+    }
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
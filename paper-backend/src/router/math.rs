@@ -0,0 +1,45 @@
+use salvo::{
+    Depot, Router,
+    oapi::{RouterExt, endpoint, extract::JsonBody},
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceResult, ValidationErrorResponse},
+    model::math::schema::{RenderMathRequest, RenderMathResponse},
+    utils::validate::ValidatedRequest,
+};
+
+pub fn create_router() -> Router {
+    Router::new()
+        .push(Router::with_path("render").post(render_math))
+        .oapi_tag("math")
+}
+
+/// Render Math
+///
+/// Renders the LaTeX formulas of the texts as MathML: `$...$` and `\(...\)` inline,
+/// `$$...$$` and `\[...\]` as blocks. The rest of each text is HTML escaped, so
+/// markdown stays markdown. A formula which fails to parse is kept as written.
+#[endpoint(
+    status_codes(200, 401, 422),
+    responses(
+        (status_code = 200, body = RenderMathResponse, description = "Rendered texts"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn render_math(
+    depot: &mut Depot,
+    request: JsonBody<RenderMathRequest>,
+) -> ServiceResult<RenderMathResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let request = request.into_inner().validated()?;
+    let html = request
+        .texts
+        .iter()
+        .map(|text| state.math.render(text))
+        .collect();
+    Ok(RenderMathResponse { html })
+}
@@ -1,43 +1,161 @@
 use salvo::{
     Depot, FlowCtrl, Request, Response, Router,
-    jwt_auth::{ConstDecoder, HeaderFinder, QueryFinder},
+    jwt_auth::{ConstDecoder, CookieFinder, HeaderFinder, JwtTokenFinder, QueryFinder},
     oapi::{RouterExt, SecurityRequirement},
     prelude::{JwtAuth, JwtAuthDepotExt, JwtAuthState},
 };
 
 use crate::{
     app_data::AppDataRef,
-    config::BackendConfig,
+    backup::refuse_writes_while_restoring,
+    config::{BackendConfig, DocsAccess},
     error::{ServiceError, ServiceResult},
-    model::user::UserRepository,
-    utils::jwt::JwtClaims,
+    i18n::{Locale, set_locale},
+    idempotency::idempotent,
+    model::{consent::pending_consents, user::User},
+    rate_limit::{limit_ai, limit_auth, limit_global},
+    resilience::serve_stale,
+    utils::{
+        jwt::{JwtClaims, JwtType},
+        session::{ACCESS_COOKIE, ACCESS_QUERY, require_csrf},
+        timeout::extend_for_ai,
+    },
 };
 
+mod account;
+mod activity;
+mod admin;
+mod ai;
 mod auth;
+mod block;
+mod comparison;
+mod conversation;
+mod custom_field;
+mod export;
+mod file;
 mod folder;
+mod graph;
+mod graphql;
+pub mod health;
+mod job;
+mod legal;
+mod llm_key;
+mod math;
+mod notification;
+mod organization;
+mod paper;
+mod prompt;
+mod reading_list;
+mod review;
+mod settings;
+mod stats;
+mod tenant;
+mod upload;
+mod usage;
 mod user;
+mod webhook;
+mod ws;
 
-pub fn create_router(config: &BackendConfig) -> Router {
-    let auth_handler: JwtAuth<JwtClaims, _> = JwtAuth::new(ConstDecoder::from_secret(
+fn jwt_auth(config: &BackendConfig) -> JwtAuth<JwtClaims, ConstDecoder> {
+    let mut finders: Vec<Box<dyn JwtTokenFinder>> = vec![
+        Box::new(HeaderFinder::new()),
+        Box::new(QueryFinder::new(ACCESS_QUERY)),
+    ];
+    if config.session.cookie_auth {
+        finders.push(Box::new(CookieFinder::new(ACCESS_COOKIE)));
+    }
+    JwtAuth::new(ConstDecoder::from_secret(
         config.jwt.access_secret.as_bytes(),
     ))
-    .finders(vec![
-        Box::new(HeaderFinder::new()),
-        Box::new(QueryFinder::new("jwt_token")),
-    ])
-    .force_passed(true);
+    .finders(finders)
+    .force_passed(true)
+}
 
-    let non_auth_router =
-        Router::new().push(Router::with_path("auth").push(auth::create_non_auth_router()));
-    let auth_router = Router::new()
-        .hoop(auth_handler)
-        .hoop(jwt_to_user)
+pub fn create_router(config: &BackendConfig) -> Router {
+    let auth_handler = jwt_auth(config);
+    let non_auth_router = Router::new()
+        .hoop(limit_global)
+        .push(
+            Router::with_path("auth")
+                .hoop(limit_auth)
+                .push(auth::create_non_auth_router()),
+        )
+        .push(Router::with_path("files").push(file::create_non_auth_router()))
+        .push(Router::with_path("legal").push(legal::create_non_auth_router()))
+        .push(Router::with_path("review").push(review::create_non_auth_router()))
+        .push(Router::with_path("tenant").push(tenant::create_non_auth_router()));
+    // usable before accepting the current legal documents
+    let consent_free_router = Router::new()
+        .push(Router::with_path("account").push(account::create_router()))
         .push(Router::with_path("auth").push(auth::create_router()))
+        .push(Router::with_path("exports").push(export::create_router()))
+        .push(Router::with_path("legal").push(legal::create_router()));
+    let consent_router = Router::new()
+        .hoop(require_consent)
+        .push(Router::with_path("activity").push(activity::create_router()))
+        .push(Router::with_path("admin").push(admin::create_router()))
+        .push(
+            Router::with_path("ai")
+                .hoop(limit_ai)
+                .hoop(extend_for_ai)
+                .push(ai::create_router()),
+        )
+        .push(Router::with_path("block").push(block::create_router()))
+        .push(Router::with_path("comparisons").push(comparison::create_router()))
+        .push(Router::with_path("conversations").push(conversation::create_router()))
+        .push(Router::with_path("custom-fields").push(custom_field::create_router()))
         .push(Router::with_path("folder").push(folder::create_router()))
+        .push(Router::with_path("graph").push(graph::create_router()))
+        .push(Router::with_path("graphql").push(graphql::create_router()))
+        .push(Router::with_path("jobs").push(job::create_router()))
+        .push(Router::with_path("llm-keys").push(llm_key::create_router()))
+        .push(Router::with_path("math").push(math::create_router()))
+        .push(Router::with_path("notifications").push(notification::create_router()))
+        .push(Router::with_path("org").push(organization::create_router()))
+        .push(Router::with_path("paper").push(paper::create_router()))
+        .push(Router::with_path("reading-list").push(reading_list::create_router()))
+        .push(Router::with_path("settings").push(settings::create_router()))
+        .push(Router::with_path("stats").push(stats::create_router()))
+        .push(Router::with_path("uploads").push(upload::create_router()))
+        .push(Router::with_path("usage").push(usage::create_router()))
         .push(Router::with_path("user").push(user::create_router()))
+        .push(Router::with_path("webhooks").push(webhook::create_router()))
+        .push(Router::with_path("ws").push(ws::create_router()));
+    let auth_router = Router::new()
+        .hoop(auth_handler)
+        .hoop(require_csrf)
+        .hoop(limit_global)
+        .hoop(serve_stale)
+        .hoop(jwt_to_user)
+        .hoop(idempotent)
+        .push(consent_free_router)
+        .push(consent_router)
         .oapi_security(SecurityRequirement::new("bearer", vec!["bearer"]));
 
-    Router::new().push(non_auth_router).push(auth_router)
+    Router::new()
+        .hoop(refuse_writes_while_restoring)
+        .push(non_auth_router)
+        .push(auth_router)
+}
+
+/// The swagger ui and the specs as the docs config exposes them, none when
+/// disabled.
+pub fn create_docs_router(
+    config: &BackendConfig,
+    access: DocsAccess,
+    docs: Router,
+) -> Option<Router> {
+    match access {
+        DocsAccess::Public => Some(docs),
+        DocsAccess::Operators => Some(
+            Router::new()
+                .hoop(jwt_auth(config))
+                .hoop(jwt_to_user)
+                .hoop(admin::require_operator)
+                .push(docs),
+        ),
+        DocsAccess::Disabled => None,
+    }
 }
 
 #[salvo::handler]
@@ -51,19 +169,41 @@ async fn jwt_to_user(
         (JwtAuthState::Authorized, Some(jwt_token)) => {
             tracing::info!("JWT is authorized");
             let claim = jwt_token.claims.clone();
+            if claim.r#type != JwtType::Access {
+                tracing::info!("JWT is not an access token");
+                res.render(ServiceError::Unauthorized("JWT Unauthorized".to_string()));
+                ctrl.skip_rest();
+                return Ok(());
+            }
             if claim.is_expired() {
                 tracing::info!("JWT is expired");
                 res.render(ServiceError::Unauthorized("JWT is expired".to_string()));
                 ctrl.skip_rest();
             }
             let state = depot.obtain::<AppDataRef>()?;
-            let user = state.mongo_client.get_user_by_uid(&claim.sub).await?;
+            if let Err(e) = claim.check_tenant(&state.tenant.id) {
+                tracing::info!("JWT of another tenant: {}", claim.tenant());
+                res.render(e);
+                ctrl.skip_rest();
+                return Ok(());
+            }
+            let user = state.cached_user(&claim.sub).await?;
             let Some(user) = user else {
                 tracing::info!("Invalid user id: {}", claim.sub);
                 res.render(ServiceError::Unauthorized("User not found".to_string()));
                 ctrl.skip_rest();
                 return Ok(());
             };
+            if user.is_token_revoked(claim.iat) {
+                tracing::info!("JWT is revoked for user: {}", claim.sub);
+                res.render(ServiceError::Unauthorized("JWT is revoked".to_string()));
+                ctrl.skip_rest();
+                return Ok(());
+            }
+            // the language the user chose over the one of the browser
+            if let Some(locale) = user.settings.language.as_deref().and_then(Locale::from_tag) {
+                set_locale(depot, res, locale);
+            }
             depot.inject(user);
             // depot.insert(DEPOT_USER, user);
             ctrl.call_next(req, depot, res).await;
@@ -83,3 +223,27 @@ async fn jwt_to_user(
     }
     Ok(())
 }
+
+/// Stop users who have not accepted the current legal documents, e.g. after a
+/// version bump, until they consent again.
+#[salvo::handler]
+async fn require_consent(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+    ctrl: &mut FlowCtrl,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+    let pending = pending_consents(user, &state.legal_config);
+    if !pending.is_empty() {
+        res.render(ServiceError::ConsentRequired(format!(
+            "Accept the current {} first",
+            pending.join(", ")
+        )));
+        ctrl.skip_rest();
+        return Ok(());
+    }
+    ctrl.call_next(req, depot, res).await;
+    Ok(())
+}
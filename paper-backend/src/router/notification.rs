@@ -0,0 +1,133 @@
+use salvo::{
+    Depot, Router,
+    oapi::{
+        RouterExt, endpoint,
+        extract::{PathParam, QueryParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult},
+    model::{
+        notification::{
+            NotificationRepository,
+            schema::{ListNotificationsResponse, UnreadCountResponse},
+        },
+        user::User,
+    },
+};
+
+const DEFAULT_NOTIFICATION_LIMIT: i64 = 50;
+const MAX_NOTIFICATION_LIMIT: i64 = 200;
+
+pub fn create_router() -> Router {
+    Router::new()
+        .push(Router::new().get(list_notifications))
+        .push(Router::with_path("unread-count").get(get_unread_count))
+        .push(Router::with_path("read-all").post(mark_all_read))
+        .push(Router::with_path("{notification_id}/read").post(mark_read))
+        .oapi_tag("notification")
+}
+
+/// List Notifications
+///
+/// Lists the latest notifications of the authenticated user, newest first, with
+/// the number of unread ones. `unread_only` skips the read ones, `limit` defaults to 50.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListNotificationsResponse, description = "Notifications of the user"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn list_notifications(
+    depot: &mut Depot,
+    unread_only: QueryParam<bool, false>,
+    limit: QueryParam<i64, false>,
+) -> ServiceResult<ListNotificationsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let limit = limit
+        .into_inner()
+        .unwrap_or(DEFAULT_NOTIFICATION_LIMIT)
+        .clamp(1, MAX_NOTIFICATION_LIMIT);
+    let notifications = state
+        .db
+        .get_notifications(&user.uid, unread_only.into_inner().unwrap_or(false), limit)
+        .await?;
+    let unread_count = state.db.count_unread_notifications(&user.uid).await?;
+    Ok(ListNotificationsResponse {
+        items: notifications.into_iter().map(Into::into).collect(),
+        unread_count,
+    })
+}
+
+/// Get Unread Count
+///
+/// Counts the unread notifications of the authenticated user, e.g. for a badge.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = UnreadCountResponse, description = "Number of unread notifications"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn get_unread_count(depot: &mut Depot) -> ServiceResult<UnreadCountResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let unread_count = state.db.count_unread_notifications(&user.uid).await?;
+    Ok(UnreadCountResponse { unread_count })
+}
+
+/// Mark Notification Read
+///
+/// Marks a notification of the authenticated user as read.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = UnreadCountResponse, description = "Notification marked read, remaining unread count"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Notification does not exist")
+    )
+)]
+async fn mark_read(
+    depot: &mut Depot,
+    notification_id: PathParam<String>,
+) -> ServiceResult<UnreadCountResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let found = state
+        .db
+        .mark_notification_read(&user.uid, &notification_id)
+        .await?;
+    if !found {
+        return Err(ServiceError::NotFound(format!(
+            "Notification {}",
+            notification_id.as_str()
+        )));
+    }
+    let unread_count = state.db.count_unread_notifications(&user.uid).await?;
+    Ok(UnreadCountResponse { unread_count })
+}
+
+/// Mark All Notifications Read
+///
+/// Marks every notification of the authenticated user as read.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = UnreadCountResponse, description = "Notifications marked read"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn mark_all_read(depot: &mut Depot) -> ServiceResult<UnreadCountResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    state.db.mark_all_notifications_read(&user.uid).await?;
+    Ok(UnreadCountResponse { unread_count: 0 })
+}
@@ -0,0 +1,222 @@
+use salvo::{
+    Depot, Response, Router,
+    oapi::{
+        RouterExt, endpoint,
+        extract::{JsonBody, PathParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    authz::{Access, Principal, Resource, assert_can},
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    i18n::default_locale,
+    model::{
+        organization::{
+            FolderTemplate, Organization, OrganizationRepository,
+            schema::{
+                AddMemberRequest, CreateOrganizationRequest, OrganizationResponse,
+                UpdateFolderTemplateRequest,
+            },
+        },
+        user::{User, UserRepository},
+    },
+    router::folder::provision_folders,
+    utils::{cache::CacheKey, validate::ValidatedRequest},
+};
+
+pub fn create_router() -> Router {
+    Router::new()
+        .push(Router::new().post(create_organization))
+        .push(
+            Router::with_path("{org_id}")
+                .get(get_organization)
+                .push(Router::with_path("folder-template").put(update_folder_template))
+                .push(Router::with_path("member").post(add_member)),
+        )
+        .oapi_tag("organization")
+}
+
+/// Fetch the organization and ensure the user may access it: the members read
+/// it, the admins manage it.
+async fn fetch_organization(
+    state: &AppDataRef,
+    org_id: &str,
+    user: &User,
+    access: Access,
+) -> ServiceResult<Organization> {
+    let org = state
+        .db
+        .get_organization_by_id(org_id)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound(format!("Organization {}", org_id)))?;
+    let principal = Principal::of(state, user);
+    assert_can(access, Resource::Organization(&org), principal)?;
+    Ok(org)
+}
+
+fn template_depth(folders: &[FolderTemplate]) -> usize {
+    folders
+        .iter()
+        .map(|folder| 1 + template_depth(&folder.children))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Create Organization
+///
+/// Creates an organization administrated by the authenticated user, who becomes its first member.
+#[endpoint(
+    status_codes(201, 400, 401, 422),
+    responses(
+        (status_code = 201, body = OrganizationResponse, description = "Organization created successfully"),
+        (status_code = 400, description = "Bad Request: User is already member of an organization"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn create_organization(
+    depot: &mut Depot,
+    request: JsonBody<CreateOrganizationRequest>,
+    resp: &mut Response,
+) -> ServiceResult<OrganizationResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    if user.org_id.is_some() {
+        return Err(ServiceError::BadRequest(
+            "You are already member of an organization".to_string(),
+        ));
+    }
+
+    let org = Organization::new(request.name, &user.uid);
+    state.db.create_organization(org.clone()).await?;
+    let mut user = user.clone();
+    user.org_id = Some(org.id.clone());
+    user.updated_at = bson::DateTime::now();
+    state.db.update_user(user.clone()).await?;
+    state.invalidate(&[CacheKey::User(&user.uid)]).await;
+
+    resp.status_code(salvo::http::StatusCode::CREATED);
+    Ok(org.into())
+}
+
+/// Get Organization
+///
+/// Gets the organization of the authenticated user.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = OrganizationResponse, description = "Organization details"),
+        (status_code = 401, description = "Unauthorized: User not a member"),
+        (status_code = 404, description = "Not Found: Organization does not exist")
+    )
+)]
+async fn get_organization(
+    depot: &mut Depot,
+    org_id: PathParam<String>,
+) -> ServiceResult<OrganizationResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let org = fetch_organization(state, &org_id, user, Access::Read).await?;
+    Ok(org.into())
+}
+
+/// Update Folder Template
+///
+/// Replaces the folders provisioned for every new member of the organization.
+/// An empty template provisions the default system folder.
+#[endpoint(
+    status_codes(200, 401, 404, 422),
+    responses(
+        (status_code = 200, body = OrganizationResponse, description = "Folder template updated successfully"),
+        (status_code = 401, description = "Unauthorized: User not an admin of the organization"),
+        (status_code = 404, description = "Not Found: Organization does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn update_folder_template(
+    depot: &mut Depot,
+    org_id: PathParam<String>,
+    request: JsonBody<UpdateFolderTemplateRequest>,
+) -> ServiceResult<OrganizationResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let mut org = fetch_organization(state, &org_id, user, Access::Write).await?;
+    let template = request
+        .folders
+        .into_iter()
+        .map(Into::into)
+        .collect::<Vec<FolderTemplate>>();
+    // the depth quota of the config, whatever the overrides of the members
+    let max_depth = state.quota_config.max_folder_depth;
+    if template_depth(&template) as u64 > max_depth {
+        return Err(ServiceError::invalid_field(
+            "folders",
+            "depth",
+            format!("Folders can be nested at most {} levels", max_depth),
+        ));
+    }
+
+    org.folder_template = template;
+    org.updated_at = bson::DateTime::now();
+    let org = state.db.update_organization(org).await?;
+    Ok(org.into())
+}
+
+/// Add Member
+///
+/// Adds a registered user to the organization and provisions the folders of the template.
+#[endpoint(
+    status_codes(200, 400, 401, 404, 422),
+    responses(
+        (status_code = 200, body = OrganizationResponse, description = "Member added successfully"),
+        (status_code = 400, description = "Bad Request: User is already member of an organization"),
+        (status_code = 401, description = "Unauthorized: User not an admin of the organization"),
+        (status_code = 404, description = "Not Found: Organization does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn add_member(
+    depot: &mut Depot,
+    org_id: PathParam<String>,
+    request: JsonBody<AddMemberRequest>,
+) -> ServiceResult<OrganizationResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let mut org = fetch_organization(state, &org_id, user, Access::Write).await?;
+    let mut member = state
+        .db
+        .get_user_by_email(&request.email)
+        .await?
+        .ok_or_else(|| {
+            ServiceError::invalid_field("email", "not_found", "No user with this email")
+        })?;
+    if member.org_id.is_some() {
+        return Err(ServiceError::BadRequest(
+            "User is already member of an organization".to_string(),
+        ));
+    }
+
+    member.org_id = Some(org.id.clone());
+    member.updated_at = bson::DateTime::now();
+    state.db.update_user(member.clone()).await?;
+    // added by another user, in the language of the server unless they chose one
+    provision_folders(&state.db, &member, default_locale(&state.settings_config)).await?;
+    state
+        .invalidate(&[CacheKey::User(&member.uid), CacheKey::Folders(&member.uid)])
+        .await;
+
+    if request.admin && !org.is_admin(&member.uid) {
+        org.admin_ids.push(member.uid);
+        org.updated_at = bson::DateTime::now();
+        org = state.db.update_organization(org).await?;
+    }
+    Ok(org.into())
+}
@@ -0,0 +1,2172 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use futures::TryStreamExt;
+use salvo::{
+    Depot, Request, Response, Router, Writer, handler,
+    http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, HeaderValue},
+    oapi::{
+        RouterExt, endpoint,
+        extract::{JsonBody, PathParam, QueryParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    authz::{Access, Principal, Resource, assert_can, can},
+    citation::refresh_citations,
+    collab::{ClosedNotes, notes_replaced},
+    dedup::{
+        compare::{PaperComparison, compare_papers},
+        find_duplicates, merge_papers,
+    },
+    embedding::cosine_similarity,
+    error::{ErrorResponse, ServiceError, ServiceResult, ValidationErrorResponse},
+    events::DomainEvent,
+    export::{ExportFormat, ExportOptions, export_paper_as, pdf::export_papers},
+    model::{
+        activity::{ActivityAction, ActivityKind},
+        blob::{BlobRepository, paper_thumbnail_key},
+        block::{BlockRepository, expand_blocks, referenced_block_ids},
+        chunk::{
+            PaperChunkRepository,
+            schema::{AskPaperRequest, AskPaperResponse},
+        },
+        citation::{CitationRepository, schema::PaperCitationsResponse},
+        content::{release_file, store_file},
+        custom_field::{CustomFieldRepository, apply_values, field_filter},
+        embedding::PaperEmbeddingRepository,
+        folder::{FolderRepository, STARRED_FOLDER_ID, SmartQuery, smart_query_filter},
+        math::schema::PaperMathResponse,
+        note::{
+            paper_note_key,
+            schema::{NotesResponse, UpdateNotesRequest},
+        },
+        page::{PaperPageRepository, schema::PaperTextResponse},
+        paper::{
+            Paper, PaperBatchOp, PaperRepository, TextStatus,
+            schema::{
+                AcceptSuggestionsRequest, BatchAction, BatchExport, BatchItemResult,
+                BatchPaperRequest, BatchPaperResponse, CreatePaperRequest, DuplicateGroupResponse,
+                ListDuplicatesResponse, ListPapersResponse, MergePapersRequest,
+                PaperFileUrlResponse, PaperResponse, RelatedPaperResponse, RelatedPapersResponse,
+                SearchPapersResponse, SearchResultResponse, UpdatePaperRequest,
+            },
+        },
+        reading_list::ReadingListRepository,
+        revision::{
+            PaperRevision, PaperRevisionRepository,
+            schema::{
+                FieldDiff, ListPaperRevisionsResponse, PaperRevisionDiffResponse,
+                PaperRevisionResponse,
+            },
+        },
+        share::{
+            ShareLink, ShareRepository, reviewer_handle,
+            schema::{
+                CreateShareLinkRequest, ListCommentsResponse, ListShareLinksResponse,
+                ShareLinkResponse,
+            },
+        },
+        txn::in_transaction,
+        user::User,
+    },
+    pdf::thumbnail::{ThumbnailSize, delete_thumbnails},
+    qa,
+    rate_limit::limit_ai,
+    router::activity::record_activity,
+    scan::run_scan_job,
+    search::{RankContext, folder_scope, rank},
+    tenant::DEFAULT_TENANT,
+    utils::{
+        cache::CacheKey,
+        diff::line_diff,
+        etag::{check_if_match, not_modified, set_etag, weak_etag},
+        fields::{FieldSelection, render_fields, render_fields_list, selected_etag},
+        ndjson::{accepts_ndjson, render_ndjson},
+        signed_url::{file_url_expiry, sign_file},
+        timeout::{extend_for_ai, extend_for_upload},
+        validate::ValidatedRequest,
+    },
+};
+
+const SEARCH_DEFAULT_LIMIT: i64 = 20;
+const SEARCH_MAX_LIMIT: i64 = 100;
+// text matches ranked per returned result
+const SEARCH_RANK_WINDOW: i64 = 5;
+const RELATED_DEFAULT_LIMIT: usize = 10;
+const RELATED_MAX_LIMIT: usize = 50;
+// attempts to save the edited notes into a paper updated meanwhile
+const SAVE_NOTES_ATTEMPTS: usize = 3;
+
+pub fn create_router() -> Router {
+    Router::new()
+        .push(Router::new().get(list_papers).post(create_paper))
+        .push(Router::with_path("batch").post(batch_papers))
+        .push(Router::with_path("duplicates").get(list_duplicates))
+        .push(Router::with_path("merge").post(merge_duplicates))
+        .push(
+            Router::with_path("compare").get(compare).push(
+                Router::new()
+                    .hoop(limit_ai)
+                    .hoop(extend_for_ai)
+                    .post(super::comparison::create_comparison),
+            ),
+        )
+        .push(Router::with_path("search").get(search_papers))
+        .push(Router::with_path("starred").get(list_starred_papers))
+        .push(
+            Router::with_path("{paper_id}")
+                .get(get_paper)
+                .put(update_paper)
+                .delete(delete_paper)
+                .push(Router::with_path("touch").post(touch_paper))
+                .push(
+                    Router::with_path("star")
+                        .post(star_paper)
+                        .delete(unstar_paper),
+                )
+                .push(Router::with_path("export").get(export_paper))
+                .push(
+                    Router::with_path("file")
+                        .push(Router::new().hoop(extend_for_upload).put(upload_paper_file))
+                        .push(Router::with_path("url").get(get_paper_file_url)),
+                )
+                .push(Router::with_path("thumbnail").get(get_paper_thumbnail))
+                .push(Router::with_path("text").get(get_paper_text))
+                .push(Router::with_path("math").get(get_paper_math))
+                .push(
+                    Router::with_path("ask")
+                        .hoop(limit_ai)
+                        .hoop(extend_for_ai)
+                        .post(ask_paper),
+                )
+                .push(Router::with_path("related").get(get_related_papers))
+                .push(Router::with_path("suggestions/accept").post(accept_suggestions))
+                .push(
+                    Router::with_path("notes")
+                        .get(get_notes)
+                        .post(update_notes)
+                        .push(Router::with_path("ws").get(connect_notes)),
+                )
+                .push(
+                    Router::with_path("revisions")
+                        .get(list_paper_revisions)
+                        .push(Router::with_path("diff").get(diff_paper_revisions))
+                        .push(
+                            Router::with_path("{revision}")
+                                .get(get_paper_revision)
+                                .push(Router::with_path("restore").post(restore_paper_revision)),
+                        ),
+                )
+                .push(
+                    Router::with_path("citations")
+                        .get(get_paper_citations)
+                        .post(refresh_paper_citations),
+                )
+                .push(
+                    Router::with_path("share-link")
+                        .get(list_share_links)
+                        .post(create_share_link)
+                        .push(Router::with_path("{link_id}").delete(revoke_share_link)),
+                )
+                .push(
+                    Router::with_path("comment")
+                        .get(list_comments)
+                        .push(Router::with_path("{comment_id}").delete(delete_comment)),
+                ),
+        )
+        .oapi_tag("paper")
+}
+
+/// Ensure the folder exists and can be written by the user.
+async fn check_folder_owner(state: &AppDataRef, folder_id: &str, user: &User) -> ServiceResult<()> {
+    let principal = Principal::of(state, user);
+    let folder = state
+        .db
+        .get_folder_by_id(folder_id)
+        .await?
+        .filter(|folder| can(Access::Write, Resource::Folder(folder), principal))
+        .ok_or_else(|| {
+            ServiceError::invalid_field("folderId", "not_found", "Folder does not exist")
+        })?;
+    if folder.archived {
+        return Err(ServiceError::invalid_field(
+            "folderId",
+            "archived",
+            "Folder is archived",
+        ));
+    }
+    if folder.is_smart() {
+        return Err(ServiceError::invalid_field(
+            "folderId",
+            "smart_folder",
+            "Papers cannot be put in a smart folder",
+        ));
+    }
+    Ok(())
+}
+
+/// Fetch the paper by ID and ensure the user may access it.
+pub(super) async fn fetch_paper(
+    state: &AppDataRef,
+    paper_id: &str,
+    user: &User,
+    access: Access,
+) -> ServiceResult<Paper> {
+    let paper = state.db.get_paper_by_id(paper_id).await?;
+    check_paper_access(paper, paper_id, Principal::of(state, user), access)
+}
+
+fn check_paper_access(
+    paper: Option<Paper>,
+    paper_id: &str,
+    principal: Principal,
+    access: Access,
+) -> ServiceResult<Paper> {
+    let paper = paper.ok_or_else(|| ServiceError::PaperNotFound(paper_id.to_string()))?;
+    assert_can(access, Resource::Paper(&paper), principal)?;
+    Ok(paper)
+}
+
+/// Keep the metadata and notes of the paper as a revision before an update
+/// replaces them, dropping the revisions past the configured count.
+async fn record_revision(state: &AppDataRef, old: &Paper, new: &Paper) -> ServiceResult<()> {
+    let max_revisions = state.revision_config.max_revisions;
+    let revision = PaperRevision::of(old);
+    if max_revisions == 0 || revision.matches(new) {
+        return Ok(());
+    }
+    state.db.save_paper_revision(revision).await?;
+    // the revisions kept are the last ones up to the replaced version
+    let oldest = (old.version + 1).saturating_sub(max_revisions);
+    state
+        .db
+        .delete_paper_revisions_before(&old.id, oldest)
+        .await
+}
+
+/// Drop the CRDT state of notes replaced by an update, the next collaborative
+/// editing starts over from the new notes.
+async fn reset_notes(state: &AppDataRef, old: &Paper, new: &Paper) -> ServiceResult<()> {
+    if old.content == new.content {
+        return Ok(());
+    }
+    state.db.delete_blob(&paper_note_key(&new.id)).await
+}
+
+/// Save the notes merged by the collaborative editing into the paper, once
+/// the last participant left. The notes replaced by an update of the paper
+/// since the room opened are kept, the merged ones are not saved over them.
+pub(super) async fn save_notes(
+    state: &AppDataRef,
+    paper_id: &str,
+    notes: ClosedNotes,
+) -> ServiceResult<()> {
+    for _ in 0..SAVE_NOTES_ATTEMPTS {
+        // deleted meanwhile
+        let Some(mut paper) = state.db.get_paper_by_id(paper_id).await? else {
+            return Ok(());
+        };
+        let content = paper.content.as_deref().unwrap_or_default();
+        if content == notes.markdown {
+            return Ok(());
+        }
+        if content != notes.base {
+            return Err(notes_replaced(paper_id));
+        }
+        let previous = paper.clone();
+        paper.content = Some(notes.markdown.clone());
+        paper.updated_at = bson::DateTime::now();
+
+        record_revision(state, &previous, &paper).await?;
+        match state.db.update_paper(paper).await {
+            Ok(paper) => {
+                state.invalidate(&[CacheKey::Paper(&paper.id)]).await;
+                state.events.publish(
+                    &paper.user_id,
+                    DomainEvent::PaperUpdated {
+                        paper_id: paper.id.clone(),
+                    },
+                );
+                return Ok(());
+            }
+            Err(ServiceError::VersionConflict(_)) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(ServiceError::VersionConflict(format!(
+        "Paper {} was modified concurrently",
+        paper_id
+    )))
+}
+
+/// Apply an update of the notes made without the WebSocket, if any, and
+/// return the merged notes.
+pub(super) async fn edit_notes(
+    state: &AppDataRef,
+    paper: &Paper,
+    update: Option<&str>,
+) -> ServiceResult<NotesResponse> {
+    let update = update
+        .map(|update| {
+            STANDARD.decode(update).map_err(|_| {
+                ServiceError::invalid_field("update", "base64", "Update must be base64 encoded")
+            })
+        })
+        .transpose()?;
+    let session = state.notes.join(state, paper).await?;
+    let applied = match update {
+        Some(bytes) => session.room.apply(state, session.participant, bytes).await,
+        None => Ok(()),
+    };
+    let response = NotesResponse {
+        paper_id: paper.id.clone(),
+        markdown: session.room.markdown(),
+        state: STANDARD.encode(session.room.state()),
+    };
+    if let Some(notes) = state.notes.leave(session).await {
+        save_notes(state, &paper.id, notes).await?;
+    }
+    applied.map(|_| response)
+}
+
+/// Transclude the reusable blocks referenced in the paper text.
+pub(super) async fn expand_paper_blocks(
+    state: &AppDataRef,
+    paper: &mut Paper,
+) -> ServiceResult<()> {
+    let texts = [&paper.r#abstract, &paper.summary, &paper.content];
+    let ids = texts
+        .iter()
+        .filter_map(|text| text.as_deref())
+        .flat_map(referenced_block_ids)
+        .collect::<Vec<_>>();
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let blocks = state.db.get_blocks_by_ids(&paper.user_id, &ids).await?;
+    for text in [
+        &mut paper.r#abstract,
+        &mut paper.summary,
+        &mut paper.content,
+    ] {
+        if let Some(text) = text.as_mut() {
+            *text = expand_blocks(text, &blocks);
+        }
+    }
+    Ok(())
+}
+
+/// Filter on the papers listed by a smart folder, narrowed down by the `field` query.
+pub(super) async fn smart_folder_filter(
+    state: &AppDataRef,
+    user: &User,
+    query: &SmartQuery,
+    field: Option<&str>,
+) -> ServiceResult<bson::Document> {
+    let expr = [query.field.as_deref(), field]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(",");
+    let fields = custom_field_filter(state, user, Some(expr.as_str())).await?;
+    let scope = match query.folder_id.as_deref() {
+        Some(folder_id) => {
+            let folders = state.cached_folders(&user.uid).await?;
+            Some(folder_scope(&folders, folder_id).into_iter().collect())
+        }
+        None => None,
+    };
+    Ok(smart_query_filter(query, scope, fields))
+}
+
+/// Filter on the custom fields of the papers from the `field` query.
+pub(super) async fn custom_field_filter(
+    state: &AppDataRef,
+    user: &User,
+    expr: Option<&str>,
+) -> ServiceResult<Option<bson::Document>> {
+    let Some(expr) = expr else {
+        return Ok(None);
+    };
+    let fields = state
+        .db
+        .get_custom_fields(&user.uid, user.org_id.as_deref())
+        .await?;
+    field_filter(expr, &fields)
+}
+
+/// List Papers
+///
+/// Lists the papers of the authenticated user, newest first, optionally only those
+/// of a folder. With `Accept: application/x-ndjson` the papers are streamed one per line.
+/// `fields` selects the returned fields, e.g. `fields=title,authors`. `field` filters
+/// on custom fields, e.g. `field=status=read,pages>=10`. The papers of a smart folder
+/// are those matching its query, run again on every listing, `folderId=starred`
+/// lists the starred papers.
+#[endpoint(
+    status_codes(200, 401, 422),
+    responses(
+        (status_code = 200, body = ListPapersResponse, description = "List of papers, or a stream of papers as ndjson"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Unknown field or invalid filter")
+    )
+)]
+async fn list_papers(
+    req: &mut Request,
+    depot: &mut Depot,
+    folder_id: QueryParam<String, false>,
+    fields: QueryParam<String, false>,
+    field: QueryParam<String, false>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let selection = FieldSelection::parse::<PaperResponse>(fields.as_deref())?;
+    let smart_query = match folder_id.as_deref() {
+        Some(STARRED_FOLDER_ID) => Some(SmartQuery::starred()),
+        Some(folder_id) => state
+            .cached_folders(&user.uid)
+            .await?
+            .into_iter()
+            .find(|folder| folder.id == folder_id)
+            .and_then(|folder| folder.query),
+        None => None,
+    };
+    let (folder_id, filter) = match smart_query {
+        Some(query) => {
+            let filter = smart_folder_filter(state, user, &query, field.as_deref()).await?;
+            (None, Some(filter))
+        }
+        None => {
+            let filter = custom_field_filter(state, user, field.as_deref()).await?;
+            (folder_id.into_inner(), filter)
+        }
+    };
+    let cursor = state
+        .db
+        .find_papers(
+            &user.uid,
+            folder_id.as_deref(),
+            filter,
+            selection.as_ref().map(FieldSelection::projection),
+        )
+        .await?;
+    if accepts_ndjson(req) {
+        match selection {
+            Some(selection) => {
+                let items =
+                    cursor.map_ok(move |paper| selection.select(&PaperResponse::from(paper)));
+                render_ndjson::<_, _, serde_json::Value>(resp, items);
+            }
+            None => render_ndjson::<_, _, PaperResponse>(resp, cursor),
+        }
+        return Ok(());
+    }
+    let papers: Vec<Paper> = cursor.try_collect().await?;
+    let papers = papers.into_iter().map(PaperResponse::from).collect();
+    render_fields_list(resp, selection.as_ref(), papers);
+    Ok(())
+}
+
+/// List Starred Papers
+///
+/// Lists the starred papers of the authenticated user, newest first. `fields` selects
+/// the returned fields.
+#[endpoint(
+    status_codes(200, 401, 422),
+    responses(
+        (status_code = 200, body = ListPapersResponse, description = "List of starred papers"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Unknown field")
+    )
+)]
+async fn list_starred_papers(
+    depot: &mut Depot,
+    fields: QueryParam<String, false>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let selection = FieldSelection::parse::<PaperResponse>(fields.as_deref())?;
+    let filter = smart_query_filter(&SmartQuery::starred(), None, None);
+    let papers: Vec<Paper> = state
+        .db
+        .find_papers(
+            &user.uid,
+            None,
+            Some(filter),
+            selection.as_ref().map(FieldSelection::projection),
+        )
+        .await?
+        .try_collect()
+        .await?;
+    let papers = papers.into_iter().map(PaperResponse::from).collect();
+    render_fields_list(resp, selection.as_ref(), papers);
+    Ok(())
+}
+
+/// Create Paper
+///
+/// Creates a new paper in a folder of the authenticated user, the default
+/// folder of their settings unless one is given, within the papers per folder
+/// quota.
+#[endpoint(
+    status_codes(201, 401, 403, 422),
+    responses(
+        (status_code = 201, body = PaperResponse, description = "Paper created successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 403, description = "Forbidden: Papers per folder quota exceeded"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error, or no folder given nor set by default")
+    )
+)]
+async fn create_paper(
+    depot: &mut Depot,
+    request: JsonBody<CreatePaperRequest>,
+    resp: &mut Response,
+) -> ServiceResult<PaperResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let folder_id = request
+        .folder_id
+        .clone()
+        .or_else(|| user.settings.default_folder_id.clone())
+        .ok_or_else(|| {
+            ServiceError::invalid_field(
+                "folderId",
+                "required",
+                "No folder given nor set by default",
+            )
+        })?;
+    check_folder_owner(state, &folder_id, user).await?;
+    state.ensure_folder_room(user, &folder_id, 1).await?;
+
+    let paper = Paper::new_from_request(&user.uid, &folder_id, request);
+    state.db.create_paper(paper.clone()).await?;
+    state.events.publish(
+        &user.uid,
+        DomainEvent::PaperCreated {
+            paper_id: paper.id.clone(),
+            folder_id: paper.folder_id.clone(),
+        },
+    );
+    resp.status_code(salvo::http::StatusCode::CREATED);
+    Ok(paper.into())
+}
+
+/// Get Paper
+///
+/// Gets a paper of the authenticated user, `fields` selects the returned fields.
+/// Answers 304 when `If-None-Match` has the current `ETag` of the paper.
+#[endpoint(
+    status_codes(200, 304, 401, 404, 422),
+    responses(
+        (status_code = 200, body = PaperResponse, description = "Paper details"),
+        (status_code = 304, description = "Not Modified: The paper did not change"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Unknown field")
+    )
+)]
+async fn get_paper(
+    req: &mut Request,
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    fields: QueryParam<String, false>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let selection = FieldSelection::parse::<PaperResponse>(fields.as_deref())?;
+    let principal = Principal::of(state, user);
+    let paper = match &selection {
+        Some(selection) => {
+            let paper = state
+                .db
+                .get_paper_projected(&paper_id, selection.projection())
+                .await?;
+            check_paper_access(paper, &paper_id, principal, Access::Read)?
+        }
+        None => {
+            let paper = state.cached_paper(&paper_id).await?;
+            check_paper_access(paper, &paper_id, principal, Access::Read)?
+        }
+    };
+    record_activity(
+        state,
+        &user.uid,
+        ActivityKind::Paper,
+        &paper.id,
+        ActivityAction::Viewed,
+    );
+    let etag = selected_etag(
+        selection.as_ref(),
+        weak_etag(paper.updated_at, paper.version),
+    );
+    if not_modified(req, resp, &etag) {
+        return Ok(());
+    }
+    render_fields(resp, selection.as_ref(), PaperResponse::from(paper));
+    Ok(())
+}
+
+/// Update Paper
+///
+/// Updates a paper of the authenticated user. `If-Match` must have the `ETag` of the
+/// paper the update is based on. `customFields` sets the values of the given custom
+/// fields only, each must fit the type of its field.
+#[endpoint(
+    status_codes(200, 401, 403, 404, 409, 412, 422, 428),
+    request_body(content = UpdatePaperRequest, description = "Update paper details"),
+    responses(
+        (status_code = 200, body = PaperResponse, description = "Paper updated successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 403, description = "Forbidden: Papers per folder quota of the new folder exceeded"),
+        (status_code = 404, description = "Not Found: Paper does not exist"),
+        (status_code = 409, description = "Conflict: The paper was modified concurrently"),
+        (status_code = 412, description = "Precondition Failed: The paper was modified meanwhile"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error"),
+        (status_code = 428, description = "Precondition Required: If-Match header is missing")
+    )
+)]
+async fn update_paper(
+    req: &mut Request,
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    request: JsonBody<UpdatePaperRequest>,
+    resp: &mut Response,
+) -> ServiceResult<PaperResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let mut paper = fetch_paper(state, &paper_id, user, Access::Write).await?;
+    check_if_match(req, &weak_etag(paper.updated_at, paper.version))?;
+    let previous = paper.clone();
+    if let Some(folder_id) = request.folder_id {
+        check_folder_owner(state, &folder_id, user).await?;
+        if folder_id != paper.folder_id {
+            state.ensure_folder_room(user, &folder_id, 1).await?;
+        }
+        paper.folder_id = folder_id;
+    }
+    if let Some(title) = request.title {
+        paper.title = title;
+    }
+    if let Some(authors) = request.authors {
+        paper.authors = authors;
+    }
+    if let Some(tags) = request.tags {
+        paper.tags = tags;
+    }
+    if request.r#abstract.is_some() {
+        paper.r#abstract = request.r#abstract;
+    }
+    if request.doi.is_some() {
+        paper.doi = request.doi;
+    }
+    if request.content.is_some() {
+        paper.content = request.content;
+    }
+    if let Some(changes) = request.custom_fields {
+        let fields = state
+            .db
+            .get_custom_fields(&user.uid, user.org_id.as_deref())
+            .await?;
+        apply_values(&mut paper.custom_fields, changes, &fields)?;
+    }
+    paper.updated_at = bson::DateTime::now();
+
+    record_revision(state, &previous, &paper).await?;
+    let updated_paper = state.db.update_paper(paper).await?;
+    reset_notes(state, &previous, &updated_paper).await?;
+    state
+        .invalidate(&[CacheKey::Paper(&updated_paper.id)])
+        .await;
+    state.events.publish(
+        &user.uid,
+        DomainEvent::PaperUpdated {
+            paper_id: updated_paper.id.clone(),
+        },
+    );
+    record_activity(
+        state,
+        &user.uid,
+        ActivityKind::Paper,
+        &updated_paper.id,
+        ActivityAction::Edited,
+    );
+    set_etag(
+        resp,
+        &weak_etag(updated_paper.updated_at, updated_paper.version),
+    );
+    Ok(updated_paper.into())
+}
+
+/// Accept Suggestions
+///
+/// Applies the tags and folder suggested for a paper of the authenticated user
+/// when it was imported: the accepted tags are added and the paper is moved to
+/// the suggested folder, then the suggestions are cleared. `tags` narrows down
+/// the accepted tags, `folder: false` keeps the paper where it is.
+#[endpoint(
+    status_codes(200, 401, 403, 404, 409, 422),
+    responses(
+        (status_code = 200, body = PaperResponse, description = "Suggestions applied"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 403, description = "Forbidden: Papers per folder quota of the suggested folder exceeded"),
+        (status_code = 404, description = "Not Found: Paper does not exist or has no suggestions"),
+        (status_code = 409, description = "Conflict: The paper was modified concurrently"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Tag not suggested or folder no longer available")
+    )
+)]
+async fn accept_suggestions(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    request: JsonBody<AcceptSuggestionsRequest>,
+    resp: &mut Response,
+) -> ServiceResult<PaperResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let mut paper = fetch_paper(state, &paper_id, user, Access::Write).await?;
+    let previous = paper.clone();
+    let suggestions = paper
+        .suggestions
+        .take()
+        .ok_or_else(|| ServiceError::NotFound(format!("Suggestions of paper {}", paper.id)))?;
+
+    let tags = request.tags.unwrap_or_else(|| suggestions.tags.clone());
+    if let Some(tag) = tags.iter().find(|tag| !suggestions.tags.contains(tag)) {
+        return Err(ServiceError::invalid_field(
+            "tags",
+            "not_suggested",
+            format!("Tag {} was not suggested", tag),
+        ));
+    }
+    for tag in tags {
+        if !paper.tags.contains(&tag) {
+            paper.tags.push(tag);
+        }
+    }
+    if let (true, Some(folder_id)) = (request.folder.unwrap_or(true), suggestions.folder_id) {
+        check_folder_owner(state, &folder_id, user).await?;
+        if folder_id != paper.folder_id {
+            state.ensure_folder_room(user, &folder_id, 1).await?;
+        }
+        paper.folder_id = folder_id;
+    }
+    paper.updated_at = bson::DateTime::now();
+
+    record_revision(state, &previous, &paper).await?;
+    let updated_paper = state.db.update_paper(paper).await?;
+    state
+        .invalidate(&[CacheKey::Paper(&updated_paper.id)])
+        .await;
+    state.events.publish(
+        &user.uid,
+        DomainEvent::PaperUpdated {
+            paper_id: updated_paper.id.clone(),
+        },
+    );
+    record_activity(
+        state,
+        &user.uid,
+        ActivityKind::Paper,
+        &updated_paper.id,
+        ActivityAction::Edited,
+    );
+    set_etag(
+        resp,
+        &weak_etag(updated_paper.updated_at, updated_paper.version),
+    );
+    Ok(updated_paper.into())
+}
+
+/// Remove what belongs to a deleted paper: its revisions, notes, thumbnails,
+/// pages, chunks, embedding, reading list items and citations. The file is
+/// released by the caller, a merge hands it over to the surviving paper.
+async fn purge_paper(state: &AppDataRef, paper_id: &str) -> ServiceResult<()> {
+    state.db.delete_paper_revisions(paper_id).await?;
+    state.db.delete_blob(&paper_note_key(paper_id)).await?;
+    delete_thumbnails(state, paper_id).await?;
+    state.db.replace_paper_pages(paper_id, Vec::new()).await?;
+    state.db.replace_paper_chunks(paper_id, Vec::new()).await?;
+    state.db.delete_paper_embedding(paper_id).await?;
+    state.db.delete_paper_reading_list_items(paper_id).await?;
+    state.db.delete_paper_citations(paper_id).await
+}
+
+/// Delete Paper
+///
+/// Deletes a paper of the authenticated user.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Paper deleted successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn delete_paper(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Write).await?;
+    state.db.delete_paper(&paper.id).await?;
+    purge_paper(state, &paper.id).await?;
+    state.invalidate(&[CacheKey::Paper(&paper.id)]).await;
+    state.events.publish(
+        &user.uid,
+        DomainEvent::PaperDeleted {
+            paper_id: paper.id.clone(),
+        },
+    );
+    if let Some(hash) = &paper.file_hash {
+        release_file(state.db.as_ref(), hash).await?;
+    }
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+/// Star or unstar a paper of the user.
+async fn set_starred(depot: &mut Depot, paper_id: &str, starred: bool) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, paper_id, user, Access::Write).await?;
+    if paper.starred == starred {
+        return Ok(());
+    }
+    state.db.set_paper_starred(&paper.id, starred).await?;
+    state.invalidate(&[CacheKey::Paper(&paper.id)]).await;
+    state.events.publish(
+        &user.uid,
+        DomainEvent::PaperUpdated {
+            paper_id: paper.id.clone(),
+        },
+    );
+    Ok(())
+}
+
+/// Star Paper
+///
+/// Stars a paper of the authenticated user, listing it among the starred papers.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Paper starred"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn star_paper(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    set_starred(depot, &paper_id, true).await?;
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+/// Unstar Paper
+///
+/// Removes a paper of the authenticated user from the starred papers.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Paper unstarred"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn unstar_paper(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    set_starred(depot, &paper_id, false).await?;
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+/// Touch Paper
+///
+/// Records that the authenticated user opened a paper, for clients showing it
+/// without fetching it again, so it comes first in the activity feed.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Activity recorded"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn touch_paper(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = state.cached_paper(&paper_id).await?;
+    let principal = Principal::of(state, user);
+    let paper = check_paper_access(paper, &paper_id, principal, Access::Read)?;
+    record_activity(
+        state,
+        &user.uid,
+        ActivityKind::Paper,
+        &paper.id,
+        ActivityAction::Viewed,
+    );
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+/// Batch Papers
+///
+/// Moves, tags, deletes or exports many papers in one request. The writes are
+/// done in a single transaction, the result of each paper is reported separately.
+/// A move is refused whole when the papers do not fit into the folder quota.
+#[endpoint(
+    status_codes(200, 401, 403, 422),
+    responses(
+        (status_code = 200, body = BatchPaperResponse, description = "Result of the batch action per paper"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 403, description = "Forbidden: Papers per folder quota of the target folder exceeded"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn batch_papers(
+    depot: &mut Depot,
+    request: JsonBody<BatchPaperRequest>,
+) -> ServiceResult<BatchPaperResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let op = match request.action {
+        BatchAction::Move => {
+            let folder_id = request.folder_id.clone().ok_or_else(|| {
+                ServiceError::invalid_field("folderId", "required", "folderId is required to move")
+            })?;
+            check_folder_owner(state, &folder_id, user).await?;
+            Some(PaperBatchOp::Move { folder_id })
+        }
+        BatchAction::Tag => {
+            if request.tags.is_empty() {
+                return Err(ServiceError::invalid_field(
+                    "tags",
+                    "required",
+                    "tags are required to tag",
+                ));
+            }
+            Some(PaperBatchOp::Tag {
+                tags: request.tags.clone(),
+            })
+        }
+        BatchAction::Delete => Some(PaperBatchOp::Delete),
+        BatchAction::Export => None,
+    };
+
+    // papers of other users are reported as missing
+    let mut papers = state
+        .db
+        .get_papers_by_ids(&user.uid, &request.paper_ids)
+        .await?;
+    papers.sort_by_key(|paper| {
+        request
+            .paper_ids
+            .iter()
+            .position(|id| id == &paper.id)
+            .unwrap_or_default()
+    });
+    let found_ids = papers
+        .iter()
+        .map(|paper| paper.id.clone())
+        .collect::<Vec<_>>();
+    if let Some(PaperBatchOp::Move { folder_id }) = &op {
+        let added = papers
+            .iter()
+            .filter(|paper| &paper.folder_id != folder_id)
+            .count();
+        state
+            .ensure_folder_room(user, folder_id, added as u64)
+            .await?;
+    }
+
+    let modifies = op.is_some();
+    let deletes = matches!(op, Some(PaperBatchOp::Delete));
+    let mut export = None;
+    let outcome = match op {
+        Some(_) if found_ids.is_empty() => Ok(()),
+        Some(op) => {
+            in_transaction(state.db.as_ref(), |db, txn| {
+                let (ids, op) = (found_ids.clone(), op.clone());
+                Box::pin(async move { db.run_paper_batch(txn, &ids, op).await })
+            })
+            .await
+        }
+        None => {
+            for paper in papers.iter_mut() {
+                expand_paper_blocks(state, paper).await?;
+            }
+            let options = ExportOptions::default().with_watermark(request.watermark.clone());
+            export_papers("Papers", &papers, &options).map(|bytes| {
+                export = Some(BatchExport {
+                    filename: "papers.pdf".to_string(),
+                    content_type: "application/pdf".to_string(),
+                    data: STANDARD.encode(bytes),
+                });
+            })
+        }
+    };
+    if modifies {
+        let keys = found_ids
+            .iter()
+            .map(|id| CacheKey::Paper(id))
+            .collect::<Vec<_>>();
+        state.invalidate(&keys).await;
+    }
+    if deletes && outcome.is_ok() {
+        for paper_id in &found_ids {
+            purge_paper(state, paper_id).await?;
+        }
+        for hash in papers.iter().filter_map(|paper| paper.file_hash.as_ref()) {
+            release_file(state.db.as_ref(), hash).await?;
+        }
+    }
+    if modifies && outcome.is_ok() {
+        for paper_id in found_ids.iter().cloned() {
+            let event = if deletes {
+                DomainEvent::PaperDeleted { paper_id }
+            } else {
+                DomainEvent::PaperUpdated { paper_id }
+            };
+            state.events.publish(&user.uid, event);
+        }
+    }
+    if let Err(e) = &outcome {
+        tracing::error!("Batch {:?} failed: {}", request.action, e);
+    }
+
+    let results = request
+        .paper_ids
+        .iter()
+        .map(|id| match &outcome {
+            _ if !found_ids.contains(id) => {
+                let e = ServiceError::PaperNotFound(id.clone());
+                BatchItemResult::failed(id, e.code(), e.message())
+            }
+            Ok(()) => BatchItemResult::ok(id),
+            Err(e) => BatchItemResult::failed(id, e.code(), e.message()),
+        })
+        .collect();
+    Ok(BatchPaperResponse { results, export })
+}
+
+/// List Duplicates
+///
+/// Lists the groups of papers of the authenticated user that look like the same
+/// publication: same DOI, same file or near-identical title.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListDuplicatesResponse, description = "Groups of duplicate papers"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn list_duplicates(depot: &mut Depot) -> ServiceResult<ListDuplicatesResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let papers = state.db.get_papers_by_user_id(&user.uid).await?;
+    let groups = find_duplicates(&papers)
+        .into_iter()
+        .map(|group| DuplicateGroupResponse {
+            reasons: group.reasons,
+            papers: papers
+                .iter()
+                .filter(|paper| group.paper_ids.contains(&paper.id))
+                .cloned()
+                .map(Into::into)
+                .collect(),
+        })
+        .collect();
+    Ok(ListDuplicatesResponse(groups))
+}
+
+/// Merge Papers
+///
+/// Merges duplicate papers into the surviving one: notes are appended, tags united,
+/// and comments and share links moved over. The merged papers are deleted.
+#[endpoint(
+    status_codes(200, 401, 404, 422),
+    responses(
+        (status_code = 200, body = PaperResponse, description = "The surviving paper"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn merge_duplicates(
+    depot: &mut Depot,
+    request: JsonBody<MergePapersRequest>,
+) -> ServiceResult<PaperResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let mut survivor = fetch_paper(state, &request.survivor_id, user, Access::Write).await?;
+    let previous = survivor.clone();
+    let duplicates = state
+        .db
+        .get_papers_by_ids(&user.uid, &request.paper_ids)
+        .await?;
+    if let Some(missing) = request
+        .paper_ids
+        .iter()
+        .find(|id| !duplicates.iter().any(|paper| &paper.id == *id))
+    {
+        return Err(ServiceError::PaperNotFound(missing.clone()));
+    }
+
+    merge_papers(&mut survivor, &duplicates);
+    record_revision(state, &previous, &survivor).await?;
+    let survivor = state.db.update_paper(survivor).await?;
+    reset_notes(state, &previous, &survivor).await?;
+    // the citations, comments and links move over with the deletion of the
+    // duplicates
+    in_transaction(state.db.as_ref(), |db, txn| {
+        let (ids, survivor_id) = (request.paper_ids.clone(), survivor.id.clone());
+        Box::pin(async move {
+            db.reassign_citations(txn, &ids, &survivor_id).await?;
+            db.reassign_paper(txn, &ids, &survivor_id).await?;
+            db.run_paper_batch(txn, &ids, PaperBatchOp::Delete).await
+        })
+    })
+    .await?;
+    for paper_id in &request.paper_ids {
+        purge_paper(state, paper_id).await?;
+    }
+    // the survivor holds on to the file it took over, the others are released
+    let mut inherited = survivor
+        .file_hash
+        .clone()
+        .filter(|_| previous.file_hash.is_none());
+    for hash in duplicates
+        .iter()
+        .filter_map(|paper| paper.file_hash.as_ref())
+    {
+        if inherited.as_ref() == Some(hash) {
+            inherited = None;
+            continue;
+        }
+        release_file(state.db.as_ref(), hash).await?;
+    }
+    let keys = request
+        .paper_ids
+        .iter()
+        .chain([&survivor.id])
+        .map(|id| CacheKey::Paper(id))
+        .collect::<Vec<_>>();
+    state.invalidate(&keys).await;
+    for paper_id in request.paper_ids.iter().cloned() {
+        state
+            .events
+            .publish(&user.uid, DomainEvent::PaperDeleted { paper_id });
+    }
+    state.events.publish(
+        &user.uid,
+        DomainEvent::PaperUpdated {
+            paper_id: survivor.id.clone(),
+        },
+    );
+    Ok(survivor.into())
+}
+
+/// Compare Papers
+///
+/// Compares two different papers of the authenticated user, e.g. duplicate drafts,
+/// field by field and section by section of their notes.
+#[endpoint(
+    status_codes(200, 401, 404, 422),
+    responses(
+        (status_code = 200, body = PaperComparison, description = "Comparison of the two papers"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Same paper given twice")
+    )
+)]
+async fn compare(
+    depot: &mut Depot,
+    a: QueryParam<String, true>,
+    b: QueryParam<String, true>,
+) -> ServiceResult<PaperComparison> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    if a.as_str() == b.as_str() {
+        return Err(ServiceError::invalid_field(
+            "b",
+            "same_paper",
+            "Two different papers are required",
+        ));
+    }
+    let mut paper_a = fetch_paper(state, &a, user, Access::Read).await?;
+    let mut paper_b = fetch_paper(state, &b, user, Access::Read).await?;
+    expand_paper_blocks(state, &mut paper_a).await?;
+    expand_paper_blocks(state, &mut paper_b).await?;
+    Ok(compare_papers(&paper_a, &paper_b))
+}
+
+/// Search Papers
+///
+/// Full text search in the papers of the authenticated user. The text relevance
+/// is boosted for recently updated and starred papers, and for papers in the
+/// folder (or its subfolders) the search runs from. `field` filters on custom
+/// fields as in the list of papers. With `debug` the applied score of every
+/// result is returned.
+#[endpoint(
+    status_codes(200, 401, 422),
+    responses(
+        (status_code = 200, body = SearchPapersResponse, description = "Matching papers, best first"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Empty query, unknown folder or invalid filter")
+    )
+)]
+async fn search_papers(
+    depot: &mut Depot,
+    q: QueryParam<String, true>,
+    folder_id: QueryParam<String, false>,
+    field: QueryParam<String, false>,
+    limit: QueryParam<i64, false>,
+    debug: QueryParam<bool, false>,
+) -> ServiceResult<SearchPapersResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let query = q.trim();
+    if query.is_empty() {
+        return Err(ServiceError::invalid_field(
+            "q",
+            "required",
+            "Search query must not be empty",
+        ));
+    }
+    let limit = limit.into_inner().unwrap_or(SEARCH_DEFAULT_LIMIT);
+    let limit = limit.clamp(1, SEARCH_MAX_LIMIT);
+
+    let mut ctx = RankContext {
+        now: bson::DateTime::now().timestamp_millis(),
+        ..Default::default()
+    };
+    if let Some(folder_id) = folder_id.into_inner() {
+        let folders = state.cached_folders(&user.uid).await?;
+        if !folders.iter().any(|folder| folder.id == folder_id) {
+            return Err(ServiceError::invalid_field(
+                "folderId",
+                "not_found",
+                "Folder does not exist",
+            ));
+        }
+        ctx.folder_scope = folder_scope(&folders, &folder_id);
+    }
+    let filter = custom_field_filter(state, user, field.as_deref()).await?;
+
+    // rank a wider window than returned, boosts may lift lower text matches
+    let matches = state
+        .db
+        .search_papers(&user.uid, query, filter, limit * SEARCH_RANK_WINDOW)
+        .await?;
+    let debug = debug.into_inner().unwrap_or(false);
+    let results = rank(&state.search_config, &ctx, matches)
+        .into_iter()
+        .take(limit as usize)
+        .map(|(scored, score)| SearchResultResponse {
+            paper: scored.paper.into(),
+            score: debug.then_some(score),
+        })
+        .collect();
+    Ok(SearchPapersResponse(results))
+}
+
+/// Export Paper
+///
+/// Exports the paper metadata, summary and notes as a single `md`, `html` or
+/// `pdf` document (the default). Pdf documents can be stamped with a watermark
+/// on every page.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, content_type = ["application/pdf", "text/markdown", "text/html"], description = "Exported document"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn export_paper(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    format: QueryParam<ExportFormat, false>,
+    watermark: QueryParam<String, false>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let mut paper = fetch_paper(state, &paper_id, user, Access::Read).await?;
+    expand_paper_blocks(state, &mut paper).await?;
+    let format = format.into_inner().unwrap_or_default();
+    let options = ExportOptions::default().with_watermark(watermark.into_inner());
+    write_export(&paper, format, &options, resp)
+}
+
+/// Render the paper in the format into the response as an attachment.
+pub(super) fn write_export(
+    paper: &Paper,
+    format: ExportFormat,
+    options: &ExportOptions,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let bytes = export_paper_as(paper, format, options)?;
+
+    resp.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(format.content_type()),
+    );
+    if let Ok(disposition) = HeaderValue::from_str(&format!(
+        "attachment; filename=\"{}.{}\"",
+        paper.id,
+        format.extension()
+    )) {
+        resp.headers_mut().insert(CONTENT_DISPOSITION, disposition);
+    }
+    resp.body(bytes);
+    Ok(())
+}
+
+/// Upload Paper File
+///
+/// Uploads the pdf of the paper as the raw request body, replacing any previous
+/// file. The text of the pages is extracted in the background, scanned files are
+/// recognized by OCR unless `ocr=false`. The thumbnails of the first page are
+/// rendered in the background too, when enabled. When a content scanner is
+/// configured the file is scanned first: a refused file is quarantined, the
+/// `textStatus` becomes `quarantined` with the reason in `fileError`.
+#[endpoint(
+    status_codes(200, 400, 401, 403, 404, 413),
+    responses(
+        (status_code = 200, body = PaperResponse, description = "File uploaded, text extraction pending"),
+        (status_code = 400, description = "Bad Request: Not a pdf"),
+        (status_code = 403, description = "Forbidden: Storage quota exceeded"),
+        (status_code = 413, description = "Payload Too Large: File over the upload limit"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn upload_paper_file(
+    req: &mut Request,
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    ocr: QueryParam<bool, false>,
+) -> ServiceResult<PaperResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Write).await?;
+    // bounded by `body_limit_config.upload_bytes`
+    let bytes = req
+        .payload()
+        .await
+        .map_err(|e| ServiceError::BadRequest(format!("Invalid upload: {}", e)))?
+        .to_vec();
+    let ocr = ocr.into_inner().unwrap_or(true);
+    let paper = attach_paper_file(state, user, paper, bytes, ocr).await?;
+    Ok(paper.into())
+}
+
+/// Store the pdf as the file of the paper, replacing any previous one, then
+/// scan it, extract its text and render its thumbnails in the background.
+pub(super) async fn attach_paper_file(
+    state: &AppDataRef,
+    user: &User,
+    mut paper: Paper,
+    bytes: Vec<u8>,
+    ocr: bool,
+) -> ServiceResult<Paper> {
+    if !bytes.starts_with(b"%PDF-") {
+        return Err(ServiceError::BadRequest(
+            "Uploaded file is not a pdf".to_string(),
+        ));
+    }
+    state
+        .ensure_storage_quota(
+            user,
+            bytes.len() as u64,
+            paper.file_size.unwrap_or_default(),
+        )
+        .await?;
+
+    let hash = store_file(state.db.as_ref(), &bytes).await?;
+    let replaced = paper.file_hash.replace(hash.clone());
+    paper.file_size = Some(bytes.len() as u64);
+    paper.file_error = None;
+    paper.text_status = Some(TextStatus::Pending);
+    paper.page_count = None;
+    paper.ocr_progress = None;
+    paper.updated_at = bson::DateTime::now();
+    let paper = match state.db.update_paper(paper).await {
+        Ok(paper) => paper,
+        Err(e) => {
+            // the paper keeps its file, drop the reference to the new one
+            release_file(state.db.as_ref(), &hash).await?;
+            return Err(e);
+        }
+    };
+    if let Some(hash) = replaced {
+        release_file(state.db.as_ref(), &hash).await?;
+    }
+    state.invalidate(&[CacheKey::Paper(&paper.id)]).await;
+    state.events.publish(
+        &user.uid,
+        DomainEvent::PaperUpdated {
+            paper_id: paper.id.clone(),
+        },
+    );
+
+    state.jobs.spawn(run_scan_job(
+        state.clone(),
+        user.uid.clone(),
+        paper.id.clone(),
+        bytes,
+        ocr,
+    ));
+    Ok(paper)
+}
+
+/// Get Paper File Url
+///
+/// Gets a signed url downloading the uploaded file of the paper without
+/// authentication, for the browser and external tools to fetch it directly and
+/// CDNs to cache it. The url expires in 15 to 20 minutes.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = PaperFileUrlResponse, description = "Signed url of the file"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist or has no file")
+    )
+)]
+async fn get_paper_file_url(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+) -> ServiceResult<PaperFileUrlResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Read).await?;
+    let file_hash = paper
+        .file_hash
+        .as_deref()
+        .ok_or_else(|| ServiceError::NotFound(format!("Paper {} has no file", paper.id)))?;
+    let expires = file_url_expiry(chrono::Utc::now().timestamp());
+    let mut url = format!(
+        "{}/api/files/{}?expires={}&signature={}",
+        state.public_url,
+        file_hash,
+        expires,
+        sign_file(&state.tenant.id, file_hash, expires)
+    );
+    if state.tenant.id != DEFAULT_TENANT {
+        url.push_str(&format!("&tenant={}", state.tenant.id));
+    }
+    Ok(PaperFileUrlResponse {
+        url,
+        expires_at: expires * 1000,
+    })
+}
+
+/// Get Paper Thumbnail
+///
+/// Gets the png of the first page of the file of the paper, `medium` by default,
+/// for the library grid. Cached by the browser for an hour, then revalidated with
+/// its `ETag`. Answers 404 until the thumbnail is rendered after upload.
+#[endpoint(
+    status_codes(200, 304, 401, 404),
+    responses(
+        (status_code = 200, description = "Thumbnail png"),
+        (status_code = 304, description = "Not Modified: The file did not change"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist or has no thumbnail")
+    )
+)]
+async fn get_paper_thumbnail(
+    req: &mut Request,
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    size: QueryParam<ThumbnailSize, false>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = state.cached_paper(&paper_id).await?;
+    let paper = check_paper_access(paper, &paper_id, Principal::of(state, user), Access::Read)?;
+    let not_found = || ServiceError::NotFound(format!("Paper {} has no thumbnail", paper.id));
+    let file_hash = paper.file_hash.as_deref().ok_or_else(not_found)?;
+    let size = size.into_inner().unwrap_or_default();
+
+    // the thumbnail changes with the file only
+    let etag = format!("\"{}-{}\"", file_hash, size.as_str());
+    if !not_modified(req, resp, &etag) {
+        let png = state
+            .db
+            .get_blob(&paper_thumbnail_key(&paper.id, size))
+            .await?
+            .ok_or_else(not_found)?;
+        resp.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("image/png"));
+        resp.body(png);
+    }
+    // not on the 404, the thumbnail of a new upload comes shortly after
+    resp.headers_mut().insert(
+        CACHE_CONTROL,
+        HeaderValue::from_static("private, max-age=3600"),
+    );
+    Ok(())
+}
+
+/// Get Paper Text
+///
+/// Gets the text extracted from the uploaded file, page by page,
+/// or only the given 1 based `page`.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = PaperTextResponse, description = "Extracted text of the paper"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn get_paper_text(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    page: QueryParam<u32, false>,
+) -> ServiceResult<PaperTextResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Read).await?;
+    let pages = state
+        .db
+        .get_paper_pages(&paper.id, page.into_inner())
+        .await?;
+    Ok(PaperTextResponse {
+        paper_id: paper.id,
+        status: paper.text_status,
+        page_count: paper.page_count,
+        ocr_progress: paper.ocr_progress,
+        pages: pages.into_iter().map(Into::into).collect(),
+    })
+}
+
+/// Get Paper Math
+///
+/// Gets the abstract and the notes of the paper with their LaTeX formulas rendered
+/// as MathML, the rest HTML escaped. Answers 304 when `If-None-Match` has the
+/// current `ETag` of the paper.
+#[endpoint(
+    status_codes(200, 304, 401, 404),
+    responses(
+        (status_code = 200, body = PaperMathResponse, description = "Rendered abstract and notes"),
+        (status_code = 304, description = "Not Modified: The paper did not change"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn get_paper_math(
+    req: &mut Request,
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = state.cached_paper(&paper_id).await?;
+    let paper = check_paper_access(paper, &paper_id, Principal::of(state, user), Access::Read)?;
+    if not_modified(req, resp, &weak_etag(paper.updated_at, paper.version)) {
+        return Ok(());
+    }
+    resp.render(PaperMathResponse {
+        abstract_html: paper
+            .r#abstract
+            .as_deref()
+            .map(|text| state.math.render(text)),
+        notes_html: paper.content.as_deref().map(|text| state.math.render(text)),
+        paper_id: paper.id,
+    });
+    Ok(())
+}
+
+/// Ask Paper
+///
+/// Answers a question from the extracted text of the paper only, with the quotes
+/// of the paper supporting the answer and the page of each. The passages of the
+/// text are embedded on the first question and reused by the next ones. `model`
+/// selects a configured model instead of the default one.
+#[endpoint(
+    status_codes(200, 401, 404, 409, 422, 429),
+    responses(
+        (status_code = 200, body = AskPaperResponse, description = "Answer of the question"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist or has no extracted text"),
+        (status_code = 409, description = "Conflict: The text of the paper is still being prepared by another request"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error or model not available"),
+        (status_code = 429, body = ErrorResponse, description = "Too Many Requests: Rate limited or monthly llm quota exceeded")
+    )
+)]
+async fn ask_paper(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    request: JsonBody<AskPaperRequest>,
+) -> ServiceResult<AskPaperResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let model = state.user_model(user, request.model);
+    if !state.llm.supports(&model) {
+        return Err(ServiceError::invalid_field(
+            "model",
+            "unsupported",
+            format!("Model {} is not available", model),
+        ));
+    }
+    let paper = fetch_paper(state, &paper_id, user, Access::Read).await?;
+    state.ensure_quota(&user.uid, &model).await?;
+    qa::ask_paper(state, &paper, &request.question, &model).await
+}
+
+/// Get Related Papers
+///
+/// Gets the papers of the library most similar to a paper of the authenticated
+/// user, by embedding, and papers recommended by Semantic Scholar when enabled
+/// by the configuration, unless `external=false`. `limit` (10 by default)
+/// bounds each list. The external suggestions are best effort, left empty when
+/// Semantic Scholar is unavailable.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = RelatedPapersResponse, description = "Related papers"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn get_related_papers(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    limit: QueryParam<usize, false>,
+    external: QueryParam<bool, false>,
+) -> ServiceResult<RelatedPapersResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Read).await?;
+    let limit = limit
+        .into_inner()
+        .unwrap_or(RELATED_DEFAULT_LIMIT)
+        .clamp(1, RELATED_MAX_LIMIT);
+
+    let mut library = Vec::new();
+    if let Some(embedding) = state.db.get_paper_embedding(&paper.id).await? {
+        let mut similar = state
+            .db
+            .get_user_embeddings(&user.uid)
+            .await?
+            .into_iter()
+            .filter(|e| e.paper_id != paper.id)
+            .map(|e| (cosine_similarity(&embedding.vector, &e.vector), e.paper_id))
+            .collect::<Vec<_>>();
+        similar.sort_by(|a, b| b.0.total_cmp(&a.0));
+        similar.truncate(limit);
+        let ids = similar.iter().map(|(_, id)| id.clone()).collect::<Vec<_>>();
+        let mut papers = state.db.get_papers_by_ids(&user.uid, &ids).await?;
+        for (similarity, id) in similar {
+            if let Some(index) = papers.iter().position(|p| p.id == id) {
+                library.push(RelatedPaperResponse {
+                    paper: papers.swap_remove(index).into(),
+                    similarity,
+                });
+            }
+        }
+    }
+
+    let mut suggestions = Vec::new();
+    let external = external.into_inner().unwrap_or(true);
+    if let (Some(client), true) = (&state.semantic_scholar, external) {
+        match client
+            .recommendations(paper.doi.as_deref(), &paper.title, limit)
+            .await
+        {
+            Ok(papers) => suggestions = papers.into_iter().map(Into::into).collect(),
+            Err(e) => tracing::warn!("Recommendations for paper {} failed: {}", paper.id, e),
+        }
+    }
+
+    Ok(RelatedPapersResponse {
+        library,
+        external: suggestions,
+    })
+}
+
+/// Get Paper Citations
+///
+/// Gets the references of the paper and the papers of the library citing it.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = PaperCitationsResponse, description = "Citations of the paper"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn get_paper_citations(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+) -> ServiceResult<PaperCitationsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Read).await?;
+    let references = state.db.get_citations_by_citing_id(&paper.id).await?;
+    let cited_by = state.db.get_citations_by_cited_id(&paper.id).await?;
+    Ok(PaperCitationsResponse {
+        references: references.into_iter().map(Into::into).collect(),
+        cited_by: cited_by.into_iter().map(Into::into).collect(),
+    })
+}
+
+/// Refresh Paper Citations
+///
+/// Extracts the references of the paper again, from the text of its file or from
+/// Crossref by DOI, and links them to the current papers of the library.
+#[endpoint(
+    status_codes(200, 401, 404, 502),
+    responses(
+        (status_code = 200, body = PaperCitationsResponse, description = "Citations of the paper"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist"),
+        (status_code = 502, description = "Bad Gateway: Crossref is unavailable")
+    )
+)]
+async fn refresh_paper_citations(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+) -> ServiceResult<PaperCitationsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Write).await?;
+    let pages = state
+        .db
+        .get_paper_pages(&paper.id, None)
+        .await?
+        .into_iter()
+        .map(|page| page.text)
+        .collect::<Vec<_>>();
+    refresh_citations(state, &paper, &pages).await?;
+
+    let references = state.db.get_citations_by_citing_id(&paper.id).await?;
+    let cited_by = state.db.get_citations_by_cited_id(&paper.id).await?;
+    Ok(PaperCitationsResponse {
+        references: references.into_iter().map(Into::into).collect(),
+        cited_by: cited_by.into_iter().map(Into::into).collect(),
+    })
+}
+
+/// List Share Links
+///
+/// Lists the anonymous reviewer links of a paper, including revoked ones.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = ListShareLinksResponse, description = "Share links of the paper"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn list_share_links(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+) -> ServiceResult<ListShareLinksResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Write).await?;
+    let links = state.db.get_share_links_by_paper_id(&paper.id).await?;
+    Ok(ListShareLinksResponse(
+        links.into_iter().map(Into::into).collect(),
+    ))
+}
+
+/// Create Share Link
+///
+/// Creates an anonymous reviewer link for the paper. Whoever holds the link can
+/// read the paper and comment on it under a pseudonymous handle.
+#[endpoint(
+    status_codes(201, 401, 404, 422),
+    responses(
+        (status_code = 201, body = ShareLinkResponse, description = "Share link created successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn create_share_link(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    request: JsonBody<CreateShareLinkRequest>,
+    resp: &mut Response,
+) -> ServiceResult<ShareLinkResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let paper = fetch_paper(state, &paper_id, user, Access::Write).await?;
+    let count = state.db.count_share_links_by_paper_id(&paper.id).await?;
+    let link = ShareLink::new(&paper.id, &user.uid, reviewer_handle(count), request);
+    state.db.create_share_link(link.clone()).await?;
+    resp.status_code(salvo::http::StatusCode::CREATED);
+    Ok(link.into())
+}
+
+/// Revoke Share Link
+///
+/// Revokes a reviewer link, e.g. in case of abuse. Comments already made stay
+/// on the paper until deleted.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Share link revoked successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper or share link does not exist")
+    )
+)]
+async fn revoke_share_link(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    link_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Write).await?;
+    let link = state
+        .db
+        .get_share_link_by_id(&link_id)
+        .await?
+        .filter(|link| link.paper_id == paper.id)
+        .ok_or_else(|| ServiceError::NotFound(format!("Share link {}", link_id.as_str())))?;
+    state.db.revoke_share_link(&link.id).await?;
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+/// List Comments
+///
+/// Lists the comments made on the paper by anonymous reviewers.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = ListCommentsResponse, description = "Comments of the paper"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn list_comments(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+) -> ServiceResult<ListCommentsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Read).await?;
+    let comments = state.db.get_comments_by_paper_id(&paper.id).await?;
+    Ok(ListCommentsResponse(
+        comments.into_iter().map(Into::into).collect(),
+    ))
+}
+
+/// Delete Comment
+///
+/// Deletes an abusive or obsolete comment from the paper.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Comment deleted successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn delete_comment(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    comment_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Write).await?;
+    state.db.delete_comment(&paper.id, &comment_id).await?;
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+/// Fetch a revision of the paper, not found if it was pruned.
+async fn fetch_revision(
+    state: &AppDataRef,
+    paper_id: &str,
+    revision: u32,
+) -> ServiceResult<PaperRevision> {
+    state
+        .db
+        .get_paper_revision(paper_id, revision)
+        .await?
+        .ok_or_else(|| {
+            ServiceError::NotFound(format!("Revision {} of paper {}", revision, paper_id))
+        })
+}
+
+/// List Paper Revisions
+///
+/// Lists the revisions kept of the metadata and notes of a paper, the latest
+/// first. A revision is the paper as it was before an update replaced it.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = ListPaperRevisionsResponse, description = "Revisions of the paper"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn list_paper_revisions(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+) -> ServiceResult<ListPaperRevisionsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Read).await?;
+    let revisions = state.db.get_paper_revisions(&paper.id).await?;
+    Ok(ListPaperRevisionsResponse(
+        revisions.into_iter().map(Into::into).collect(),
+    ))
+}
+
+/// Get Paper Revision
+///
+/// Gets the metadata and notes of a paper as they were at a revision.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = PaperRevisionResponse, description = "Revision of the paper"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper or revision does not exist")
+    )
+)]
+async fn get_paper_revision(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    revision: PathParam<u32>,
+) -> ServiceResult<PaperRevisionResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Read).await?;
+    let revision = fetch_revision(state, &paper.id, revision.into_inner()).await?;
+    Ok(revision.into())
+}
+
+/// Diff Paper Revisions
+///
+/// Compares two revisions of a paper line by line, `from` with `to` or with the
+/// current paper when `to` is absent. Only the changed fields are returned.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = PaperRevisionDiffResponse, description = "Changes between the revisions"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper or revision does not exist")
+    )
+)]
+async fn diff_paper_revisions(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    from: QueryParam<u32, true>,
+    to: QueryParam<u32, false>,
+) -> ServiceResult<PaperRevisionDiffResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Read).await?;
+    let (from, to) = (from.into_inner(), to.into_inner());
+    let old = fetch_revision(state, &paper.id, from).await?;
+    let new = match to {
+        Some(to) => fetch_revision(state, &paper.id, to).await?,
+        None => PaperRevision::of(&paper),
+    };
+    let fields = old
+        .fields()
+        .into_iter()
+        .zip(new.fields())
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((field, old), (_, new))| FieldDiff {
+            field: field.to_string(),
+            lines: line_diff(&old, &new),
+        })
+        .collect();
+    Ok(PaperRevisionDiffResponse {
+        paper_id: paper.id,
+        from,
+        to,
+        fields,
+    })
+}
+
+/// Restore Paper Revision
+///
+/// Gives a paper of the authenticated user back the metadata and notes of a
+/// revision. The replaced ones are kept as a new revision, so a restore can be
+/// undone like any update.
+#[endpoint(
+    status_codes(200, 401, 404, 409),
+    responses(
+        (status_code = 200, body = PaperResponse, description = "Revision restored"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper or revision does not exist"),
+        (status_code = 409, description = "Conflict: The paper was modified concurrently")
+    )
+)]
+async fn restore_paper_revision(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    revision: PathParam<u32>,
+    resp: &mut Response,
+) -> ServiceResult<PaperResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let mut paper = fetch_paper(state, &paper_id, user, Access::Write).await?;
+    let revision = fetch_revision(state, &paper.id, revision.into_inner()).await?;
+    let previous = paper.clone();
+    revision.restore_into(&mut paper);
+    paper.updated_at = bson::DateTime::now();
+
+    record_revision(state, &previous, &paper).await?;
+    let updated_paper = state.db.update_paper(paper).await?;
+    reset_notes(state, &previous, &updated_paper).await?;
+    state
+        .invalidate(&[CacheKey::Paper(&updated_paper.id)])
+        .await;
+    state.events.publish(
+        &user.uid,
+        DomainEvent::PaperUpdated {
+            paper_id: updated_paper.id.clone(),
+        },
+    );
+    record_activity(
+        state,
+        &user.uid,
+        ActivityKind::Paper,
+        &updated_paper.id,
+        ActivityAction::Edited,
+    );
+    set_etag(
+        resp,
+        &weak_etag(updated_paper.updated_at, updated_paper.version),
+    );
+    Ok(updated_paper.into())
+}
+
+/// Get Paper Notes
+///
+/// Gets the notes of a paper merged from the edits of every participant, as
+/// markdown, with the CRDT state for clients editing without the WebSocket.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = NotesResponse, description = "Merged notes of the paper"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist")
+    )
+)]
+async fn get_notes(depot: &mut Depot, paper_id: PathParam<String>) -> ServiceResult<NotesResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let paper = fetch_paper(state, &paper_id, user, Access::Read).await?;
+    edit_notes(state, &paper, None).await
+}
+
+/// Update Paper Notes
+///
+/// Merges an update of the notes made without the WebSocket, e.g. offline, and
+/// returns the merged notes. The update is a yrs v1 update, base64 encoded.
+#[endpoint(
+    status_codes(200, 401, 404, 422),
+    request_body(content = UpdateNotesRequest, description = "Update of the notes"),
+    responses(
+        (status_code = 200, body = NotesResponse, description = "Merged notes of the paper"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Invalid update")
+    )
+)]
+async fn update_notes(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    request: JsonBody<UpdateNotesRequest>,
+) -> ServiceResult<NotesResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let paper = fetch_paper(state, &paper_id, user, Access::Write).await?;
+    edit_notes(state, &paper, Some(&request.update)).await
+}
+
+/// Upgrade to a WebSocket editing the notes of the paper together with the
+/// other participants.
+#[handler]
+async fn connect_notes(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?.clone();
+    let user = depot.obtain::<User>()?;
+
+    let paper_id = req.param::<String>("paper_id").unwrap_or_default();
+    let paper = fetch_paper(&state, &paper_id, user, Access::Read).await?;
+    let can_edit = can(
+        Access::Write,
+        Resource::Notes(&paper),
+        Principal::of(&state, user),
+    );
+    let session = super::ws::SocketSession::of(depot)?;
+    super::ws::upgrade_notes(req, res, state, Some(session), paper, can_edit).await
+}
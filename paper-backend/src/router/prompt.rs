@@ -0,0 +1,206 @@
+use salvo::{
+    Depot, Response, Router,
+    oapi::{
+        endpoint,
+        extract::{JsonBody, PathParam, QueryParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    model::{
+        prompt::{
+            PromptTemplate, PromptTemplateRepository,
+            schema::{
+                CreatePromptTemplateRequest, ListPromptTemplatesResponse, PromptTemplateResponse,
+                UpdatePromptTemplateRequest,
+            },
+        },
+        user::User,
+    },
+    utils::{template::template_variables, validate::ValidatedRequest},
+};
+
+// mounted under the operator only `/admin` routes
+pub fn create_router() -> Router {
+    Router::new()
+        .push(
+            Router::new()
+                .get(list_prompt_templates)
+                .post(create_prompt_template),
+        )
+        .push(
+            Router::with_path("{prompt_id}")
+                .get(get_prompt_template)
+                .put(update_prompt_template)
+                .delete(delete_prompt_template),
+        )
+}
+
+/// Every placeholder of the body has to be a declared variable.
+fn check_variables(body: &str, variables: &[String]) -> ServiceResult<()> {
+    match template_variables(body)
+        .into_iter()
+        .find(|name| !variables.contains(name))
+    {
+        Some(name) => Err(ServiceError::invalid_field(
+            "body",
+            "undeclared_variable",
+            format!(
+                "The placeholder {{{{{}}}}} is not a declared variable",
+                name
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+async fn get_existing_template(
+    state: &AppDataRef,
+    prompt_id: &str,
+) -> ServiceResult<PromptTemplate> {
+    state
+        .db
+        .get_prompt_template(prompt_id)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound(format!("Prompt template {}", prompt_id)))
+}
+
+/// List Prompt Templates
+///
+/// Lists the stored prompt templates, all versions of `name` when given, by name
+/// then newest version first. Operators only.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListPromptTemplatesResponse, description = "Prompt templates"),
+        (status_code = 401, description = "Unauthorized: User not an operator")
+    )
+)]
+async fn list_prompt_templates(
+    depot: &mut Depot,
+    name: QueryParam<String, false>,
+) -> ServiceResult<ListPromptTemplatesResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let name = name.into_inner();
+    let templates = state.db.get_prompt_templates(name.as_deref()).await?;
+    Ok(ListPromptTemplatesResponse(
+        templates.into_iter().map(Into::into).collect(),
+    ))
+}
+
+/// Create Prompt Template
+///
+/// Stores a new version of a prompt template, the next one after the latest of
+/// the name. The ai features use the latest version from their next call on.
+/// Operators only.
+#[endpoint(
+    status_codes(201, 401, 422),
+    responses(
+        (status_code = 201, body = PromptTemplateResponse, description = "Prompt template created"),
+        (status_code = 401, description = "Unauthorized: User not an operator"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error or undeclared variable")
+    )
+)]
+async fn create_prompt_template(
+    depot: &mut Depot,
+    request: JsonBody<CreatePromptTemplateRequest>,
+    resp: &mut Response,
+) -> ServiceResult<PromptTemplateResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    check_variables(&request.body, &request.variables)?;
+    let latest = state.db.find_prompt_template(&request.name, None).await?;
+    let version = latest.map_or(1, |template| template.version + 1);
+
+    let template = PromptTemplate::new(&user.uid, version, request);
+    state.db.create_prompt_template(template.clone()).await?;
+    resp.status_code(salvo::http::StatusCode::CREATED);
+    Ok(template.into())
+}
+
+/// Get Prompt Template
+///
+/// Gets a version of a prompt template. Operators only.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = PromptTemplateResponse, description = "Prompt template"),
+        (status_code = 401, description = "Unauthorized: User not an operator"),
+        (status_code = 404, description = "Not Found: Prompt template does not exist")
+    )
+)]
+async fn get_prompt_template(
+    depot: &mut Depot,
+    prompt_id: PathParam<String>,
+) -> ServiceResult<PromptTemplateResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let template = get_existing_template(state, &prompt_id).await?;
+    Ok(template.into())
+}
+
+/// Update Prompt Template
+///
+/// Replaces the body, variables and description of a version of a prompt
+/// template in place, the ai features pick it up from their next call on.
+/// Operators only.
+#[endpoint(
+    status_codes(200, 401, 404, 422),
+    responses(
+        (status_code = 200, body = PromptTemplateResponse, description = "Prompt template updated"),
+        (status_code = 401, description = "Unauthorized: User not an operator"),
+        (status_code = 404, description = "Not Found: Prompt template does not exist"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error or undeclared variable")
+    )
+)]
+async fn update_prompt_template(
+    depot: &mut Depot,
+    prompt_id: PathParam<String>,
+    request: JsonBody<UpdatePromptTemplateRequest>,
+) -> ServiceResult<PromptTemplateResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let request = request.into_inner().validated()?;
+    check_variables(&request.body, &request.variables)?;
+    let mut template = get_existing_template(state, &prompt_id).await?;
+    template.description = request.description;
+    template.variables = request.variables;
+    template.body = request.body;
+    template.updated_at = bson::DateTime::now();
+    state.db.update_prompt_template(template.clone()).await?;
+    Ok(template.into())
+}
+
+/// Delete Prompt Template
+///
+/// Deletes a version of a prompt template, the ai features fall back to the
+/// previous version, or to the built-in prompt. Operators only.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Prompt template deleted"),
+        (status_code = 401, description = "Unauthorized: User not an operator"),
+        (status_code = 404, description = "Not Found: Prompt template does not exist")
+    )
+)]
+async fn delete_prompt_template(
+    depot: &mut Depot,
+    prompt_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    if !state.db.delete_prompt_template(&prompt_id).await? {
+        return Err(ServiceError::NotFound(format!(
+            "Prompt template {}",
+            prompt_id
+        )));
+    }
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
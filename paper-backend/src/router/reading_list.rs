@@ -0,0 +1,274 @@
+use std::collections::HashSet;
+
+use salvo::{
+    Depot, Response, Router, Writer,
+    oapi::{
+        RouterExt, endpoint,
+        extract::{JsonBody, PathParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    authz::{Access, Principal, Resource, can},
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    model::{
+        paper::PaperRepository,
+        reading_list::{
+            ReadingListItem, ReadingListRepository, ReadingStatus,
+            schema::{
+                AddReadingListItemRequest, ReadingListItemResponse, ReadingListResponse,
+                ReadingStatsResponse, ReorderReadingListRequest, UpdateReadingListItemRequest,
+            },
+        },
+        user::User,
+    },
+    utils::validate::ValidatedRequest,
+};
+
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+pub fn create_router() -> Router {
+    Router::new()
+        .push(
+            Router::new()
+                .get(get_reading_list)
+                .post(add_to_reading_list),
+        )
+        .push(Router::with_path("order").put(reorder_reading_list))
+        .push(
+            Router::with_path("{paper_id}")
+                .put(update_reading_list_item)
+                .delete(remove_from_reading_list),
+        )
+        .oapi_tag("reading-list")
+}
+
+/// Count the papers per status and those finished in the last week and month.
+fn reading_stats(items: &[ReadingListItem], now: i64) -> ReadingStatsResponse {
+    let mut stats = ReadingStatsResponse::default();
+    for item in items {
+        match item.status {
+            ReadingStatus::ToRead => stats.to_read += 1,
+            ReadingStatus::Reading => stats.reading += 1,
+            ReadingStatus::Done => stats.done += 1,
+        }
+        let Some(finished_at) = item.finished_at else {
+            continue;
+        };
+        let age = now - finished_at.timestamp_millis();
+        if age <= 7 * DAY_MILLIS {
+            stats.read_this_week += 1;
+        }
+        if age <= 30 * DAY_MILLIS {
+            stats.read_this_month += 1;
+        }
+    }
+    stats
+}
+
+async fn get_queued_item(
+    state: &AppDataRef,
+    user: &User,
+    paper_id: &str,
+) -> ServiceResult<ReadingListItem> {
+    state
+        .db
+        .get_reading_list_item(&user.uid, paper_id)
+        .await?
+        .ok_or_else(|| {
+            ServiceError::NotFound(format!("Paper {} is not in the reading list", paper_id))
+        })
+}
+
+/// Get Reading List
+///
+/// Gets the reading queue of the authenticated user in order, with the reading
+/// progress statistics.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ReadingListResponse, description = "Reading queue and statistics"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn get_reading_list(depot: &mut Depot) -> ServiceResult<ReadingListResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let items = state.db.get_reading_list(&user.uid).await?;
+    let stats = reading_stats(&items, bson::DateTime::now().timestamp_millis());
+    Ok(ReadingListResponse {
+        items: items.into_iter().map(Into::into).collect(),
+        stats,
+    })
+}
+
+/// Add To Reading List
+///
+/// Queues a paper of the authenticated user at the end of the reading list.
+#[endpoint(
+    status_codes(201, 401, 404, 409, 422),
+    responses(
+        (status_code = 201, body = ReadingListItemResponse, description = "Paper queued"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper does not exist"),
+        (status_code = 409, description = "Conflict: Paper already queued"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn add_to_reading_list(
+    depot: &mut Depot,
+    request: JsonBody<AddReadingListItemRequest>,
+    resp: &mut Response,
+) -> ServiceResult<ReadingListItemResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    // only the papers of the user make it to their list
+    let principal = Principal::of(state, user);
+    let paper = state
+        .db
+        .get_paper_by_id(&request.paper_id)
+        .await?
+        .filter(|paper| can(Access::Write, Resource::Paper(paper), principal))
+        .ok_or_else(|| ServiceError::PaperNotFound(request.paper_id.clone()))?;
+
+    let items = state.db.get_reading_list(&user.uid).await?;
+    if items.iter().any(|item| item.paper_id == paper.id) {
+        return Err(ServiceError::NameConflict(
+            "Paper is already in the reading list".to_string(),
+        ));
+    }
+    let position = items.last().map_or(0, |item| item.position + 1);
+    let mut item = ReadingListItem::new(&user.uid, &paper.id, paper.title, position);
+    item.priority = request.priority;
+    state.db.create_reading_list_item(item.clone()).await?;
+    resp.status_code(salvo::http::StatusCode::CREATED);
+    Ok(item.into())
+}
+
+/// Update Reading List Item
+///
+/// Changes the priority or the reading status of a queued paper. Moving to
+/// `reading` and `done` records when reading started and finished.
+#[endpoint(
+    status_codes(200, 401, 404, 422),
+    request_body(content = UpdateReadingListItemRequest, description = "Update queued paper"),
+    responses(
+        (status_code = 200, body = ReadingListItemResponse, description = "Queued paper updated"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper is not queued"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn update_reading_list_item(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    request: JsonBody<UpdateReadingListItemRequest>,
+) -> ServiceResult<ReadingListItemResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let mut item = get_queued_item(state, user, &paper_id).await?;
+    if let Some(priority) = request.priority {
+        item.priority = priority;
+    }
+    if let Some(status) = request.status {
+        item.set_status(status);
+    }
+    item.updated_at = bson::DateTime::now();
+
+    state.db.update_reading_list_item(item.clone()).await?;
+    Ok(item.into())
+}
+
+/// Reorder Reading List
+///
+/// Moves the given papers to the front of the queue in the given order, the
+/// other papers keep their relative order after them.
+#[endpoint(
+    status_codes(200, 401, 422),
+    responses(
+        (status_code = 200, body = ReadingListResponse, description = "Reordered reading queue"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Paper not queued or given twice")
+    )
+)]
+async fn reorder_reading_list(
+    depot: &mut Depot,
+    request: JsonBody<ReorderReadingListRequest>,
+) -> ServiceResult<ReadingListResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let items = state.db.get_reading_list(&user.uid).await?;
+
+    let mut seen = HashSet::new();
+    for paper_id in &request.paper_ids {
+        if !seen.insert(paper_id.as_str()) {
+            return Err(ServiceError::invalid_field(
+                "paperIds",
+                "duplicate",
+                format!("Paper {} is given twice", paper_id),
+            ));
+        }
+        if !items.iter().any(|item| &item.paper_id == paper_id) {
+            return Err(ServiceError::invalid_field(
+                "paperIds",
+                "not_queued",
+                format!("Paper {} is not in the reading list", paper_id),
+            ));
+        }
+    }
+    let order = request
+        .paper_ids
+        .iter()
+        .cloned()
+        .chain(
+            items
+                .iter()
+                .filter(|item| !seen.contains(item.paper_id.as_str()))
+                .map(|item| item.paper_id.clone()),
+        )
+        .collect::<Vec<_>>();
+    state.db.reorder_reading_list(&user.uid, &order).await?;
+
+    let items = state.db.get_reading_list(&user.uid).await?;
+    let stats = reading_stats(&items, bson::DateTime::now().timestamp_millis());
+    Ok(ReadingListResponse {
+        items: items.into_iter().map(Into::into).collect(),
+        stats,
+    })
+}
+
+/// Remove From Reading List
+///
+/// Removes a paper from the reading queue, the paper itself is kept.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Paper removed from the reading list"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Paper is not queued")
+    )
+)]
+async fn remove_from_reading_list(
+    depot: &mut Depot,
+    paper_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let item = get_queued_item(state, user, &paper_id).await?;
+    state
+        .db
+        .delete_reading_list_item(&user.uid, &item.paper_id)
+        .await?;
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
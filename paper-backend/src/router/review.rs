@@ -0,0 +1,197 @@
+use salvo::{
+    Depot, Request, Response, Router, handler,
+    oapi::{
+        RouterExt, endpoint,
+        extract::{JsonBody, PathParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    authz::{Access, Principal, Resource, assert_can_read, assert_can_write, can},
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    export::{ExportFormat, ExportOptions},
+    model::{
+        note::schema::{NotesResponse, UpdateNotesRequest},
+        paper::{Paper, PaperRepository},
+        share::{
+            Comment, ShareLink, ShareRepository,
+            schema::{CommentResponse, CreateCommentRequest, ReviewResponse},
+        },
+    },
+    router::{
+        paper::{edit_notes, expand_paper_blocks, write_export},
+        ws::upgrade_notes,
+    },
+    utils::validate::ValidatedRequest,
+};
+
+/// Routes for anonymous reviewers, authenticated by the share link token only.
+pub fn create_non_auth_router() -> Router {
+    Router::with_path("{token}")
+        .get(get_review)
+        .push(Router::with_path("comment").post(create_comment))
+        .push(Router::with_path("export").get(export_review_pdf))
+        .push(
+            Router::with_path("notes")
+                .get(get_review_notes)
+                .post(update_review_notes)
+                .push(Router::with_path("ws").get(connect_review_notes)),
+        )
+        .oapi_tag("review")
+}
+
+/// Resolve an active share link and the paper it grants access to.
+async fn get_shared_paper(state: &AppDataRef, token: &str) -> ServiceResult<(ShareLink, Paper)> {
+    // revoked and expired links look the same as unknown ones
+    let link = state
+        .db
+        .get_share_link_by_token(token)
+        .await?
+        .filter(|link| link.is_active())
+        .ok_or_else(|| ServiceError::NotFound("Share link".to_string()))?;
+    let paper = state
+        .db
+        .get_paper_by_id(&link.paper_id)
+        .await?
+        .ok_or_else(|| ServiceError::PaperNotFound(link.paper_id.clone()))?;
+    assert_can_read(Resource::Paper(&paper), Principal::ShareLink(&link))?;
+    Ok((link, paper))
+}
+
+/// Get Review
+///
+/// Gets the shared paper together with the comments of all reviewers.
+#[endpoint(
+    status_codes(200, 404),
+    responses(
+        (status_code = 200, body = ReviewResponse, description = "Shared paper and comments"),
+        (status_code = 404, description = "Not Found: Share link does not exist or was revoked")
+    )
+)]
+async fn get_review(depot: &mut Depot, token: PathParam<String>) -> ServiceResult<ReviewResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let (link, mut paper) = get_shared_paper(state, &token).await?;
+    expand_paper_blocks(state, &mut paper).await?;
+    let comments = state.db.get_comments_by_paper_id(&paper.id).await?;
+    Ok(link.review(paper, comments))
+}
+
+/// Create Review Comment
+///
+/// Adds a comment to the shared paper under the pseudonym of the link.
+#[endpoint(
+    status_codes(201, 404, 422),
+    responses(
+        (status_code = 201, body = CommentResponse, description = "Comment created successfully"),
+        (status_code = 404, description = "Not Found: Share link does not exist or was revoked"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn create_comment(
+    depot: &mut Depot,
+    token: PathParam<String>,
+    request: JsonBody<CreateCommentRequest>,
+    resp: &mut Response,
+) -> ServiceResult<CommentResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let request = request.into_inner().validated()?;
+    let (link, _) = get_shared_paper(state, &token).await?;
+    let comment = Comment::new_from_link(&link, request);
+    state.db.create_comment(comment.clone()).await?;
+    resp.status_code(salvo::http::StatusCode::CREATED);
+    Ok(comment.into())
+}
+
+/// Export Review
+///
+/// Exports the shared paper as pdf, stamped with the watermark of the link.
+#[endpoint(
+    status_codes(200, 404),
+    responses(
+        (status_code = 200, content_type = "application/pdf", description = "Exported pdf document"),
+        (status_code = 404, description = "Not Found: Share link does not exist or was revoked")
+    )
+)]
+async fn export_review_pdf(
+    depot: &mut Depot,
+    token: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let (link, mut paper) = get_shared_paper(state, &token).await?;
+    expand_paper_blocks(state, &mut paper).await?;
+    let options = ExportOptions::default().with_watermark(link.watermark);
+    write_export(&paper, ExportFormat::Pdf, &options, resp)
+}
+
+/// Get Review Notes
+///
+/// Gets the notes of the shared paper merged from the edits of every
+/// participant, as markdown, with the CRDT state.
+#[endpoint(
+    status_codes(200, 404),
+    responses(
+        (status_code = 200, body = NotesResponse, description = "Merged notes of the paper"),
+        (status_code = 404, description = "Not Found: Share link does not exist or was revoked")
+    )
+)]
+async fn get_review_notes(
+    depot: &mut Depot,
+    token: PathParam<String>,
+) -> ServiceResult<NotesResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let (_, paper) = get_shared_paper(state, &token).await?;
+    edit_notes(state, &paper, None).await
+}
+
+/// Update Review Notes
+///
+/// Merges an update of the notes of the shared paper made without the
+/// WebSocket. Only links with the `edit` permission may edit the notes.
+#[endpoint(
+    status_codes(200, 401, 404, 422),
+    request_body(content = UpdateNotesRequest, description = "Update of the notes"),
+    responses(
+        (status_code = 200, body = NotesResponse, description = "Merged notes of the paper"),
+        (status_code = 401, description = "Unauthorized: The link may not edit the notes"),
+        (status_code = 404, description = "Not Found: Share link does not exist or was revoked"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Invalid update")
+    )
+)]
+async fn update_review_notes(
+    depot: &mut Depot,
+    token: PathParam<String>,
+    request: JsonBody<UpdateNotesRequest>,
+) -> ServiceResult<NotesResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let request = request.into_inner().validated()?;
+    let (link, paper) = get_shared_paper(state, &token).await?;
+    assert_can_write(Resource::Notes(&paper), Principal::ShareLink(&link))?;
+    edit_notes(state, &paper, Some(&request.update)).await
+}
+
+/// Upgrade to a WebSocket following the editing of the notes of the shared
+/// paper, and taking part in it with the `edit` permission.
+#[handler]
+async fn connect_review_notes(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?.clone();
+
+    let token = req.param::<String>("token").unwrap_or_default();
+    let (link, paper) = get_shared_paper(&state, &token).await?;
+    let can_edit = can(
+        Access::Write,
+        Resource::Notes(&paper),
+        Principal::ShareLink(&link),
+    );
+    upgrade_notes(req, res, state, None, paper, can_edit).await
+}
@@ -0,0 +1,114 @@
+use salvo::{
+    Depot, Router,
+    oapi::{RouterExt, endpoint, extract::JsonBody},
+};
+
+use crate::{
+    app_data::AppDataRef,
+    authz::{Access, Principal, Resource, can},
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    model::{
+        folder::FolderRepository,
+        user::{
+            User, UserRepository,
+            schema::{SettingsResponse, UpdateSettingsRequest},
+        },
+    },
+    utils::{cache::CacheKey, validate::ValidatedRequest},
+};
+
+pub fn create_router() -> Router {
+    Router::new()
+        .get(get_settings)
+        .put(update_settings)
+        .oapi_tag("settings")
+}
+
+fn settings_response(state: &AppDataRef, user: &User) -> SettingsResponse {
+    let defaults = &state.settings_config;
+    SettingsResponse {
+        language: user.settings.language(defaults).to_string(),
+        default_folder_id: user.settings.default_folder_id.clone(),
+        ai_model: state.user_model(user, None),
+        summary_language: user.settings.summary_language(defaults).to_string(),
+        timezone: user.settings.timezone(defaults).name().to_string(),
+        notifications: user.into(),
+    }
+}
+
+/// Get Settings
+///
+/// Gets the settings of the authenticated user, the defaults of the server in
+/// place of the ones they did not set.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = SettingsResponse, description = "Settings of the user"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn get_settings(depot: &mut Depot) -> ServiceResult<SettingsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+    Ok(settings_response(state, user))
+}
+
+/// Update Settings
+///
+/// Updates the settings of the authenticated user. The absent fields are kept,
+/// an empty one goes back to the default of the server.
+#[endpoint(
+    status_codes(200, 401, 422),
+    responses(
+        (status_code = 200, body = SettingsResponse, description = "Settings updated"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error, unknown folder or model not available")
+    )
+)]
+async fn update_settings(
+    depot: &mut Depot,
+    request: JsonBody<UpdateSettingsRequest>,
+) -> ServiceResult<SettingsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let unsupported = request
+        .ai_model
+        .as_deref()
+        .filter(|model| !model.is_empty() && !state.llm.supports(model));
+    if let Some(model) = unsupported {
+        return Err(ServiceError::invalid_field(
+            "aiModel",
+            "unsupported",
+            format!("Model {} is not available", model),
+        ));
+    }
+    if let Some(folder_id) = request
+        .default_folder_id
+        .as_deref()
+        .filter(|folder_id| !folder_id.is_empty())
+    {
+        let principal = Principal::of(state, user);
+        let folder = state.db.get_folder_by_id(folder_id).await?;
+        if !folder.is_some_and(|folder| can(Access::Write, Resource::Folder(&folder), principal)) {
+            return Err(ServiceError::invalid_field(
+                "defaultFolderId",
+                "not_found",
+                format!("Folder {} does not exist", folder_id),
+            ));
+        }
+    }
+
+    // the cached user may miss the last digest time
+    let mut user = state
+        .db
+        .get_user_by_uid(&user.uid)
+        .await?
+        .unwrap_or_else(|| user.clone());
+    user.update_settings(request);
+    user.updated_at = bson::DateTime::now();
+    state.db.update_user(user.clone()).await?;
+    state.invalidate(&[CacheKey::User(&user.uid)]).await;
+    Ok(settings_response(state, &user))
+}
@@ -0,0 +1,88 @@
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use salvo::{
+    Depot, Router,
+    oapi::{RouterExt, endpoint},
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::ServiceResult,
+    model::{
+        stats::{
+            StatsRepository,
+            schema::{UserStatsResponse, WeeklyActivity},
+        },
+        usage::{UsageRepository, month_start},
+        user::User,
+    },
+};
+
+// weeks of the activity time series, the current one included
+const ACTIVITY_WEEKS: i64 = 12;
+
+pub fn create_router() -> Router {
+    Router::new().get(get_stats).oapi_tag("stats")
+}
+
+/// Start of the week (sunday, as `$dateTrunc` uses by default) containing the time.
+fn week_start(time: DateTime<Utc>) -> DateTime<Utc> {
+    let day = time.date_naive() - Duration::days(time.weekday().num_days_from_sunday() as i64);
+    Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0).unwrap_or_default())
+}
+
+/// Get Stats
+///
+/// Gets the usage statistics of the authenticated user: counts of papers, folders
+/// and notes, storage used, llm tokens consumed this month and the papers added per
+/// week. The statistics are cached for a minute.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = UserStatsResponse, description = "Usage statistics"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn get_stats(depot: &mut Depot) -> ServiceResult<UserStatsResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    if let Some(stats) = state.stats_cache.get(&user.uid) {
+        return Ok(stats);
+    }
+
+    let db = &state.db;
+    let now = Utc::now();
+    let first_week = week_start(now) - Duration::weeks(ACTIVITY_WEEKS - 1);
+    let (papers, folders, notes, storage_bytes, ai_tokens_this_month, added) = tokio::try_join!(
+        db.count_papers(&user.uid),
+        db.count_folders(&user.uid),
+        db.count_notes(&user.uid),
+        db.sum_storage_bytes(&user.uid),
+        db.sum_tokens_since(&user.uid, month_start(now).into()),
+        db.papers_added_per_week(&user.uid, first_week.into()),
+    )?;
+
+    // fill in the weeks without new papers
+    let activity = (0..ACTIVITY_WEEKS)
+        .map(|i| {
+            let week = (first_week + Duration::weeks(i)).timestamp_millis();
+            let papers_added = added
+                .iter()
+                .find(|(start, _)| start.timestamp_millis() == week)
+                .map_or(0, |(_, count)| *count);
+            WeeklyActivity { week, papers_added }
+        })
+        .collect();
+
+    let stats = UserStatsResponse {
+        papers,
+        folders,
+        notes,
+        storage_bytes,
+        ai_tokens_this_month,
+        activity,
+        computed_at: now.timestamp_millis(),
+    };
+    state.stats_cache.insert(&user.uid, stats.clone());
+    Ok(stats)
+}
@@ -0,0 +1,34 @@
+use salvo::{
+    Depot, Router,
+    oapi::{RouterExt, endpoint},
+};
+
+use crate::{app_data::AppDataRef, error::ServiceResult, tenant::TenantResponse};
+
+pub fn create_non_auth_router() -> Router {
+    Router::new().get(get_tenant).oapi_tag("tenant")
+}
+
+/// Get Tenant
+///
+/// Gets the organization serving the request, resolved from the `X-Tenant`
+/// header, the host, or the token, with the branding the frontends present it
+/// with.
+#[endpoint(
+    status_codes(200, 404),
+    responses(
+        (status_code = 200, body = TenantResponse, description = "Tenant of the request"),
+        (status_code = 404, description = "Not Found: Unknown tenant in the X-Tenant header")
+    )
+)]
+async fn get_tenant(depot: &mut Depot) -> ServiceResult<TenantResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let tenant = &state.tenant;
+    Ok(TenantResponse {
+        id: tenant.id.clone(),
+        name: tenant.branding.name.clone(),
+        logo_url: tenant.branding.logo_url.clone(),
+        primary_color: tenant.branding.primary_color.clone(),
+    })
+}
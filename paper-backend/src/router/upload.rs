@@ -0,0 +1,462 @@
+//! Resumable uploads of the pdf of a paper, following the core of the tus 1.0.0
+//! protocol with its creation, termination and checksum extensions. A client
+//! announces the length of the file, sends it in chunks, and after a dropped
+//! connection asks for the offset received to resume from there.
+
+use std::collections::HashMap;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use salvo::{
+    Depot, FlowCtrl, Request, Response, Router, handler,
+    http::{
+        Method, StatusCode,
+        header::{CACHE_CONTROL, CONTENT_TYPE, HeaderValue, LOCATION},
+    },
+    oapi::{RouterExt, endpoint, extract::PathParam},
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    app_data::AppDataRef,
+    authz::Access,
+    error::{ServiceError, ServiceResult},
+    model::{
+        upload::{UploadChunk, UploadRepository, UploadSession},
+        user::User,
+    },
+    router::paper::{attach_paper_file, fetch_paper},
+    utils::timeout::extend_for_upload,
+};
+
+const TUS_VERSION: &str = "1.0.0";
+const TUS_EXTENSIONS: &str = "creation,termination,checksum";
+const TUS_CHECKSUM_ALGORITHMS: &str = "sha256";
+const CHUNK_CONTENT_TYPE: &str = "application/offset+octet-stream";
+
+// chunks are stored as documents, well below their 16MB limit
+const MAX_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+pub fn create_router() -> Router {
+    Router::new()
+        .hoop(tus_resumable)
+        .options(upload_options)
+        .post(create_upload)
+        .push(
+            Router::with_path("{upload_id}")
+                .head(get_upload_offset)
+                .delete(delete_upload)
+                .push(Router::new().hoop(extend_for_upload).patch(upload_chunk)),
+        )
+        .oapi_tag("upload")
+}
+
+/// Answer the version of the protocol, and refuse the requests made for
+/// another one. `OPTIONS` is how clients discover it, it needs none.
+#[handler]
+async fn tus_resumable(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    res.headers_mut()
+        .insert("tus-resumable", HeaderValue::from_static(TUS_VERSION));
+    if req.method() != Method::OPTIONS && header(req, "tus-resumable") != Some(TUS_VERSION) {
+        res.headers_mut()
+            .insert("tus-version", HeaderValue::from_static(TUS_VERSION));
+        res.render(ServiceError::PreconditionFailed(format!(
+            "Tus-Resumable {} is required",
+            TUS_VERSION
+        )));
+        ctrl.skip_rest();
+        return;
+    }
+    ctrl.call_next(req, depot, res).await;
+}
+
+fn header<'a>(req: &'a Request, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+}
+
+fn number_header(req: &Request, name: &str) -> ServiceResult<u64> {
+    header(req, name)
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| ServiceError::BadRequest(format!("{} must be a number of bytes", name)))
+}
+
+/// The pairs of `Upload-Metadata`, e.g. `paperId cDE=,ocr ZmFsc2U=`, with
+/// their values decoded from base64. A key may come without a value.
+fn parse_metadata(value: &str) -> ServiceResult<HashMap<String, String>> {
+    let invalid = || ServiceError::BadRequest("Invalid Upload-Metadata".to_string());
+    let mut metadata = HashMap::new();
+    for pair in value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+    {
+        let (key, value) = match pair.split_once(' ') {
+            Some((key, value)) => {
+                let value = STANDARD.decode(value.trim()).map_err(|_| invalid())?;
+                (key, String::from_utf8(value).map_err(|_| invalid())?)
+            }
+            None => (pair, String::new()),
+        };
+        metadata.insert(key.to_string(), value);
+    }
+    Ok(metadata)
+}
+
+/// The digest of `Upload-Checksum`, e.g. `sha256 <base64>`.
+fn parse_checksum(value: &str) -> ServiceResult<Vec<u8>> {
+    let (algorithm, digest) = value
+        .split_once(' ')
+        .ok_or_else(|| ServiceError::BadRequest("Invalid Upload-Checksum".to_string()))?;
+    if algorithm != TUS_CHECKSUM_ALGORITHMS {
+        return Err(ServiceError::BadRequest(format!(
+            "Unsupported checksum algorithm {}",
+            algorithm
+        )));
+    }
+    STANDARD
+        .decode(digest.trim())
+        .map_err(|_| ServiceError::BadRequest("Invalid Upload-Checksum".to_string()))
+}
+
+/// The file of the chunks, which must follow each other up to the length.
+fn assemble(chunks: Vec<UploadChunk>, length: u64) -> ServiceResult<Vec<u8>> {
+    let mut file = Vec::with_capacity(length as usize);
+    for chunk in chunks {
+        if chunk.offset != file.len() as u64 {
+            return Err(ServiceError::VersionConflict(format!(
+                "Chunk at offset {} does not follow the {} bytes before it",
+                chunk.offset,
+                file.len()
+            )));
+        }
+        file.extend(chunk.data.bytes);
+    }
+    if file.len() as u64 != length {
+        return Err(ServiceError::VersionConflict(format!(
+            "Upload has {} bytes instead of {}",
+            file.len(),
+            length
+        )));
+    }
+    Ok(file)
+}
+
+async fn fetch_upload(
+    state: &AppDataRef,
+    user: &User,
+    upload_id: &str,
+) -> ServiceResult<UploadSession> {
+    state
+        .db
+        .get_upload(&user.uid, upload_id)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound(format!("Upload {}", upload_id)))
+}
+
+/// Upload Options
+///
+/// Describes the resumable uploads supported: the version of the tus protocol,
+/// its extensions, the checksum algorithms and the largest file accepted.
+#[endpoint(
+    status_codes(204),
+    responses(
+        (status_code = 204, description = "Tus capabilities in the headers")
+    )
+)]
+async fn upload_options(depot: &mut Depot, resp: &mut Response) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+
+    let headers = resp.headers_mut();
+    headers.insert("tus-version", HeaderValue::from_static(TUS_VERSION));
+    headers.insert("tus-extension", HeaderValue::from_static(TUS_EXTENSIONS));
+    headers.insert(
+        "tus-checksum-algorithm",
+        HeaderValue::from_static(TUS_CHECKSUM_ALGORITHMS),
+    );
+    headers.insert(
+        "tus-max-size",
+        HeaderValue::from(state.body_limit_config.upload_bytes),
+    );
+    resp.status_code(StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+/// Create Upload
+///
+/// Starts a resumable upload of the pdf of a paper. `Upload-Length` is the size
+/// of the file, `Upload-Metadata` holds the `paperId` and optionally `ocr` set to
+/// `false`, base64 encoded. The url of the upload is in `Location`. Uploads not
+/// completed within a day are discarded.
+#[endpoint(
+    status_codes(201, 400, 401, 403, 404, 412, 413),
+    responses(
+        (status_code = 201, description = "Upload created, its url in Location"),
+        (status_code = 400, description = "Bad Request: Invalid length or metadata"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 403, description = "Forbidden: Storage quota exceeded"),
+        (status_code = 404, description = "Not Found: Paper does not exist"),
+        (status_code = 412, description = "Precondition Failed: Unsupported tus version"),
+        (status_code = 413, description = "Payload Too Large: File over the upload limit")
+    )
+)]
+async fn create_upload(
+    req: &mut Request,
+    depot: &mut Depot,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let length = number_header(req, "upload-length")?;
+    let limit = state.body_limit_config.upload_bytes;
+    if length > limit {
+        return Err(ServiceError::PayloadTooLarge(limit));
+    }
+    if length == 0 {
+        return Err(ServiceError::BadRequest(
+            "Uploaded file is empty".to_string(),
+        ));
+    }
+    let metadata = parse_metadata(header(req, "upload-metadata").unwrap_or_default())?;
+    let paper_id = metadata
+        .get("paperId")
+        .ok_or_else(|| ServiceError::BadRequest("Upload-Metadata lacks the paperId".to_string()))?;
+    let ocr = metadata.get("ocr").is_none_or(|ocr| ocr != "false");
+
+    // fail before the file is sent rather than once it is complete
+    let paper = fetch_paper(state, paper_id, user, Access::Write).await?;
+    state
+        .ensure_storage_quota(user, length, paper.file_size.unwrap_or_default())
+        .await?;
+
+    let upload = UploadSession::new(&user.uid, &paper.id, length, ocr);
+    let location = format!("{}/{}", req.uri().path().trim_end_matches('/'), upload.id);
+    state.db.create_upload(upload).await?;
+
+    if let Ok(location) = HeaderValue::from_str(&location) {
+        resp.headers_mut().insert(LOCATION, location);
+    }
+    resp.status_code(StatusCode::CREATED);
+    Ok(())
+}
+
+/// Get Upload Offset
+///
+/// Gets the bytes of the file received so far in `Upload-Offset`, where the
+/// client resumes the upload, and the size of the file in `Upload-Length`.
+#[endpoint(
+    status_codes(200, 401, 404, 412),
+    responses(
+        (status_code = 200, description = "Offset of the upload in the headers"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Upload does not exist or expired"),
+        (status_code = 412, description = "Precondition Failed: Unsupported tus version")
+    )
+)]
+async fn get_upload_offset(
+    depot: &mut Depot,
+    upload_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let upload = fetch_upload(state, user, &upload_id).await?;
+    let headers = resp.headers_mut();
+    headers.insert("upload-offset", HeaderValue::from(upload.offset));
+    headers.insert("upload-length", HeaderValue::from(upload.length));
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    Ok(())
+}
+
+/// Upload Chunk
+///
+/// Appends the body to the file at `Upload-Offset`, which must be the offset
+/// received so far, with the `application/offset+octet-stream` content type.
+/// Chunks are at most 8MB. With `Upload-Checksum: sha256 <base64>` a corrupted
+/// chunk is refused with a 460 and can be sent again. The chunk completing the
+/// file attaches it to the paper once it is checked to be a pdf, the text is
+/// then extracted as after a direct upload.
+#[endpoint(
+    status_codes(204, 400, 401, 403, 404, 409, 412, 413, 460),
+    responses(
+        (status_code = 204, description = "Chunk stored, the new offset in Upload-Offset"),
+        (status_code = 400, description = "Bad Request: Invalid chunk, or the file is not a pdf"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 403, description = "Forbidden: Storage quota exceeded"),
+        (status_code = 404, description = "Not Found: Upload or paper does not exist"),
+        (status_code = 409, description = "Conflict: Upload-Offset is not the offset received"),
+        (status_code = 412, description = "Precondition Failed: Unsupported tus version"),
+        (status_code = 413, description = "Payload Too Large: Chunk over 8MB"),
+        (status_code = 460, description = "Checksum Mismatch: The chunk was corrupted")
+    )
+)]
+async fn upload_chunk(
+    req: &mut Request,
+    depot: &mut Depot,
+    upload_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let content_type = header(req, CONTENT_TYPE.as_str()).unwrap_or_default();
+    if content_type != CHUNK_CONTENT_TYPE {
+        return Err(ServiceError::BadRequest(format!(
+            "Chunks must be sent as {}",
+            CHUNK_CONTENT_TYPE
+        )));
+    }
+    let offset = number_header(req, "upload-offset")?;
+    let checksum = header(req, "upload-checksum")
+        .map(parse_checksum)
+        .transpose()?;
+    let upload = fetch_upload(state, user, &upload_id).await?;
+    if offset != upload.offset {
+        return Err(ServiceError::VersionConflict(format!(
+            "Upload is at offset {}, not {}",
+            upload.offset, offset
+        )));
+    }
+
+    // bounded by `body_limit_config.upload_bytes`
+    let bytes = req
+        .payload()
+        .await
+        .map_err(|e| ServiceError::BadRequest(format!("Invalid upload: {}", e)))?
+        .to_vec();
+    if bytes.len() > MAX_CHUNK_BYTES {
+        return Err(ServiceError::PayloadTooLarge(MAX_CHUNK_BYTES as u64));
+    }
+    let new_offset = upload.offset + bytes.len() as u64;
+    if new_offset > upload.length {
+        return Err(ServiceError::BadRequest(format!(
+            "Chunk goes past the Upload-Length of {}",
+            upload.length
+        )));
+    }
+    if checksum.is_some_and(|checksum| Sha256::digest(&bytes).as_slice() != checksum) {
+        return Err(ServiceError::ChecksumMismatch(format!(
+            "Chunk at offset {} does not match its Upload-Checksum",
+            offset
+        )));
+    }
+
+    if !bytes.is_empty() {
+        state
+            .db
+            .put_upload_chunk(UploadChunk::new(&upload, bytes))
+            .await?;
+        if !state
+            .db
+            .advance_upload(&upload.id, upload.offset, new_offset)
+            .await?
+        {
+            return Err(ServiceError::VersionConflict(format!(
+                "Upload {} was resumed by another request",
+                upload.id
+            )));
+        }
+    }
+    if new_offset == upload.length {
+        complete_upload(state, user, &upload).await?;
+    }
+    resp.headers_mut()
+        .insert("upload-offset", HeaderValue::from(new_offset));
+    resp.status_code(StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+/// Attach the file to the paper, then discard the upload unless a server error
+/// failed it: an empty chunk at the last offset completes it again.
+async fn complete_upload(
+    state: &AppDataRef,
+    user: &User,
+    upload: &UploadSession,
+) -> ServiceResult<()> {
+    let attached = attach_upload(state, user, upload).await;
+    if !attached
+        .as_ref()
+        .is_err_and(|e| e.status_code().is_server_error())
+    {
+        state.db.delete_upload(&upload.id).await?;
+    }
+    attached
+}
+
+/// Assemble the chunks and attach them to the paper as its pdf.
+async fn attach_upload(
+    state: &AppDataRef,
+    user: &User,
+    upload: &UploadSession,
+) -> ServiceResult<()> {
+    let chunks = state.db.get_upload_chunks(&upload.id).await?;
+    let file = assemble(chunks, upload.length)?;
+    let paper = fetch_paper(state, &upload.paper_id, user, Access::Write).await?;
+    attach_paper_file(state, user, paper, file, upload.ocr).await?;
+    Ok(())
+}
+
+/// Delete Upload
+///
+/// Abandons a resumable upload, its chunks are deleted.
+#[endpoint(
+    status_codes(204, 401, 404, 412),
+    responses(
+        (status_code = 204, description = "Upload deleted"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Upload does not exist or expired"),
+        (status_code = 412, description = "Precondition Failed: Unsupported tus version")
+    )
+)]
+async fn delete_upload(
+    depot: &mut Depot,
+    upload_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let upload = fetch_upload(state, user, &upload_id).await?;
+    state.db.delete_upload(&upload.id).await?;
+    resp.status_code(StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metadata() {
+        let metadata = parse_metadata("paperId cDE=, ocr ZmFsc2U=,is_draft").unwrap();
+        assert_eq!(metadata["paperId"], "p1");
+        assert_eq!(metadata["ocr"], "false");
+        assert_eq!(metadata["is_draft"], "");
+        assert!(parse_metadata("paperId not-base64!").is_err());
+    }
+
+    #[test]
+    fn test_assemble() {
+        let upload = UploadSession::new("u1", "p1", 5, true);
+        let first = UploadChunk::new(&upload, b"%PD".to_vec());
+        let upload = UploadSession {
+            offset: 3,
+            ..upload
+        };
+        let second = UploadChunk::new(&upload, b"F-".to_vec());
+        assert_eq!(
+            assemble(vec![first.clone(), second.clone()], 5).unwrap(),
+            b"%PDF-"
+        );
+        assert!(assemble(vec![first.clone()], 5).is_err());
+        assert!(assemble(vec![second, first], 5).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use chrono::Utc;
+use salvo::{
+    Depot, Router,
+    oapi::{RouterExt, endpoint},
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::ServiceResult,
+    model::{
+        usage::{
+            UsageRepository, month_start, next_month_start,
+            schema::{UsageResponse, UsageTotalResponse},
+        },
+        user::User,
+    },
+};
+
+// models and features a user can have used, bounds the groups returned
+const MAX_USAGE_GROUPS: i64 = 100;
+
+pub fn create_router() -> Router {
+    Router::new().get(get_usage).oapi_tag("usage")
+}
+
+/// Get Usage
+///
+/// Gets the llm usage of the authenticated user in the current month: requests,
+/// tokens and estimated cost, in total and per model and feature, with the monthly
+/// token quota and what remains of it. The calls made with the own api keys of
+/// the user are listed but do not count against the quota.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = UsageResponse, description = "Llm usage of the user"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn get_usage(depot: &mut Depot) -> ServiceResult<UsageResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let db = &state.db;
+    let now = Utc::now();
+    let since = month_start(now);
+    let user_id = Some(user.uid.as_str());
+    let (total, billed, by_model, by_feature) = tokio::try_join!(
+        db.sum_usage_since(user_id, None, since.into(), 1),
+        db.sum_tokens_since(&user.uid, since.into()),
+        db.sum_usage_since(user_id, Some("model"), since.into(), MAX_USAGE_GROUPS),
+        db.sum_usage_since(user_id, Some("feature"), since.into(), MAX_USAGE_GROUPS),
+    )?;
+    let total = total.into_iter().next().unwrap_or_default();
+    let quota = state.usage_config.monthly_token_quota;
+
+    Ok(UsageResponse {
+        period_start: since.timestamp_millis(),
+        period_end: next_month_start(now).timestamp_millis(),
+        remaining: quota.map(|quota| quota.saturating_sub(billed)),
+        quota,
+        total: total.into(),
+        by_model: by_model.into_iter().map(UsageTotalResponse::from).collect(),
+        by_feature: by_feature
+            .into_iter()
+            .map(UsageTotalResponse::from)
+            .collect(),
+    })
+}
@@ -1,20 +1,34 @@
-use salvo::{Router, Writer, handler};
+use salvo::{
+    Depot, Router, Writer, handler,
+    oapi::{RouterExt, endpoint, extract::JsonBody},
+};
 
 use crate::{
     app_data::AppDataRef,
-    error::ServiceResult,
+    error::{ServiceResult, ValidationErrorResponse},
     model::user::{
         User, UserRepository,
-        schema::{UpdateUserInfo, UserInfoResponse},
+        schema::{
+            NotificationPreferencesResponse, UpdateNotificationPreferencesRequest, UpdateUserInfo,
+            UserInfoResponse,
+        },
     },
+    utils::{cache::CacheKey, validate::ValidatedRequest},
 };
 
 pub fn create_router() -> Router {
-    Router::new().push(
-        Router::with_path("info")
-            .get(get_user_info)
-            .post(update_user_info),
-    )
+    Router::new()
+        .push(
+            Router::with_path("info")
+                .get(get_user_info)
+                .post(update_user_info),
+        )
+        .push(
+            Router::with_path("notification-preferences")
+                .get(get_notification_preferences)
+                .put(update_notification_preferences),
+        )
+        .oapi_tag("user")
 }
 
 #[handler]
@@ -31,6 +45,56 @@ async fn update_user_info(info: UpdateUserInfo, depot: &mut salvo::Depot) -> Ser
     let new_user = User {
         ..current_user.clone()
     };
-    state.mongo_client.update_user(new_user).await?;
+    state.db.update_user(new_user).await?;
+    state.invalidate(&[CacheKey::User(&current_user.uid)]).await;
     Ok(())
 }
+
+/// Get Notification Preferences
+///
+/// Gets the emails the authenticated user receives.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = NotificationPreferencesResponse, description = "Notification preferences"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn get_notification_preferences(
+    depot: &mut Depot,
+) -> ServiceResult<NotificationPreferencesResponse> {
+    let user = depot.obtain::<User>()?;
+    Ok(user.into())
+}
+
+/// Update Notification Preferences
+///
+/// Opts the authenticated user in or out of the weekly digest and the summary emails.
+#[endpoint(
+    status_codes(200, 401, 422),
+    responses(
+        (status_code = 200, body = NotificationPreferencesResponse, description = "Notification preferences updated"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error")
+    )
+)]
+async fn update_notification_preferences(
+    depot: &mut Depot,
+    request: JsonBody<UpdateNotificationPreferencesRequest>,
+) -> ServiceResult<NotificationPreferencesResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    // the cached user may miss the last digest time
+    let mut user = state
+        .db
+        .get_user_by_uid(&user.uid)
+        .await?
+        .unwrap_or_else(|| user.clone());
+    user.notification_preferences.update(request);
+    user.updated_at = bson::DateTime::now();
+    state.db.update_user(user.clone()).await?;
+    state.invalidate(&[CacheKey::User(&user.uid)]).await;
+    Ok((&user).into())
+}
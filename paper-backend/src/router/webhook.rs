@@ -0,0 +1,171 @@
+use salvo::{
+    Depot, Response, Router,
+    oapi::{
+        RouterExt, endpoint,
+        extract::{JsonBody, PathParam, QueryParam},
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult, ValidationErrorResponse},
+    model::{
+        user::User,
+        webhook::{
+            Webhook, WebhookRepository,
+            schema::{
+                CreateWebhookRequest, ListWebhookDeliveriesResponse, ListWebhooksResponse,
+                WebhookResponse,
+            },
+        },
+    },
+    utils::validate::ValidatedRequest,
+};
+
+// webhooks a user can register
+const MAX_WEBHOOKS: usize = 10;
+const DEFAULT_DELIVERY_LIMIT: i64 = 50;
+const MAX_DELIVERY_LIMIT: i64 = 200;
+
+pub fn create_router() -> Router {
+    Router::new()
+        .push(Router::new().get(list_webhooks).post(create_webhook))
+        .push(
+            Router::with_path("{webhook_id}")
+                .delete(delete_webhook)
+                .push(Router::with_path("deliveries").get(list_deliveries)),
+        )
+        .oapi_tag("webhook")
+}
+
+async fn get_owned_webhook(
+    state: &AppDataRef,
+    user: &User,
+    webhook_id: &str,
+) -> ServiceResult<Webhook> {
+    state
+        .db
+        .get_webhook(&user.uid, webhook_id)
+        .await?
+        .ok_or_else(|| ServiceError::NotFound(format!("Webhook {}", webhook_id)))
+}
+
+/// List Webhooks
+///
+/// Lists the webhooks of the authenticated user, without their secrets.
+#[endpoint(
+    status_codes(200, 401),
+    responses(
+        (status_code = 200, body = ListWebhooksResponse, description = "Webhooks of the user"),
+        (status_code = 401, description = "Unauthorized: User not authenticated")
+    )
+)]
+async fn list_webhooks(depot: &mut Depot) -> ServiceResult<ListWebhooksResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let webhooks = state.db.get_webhooks(&user.uid).await?;
+    Ok(ListWebhooksResponse(
+        webhooks.into_iter().map(Into::into).collect(),
+    ))
+}
+
+/// Create Webhook
+///
+/// Registers an url the events of the authenticated user are posted to, all of
+/// them or only the given types. Each payload is signed: the `X-Webhook-Signature`
+/// header is `sha256=` followed by the hex HMAC-SHA256, keyed with the returned
+/// secret, of the `X-Webhook-Timestamp` header, a dot and the body. Failed
+/// deliveries are retried with backoff.
+#[endpoint(
+    status_codes(201, 401, 422),
+    responses(
+        (status_code = 201, body = WebhookResponse, description = "Webhook created, with its secret"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 422, body = ValidationErrorResponse, description = "Unprocessable Entity: Validation error or too many webhooks")
+    )
+)]
+async fn create_webhook(
+    depot: &mut Depot,
+    request: JsonBody<CreateWebhookRequest>,
+    resp: &mut Response,
+) -> ServiceResult<WebhookResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let request = request.into_inner().validated()?;
+    let webhooks = state.db.get_webhooks(&user.uid).await?;
+    if webhooks.len() >= MAX_WEBHOOKS {
+        return Err(ServiceError::invalid_field(
+            "url",
+            "limit",
+            format!("At most {} webhooks can be registered", MAX_WEBHOOKS),
+        ));
+    }
+
+    let mut webhook = Webhook::new(&user.uid, request);
+    let secret = webhook.secret.clone();
+    webhook.secret = state.seal_secret(&secret)?;
+    state.db.create_webhook(webhook.clone()).await?;
+    resp.status_code(salvo::http::StatusCode::CREATED);
+    Ok(WebhookResponse {
+        secret: Some(secret),
+        ..webhook.into()
+    })
+}
+
+/// Delete Webhook
+///
+/// Deletes a webhook of the authenticated user with its delivery log.
+#[endpoint(
+    status_codes(204, 401, 404),
+    responses(
+        (status_code = 204, description = "Webhook deleted successfully"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Webhook does not exist")
+    )
+)]
+async fn delete_webhook(
+    depot: &mut Depot,
+    webhook_id: PathParam<String>,
+    resp: &mut Response,
+) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let webhook = get_owned_webhook(state, user, &webhook_id).await?;
+    state.db.delete_webhook(&user.uid, &webhook.id).await?;
+    resp.status_code(salvo::http::StatusCode::NO_CONTENT);
+    Ok(())
+}
+
+/// List Webhook Deliveries
+///
+/// Lists the latest deliveries to a webhook of the authenticated user, newest
+/// first, `limit` defaults to 50. Deliveries are kept for 30 days.
+#[endpoint(
+    status_codes(200, 401, 404),
+    responses(
+        (status_code = 200, body = ListWebhookDeliveriesResponse, description = "Deliveries of the webhook"),
+        (status_code = 401, description = "Unauthorized: User not authenticated"),
+        (status_code = 404, description = "Not Found: Webhook does not exist")
+    )
+)]
+async fn list_deliveries(
+    depot: &mut Depot,
+    webhook_id: PathParam<String>,
+    limit: QueryParam<i64, false>,
+) -> ServiceResult<ListWebhookDeliveriesResponse> {
+    let state = depot.obtain::<AppDataRef>()?;
+    let user = depot.obtain::<User>()?;
+
+    let webhook = get_owned_webhook(state, user, &webhook_id).await?;
+    let limit = limit
+        .into_inner()
+        .unwrap_or(DEFAULT_DELIVERY_LIMIT)
+        .clamp(1, MAX_DELIVERY_LIMIT);
+    let deliveries = state.db.get_webhook_deliveries(&webhook.id, limit).await?;
+    Ok(ListWebhookDeliveriesResponse(
+        deliveries.into_iter().map(Into::into).collect(),
+    ))
+}
@@ -0,0 +1,267 @@
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use salvo::{
+    Depot, Request, Response, Router, handler,
+    prelude::JwtAuthDepotExt,
+    websocket::{Message, WebSocket, WebSocketUpgrade},
+};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    app_data::AppDataRef,
+    error::{ServiceError, ServiceResult},
+    events::Event,
+    model::{paper::Paper, user::User},
+    router::paper::save_notes,
+    utils::{jwt::JwtClaims, session::check_socket_csrf},
+};
+
+// how often the session of an open socket is checked again
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// sent to a participant who may only follow the editing of the notes
+const READ_ONLY_MESSAGE: &str = r#"{"type":"read_only"}"#;
+// sent back when an update of the notes cannot be merged
+const INVALID_UPDATE_MESSAGE: &str = r#"{"type":"invalid_update"}"#;
+// sent before closing the socket of a room whose notes an update of the paper
+// replaced
+const NOTES_REPLACED_MESSAGE: &str = r#"{"type":"notes_replaced"}"#;
+// sent before closing the socket of an expired or revoked session
+const SESSION_ENDED_MESSAGE: &str = r#"{"type":"session_ended"}"#;
+
+pub fn create_router() -> Router {
+    Router::new().get(connect)
+}
+
+/// Sent by the client to receive only some kinds of events, all by default.
+#[derive(Debug, Deserialize)]
+struct SubscribeMessage {
+    // e.g. `["folder_created", "paper_added"]`
+    subscribe: Option<Vec<String>>,
+}
+
+/// The session a socket was opened with, checked again while the socket is
+/// open: the socket is closed once the token expires, or the sessions of the
+/// user are revoked.
+#[derive(Debug, Clone)]
+pub(super) struct SocketSession {
+    user_id: String,
+    claims: JwtClaims,
+}
+
+impl SocketSession {
+    pub(super) fn of(depot: &Depot) -> ServiceResult<Self> {
+        let user_id = depot.obtain::<User>()?.uid.clone();
+        let claims = depot
+            .jwt_auth_data::<JwtClaims>()
+            .ok_or_else(|| ServiceError::Unauthorized("JWT token not provided".to_string()))?
+            .claims
+            .clone();
+        Ok(SocketSession { user_id, claims })
+    }
+
+    async fn is_valid(&self, state: &AppDataRef) -> bool {
+        if self.claims.is_expired() {
+            return false;
+        }
+        match state.cached_user(&self.user_id).await {
+            Ok(Some(user)) => !user.is_token_revoked(self.claims.iat),
+            Ok(None) => false,
+            // kept open while the database is unreachable
+            Err(e) => {
+                tracing::warn!("Failed to check the session of {}: {}", self.user_id, e);
+                true
+            }
+        }
+    }
+}
+
+/// Upgrade to a WebSocket streaming the events of the user as json messages,
+/// so every open tab and device stays in sync without polling. The upgrades
+/// signed in by the session cookie carry the csrf token in the query.
+#[handler]
+async fn connect(req: &mut Request, res: &mut Response, depot: &mut Depot) -> ServiceResult<()> {
+    let state = depot.obtain::<AppDataRef>()?.clone();
+    let session = SocketSession::of(depot)?;
+    check_socket_csrf(req, &state.session_config)?;
+
+    WebSocketUpgrade::new()
+        .upgrade(req, res, move |ws| relay_events(ws, state, session))
+        .await
+        .map_err(|e| ServiceError::BadRequest(format!("WebSocket upgrade failed: {}", e)))
+}
+
+/// The message of an event, `None` when the client is not interested.
+fn event_message(event: &Event, kinds: &Option<Vec<String>>) -> Option<Message> {
+    let subscribed = kinds
+        .as_ref()
+        .is_none_or(|kinds| kinds.iter().any(|kind| kind == event.payload.kind()));
+    if !subscribed {
+        return None;
+    }
+    serde_json::to_string(event).ok().map(Message::text)
+}
+
+async fn relay_events(ws: WebSocket, state: AppDataRef, session: SocketSession) {
+    let user_id = session.user_id.clone();
+    let mut events = state.events.subscribe();
+    let (mut sender, mut receiver) = ws.split();
+    let mut kinds: Option<Vec<String>> = None;
+    let mut session_check = session_check_interval();
+
+    loop {
+        tokio::select! {
+            _ = session_check.tick() => {
+                if !session.is_valid(&state).await {
+                    let _ = sender.send(Message::text(SESSION_ENDED_MESSAGE)).await;
+                    break;
+                }
+            }
+            event = events.recv() => {
+                let message = match event {
+                    Ok(event) if event.user_id == user_id => event_message(&event, &kinds),
+                    Ok(_) => None,
+                    Err(RecvError::Lagged(missed)) => {
+                        let receiver = format!("WebSocket of user {}", user_id);
+                        state.events.record_missed(&receiver, missed);
+                        // the client should reload its data
+                        Some(Message::text(r#"{"type":"resync"}"#))
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                let Some(message) = message else {
+                    continue;
+                };
+                if sender.send(message).await.is_err() {
+                    break;
+                }
+            }
+            message = receiver.next() => match message {
+                Some(Ok(message)) if message.is_close() => break,
+                Some(Ok(message)) if message.is_text() => {
+                    match serde_json::from_slice::<SubscribeMessage>(message.as_bytes()) {
+                        Ok(request) => kinds = request.subscribe,
+                        Err(e) => tracing::info!("Invalid WebSocket message: {}", e),
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => break,
+            },
+        }
+    }
+}
+
+// the first tick is delayed, the session was checked by the upgrade
+fn session_check_interval() -> tokio::time::Interval {
+    let start = tokio::time::Instant::now() + SESSION_CHECK_INTERVAL;
+    tokio::time::interval_at(start, SESSION_CHECK_INTERVAL)
+}
+
+/// Upgrade to a WebSocket editing the notes of the paper. Binary messages are
+/// yrs v1 updates: the whole state is sent first, then the updates of the other
+/// participants, and the updates of the client are merged and relayed. The
+/// sockets of the share links have no session to check. The upgrades signed in
+/// by the session cookie carry the csrf token in the query.
+pub(super) async fn upgrade_notes(
+    req: &mut Request,
+    res: &mut Response,
+    state: AppDataRef,
+    session: Option<SocketSession>,
+    paper: Paper,
+    can_edit: bool,
+) -> ServiceResult<()> {
+    if session.is_some() {
+        check_socket_csrf(req, &state.session_config)?;
+    }
+    WebSocketUpgrade::new()
+        .upgrade(req, res, move |ws| {
+            relay_notes(ws, state, session, paper, can_edit)
+        })
+        .await
+        .map_err(|e| ServiceError::BadRequest(format!("WebSocket upgrade failed: {}", e)))
+}
+
+async fn relay_notes(
+    ws: WebSocket,
+    state: AppDataRef,
+    socket_session: Option<SocketSession>,
+    paper: Paper,
+    can_edit: bool,
+) {
+    let session = match state.notes.join(&state, &paper).await {
+        Ok(session) => session,
+        Err(e) => {
+            tracing::error!("Failed to open the notes of paper {}: {}", paper.id, e);
+            return;
+        }
+    };
+    let mut updates = session.room.subscribe();
+    let (mut sender, mut receiver) = ws.split();
+    let mut connected = sender
+        .send(Message::binary(session.room.state()))
+        .await
+        .is_ok();
+    let mut session_check = session_check_interval();
+
+    while connected {
+        tokio::select! {
+            _ = session_check.tick() => {
+                let valid = match &socket_session {
+                    Some(socket_session) => socket_session.is_valid(&state).await,
+                    None => true,
+                };
+                if !valid {
+                    let _ = sender.send(Message::text(SESSION_ENDED_MESSAGE)).await;
+                    break;
+                }
+            }
+            update = updates.recv() => {
+                let message = match update {
+                    Ok(update) if update.participant == session.participant => continue,
+                    Ok(update) => Message::binary(update.bytes.to_vec()),
+                    // merging the whole state again catches up
+                    Err(RecvError::Lagged(_)) => Message::binary(session.room.state()),
+                    Err(RecvError::Closed) => break,
+                };
+                connected = sender.send(message).await.is_ok();
+            }
+            message = receiver.next() => match message {
+                Some(Ok(message)) if message.is_close() => break,
+                Some(Ok(message)) if message.is_binary() => {
+                    let reply = match can_edit {
+                        false => Some(READ_ONLY_MESSAGE),
+                        true => {
+                            let bytes = message.as_bytes().to_vec();
+                            match session.room.apply(&state, session.participant, bytes).await {
+                                Ok(()) => None,
+                                // the notes were replaced, they are to be opened again
+                                Err(ServiceError::VersionConflict(_)) => {
+                                    let message = Message::text(NOTES_REPLACED_MESSAGE);
+                                    let _ = sender.send(message).await;
+                                    break;
+                                }
+                                Err(e) => {
+                                    tracing::info!("Invalid notes update: {}", e);
+                                    Some(INVALID_UPDATE_MESSAGE)
+                                }
+                            }
+                        }
+                    };
+                    if let Some(reply) = reply {
+                        connected = sender.send(Message::text(reply)).await.is_ok();
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => break,
+            },
+        }
+    }
+
+    if let Some(notes) = state.notes.leave(session).await {
+        if let Err(e) = save_notes(&state, &paper.id, notes).await {
+            tracing::error!("Failed to save the notes of paper {}: {}", paper.id, e);
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use std::{sync::Arc, time::Duration};
+
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::{
+    app_data::AppDataRef,
+    config::ScanConfig,
+    error::{ServiceError, ServiceResult},
+    events::DomainEvent,
+    model::{
+        blob::{BlobRepository, quarantine_file_key},
+        content::release_file,
+        paper::{PaperRepository, TextStatus},
+        quarantine::{QuarantineRepository, QuarantinedFile},
+    },
+    pdf::{run_extraction_job, thumbnail::run_thumbnail_job},
+    utils::cache::CacheKey,
+};
+
+// bytes sent to clamd by `INSTREAM` chunk
+const CLAMAV_CHUNK_BYTES: usize = 64 * 1024;
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// What a scanner found in a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    // the name of the signature matched
+    Infected(String),
+    // refused by the policy of the scanner, e.g. active content
+    Disallowed(String),
+}
+
+#[async_trait::async_trait]
+pub trait ContentScanner: Send + Sync + std::fmt::Debug {
+    /// Name of the scanner, recorded with the files it quarantines.
+    fn name(&self) -> &'static str;
+    async fn scan(&self, bytes: &[u8]) -> ServiceResult<ScanVerdict>;
+}
+
+pub fn create_scanner(config: Option<&ScanConfig>) -> Arc<dyn ContentScanner> {
+    match config {
+        None => Arc::new(NoopScanner),
+        Some(ScanConfig::Clamav {
+            address,
+            timeout_secs,
+        }) => Arc::new(ClamAvScanner {
+            address: address.clone(),
+            timeout: Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS)),
+        }),
+        Some(ScanConfig::Http {
+            url,
+            api_key,
+            timeout_secs,
+        }) => Arc::new(HttpScanner {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(
+                    timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS),
+                ))
+                .build()
+                .expect("Failed to create content scanner client"),
+            url: url.clone(),
+            api_key: api_key.clone(),
+        }),
+    }
+}
+
+/// Lets every file through, when no scanner is configured.
+#[derive(Debug)]
+pub struct NoopScanner;
+
+#[async_trait::async_trait]
+impl ContentScanner for NoopScanner {
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    async fn scan(&self, _bytes: &[u8]) -> ServiceResult<ScanVerdict> {
+        Ok(ScanVerdict::Clean)
+    }
+}
+
+/// Streams the file to a clamd daemon over TCP with `INSTREAM`.
+#[derive(Debug)]
+pub struct ClamAvScanner {
+    address: String,
+    timeout: Duration,
+}
+
+impl ClamAvScanner {
+    async fn instream(&self, bytes: &[u8]) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(&self.address).await?;
+        stream.write_all(b"zINSTREAM\0").await?;
+        for chunk in bytes.chunks(CLAMAV_CHUNK_BYTES) {
+            stream
+                .write_all(&(chunk.len() as u32).to_be_bytes())
+                .await?;
+            stream.write_all(chunk).await?;
+        }
+        stream.write_all(&0u32.to_be_bytes()).await?;
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await?;
+        Ok(String::from_utf8_lossy(&reply).to_string())
+    }
+}
+
+/// The verdict of a clamd reply, e.g. `stream: Eicar-Signature FOUND`.
+fn parse_clamav_reply(reply: &str) -> ServiceResult<ScanVerdict> {
+    let reply = reply.trim_end_matches('\0').trim();
+    let result = reply.strip_prefix("stream:").unwrap_or(reply).trim();
+    if result == "OK" {
+        return Ok(ScanVerdict::Clean);
+    }
+    match result.strip_suffix(" FOUND") {
+        Some(signature) => Ok(ScanVerdict::Infected(signature.trim().to_string())),
+        None => Err(ServiceError::UpstreamError(format!("clamd: {}", reply))),
+    }
+}
+
+#[async_trait::async_trait]
+impl ContentScanner for ClamAvScanner {
+    fn name(&self) -> &'static str {
+        "clamav"
+    }
+
+    async fn scan(&self, bytes: &[u8]) -> ServiceResult<ScanVerdict> {
+        let reply = tokio::time::timeout(self.timeout, self.instream(bytes))
+            .await
+            .map_err(|_| ServiceError::UpstreamError("clamd timed out".to_string()))?
+            .map_err(|e| ServiceError::UpstreamError(format!("clamd: {}", e)))?;
+        parse_clamav_reply(&reply)
+    }
+}
+
+/// Posts the file to an external scanning service, answering
+/// `{ "verdict": "clean" | "infected" | "disallowed", "reason": "..." }`.
+pub struct HttpScanner {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+impl std::fmt::Debug for HttpScanner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpScanner")
+            .field("url", &self.url)
+            .finish()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum HttpVerdict {
+    Clean,
+    Infected,
+    Disallowed,
+}
+
+#[derive(Debug, Deserialize)]
+struct HttpScanResponse {
+    verdict: HttpVerdict,
+    reason: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl ContentScanner for HttpScanner {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    async fn scan(&self, bytes: &[u8]) -> ServiceResult<ScanVerdict> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/pdf")
+            .body(bytes.to_vec());
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ServiceError::UpstreamError(e.to_string()))?
+            .error_for_status()
+            .map_err(|e| ServiceError::UpstreamError(e.to_string()))?
+            .json::<HttpScanResponse>()
+            .await
+            .map_err(|e| ServiceError::UpstreamError(e.to_string()))?;
+        let reason = response.reason.unwrap_or_default();
+        Ok(match response.verdict {
+            HttpVerdict::Clean => ScanVerdict::Clean,
+            HttpVerdict::Infected => ScanVerdict::Infected(reason),
+            HttpVerdict::Disallowed => ScanVerdict::Disallowed(reason),
+        })
+    }
+}
+
+/// Background job run after upload: scan the file, then extract its text and
+/// render its thumbnails when clean. A file found infected or disallowed, or
+/// which could not be scanned, is moved to the quarantine and detached from
+/// the paper with the reason.
+pub async fn run_scan_job(
+    state: AppDataRef,
+    user_id: String,
+    paper_id: String,
+    bytes: Vec<u8>,
+    ocr: bool,
+) {
+    let reason = match state.scanner.scan(&bytes).await {
+        Ok(ScanVerdict::Clean) => {
+            if state.thumbnails.is_some() {
+                state.jobs.spawn(run_thumbnail_job(
+                    state.clone(),
+                    paper_id.clone(),
+                    bytes.clone(),
+                ));
+            }
+            run_extraction_job(state, user_id, paper_id, bytes, ocr).await;
+            return;
+        }
+        Ok(ScanVerdict::Infected(signature)) => format!("Malware detected: {}", signature),
+        Ok(ScanVerdict::Disallowed(reason)) => format!("File not allowed: {}", reason),
+        Err(e) => {
+            tracing::error!("Scan of the file of paper {} failed: {}", paper_id, e);
+            "The file could not be scanned".to_string()
+        }
+    };
+    tracing::warn!("Quarantining the file of paper {}: {}", paper_id, reason);
+    if let Err(e) = quarantine_file(&state, &user_id, &paper_id, &bytes, &reason).await {
+        tracing::error!("Failed to quarantine the file of paper {}: {}", paper_id, e);
+    }
+    state.invalidate(&[CacheKey::Paper(&paper_id)]).await;
+    state.events.publish(
+        &user_id,
+        DomainEvent::TextExtracted {
+            paper_id,
+            status: TextStatus::Quarantined,
+        },
+    );
+}
+
+async fn quarantine_file(
+    state: &AppDataRef,
+    user_id: &str,
+    paper_id: &str,
+    bytes: &[u8],
+    reason: &str,
+) -> ServiceResult<()> {
+    let file = QuarantinedFile::new(user_id, paper_id, bytes, reason, state.scanner.name());
+    state
+        .db
+        .put_blob(&quarantine_file_key(&file.id), bytes)
+        .await?;
+    state.db.create_quarantined_file(file.clone()).await?;
+    // replaced or deleted since, which released the file already
+    if state
+        .db
+        .set_paper_file_refused(paper_id, &file.file_hash, reason)
+        .await?
+    {
+        release_file(state.db.as_ref(), &file.file_hash).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clamav_reply() {
+        assert_eq!(
+            parse_clamav_reply("stream: OK\0").unwrap(),
+            ScanVerdict::Clean
+        );
+        assert_eq!(
+            parse_clamav_reply("stream: Eicar-Signature FOUND\0").unwrap(),
+            ScanVerdict::Infected("Eicar-Signature".to_string())
+        );
+        assert!(parse_clamav_reply("INSTREAM size limit exceeded. ERROR\0").is_err());
+    }
+}
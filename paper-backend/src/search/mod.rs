@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+
+pub use paper_schema::paper::ScoreBreakdown;
+
+use crate::{
+    config::SearchConfig,
+    model::{folder::Folder, paper::ScoredPaper},
+};
+
+/// Signals of the context the search runs in.
+#[derive(Debug, Default)]
+pub struct RankContext {
+    // the folder searched from and its descendants
+    pub folder_scope: HashSet<String>,
+    // milliseconds since epoch
+    pub now: i64,
+}
+
+/// The folder and all its descendants.
+pub fn folder_scope(folders: &[Folder], folder_id: &str) -> HashSet<String> {
+    let mut scope = HashSet::from([folder_id.to_string()]);
+    let mut level = vec![folder_id.to_string()];
+    while !level.is_empty() {
+        level = folders
+            .iter()
+            .filter(|f| f.parent_id.as_ref().is_some_and(|p| level.contains(p)))
+            .filter(|f| !scope.contains(&f.id))
+            .map(|f| f.id.clone())
+            .collect();
+        scope.extend(level.iter().cloned());
+    }
+    scope
+}
+
+pub fn score_paper(
+    config: &SearchConfig,
+    ctx: &RankContext,
+    paper: &ScoredPaper,
+) -> ScoreBreakdown {
+    let age_days = (ctx.now - paper.paper.updated_at.timestamp_millis()).max(0) as f64
+        / (24 * 60 * 60 * 1000) as f64;
+    let recency = if config.recency_half_life_days > 0.0 {
+        1.0 + config.recency_boost * 0.5f64.powf(age_days / config.recency_half_life_days)
+    } else {
+        1.0
+    };
+    let starred = if paper.paper.starred {
+        config.starred_boost
+    } else {
+        1.0
+    };
+    let folder = if ctx.folder_scope.contains(&paper.paper.folder_id) {
+        config.folder_boost
+    } else {
+        1.0
+    };
+    ScoreBreakdown {
+        text: paper.score,
+        recency,
+        starred,
+        folder,
+        total: paper.score * recency * starred * folder,
+    }
+}
+
+/// Rank the text matches by their boosted score, highest first.
+pub fn rank(
+    config: &SearchConfig,
+    ctx: &RankContext,
+    papers: Vec<ScoredPaper>,
+) -> Vec<(ScoredPaper, ScoreBreakdown)> {
+    let mut ranked = papers
+        .into_iter()
+        .map(|paper| {
+            let score = score_paper(config, ctx, &paper);
+            (paper, score)
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1.total.total_cmp(&a.1.total));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::DateTime;
+
+    use super::*;
+    use crate::model::paper::Paper;
+
+    fn scored(id: &str, score: f64, days_old: i64, starred: bool) -> ScoredPaper {
+        let mut paper = Paper::new("user", "folder", id.to_string());
+        paper.id = id.to_string();
+        paper.updated_at = DateTime::from_millis(1_000_000_000_000 - days_old * 86_400_000);
+        paper.starred = starred;
+        ScoredPaper { paper, score }
+    }
+
+    #[test]
+    fn test_rank_boosts() {
+        let config = SearchConfig::default();
+        let ctx = RankContext {
+            folder_scope: HashSet::new(),
+            now: 1_000_000_000_000,
+        };
+        let papers = vec![
+            scored("old", 1.2, 365, false),
+            scored("recent", 1.0, 0, false),
+            scored("starred", 0.9, 365, true),
+        ];
+        let ranked = rank(&config, &ctx, papers)
+            .into_iter()
+            .map(|(p, _)| p.paper.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ranked, vec!["recent", "starred", "old"]);
+    }
+}
@@ -0,0 +1,151 @@
+use serde::Deserialize;
+
+use crate::{
+    error::ServiceResult,
+    i18n::Locale,
+    model::{
+        database::Database,
+        folder::{Folder, FolderRepository, schema::CreateFolderRequest},
+        paper::{Paper, PaperRepository, schema::CreatePaperRequest},
+        txn::TxnContext,
+        user::{User, UserRepository, UserStatus},
+    },
+    utils::password::hash_password,
+};
+
+pub const DEMO_EMAIL: &str = "demo@example.com";
+pub const DEMO_PASSWORD: &str = "demo-password";
+
+// the library of the demo account
+const FIXTURES: &str = include_str!("fixtures.json");
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FolderFixture {
+    name: String,
+    description: Option<String>,
+    color: Option<String>,
+    icon: Option<String>,
+    #[serde(default)]
+    papers: Vec<PaperFixture>,
+    #[serde(default)]
+    children: Vec<FolderFixture>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PaperFixture {
+    title: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    r#abstract: Option<String>,
+    doi: Option<String>,
+    // markdown notes of the reader
+    notes: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    starred: bool,
+}
+
+/// What the seeding created.
+#[derive(Debug, Default)]
+pub struct Seeded {
+    pub folders: usize,
+    pub papers: usize,
+}
+
+/// Give the demo account, created active if missing, the library of the
+/// fixtures. The previous library of the account is deleted, so that seeding
+/// again resets the demo.
+pub async fn seed_demo(db: &dyn Database) -> ServiceResult<Seeded> {
+    let fixtures: Vec<FolderFixture> =
+        serde_json::from_str(FIXTURES).expect("Invalid seed fixtures");
+    let user = match db.get_user_by_email(DEMO_EMAIL).await? {
+        Some(user) => {
+            for paper in db.get_papers_by_user_id(&user.uid).await? {
+                db.delete_paper(&paper.id).await?;
+            }
+            for folder in db.get_folders_by_user_id(&user.uid).await? {
+                db.delete_folder(&folder.id).await?;
+            }
+            user
+        }
+        None => {
+            let password_hash = hash_password(DEMO_PASSWORD)?;
+            let mut user = User::new_by_email(
+                DEMO_EMAIL.to_string(),
+                Some("demo".to_string()),
+                password_hash,
+            );
+            user.status = UserStatus::Active;
+            db.create_user(user.clone()).await?;
+            user
+        }
+    };
+
+    let mut seeded = Seeded::default();
+    let system_folder = Folder::default_system_folder(&user.uid, Locale::default());
+    db.create_folder(&mut TxnContext::none(), system_folder)
+        .await?;
+    seeded.folders += 1;
+    // the system folder comes first
+    let mut pending = fixtures
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(i, fixture)| (None, i + 1, fixture))
+        .collect::<Vec<_>>();
+    while let Some((parent_id, sort_order, fixture)) = pending.pop() {
+        let request = CreateFolderRequest {
+            parent_id,
+            name: fixture.name.clone(),
+            description: fixture.description.clone(),
+            color: fixture.color.clone(),
+            icon: fixture.icon.clone(),
+            query: None,
+        };
+        let mut folder = Folder::new_from_request(&user.uid, request);
+        folder.sort_order = sort_order as u32;
+        db.create_folder(&mut TxnContext::none(), folder.clone())
+            .await?;
+        seeded.folders += 1;
+
+        for fixture in &fixture.papers {
+            let request = CreatePaperRequest {
+                folder_id: None,
+                title: fixture.title.clone(),
+                authors: fixture.authors.clone(),
+                r#abstract: fixture.r#abstract.clone(),
+                doi: fixture.doi.clone(),
+                content: fixture.notes.clone(),
+                tags: fixture.tags.clone(),
+            };
+            let mut paper = Paper::new_from_request(&user.uid, &folder.id, request);
+            paper.starred = fixture.starred;
+            db.create_paper(paper).await?;
+            seeded.papers += 1;
+        }
+        pending.extend(
+            fixture
+                .children
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(i, child)| (Some(folder.id.clone()), i, child)),
+        );
+    }
+    Ok(seeded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixtures() {
+        let fixtures: Vec<FolderFixture> = serde_json::from_str(FIXTURES).unwrap();
+        assert!(!fixtures.is_empty());
+        assert!(fixtures.iter().any(|f| !f.children.is_empty()));
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use salvo::{
+    Depot, FlowCtrl, Request, Response, Scribe,
+    http::header::{AUTHORIZATION, HOST},
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_data::{AppData, AppDataRef},
+    config::Config,
+    error::{ServiceError, ServiceResult},
+    utils::jwt::{verify_access_token, verify_refresh_token},
+};
+
+pub const DEFAULT_TENANT: &str = "default";
+// names the tenant of a request sent to a host shared by the tenants
+pub const TENANT_HEADER: &str = "x-tenant";
+
+/// The state of every tenant served by the deployment, the default one first.
+/// Each has its own database, the requests only reach the one of their tenant.
+#[derive(Debug, Clone)]
+pub struct Tenants {
+    all: Vec<AppDataRef>,
+    // index in `all`
+    hosts: HashMap<String, usize>,
+}
+
+impl Tenants {
+    pub async fn new(config: &Config, default: AppDataRef) -> Self {
+        let mut tenants = Tenants {
+            all: vec![default],
+            hosts: HashMap::new(),
+        };
+        for tenant in &config.tenants {
+            if tenants.get(&tenant.id).is_some() {
+                panic!("Tenant {} is declared twice", tenant.id);
+            }
+            for host in &tenant.hosts {
+                tenants.hosts.insert(host.to_lowercase(), tenants.all.len());
+            }
+            tenants
+                .all
+                .push(AppData::for_tenant(config, tenant.clone()).await);
+        }
+        tenants
+    }
+
+    /// Every tenant, the default one first.
+    pub fn all(&self) -> &[AppDataRef] {
+        &self.all
+    }
+
+    pub fn get(&self, id: &str) -> Option<&AppDataRef> {
+        self.all.iter().find(|state| state.tenant.id == id)
+    }
+
+    /// The tenant named by the `X-Tenant` header or the `tenant` query
+    /// parameter, or else served on the host of the request, or else issuing
+    /// its token, the default one otherwise. A token of another tenant is
+    /// refused once authenticated.
+    fn resolve(&self, req: &Request) -> ServiceResult<&AppDataRef> {
+        let named = req
+            .headers()
+            .get(TENANT_HEADER)
+            .map(|id| id.to_str().unwrap_or_default().to_string())
+            .or_else(|| req.query::<String>("tenant"));
+        if let Some(id) = named {
+            return self
+                .get(&id)
+                .ok_or_else(|| ServiceError::NotFound(format!("Tenant {}", id)));
+        }
+        let host = req
+            .uri()
+            .host()
+            .or_else(|| req.headers().get(HOST)?.to_str().ok())
+            .map(|host| host.split(':').next().unwrap_or(host).to_lowercase());
+        if let Some(&index) = host.and_then(|host| self.hosts.get(&host)) {
+            return Ok(&self.all[index]);
+        }
+        Ok(token_tenant(req)
+            .and_then(|id| self.get(&id))
+            .unwrap_or(&self.all[0]))
+    }
+}
+
+/// The tenant in the access token of the request, or else in its refresh
+/// cookie.
+fn token_tenant(req: &Request) -> Option<String> {
+    let bearer = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(String::from)
+        .or_else(|| req.query::<String>("jwt_token"));
+    let claims = match bearer {
+        Some(token) => verify_access_token(&token).ok(),
+        None => verify_refresh_token(req.cookies().get("refresh_token")?.value()).ok(),
+    };
+    claims?.tid
+}
+
+/// Give the handlers the state of the tenant of the request, see
+/// [`Tenants::resolve`].
+#[salvo::handler]
+pub async fn resolve_tenant(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    let Ok(tenants) = depot.obtain::<Tenants>() else {
+        return;
+    };
+    match tenants.resolve(req).cloned() {
+        Ok(state) => {
+            depot.inject(state);
+        }
+        Err(e) => {
+            res.render(e);
+            ctrl.skip_rest();
+        }
+    }
+}
+
+/// Response schema for the tenant serving the request, for the frontends to
+/// present it.
+#[derive(Debug, Serialize, Deserialize, ToSchema, ToResponse)]
+#[serde(rename_all = "camelCase")]
+pub struct TenantResponse {
+    #[salvo(schema(example = "acme"))]
+    pub id: String,
+    #[salvo(schema(example = "Acme Research"))]
+    pub name: Option<String>,
+    pub logo_url: Option<String>,
+    #[salvo(schema(example = "#1a73e8"))]
+    pub primary_color: Option<String>,
+}
+
+impl Scribe for TenantResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo::{
+        Router, Service, affix_state,
+        test::{ResponseExt, TestClient},
+    };
+
+    use super::*;
+    use crate::config::TenantConfig;
+
+    #[salvo::handler]
+    async fn tenant_id(depot: &mut Depot) -> String {
+        depot
+            .obtain::<AppDataRef>()
+            .map(|state| state.tenant.id.clone())
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn test_resolve_tenant() {
+        let acme = TenantConfig {
+            id: "acme".to_string(),
+            hosts: vec!["acme.example.com".to_string()],
+            ..Default::default()
+        };
+        let tenants = Tenants {
+            all: vec![
+                AppData::for_tests().await,
+                AppData::for_tenant_tests(acme).await,
+            ],
+            hosts: HashMap::from([("acme.example.com".to_string(), 1)]),
+        };
+        let router = Router::new()
+            .hoop(affix_state::inject(tenants))
+            .hoop(resolve_tenant)
+            .goal(tenant_id);
+        let service = Service::new(router);
+
+        let mut resp = TestClient::get("http://127.0.0.1/").send(&service).await;
+        assert_eq!(resp.take_string().await.unwrap(), DEFAULT_TENANT);
+        let mut resp = TestClient::get("http://acme.example.com:8080/")
+            .send(&service)
+            .await;
+        assert_eq!(resp.take_string().await.unwrap(), "acme");
+        let mut resp = TestClient::get("http://127.0.0.1/")
+            .add_header(TENANT_HEADER, "acme", true)
+            .send(&service)
+            .await;
+        assert_eq!(resp.take_string().await.unwrap(), "acme");
+        let resp = TestClient::get("http://127.0.0.1/")
+            .add_header(TENANT_HEADER, "unknown", true)
+            .send(&service)
+            .await;
+        assert_eq!(resp.status_code, Some(salvo::http::StatusCode::NOT_FOUND));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{
+    app_data::AppDataRef,
+    digest::send_weekly_digests,
+    model::{
+        blob::BlobRepository,
+        export::{ExportRepository, export_file_key},
+        revision::PaperRevisionRepository,
+    },
+};
+
+// users become due for their digest in the morning of their timezone, checked every hour
+const DIGEST_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(3600);
+// revisions expire by the day, pruned every hour
+const REVISION_PRUNE_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(3600);
+// exports expire by the day, pruned every hour
+const EXPORT_PRUNE_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(3600);
+
+pub async fn register_timed_task(context: AppDataRef) {
+    let digest_context = context.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DIGEST_INTERVAL);
+        loop {
+            interval.tick().await;
+            send_weekly_digests(&digest_context).await;
+        }
+    });
+    let revision_context = context.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REVISION_PRUNE_INTERVAL);
+        loop {
+            interval.tick().await;
+            prune_paper_revisions(&revision_context).await;
+        }
+    });
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EXPORT_PRUNE_INTERVAL);
+        loop {
+            interval.tick().await;
+            prune_exports(&context).await;
+        }
+    });
+}
+
+/// Delete the revisions of papers replaced longer ago than the retention.
+async fn prune_paper_revisions(context: &AppDataRef) {
+    let retention_days = context.revision_config.retention_days;
+    if retention_days == 0 {
+        return;
+    }
+    let retention = std::time::Duration::from_secs(retention_days * 24 * 3600);
+    let before = bson::DateTime::from_system_time(std::time::SystemTime::now() - retention);
+    match context.db.delete_expired_paper_revisions(before).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("Pruned {} expired paper revisions", count),
+        Err(e) => tracing::error!("Failed to prune paper revisions: {}", e),
+    }
+}
+
+/// Delete the exports started longer ago than the retention, archive first so
+/// a failure leaves the export to retry.
+async fn prune_exports(context: &AppDataRef) {
+    let retention =
+        std::time::Duration::from_secs(context.export_config.retention_days * 24 * 3600);
+    let before = bson::DateTime::from_system_time(std::time::SystemTime::now() - retention);
+    let jobs = match context.db.get_expired_exports(before).await {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            tracing::error!("Failed to list expired exports: {}", e);
+            return;
+        }
+    };
+    let mut count = 0;
+    for job in jobs {
+        let deleted = async {
+            context.db.delete_blob(&export_file_key(&job.id)).await?;
+            context.db.delete_export(&job.id).await
+        };
+        match deleted.await {
+            Ok(()) => count += 1,
+            Err(e) => tracing::error!("Failed to delete expired export {}: {}", job.id, e),
+        }
+    }
+    if count > 0 {
+        tracing::info!("Pruned {} expired exports", count);
+    }
+}
@@ -0,0 +1,45 @@
+use futures::{Stream, StreamExt, stream};
+use salvo::conn::rustls::{Keycert, RustlsConfig};
+
+use crate::config::TlsConfig;
+
+// pem encoded certificate chain and private key
+type Pem = (Vec<u8>, Vec<u8>);
+
+async fn read_pem(config: &TlsConfig) -> anyhow::Result<Pem> {
+    let cert = tokio::fs::read(&config.cert_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", config.cert_path, e))?;
+    let key = tokio::fs::read(&config.key_path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", config.key_path, e))?;
+    Ok((cert, key))
+}
+
+fn rustls_config((cert, key): &Pem) -> RustlsConfig {
+    RustlsConfig::new(Keycert::new().cert(cert.clone()).key(key.clone()))
+}
+
+/// The tls configs of the listener: the certificate at startup, then again each
+/// time its files change, so a renewed certificate is served without a restart.
+/// The listener negotiates http/2 over them.
+pub async fn rustls_configs(
+    config: TlsConfig,
+) -> anyhow::Result<impl Stream<Item = RustlsConfig> + Send + 'static> {
+    let pem = read_pem(&config).await?;
+    let first = rustls_config(&pem);
+    let reloads = stream::unfold((config, pem), |(config, current)| async move {
+        loop {
+            tokio::time::sleep(config.reload_interval()).await;
+            match read_pem(&config).await {
+                Ok(pem) if pem != current => {
+                    tracing::info!("Reloaded tls certificate from {}", config.cert_path);
+                    return Some((rustls_config(&pem), (config, pem)));
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Failed to reload tls certificate: {}", e),
+            }
+        }
+    });
+    Ok(stream::once(async move { first }).chain(reloads))
+}
@@ -0,0 +1,164 @@
+use salvo::{
+    Depot, FlowCtrl, Request, Response, handler, http::header::HeaderValue, oapi::OpenApi,
+};
+use serde_json::Value;
+
+use crate::utils::time::{self, TimestampFormat};
+
+/// Response header naming the version of the api answering the request.
+pub const API_VERSION_HEADER: &str = "api-version";
+
+/// A version of the api, mounted under `/api/{version}` with its own OpenAPI
+/// document. A breaking change of the schemas, e.g. of the error payload, ships
+/// in a new version while the older ones keep answering as before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    /// Timestamps in milliseconds since epoch.
+    V1,
+    /// Timestamps as RFC 3339 strings in UTC.
+    V2,
+}
+
+impl ApiVersion {
+    /// Every version served, the latest last.
+    pub const ALL: &'static [ApiVersion] = &[ApiVersion::V1, ApiVersion::V2];
+    /// The version the swagger ui documents.
+    pub const LATEST: ApiVersion = ApiVersion::V2;
+
+    pub fn path(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "v1",
+            ApiVersion::V2 => "v2",
+        }
+    }
+
+    pub fn timestamp_format(self) -> TimestampFormat {
+        match self {
+            ApiVersion::V1 => TimestampFormat::Millis,
+            ApiVersion::V2 => TimestampFormat::Rfc3339,
+        }
+    }
+
+    /// The OpenAPI document of the routes of the version. The schemas describe
+    /// the timestamps as RFC 3339 strings, the versions writing milliseconds
+    /// document them as integers.
+    pub fn document(self, doc: OpenApi) -> OpenApi {
+        if self.timestamp_format() == TimestampFormat::Rfc3339 {
+            return doc;
+        }
+        let mut value = match serde_json::to_value(&doc) {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::error!("Failed to serialize the {} api doc: {}", self.path(), e);
+                return doc;
+            }
+        };
+        millis_timestamps(&mut value);
+        match serde_json::from_value(value) {
+            Ok(versioned) => versioned,
+            Err(e) => {
+                tracing::error!("Failed to document the {} timestamps: {}", self.path(), e);
+                doc
+            }
+        }
+    }
+
+    /// The version answering the response, the first one outside of the api.
+    pub fn of_response(res: &Response) -> Self {
+        let version = res
+            .headers()
+            .get(API_VERSION_HEADER)
+            .and_then(|value| value.to_str().ok());
+        ApiVersion::ALL
+            .iter()
+            .copied()
+            .find(|v| Some(v.path()) == version)
+            .unwrap_or(ApiVersion::V1)
+    }
+}
+
+/// The `date-time` strings of the schema, nested or not, as `int64` integers.
+fn millis_timestamps(schema: &mut Value) {
+    match schema {
+        Value::Object(fields) => {
+            if fields.get("format").and_then(Value::as_str) == Some("date-time") {
+                match fields.get_mut("type") {
+                    // e.g. `["string", "null"]` for an optional one
+                    Some(Value::Array(types)) => {
+                        for kind in types
+                            .iter_mut()
+                            .filter(|kind| kind.as_str() == Some("string"))
+                        {
+                            *kind = Value::from("integer");
+                        }
+                    }
+                    _ => {
+                        fields.insert("type".to_string(), Value::from("integer"));
+                    }
+                }
+                fields.insert("format".to_string(), Value::from("int64"));
+            }
+            fields.values_mut().for_each(millis_timestamps);
+        }
+        Value::Array(schemas) => schemas.iter_mut().for_each(millis_timestamps),
+        _ => {}
+    }
+}
+
+/// Hoop of the routes of the version, injecting it into the depot and naming
+/// it on the response. The rest of the routes write the timestamps of the
+/// version.
+#[handler]
+impl ApiVersion {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        depot.inject(*self);
+        res.headers_mut()
+            .insert(API_VERSION_HEADER, HeaderValue::from_static(self.path()));
+        time::with_format(self.timestamp_format(), ctrl.call_next(req, depot, res)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_of_response() {
+        let mut res = Response::new();
+        assert_eq!(ApiVersion::of_response(&res), ApiVersion::V1);
+        res.headers_mut()
+            .insert(API_VERSION_HEADER, HeaderValue::from_static("v1"));
+        assert_eq!(ApiVersion::of_response(&res), ApiVersion::V1);
+        res.headers_mut()
+            .insert(API_VERSION_HEADER, HeaderValue::from_static("v2"));
+        assert_eq!(ApiVersion::of_response(&res), ApiVersion::V2);
+    }
+
+    #[test]
+    fn test_millis_timestamps() {
+        let mut schema = serde_json::json!({
+            "properties": {
+                "createdAt": { "type": "string", "format": "date-time" },
+                "dueAt": { "type": ["string", "null"], "format": "date-time" },
+                "title": { "type": "string" },
+            }
+        });
+        millis_timestamps(&mut schema);
+        assert_eq!(
+            schema,
+            serde_json::json!({
+                "properties": {
+                    "createdAt": { "type": "integer", "format": "int64" },
+                    "dueAt": { "type": ["integer", "null"], "format": "int64" },
+                    "title": { "type": "string" },
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use salvo::{
+    Depot, FlowCtrl, Request, Response,
+    http::header::{CONTENT_LENGTH, CONTENT_TYPE},
+};
+
+use crate::{app_data::AppDataRef, config::BodyLimitConfig, error::ServiceError};
+
+/// The limit of the body of the request: json and forms are small, any other
+/// content type is an uploaded file.
+fn body_limit(config: &BodyLimitConfig, content_type: Option<&str>) -> u64 {
+    let mime = content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .unwrap_or_default()
+        .trim();
+    match mime {
+        "" | "application/x-www-form-urlencoded" | "multipart/form-data" => config.json_bytes,
+        mime if mime == "application/json" || mime.ends_with("+json") => config.json_bytes,
+        _ => config.upload_bytes,
+    }
+}
+
+/// Answer a 413 to a request announcing a body over its limit, and bound the
+/// reading of bodies sent without a length.
+#[salvo::handler]
+pub async fn limit_body(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    let Ok(state) = depot.obtain::<AppDataRef>() else {
+        return;
+    };
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    let limit = body_limit(&state.body_limit_config, content_type);
+    let length = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if length.is_some_and(|length| length > limit) {
+        res.render(ServiceError::PayloadTooLarge(limit));
+        ctrl.skip_rest();
+        return;
+    }
+    req.set_secure_max_size(limit as usize);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_limit() {
+        let config = BodyLimitConfig {
+            json_bytes: 1,
+            upload_bytes: 2,
+        };
+        assert_eq!(body_limit(&config, None), 1);
+        assert_eq!(
+            body_limit(&config, Some("application/json; charset=utf-8")),
+            1
+        );
+        assert_eq!(body_limit(&config, Some("application/merge-patch+json")), 1);
+        assert_eq!(body_limit(&config, Some("application/pdf")), 2);
+        assert_eq!(body_limit(&config, Some("application/octet-stream")), 2);
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{config::CacheConfig, error::ServiceResult};
+
+/// In-memory cache whose entries expire after a fixed time to live.
+#[derive(Debug)]
+pub struct TtlCache<V> {
+    ttl: Duration,
+    // the oldest entry is evicted beyond
+    capacity: usize,
+    entries: Mutex<HashMap<String, (Instant, V)>>,
+}
+
+impl<V: Clone> TtlCache<V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_capacity(ttl, usize::MAX)
+    }
+
+    /// A cache holding at most `capacity` entries, evicting the oldest.
+    pub fn with_capacity(ttl: Duration, capacity: usize) -> Self {
+        TtlCache {
+            ttl,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(key) {
+            Some((inserted_at, value)) if inserted_at.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: &str, value: V) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        // drop expired entries so keys of inactive users do not pile up
+        let ttl = self.ttl;
+        entries.retain(|_, (inserted_at, _)| inserted_at.elapsed() < ttl);
+        if entries.len() >= self.capacity && !entries.contains_key(key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (inserted_at, _))| *inserted_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key.to_string(), (Instant::now(), value));
+    }
+
+    pub fn remove(&self, key: &str) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.remove(key);
+    }
+}
+
+/// Hot documents kept out of MongoDB, invalidated on writes.
+#[derive(Debug, Clone, Copy)]
+pub enum CacheKey<'a> {
+    // user by uid, read on every authenticated request
+    User(&'a str),
+    // all folders of a user
+    Folders(&'a str),
+    // paper by id
+    Paper(&'a str),
+}
+
+impl fmt::Display for CacheKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheKey::User(uid) => write!(f, "user:{}", uid),
+            CacheKey::Folders(user_id) => write!(f, "folders:{}", user_id),
+            CacheKey::Paper(id) => write!(f, "paper:{}", id),
+        }
+    }
+}
+
+/// Cache of serialized documents. Failures are logged and behave as misses, the
+/// database stays the source of truth.
+#[async_trait::async_trait]
+pub trait Cache: Send + Sync + fmt::Debug {
+    async fn get(&self, key: &str) -> Option<Vec<u8>>;
+    async fn set(&self, key: &str, value: Vec<u8>);
+    async fn delete(&self, key: &str);
+}
+
+pub async fn create_cache(config: &CacheConfig) -> ServiceResult<Arc<dyn Cache>> {
+    let ttl = Duration::from_secs(config.ttl_secs);
+    let memory = || Arc::new(MemoryCache::new(ttl, config.max_entries));
+    match &config.redis_url {
+        #[cfg(feature = "redis")]
+        Some(url) => match RedisCache::new(url, ttl).await {
+            Ok(cache) => Ok(Arc::new(cache)),
+            Err(e) => {
+                tracing::error!("Failed to connect to redis, caching in memory: {}", e);
+                Ok(memory())
+            }
+        },
+        #[cfg(not(feature = "redis"))]
+        Some(_) => Err(crate::error::ServiceError::InternalServerError(
+            "`cache_config.redis_url` needs the `redis` feature".to_string(),
+        )),
+        None => Ok(memory()),
+    }
+}
+
+/// Read the document from the cache, stored as bson to keep the dates as they are.
+pub async fn get_cached<T: DeserializeOwned>(cache: &dyn Cache, key: CacheKey<'_>) -> Option<T> {
+    let bytes = cache.get(&key.to_string()).await?;
+    match bson::from_slice(&bytes) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            tracing::error!("Failed to decode cached {}: {}", key, e);
+            None
+        }
+    }
+}
+
+pub async fn set_cached<T: Serialize>(cache: &dyn Cache, key: CacheKey<'_>, value: &T) {
+    match bson::to_vec(value) {
+        Ok(bytes) => cache.set(&key.to_string(), bytes).await,
+        Err(e) => tracing::error!("Failed to encode {} for the cache: {}", key, e),
+    }
+}
+
+/// In process cache, for a single instance, evicting the oldest documents
+/// beyond `max_entries`.
+#[derive(Debug)]
+pub struct MemoryCache {
+    entries: TtlCache<Vec<u8>>,
+}
+
+impl MemoryCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        MemoryCache {
+            entries: TtlCache::with_capacity(ttl, max_entries),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Cache for MemoryCache {
+    async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.entries.get(key)
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>) {
+        self.entries.insert(key, value);
+    }
+
+    async fn delete(&self, key: &str) {
+        self.entries.remove(key);
+    }
+}
+
+/// Cache shared by every instance, so writes on one invalidate reads on all.
+#[cfg(feature = "redis")]
+pub struct RedisCache {
+    connection: redis::aio::ConnectionManager,
+    ttl: Duration,
+}
+
+#[cfg(feature = "redis")]
+impl fmt::Debug for RedisCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisCache")
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+#[cfg(feature = "redis")]
+impl RedisCache {
+    pub async fn new(url: &str, ttl: Duration) -> redis::RedisResult<Self> {
+        let client = redis::Client::open(url)?;
+        let connection = redis::aio::ConnectionManager::new(client).await?;
+        Ok(RedisCache { connection, ttl })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait::async_trait]
+impl Cache for RedisCache {
+    async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let mut connection = self.connection.clone();
+        redis::AsyncCommands::get::<_, Option<Vec<u8>>>(&mut connection, format!("cache:{}", key))
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("Failed to read {} from redis: {}", key, e);
+                None
+            })
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>) {
+        let mut connection = self.connection.clone();
+        let result = redis::AsyncCommands::set_ex::<_, _, ()>(
+            &mut connection,
+            format!("cache:{}", key),
+            value,
+            self.ttl.as_secs(),
+        )
+        .await;
+        if let Err(e) = result {
+            tracing::error!("Failed to write {} to redis: {}", key, e);
+        }
+    }
+
+    async fn delete(&self, key: &str) {
+        let mut connection = self.connection.clone();
+        let result =
+            redis::AsyncCommands::del::<_, ()>(&mut connection, format!("cache:{}", key)).await;
+        if let Err(e) = result {
+            tracing::error!("Failed to delete {} from redis: {}", key, e);
+        }
+    }
+}
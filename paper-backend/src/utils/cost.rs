@@ -0,0 +1,82 @@
+/// Price of a model in USD per million tokens.
+#[derive(Debug, Clone, Copy)]
+pub struct ModelPrice {
+    pub model: &'static str,
+    pub input: f64,
+    pub output: f64,
+}
+
+// list prices of the supported models, keep in sync with the providers
+pub const MODEL_PRICES: &[ModelPrice] = &[
+    ModelPrice {
+        model: "deepseek-chat",
+        input: 0.27,
+        output: 1.10,
+    },
+    ModelPrice {
+        model: "deepseek-reasoner",
+        input: 0.55,
+        output: 2.19,
+    },
+    ModelPrice {
+        model: "gpt-4o-mini",
+        input: 0.15,
+        output: 0.60,
+    },
+    ModelPrice {
+        model: "gpt-4o",
+        input: 2.50,
+        output: 10.00,
+    },
+    ModelPrice {
+        model: "gpt-4.1-mini",
+        input: 0.40,
+        output: 1.60,
+    },
+    ModelPrice {
+        model: "gpt-4.1",
+        input: 2.00,
+        output: 8.00,
+    },
+];
+
+// tokens added by the chat format for each message
+const MESSAGE_OVERHEAD_TOKENS: u64 = 4;
+
+pub fn model_price(model: &str) -> Option<ModelPrice> {
+    MODEL_PRICES.iter().find(|p| p.model == model).copied()
+}
+
+impl ModelPrice {
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input + output_tokens as f64 * self.output) / 1_000_000.0
+    }
+}
+
+/// Rough token count of a chat message without running the tokenizer:
+/// about 4 ascii characters per token, one token per other (e.g. CJK) character.
+pub fn estimate_tokens(text: &str) -> u64 {
+    let (ascii, other) = text.chars().fold((0u64, 0u64), |(ascii, other), c| {
+        if c.is_ascii() {
+            (ascii + 1, other)
+        } else {
+            (ascii, other + 1)
+        }
+    });
+    ascii.div_ceil(4) + other + MESSAGE_OVERHEAD_TOKENS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), MESSAGE_OVERHEAD_TOKENS);
+        assert_eq!(estimate_tokens("abcdefgh"), 2 + MESSAGE_OVERHEAD_TOKENS);
+        assert_eq!(estimate_tokens("论文ab"), 3 + MESSAGE_OVERHEAD_TOKENS);
+
+        let price = model_price("gpt-4o").unwrap();
+        assert!((price.cost(1_000_000, 0) - 2.5).abs() < 1e-9);
+    }
+}
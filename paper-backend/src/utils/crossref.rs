@@ -0,0 +1,114 @@
+use std::{sync::Arc, time::Duration};
+
+use serde::Deserialize;
+
+use crate::{
+    config::CircuitBreakerConfig,
+    error::{ServiceError, ServiceResult},
+    pdf::references::ParsedReference,
+    resilience::breaker::CircuitBreaker,
+    utils::cache::TtlCache,
+};
+
+const CROSSREF_API: &str = "https://api.crossref.org";
+// the deposited references of a work seldom change
+const REFERENCES_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Client of the public Crossref api, used for the metadata of papers with a DOI.
+#[derive(Debug, Clone)]
+pub struct CrossrefClient {
+    client: reqwest::Client,
+    breaker: Arc<CircuitBreaker>,
+    // by DOI, served without calling Crossref, during its outages too
+    references: Arc<TtlCache<Vec<ParsedReference>>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkResponse {
+    message: Work,
+}
+
+#[derive(Debug, Deserialize)]
+struct Work {
+    #[serde(default)]
+    reference: Vec<WorkReference>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkReference {
+    #[serde(rename = "DOI")]
+    doi: Option<String>,
+    unstructured: Option<String>,
+    #[serde(rename = "article-title")]
+    article_title: Option<String>,
+    author: Option<String>,
+    year: Option<String>,
+}
+
+impl Default for CrossrefClient {
+    fn default() -> Self {
+        Self::new(&CircuitBreakerConfig::default())
+    }
+}
+
+impl CrossrefClient {
+    pub fn new(breaker_config: &CircuitBreakerConfig) -> Self {
+        CrossrefClient {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(20))
+                .user_agent(concat!("paper-backend/", env!("CARGO_PKG_VERSION")))
+                .build()
+                .unwrap_or_default(),
+            breaker: Arc::new(CircuitBreaker::new("Crossref", breaker_config)),
+            references: Arc::new(TtlCache::new(REFERENCES_CACHE_TTL)),
+        }
+    }
+
+    /// References deposited with the work, empty when the work is unknown.
+    /// A 503 while Crossref is failing, unless they were fetched recently.
+    pub async fn fetch_references(&self, doi: &str) -> ServiceResult<Vec<ParsedReference>> {
+        let key = doi.to_lowercase();
+        if let Some(references) = self.references.get(&key) {
+            return Ok(references);
+        }
+        let references = self.breaker.call(self.request_references(doi)).await?;
+        self.references.insert(&key, references.clone());
+        Ok(references)
+    }
+
+    async fn request_references(&self, doi: &str) -> ServiceResult<Vec<ParsedReference>> {
+        let response = self
+            .client
+            .get(format!("{}/works/{}", CROSSREF_API, doi))
+            .send()
+            .await
+            .map_err(|e| ServiceError::UpstreamError(e.to_string()))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        let work = response
+            .error_for_status()
+            .map_err(|e| ServiceError::UpstreamError(e.to_string()))?
+            .json::<WorkResponse>()
+            .await
+            .map_err(|e| ServiceError::UpstreamError(e.to_string()))?;
+
+        let references = work
+            .message
+            .reference
+            .into_iter()
+            .filter_map(|reference| {
+                let raw = reference.unstructured.or_else(|| {
+                    let parts = [reference.author, reference.article_title, reference.year];
+                    let parts = parts.into_iter().flatten().collect::<Vec<_>>();
+                    (!parts.is_empty()).then(|| parts.join(". "))
+                })?;
+                Some(ParsedReference {
+                    raw,
+                    doi: reference.doi.map(|doi| doi.to_lowercase()),
+                })
+            })
+            .collect();
+        Ok(references)
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce, aead::Aead};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::EncryptionConfig,
+    error::{ServiceError, ServiceResult},
+};
+
+const KEY_BYTES: usize = 32;
+const NONCE_BYTES: usize = 12;
+// sealed strings are `enc:{key id}:{base64 of the nonce and ciphertext}`
+const SEALED_PREFIX: &str = "enc:";
+// sealed bytes are the marker, the key id and `:`, then the nonce and ciphertext
+const SEALED_MARKER: &[u8] = b"\0enc:";
+
+/// Seals the sensitive fields stored for the users, e.g. their llm api keys,
+/// with AES-256-GCM. Every value records the id of the key sealing it: the
+/// current key seals, the keys retired by a rotation still open what was sealed
+/// under them until it is sealed again, see
+/// [`reencrypt_fields`](crate::migrations::reencrypt_fields).
+pub struct Cipher {
+    key_id: String,
+    keys: HashMap<String, Aes256Gcm>,
+    // the editing state of the collaborative notes is sealed too
+    pub encrypt_notes: bool,
+}
+
+impl std::fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cipher")
+            .field("key_id", &self.key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The key from the base64 of 32 bytes, e.g. `openssl rand -base64 32`.
+fn parse_key(id: &str, key: &str) -> anyhow::Result<Aes256Gcm> {
+    let key = STANDARD.decode(key.trim())?;
+    if key.len() != KEY_BYTES {
+        anyhow::bail!("The encryption key {} must be {} bytes", id, KEY_BYTES);
+    }
+    Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow::anyhow!("Invalid encryption key {}", id))
+}
+
+fn failed() -> ServiceError {
+    ServiceError::InternalServerError("Decryption failed".to_string())
+}
+
+impl Cipher {
+    pub fn new(config: &EncryptionConfig) -> anyhow::Result<Self> {
+        if config.key_id.is_empty() || config.key_id.contains(':') {
+            anyhow::bail!("Invalid encryption key id {}", config.key_id);
+        }
+        let mut keys = HashMap::new();
+        for (id, key) in &config.previous_keys {
+            keys.insert(id.clone(), parse_key(id, key)?);
+        }
+        keys.insert(
+            config.key_id.clone(),
+            parse_key(&config.key_id, &config.master_key)?,
+        );
+        Ok(Cipher {
+            key_id: config.key_id.clone(),
+            keys,
+            encrypt_notes: config.encrypt_notes,
+        })
+    }
+
+    fn seal(&self, plain: &[u8]) -> ServiceResult<Vec<u8>> {
+        let nonce: [u8; NONCE_BYTES] = rand::random();
+        let sealed = self.keys[&self.key_id]
+            .encrypt(Nonce::from_slice(&nonce), plain)
+            .map_err(|_| ServiceError::InternalServerError("Encryption failed".to_string()))?;
+        Ok([nonce.as_slice(), &sealed].concat())
+    }
+
+    fn open(&self, key_id: &str, sealed: &[u8]) -> ServiceResult<Vec<u8>> {
+        let key = self.keys.get(key_id).ok_or_else(|| {
+            ServiceError::InternalServerError(format!("Unknown encryption key {}", key_id))
+        })?;
+        open_with(key, sealed)
+    }
+
+    /// A secret sealed before the values recorded their key, the base64 of the
+    /// nonce and ciphertext only, under the master key of the time, retired
+    /// since or not. None when no key opens it.
+    fn open_untagged(&self, value: &str) -> Option<String> {
+        let sealed = STANDARD.decode(value).ok()?;
+        let plain = self
+            .keys
+            .values()
+            .find_map(|key| open_with(key, &sealed).ok())?;
+        String::from_utf8(plain).ok()
+    }
+
+    /// The secret sealed under the current key.
+    pub fn encrypt(&self, secret: &str) -> ServiceResult<String> {
+        let sealed = self.seal(secret.as_bytes())?;
+        Ok(format!(
+            "{}{}:{}",
+            SEALED_PREFIX,
+            self.key_id,
+            STANDARD.encode(sealed)
+        ))
+    }
+
+    /// The secret sealed by [`Cipher::encrypt`], a value stored before it was
+    /// sealed as is. Fails when sealed under an unknown key or altered since.
+    pub fn decrypt(&self, value: &str) -> ServiceResult<String> {
+        let Some((key_id, sealed)) = sealed_str(value) else {
+            return Ok(value.to_string());
+        };
+        let sealed = STANDARD.decode(sealed).map_err(|_| failed())?;
+        String::from_utf8(self.open(key_id, &sealed)?).map_err(|_| failed())
+    }
+
+    /// A secret never stored in clear, e.g. a llm api key: sealed by
+    /// [`Cipher::encrypt`], or by the first versions without the key id.
+    pub fn decrypt_sealed(&self, value: &str) -> ServiceResult<String> {
+        if is_sealed(value) {
+            return self.decrypt(value);
+        }
+        self.open_untagged(value).ok_or_else(failed)
+    }
+
+    /// The bytes sealed under the current key.
+    pub fn encrypt_bytes(&self, bytes: &[u8]) -> ServiceResult<Vec<u8>> {
+        let sealed = self.seal(bytes)?;
+        Ok([SEALED_MARKER, self.key_id.as_bytes(), b":", &sealed].concat())
+    }
+
+    /// The bytes sealed by [`Cipher::encrypt_bytes`], as is when not sealed.
+    pub fn decrypt_bytes(&self, bytes: &[u8]) -> ServiceResult<Vec<u8>> {
+        match sealed_bytes(bytes) {
+            Some((key_id, sealed)) => self.open(key_id, sealed),
+            None => Ok(bytes.to_vec()),
+        }
+    }
+
+    /// Whether the value is sealed under the current key already.
+    pub fn is_current(&self, value: &str) -> bool {
+        sealed_str(value).is_some_and(|(key_id, _)| key_id == self.key_id)
+    }
+
+    /// Whether the bytes are sealed under the current key already.
+    pub fn is_current_bytes(&self, bytes: &[u8]) -> bool {
+        sealed_bytes(bytes).is_some_and(|(key_id, _)| key_id == self.key_id)
+    }
+}
+
+/// The key id and the sealed part of a sealed string.
+fn sealed_str(value: &str) -> Option<(&str, &str)> {
+    value.strip_prefix(SEALED_PREFIX)?.split_once(':')
+}
+
+/// The key id and the sealed part of sealed bytes.
+fn sealed_bytes(bytes: &[u8]) -> Option<(&str, &[u8])> {
+    let rest = bytes.strip_prefix(SEALED_MARKER)?;
+    let end = rest.iter().position(|&b| b == b':')?;
+    let key_id = std::str::from_utf8(&rest[..end]).ok()?;
+    Some((key_id, &rest[end + 1..]))
+}
+
+/// The nonce then the ciphertext, opened with the key.
+fn open_with(key: &Aes256Gcm, sealed: &[u8]) -> ServiceResult<Vec<u8>> {
+    if sealed.len() < NONCE_BYTES {
+        return Err(failed());
+    }
+    let (nonce, sealed) = sealed.split_at(NONCE_BYTES);
+    key.decrypt(Nonce::from_slice(nonce), sealed)
+        .map_err(|_| failed())
+}
+
+/// Whether the value was sealed by a [`Cipher`], which is needed to open it.
+pub fn is_sealed(value: &str) -> bool {
+    sealed_str(value).is_some()
+}
+
+/// Whether the bytes were sealed by a [`Cipher`], which is needed to open them.
+pub fn is_sealed_bytes(bytes: &[u8]) -> bool {
+    sealed_bytes(bytes).is_some()
+}
+
+/// The key derived from a secret only the client holds, e.g. an idempotency key.
+fn secret_key(secret: &str) -> Aes256Gcm {
+    Aes256Gcm::new_from_slice(&Sha256::digest(secret.as_bytes()))
+        .expect("A sha256 digest is a valid key")
+}
+
+/// The base64 of the bytes sealed under a key derived from the secret, so what
+/// is stored cannot be opened without the secret, which is not stored.
+pub fn seal_with_secret(secret: &str, plain: &[u8]) -> ServiceResult<String> {
+    let nonce: [u8; NONCE_BYTES] = rand::random();
+    let sealed = secret_key(secret)
+        .encrypt(Nonce::from_slice(&nonce), plain)
+        .map_err(|_| ServiceError::InternalServerError("Encryption failed".to_string()))?;
+    Ok(STANDARD.encode([nonce.as_slice(), &sealed].concat()))
+}
+
+/// The bytes sealed by [`seal_with_secret`] with the same secret.
+pub fn open_with_secret(secret: &str, sealed: &str) -> ServiceResult<Vec<u8>> {
+    let sealed = STANDARD.decode(sealed).map_err(|_| failed())?;
+    open_with(&secret_key(secret), &sealed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(key_id: &str, key: u8, previous: &[(&str, u8)]) -> EncryptionConfig {
+        EncryptionConfig {
+            key_id: key_id.to_string(),
+            master_key: STANDARD.encode([key; KEY_BYTES]),
+            previous_keys: previous
+                .iter()
+                .map(|(id, key)| (id.to_string(), STANDARD.encode([*key; KEY_BYTES])))
+                .collect(),
+            encrypt_notes: false,
+        }
+    }
+
+    #[test]
+    fn test_cipher_roundtrip() {
+        let cipher = Cipher::new(&config("1", 7, &[])).unwrap();
+        let sealed = cipher.encrypt("sk-secret").unwrap();
+        assert!(sealed.starts_with("enc:1:"));
+        assert_ne!(sealed, cipher.encrypt("sk-secret").unwrap());
+        assert_eq!(cipher.decrypt(&sealed).unwrap(), "sk-secret");
+        // stored before encryption
+        assert_eq!(cipher.decrypt("whsec_plain").unwrap(), "whsec_plain");
+
+        let bytes = cipher.encrypt_bytes(&[0, 1, 2]).unwrap();
+        assert!(cipher.is_current_bytes(&bytes));
+        assert_eq!(cipher.decrypt_bytes(&bytes).unwrap(), vec![0, 1, 2]);
+        assert_eq!(cipher.decrypt_bytes(&[0, 1, 2]).unwrap(), vec![0, 1, 2]);
+
+        let mut config = config("1", 8, &[]);
+        assert!(Cipher::new(&config).unwrap().decrypt(&sealed).is_err());
+        config.master_key = STANDARD.encode([7u8; 16]);
+        assert!(Cipher::new(&config).is_err());
+    }
+
+    #[test]
+    fn test_cipher_rotation() {
+        let old = Cipher::new(&config("1", 7, &[])).unwrap();
+        let sealed = old.encrypt("sk-secret").unwrap();
+
+        let rotated = Cipher::new(&config("2", 8, &[("1", 7)])).unwrap();
+        assert!(!rotated.is_current(&sealed));
+        assert_eq!(rotated.decrypt(&sealed).unwrap(), "sk-secret");
+        let resealed = rotated.encrypt("sk-secret").unwrap();
+        assert!(rotated.is_current(&resealed));
+        assert!(old.decrypt(&resealed).is_err());
+    }
+
+    #[test]
+    fn test_cipher_untagged() {
+        // sealed before the values recorded their key
+        let first = Cipher::new(&config("1", 7, &[])).unwrap();
+        let untagged = STANDARD.encode(first.seal(b"sk-secret").unwrap());
+        assert!(!first.is_current(&untagged));
+        assert_eq!(first.decrypt_sealed(&untagged).unwrap(), "sk-secret");
+        let sealed = first.encrypt("sk-secret").unwrap();
+        assert_eq!(first.decrypt_sealed(&sealed).unwrap(), "sk-secret");
+
+        let rotated = Cipher::new(&config("2", 8, &[("1", 7)])).unwrap();
+        assert_eq!(rotated.decrypt_sealed(&untagged).unwrap(), "sk-secret");
+        let other = Cipher::new(&config("2", 8, &[])).unwrap();
+        assert!(other.decrypt_sealed(&untagged).is_err());
+        assert!(other.decrypt_sealed("sk-secret").is_err());
+    }
+
+    #[test]
+    fn test_seal_with_secret() {
+        let sealed = seal_with_secret("user:key", b"{\"token\":\"t\"}").unwrap();
+        assert!(!sealed.contains("token"));
+        assert_eq!(
+            open_with_secret("user:key", &sealed).unwrap(),
+            b"{\"token\":\"t\"}"
+        );
+        assert!(open_with_secret("user:other", &sealed).is_err());
+        assert!(open_with_secret("user:key", "c2hvcnQ=").is_err());
+    }
+}
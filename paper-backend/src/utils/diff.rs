@@ -0,0 +1,96 @@
+pub use paper_schema::revision::{DiffLine, DiffOp};
+
+// lines compared one by one, longer texts are shown as replaced whole
+const MAX_DIFF_LINES: usize = 2000;
+
+fn diff_line(op: DiffOp, text: &str) -> DiffLine {
+    DiffLine {
+        op,
+        text: text.to_string(),
+    }
+}
+
+/// The lines of the old text removed and the ones of the new text added, in
+/// order, from the longest common subsequence of the lines of both.
+pub fn line_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+    // the common head and tail are kept as they are
+    let head = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let tail = old[head..]
+        .iter()
+        .rev()
+        .zip(new[head..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (&old[head..old.len() - tail], &new[head..new.len() - tail]);
+
+    let mut diff = old[..head]
+        .iter()
+        .map(|line| diff_line(DiffOp::Equal, line))
+        .collect::<Vec<_>>();
+    if a.len().max(b.len()) > MAX_DIFF_LINES {
+        diff.extend(a.iter().map(|line| diff_line(DiffOp::Delete, line)));
+        diff.extend(b.iter().map(|line| diff_line(DiffOp::Insert, line)));
+    } else {
+        // lcs[i][j]: length of the common subsequence of a[i..] and b[j..]
+        let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = match a[i] == b[j] {
+                    true => lcs[i + 1][j + 1] + 1,
+                    false => lcs[i + 1][j].max(lcs[i][j + 1]),
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                diff.push(diff_line(DiffOp::Equal, a[i]));
+                (i, j) = (i + 1, j + 1);
+            } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                diff.push(diff_line(DiffOp::Delete, a[i]));
+                i += 1;
+            } else {
+                diff.push(diff_line(DiffOp::Insert, b[j]));
+                j += 1;
+            }
+        }
+    }
+    diff.extend(
+        old[old.len() - tail..]
+            .iter()
+            .map(|line| diff_line(DiffOp::Equal, line)),
+    );
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_diff() {
+        let diff = line_diff("a\nb\nc\nd", "a\nc\nx\nd");
+        let ops = diff
+            .iter()
+            .map(|line| (line.op, line.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ops,
+            vec![
+                (DiffOp::Equal, "a"),
+                (DiffOp::Delete, "b"),
+                (DiffOp::Equal, "c"),
+                (DiffOp::Insert, "x"),
+                (DiffOp::Equal, "d"),
+            ]
+        );
+        assert!(
+            line_diff("same", "same")
+                .iter()
+                .all(|l| l.op == DiffOp::Equal)
+        );
+        assert_eq!(line_diff("", "new")[0], diff_line(DiffOp::Insert, "new"));
+    }
+}
@@ -0,0 +1,100 @@
+use salvo::{
+    Request, Response,
+    http::{
+        StatusCode,
+        header::{ETAG, HeaderName, HeaderValue, IF_MATCH, IF_NONE_MATCH},
+    },
+};
+use sha2::{Digest, Sha256};
+
+use crate::error::{ServiceError, ServiceResult};
+
+/// Weak entity tag of a document, changes with every update.
+pub fn weak_etag(updated_at: bson::DateTime, version: u32) -> String {
+    format!("W/\"{}-{}\"", updated_at.timestamp_millis(), version)
+}
+
+/// Weak entity tag of a list, changes when a document is added, removed or updated.
+pub fn list_etag<'a, I>(items: I) -> String
+where
+    I: IntoIterator<Item = (&'a str, bson::DateTime, u32)>,
+{
+    let mut hasher = Sha256::new();
+    for (id, updated_at, version) in items {
+        hasher.update(id.as_bytes());
+        hasher.update(updated_at.timestamp_millis().to_be_bytes());
+        hasher.update(version.to_be_bytes());
+    }
+    digest_etag(hasher)
+}
+
+/// Like `list_etag`, also changing with the counts given with the documents,
+/// e.g. the papers of the folders.
+pub fn counted_list_etag<'a, I>(items: I) -> String
+where
+    I: IntoIterator<Item = (&'a str, bson::DateTime, u32, u64)>,
+{
+    let mut hasher = Sha256::new();
+    for (id, updated_at, version, count) in items {
+        hasher.update(id.as_bytes());
+        hasher.update(updated_at.timestamp_millis().to_be_bytes());
+        hasher.update(version.to_be_bytes());
+        hasher.update(count.to_be_bytes());
+    }
+    digest_etag(hasher)
+}
+
+fn digest_etag(hasher: Sha256) -> String {
+    let digest = hasher
+        .finalize()
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!("W/\"{}\"", digest)
+}
+
+/// Whether the header lists the tag, compared weakly, or is `*`.
+fn header_matches(req: &Request, name: HeaderName, etag: &str) -> bool {
+    let Some(header) = req.headers().get(name).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    header
+        .split(',')
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+pub fn set_etag(res: &mut Response, etag: &str) {
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        res.headers_mut().insert(ETAG, value);
+    }
+}
+
+/// Set the tag on the response, and answer 304 when the client already has this
+/// version. Returns whether the body can be skipped.
+pub fn not_modified(req: &Request, res: &mut Response, etag: &str) -> bool {
+    set_etag(res, etag);
+    if header_matches(req, IF_NONE_MATCH, etag) {
+        res.status_code(StatusCode::NOT_MODIFIED);
+        return true;
+    }
+    false
+}
+
+/// Ensure the update is based on the current version of the document, given by
+/// `If-Match`, so concurrent clients do not overwrite each other.
+pub fn check_if_match(req: &Request, etag: &str) -> ServiceResult<()> {
+    if !req.headers().contains_key(IF_MATCH) {
+        return Err(ServiceError::PreconditionRequired(
+            "If-Match header is required".to_string(),
+        ));
+    }
+    if !header_matches(req, IF_MATCH, etag) {
+        return Err(ServiceError::PreconditionFailed(format!(
+            "Modified meanwhile, the current version is {}",
+            etag
+        )));
+    }
+    Ok(())
+}
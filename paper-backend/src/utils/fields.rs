@@ -0,0 +1,113 @@
+use bson::{Document, doc};
+use salvo::{Response, writing::Json};
+use serde::Serialize;
+
+use crate::error::{ServiceError, ServiceResult};
+
+/// A response whose fields can be selected with `?fields=`.
+pub trait SparseFields {
+    /// Selectable response fields with the stored fields they are built from.
+    const FIELDS: &'static [(&'static str, &'static [&'static str])];
+    /// Stored fields always loaded, required to build the model.
+    const REQUIRED: &'static [&'static str];
+}
+
+/// Sparse fieldset, e.g. `?fields=title,authors`. The `id` is always returned.
+#[derive(Debug, Clone)]
+pub struct FieldSelection {
+    fields: Vec<String>,
+    projection: Document,
+}
+
+impl FieldSelection {
+    /// Parse the comma separated field list, `None` when all fields are requested.
+    pub fn parse<T: SparseFields>(fields: Option<&str>) -> ServiceResult<Option<Self>> {
+        let Some(fields) = fields else {
+            return Ok(None);
+        };
+        let mut selected = vec!["id".to_string()];
+        let mut projection = doc! {};
+        for name in T::REQUIRED {
+            projection.insert(*name, 1);
+        }
+        for name in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let (_, stored) = T::FIELDS
+                .iter()
+                .find(|(field, _)| *field == name)
+                .ok_or_else(|| {
+                    ServiceError::invalid_field(
+                        "fields",
+                        "unknown_field",
+                        format!("Unknown field: {}", name),
+                    )
+                })?;
+            for stored in *stored {
+                projection.insert(*stored, 1);
+            }
+            if !selected.iter().any(|f| f == name) {
+                selected.push(name.to_string());
+            }
+        }
+        Ok(Some(FieldSelection {
+            fields: selected,
+            projection,
+        }))
+    }
+
+    /// Mongo projection loading only what the selected fields need.
+    pub fn projection(&self) -> Document {
+        self.projection.clone()
+    }
+
+    /// Serialize the response keeping only the selected fields.
+    pub fn select<T: Serialize>(&self, value: &T) -> serde_json::Value {
+        match serde_json::to_value(value) {
+            Ok(serde_json::Value::Object(mut map)) => {
+                map.retain(|key, _| self.fields.contains(key));
+                serde_json::Value::Object(map)
+            }
+            Ok(value) => value,
+            Err(_) => serde_json::Value::Null,
+        }
+    }
+}
+
+/// The entity tag of the response, a sparse one is another representation
+/// than the full one and gets its own tag, e.g. `W/"1714552200000-3;authors,title"`.
+pub fn selected_etag(selection: Option<&FieldSelection>, etag: String) -> String {
+    let Some(selection) = selection else {
+        return etag;
+    };
+    let mut fields = selection.fields.clone();
+    fields.sort();
+    format!("{};{}\"", etag.trim_end_matches('"'), fields.join(","))
+}
+
+/// Render the response, sparse when fields were selected.
+pub fn render_fields<T: Serialize>(
+    res: &mut Response,
+    selection: Option<&FieldSelection>,
+    value: T,
+) {
+    match selection {
+        Some(selection) => res.render(Json(selection.select(&value))),
+        None => res.render(Json(value)),
+    }
+}
+
+/// Render the list, every item sparse when fields were selected.
+pub fn render_fields_list<T: Serialize>(
+    res: &mut Response,
+    selection: Option<&FieldSelection>,
+    values: Vec<T>,
+) {
+    match selection {
+        Some(selection) => res.render(Json(
+            values
+                .iter()
+                .map(|v| selection.select(v))
+                .collect::<Vec<_>>(),
+        )),
+        None => res.render(Json(values)),
+    }
+}
@@ -0,0 +1,59 @@
+use std::{
+    future::Future,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use tokio::sync::Notify;
+
+use crate::utils::request_id::{current_request_id, scope_request};
+
+/// Background jobs in flight, so shutdown can wait for them to finish.
+#[derive(Debug, Clone, Default)]
+pub struct JobTracker {
+    inner: Arc<JobTrackerInner>,
+}
+
+#[derive(Debug, Default)]
+struct JobTrackerInner {
+    running: AtomicUsize,
+    idle: Notify,
+}
+
+impl JobTracker {
+    /// Run the job in the background, as part of the current request if any.
+    pub fn spawn<F>(&self, job: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let inner = Arc::clone(&self.inner);
+        inner.running.fetch_add(1, Ordering::SeqCst);
+        let request_id = current_request_id();
+        tokio::spawn(async move {
+            match request_id {
+                Some(request_id) => scope_request(request_id, job).await,
+                None => job.await,
+            }
+            if inner.running.fetch_sub(1, Ordering::SeqCst) == 1 {
+                inner.idle.notify_waiters();
+            }
+        });
+    }
+
+    pub fn running(&self) -> usize {
+        self.inner.running.load(Ordering::SeqCst)
+    }
+
+    /// Wait until no job is running.
+    pub async fn wait(&self) {
+        loop {
+            let idle = self.inner.idle.notified();
+            if self.running() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
@@ -3,12 +3,19 @@ use serde::{Deserialize, Serialize};
 
 use std::sync::OnceLock;
 
-use crate::{config::Jwt, error::ServiceResult};
+use crate::{
+    config::Jwt,
+    error::{ServiceError, ServiceResult},
+    tenant::DEFAULT_TENANT,
+};
 static ACCESS_TOKEN_SECRET: OnceLock<String> = OnceLock::new();
 static REFRESH_TOKEN_SECRET: OnceLock<String> = OnceLock::new();
 
-const ACCESS_TOKEN_EXPIRATION: i64 = 3600; // 1 hour
+pub const ACCESS_TOKEN_EXPIRATION: i64 = 3600; // 1 hour
 const REFRESH_TOKEN_EXPIRATION: i64 = 604800; // 7 days
+const VERIFY_TOKEN_EXPIRATION: i64 = 86400; // 1 day
+const RESET_TOKEN_EXPIRATION: i64 = 1800; // 30 minutes
+const DELETE_TOKEN_EXPIRATION: i64 = 900; // 15 minutes
 
 pub fn set_jwt_config(jwt: &Jwt) {
     ACCESS_TOKEN_SECRET.set(jwt.access_secret.clone()).ok();
@@ -40,41 +47,71 @@ pub struct JwtClaims {
     pub exp: i64,
     // (type): Type of the JWT, can be used to differentiate between access and refresh tokens
     pub r#type: JwtType,
+    // (tenant): Tenant of the user, absent for the default one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tid: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum JwtType {
     Access,
     Refresh,
+    // email verification link
+    Verify,
+    // password reset link
+    Reset,
+    // confirmation of the deletion of an account without password
+    Delete,
+}
+
+fn tenant_claim(tenant: &str) -> Option<String> {
+    (tenant != DEFAULT_TENANT).then(|| tenant.to_string())
 }
 
 impl JwtClaims {
-    pub fn access(sub: String, iat: i64, exp: i64) -> Self {
+    pub fn access(sub: String, tenant: &str, iat: i64, exp: i64) -> Self {
         JwtClaims {
             sub,
             iat,
             exp,
             r#type: JwtType::Access,
+            tid: tenant_claim(tenant),
         }
     }
-    pub fn refresh(sub: String, iat: i64, exp: i64) -> Self {
+    pub fn refresh(sub: String, tenant: &str, iat: i64, exp: i64) -> Self {
         JwtClaims {
             sub,
             iat,
             exp,
             r#type: JwtType::Refresh,
+            tid: tenant_claim(tenant),
         }
     }
 
     pub fn is_expired(&self) -> bool {
         chrono::Utc::now().timestamp() > self.exp
     }
+
+    /// The tenant which issued the token.
+    pub fn tenant(&self) -> &str {
+        self.tid.as_deref().unwrap_or(DEFAULT_TENANT)
+    }
+
+    /// Refuse the tokens issued by another tenant than the one of the request.
+    pub fn check_tenant(&self, tenant: &str) -> ServiceResult<()> {
+        if self.tenant() != tenant {
+            return Err(ServiceError::Unauthorized(
+                "Token of another tenant".to_string(),
+            ));
+        }
+        Ok(())
+    }
 }
 
-pub fn generate_jwt_token(sub: String) -> ServiceResult<String> {
+pub fn generate_jwt_token(sub: String, tenant: &str) -> ServiceResult<String> {
     let current_time = chrono::Utc::now().timestamp();
     let expiration_time = current_time + ACCESS_TOKEN_EXPIRATION;
-    let claims = JwtClaims::access(sub, current_time, expiration_time);
+    let claims = JwtClaims::access(sub, tenant, current_time, expiration_time);
     Ok(encode(
         &Header::default(),
         &claims,
@@ -82,10 +119,10 @@ pub fn generate_jwt_token(sub: String) -> ServiceResult<String> {
     )?)
 }
 
-pub fn generate_refresh_token(sub: String) -> ServiceResult<String> {
+pub fn generate_refresh_token(sub: String, tenant: &str) -> ServiceResult<String> {
     let current_time = chrono::Utc::now().timestamp();
     let expiration_time = current_time + REFRESH_TOKEN_EXPIRATION;
-    let claims = JwtClaims::refresh(sub, current_time, expiration_time);
+    let claims = JwtClaims::refresh(sub, tenant, current_time, expiration_time);
     Ok(encode(
         &Header::default(),
         &claims,
@@ -101,3 +138,69 @@ pub fn verify_refresh_token(token: &str) -> ServiceResult<JwtClaims> {
     )?;
     Ok(token_data.claims)
 }
+
+/// Verify an access token outside of the salvo jwt middleware, for the grpc
+/// server.
+pub fn verify_access_token(token: &str) -> ServiceResult<JwtClaims> {
+    verify_action_token(token, JwtType::Access)
+}
+
+pub fn generate_verify_token(sub: String, tenant: &str) -> ServiceResult<String> {
+    generate_action_token(sub, tenant, JwtType::Verify, VERIFY_TOKEN_EXPIRATION)
+}
+
+pub fn verify_verify_token(token: &str) -> ServiceResult<JwtClaims> {
+    verify_action_token(token, JwtType::Verify)
+}
+
+pub fn generate_reset_token(sub: String, tenant: &str) -> ServiceResult<String> {
+    generate_action_token(sub, tenant, JwtType::Reset, RESET_TOKEN_EXPIRATION)
+}
+
+pub fn verify_reset_token(token: &str) -> ServiceResult<JwtClaims> {
+    verify_action_token(token, JwtType::Reset)
+}
+
+pub fn generate_delete_token(sub: String, tenant: &str) -> ServiceResult<String> {
+    generate_action_token(sub, tenant, JwtType::Delete, DELETE_TOKEN_EXPIRATION)
+}
+
+pub fn verify_delete_token(token: &str) -> ServiceResult<JwtClaims> {
+    verify_action_token(token, JwtType::Delete)
+}
+
+// one-off tokens sent by email, signed with the access secret
+fn generate_action_token(
+    sub: String,
+    tenant: &str,
+    r#type: JwtType,
+    expiration: i64,
+) -> ServiceResult<String> {
+    let current_time = chrono::Utc::now().timestamp();
+    let claims = JwtClaims {
+        sub,
+        iat: current_time,
+        exp: current_time + expiration,
+        r#type,
+        tid: tenant_claim(tenant),
+    };
+    Ok(encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(get_access_secret().as_bytes()),
+    )?)
+}
+
+fn verify_action_token(token: &str, r#type: JwtType) -> ServiceResult<JwtClaims> {
+    let claims = decode::<JwtClaims>(
+        token,
+        &jsonwebtoken::DecodingKey::from_secret(get_access_secret().as_bytes()),
+        &jsonwebtoken::Validation::default(),
+    )
+    .map_err(|e| ServiceError::Unauthorized(format!("Invalid token: {}", e)))?
+    .claims;
+    if claims.r#type != r#type {
+        return Err(ServiceError::Unauthorized("Invalid token type".to_string()));
+    }
+    Ok(claims)
+}
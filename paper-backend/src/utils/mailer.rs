@@ -0,0 +1,249 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{Mailbox, header::ContentType},
+    transport::smtp::authentication::Credentials,
+};
+
+use crate::{
+    config::SmtpConfig,
+    error::{ServiceError, ServiceResult},
+    resilience::breaker::CircuitBreaker,
+};
+
+// mails kept to be sent again, the oldest are dropped beyond
+const MAX_QUEUED_MAILS: usize = 1_000;
+
+/// An outgoing plain text email.
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait::async_trait]
+pub trait Mailer: Send + Sync + std::fmt::Debug {
+    async fn send(&self, mail: Mail) -> ServiceResult<()>;
+
+    /// Send again the mails which could not be sent, if any were kept.
+    async fn retry_queued(&self) {}
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl std::fmt::Debug for SmtpMailer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SmtpMailer")
+            .field("from", &self.from)
+            .finish()
+    }
+}
+
+impl SmtpMailer {
+    pub fn new(config: &SmtpConfig) -> ServiceResult<Self> {
+        let builder = if config.starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
+        }
+        .map_err(|e| ServiceError::MailError(e.to_string()))?;
+        let mut builder = builder.credentials(Credentials::new(
+            config.username.clone(),
+            config.password.clone(),
+        ));
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        let from = config
+            .from
+            .parse()
+            .map_err(|e: lettre::address::AddressError| ServiceError::MailError(e.to_string()))?;
+        Ok(SmtpMailer {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, mail: Mail) -> ServiceResult<()> {
+        let to: Mailbox = mail
+            .to
+            .parse()
+            .map_err(|e: lettre::address::AddressError| ServiceError::BadRequest(e.to_string()))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(mail.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(mail.body)
+            .map_err(|e| ServiceError::MailError(e.to_string()))?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| ServiceError::MailError(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Sends through the inner mailer unless its circuit is open. The mails it
+/// fails to send are queued and sent again once it is back, the callers are
+/// not failed by an outage of the smtp server.
+#[derive(Debug)]
+pub struct BreakerMailer<M> {
+    inner: M,
+    breaker: CircuitBreaker,
+    queue: Mutex<VecDeque<Mail>>,
+}
+
+impl<M: Mailer> BreakerMailer<M> {
+    pub fn new(inner: M, breaker: CircuitBreaker) -> Self {
+        BreakerMailer {
+            inner,
+            breaker,
+            queue: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn queue(&self, mail: Mail) {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        if queue.len() >= MAX_QUEUED_MAILS {
+            queue.pop_front();
+        }
+        queue.push_back(mail);
+    }
+}
+
+#[async_trait::async_trait]
+impl<M: Mailer> Mailer for BreakerMailer<M> {
+    async fn send(&self, mail: Mail) -> ServiceResult<()> {
+        match self.breaker.call(self.inner.send(mail.clone())).await {
+            Err(e @ (ServiceError::MailError(_) | ServiceError::CircuitOpen { .. })) => {
+                tracing::warn!("Mail to {} queued, not sent: {}", mail.to, e);
+                self.queue(mail);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    async fn retry_queued(&self) {
+        let mails = {
+            let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+            queue.drain(..).collect::<Vec<_>>()
+        };
+        if mails.is_empty() {
+            return;
+        }
+        tracing::info!("Sending {} queued mails again", mails.len());
+        for mail in mails {
+            // queued again while the server still fails
+            if let Err(e) = self.send(mail).await {
+                tracing::error!("Failed to send a queued mail: {}", e);
+            }
+        }
+    }
+}
+
+/// Mailer used when no smtp is configured, only writes the mail to the log.
+#[derive(Debug)]
+pub struct LogMailer;
+
+#[async_trait::async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, mail: Mail) -> ServiceResult<()> {
+        tracing::info!(
+            "Mail to {}, subject: {}\n{}",
+            mail.to,
+            mail.subject,
+            mail.body
+        );
+        Ok(())
+    }
+}
+
+/// Mailer capturing outgoing mails in memory, for tests.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MemoryMailer {
+    pub sent: std::sync::Mutex<Vec<Mail>>,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, mail: Mail) -> ServiceResult<()> {
+        self.sent.lock().unwrap().push(mail);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_mailer_captures_mail() {
+        let mailer = MemoryMailer::default();
+        mailer
+            .send(Mail {
+                to: "someone@example.com".to_string(),
+                subject: "hello".to_string(),
+                body: "world".to_string(),
+            })
+            .await
+            .unwrap();
+        let sent = mailer.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "someone@example.com");
+    }
+
+    /// Fails until told otherwise.
+    #[derive(Debug, Default)]
+    struct FlakyMailer {
+        down: std::sync::atomic::AtomicBool,
+        sent: MemoryMailer,
+    }
+
+    #[async_trait::async_trait]
+    impl Mailer for FlakyMailer {
+        async fn send(&self, mail: Mail) -> ServiceResult<()> {
+            if self.down.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(ServiceError::MailError("Connection refused".to_string()));
+            }
+            self.sent.send(mail).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_breaker_mailer_queues_failed_mail() {
+        let flaky = FlakyMailer::default();
+        flaky.down.store(true, std::sync::atomic::Ordering::SeqCst);
+        let config = crate::config::CircuitBreakerConfig {
+            failure_threshold: 1,
+            open_secs: 0,
+        };
+        let mailer = BreakerMailer::new(flaky, CircuitBreaker::new("smtp", &config));
+        let mail = Mail {
+            to: "someone@example.com".to_string(),
+            subject: "hello".to_string(),
+            body: "world".to_string(),
+        };
+        mailer.send(mail).await.unwrap();
+        assert!(mailer.inner.sent.sent.lock().unwrap().is_empty());
+
+        mailer
+            .inner
+            .down
+            .store(false, std::sync::atomic::Ordering::SeqCst);
+        mailer.retry_queued().await;
+        assert_eq!(mailer.inner.sent.sent.lock().unwrap().len(), 1);
+        assert!(mailer.queue.lock().unwrap().is_empty());
+    }
+}
@@ -1 +1,23 @@
+pub mod api_version;
+pub mod body_limit;
+pub mod cache;
+pub mod cost;
+pub mod crossref;
+pub mod crypto;
+pub mod diff;
+pub mod etag;
+pub mod fields;
+pub mod jobs;
 pub mod jwt;
+pub mod mailer;
+pub mod ndjson;
+pub mod password;
+pub mod request_id;
+pub mod security_headers;
+pub mod semantic_scholar;
+pub mod session;
+pub mod signed_url;
+pub mod template;
+pub mod time;
+pub mod timeout;
+pub mod validate;
@@ -0,0 +1,39 @@
+use futures::{Stream, StreamExt};
+use salvo::{
+    Request, Response,
+    http::header::{ACCEPT, CONTENT_TYPE, HeaderValue},
+};
+use serde::Serialize;
+
+use crate::error::ServiceError;
+
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Whether the client asked for a newline-delimited JSON stream instead of a JSON array.
+pub fn accepts_ndjson(req: &Request) -> bool {
+    req.headers()
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|v| v.split(';').next().unwrap_or_default().trim() == NDJSON_CONTENT_TYPE)
+}
+
+/// Stream the items as one JSON document per line, straight from the database cursor,
+/// so large listings are neither buffered nor held in memory.
+pub fn render_ndjson<S, T, R>(res: &mut Response, items: S)
+where
+    S: Stream<Item = Result<T, ServiceError>> + Send + 'static,
+    R: From<T> + Serialize,
+{
+    let lines = items.map(|item| {
+        let item = R::from(item?);
+        let mut line = serde_json::to_vec(&item)
+            .map_err(|e| ServiceError::InternalServerError(e.to_string()))?;
+        line.push(b'\n');
+        Ok::<_, ServiceError>(line)
+    });
+    res.headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(NDJSON_CONTENT_TYPE));
+    res.stream(lines);
+}
@@ -0,0 +1,76 @@
+use std::future::Future;
+
+use salvo::{Depot, FlowCtrl, Request, Response, http::header::HeaderValue};
+use tracing::Instrument;
+
+use crate::error::REQUEST_ID_HEADER;
+
+const MAX_REQUEST_ID_CHARS: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Id of the request being handled, also set in the background jobs and event
+/// subscribers it started.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Run the future as part of the request, in a tracing span with its id.
+pub async fn scope_request<F: Future>(request_id: String, future: F) -> F::Output {
+    let span = tracing::info_span!("request", request_id = %request_id);
+    REQUEST_ID.scope(request_id, future.instrument(span)).await
+}
+
+/// Ids given by clients or proxies are kept when short and header safe.
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_CHARS
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+/// Give every request an `X-Request-Id`, the one sent by the client or a new
+/// one, set on the response and on the logs, error payloads and records of it.
+#[salvo::handler]
+pub async fn request_id(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+    ctrl: &mut FlowCtrl,
+) {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    scope_request(id, ctrl.call_next(req, depot, res)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_request_id() {
+        assert!(is_valid_request_id("3f2b9c1e-8d4a-4e6f-9b7a-2c1d0e5f6a7b"));
+        assert!(is_valid_request_id("lb.edge:1234_abc"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("id with spaces"));
+        assert!(!is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_CHARS + 1)));
+    }
+
+    #[tokio::test]
+    async fn test_current_request_id() {
+        assert_eq!(current_request_id(), None);
+        let id = scope_request("req-1".to_string(), async { current_request_id() }).await;
+        assert_eq!(id.as_deref(), Some("req-1"));
+    }
+}
@@ -0,0 +1,118 @@
+use salvo::{
+    Depot, FlowCtrl, Request, Response, handler,
+    http::header::{
+        CONTENT_SECURITY_POLICY, HeaderName, HeaderValue, REFERRER_POLICY,
+        STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+    },
+};
+
+use crate::config::SecurityHeadersConfig;
+
+// served by the swagger ui, which needs its own policy
+const DOCS_PATH: &str = "/swagger-ui";
+
+/// Hoop of the service setting the security headers on every response, unless
+/// the route set its own. The headers left empty in the config are not sent.
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    // the headers of every response, but the csp
+    common: Vec<(HeaderName, HeaderValue)>,
+    csp: Option<HeaderValue>,
+    docs_csp: Option<HeaderValue>,
+}
+
+fn header_value(value: &str) -> anyhow::Result<Option<HeaderValue>> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(HeaderValue::from_str(value).map_err(|_| {
+        anyhow::anyhow!("Invalid security header value: {}", value)
+    })?))
+}
+
+impl SecurityHeaders {
+    pub fn new(config: &SecurityHeadersConfig) -> anyhow::Result<Self> {
+        let mut common = vec![(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"))];
+        for (name, value) in [
+            (REFERRER_POLICY, &config.referrer_policy),
+            (X_FRAME_OPTIONS, &config.frame_options),
+            (STRICT_TRANSPORT_SECURITY, &config.strict_transport_security),
+        ] {
+            if let Some(value) = header_value(value)? {
+                common.push((name, value));
+            }
+        }
+        Ok(SecurityHeaders {
+            common,
+            csp: header_value(&config.content_security_policy)?,
+            docs_csp: header_value(&config.docs_content_security_policy)?,
+        })
+    }
+
+    fn csp(&self, path: &str) -> Option<&HeaderValue> {
+        match path.starts_with(DOCS_PATH) {
+            true => self.docs_csp.as_ref(),
+            false => self.csp.as_ref(),
+        }
+    }
+}
+
+#[handler]
+impl SecurityHeaders {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        ctrl.call_next(req, depot, res).await;
+        let csp = self.csp(req.uri().path()).cloned();
+        let headers = res.headers_mut();
+        for (name, value) in &self.common {
+            if !headers.contains_key(name) {
+                headers.insert(name.clone(), value.clone());
+            }
+        }
+        if let Some(csp) = csp.filter(|_| !headers.contains_key(CONTENT_SECURITY_POLICY)) {
+            headers.insert(CONTENT_SECURITY_POLICY, csp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_security_headers() {
+        let config = SecurityHeadersConfig {
+            frame_options: String::new(),
+            ..Default::default()
+        };
+        let headers = SecurityHeaders::new(&config).unwrap();
+        let names = headers
+            .common
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&X_CONTENT_TYPE_OPTIONS));
+        assert!(names.contains(&STRICT_TRANSPORT_SECURITY));
+        assert!(!names.contains(&X_FRAME_OPTIONS));
+
+        assert_ne!(
+            headers.csp("/api/v1/paper"),
+            headers.csp("/swagger-ui/index.html")
+        );
+        assert_eq!(
+            headers.csp("/swagger-ui/index.html"),
+            headers.docs_csp.as_ref()
+        );
+
+        let config = SecurityHeadersConfig {
+            referrer_policy: "no-referrer\n".to_string(),
+            ..Default::default()
+        };
+        assert!(SecurityHeaders::new(&config).is_err());
+    }
+}
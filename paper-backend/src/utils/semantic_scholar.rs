@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::error::{ServiceError, ServiceResult};
+
+const SEMANTIC_SCHOLAR_API: &str = "https://api.semanticscholar.org";
+const PAPER_FIELDS: &str = "title,url,year,authors,externalIds";
+
+/// Client of the Semantic Scholar api, used for recommendations of papers
+/// outside the library.
+#[derive(Debug, Clone)]
+pub struct SemanticScholarClient {
+    client: reqwest::Client,
+    api_key: Option<String>,
+}
+
+/// A paper known to Semantic Scholar.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalPaper {
+    pub title: String,
+    pub url: Option<String>,
+    pub year: Option<i32>,
+    #[serde(default)]
+    pub authors: Vec<ExternalAuthor>,
+    #[serde(default)]
+    pub external_ids: Option<ExternalIds>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExternalAuthor {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExternalIds {
+    #[serde(rename = "DOI")]
+    pub doi: Option<String>,
+}
+
+impl ExternalPaper {
+    pub fn doi(&self) -> Option<String> {
+        self.external_ids
+            .as_ref()?
+            .doi
+            .as_ref()
+            .map(|doi| doi.to_lowercase())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecommendationsResponse {
+    #[serde(default)]
+    recommended_papers: Vec<ExternalPaper>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MatchResponse {
+    #[serde(default)]
+    data: Vec<MatchedPaper>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MatchedPaper {
+    paper_id: String,
+}
+
+impl SemanticScholarClient {
+    pub fn new(api_key: Option<String>) -> Self {
+        SemanticScholarClient {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .user_agent(concat!("paper-backend/", env!("CARGO_PKG_VERSION")))
+                .build()
+                .unwrap_or_default(),
+            api_key,
+        }
+    }
+
+    fn get(&self, url: String) -> reqwest::RequestBuilder {
+        let request = self.client.get(url);
+        match &self.api_key {
+            Some(api_key) => request.header("x-api-key", api_key),
+            None => request,
+        }
+    }
+
+    /// Id of the paper best matching the title, if any.
+    async fn match_title(&self, title: &str) -> ServiceResult<Option<String>> {
+        let response = self
+            .get(format!(
+                "{}/graph/v1/paper/search/match",
+                SEMANTIC_SCHOLAR_API
+            ))
+            .query(&[("query", title), ("fields", "paperId")])
+            .send()
+            .await
+            .map_err(|e| ServiceError::UpstreamError(e.to_string()))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let matched = response
+            .error_for_status()
+            .map_err(|e| ServiceError::UpstreamError(e.to_string()))?
+            .json::<MatchResponse>()
+            .await
+            .map_err(|e| ServiceError::UpstreamError(e.to_string()))?;
+        Ok(matched.data.into_iter().next().map(|p| p.paper_id))
+    }
+
+    /// Papers recommended for the paper, found by DOI or else by title. Empty
+    /// when Semantic Scholar does not know the paper.
+    pub async fn recommendations(
+        &self,
+        doi: Option<&str>,
+        title: &str,
+        limit: usize,
+    ) -> ServiceResult<Vec<ExternalPaper>> {
+        let paper_id = match doi {
+            Some(doi) => format!("DOI:{}", doi),
+            None => match self.match_title(title).await? {
+                Some(paper_id) => paper_id,
+                None => return Ok(Vec::new()),
+            },
+        };
+        let limit = limit.to_string();
+        let response = self
+            .get(format!(
+                "{}/recommendations/v1/papers/forpaper/{}",
+                SEMANTIC_SCHOLAR_API, paper_id
+            ))
+            .query(&[("fields", PAPER_FIELDS), ("limit", limit.as_str())])
+            .send()
+            .await
+            .map_err(|e| ServiceError::UpstreamError(e.to_string()))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        let recommendations = response
+            .error_for_status()
+            .map_err(|e| ServiceError::UpstreamError(e.to_string()))?
+            .json::<RecommendationsResponse>()
+            .await
+            .map_err(|e| ServiceError::UpstreamError(e.to_string()))?;
+        Ok(recommendations.recommended_papers)
+    }
+}
@@ -0,0 +1,224 @@
+use salvo::{
+    Depot, FlowCtrl, Request, Response,
+    http::{
+        Method,
+        cookie::{Cookie, CookieBuilder, SameSite, time::Duration},
+        header::AUTHORIZATION,
+    },
+};
+
+use crate::{
+    app_data::AppDataRef,
+    config::{SameSitePolicy, SessionConfig},
+    error::{ServiceError, ServiceResult},
+    utils::jwt::ACCESS_TOKEN_EXPIRATION,
+};
+
+pub const ACCESS_COOKIE: &str = "access_token";
+pub const REFRESH_COOKIE: &str = "refresh_token";
+pub const CSRF_COOKIE: &str = "csrf_token";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+// the csrf token of the WebSocket upgrades, which take no headers
+pub const CSRF_QUERY: &str = "csrf_token";
+// the finder of the access token in the query, e.g. for websockets
+pub const ACCESS_QUERY: &str = "jwt_token";
+
+const REFRESH_COOKIE_DAYS: i64 = 30;
+const CSRF_TOKEN_BYTES: usize = 32;
+
+fn same_site(policy: SameSitePolicy) -> SameSite {
+    match policy {
+        SameSitePolicy::Strict => SameSite::Strict,
+        SameSitePolicy::Lax => SameSite::Lax,
+        SameSitePolicy::None => SameSite::None,
+    }
+}
+
+fn cookie(
+    config: &SessionConfig,
+    name: &'static str,
+    value: String,
+    max_age: Duration,
+) -> CookieBuilder<'static> {
+    let mut cookie = CookieBuilder::new(name, value)
+        .max_age(max_age)
+        .same_site(same_site(config.same_site))
+        .secure(config.secure);
+    if let Some(domain) = &config.domain {
+        cookie = cookie.domain(domain.clone());
+    }
+    cookie
+}
+
+fn csrf_token() -> String {
+    let bytes: [u8; CSRF_TOKEN_BYTES] = rand::random();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The refresh token, read by the refresh route only.
+pub fn set_refresh_cookie(resp: &mut Response, config: &SessionConfig, refresh_token: String) {
+    resp.add_cookie(
+        cookie(
+            config,
+            REFRESH_COOKIE,
+            refresh_token,
+            Duration::days(REFRESH_COOKIE_DAYS),
+        )
+        .http_only(true)
+        .build(),
+    );
+}
+
+/// In cookie mode, the access token in an httpOnly cookie, and the csrf token
+/// of the session in a cookie the frontend reads, a new one unless the request
+/// carries one already.
+pub fn set_session_cookies(
+    req: &Request,
+    resp: &mut Response,
+    config: &SessionConfig,
+    access_token: &str,
+) {
+    if !config.cookie_auth {
+        return;
+    }
+    let max_age = Duration::seconds(ACCESS_TOKEN_EXPIRATION);
+    resp.add_cookie(
+        cookie(config, ACCESS_COOKIE, access_token.to_string(), max_age)
+            .path("/")
+            .http_only(true)
+            .build(),
+    );
+    let csrf = match req.cookie(CSRF_COOKIE) {
+        Some(csrf) => csrf.value().to_string(),
+        None => csrf_token(),
+    };
+    // outlives the access token, the refresh renews both
+    resp.add_cookie(
+        cookie(
+            config,
+            CSRF_COOKIE,
+            csrf,
+            Duration::days(REFRESH_COOKIE_DAYS),
+        )
+        .path("/")
+        .build(),
+    );
+}
+
+/// Expire the cookies of the session.
+pub fn clear_session_cookies(resp: &mut Response, config: &SessionConfig) {
+    resp.add_cookie(
+        cookie(config, REFRESH_COOKIE, String::new(), Duration::ZERO)
+            .http_only(true)
+            .build(),
+    );
+    if config.cookie_auth {
+        for name in [ACCESS_COOKIE, CSRF_COOKIE] {
+            resp.add_cookie(
+                cookie(config, name, String::new(), Duration::ZERO)
+                    .path("/")
+                    .build(),
+            );
+        }
+    }
+}
+
+fn authenticated_by_cookie(req: &Request) -> bool {
+    !req.headers().contains_key(AUTHORIZATION)
+        && req.query::<String>(ACCESS_QUERY).is_none()
+        && req.cookie(ACCESS_COOKIE).is_some()
+}
+
+pub(crate) fn is_mutating(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+// in constant time, not to tell how much of the token was guessed
+fn tokens_match(sent: &str, expected: &str) -> bool {
+    sent.len() == expected.len()
+        && sent
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Whether the csrf token sent repeats the csrf cookie.
+fn csrf_valid(sent: Option<&str>, expected: Option<&str>) -> bool {
+    match (sent, expected) {
+        (Some(sent), Some(expected)) => !expected.is_empty() && tokens_match(sent, expected),
+        _ => false,
+    }
+}
+
+/// Refuse the mutating requests authenticated by the session cookie unless the
+/// `X-CSRF-Token` header repeats the csrf cookie, which a page of another site
+/// can neither read nor set. The bearer tokens are never sent by the browser on
+/// its own and need none.
+#[salvo::handler]
+pub async fn require_csrf(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    let Ok(state) = depot.obtain::<AppDataRef>() else {
+        return;
+    };
+    if !state.session_config.cookie_auth
+        || !is_mutating(req.method())
+        || !authenticated_by_cookie(req)
+    {
+        return;
+    }
+    let expected = req.cookie(CSRF_COOKIE).map(Cookie::value);
+    let sent = req.header::<String>(CSRF_HEADER);
+    if !csrf_valid(sent.as_deref(), expected) {
+        tracing::info!("Missing or invalid csrf token on {}", req.uri().path());
+        res.render(ServiceError::CsrfRejected(
+            "Missing or invalid csrf token".to_string(),
+        ));
+        ctrl.skip_rest();
+    }
+}
+
+/// Refuse the WebSocket upgrades authenticated by the session cookie unless the
+/// `csrf_token` query repeats the csrf cookie. The browsers send the cookies
+/// with the upgrades opened by the pages of other sites too, and cors does not
+/// apply to the sockets.
+pub fn check_socket_csrf(req: &Request, config: &SessionConfig) -> ServiceResult<()> {
+    if !config.cookie_auth || !authenticated_by_cookie(req) {
+        return Ok(());
+    }
+    let expected = req.cookie(CSRF_COOKIE).map(Cookie::value);
+    let sent = req.query::<String>(CSRF_QUERY);
+    if !csrf_valid(sent.as_deref(), expected) {
+        tracing::info!("Missing or invalid csrf token on {}", req.uri().path());
+        return Err(ServiceError::CsrfRejected(
+            "Missing or invalid csrf token".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csrf_token() {
+        let token = csrf_token();
+        assert_eq!(token.len(), CSRF_TOKEN_BYTES * 2);
+        assert_ne!(token, csrf_token());
+        assert!(tokens_match(&token, &token.clone()));
+        assert!(!tokens_match(&token, &csrf_token()));
+        assert!(!tokens_match(&token[1..], &token));
+        assert!(csrf_valid(Some(&token), Some(&token)));
+        assert!(!csrf_valid(None, Some(&token)));
+        assert!(!csrf_valid(Some(""), Some("")));
+
+        assert!(is_mutating(&Method::POST));
+        assert!(is_mutating(&Method::DELETE));
+        assert!(!is_mutating(&Method::GET));
+    }
+}
@@ -0,0 +1,47 @@
+/// Fill the `{{name}}` placeholders of the template with the values, unknown
+/// placeholders are left as they are.
+pub fn render_template(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{{{}}}}}", name), value)
+        })
+}
+
+/// Names of the `{{name}}` placeholders of the template, in order of first use.
+pub fn template_variables(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("}}") else {
+            break;
+        };
+        let name = &rest[..end];
+        if !name.is_empty() && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+        rest = &rest[end + 2..];
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let text = render_template(
+            "Hello {{name}}, {{count}} new papers. {{unknown}}",
+            &[("name", "Ada"), ("count", "3")],
+        );
+        assert_eq!(text, "Hello Ada, 3 new papers. {{unknown}}");
+    }
+
+    #[test]
+    fn test_template_variables() {
+        let names = template_variables("{{name}} read {{count}} papers, {{name}}. {{open");
+        assert_eq!(names, vec!["name", "count"]);
+    }
+}
@@ -0,0 +1,2 @@
+// the serde of the timestamps is shared with the client, by the schemas
+pub use paper_schema::time::*;
@@ -0,0 +1,127 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use salvo::{Depot, FlowCtrl, Request, Response};
+use tokio::time::Instant;
+
+use crate::{app_data::AppDataRef, config::TimeoutConfig, error::ServiceError};
+
+/// The budget of a request, moved by the routes needing more time than the
+/// default one.
+#[derive(Debug, Clone)]
+struct Deadline(Arc<Mutex<(Instant, u64)>>);
+
+impl Deadline {
+    fn new(start: Instant, secs: u64) -> Self {
+        Deadline(Arc::new(Mutex::new((
+            start + Duration::from_secs(secs),
+            secs,
+        ))))
+    }
+
+    fn get(&self) -> (Instant, u64) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn extend(&self, start: Instant, secs: u64) {
+        let mut deadline = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        *deadline = (start + Duration::from_secs(secs), secs);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Budget {
+    Ai,
+    Upload,
+}
+
+impl Budget {
+    fn secs(self, config: &TimeoutConfig) -> u64 {
+        match self {
+            Budget::Ai => config.ai_secs,
+            Budget::Upload => config.upload_secs,
+        }
+    }
+}
+
+/// Answer a 504 to the requests not answered within their budget, the
+/// `request_secs` of the config unless a route gives another. The handler is
+/// dropped, which cancels its pending calls.
+#[salvo::handler]
+pub async fn limit_time(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    ctrl: &mut FlowCtrl,
+) {
+    let Ok(state) = depot.obtain::<AppDataRef>() else {
+        return;
+    };
+    let deadline = Deadline::new(Instant::now(), state.timeout_config.request_secs);
+    depot.inject(deadline.clone());
+
+    let mut next = Box::pin(ctrl.call_next(req, depot, res));
+    let secs = loop {
+        let (at, secs) = deadline.get();
+        tokio::select! {
+            _ = &mut next => return,
+            _ = tokio::time::sleep_until(at) => {
+                // extended while sleeping, wait for the new deadline
+                if deadline.get().0 <= Instant::now() {
+                    break secs;
+                }
+            }
+        }
+    };
+    drop(next);
+    tracing::warn!(
+        "{} {} timed out after {}s",
+        req.method(),
+        req.uri().path(),
+        secs
+    );
+    ctrl.skip_rest();
+    res.render(ServiceError::Timeout(secs));
+}
+
+fn extend_time(depot: &Depot, budget: Budget) {
+    let Ok(state) = depot.obtain::<AppDataRef>() else {
+        return;
+    };
+    let secs = budget.secs(&state.timeout_config);
+    if let Ok(deadline) = depot.obtain::<Deadline>() {
+        deadline.extend(Instant::now(), secs);
+    }
+}
+
+/// The longer budget of the llm backed routes.
+#[salvo::handler]
+pub async fn extend_for_ai(depot: &mut Depot) {
+    extend_time(depot, Budget::Ai);
+}
+
+/// The longer budget of the file uploads.
+#[salvo::handler]
+pub async fn extend_for_upload(depot: &mut Depot) {
+    extend_time(depot, Budget::Upload);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_deadline_extend() {
+        let start = Instant::now();
+        let deadline = Deadline::new(start, 30);
+        assert_eq!(deadline.get(), (start + Duration::from_secs(30), 30));
+        deadline.clone().extend(start, 300);
+        assert_eq!(deadline.get(), (start + Duration::from_secs(300), 300));
+
+        let config = TimeoutConfig::default();
+        assert_eq!(Budget::Ai.secs(&config), 300);
+        assert_eq!(Budget::Upload.secs(&config), 600);
+    }
+}
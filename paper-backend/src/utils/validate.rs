@@ -0,0 +1,28 @@
+pub use paper_schema::validate::{
+    MAX_TAG_CHARS, trim, trim_all, trim_option, validate_color, validate_tags,
+};
+use validator::{Validate, ValidationErrors};
+
+use crate::error::ServiceResult;
+
+/// Uniform validation of incoming request schemas:
+/// fields are normalized (trimmed...) first, then checked by the `Validate` rules
+/// of the schema and the `check` of the server.
+/// Failures are returned as a 422 with field-level details.
+pub trait ValidatedRequest: Validate + Sized {
+    fn normalize(&mut self) {}
+
+    /// Rules the schemas shared with the client cannot check, e.g. the event
+    /// types known to the server, added to the errors of the `Validate` ones.
+    fn check(&self, _errors: &mut ValidationErrors) {}
+
+    fn validated(mut self) -> ServiceResult<Self> {
+        self.normalize();
+        let mut errors = self.validate().err().unwrap_or_default();
+        self.check(&mut errors);
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+        Ok(self)
+    }
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::TestApp;
+use reqwest::{Method, StatusCode};
+use serde_json::json;
+
+#[tokio::test]
+#[ignore = "starts a MongoDB container, needs docker"]
+async fn test_login() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let resp = app
+        .request(Method::GET, &user, "/api/folder")
+        .send()
+        .await
+        .unwrap();
+    app.assert_response(StatusCode::OK, "get", "/api/folder", resp)
+        .await;
+
+    let resp = app.client.get(app.url("/api/folder")).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = app
+        .client
+        .get(app.url("/api/folder"))
+        .bearer_auth("not.a.token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[ignore = "starts a MongoDB container, needs docker"]
+async fn test_register() {
+    let app = TestApp::spawn().await;
+    let account = json!({ "email": "reader@example.com", "password": "correct horse" });
+
+    let resp = app
+        .client
+        .post(app.url("/api/auth/register"))
+        .json(&account)
+        .send()
+        .await
+        .unwrap();
+    let body = app
+        .assert_response(StatusCode::CREATED, "post", "/api/auth/register", resp)
+        .await;
+    assert!(body["userId"].is_string());
+
+    let resp = app
+        .client
+        .post(app.url("/api/auth/register"))
+        .json(&account)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // pending until the email is verified
+    let resp = app
+        .client
+        .post(app.url("/api/auth/email-login"))
+        .json(&account)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[ignore = "starts a MongoDB container, needs docker"]
+async fn test_api_versions() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    for path in ["/api/folder", "/api/v1/folder"] {
+        let resp = app.request(Method::GET, &user, path).send().await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()["api-version"], "v1");
+    }
+    let doc: serde_json::Value = app
+        .client
+        .get(app.url("/api-doc/v1/openapi.json"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(doc["paths"]["/api/v1/folder"]["get"].is_object());
+    assert!(doc["paths"]["/api/folder"].is_null());
+}
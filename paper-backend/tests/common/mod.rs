@@ -0,0 +1,236 @@
+//! Harness of the end-to-end tests: the server binary against a MongoDB
+//! container, called over http. They need docker, and run with
+//! `cargo test -p paper-backend -- --ignored`.
+
+#![allow(dead_code)]
+
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::{Duration, Instant},
+};
+
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde_json::{Value, json};
+use testcontainers_modules::{
+    mongo::Mongo,
+    testcontainers::{ContainerAsync, runners::AsyncRunner},
+};
+use tokio::process::{Child, Command};
+
+// time given to the server to answer its health check
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A running server, stopped with its database when dropped.
+pub struct TestApp {
+    pub base_url: String,
+    pub client: reqwest::Client,
+    // the document served at `/api-doc/openapi.json`
+    pub openapi: Value,
+    server: Child,
+    // holds the config and the logs
+    dir: PathBuf,
+    _mongo: ContainerAsync<Mongo>,
+}
+
+/// A user logged in by phone, without any folder yet.
+pub struct TestUser {
+    pub id: String,
+    pub token: String,
+}
+
+impl TestApp {
+    pub async fn spawn() -> TestApp {
+        // a replica set, the repositories use transactions
+        let mongo = Mongo::repl_set()
+            .start()
+            .await
+            .expect("Failed to start MongoDB, is docker running?");
+        let mongo_port = mongo.get_host_port_ipv4(27017).await.unwrap();
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap()
+            .port();
+
+        let dir = std::env::temp_dir().join(format!("paper-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = format!(
+            r#"
+[frontend_config]
+cors = []
+
+[backend_config]
+address = "127.0.0.1:{port}"
+
+[backend_config.jwt]
+access_secret = "test_access_secret"
+refresh_secret = "test_refresh_secret"
+
+[log_config]
+enable_debug = true
+directory = "{dir}"
+prefix = "paper-backend"
+
+[mongo_config]
+uri = "mongodb://127.0.0.1:{mongo_port}/?directConnection=true"
+db_name = "paper"
+
+[llm_config]
+provider = "mock"
+
+[rate_limit_config]
+enabled = false
+"#,
+            dir = dir.display(),
+        );
+        let config_path = dir.join("config.toml");
+        std::fs::write(&config_path, config).unwrap();
+
+        let server = Command::new(env!("CARGO_BIN_EXE_paper-backend"))
+            .arg(&config_path)
+            .arg("--no-watch")
+            .stdout(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("Failed to start the server");
+        let base_url = format!("http://127.0.0.1:{}", port);
+        let client = reqwest::Client::new();
+        wait_healthy(&client, &base_url, &dir).await;
+        let openapi = client
+            .get(format!("{}/api-doc/openapi.json", base_url))
+            .send()
+            .await
+            .and_then(Response::error_for_status)
+            .expect("Failed to fetch the OpenAPI document")
+            .json()
+            .await
+            .unwrap();
+        TestApp {
+            base_url,
+            client,
+            openapi,
+            server,
+            dir,
+            _mongo: mongo,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// A new user, the first phone login creates the account.
+    pub async fn create_user(&self) -> TestUser {
+        let phone = format!("139{:08}", rand_digits());
+        let resp = self
+            .client
+            .post(self.url("/api/auth/phone-login"))
+            .json(&json!({ "phone": phone, "code": "123456" }))
+            .send()
+            .await
+            .unwrap();
+        let body = self
+            .assert_conforms("post", "/api/auth/phone-login", resp)
+            .await;
+        TestUser {
+            id: body["userId"].as_str().unwrap().to_string(),
+            token: body["accessToken"].as_str().unwrap().to_string(),
+        }
+    }
+
+    /// A request of the user to the api, e.g. `app.request(Method::GET, &user, "/api/folder")`.
+    pub fn request(&self, method: Method, user: &TestUser, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, self.url(path))
+            .bearer_auth(&user.token)
+    }
+
+    /// The json body of the response, after checking it against the schema the
+    /// OpenAPI document gives for the operation and the status. `path` is the
+    /// template of the operation, e.g. `/api/folder/{folder_id}`.
+    pub async fn assert_conforms(&self, method: &str, path: &str, resp: Response) -> Value {
+        let status = resp.status();
+        let operation = &self.openapi["paths"][path][method];
+        assert!(
+            operation.is_object(),
+            "No operation {} {} in the OpenAPI document",
+            method,
+            path
+        );
+        let response = &operation["responses"][status.as_str()];
+        assert!(
+            response.is_object(),
+            "Undocumented status {} of {} {}",
+            status,
+            method,
+            path
+        );
+        let body = resp.text().await.unwrap();
+        let schema = &response["content"]["application/json"]["schema"];
+        if schema.is_null() {
+            return Value::Null;
+        }
+        let body: Value = serde_json::from_str(&body)
+            .unwrap_or_else(|e| panic!("{} {} answered invalid json: {}", method, path, e));
+        // the references of the schema point into the whole document
+        let mut schema = schema.clone();
+        if let Value::Object(schema) = &mut schema {
+            schema.insert("components".to_string(), self.openapi["components"].clone());
+        }
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        let errors = validator
+            .iter_errors(&body)
+            .map(|e| format!("{} at {}", e, e.instance_path))
+            .collect::<Vec<_>>();
+        assert!(
+            errors.is_empty(),
+            "{} {} answered {} not matching its schema: {}\n{}",
+            method,
+            path,
+            status,
+            errors.join(", "),
+            body
+        );
+        body
+    }
+
+    /// Asserts the status of the response, then its conformance.
+    pub async fn assert_response(
+        &self,
+        status: StatusCode,
+        method: &str,
+        path: &str,
+        resp: Response,
+    ) -> Value {
+        assert_eq!(resp.status(), status, "{} {}", method, path);
+        self.assert_conforms(method, path, resp).await
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        self.server.start_kill().ok();
+        std::fs::remove_dir_all(&self.dir).ok();
+    }
+}
+
+async fn wait_healthy(client: &reqwest::Client, base_url: &str, dir: &Path) {
+    let started = Instant::now();
+    loop {
+        let health = client.get(format!("{}/healthz", base_url)).send().await;
+        if health.is_ok_and(|resp| resp.status().is_success()) {
+            return;
+        }
+        if started.elapsed() > STARTUP_TIMEOUT {
+            panic!(
+                "The server did not start, see the logs in {}",
+                dir.display()
+            );
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+fn rand_digits() -> u32 {
+    (uuid::Uuid::new_v4().as_u128() % 100_000_000) as u32
+}
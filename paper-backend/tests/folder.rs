@@ -0,0 +1,85 @@
+mod common;
+
+use common::TestApp;
+use reqwest::{Method, StatusCode, header};
+use serde_json::json;
+
+#[tokio::test]
+#[ignore = "starts a MongoDB container, needs docker"]
+async fn test_folders() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    // the virtual starred folder and the system folder provisioned on first listing
+    let resp = app
+        .request(Method::GET, &user, "/api/folder")
+        .send()
+        .await
+        .unwrap();
+    let folders = app
+        .assert_response(StatusCode::OK, "get", "/api/folder", resp)
+        .await;
+    assert_eq!(folders.as_array().unwrap().len(), 2);
+
+    let resp = app
+        .request(Method::POST, &user, "/api/folder")
+        .json(&json!({ "name": "To read" }))
+        .send()
+        .await
+        .unwrap();
+    let folder = app
+        .assert_response(StatusCode::CREATED, "post", "/api/folder", resp)
+        .await;
+    let path = format!("/api/folder/{}", folder["id"].as_str().unwrap());
+
+    let resp = app
+        .request(Method::PUT, &user, &path)
+        .json(&json!({ "name": "Read" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::PRECONDITION_REQUIRED);
+    let resp = app
+        .request(Method::PUT, &user, &path)
+        .header(header::IF_MATCH, "*")
+        .json(&json!({ "name": "Read" }))
+        .send()
+        .await
+        .unwrap();
+    let etag = resp.headers()[header::ETAG].clone();
+    let folder = app
+        .assert_response(StatusCode::OK, "put", "/api/folder/{folder_id}", resp)
+        .await;
+    assert_eq!(folder["name"], "Read");
+    assert_eq!(folder["version"], 1);
+
+    let resp = app
+        .request(Method::PUT, &user, &path)
+        .header(header::IF_MATCH, etag.clone())
+        .json(&json!({ "description": "Done" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = app
+        .request(Method::PUT, &user, &path)
+        .header(header::IF_MATCH, etag)
+        .json(&json!({ "description": "Not done" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+    // invisible to the other users
+    let other = app.create_user().await;
+    let resp = app
+        .request(Method::GET, &other, "/api/folder")
+        .send()
+        .await
+        .unwrap();
+    let folders = app
+        .assert_response(StatusCode::OK, "get", "/api/folder", resp)
+        .await;
+    let folders = folders.as_array().unwrap();
+    assert!(folders.iter().all(|f| f["id"] != folder["id"]));
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::TestApp;
+use reqwest::{Method, StatusCode};
+use serde_json::json;
+
+#[tokio::test]
+#[ignore = "starts a MongoDB container, needs docker"]
+async fn test_papers() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let resp = app
+        .request(Method::POST, &user, "/api/folder")
+        .json(&json!({ "name": "Transformers" }))
+        .send()
+        .await
+        .unwrap();
+    let folder = app
+        .assert_response(StatusCode::CREATED, "post", "/api/folder", resp)
+        .await;
+
+    let request = json!({
+        "folderId": folder["id"],
+        "title": "Attention Is All You Need",
+        "authors": ["Ashish Vaswani"],
+        "tags": ["nlp"],
+    });
+    let resp = app
+        .request(Method::POST, &user, "/api/paper")
+        .json(&request)
+        .send()
+        .await
+        .unwrap();
+    let paper = app
+        .assert_response(StatusCode::CREATED, "post", "/api/paper", resp)
+        .await;
+    let path = format!("/api/paper/{}", paper["id"].as_str().unwrap());
+
+    let resp = app.request(Method::GET, &user, &path).send().await.unwrap();
+    let fetched = app
+        .assert_response(StatusCode::OK, "get", "/api/paper/{paper_id}", resp)
+        .await;
+    assert_eq!(fetched["title"], "Attention Is All You Need");
+
+    let resp = app
+        .request(Method::POST, &user, &format!("{}/star", path))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let resp = app
+        .request(Method::GET, &user, "/api/paper/starred")
+        .send()
+        .await
+        .unwrap();
+    let starred = app
+        .assert_response(StatusCode::OK, "get", "/api/paper/starred", resp)
+        .await;
+    assert_eq!(starred[0]["id"], paper["id"]);
+
+    // refused to the other users
+    let other = app.create_user().await;
+    let resp = app
+        .request(Method::GET, &other, &path)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = app
+        .request(Method::DELETE, &user, &path)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let resp = app.request(Method::GET, &user, &path).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
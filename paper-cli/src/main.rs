@@ -0,0 +1,285 @@
+//! Paper cli
+//!
+//! Talks to the REST api of paper-backend, to script imports and smoke test
+//! deployments. `login` keeps the access token in `~/.paper-cli/token` for the
+//! following commands, `--token` or `PAPER_TOKEN` take precedence over it.
+
+use std::{fs, io::Write, path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use paper_client::{
+    Client, ClientError,
+    schema::{
+        auth::EmailLogin,
+        folder::{CreateFolderRequest, FolderResponse},
+        job::JobStatus,
+        paper::CreatePaperRequest,
+    },
+};
+use reqwest::StatusCode;
+use serde::Serialize;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Command line client of the paper api")]
+struct Cli {
+    /// Url of the deployment, without the `/api` suffix
+    #[arg(long, env = "PAPER_URL", default_value = "http://127.0.0.1:7878")]
+    url: String,
+    /// Access token, instead of the one saved by `login`
+    #[arg(long, env = "PAPER_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Sign in by email and save the access token
+    Login {
+        #[arg(long)]
+        email: String,
+        /// Read from stdin when absent
+        #[arg(long, env = "PAPER_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+    },
+    /// Check the deployment and its dependencies are ready
+    Check,
+    #[command(subcommand)]
+    Folders(FolderCommand),
+    /// Create a paper for each pdf and upload the file into it
+    Upload {
+        /// Folder receiving the papers
+        #[arg(long)]
+        folder: String,
+        /// Recognize the text of scanned pdfs
+        #[arg(long)]
+        ocr: bool,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Summarize the papers of a folder into a new paper, archiving the folder
+    Summarize {
+        folder_id: String,
+        /// A configured model instead of the default one
+        #[arg(long)]
+        model: Option<String>,
+    },
+    #[command(subcommand)]
+    Jobs(JobCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum FolderCommand {
+    /// Print the folder tree with the ids
+    List,
+    Create {
+        name: String,
+        #[arg(long)]
+        parent: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum JobCommand {
+    /// The latest background jobs
+    List,
+    /// Follow a job until it is done or failed
+    Tail {
+        job_id: String,
+        /// Seconds between two polls
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+    },
+}
+
+fn token_path() -> anyhow::Result<PathBuf> {
+    let home = std::env::var("HOME").context("HOME is not set")?;
+    Ok(PathBuf::from(home).join(".paper-cli").join("token"))
+}
+
+fn saved_token() -> Option<String> {
+    let token = fs::read_to_string(token_path().ok()?).ok()?;
+    Some(token.trim().to_string())
+}
+
+fn save_token(token: &str) -> anyhow::Result<()> {
+    let path = token_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, token).with_context(|| format!("Failed to save {}", path.display()))
+}
+
+fn read_password() -> anyhow::Result<String> {
+    print!("Password: ");
+    std::io::stdout().flush()?;
+    let mut password = String::new();
+    std::io::stdin().read_line(&mut password)?;
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// The value of an enum on the wire, e.g. `folder_copy`.
+fn wire_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// The error answered by the api, with a hint when the token is missing or expired.
+fn api_error(error: ClientError) -> anyhow::Error {
+    match &error {
+        ClientError::Api { status, .. } if *status == StatusCode::UNAUTHORIZED => {
+            anyhow::anyhow!("{}, run `paper-cli login` first", error)
+        }
+        _ => error.into(),
+    }
+}
+
+fn print_tree(folders: &[FolderResponse], parent: Option<&str>, depth: usize) {
+    for folder in folders.iter().filter(|f| f.parent_id.as_deref() == parent) {
+        let archived = if folder.archived { " (archived)" } else { "" };
+        println!(
+            "{}{}  {}{}",
+            "  ".repeat(depth),
+            folder.id,
+            folder.name,
+            archived
+        );
+        print_tree(folders, Some(&folder.id), depth + 1);
+    }
+}
+
+async fn upload(client: &Client, folder: &str, ocr: bool, files: &[PathBuf]) -> anyhow::Result<()> {
+    for file in files {
+        let pdf = fs::read(file).with_context(|| format!("Failed to read {}", file.display()))?;
+        let title = file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let paper = client
+            .create_paper(&CreatePaperRequest {
+                folder_id: Some(folder.to_string()),
+                title,
+                authors: Vec::new(),
+                r#abstract: None,
+                doi: None,
+                content: None,
+                tags: Vec::new(),
+            })
+            .await
+            .map_err(api_error)
+            .with_context(|| format!("Failed to create the paper of {}", file.display()))?;
+        let paper = client
+            .upload_paper_file(&paper.id, pdf, ocr)
+            .await
+            .map_err(api_error)
+            .with_context(|| format!("Failed to upload {}", file.display()))?;
+        let pages = paper.page_count.map(|n| format!(", {} pages", n));
+        println!("{}  {}{}", paper.id, paper.title, pages.unwrap_or_default());
+    }
+    Ok(())
+}
+
+async fn tail_job(client: &Client, job_id: &str, interval: Duration) -> anyhow::Result<()> {
+    let mut last = None;
+    loop {
+        let job = client.get_job(job_id).await.map_err(api_error)?;
+        let progress = job
+            .progress
+            .as_ref()
+            .map(|p| format!(" {}/{}", p.done, p.total));
+        let line = format!(
+            "{} {}{}",
+            wire_name(&job.kind),
+            wire_name(&job.status),
+            progress.unwrap_or_default()
+        );
+        if last.as_ref() != Some(&line) {
+            println!("{}", line);
+            last = Some(line);
+        }
+        if job.status != JobStatus::Running {
+            if let Some(result_id) = &job.result_id {
+                println!("result: {}", result_id);
+            }
+            return match job.error {
+                Some(error) => anyhow::bail!("Job {} failed: {}", job.id, error),
+                None => Ok(()),
+            };
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let mut client = Client::new(&cli.url);
+    client.set_token(cli.token.or_else(saved_token));
+
+    match cli.command {
+        Command::Login { email, password } => {
+            let password = match password {
+                Some(password) => password,
+                None => read_password()?,
+            };
+            let login = client.email_login(&EmailLogin { email, password }).await?;
+            save_token(&login.access_token)?;
+            println!("Signed in as {}", login.user_id);
+        }
+        Command::Check => {
+            let ready = client.readyz().await?;
+            println!("{}", serde_json::to_string_pretty(&ready)?);
+        }
+        Command::Folders(FolderCommand::List) => {
+            let folders = client.list_folders().await.map_err(api_error)?;
+            print_tree(&folders.0, None, 0);
+        }
+        Command::Folders(FolderCommand::Create { name, parent }) => {
+            let folder = client
+                .create_folder(
+                    &CreateFolderRequest {
+                        parent_id: parent,
+                        name,
+                        description: None,
+                        color: None,
+                        icon: None,
+                        query: None,
+                    },
+                    false,
+                )
+                .await
+                .map_err(api_error)?;
+            println!("{}", folder.id);
+        }
+        Command::Upload { folder, ocr, files } => upload(&client, &folder, ocr, &files).await?,
+        Command::Summarize { folder_id, model } => {
+            let wrap_up = client
+                .wrap_up_folder(&folder_id, model.as_deref())
+                .await
+                .map_err(api_error)?;
+            let paper = wrap_up.summary_paper;
+            println!("{}  {}", paper.id, paper.title);
+            if let Some(summary) = paper.summary {
+                println!("\n{}", summary);
+            }
+        }
+        Command::Jobs(JobCommand::List) => {
+            for job in client.list_jobs().await.map_err(api_error)?.0 {
+                println!(
+                    "{}  {} {} {}",
+                    job.id,
+                    wire_name(&job.kind),
+                    wire_name(&job.status),
+                    job.resource_id
+                );
+            }
+        }
+        Command::Jobs(JobCommand::Tail { job_id, interval }) => {
+            tail_job(&client, &job_id, Duration::from_secs(interval)).await?
+        }
+    }
+    Ok(())
+}
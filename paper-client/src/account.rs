@@ -0,0 +1,57 @@
+use paper_schema::{
+    account::DeleteAccountRequest,
+    activity::ListActivityResponse,
+    consent::{ConsentRequest, ConsentResponse, LegalDocumentsResponse},
+    export::ExportJobResponse,
+    stats::UserStatsResponse,
+    usage::UsageResponse,
+};
+
+use crate::{Client, ClientResult, query};
+
+impl Client {
+    /// Start the export of every document of the user into a zip archive.
+    pub async fn export_account(&self) -> ClientResult<ExportJobResponse> {
+        self.json(self.post("account/export")).await
+    }
+
+    pub async fn delete_account(&self, request: &DeleteAccountRequest) -> ClientResult<()> {
+        self.empty(self.delete("account").json(request)).await
+    }
+
+    pub async fn get_export(&self, export_id: &str) -> ClientResult<ExportJobResponse> {
+        self.json(self.get(&format!("exports/{}", export_id))).await
+    }
+
+    /// The archive of a finished export.
+    pub async fn download_export(&self, export_id: &str) -> ClientResult<Vec<u8>> {
+        self.bytes(self.get(&format!("exports/{}/download", export_id)))
+            .await
+    }
+
+    pub async fn list_activity(&self, limit: Option<i64>) -> ClientResult<ListActivityResponse> {
+        let query = query(&[("limit", limit.map(|l| l.to_string()))]);
+        self.json(self.get("activity").query(&query)).await
+    }
+
+    pub async fn get_stats(&self) -> ClientResult<UserStatsResponse> {
+        self.json(self.get("stats")).await
+    }
+
+    pub async fn get_usage(&self) -> ClientResult<UsageResponse> {
+        self.json(self.get("usage")).await
+    }
+
+    /// The current terms and privacy policy, without signing in.
+    pub async fn get_legal_documents(&self) -> ClientResult<LegalDocumentsResponse> {
+        self.json(self.get("legal")).await
+    }
+
+    pub async fn get_consent(&self) -> ClientResult<ConsentResponse> {
+        self.json(self.get("legal/consent")).await
+    }
+
+    pub async fn give_consent(&self, request: &ConsentRequest) -> ClientResult<ConsentResponse> {
+        self.json(self.post("legal/consent").json(request)).await
+    }
+}
@@ -0,0 +1,111 @@
+use paper_schema::{
+    backup::{BackupJobResponse, ListBackupJobsResponse, RestoreBackupRequest},
+    login_attempt::ListLoginLocksResponse,
+    migration::ListMigrationsResponse,
+    prompt::{
+        CreatePromptTemplateRequest, ListPromptTemplatesResponse, PromptTemplateResponse,
+        UpdatePromptTemplateRequest,
+    },
+    quota::{UpdateUserQuotasRequest, UserQuotasResponse},
+    retry::DbRetriesResponse,
+    usage::AdminUsageResponse,
+};
+
+use crate::{Client, ClientResult, query};
+
+/// The operator endpoints, under `/admin`.
+impl Client {
+    pub async fn get_all_usage(&self, limit: Option<i64>) -> ClientResult<AdminUsageResponse> {
+        let query = query(&[("limit", limit.map(|l| l.to_string()))]);
+        self.json(self.get("admin/usage").query(&query)).await
+    }
+
+    pub async fn list_migrations(&self) -> ClientResult<ListMigrationsResponse> {
+        self.json(self.get("admin/migrations")).await
+    }
+
+    pub async fn get_db_retries(&self) -> ClientResult<DbRetriesResponse> {
+        self.json(self.get("admin/db-retries")).await
+    }
+
+    pub async fn create_backup(&self) -> ClientResult<BackupJobResponse> {
+        self.json(self.post("admin/backup")).await
+    }
+
+    pub async fn restore_backup(
+        &self,
+        request: &RestoreBackupRequest,
+    ) -> ClientResult<BackupJobResponse> {
+        self.json(self.post("admin/restore").json(request)).await
+    }
+
+    pub async fn list_backup_jobs(&self) -> ClientResult<ListBackupJobsResponse> {
+        self.json(self.get("admin/backups")).await
+    }
+
+    pub async fn get_backup_job(&self, job_id: &str) -> ClientResult<BackupJobResponse> {
+        self.json(self.get(&format!("admin/backups/{}", job_id)))
+            .await
+    }
+
+    pub async fn get_user_quotas(&self, user_id: &str) -> ClientResult<UserQuotasResponse> {
+        self.json(self.get(&format!("admin/users/{}/quotas", user_id)))
+            .await
+    }
+
+    pub async fn update_user_quotas(
+        &self,
+        user_id: &str,
+        request: &UpdateUserQuotasRequest,
+    ) -> ClientResult<UserQuotasResponse> {
+        let path = format!("admin/users/{}/quotas", user_id);
+        self.json(self.put(&path).json(request)).await
+    }
+
+    pub async fn list_login_locks(&self) -> ClientResult<ListLoginLocksResponse> {
+        self.json(self.get("admin/login-locks")).await
+    }
+
+    /// Unlock `account:{email}` or `ip:{ip}`.
+    pub async fn unlock_login(&self, key: &str) -> ClientResult<()> {
+        self.empty(self.delete(&format!("admin/login-locks/{}", key)))
+            .await
+    }
+
+    pub async fn list_prompt_templates(
+        &self,
+        name: Option<&str>,
+    ) -> ClientResult<ListPromptTemplatesResponse> {
+        let query = query(&[("name", name.map(str::to_string))]);
+        self.json(self.get("admin/prompts").query(&query)).await
+    }
+
+    pub async fn create_prompt_template(
+        &self,
+        request: &CreatePromptTemplateRequest,
+    ) -> ClientResult<PromptTemplateResponse> {
+        self.json(self.post("admin/prompts").json(request)).await
+    }
+
+    pub async fn get_prompt_template(
+        &self,
+        prompt_id: &str,
+    ) -> ClientResult<PromptTemplateResponse> {
+        self.json(self.get(&format!("admin/prompts/{}", prompt_id)))
+            .await
+    }
+
+    pub async fn update_prompt_template(
+        &self,
+        prompt_id: &str,
+        request: &UpdatePromptTemplateRequest,
+    ) -> ClientResult<PromptTemplateResponse> {
+        let path = format!("admin/prompts/{}", prompt_id);
+        self.json(self.put(&path).json(request)).await
+    }
+
+    pub async fn delete_prompt_template(&self, prompt_id: &str) -> ClientResult<()> {
+        self.empty(self.delete(&format!("admin/prompts/{}", prompt_id)))
+            .await
+    }
+}
@@ -0,0 +1,10 @@
+use paper_schema::ai::{EstimateRequest, EstimateResponse};
+
+use crate::{Client, ClientResult};
+
+impl Client {
+    /// The tokens and cost of a prompt with each configured model.
+    pub async fn estimate(&self, request: &EstimateRequest) -> ClientResult<EstimateResponse> {
+        self.json(self.post("ai/estimate").json(request)).await
+    }
+}
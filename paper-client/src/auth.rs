@@ -0,0 +1,52 @@
+use paper_schema::auth::{
+    EmailLogin, EmailRegister, ForgotPassword, LoginResult, PhoneLogin, RegisterResult,
+    ResetPassword,
+};
+
+use crate::{Client, ClientResult};
+
+impl Client {
+    /// Sign in by phone, keeping the access token for the following requests.
+    pub async fn phone_login(&mut self, login: &PhoneLogin) -> ClientResult<LoginResult> {
+        let result: LoginResult = self.json(self.post("auth/phone-login").json(login)).await?;
+        self.token = Some(result.access_token.clone());
+        Ok(result)
+    }
+
+    /// Sign in by email, keeping the access token for the following requests.
+    pub async fn email_login(&mut self, login: &EmailLogin) -> ClientResult<LoginResult> {
+        let result: LoginResult = self.json(self.post("auth/email-login").json(login)).await?;
+        self.token = Some(result.access_token.clone());
+        Ok(result)
+    }
+
+    /// A new access token, from the refresh token set by the last login.
+    pub async fn refresh(&mut self) -> ClientResult<LoginResult> {
+        let result: LoginResult = self.json(self.post("auth/refresh")).await?;
+        self.token = Some(result.access_token.clone());
+        Ok(result)
+    }
+
+    pub async fn logout(&mut self) -> ClientResult<()> {
+        self.empty(self.post("auth/logout")).await?;
+        self.token = None;
+        Ok(())
+    }
+
+    pub async fn register(&self, register: &EmailRegister) -> ClientResult<RegisterResult> {
+        self.json(self.post("auth/register").json(register)).await
+    }
+
+    pub async fn verify_email(&self, token: &str) -> ClientResult<()> {
+        self.empty(self.get("auth/verify").query(&[("token", token)]))
+            .await
+    }
+
+    pub async fn forgot_password(&self, forgot: &ForgotPassword) -> ClientResult<()> {
+        self.empty(self.post("auth/forgot").json(forgot)).await
+    }
+
+    pub async fn reset_password(&self, reset: &ResetPassword) -> ClientResult<()> {
+        self.empty(self.post("auth/reset").json(reset)).await
+    }
+}
@@ -0,0 +1,33 @@
+use paper_schema::block::{
+    BlockResponse, CreateBlockRequest, ListBlocksResponse, UpdateBlockRequest,
+};
+
+use crate::{Client, ClientResult};
+
+impl Client {
+    pub async fn list_blocks(&self) -> ClientResult<ListBlocksResponse> {
+        self.json(self.get("block")).await
+    }
+
+    pub async fn create_block(&self, request: &CreateBlockRequest) -> ClientResult<BlockResponse> {
+        self.json(self.post("block").json(request)).await
+    }
+
+    pub async fn get_block(&self, block_id: &str) -> ClientResult<BlockResponse> {
+        self.json(self.get(&format!("block/{}", block_id))).await
+    }
+
+    pub async fn update_block(
+        &self,
+        block_id: &str,
+        request: &UpdateBlockRequest,
+    ) -> ClientResult<BlockResponse> {
+        self.json(self.put(&format!("block/{}", block_id)).json(request))
+            .await
+    }
+
+    pub async fn delete_block(&self, block_id: &str) -> ClientResult<()> {
+        self.empty(self.delete(&format!("block/{}", block_id)))
+            .await
+    }
+}
@@ -0,0 +1,47 @@
+use paper_schema::comparison::{ComparePapersRequest, ComparisonResponse, ListComparisonsResponse};
+
+use crate::{Client, ClientResult, query};
+
+impl Client {
+    /// Compare papers with a model, the comparison is kept.
+    pub async fn create_comparison(
+        &self,
+        request: &ComparePapersRequest,
+    ) -> ClientResult<ComparisonResponse> {
+        self.json(self.post("paper/compare").json(request)).await
+    }
+
+    pub async fn list_comparisons(
+        &self,
+        limit: Option<i64>,
+    ) -> ClientResult<ListComparisonsResponse> {
+        let query = query(&[("limit", limit.map(|l| l.to_string()))]);
+        self.json(self.get("comparisons").query(&query)).await
+    }
+
+    pub async fn get_comparison(&self, comparison_id: &str) -> ClientResult<ComparisonResponse> {
+        self.json(self.get(&format!("comparisons/{}", comparison_id)))
+            .await
+    }
+
+    pub async fn regenerate_comparison(
+        &self,
+        comparison_id: &str,
+        model: Option<&str>,
+    ) -> ClientResult<ComparisonResponse> {
+        let path = format!("comparisons/{}/regenerate", comparison_id);
+        let query = query(&[("model", model.map(str::to_string))]);
+        self.json(self.post(&path).query(&query)).await
+    }
+
+    /// The comparison as markdown.
+    pub async fn export_comparison(&self, comparison_id: &str) -> ClientResult<Vec<u8>> {
+        self.bytes(self.get(&format!("comparisons/{}/export", comparison_id)))
+            .await
+    }
+
+    pub async fn delete_comparison(&self, comparison_id: &str) -> ClientResult<()> {
+        self.empty(self.delete(&format!("comparisons/{}", comparison_id)))
+            .await
+    }
+}
@@ -0,0 +1,83 @@
+use paper_schema::conversation::{
+    ConversationResponse, CreateConversationRequest, ListConversationsResponse,
+    ListMessagesResponse, SearchConversationsResponse, SendMessageRequest, SendMessageResponse,
+    UpdateConversationRequest,
+};
+
+use crate::{Client, ClientResult, query};
+
+impl Client {
+    pub async fn list_conversations(
+        &self,
+        limit: Option<i64>,
+    ) -> ClientResult<ListConversationsResponse> {
+        let query = query(&[("limit", limit.map(|l| l.to_string()))]);
+        self.json(self.get("conversations").query(&query)).await
+    }
+
+    pub async fn create_conversation(
+        &self,
+        request: &CreateConversationRequest,
+    ) -> ClientResult<ConversationResponse> {
+        self.json(self.post("conversations").json(request)).await
+    }
+
+    pub async fn search_conversations(
+        &self,
+        q: &str,
+        limit: Option<i64>,
+    ) -> ClientResult<SearchConversationsResponse> {
+        let query = query(&[
+            ("q", Some(q.to_string())),
+            ("limit", limit.map(|l| l.to_string())),
+        ]);
+        self.json(self.get("conversations/search").query(&query))
+            .await
+    }
+
+    pub async fn get_conversation(
+        &self,
+        conversation_id: &str,
+    ) -> ClientResult<ConversationResponse> {
+        self.json(self.get(&format!("conversations/{}", conversation_id)))
+            .await
+    }
+
+    pub async fn update_conversation(
+        &self,
+        conversation_id: &str,
+        request: &UpdateConversationRequest,
+    ) -> ClientResult<ConversationResponse> {
+        let path = format!("conversations/{}", conversation_id);
+        self.json(self.put(&path).json(request)).await
+    }
+
+    pub async fn delete_conversation(&self, conversation_id: &str) -> ClientResult<()> {
+        self.empty(self.delete(&format!("conversations/{}", conversation_id)))
+            .await
+    }
+
+    /// The messages sent before `before`, milliseconds since epoch, oldest first.
+    pub async fn list_messages(
+        &self,
+        conversation_id: &str,
+        before: Option<i64>,
+        limit: Option<i64>,
+    ) -> ClientResult<ListMessagesResponse> {
+        let path = format!("conversations/{}/messages", conversation_id);
+        let query = query(&[
+            ("before", before.map(|b| b.to_string())),
+            ("limit", limit.map(|l| l.to_string())),
+        ]);
+        self.json(self.get(&path).query(&query)).await
+    }
+
+    pub async fn send_message(
+        &self,
+        conversation_id: &str,
+        request: &SendMessageRequest,
+    ) -> ClientResult<SendMessageResponse> {
+        let path = format!("conversations/{}/messages", conversation_id);
+        self.json(self.post(&path).json(request)).await
+    }
+}
@@ -0,0 +1,24 @@
+use paper_schema::custom_field::{
+    CreateCustomFieldRequest, CustomFieldResponse, ListCustomFieldsResponse,
+};
+
+use crate::{Client, ClientResult};
+
+impl Client {
+    /// The custom fields of the team of the user.
+    pub async fn list_custom_fields(&self) -> ClientResult<ListCustomFieldsResponse> {
+        self.json(self.get("custom-fields")).await
+    }
+
+    pub async fn create_custom_field(
+        &self,
+        request: &CreateCustomFieldRequest,
+    ) -> ClientResult<CustomFieldResponse> {
+        self.json(self.post("custom-fields").json(request)).await
+    }
+
+    pub async fn delete_custom_field(&self, field_id: &str) -> ClientResult<()> {
+        self.empty(self.delete(&format!("custom-fields/{}", field_id)))
+            .await
+    }
+}
@@ -0,0 +1,127 @@
+use paper_schema::{
+    export::{ExportFormat, ExportJobResponse},
+    folder::{
+        CopyFolderRequest, CreateFolderRequest, FolderResponse, ImportFoldersRequest,
+        ImportFoldersResponse, ListFolderPapersResponse, ListFoldersResponse, MoveFolderRequest,
+        MoveFolderResponse, ReorderFoldersRequest, UpdateFolderRequest, WrapUpFolderResponse,
+    },
+    job::JobResponse,
+};
+
+use crate::{Client, ClientResult, Tagged, enum_value, if_match, query};
+
+/// The `auto_rename` of the writes suffixes the name of a folder taken by a
+/// sibling instead of answering 409.
+impl Client {
+    /// The folders of the user, after the virtual `starred` folder.
+    pub async fn list_folders(&self) -> ClientResult<ListFoldersResponse> {
+        self.json(self.get("folder")).await
+    }
+
+    pub async fn create_folder(
+        &self,
+        request: &CreateFolderRequest,
+        auto_rename: bool,
+    ) -> ClientResult<FolderResponse> {
+        let query = [("auto_rename", auto_rename)];
+        self.json(self.post("folder").query(&query).json(request))
+            .await
+    }
+
+    pub async fn reorder_folders(
+        &self,
+        request: &ReorderFoldersRequest,
+    ) -> ClientResult<ListFoldersResponse> {
+        self.json(self.put("folder/reorder").json(request)).await
+    }
+
+    pub async fn import_folders(
+        &self,
+        request: &ImportFoldersRequest,
+        auto_rename: bool,
+    ) -> ClientResult<ImportFoldersResponse> {
+        let query = [("auto_rename", auto_rename)];
+        self.json(self.post("folder/import").query(&query).json(request))
+            .await
+    }
+
+    pub async fn get_folder(&self, folder_id: &str) -> ClientResult<Tagged<FolderResponse>> {
+        self.tagged(self.get(&format!("folder/{}/literatures", folder_id)))
+            .await
+    }
+
+    /// A page of the papers of the folder, newest first, after the first `offset`.
+    pub async fn list_folder_papers(
+        &self,
+        folder_id: &str,
+        offset: Option<u64>,
+        limit: Option<i64>,
+    ) -> ClientResult<ListFolderPapersResponse> {
+        let path = format!("folder/{}/papers", folder_id);
+        let query = query(&[
+            ("offset", offset.map(|o| o.to_string())),
+            ("limit", limit.map(|l| l.to_string())),
+        ]);
+        self.json(self.get(&path).query(&query)).await
+    }
+
+    /// `etag` is the one of the folder read, answers 412 when it changed since.
+    pub async fn update_folder(
+        &self,
+        folder_id: &str,
+        etag: &str,
+        request: &UpdateFolderRequest,
+        auto_rename: bool,
+    ) -> ClientResult<Tagged<FolderResponse>> {
+        let request = self
+            .put(&format!("folder/{}", folder_id))
+            .query(&[("auto_rename", auto_rename)])
+            .json(request);
+        self.tagged(if_match(request, etag)).await
+    }
+
+    pub async fn move_folder(
+        &self,
+        folder_id: &str,
+        etag: &str,
+        request: &MoveFolderRequest,
+        auto_rename: bool,
+    ) -> ClientResult<Tagged<MoveFolderResponse>> {
+        let request = self
+            .post(&format!("folder/{}/move", folder_id))
+            .query(&[("auto_rename", auto_rename)])
+            .json(request);
+        self.tagged(if_match(request, etag)).await
+    }
+
+    /// Copies the folder, its papers in a job when there are many.
+    pub async fn copy_folder(
+        &self,
+        folder_id: &str,
+        request: &CopyFolderRequest,
+    ) -> ClientResult<JobResponse> {
+        let path = format!("folder/{}/copy", folder_id);
+        self.json(self.post(&path).json(request)).await
+    }
+
+    pub async fn export_folder(
+        &self,
+        folder_id: &str,
+        format: Option<ExportFormat>,
+    ) -> ClientResult<ExportJobResponse> {
+        let path = format!("folder/{}/export", folder_id);
+        let query = query(&[("format", format.and_then(enum_value))]);
+        self.json(self.get(&path).query(&query)).await
+    }
+
+    /// Summarizes the papers of the folder into a new paper, archiving the folder.
+    pub async fn wrap_up_folder(
+        &self,
+        folder_id: &str,
+        model: Option<&str>,
+    ) -> ClientResult<WrapUpFolderResponse> {
+        let path = format!("folder/{}/wrap-up", folder_id);
+        let query = query(&[("model", model.map(str::to_string))]);
+        self.json(self.post(&path).query(&query)).await
+    }
+}
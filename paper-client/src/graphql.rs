@@ -0,0 +1,12 @@
+use serde_json::{Value, json};
+
+use crate::{Client, ClientResult};
+
+impl Client {
+    /// Run a query of the `/graphql` endpoint, the response with its `data`
+    /// and `errors`.
+    pub async fn graphql(&self, query: &str, variables: Option<Value>) -> ClientResult<Value> {
+        let body = json!({ "query": query, "variables": variables });
+        self.json(self.post("graphql").json(&body)).await
+    }
+}
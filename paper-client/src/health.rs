@@ -0,0 +1,17 @@
+use paper_schema::health::HealthResponse;
+
+use crate::{Client, ClientResult};
+
+impl Client {
+    /// Whether the server is up, served next to the api.
+    pub async fn healthz(&self) -> ClientResult<HealthResponse> {
+        let url = format!("{}/healthz", self.base_url);
+        self.json(self.http.get(url)).await
+    }
+
+    /// Whether the server and its dependencies are ready, a 503 when not.
+    pub async fn readyz(&self) -> ClientResult<HealthResponse> {
+        let url = format!("{}/readyz", self.base_url);
+        self.json(self.http.get(url)).await
+    }
+}
@@ -0,0 +1,14 @@
+use paper_schema::job::{JobResponse, ListJobsResponse};
+
+use crate::{Client, ClientResult};
+
+impl Client {
+    /// The latest background jobs of the user.
+    pub async fn list_jobs(&self) -> ClientResult<ListJobsResponse> {
+        self.json(self.get("jobs")).await
+    }
+
+    pub async fn get_job(&self, job_id: &str) -> ClientResult<JobResponse> {
+        self.json(self.get(&format!("jobs/{}", job_id))).await
+    }
+}
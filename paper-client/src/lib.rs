@@ -0,0 +1,249 @@
+//! Paper client
+//!
+//! Typed client of the paper-backend REST api, with the request and response
+//! schemas of paper-schema shared with the server, so the cli, the integration
+//! tests and other services don't hand-roll http calls. The endpoints are
+//! methods of [`Client`], grouped in modules like the routers of the server.
+
+mod account;
+mod admin;
+mod ai;
+mod auth;
+mod block;
+mod comparison;
+mod conversation;
+mod custom_field;
+mod folder;
+mod graphql;
+mod health;
+mod job;
+mod llm_key;
+mod math;
+mod notification;
+mod organization;
+mod paper;
+mod reading_list;
+mod review;
+mod upload;
+mod user;
+mod webhook;
+
+pub use paper_schema::{
+    self as schema,
+    error::{ErrorCode, ErrorResponse, ValidationErrorResponse},
+    export::ExportFormat,
+    paper::PaperComparison,
+};
+use reqwest::{
+    Method, RequestBuilder, Response, StatusCode,
+    header::{ETAG, IF_MATCH},
+};
+use serde::{Serialize, de::DeserializeOwned};
+pub use upload::MAX_CHUNK_BYTES;
+
+/// Api version the client speaks, the routes are under `/api/{version}`.
+pub const API_VERSION: &str = "v1";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("{status}: {}", .error.message)]
+    Api {
+        status: StatusCode,
+        error: ErrorResponse,
+    },
+    #[error("{}: {:?}", .0.message, .0.errors)]
+    Validation(ValidationErrorResponse),
+    #[error("Unexpected response {status}: {body}")]
+    Unexpected { status: StatusCode, body: String },
+}
+
+impl ClientError {
+    /// The code of the error answered by the api.
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            ClientError::Api { error, .. } => Some(error.code),
+            ClientError::Validation(errors) => Some(errors.code),
+            _ => None,
+        }
+    }
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
+/// A document with its `ETag`, the `If-Match` of the updates of it.
+#[derive(Debug, Clone)]
+pub struct Tagged<T> {
+    pub value: T,
+    pub etag: String,
+}
+
+/// The api of a paper-backend deployment, signed in with an access token. The
+/// refresh token set by the logins is kept in the cookies of the client.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    // e.g. `http://127.0.0.1:7878`, without the `/api` suffix
+    base_url: String,
+    token: Option<String>,
+    // sent as `X-Tenant`, for the deployments serving several organizations
+    tenant: Option<String>,
+}
+
+impl Client {
+    pub fn new(base_url: &str) -> Self {
+        let http = reqwest::Client::builder()
+            .cookie_store(true)
+            .build()
+            .expect("Failed to build the http client");
+        Client {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: None,
+            tenant: None,
+        }
+    }
+
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    pub fn set_token(&mut self, token: Option<String>) {
+        self.token = token;
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/{}/{}", self.base_url, API_VERSION, path)
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let mut request = self.http.request(method, self.url(path));
+        if let Some(tenant) = &self.tenant {
+            request = request.header("x-tenant", tenant);
+        }
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.request(Method::GET, path)
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        self.request(Method::POST, path)
+    }
+
+    fn put(&self, path: &str) -> RequestBuilder {
+        self.request(Method::PUT, path)
+    }
+
+    fn delete(&self, path: &str) -> RequestBuilder {
+        self.request(Method::DELETE, path)
+    }
+
+    async fn send(&self, request: RequestBuilder) -> ClientResult<Response> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await?;
+        if status == StatusCode::UNPROCESSABLE_ENTITY {
+            if let Ok(errors) = serde_json::from_str(&body) {
+                return Err(ClientError::Validation(errors));
+            }
+        }
+        match serde_json::from_str(&body) {
+            Ok(error) => Err(ClientError::Api { status, error }),
+            Err(_) => Err(ClientError::Unexpected { status, body }),
+        }
+    }
+
+    async fn json<T: DeserializeOwned>(&self, request: RequestBuilder) -> ClientResult<T> {
+        Ok(self.send(request).await?.json().await?)
+    }
+
+    /// For the endpoints answering 204 or a body of no interest.
+    async fn empty(&self, request: RequestBuilder) -> ClientResult<()> {
+        self.send(request).await?;
+        Ok(())
+    }
+
+    /// For the downloads, e.g. an exported pdf.
+    async fn bytes(&self, request: RequestBuilder) -> ClientResult<Vec<u8>> {
+        Ok(self.send(request).await?.bytes().await?.to_vec())
+    }
+
+    async fn tagged<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> ClientResult<Tagged<T>> {
+        let response = self.send(request).await?;
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        Ok(Tagged {
+            value: response.json().await?,
+            etag,
+        })
+    }
+}
+
+fn if_match(request: RequestBuilder, etag: &str) -> RequestBuilder {
+    request.header(IF_MATCH, etag)
+}
+
+/// The query of the parameters given, the absent ones left out.
+fn query<'a>(params: &[(&'a str, Option<String>)]) -> Vec<(&'a str, String)> {
+    params
+        .iter()
+        .filter_map(|(name, value)| Some((*name, value.clone()?)))
+        .collect()
+}
+
+/// The query value of a serialized enum, e.g. `pdf` for `ExportFormat::Pdf`.
+fn enum_value<T: Serialize>(value: T) -> Option<String> {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(value)) => Some(value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query() {
+        let params = query(&[
+            ("folder_id", Some("f1".to_string())),
+            ("limit", None),
+            ("format", enum_value(ExportFormat::Md)),
+        ]);
+        assert_eq!(
+            params,
+            vec![
+                ("folder_id", "f1".to_string()),
+                ("format", "md".to_string())
+            ]
+        );
+        let client = Client::new("http://127.0.0.1:7878/");
+        assert_eq!(client.url("folder"), "http://127.0.0.1:7878/api/v1/folder");
+    }
+}
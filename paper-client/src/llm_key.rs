@@ -0,0 +1,28 @@
+use paper_schema::llm_key::{ListLlmKeysResponse, LlmKeyResponse, StoreLlmKeyRequest};
+
+use crate::{Client, ClientResult};
+
+impl Client {
+    pub async fn list_llm_keys(&self) -> ClientResult<ListLlmKeysResponse> {
+        self.json(self.get("llm-keys")).await
+    }
+
+    pub async fn store_llm_key(
+        &self,
+        provider: &str,
+        request: &StoreLlmKeyRequest,
+    ) -> ClientResult<LlmKeyResponse> {
+        let path = format!("llm-keys/{}", provider);
+        self.json(self.put(&path).json(request)).await
+    }
+
+    pub async fn validate_llm_key(&self, provider: &str) -> ClientResult<LlmKeyResponse> {
+        self.json(self.post(&format!("llm-keys/{}/validate", provider)))
+            .await
+    }
+
+    pub async fn delete_llm_key(&self, provider: &str) -> ClientResult<()> {
+        self.empty(self.delete(&format!("llm-keys/{}", provider)))
+            .await
+    }
+}
@@ -0,0 +1,13 @@
+use paper_schema::math::{RenderMathRequest, RenderMathResponse};
+
+use crate::{Client, ClientResult};
+
+impl Client {
+    /// The texts with their LaTeX formulas rendered as MathML.
+    pub async fn render_math(
+        &self,
+        request: &RenderMathRequest,
+    ) -> ClientResult<RenderMathResponse> {
+        self.json(self.post("math/render").json(request)).await
+    }
+}
@@ -0,0 +1,33 @@
+use paper_schema::notification::{ListNotificationsResponse, UnreadCountResponse};
+
+use crate::{Client, ClientResult, query};
+
+impl Client {
+    pub async fn list_notifications(
+        &self,
+        unread_only: bool,
+        limit: Option<i64>,
+    ) -> ClientResult<ListNotificationsResponse> {
+        let query = query(&[
+            ("unread_only", Some(unread_only.to_string())),
+            ("limit", limit.map(|l| l.to_string())),
+        ]);
+        self.json(self.get("notifications").query(&query)).await
+    }
+
+    pub async fn get_unread_count(&self) -> ClientResult<UnreadCountResponse> {
+        self.json(self.get("notifications/unread-count")).await
+    }
+
+    pub async fn mark_notification_read(
+        &self,
+        notification_id: &str,
+    ) -> ClientResult<UnreadCountResponse> {
+        let path = format!("notifications/{}/read", notification_id);
+        self.json(self.post(&path)).await
+    }
+
+    pub async fn mark_all_notifications_read(&self) -> ClientResult<UnreadCountResponse> {
+        self.json(self.post("notifications/read-all")).await
+    }
+}
@@ -0,0 +1,36 @@
+use paper_schema::organization::{
+    AddMemberRequest, CreateOrganizationRequest, OrganizationResponse, UpdateFolderTemplateRequest,
+};
+
+use crate::{Client, ClientResult};
+
+impl Client {
+    pub async fn create_organization(
+        &self,
+        request: &CreateOrganizationRequest,
+    ) -> ClientResult<OrganizationResponse> {
+        self.json(self.post("org").json(request)).await
+    }
+
+    pub async fn get_organization(&self, org_id: &str) -> ClientResult<OrganizationResponse> {
+        self.json(self.get(&format!("org/{}", org_id))).await
+    }
+
+    pub async fn update_folder_template(
+        &self,
+        org_id: &str,
+        request: &UpdateFolderTemplateRequest,
+    ) -> ClientResult<OrganizationResponse> {
+        let path = format!("org/{}/folder-template", org_id);
+        self.json(self.put(&path).json(request)).await
+    }
+
+    pub async fn add_member(
+        &self,
+        org_id: &str,
+        request: &AddMemberRequest,
+    ) -> ClientResult<OrganizationResponse> {
+        let path = format!("org/{}/member", org_id);
+        self.json(self.post(&path).json(request)).await
+    }
+}
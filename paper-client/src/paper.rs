@@ -0,0 +1,321 @@
+use paper_schema::{
+    chunk::{AskPaperRequest, AskPaperResponse},
+    citation::{GraphResponse, PaperCitationsResponse},
+    export::ExportFormat,
+    math::PaperMathResponse,
+    note::{NotesResponse, UpdateNotesRequest},
+    page::PaperTextResponse,
+    paper::{
+        AcceptSuggestionsRequest, BatchPaperRequest, BatchPaperResponse, CreatePaperRequest,
+        ListDuplicatesResponse, ListPapersResponse, MergePapersRequest, PaperComparison,
+        PaperFileUrlResponse, PaperResponse, RelatedPapersResponse, SearchPapersResponse,
+        UpdatePaperRequest,
+    },
+    revision::{ListPaperRevisionsResponse, PaperRevisionDiffResponse, PaperRevisionResponse},
+    share::{
+        CreateShareLinkRequest, ListCommentsResponse, ListShareLinksResponse, ShareLinkResponse,
+    },
+};
+use reqwest::header::CONTENT_TYPE;
+
+use crate::{Client, ClientResult, Tagged, enum_value, if_match, query};
+
+impl Client {
+    /// The papers of a folder, or of every folder, newest first. `field` filters on
+    /// custom fields, e.g. `status=read,pages>=10`.
+    pub async fn list_papers(
+        &self,
+        folder_id: Option<&str>,
+        field: Option<&str>,
+    ) -> ClientResult<ListPapersResponse> {
+        let query = query(&[
+            ("folder_id", folder_id.map(str::to_string)),
+            ("field", field.map(str::to_string)),
+        ]);
+        self.json(self.get("paper").query(&query)).await
+    }
+
+    pub async fn list_starred_papers(&self) -> ClientResult<ListPapersResponse> {
+        self.json(self.get("paper/starred")).await
+    }
+
+    pub async fn create_paper(&self, request: &CreatePaperRequest) -> ClientResult<PaperResponse> {
+        self.json(self.post("paper").json(request)).await
+    }
+
+    pub async fn batch_papers(
+        &self,
+        request: &BatchPaperRequest,
+    ) -> ClientResult<BatchPaperResponse> {
+        self.json(self.post("paper/batch").json(request)).await
+    }
+
+    pub async fn list_duplicates(&self) -> ClientResult<ListDuplicatesResponse> {
+        self.json(self.get("paper/duplicates")).await
+    }
+
+    pub async fn merge_papers(&self, request: &MergePapersRequest) -> ClientResult<PaperResponse> {
+        self.json(self.post("paper/merge").json(request)).await
+    }
+
+    /// The structured diff of two papers, without a model.
+    pub async fn compare_papers(&self, a: &str, b: &str) -> ClientResult<PaperComparison> {
+        let query = [("a", a), ("b", b)];
+        self.json(self.get("paper/compare").query(&query)).await
+    }
+
+    pub async fn search_papers(
+        &self,
+        q: &str,
+        folder_id: Option<&str>,
+        field: Option<&str>,
+        limit: Option<i64>,
+    ) -> ClientResult<SearchPapersResponse> {
+        let query = query(&[
+            ("q", Some(q.to_string())),
+            ("folder_id", folder_id.map(str::to_string)),
+            ("field", field.map(str::to_string)),
+            ("limit", limit.map(|l| l.to_string())),
+        ]);
+        self.json(self.get("paper/search").query(&query)).await
+    }
+
+    pub async fn get_paper(&self, paper_id: &str) -> ClientResult<Tagged<PaperResponse>> {
+        self.tagged(self.get(&format!("paper/{}", paper_id))).await
+    }
+
+    /// `etag` is the one of the paper read, answers 412 when it changed since.
+    pub async fn update_paper(
+        &self,
+        paper_id: &str,
+        etag: &str,
+        request: &UpdatePaperRequest,
+    ) -> ClientResult<Tagged<PaperResponse>> {
+        let request = self.put(&format!("paper/{}", paper_id)).json(request);
+        self.tagged(if_match(request, etag)).await
+    }
+
+    pub async fn delete_paper(&self, paper_id: &str) -> ClientResult<()> {
+        self.empty(self.delete(&format!("paper/{}", paper_id)))
+            .await
+    }
+
+    /// Records that the paper was opened, for the recent papers.
+    pub async fn touch_paper(&self, paper_id: &str) -> ClientResult<()> {
+        self.empty(self.post(&format!("paper/{}/touch", paper_id)))
+            .await
+    }
+
+    pub async fn star_paper(&self, paper_id: &str) -> ClientResult<()> {
+        self.empty(self.post(&format!("paper/{}/star", paper_id)))
+            .await
+    }
+
+    pub async fn unstar_paper(&self, paper_id: &str) -> ClientResult<()> {
+        self.empty(self.delete(&format!("paper/{}/star", paper_id)))
+            .await
+    }
+
+    /// The paper as a document, pdf by default.
+    pub async fn export_paper(
+        &self,
+        paper_id: &str,
+        format: Option<ExportFormat>,
+        watermark: Option<&str>,
+    ) -> ClientResult<Vec<u8>> {
+        let path = format!("paper/{}/export", paper_id);
+        let query = query(&[
+            ("format", format.and_then(enum_value)),
+            ("watermark", watermark.map(str::to_string)),
+        ]);
+        self.bytes(self.get(&path).query(&query)).await
+    }
+
+    /// Uploads the pdf of the paper, `ocr` recognizes the text of scanned pages.
+    pub async fn upload_paper_file(
+        &self,
+        paper_id: &str,
+        pdf: Vec<u8>,
+        ocr: bool,
+    ) -> ClientResult<PaperResponse> {
+        let request = self
+            .put(&format!("paper/{}/file", paper_id))
+            .query(&[("ocr", ocr)])
+            .header(CONTENT_TYPE, "application/pdf")
+            .body(pdf);
+        self.json(request).await
+    }
+
+    /// A signed url downloading the pdf without authentication until it expires.
+    pub async fn get_paper_file_url(&self, paper_id: &str) -> ClientResult<PaperFileUrlResponse> {
+        let path = format!("paper/{}/file/url", paper_id);
+        self.json(self.get(&path)).await
+    }
+
+    /// The png of the first page of the pdf, `medium` by default.
+    pub async fn get_paper_thumbnail(
+        &self,
+        paper_id: &str,
+        size: Option<ThumbnailSize>,
+    ) -> ClientResult<Vec<u8>> {
+        let path = format!("paper/{}/thumbnail", paper_id);
+        let query = query(&[("size", size.map(|size| size.as_str().to_string()))]);
+        self.bytes(self.get(&path).query(&query)).await
+    }
+
+    /// The text of the pdf, of every page or of the one given.
+    pub async fn get_paper_text(
+        &self,
+        paper_id: &str,
+        page: Option<u32>,
+    ) -> ClientResult<PaperTextResponse> {
+        let path = format!("paper/{}/text", paper_id);
+        let query = query(&[("page", page.map(|p| p.to_string()))]);
+        self.json(self.get(&path).query(&query)).await
+    }
+
+    /// The abstract and notes of the paper with their formulas as MathML.
+    pub async fn get_paper_math(&self, paper_id: &str) -> ClientResult<PaperMathResponse> {
+        self.json(self.get(&format!("paper/{}/math", paper_id)))
+            .await
+    }
+
+    pub async fn ask_paper(
+        &self,
+        paper_id: &str,
+        request: &AskPaperRequest,
+    ) -> ClientResult<AskPaperResponse> {
+        let path = format!("paper/{}/ask", paper_id);
+        self.json(self.post(&path).json(request)).await
+    }
+
+    pub async fn get_related_papers(
+        &self,
+        paper_id: &str,
+        limit: Option<usize>,
+        external: bool,
+    ) -> ClientResult<RelatedPapersResponse> {
+        let path = format!("paper/{}/related", paper_id);
+        let query = query(&[
+            ("limit", limit.map(|l| l.to_string())),
+            ("external", Some(external.to_string())),
+        ]);
+        self.json(self.get(&path).query(&query)).await
+    }
+
+    pub async fn accept_suggestions(
+        &self,
+        paper_id: &str,
+        request: &AcceptSuggestionsRequest,
+    ) -> ClientResult<Tagged<PaperResponse>> {
+        let path = format!("paper/{}/suggestions/accept", paper_id);
+        self.tagged(self.post(&path).json(request)).await
+    }
+
+    pub async fn get_notes(&self, paper_id: &str) -> ClientResult<NotesResponse> {
+        self.json(self.get(&format!("paper/{}/notes", paper_id)))
+            .await
+    }
+
+    pub async fn update_notes(
+        &self,
+        paper_id: &str,
+        request: &UpdateNotesRequest,
+    ) -> ClientResult<NotesResponse> {
+        let path = format!("paper/{}/notes", paper_id);
+        self.json(self.post(&path).json(request)).await
+    }
+
+    pub async fn list_paper_revisions(
+        &self,
+        paper_id: &str,
+    ) -> ClientResult<ListPaperRevisionsResponse> {
+        self.json(self.get(&format!("paper/{}/revisions", paper_id)))
+            .await
+    }
+
+    pub async fn get_paper_revision(
+        &self,
+        paper_id: &str,
+        revision: u32,
+    ) -> ClientResult<PaperRevisionResponse> {
+        let path = format!("paper/{}/revisions/{}", paper_id, revision);
+        self.json(self.get(&path)).await
+    }
+
+    /// The changes from a revision to another one, or to the current paper.
+    pub async fn diff_paper_revisions(
+        &self,
+        paper_id: &str,
+        from: u32,
+        to: Option<u32>,
+    ) -> ClientResult<PaperRevisionDiffResponse> {
+        let path = format!("paper/{}/revisions/diff", paper_id);
+        let query = query(&[
+            ("from", Some(from.to_string())),
+            ("to", to.map(|t| t.to_string())),
+        ]);
+        self.json(self.get(&path).query(&query)).await
+    }
+
+    pub async fn restore_paper_revision(
+        &self,
+        paper_id: &str,
+        revision: u32,
+    ) -> ClientResult<Tagged<PaperResponse>> {
+        let path = format!("paper/{}/revisions/{}/restore", paper_id, revision);
+        self.tagged(self.post(&path)).await
+    }
+
+    pub async fn get_paper_citations(
+        &self,
+        paper_id: &str,
+    ) -> ClientResult<PaperCitationsResponse> {
+        self.json(self.get(&format!("paper/{}/citations", paper_id)))
+            .await
+    }
+
+    /// Looks the citations of the paper up again.
+    pub async fn refresh_paper_citations(
+        &self,
+        paper_id: &str,
+    ) -> ClientResult<PaperCitationsResponse> {
+        self.json(self.post(&format!("paper/{}/citations", paper_id)))
+            .await
+    }
+
+    /// The citation graph of the papers of the user.
+    pub async fn get_graph(&self) -> ClientResult<GraphResponse> {
+        self.json(self.get("graph")).await
+    }
+
+    pub async fn list_share_links(&self, paper_id: &str) -> ClientResult<ListShareLinksResponse> {
+        self.json(self.get(&format!("paper/{}/share-link", paper_id)))
+            .await
+    }
+
+    pub async fn create_share_link(
+        &self,
+        paper_id: &str,
+        request: &CreateShareLinkRequest,
+    ) -> ClientResult<ShareLinkResponse> {
+        let path = format!("paper/{}/share-link", paper_id);
+        self.json(self.post(&path).json(request)).await
+    }
+
+    pub async fn revoke_share_link(&self, paper_id: &str, link_id: &str) -> ClientResult<()> {
+        let path = format!("paper/{}/share-link/{}", paper_id, link_id);
+        self.empty(self.delete(&path)).await
+    }
+
+    /// The comments left on the paper through its share links.
+    pub async fn list_comments(&self, paper_id: &str) -> ClientResult<ListCommentsResponse> {
+        self.json(self.get(&format!("paper/{}/comment", paper_id)))
+            .await
+    }
+
+    pub async fn delete_comment(&self, paper_id: &str, comment_id: &str) -> ClientResult<()> {
+        let path = format!("paper/{}/comment/{}", paper_id, comment_id);
+        self.empty(self.delete(&path)).await
+    }
+}
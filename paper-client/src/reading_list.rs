@@ -0,0 +1,41 @@
+use paper_schema::reading_list::{
+    AddReadingListItemRequest, ReadingListItemResponse, ReadingListResponse,
+    ReorderReadingListRequest, UpdateReadingListItemRequest,
+};
+
+use crate::{Client, ClientResult};
+
+impl Client {
+    pub async fn get_reading_list(&self) -> ClientResult<ReadingListResponse> {
+        self.json(self.get("reading-list")).await
+    }
+
+    pub async fn add_to_reading_list(
+        &self,
+        request: &AddReadingListItemRequest,
+    ) -> ClientResult<ReadingListItemResponse> {
+        self.json(self.post("reading-list").json(request)).await
+    }
+
+    pub async fn reorder_reading_list(
+        &self,
+        request: &ReorderReadingListRequest,
+    ) -> ClientResult<ReadingListResponse> {
+        self.json(self.put("reading-list/order").json(request))
+            .await
+    }
+
+    pub async fn update_reading_list_item(
+        &self,
+        paper_id: &str,
+        request: &UpdateReadingListItemRequest,
+    ) -> ClientResult<ReadingListItemResponse> {
+        let path = format!("reading-list/{}", paper_id);
+        self.json(self.put(&path).json(request)).await
+    }
+
+    pub async fn remove_from_reading_list(&self, paper_id: &str) -> ClientResult<()> {
+        self.empty(self.delete(&format!("reading-list/{}", paper_id)))
+            .await
+    }
+}
@@ -0,0 +1,43 @@
+use paper_schema::{
+    note::{NotesResponse, UpdateNotesRequest},
+    share::{CommentResponse, CreateCommentRequest, ReviewResponse},
+};
+
+use crate::{Client, ClientResult};
+
+/// The endpoints of a share link, signed in or not.
+impl Client {
+    pub async fn get_review(&self, token: &str) -> ClientResult<ReviewResponse> {
+        self.json(self.get(&format!("review/{}", token))).await
+    }
+
+    pub async fn create_review_comment(
+        &self,
+        token: &str,
+        request: &CreateCommentRequest,
+    ) -> ClientResult<CommentResponse> {
+        let path = format!("review/{}/comment", token);
+        self.json(self.post(&path).json(request)).await
+    }
+
+    /// The shared paper as a pdf.
+    pub async fn export_review(&self, token: &str) -> ClientResult<Vec<u8>> {
+        self.bytes(self.get(&format!("review/{}/export", token)))
+            .await
+    }
+
+    pub async fn get_review_notes(&self, token: &str) -> ClientResult<NotesResponse> {
+        self.json(self.get(&format!("review/{}/notes", token)))
+            .await
+    }
+
+    /// Needs a share link with the `edit` permission.
+    pub async fn update_review_notes(
+        &self,
+        token: &str,
+        request: &UpdateNotesRequest,
+    ) -> ClientResult<NotesResponse> {
+        let path = format!("review/{}/notes", token);
+        self.json(self.post(&path).json(request)).await
+    }
+}
@@ -0,0 +1,133 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use reqwest::{
+    Method, Response,
+    header::{CONTENT_TYPE, LOCATION},
+};
+use sha2::{Digest, Sha256};
+
+use crate::{Client, ClientError, ClientResult};
+
+const TUS_VERSION: &str = "1.0.0";
+const CHUNK_CONTENT_TYPE: &str = "application/offset+octet-stream";
+// chunks sent again after a dropped connection, in a row
+const MAX_CHUNK_RETRIES: u32 = 5;
+
+/// The largest chunk the server accepts.
+pub const MAX_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+fn upload_path(upload_id: &str) -> String {
+    format!("uploads/{}", upload_id)
+}
+
+fn header(response: &Response, name: &str) -> ClientResult<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .ok_or_else(|| ClientError::Unexpected {
+            status: response.status(),
+            body: format!("Missing {} header", name),
+        })
+}
+
+fn offset_header(response: &Response) -> ClientResult<u64> {
+    let offset = header(response, "upload-offset")?;
+    offset.parse().map_err(|_| ClientError::Unexpected {
+        status: response.status(),
+        body: format!("Invalid Upload-Offset {}", offset),
+    })
+}
+
+impl Client {
+    /// Starts a resumable upload of the pdf of the paper, returns the id of the upload.
+    pub async fn create_upload(
+        &self,
+        paper_id: &str,
+        length: u64,
+        ocr: bool,
+    ) -> ClientResult<String> {
+        let metadata = format!(
+            "paperId {},ocr {}",
+            STANDARD.encode(paper_id),
+            STANDARD.encode(ocr.to_string())
+        );
+        let request = self
+            .post("uploads")
+            .header("tus-resumable", TUS_VERSION)
+            .header("upload-length", length)
+            .header("upload-metadata", metadata);
+        let response = self.send(request).await?;
+        // the path of the upload, e.g. `/api/v1/uploads/{id}`
+        let location = header(&response, LOCATION.as_str())?;
+        Ok(location.rsplit('/').next().unwrap_or_default().to_string())
+    }
+
+    /// The bytes of the upload received so far, where it resumes.
+    pub async fn get_upload_offset(&self, upload_id: &str) -> ClientResult<u64> {
+        let request = self
+            .request(Method::HEAD, &upload_path(upload_id))
+            .header("tus-resumable", TUS_VERSION);
+        offset_header(&self.send(request).await?)
+    }
+
+    /// Sends the chunk of the file at the offset, returns the new offset. The
+    /// chunk completing the file attaches it to the paper.
+    pub async fn upload_chunk(
+        &self,
+        upload_id: &str,
+        offset: u64,
+        chunk: &[u8],
+    ) -> ClientResult<u64> {
+        let checksum = format!("sha256 {}", STANDARD.encode(Sha256::digest(chunk)));
+        let request = self
+            .request(Method::PATCH, &upload_path(upload_id))
+            .header("tus-resumable", TUS_VERSION)
+            .header("upload-offset", offset)
+            .header("upload-checksum", checksum)
+            .header(CONTENT_TYPE, CHUNK_CONTENT_TYPE)
+            .body(chunk.to_vec());
+        offset_header(&self.send(request).await?)
+    }
+
+    pub async fn delete_upload(&self, upload_id: &str) -> ClientResult<()> {
+        let request = self
+            .delete(&upload_path(upload_id))
+            .header("tus-resumable", TUS_VERSION);
+        self.empty(request).await
+    }
+
+    /// Uploads the pdf of the paper in chunks of `chunk_size`, up to
+    /// [`MAX_CHUNK_BYTES`]. After a dropped connection the upload resumes from
+    /// the offset the server received.
+    pub async fn upload_paper_file_resumable(
+        &self,
+        paper_id: &str,
+        pdf: &[u8],
+        ocr: bool,
+        chunk_size: usize,
+    ) -> ClientResult<()> {
+        let chunk_size = chunk_size.clamp(1, MAX_CHUNK_BYTES);
+        let upload_id = self.create_upload(paper_id, pdf.len() as u64, ocr).await?;
+        let mut offset = 0;
+        let mut retries = 0;
+        while offset < pdf.len() {
+            let end = (offset + chunk_size).min(pdf.len());
+            match self
+                .upload_chunk(&upload_id, offset as u64, &pdf[offset..end])
+                .await
+            {
+                Ok(received) => {
+                    offset = received as usize;
+                    retries = 0;
+                }
+                Err(ClientError::Http(_)) if retries < MAX_CHUNK_RETRIES => {
+                    retries += 1;
+                    offset = self.get_upload_offset(&upload_id).await? as usize;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
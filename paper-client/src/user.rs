@@ -0,0 +1,41 @@
+use paper_schema::user::{
+    NotificationPreferencesResponse, SettingsResponse, UpdateNotificationPreferencesRequest,
+    UpdateSettingsRequest, UpdateUserInfo, UserInfoResponse,
+};
+
+use crate::{Client, ClientResult};
+
+impl Client {
+    pub async fn get_user_info(&self) -> ClientResult<UserInfoResponse> {
+        self.json(self.get("user/info")).await
+    }
+
+    pub async fn update_user_info(&self, info: &UpdateUserInfo) -> ClientResult<()> {
+        self.empty(self.post("user/info").json(info)).await
+    }
+
+    pub async fn get_notification_preferences(
+        &self,
+    ) -> ClientResult<NotificationPreferencesResponse> {
+        self.json(self.get("user/notification-preferences")).await
+    }
+
+    pub async fn update_notification_preferences(
+        &self,
+        request: &UpdateNotificationPreferencesRequest,
+    ) -> ClientResult<NotificationPreferencesResponse> {
+        self.json(self.put("user/notification-preferences").json(request))
+            .await
+    }
+
+    pub async fn get_settings(&self) -> ClientResult<SettingsResponse> {
+        self.json(self.get("settings")).await
+    }
+
+    pub async fn update_settings(
+        &self,
+        request: &UpdateSettingsRequest,
+    ) -> ClientResult<SettingsResponse> {
+        self.json(self.put("settings").json(request)).await
+    }
+}
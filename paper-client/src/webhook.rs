@@ -0,0 +1,33 @@
+use paper_schema::webhook::{
+    CreateWebhookRequest, ListWebhookDeliveriesResponse, ListWebhooksResponse, WebhookResponse,
+};
+
+use crate::{Client, ClientResult, query};
+
+impl Client {
+    pub async fn list_webhooks(&self) -> ClientResult<ListWebhooksResponse> {
+        self.json(self.get("webhooks")).await
+    }
+
+    pub async fn create_webhook(
+        &self,
+        request: &CreateWebhookRequest,
+    ) -> ClientResult<WebhookResponse> {
+        self.json(self.post("webhooks").json(request)).await
+    }
+
+    pub async fn delete_webhook(&self, webhook_id: &str) -> ClientResult<()> {
+        self.empty(self.delete(&format!("webhooks/{}", webhook_id)))
+            .await
+    }
+
+    pub async fn list_webhook_deliveries(
+        &self,
+        webhook_id: &str,
+        limit: Option<i64>,
+    ) -> ClientResult<ListWebhookDeliveriesResponse> {
+        let path = format!("webhooks/{}/deliveries", webhook_id);
+        let query = query(&[("limit", limit.map(|l| l.to_string()))]);
+        self.json(self.get(&path).query(&query)).await
+    }
+}
@@ -0,0 +1,48 @@
+#[cfg(feature = "salvo")]
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::conversation::{ConversationResponse, MessageResponse};
+
+/// Delete Account Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteAccountRequest {
+    /// Current password, required when the account has one
+    #[validate(length(max = 128))]
+    pub password: Option<String>,
+    /// Token emailed to confirm the deletion of an account without password
+    #[validate(length(max = 2048))]
+    pub confirmation_token: Option<String>,
+}
+
+/// Manifest at the root of an account archive.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TakeoutManifest {
+    pub user_id: String,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub exported_at: i64,
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    pub path: String,
+    pub description: String,
+    // records in a json file, absent for documents and uploads
+    pub count: Option<usize>,
+}
+
+/// A conversation of an account archive with all its messages, oldest first.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TakeoutConversation {
+    #[serde(flatten)]
+    pub conversation: ConversationResponse,
+    pub messages: Vec<MessageResponse>,
+}
@@ -0,0 +1,51 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Response schema for a recently opened paper or folder.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityResponse {
+    pub kind: ActivityKind,
+    pub resource_id: String,
+    /// Current title of the paper or name of the folder
+    pub title: String,
+    /// What the user last did with it
+    pub action: ActivityAction,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub at: i64,
+}
+
+/// Response schema for the activity feed, most recent first.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+pub struct ListActivityResponse(pub Vec<ActivityResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListActivityResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityKind {
+    Paper,
+    Folder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityAction {
+    Viewed,
+    Edited,
+}
@@ -0,0 +1,66 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Estimate Request schema.
+/// The input is the prompt plus the text of the given papers, as sent to the model.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct EstimateRequest {
+    /// Defaults to the configured model
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "deepseek-chat")))]
+    pub model: Option<String>,
+    #[validate(length(max = 200000))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "Summarize the key findings of these papers."))
+    )]
+    pub prompt: Option<String>,
+    #[validate(length(max = 500))]
+    #[serde(default)]
+    pub paper_ids: Vec<String>,
+    /// Expected length of the answer, defaults to 1024 tokens
+    #[validate(range(min = 1, max = 100000))]
+    pub max_output_tokens: Option<u64>,
+}
+
+/// Estimated usage of a single model.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ModelEstimate {
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// In USD
+    pub input_cost: f64,
+    /// In USD
+    pub output_cost: f64,
+    /// In USD
+    pub total_cost: f64,
+}
+
+/// Response schema for the cost estimate.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct EstimateResponse {
+    pub estimate: ModelEstimate,
+    /// Same request on the other supported models, cheapest first
+    pub alternatives: Vec<ModelEstimate>,
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "USD")))]
+    pub currency: String,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for EstimateResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
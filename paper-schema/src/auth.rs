@@ -0,0 +1,118 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Phone Login Request Body
+#[derive(Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct PhoneLogin {
+    #[validate(length(equal = 11))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(max_length = 11, min_length = 11, example = "139xxxx1234"))
+    )]
+    pub phone: String,
+    #[validate(length(equal = 6))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(max_length = 6, min_length = 6, example = "123456"))
+    )]
+    pub code: String,
+}
+
+/// Email Register Request Body
+#[derive(Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct EmailRegister {
+    #[validate(email)]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "someone@example.com")))]
+    pub email: String,
+    #[validate(length(min = 8, max = 64))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(min_length = 8, max_length = 64, example = "password"))
+    )]
+    pub password: String,
+    #[validate(length(min = 1, max = 32))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "someone")))]
+    pub username: Option<String>,
+}
+
+/// Email Login Request Body
+#[derive(Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct EmailLogin {
+    #[validate(email)]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "someone@example.com")))]
+    pub email: String,
+    #[validate(length(min = 1))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "password")))]
+    pub password: String,
+}
+
+/// Forgot Password Request Body
+#[derive(Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ForgotPassword {
+    #[validate(email)]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "someone@example.com")))]
+    pub email: String,
+}
+
+/// Reset Password Request Body
+#[derive(Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ResetPassword {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "reset.token.here")))]
+    pub token: String,
+    #[validate(length(min = 8, max = 64))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(min_length = 8, max_length = 64, example = "new-password"))
+    )]
+    pub password: String,
+}
+
+/// Register Response Body
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterResult {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "user-uuid")))]
+    pub user_id: String,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for RegisterResult {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Login Response Body
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct LoginResult {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "jwt.token.here")))]
+    pub access_token: String,
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "user-uuid")))]
+    pub user_id: String,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for LoginResult {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
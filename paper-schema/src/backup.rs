@@ -0,0 +1,77 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::paper::Progress;
+
+/// Response schema for a backup or a restore, run in the background.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct BackupJobResponse {
+    pub id: String,
+    pub kind: BackupKind,
+    /// The backup restored, for a restore
+    pub backup_id: Option<String>,
+    pub status: BackupStatus,
+    /// Collections and blobs copied so far
+    pub progress: Option<Progress>,
+    /// Bytes of the snapshot, once ready
+    pub size: Option<u64>,
+    pub error: Option<String>,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+    #[serde(default, with = "crate::time::option_millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = Option<String>, format = DateTime)))]
+    pub finished_at: Option<i64>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for BackupJobResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Response schema for the backups and restores, the latest first.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+pub struct ListBackupJobsResponse(pub Vec<BackupJobResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListBackupJobsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Request schema to restore a backup.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreBackupRequest {
+    /// A backup ready to be restored
+    pub backup_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum BackupKind {
+    Backup,
+    Restore,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum BackupStatus {
+    Running,
+    Ready,
+    Failed,
+}
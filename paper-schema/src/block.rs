@@ -0,0 +1,69 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Response schema for a reusable content block.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct BlockResponse {
+    pub id: String,
+    pub name: String,
+    pub content: String,
+    /// Reference to put into a paper, expanded at export time
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "{{block:block-uuid}}")))]
+    pub reference: String,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for BlockResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct ListBlocksResponse(pub Vec<BlockResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListBlocksResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Create Block Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CreateBlockRequest {
+    #[validate(length(min = 1, max = 64))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(min_length = 1, max_length = 64, example = "Acknowledgments"))
+    )]
+    pub name: String,
+    #[validate(length(max = 20000))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(max_length = 20000, example = "We thank ..."))
+    )]
+    pub content: String,
+}
+
+/// Update Block Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateBlockRequest {
+    #[validate(length(min = 1, max = 64))]
+    pub name: Option<String>,
+    #[validate(length(max = 20000))]
+    pub content: Option<String>,
+}
@@ -0,0 +1,53 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Ask Paper Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct AskPaperRequest {
+    #[validate(length(min = 1, max = 2000))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "Which datasets were used for the evaluation?"))
+    )]
+    pub question: String,
+    /// Defaults to the configured model
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "deepseek-chat")))]
+    pub model: Option<String>,
+}
+
+/// A passage of the paper supporting the answer.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct QuoteResponse {
+    /// 1 based page number
+    pub page: u32,
+    pub text: String,
+}
+
+/// Response schema for a question answered from the text of a paper.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct AskPaperResponse {
+    pub answer: String,
+    /// Verbatim quotes of the paper the answer relies on, empty when the
+    /// paper does not answer the question
+    pub quotes: Vec<QuoteResponse>,
+    pub model: String,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for AskPaperResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
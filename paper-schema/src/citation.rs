@@ -0,0 +1,73 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CitationResponse {
+    /// The paper containing the reference
+    pub citing_id: String,
+    /// The referenced paper, when it is in the library
+    pub cited_id: Option<String>,
+    /// The reference as written in the reference list
+    pub raw: String,
+    pub doi: Option<String>,
+}
+
+/// Response schema for the citations of a paper.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct PaperCitationsResponse {
+    /// Papers referenced by this paper
+    pub references: Vec<CitationResponse>,
+    /// Papers of the library referencing this paper
+    pub cited_by: Vec<CitationResponse>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for PaperCitationsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct GraphNode {
+    pub id: String,
+    pub title: String,
+    pub folder_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct GraphEdge {
+    /// Id of the citing paper
+    pub source: String,
+    /// Id of the cited paper
+    pub target: String,
+}
+
+/// Response schema for the citation graph of the library.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct GraphResponse {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for GraphResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
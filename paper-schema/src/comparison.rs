@@ -0,0 +1,82 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+fn validate_distinct(ids: &[String]) -> Result<(), ValidationError> {
+    if ids.iter().enumerate().any(|(i, id)| ids[..i].contains(id)) {
+        let mut error = ValidationError::new("same_paper");
+        error.message = Some("each paper can be compared only once".into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Compare Papers Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ComparePapersRequest {
+    #[validate(length(min = 2, max = 5), custom(function = "validate_distinct"))]
+    pub paper_ids: Vec<String>,
+    /// Defaults to the configured model
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "deepseek-chat")))]
+    pub model: Option<String>,
+}
+
+/// Response schema for a comparison of papers.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonResponse {
+    pub id: String,
+    pub paper_ids: Vec<String>,
+    pub summary: String,
+    /// In the order of `paperIds`
+    pub papers: Vec<ComparedPaper>,
+    /// Findings of the papers that disagree
+    pub contradictions: Vec<String>,
+    pub model: String,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub generated_at: i64,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for ComparisonResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Response schema for the comparisons of the user, newest first.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct ListComparisonsResponse(pub Vec<ComparisonResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListComparisonsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// What the comparison says of one of the papers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ComparedPaper {
+    pub paper_id: String,
+    // title at the time of the comparison
+    pub title: String,
+    pub methods: String,
+    pub datasets: String,
+    pub results: String,
+}
@@ -0,0 +1,62 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct LegalDocumentResponse {
+    pub version: String,
+    pub url: String,
+}
+
+/// Response schema for the current legal documents of the instance.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct LegalDocumentsResponse {
+    pub terms: Option<LegalDocumentResponse>,
+    pub privacy_policy: Option<LegalDocumentResponse>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for LegalDocumentsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Response schema for the consent of the user.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ConsentResponse {
+    /// Versions the user accepted last
+    pub terms_version: Option<String>,
+    pub privacy_version: Option<String>,
+    /// Documents to accept before using the api, `terms` and / or `privacyPolicy`
+    pub pending: Vec<String>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for ConsentResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Consent Request schema, the versions must be the current ones.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ConsentRequest {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "2025-01")))]
+    pub terms_version: Option<String>,
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "2025-01")))]
+    pub privacy_version: Option<String>,
+}
@@ -0,0 +1,167 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Response schema for a conversation, without its messages.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationResponse {
+    pub id: String,
+    /// Taken from the first message unless renamed, none before it
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "How do transformers handle long contexts?"))
+    )]
+    pub title: Option<String>,
+    pub pinned: bool,
+    pub message_count: u32,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub updated_at: i64,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for ConversationResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Response schema for the conversations of the user, pinned ones first, then
+/// the most recently active.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct ListConversationsResponse(pub Vec<ConversationResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListConversationsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Response schema for a message of a conversation.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct MessageResponse {
+    pub id: String,
+    pub conversation_id: String,
+    pub role: MessageRole,
+    pub content: String,
+    /// Model which wrote the answer, for assistant messages
+    pub model: Option<String>,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+}
+
+/// Response schema for a page of the history of a conversation, oldest first.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ListMessagesResponse {
+    pub items: Vec<MessageResponse>,
+    /// Whether older messages remain, fetched with `before` set to the
+    /// `createdAt` of the first item
+    pub has_more: bool,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListMessagesResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Create Conversation Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CreateConversationRequest {
+    /// Taken from the first message when absent
+    #[validate(length(min = 1, max = 200))]
+    pub title: Option<String>,
+}
+
+/// Update Conversation Request schema, absent fields are left as they are.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateConversationRequest {
+    #[validate(length(min = 1, max = 200))]
+    pub title: Option<String>,
+    pub pinned: Option<bool>,
+}
+
+/// Send Message Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SendMessageRequest {
+    #[validate(length(min = 1, max = 20000))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "How do transformers handle long contexts?"))
+    )]
+    pub content: String,
+    /// Defaults to the configured model
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "deepseek-chat")))]
+    pub model: Option<String>,
+}
+
+/// Response schema for a sent message and the answer of the model.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct SendMessageResponse {
+    pub message: MessageResponse,
+    pub reply: MessageResponse,
+    pub conversation: ConversationResponse,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for SendMessageResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// A message matching a search, with the conversation it belongs to.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSearchResultResponse {
+    pub conversation_id: String,
+    pub conversation_title: Option<String>,
+    pub message: MessageResponse,
+}
+
+/// Response schema for a search within the conversations, best match first.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct SearchConversationsResponse(pub Vec<MessageSearchResultResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for SearchConversationsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum MessageRole {
+    User,
+    Assistant,
+}
@@ -0,0 +1,97 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+fn validate_key(key: &str) -> Result<(), ValidationError> {
+    let mut chars = key.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if valid {
+        return Ok(());
+    }
+    let mut error = ValidationError::new("key");
+    error.message = Some("the key must start with a-z and contain only a-z, 0-9 and _".into());
+    Err(error)
+}
+
+/// Create Custom Field Request schema.
+/// `options` lists the allowed values of a `select` field.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCustomFieldRequest {
+    #[validate(length(min = 1, max = 40), custom(function = "validate_key"))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "reading_status")))]
+    pub key: String,
+    #[validate(length(min = 1, max = 100))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "Reading status")))]
+    pub name: String,
+    pub r#type: FieldType,
+    #[serde(default)]
+    #[validate(length(max = 50))]
+    pub options: Vec<String>,
+    /// Shared with the organization of the user, its admins only
+    #[serde(default)]
+    pub team: bool,
+}
+
+/// Response schema for a custom field of the papers.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct CustomFieldResponse {
+    pub id: String,
+    pub key: String,
+    pub name: String,
+    pub r#type: FieldType,
+    pub options: Vec<String>,
+    /// Defined for the organization of the user
+    pub team: bool,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for CustomFieldResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct ListCustomFieldsResponse(pub Vec<CustomFieldResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListCustomFieldsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    Text,
+    Number,
+    // YYYY-MM-DD
+    Date,
+    // one of the options
+    Select,
+}
+
+/// Value of a custom field on a paper, dates and options are stored as text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(untagged)]
+pub enum FieldValue {
+    Number(f64),
+    Text(String),
+}
@@ -0,0 +1,112 @@
+#[cfg(feature = "salvo")]
+use salvo::oapi::{ToResponse, ToSchema};
+use serde::{Deserialize, Serialize};
+
+/// Stable machine-readable error codes, clients should branch on these instead of messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    BadRequest,
+    Unauthorized,
+    ConsentRequired,
+    CsrfRejected,
+    TokenInvalid,
+    DuplicateUser,
+    NotFound,
+    FolderNotFound,
+    PaperNotFound,
+    NameConflict,
+    VersionConflict,
+    PreconditionFailed,
+    PreconditionRequired,
+    ChecksumMismatch,
+    PayloadTooLarge,
+    RateLimited,
+    LoginLocked,
+    QuotaExceeded,
+    ResourceQuotaExceeded,
+    ValidationFailed,
+    InternalError,
+    DatabaseError,
+    DatabaseUnavailable,
+    SerializationError,
+    MailError,
+    LlmError,
+    EmbeddingError,
+    PdfError,
+    UpstreamError,
+    Timeout,
+    ServiceUnavailable,
+}
+
+/// Body of every non-422 error response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorResponse {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "FOLDER_NOT_FOUND")))]
+    pub code: ErrorCode,
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "Folder folder-uuid not found"))
+    )]
+    pub message: String,
+    /// Extra structured information about the error, depends on the code
+    pub details: Option<serde_json::Value>,
+    /// Id of the request, to correlate with server logs
+    pub request_id: Option<String>,
+}
+
+/// Body of every 422 response, one entry per rejected field.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationErrorResponse {
+    /// Always `VALIDATION_FAILED`
+    pub code: ErrorCode,
+    pub message: String,
+    pub errors: Vec<FieldError>,
+    pub request_id: Option<String>,
+}
+
+impl ValidationErrorResponse {
+    pub fn new(errors: Vec<FieldError>) -> Self {
+        ValidationErrorResponse {
+            code: ErrorCode::ValidationFailed,
+            message: "Validation failed".to_string(),
+            errors,
+            request_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    /// Path of the field in the request body, e.g. `children[0].name`
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "name")))]
+    pub field: String,
+    /// Machine readable code of the failed rule, e.g. `length`, `email`
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "length")))]
+    pub code: String,
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "name must be between 1 and 64 characters"))
+    )]
+    pub message: String,
+    /// The rejected value, when it is safe to echo back
+    pub rejected_value: Option<serde_json::Value>,
+}
+
+impl FieldError {
+    pub fn new(field: impl ToString, code: impl ToString, message: impl ToString) -> Self {
+        FieldError {
+            field: field.to_string(),
+            code: code.to_string(),
+            message: message.to_string(),
+            rejected_value: None,
+        }
+    }
+}
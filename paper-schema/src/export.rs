@@ -0,0 +1,93 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Response schema for an export, built in the background.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ExportJobResponse {
+    pub id: String,
+    pub kind: ExportKind,
+    /// The folder exported, or the user for an account export
+    pub resource_id: String,
+    pub format: ExportFormat,
+    pub status: ExportStatus,
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "Thesis.zip")))]
+    pub file_name: String,
+    /// Bytes of the archive, once ready
+    pub size: Option<u64>,
+    /// Path of the download, once ready
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "/api/exports/export-uuid/download"))
+    )]
+    pub download_url: Option<String>,
+    pub error: Option<String>,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+    #[serde(default, with = "crate::time::option_millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = Option<String>, format = DateTime)))]
+    pub finished_at: Option<i64>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for ExportJobResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Document format of an exported paper.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Md,
+    Html,
+    #[default]
+    Pdf,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Md => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Md => "text/markdown; charset=utf-8",
+            ExportFormat::Html => "text/html; charset=utf-8",
+            ExportFormat::Pdf => "application/pdf",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ExportKind {
+    // papers of a folder and its subfolders
+    #[default]
+    Folder,
+    // everything stored for the user, with a manifest
+    Account,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatus {
+    Pending,
+    Ready,
+    Failed,
+}
@@ -0,0 +1,328 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{paper::PaperResponse, validate::validate_color};
+
+pub const FOLDER_NAME_MAX_CHARS: u64 = 64;
+pub const FOLDER_DESCRIPTION_MAX_CHARS: u64 = 1000;
+pub const FOLDER_ICON_MAX_CHARS: u64 = 32;
+
+/// Response schema for a folder.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct FolderResponse {
+    pub id: String,
+    pub parent_id: Option<String>,
+
+    pub name: String,
+    pub description: Option<String>,
+    pub r#type: FolderType,
+    /// Search run to list the papers of a smart folder
+    pub query: Option<SmartQuery>,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    /// Rank of the folder among its siblings, lowest first
+    pub sort_order: u32,
+    pub archived: bool,
+    /// Incremented on every update of the folder
+    pub version: u32,
+    /// Papers filed in the folder, given in the folder listing and details
+    /// except for the smart folders, whose papers are searched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paper_count: Option<u64>,
+    /// Folders right under the folder, given in the folder listing and details
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub child_count: Option<u64>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for FolderResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct ListFoldersResponse(pub Vec<FolderResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListFoldersResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Response schema for a page of the papers of a folder, newest first.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ListFolderPapersResponse {
+    pub items: Vec<PaperResponse>,
+    /// Papers of the folder in all
+    pub total: u64,
+    /// Whether more papers remain, fetched with `offset` past the last item
+    pub has_more: bool,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListFolderPapersResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Response schema for the wrap-up of a project folder.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct WrapUpFolderResponse {
+    pub folder: FolderResponse,
+    pub summary_paper: PaperResponse,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for WrapUpFolderResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// A folder on the path from the root to a folder.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct FolderPathItem {
+    pub id: String,
+    pub name: String,
+}
+
+/// Response schema for a moved folder.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct MoveFolderResponse {
+    pub folder: FolderResponse,
+    /// New path of the folder, from the root down to the folder itself
+    pub path: Vec<FolderPathItem>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for MoveFolderResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Create Folder Request schema.
+/// default type is `user`, `smart` when a query is given
+/// if parent_id is None, it will be created in the root folder.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CreateFolderRequest {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "parent-folder-uuid")))]
+    pub parent_id: Option<String>, // uuid of parent folder
+    #[validate(length(min = 1, max = FOLDER_NAME_MAX_CHARS))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(min_length = 1, max_length = 64, example = "folder-name"))
+    )]
+    pub name: String,
+    #[validate(length(max = FOLDER_DESCRIPTION_MAX_CHARS))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(max_length = 1000, example = "This is a folder description."))
+    )]
+    pub description: Option<String>,
+    #[validate(custom(function = "validate_color"))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "#4f86f7")))]
+    pub color: Option<String>,
+    #[validate(length(max = FOLDER_ICON_MAX_CHARS))]
+    #[cfg_attr(feature = "salvo", salvo(schema(max_length = 32, example = "book")))]
+    pub icon: Option<String>,
+    /// Makes a smart folder listing the papers matching the query
+    #[validate(nested)]
+    pub query: Option<SmartQuery>,
+}
+
+/// Update Folder Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateFolderRequest {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "parent-folder-uuid")))]
+    pub parent_id: Option<String>, // uuid of parent folder
+    #[validate(length(min = 1, max = FOLDER_NAME_MAX_CHARS))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(min_length = 1, max_length = 64, example = "folder-name"))
+    )]
+    pub name: Option<String>,
+    #[validate(length(max = FOLDER_DESCRIPTION_MAX_CHARS))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(max_length = 1000, example = "This is a folder description."))
+    )]
+    pub description: Option<String>,
+    #[validate(custom(function = "validate_color"))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "#4f86f7")))]
+    pub color: Option<String>,
+    #[validate(length(max = FOLDER_ICON_MAX_CHARS))]
+    #[cfg_attr(feature = "salvo", salvo(schema(max_length = 32, example = "book")))]
+    pub icon: Option<String>,
+    /// Query of a smart folder
+    #[validate(nested)]
+    pub query: Option<SmartQuery>,
+}
+
+/// Move Folder Request schema.
+/// if parent_id is None, the folder is moved to the root.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct MoveFolderRequest {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "parent-folder-uuid")))]
+    pub parent_id: Option<String>, // uuid of the new parent folder
+}
+
+/// Copy Folder Request schema.
+/// if parent_id is None, the copy is placed next to the folder.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CopyFolderRequest {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "parent-folder-uuid")))]
+    pub parent_id: Option<String>, // uuid of the parent of the copy
+    /// Copy the uploaded files and their text too, not only the metadata
+    #[serde(default)]
+    pub include_files: bool,
+}
+
+/// Reorder Folders Request schema.
+/// if parent_id is None, the folders at the root are reordered.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderFoldersRequest {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "parent-folder-uuid")))]
+    pub parent_id: Option<String>, // uuid of the parent of the reordered folders
+    /// Sibling folders in their new order, folders left out keep their relative
+    /// order after the given ones
+    #[validate(length(min = 1))]
+    pub folder_ids: Vec<String>,
+}
+
+/// A folder of an imported tree, with its subfolders.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ImportFolderNode {
+    /// Chosen by the client, unique in the request, mapped to the id of the
+    /// created folder in the response
+    #[validate(length(min = 1, max = 128))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "reading/ml")))]
+    pub key: String,
+    #[validate(length(min = 1, max = FOLDER_NAME_MAX_CHARS))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(min_length = 1, max_length = 64, example = "folder-name"))
+    )]
+    pub name: String,
+    #[validate(length(max = FOLDER_DESCRIPTION_MAX_CHARS))]
+    #[cfg_attr(feature = "salvo", salvo(schema(max_length = 1000)))]
+    pub description: Option<String>,
+    #[validate(custom(function = "validate_color"))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "#4f86f7")))]
+    pub color: Option<String>,
+    #[validate(length(max = FOLDER_ICON_MAX_CHARS))]
+    #[cfg_attr(feature = "salvo", salvo(schema(max_length = 32, example = "book")))]
+    pub icon: Option<String>,
+    #[serde(default)]
+    #[validate(nested)]
+    pub children: Vec<ImportFolderNode>,
+}
+
+/// Import Folders Request schema, a tree of folders created at once.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ImportFoldersRequest {
+    /// Folder the imported tree goes under, the root level when absent
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "parent-folder-uuid")))]
+    pub parent_id: Option<String>,
+    #[validate(length(min = 1), nested)]
+    pub folders: Vec<ImportFolderNode>,
+}
+
+/// Response schema for an imported tree of folders.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ImportFoldersResponse {
+    /// Id of the created folder by key of the request
+    pub ids: BTreeMap<String, String>,
+    /// The created folders, parents before their subfolders
+    pub folders: Vec<FolderResponse>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for ImportFoldersResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+pub enum FolderType {
+    #[serde(rename = "system")]
+    SystemDefined,
+    #[serde(rename = "user")]
+    UserDefined,
+    // lists the papers matching its query
+    #[serde(rename = "smart")]
+    Smart,
+}
+
+/// Search stored by a smart folder, run again every time its papers are listed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SmartQuery {
+    /// Full text search in the papers
+    #[validate(length(max = 500))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "diffusion models")))]
+    pub q: Option<String>,
+    /// Only the papers of the folder and its subfolders
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "folder-uuid")))]
+    pub folder_id: Option<String>,
+    /// Only the papers with all the tags
+    #[serde(default)]
+    #[validate(length(max = 20))]
+    pub tags: Vec<String>,
+    pub starred: Option<bool>,
+    /// Conditions on custom fields, as the `field` filter of the papers
+    #[validate(length(max = 1000))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "status=read,pages>=10")))]
+    pub field: Option<String>,
+}
+
+impl SmartQuery {
+    /// Query of the starred papers.
+    pub fn starred() -> Self {
+        SmartQuery {
+            starred: Some(true),
+            ..Default::default()
+        }
+    }
+}
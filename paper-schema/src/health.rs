@@ -0,0 +1,33 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    /// Serving, with a non critical dependency down
+    Degraded,
+    Down,
+}
+
+/// Response schema for the liveness and readiness probes. The status of
+/// each dependency is only logged, the probes are not authenticated.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct HealthResponse {
+    pub status: HealthStatus,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for HealthResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
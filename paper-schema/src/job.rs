@@ -0,0 +1,68 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::paper::Progress;
+
+/// Response schema for a job of the user, run in the background.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct JobResponse {
+    pub id: String,
+    pub kind: JobKind,
+    /// The resource the job works on, e.g. the folder copied
+    pub resource_id: String,
+    pub status: JobStatus,
+    /// Items processed so far, e.g. the papers copied
+    pub progress: Option<Progress>,
+    /// The resource created by the job, e.g. the copy of the folder
+    pub result_id: Option<String>,
+    pub error: Option<String>,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+    #[serde(default, with = "crate::time::option_millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = Option<String>, format = DateTime)))]
+    pub finished_at: Option<i64>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for JobResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Response schema for the jobs of the user, the latest first.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+pub struct ListJobsResponse(pub Vec<JobResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListJobsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    // copy of a folder with its subfolders and papers
+    FolderCopy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Done,
+    Failed,
+}
@@ -0,0 +1,46 @@
+//! Paper schema
+//!
+//! Request and response schemas of the paper-backend REST api, shared by the
+//! server and the typed client. With the `salvo` feature, which the server
+//! enables, the schemas derive their openapi documentation and the responses
+//! render themselves as json.
+
+pub mod account;
+pub mod activity;
+pub mod ai;
+pub mod auth;
+pub mod backup;
+pub mod block;
+pub mod chunk;
+pub mod citation;
+pub mod comparison;
+pub mod consent;
+pub mod conversation;
+pub mod custom_field;
+pub mod error;
+pub mod export;
+pub mod folder;
+pub mod health;
+pub mod job;
+pub mod llm_key;
+pub mod login_attempt;
+pub mod math;
+pub mod migration;
+pub mod note;
+pub mod notification;
+pub mod organization;
+pub mod page;
+pub mod paper;
+pub mod prompt;
+pub mod quarantine;
+pub mod quota;
+pub mod reading_list;
+pub mod retry;
+pub mod revision;
+pub mod share;
+pub mod stats;
+pub mod time;
+pub mod usage;
+pub mod user;
+pub mod validate;
+pub mod webhook;
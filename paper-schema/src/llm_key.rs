@@ -0,0 +1,56 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Response schema for an api key of the user, the key itself is never
+/// returned.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct LlmKeyResponse {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "openai")))]
+    pub provider: String,
+    /// Last characters of the key
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "…3xQz")))]
+    pub key_hint: String,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+    /// When the provider last accepted the key
+    #[serde(default, with = "crate::time::option_millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = Option<String>, format = DateTime)))]
+    pub validated_at: Option<i64>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for LlmKeyResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct ListLlmKeysResponse(pub Vec<LlmKeyResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListLlmKeysResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Store Llm Key Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct StoreLlmKeyRequest {
+    #[validate(length(min = 8, max = 512))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "sk-...")))]
+    pub api_key: String,
+}
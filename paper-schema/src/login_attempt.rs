@@ -0,0 +1,37 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Response schema for an account or an ip locked after failed logins.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct LoginLockResponse {
+    /// `account:{email}` or `ip:{ip}`
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "account:jane@example.com")))]
+    pub key: String,
+    pub failures: i64,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub last_failed_at: i64,
+    /// When logins are allowed again
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub locked_until: i64,
+}
+
+/// Response schema for the current login locks, the latest failures first.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+pub struct ListLoginLocksResponse(pub Vec<LoginLockResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListLoginLocksResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
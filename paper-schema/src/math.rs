@@ -0,0 +1,48 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Render Math Request schema, e.g. the abstracts of a listing at once.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+pub struct RenderMathRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub texts: Vec<String>,
+}
+
+/// Response schema for rendered texts, in the order of the request.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+pub struct RenderMathResponse {
+    /// The texts escaped, with their formulas as MathML
+    pub html: Vec<String>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for RenderMathResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Response schema for the abstract and notes of a paper rendered.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct PaperMathResponse {
+    pub paper_id: String,
+    pub abstract_html: Option<String>,
+    pub notes_html: Option<String>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for PaperMathResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
@@ -0,0 +1,35 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Response schema for a migration of the stored documents.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationResponse {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "0001_backfill_versions")))]
+    pub id: String,
+    pub description: String,
+    pub applied: bool,
+    #[serde(default, with = "crate::time::option_millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = Option<String>, format = DateTime)))]
+    pub applied_at: Option<i64>,
+    /// Documents changed when it was applied
+    pub affected: Option<u64>,
+}
+
+/// Response schema for the migrations, in the order they are applied.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+pub struct ListMigrationsResponse(pub Vec<MigrationResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListMigrationsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
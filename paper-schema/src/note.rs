@@ -0,0 +1,35 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Response schema for the notes of a paper merged from every edit.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct NotesResponse {
+    pub paper_id: String,
+    pub markdown: String,
+    /// The whole CRDT state as a yrs v1 update, base64 encoded
+    pub state: String,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for NotesResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Update Notes Request schema, for clients editing without the WebSocket.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+pub struct UpdateNotesRequest {
+    /// A yrs v1 update of the notes, base64 encoded
+    #[validate(length(min = 1))]
+    pub update: String,
+}
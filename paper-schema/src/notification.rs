@@ -0,0 +1,78 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Response schema for a notification.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationResponse {
+    pub id: String,
+    pub kind: NotificationKind,
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "Project \"Thesis\" wrapped up"))
+    )]
+    pub title: String,
+    pub content: Option<String>,
+    /// Id of the folder or paper the notification refers to
+    pub resource_id: Option<String>,
+    pub read: bool,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for NotificationResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Response schema for the notifications of the user, newest first.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ListNotificationsResponse {
+    pub items: Vec<NotificationResponse>,
+    pub unread_count: u64,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListNotificationsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct UnreadCountResponse {
+    pub unread_count: u64,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for UnreadCountResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+pub enum NotificationKind {
+    #[serde(rename = "folder_wrapped_up")]
+    FolderWrappedUp,
+    // text of an uploaded file extracted, or failed to
+    #[serde(rename = "import_finished")]
+    ImportFinished,
+    // archive of a folder export ready to download
+    #[serde(rename = "export_ready")]
+    ExportReady,
+}
@@ -0,0 +1,94 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::folder::{FOLDER_DESCRIPTION_MAX_CHARS, FOLDER_NAME_MAX_CHARS};
+
+/// Response schema for an organization.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizationResponse {
+    pub id: String,
+    pub name: String,
+    pub admin_ids: Vec<String>,
+    pub folder_template: Vec<FolderTemplate>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for OrganizationResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Create Organization Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CreateOrganizationRequest {
+    #[validate(length(min = 1, max = 128))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "NLP Lab")))]
+    pub name: String,
+}
+
+/// A folder of the template, with its subfolders.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct FolderTemplateRequest {
+    #[validate(length(min = 1, max = FOLDER_NAME_MAX_CHARS))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "Reading Group")))]
+    pub name: String,
+    #[validate(length(max = FOLDER_DESCRIPTION_MAX_CHARS))]
+    pub description: Option<String>,
+    #[validate(length(max = 20), nested)]
+    #[serde(default)]
+    pub children: Vec<FolderTemplateRequest>,
+}
+
+impl From<FolderTemplateRequest> for FolderTemplate {
+    fn from(request: FolderTemplateRequest) -> Self {
+        FolderTemplate {
+            name: request.name,
+            description: request.description,
+            children: request.children.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Update Folder Template Request schema.
+/// The folders are provisioned for every new member of the organization.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateFolderTemplateRequest {
+    #[validate(length(max = 20), nested)]
+    pub folders: Vec<FolderTemplateRequest>,
+}
+
+/// Add Member Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct AddMemberRequest {
+    #[validate(email)]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "member@example.com")))]
+    pub email: String,
+    #[serde(default)]
+    pub admin: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+pub struct FolderTemplate {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub children: Vec<FolderTemplate>,
+}
@@ -0,0 +1,38 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::paper::{Progress, TextStatus};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct PageTextResponse {
+    /// 1 based page number
+    pub page: u32,
+    pub text: String,
+}
+
+/// Response schema for the extracted text of a paper.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct PaperTextResponse {
+    pub paper_id: String,
+    /// Not set when no file was uploaded
+    pub status: Option<TextStatus>,
+    pub page_count: Option<u32>,
+    pub ocr_progress: Option<Progress>,
+    pub pages: Vec<PageTextResponse>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for PaperTextResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{custom_field::FieldValue, error::ErrorCode, validate::validate_tags};
+
+/// Response schema for a paper.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct PaperResponse {
+    pub id: String,
+    pub folder_id: String,
+
+    pub title: String,
+    pub authors: Vec<String>,
+    pub r#abstract: Option<String>,
+    pub doi: Option<String>,
+    pub content: Option<String>,
+    pub summary: Option<String>,
+    pub tags: Vec<String>,
+
+    pub has_file: bool,
+    pub file_size: Option<u64>,
+    /// Why the uploaded file was refused, e.g. by the content scan
+    pub file_error: Option<String>,
+    pub text_status: Option<TextStatus>,
+    pub page_count: Option<u32>,
+    pub ocr_progress: Option<Progress>,
+    pub starred: bool,
+    /// Tags and folder suggested from the abstract, pending until accepted
+    pub suggestions: Option<SuggestionsResponse>,
+    /// Values of the custom fields of the user and their organization, by key
+    pub custom_fields: BTreeMap<String, FieldValue>,
+    /// Incremented on every update of the paper
+    pub version: u32,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for PaperResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct ListPapersResponse(pub Vec<PaperResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListPapersResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SuggestionsResponse {
+    /// Tags the paper does not have yet, existing tags of the user preferred
+    pub tags: Vec<String>,
+    /// Folder the paper would fit in, none when it is in the best one
+    pub folder_id: Option<String>,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+}
+
+/// Accept Suggestions Request schema, everything suggested is accepted by default.
+#[derive(Debug, Default, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptSuggestionsRequest {
+    /// The suggested tags to add, all of them when absent
+    #[validate(length(max = 50))]
+    pub tags: Option<Vec<String>>,
+    /// Whether to move the paper to the suggested folder, defaults to true
+    pub folder: Option<bool>,
+}
+
+/// Create Paper Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePaperRequest {
+    /// The default folder of the settings when absent
+    #[validate(length(min = 1))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "folder-uuid")))]
+    pub folder_id: Option<String>,
+    #[validate(length(min = 1, max = 512))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(
+            min_length = 1,
+            max_length = 512,
+            example = "Attention Is All You Need"
+        ))
+    )]
+    pub title: String,
+    #[validate(length(max = 100))]
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[validate(length(max = 10000))]
+    pub r#abstract: Option<String>,
+    #[validate(length(max = 128))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "10.48550/arXiv.1706.03762"))
+    )]
+    pub doi: Option<String>,
+    #[validate(length(max = 200000))]
+    pub content: Option<String>,
+    #[validate(length(max = 50), custom(function = "validate_tags"))]
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Update Paper Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePaperRequest {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "folder-uuid")))]
+    pub folder_id: Option<String>,
+    #[validate(length(min = 1, max = 512))]
+    pub title: Option<String>,
+    #[validate(length(max = 100))]
+    pub authors: Option<Vec<String>>,
+    #[validate(length(max = 10000))]
+    pub r#abstract: Option<String>,
+    #[validate(length(max = 128))]
+    pub doi: Option<String>,
+    #[validate(length(max = 200000))]
+    pub content: Option<String>,
+    #[validate(length(max = 50), custom(function = "validate_tags"))]
+    pub tags: Option<Vec<String>>,
+    /// Values of custom fields by key, null removes the value
+    #[validate(length(max = 100))]
+    pub custom_fields: Option<BTreeMap<String, Option<FieldValue>>>,
+}
+
+pub const BATCH_MAX_PAPERS: u64 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum BatchAction {
+    Move,
+    Tag,
+    Delete,
+    Export,
+}
+
+/// Batch Paper Request schema.
+/// `folderId` is required for `move`, `tags` for `tag`.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct BatchPaperRequest {
+    pub action: BatchAction,
+    #[validate(length(min = 1, max = BATCH_MAX_PAPERS))]
+    pub paper_ids: Vec<String>,
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "folder-uuid")))]
+    pub folder_id: Option<String>,
+    /// Tags added to every paper
+    #[validate(length(max = 50), custom(function = "validate_tags"))]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Watermark of the exported document
+    pub watermark: Option<String>,
+}
+
+/// Outcome of the batch action for a single paper.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct BatchItemResult {
+    pub paper_id: String,
+    pub success: bool,
+    pub error_code: Option<ErrorCode>,
+    pub error_message: Option<String>,
+}
+
+impl BatchItemResult {
+    pub fn ok(paper_id: &str) -> Self {
+        BatchItemResult {
+            paper_id: paper_id.to_string(),
+            success: true,
+            error_code: None,
+            error_message: None,
+        }
+    }
+
+    pub fn failed(paper_id: &str, code: ErrorCode, message: String) -> Self {
+        BatchItemResult {
+            paper_id: paper_id.to_string(),
+            success: false,
+            error_code: Some(code),
+            error_message: Some(message),
+        }
+    }
+}
+
+/// Document produced by a batch export.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct BatchExport {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "papers.pdf")))]
+    pub filename: String,
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "application/pdf")))]
+    pub content_type: String,
+    /// Base64 encoded content
+    pub data: String,
+}
+
+/// Response schema for a batch action.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct BatchPaperResponse {
+    pub results: Vec<BatchItemResult>,
+    /// Only set for the `export` action
+    pub export: Option<BatchExport>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for BatchPaperResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// A group of papers detected as the same publication.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroupResponse {
+    pub reasons: Vec<DuplicateReason>,
+    pub papers: Vec<PaperResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct ListDuplicatesResponse(pub Vec<DuplicateGroupResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListDuplicatesResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// A paper matching the search, the score is only given in debug mode.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResultResponse {
+    pub paper: PaperResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<ScoreBreakdown>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct SearchPapersResponse(pub Vec<SearchResultResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for SearchPapersResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// A paper of the library similar to the given one.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedPaperResponse {
+    pub paper: PaperResponse,
+    /// Cosine similarity of the embeddings, from -1 to 1
+    pub similarity: f32,
+}
+
+/// A paper outside the library, recommended by Semantic Scholar.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalPaperResponse {
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "BERT: Pre-training of Deep Bidirectional Transformers"))
+    )]
+    pub title: String,
+    pub url: Option<String>,
+    pub year: Option<i32>,
+    pub authors: Vec<String>,
+    pub doi: Option<String>,
+}
+
+/// Response schema for the papers related to a paper.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedPapersResponse {
+    /// Most similar first, empty when the embeddings are not available
+    pub library: Vec<RelatedPaperResponse>,
+    /// Empty when the external suggestions are disabled or unavailable
+    pub external: Vec<ExternalPaperResponse>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for RelatedPapersResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Response schema for a signed url downloading the file of a paper.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct PaperFileUrlResponse {
+    /// Downloads the file without authentication until it expires, the same
+    /// for a few minutes so it can be cached
+    pub url: String,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub expires_at: i64,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for PaperFileUrlResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Merge Papers Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct MergePapersRequest {
+    /// The paper kept, the others are merged into it and deleted
+    #[validate(length(min = 1))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "paper-uuid")))]
+    pub survivor_id: String,
+    #[validate(length(min = 1, max = 50))]
+    pub paper_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum TextStatus {
+    Pending,
+    // no text layer found, OCR in progress
+    Ocr,
+    Ready,
+    Failed,
+    // refused by the content scan, the file was moved to the quarantine
+    Quarantined,
+}
+
+/// Progress of a long running processing of the file of a paper.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+pub struct Progress {
+    pub done: u32,
+    pub total: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicateReason {
+    Doi,
+    Title,
+    FileHash,
+}
+
+/// How the final score of a search result was computed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreBreakdown {
+    pub text: f64,
+    pub recency: f64,
+    pub starred: f64,
+    pub folder: f64,
+    pub total: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum SectionStatus {
+    /// Same heading and (almost) the same text
+    Common,
+    /// Same heading, different text
+    Divergent,
+    OnlyInA,
+    OnlyInB,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct FieldComparison {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "authors")))]
+    pub field: String,
+    pub same: bool,
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SectionComparison {
+    pub heading: String,
+    pub status: SectionStatus,
+    /// Word overlap of the two texts, between 0.0 and 1.0
+    pub similarity: f64,
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+/// Structured diff of two papers.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct PaperComparison {
+    pub a_id: String,
+    pub b_id: String,
+    pub fields: Vec<FieldComparison>,
+    /// Sections of the notes, split at markdown headings
+    pub sections: Vec<SectionComparison>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for PaperComparison {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
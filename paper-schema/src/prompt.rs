@@ -0,0 +1,92 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+/// Response schema for a prompt template.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplateResponse {
+    pub id: String,
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "folder_wrap_up")))]
+    pub name: String,
+    pub version: u32,
+    pub description: Option<String>,
+    /// Names of the `{{variable}}` placeholders of the body
+    pub variables: Vec<String>,
+    pub body: String,
+    pub created_by: String,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub updated_at: i64,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for PromptTemplateResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct ListPromptTemplatesResponse(pub Vec<PromptTemplateResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListPromptTemplatesResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), ValidationError> {
+    let valid = name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '.');
+    if valid {
+        return Ok(());
+    }
+    let mut error = ValidationError::new("name");
+    error.message = Some("only lowercase letters, digits, `_` and `.` are allowed".into());
+    Err(error)
+}
+
+/// Create Prompt Template Request schema. The template gets the next version of
+/// the name, the first one being 1.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePromptTemplateRequest {
+    #[validate(length(min = 1, max = 100), custom(function = "validate_name"))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "folder_wrap_up")))]
+    pub name: String,
+    #[validate(length(max = 1000))]
+    pub description: Option<String>,
+    #[serde(default)]
+    #[validate(length(max = 50))]
+    pub variables: Vec<String>,
+    #[validate(length(min = 1, max = 100000))]
+    pub body: String,
+}
+
+/// Update Prompt Template Request schema, the fields replace those of the version.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePromptTemplateRequest {
+    #[validate(length(max = 1000))]
+    pub description: Option<String>,
+    #[serde(default)]
+    #[validate(length(max = 50))]
+    pub variables: Vec<String>,
+    #[validate(length(min = 1, max = 100000))]
+    pub body: String,
+}
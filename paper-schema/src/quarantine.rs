@@ -0,0 +1,49 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Response schema for a file refused by the content scan.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantinedFileResponse {
+    pub id: String,
+    pub user_id: String,
+    /// The paper the file was uploaded to
+    pub paper_id: String,
+    pub file_hash: String,
+    pub file_size: u64,
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "Malware detected: Eicar-Signature"))
+    )]
+    pub reason: String,
+    /// The scanner which refused the file, `clamav` or `http`
+    pub scanner: String,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for QuarantinedFileResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Response schema for the quarantined files, the latest first.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+pub struct ListQuarantinedFilesResponse(pub Vec<QuarantinedFileResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListQuarantinedFilesResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
@@ -0,0 +1,53 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Limits on the library of a user, unlimited when absent.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaLimitsResponse {
+    pub max_folder_depth: Option<u64>,
+    pub max_folders: Option<u64>,
+    pub max_papers_per_folder: Option<u64>,
+    pub max_storage_bytes: Option<u64>,
+}
+
+/// Response schema for the quotas of a user.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct UserQuotasResponse {
+    pub user_id: String,
+    /// The limits enforced, the overrides of the user over the config
+    pub limits: QuotaLimitsResponse,
+    /// The limits set for this user only
+    pub overrides: QuotaLimitsResponse,
+    pub folders: u64,
+    pub storage_bytes: u64,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for UserQuotasResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Update User Quotas Request schema, replaces the overrides of the user.
+/// Absent limits fall back to the config.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateUserQuotasRequest {
+    #[validate(range(min = 1, max = 64))]
+    pub max_folder_depth: Option<u64>,
+    pub max_folders: Option<u64>,
+    pub max_papers_per_folder: Option<u64>,
+    pub max_storage_bytes: Option<u64>,
+}
@@ -0,0 +1,119 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Response schema for a queued paper.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingListItemResponse {
+    pub paper_id: String,
+    pub title: String,
+    pub priority: ReadingPriority,
+    pub status: ReadingStatus,
+    /// Position in the queue, starting at 0
+    pub position: u32,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub added_at: i64,
+    #[serde(default, with = "crate::time::option_millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = Option<String>, format = DateTime)))]
+    pub started_at: Option<i64>,
+    #[serde(default, with = "crate::time::option_millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = Option<String>, format = DateTime)))]
+    pub finished_at: Option<i64>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for ReadingListItemResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Reading progress of the user, counted over the whole queue.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingStatsResponse {
+    pub to_read: u32,
+    pub reading: u32,
+    pub done: u32,
+    /// Papers finished in the last 7 days
+    pub read_this_week: u32,
+    /// Papers finished in the last 30 days
+    pub read_this_month: u32,
+}
+
+/// Response schema for the reading list.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingListResponse {
+    pub items: Vec<ReadingListItemResponse>,
+    pub stats: ReadingStatsResponse,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for ReadingListResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Add To Reading List Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct AddReadingListItemRequest {
+    #[validate(length(min = 1))]
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "paper-uuid")))]
+    pub paper_id: String,
+    #[serde(default)]
+    pub priority: ReadingPriority,
+}
+
+/// Update Reading List Item Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateReadingListItemRequest {
+    pub priority: Option<ReadingPriority>,
+    pub status: Option<ReadingStatus>,
+}
+
+/// Reorder Reading List Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderReadingListRequest {
+    /// Queued papers in their new order, papers left out keep their relative
+    /// order after the given ones
+    #[validate(length(min = 1))]
+    pub paper_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ReadingPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum ReadingStatus {
+    #[default]
+    ToRead,
+    Reading,
+    Done,
+}
@@ -0,0 +1,39 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Response schema for the retries of a repository call.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct OperationRetriesResponse {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "get_paper_by_id")))]
+    pub operation: String,
+    pub retries: u64,
+}
+
+/// Response schema for the retries of the database calls since the start.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct DbRetriesResponse {
+    /// Attempts made again after a transient error
+    pub retries: u64,
+    /// Calls which succeeded after a retry
+    pub recovered: u64,
+    /// Calls which still failed after a retry
+    pub failed: u64,
+    /// The operations retried, the most retried first
+    pub operations: Vec<OperationRetriesResponse>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for DbRetriesResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::custom_field::FieldValue;
+
+/// Response schema for a revision of a paper, its metadata and notes as
+/// they were before an update.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct PaperRevisionResponse {
+    pub paper_id: String,
+    /// The version of the paper it was
+    pub revision: u32,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub edited_at: i64,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub replaced_at: i64,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub r#abstract: Option<String>,
+    pub doi: Option<String>,
+    pub content: Option<String>,
+    pub tags: Vec<String>,
+    pub custom_fields: BTreeMap<String, FieldValue>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for PaperRevisionResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// A revision in the history of a paper, without its content.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct PaperRevisionItem {
+    pub revision: u32,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub edited_at: i64,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub replaced_at: i64,
+    pub title: String,
+}
+
+/// Response schema for the revisions of a paper, the latest first.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+pub struct ListPaperRevisionsResponse(pub Vec<PaperRevisionItem>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListPaperRevisionsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// The lines of a field changed between two revisions.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+pub struct FieldDiff {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "content")))]
+    pub field: String,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Response schema for the changes between two revisions of a paper.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct PaperRevisionDiffResponse {
+    pub paper_id: String,
+    pub from: u32,
+    /// Absent for the current version of the paper
+    pub to: Option<u32>,
+    /// The changed fields only
+    pub fields: Vec<FieldDiff>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for PaperRevisionDiffResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// A line of a diff, kept, added or removed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+}
@@ -0,0 +1,147 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Response schema for a share link, only visible to the owner.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLinkResponse {
+    pub id: String,
+    pub paper_id: String,
+    /// Secret part of the link, `/api/review/{token}`
+    pub token: String,
+    /// Pseudonym shown for the comments made through this link
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "Reviewer A")))]
+    pub handle: String,
+    pub permission: SharePermission,
+    pub watermark: Option<String>,
+    pub revoked: bool,
+    #[serde(default, with = "crate::time::option_millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = Option<String>, format = DateTime)))]
+    pub expires_at: Option<i64>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for ShareLinkResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct ListShareLinksResponse(pub Vec<ShareLinkResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListShareLinksResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Create Share Link Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CreateShareLinkRequest {
+    /// Watermark stamped on exports made through the link, e.g. the reviewer email
+    #[validate(length(max = 64))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "DRAFT — do not distribute"))
+    )]
+    pub watermark: Option<String>,
+    /// The link stops working after this many days
+    #[validate(range(min = 1, max = 365))]
+    pub expires_in_days: Option<i64>,
+    /// What the reviewer may do, `comment` by default
+    pub permission: Option<SharePermission>,
+}
+
+/// Response schema for a comment, the author is only known by its handle.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct CommentResponse {
+    pub id: String,
+    pub paper_id: String,
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "Reviewer A")))]
+    pub author_handle: String,
+    pub content: String,
+    pub anchor: Option<String>,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for CommentResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct ListCommentsResponse(pub Vec<CommentResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListCommentsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Create Comment Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCommentRequest {
+    #[validate(length(min = 1, max = 5000))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "The ablation in section 4 is missing a baseline."))
+    )]
+    pub content: String,
+    /// Location in the paper the comment refers to, e.g. a quoted sentence or section id
+    #[validate(length(max = 500))]
+    pub anchor: Option<String>,
+}
+
+/// What an anonymous reviewer sees through a share link.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewResponse {
+    /// Own pseudonym of the reviewer
+    pub handle: String,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub r#abstract: Option<String>,
+    pub content: Option<String>,
+    pub comments: Vec<CommentResponse>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for ReviewResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+pub enum SharePermission {
+    // read the paper and add comments / annotations, no edit
+    #[serde(rename = "comment")]
+    Comment,
+    // comment and edit the notes together with the owner
+    #[serde(rename = "edit")]
+    Edit,
+}
@@ -0,0 +1,47 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Papers added in the week starting at `week`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyActivity {
+    /// Start of the week
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub week: i64,
+    pub papers_added: u64,
+}
+
+/// Usage statistics of a user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct UserStatsResponse {
+    pub papers: u64,
+    pub folders: u64,
+    /// Papers with notes
+    pub notes: u64,
+    /// Bytes of the attached files
+    pub storage_bytes: u64,
+    /// Estimated llm tokens consumed since the start of the month
+    pub ai_tokens_this_month: u64,
+    /// Oldest week first
+    pub activity: Vec<WeeklyActivity>,
+    /// When the statistics were computed
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub computed_at: i64,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for UserStatsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
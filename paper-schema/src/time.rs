@@ -0,0 +1,179 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+/// How the timestamps of the responses are written, by the version of the api
+/// answering. The timestamps are kept in milliseconds since epoch in the
+/// schemas and converted as they are serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// Milliseconds since epoch, e.g. `1714552200000`.
+    Millis,
+    /// RFC 3339 strings in UTC, e.g. `2024-05-01T08:30:00.000Z`.
+    Rfc3339,
+}
+
+tokio::task_local! {
+    static FORMAT: TimestampFormat;
+}
+
+/// Run the future, its responses writing the timestamps in the format.
+pub async fn with_format<F: Future>(format: TimestampFormat, future: F) -> F::Output {
+    FORMAT.scope(format, future).await
+}
+
+// milliseconds outside of the api, e.g. for the webhooks and the exports
+fn current_format() -> TimestampFormat {
+    FORMAT
+        .try_with(|format| *format)
+        .unwrap_or(TimestampFormat::Millis)
+}
+
+pub fn to_rfc3339(millis: i64) -> String {
+    DateTime::<Utc>::from_timestamp_millis(millis)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// A timestamp of the input, RFC 3339 at any offset or milliseconds since
+/// epoch, in milliseconds since epoch.
+pub fn parse_timestamp(value: &str) -> Option<i64> {
+    let value = value.trim();
+    value.parse::<i64>().ok().or_else(|| {
+        DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|time| time.timestamp_millis())
+    })
+}
+
+/// The timestamp as a json value in the current format, for the untyped
+/// payloads, e.g. the details of the errors.
+pub fn to_json(millis: i64) -> serde_json::Value {
+    match current_format() {
+        TimestampFormat::Millis => millis.into(),
+        TimestampFormat::Rfc3339 => to_rfc3339(millis).into(),
+    }
+}
+
+// the clients read the timestamps of every version
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TimestampInput {
+    Millis(i64),
+    Text(String),
+    Bson(bson::DateTime),
+}
+
+impl TimestampInput {
+    fn millis<E: Error>(self) -> Result<i64, E> {
+        match self {
+            TimestampInput::Millis(millis) => Ok(millis),
+            TimestampInput::Text(text) => parse_timestamp(&text)
+                .ok_or_else(|| E::custom(format!("invalid timestamp {}", text))),
+            TimestampInput::Bson(date) => Ok(date.timestamp_millis()),
+        }
+    }
+}
+
+/// Serde of the timestamps in milliseconds of the response schemas, with
+/// `#[serde(with = "crate::time::millis")]`.
+pub mod millis {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(millis: &i64, serializer: S) -> Result<S::Ok, S::Error> {
+        match current_format() {
+            TimestampFormat::Millis => serializer.serialize_i64(*millis),
+            TimestampFormat::Rfc3339 => serializer.serialize_str(&to_rfc3339(*millis)),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+        TimestampInput::deserialize(deserializer)?.millis()
+    }
+}
+
+/// As `millis`, for the optional timestamps.
+pub mod option_millis {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        millis: &Option<i64>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match millis {
+            Some(millis) => super::millis::serialize(millis, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<i64>, D::Error> {
+        Option::<TimestampInput>::deserialize(deserializer)?
+            .map(TimestampInput::millis)
+            .transpose()
+    }
+}
+
+/// As `millis`, for the `bson::DateTime` of the responses, which v1 answers as
+/// extended json dates.
+pub mod bson_datetime {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        date: &bson::DateTime,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match current_format() {
+            TimestampFormat::Millis => date.serialize(serializer),
+            TimestampFormat::Rfc3339 => {
+                serializer.serialize_str(&to_rfc3339(date.timestamp_millis()))
+            }
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<bson::DateTime, D::Error> {
+        TimestampInput::deserialize(deserializer)?
+            .millis()
+            .map(bson::DateTime::from_millis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Event {
+        #[serde(with = "millis")]
+        at: i64,
+        #[serde(with = "option_millis")]
+        until: Option<i64>,
+    }
+
+    #[tokio::test]
+    async fn test_timestamp_format() {
+        let event = Event {
+            at: 1714552200000,
+            until: None,
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(json, r#"{"at":1714552200000,"until":null}"#);
+        let json = with_format(TimestampFormat::Rfc3339, async {
+            serde_json::to_string(&event).unwrap()
+        })
+        .await;
+        assert_eq!(json, r#"{"at":"2024-05-01T08:30:00.000Z","until":null}"#);
+
+        let event: Event =
+            serde_json::from_str(r#"{"at":"2024-05-01T10:30:00+02:00","until":1714552200000}"#)
+                .unwrap();
+        assert_eq!(event.at, 1714552200000);
+        assert_eq!(event.until, Some(1714552200000));
+        assert!(serde_json::from_str::<Event>(r#"{"at":"yesterday","until":null}"#).is_err());
+        assert_eq!(parse_timestamp("2024-05-01T08:30:00Z"), Some(1714552200000));
+        assert_eq!(parse_timestamp("1714552200000"), Some(1714552200000));
+        assert_eq!(parse_timestamp("2024-05-01"), None);
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Usage summed over the events sharing a model, feature or user.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct UsageTotalResponse {
+    /// The model, feature or user id, absent for the overall total
+    pub key: Option<String>,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Estimated cost in USD, models without a known price count as free
+    pub cost: f64,
+}
+
+/// Response schema for the llm usage of the user in the current month.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct UsageResponse {
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub period_start: i64,
+    /// When the quota is reset
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub period_end: i64,
+    pub total: UsageTotalResponse,
+    /// Monthly token quota, absent when unlimited
+    pub quota: Option<u64>,
+    pub remaining: Option<u64>,
+    pub by_model: Vec<UsageTotalResponse>,
+    pub by_feature: Vec<UsageTotalResponse>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for UsageResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Response schema for the llm usage of all users in the current month.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct AdminUsageResponse {
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub period_start: i64,
+    pub total: UsageTotalResponse,
+    pub by_model: Vec<UsageTotalResponse>,
+    pub by_feature: Vec<UsageTotalResponse>,
+    /// Heaviest users first
+    pub by_user: Vec<UsageTotalResponse>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for AdminUsageResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
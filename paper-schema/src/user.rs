@@ -0,0 +1,109 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserInfoResponse {
+    pub uid: String,
+    pub username: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub wechat_id: Option<String>,
+    pub org_id: Option<String>,
+    #[serde(with = "crate::time::bson_datetime")]
+    pub created_at: bson::DateTime,
+    #[serde(with = "crate::time::bson_datetime")]
+    pub updated_at: bson::DateTime,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for UserInfoResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Extractible)]
+#[cfg_attr(feature = "salvo", salvo(extract(default_source(from = "body"))))]
+pub struct UpdateUserInfo {
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Response schema for the notification preferences of the user.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationPreferencesResponse {
+    /// Weekly email summing up the new papers, the reading list and the AI summaries
+    pub weekly_digest: bool,
+    /// Email when an AI summary is ready
+    pub summary_ready_email: bool,
+    #[serde(default, with = "crate::time::option_millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = Option<String>, format = DateTime)))]
+    pub last_digest_at: Option<i64>,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for NotificationPreferencesResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Update Notification Preferences Request schema, absent fields are kept.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateNotificationPreferencesRequest {
+    pub weekly_digest: Option<bool>,
+    pub summary_ready_email: Option<bool>,
+}
+
+/// Response schema for the settings of the user, the defaults of the server
+/// in place of the unset ones.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsResponse {
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "zh-CN")))]
+    pub language: String,
+    /// Folder the new papers go in by default
+    pub default_folder_id: Option<String>,
+    /// Model of the AI features when a request names none
+    pub ai_model: String,
+    /// Language the AI summaries are written in
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "en")))]
+    pub summary_language: String,
+    /// Timezone of the weekly digest, sent at 8 in the morning
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "Asia/Shanghai")))]
+    pub timezone: String,
+    pub notifications: NotificationPreferencesResponse,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for SettingsResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Update Settings Request schema, absent fields are kept and empty ones go
+/// back to the default.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSettingsRequest {
+    pub language: Option<String>,
+    pub default_folder_id: Option<String>,
+    #[validate(length(max = 128))]
+    pub ai_model: Option<String>,
+    pub summary_language: Option<String>,
+    pub timezone: Option<String>,
+    pub notifications: Option<UpdateNotificationPreferencesRequest>,
+}
@@ -0,0 +1,48 @@
+//! Normalization and rules of the fields shared by the request schemas.
+
+use validator::ValidationError;
+
+pub fn trim(value: &mut String) {
+    let trimmed = value.trim();
+    if trimmed.len() != value.len() {
+        *value = trimmed.to_string();
+    }
+}
+
+/// Trim the value, blank values become `None`.
+pub fn trim_option(value: &mut Option<String>) {
+    if let Some(v) = value {
+        trim(v);
+        if v.is_empty() {
+            *value = None;
+        }
+    }
+}
+
+pub fn trim_all(values: &mut Vec<String>) {
+    values.iter_mut().for_each(trim);
+    values.retain(|v| !v.is_empty());
+}
+
+/// A color as `#rrggbb` hex.
+pub fn validate_color(color: &str) -> Result<(), ValidationError> {
+    let hex = color.strip_prefix('#').unwrap_or_default();
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        let mut error = ValidationError::new("color");
+        error.message = Some("color must be given as #rrggbb".into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+pub const MAX_TAG_CHARS: usize = 32;
+
+pub fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    if tags.iter().any(|tag| tag.chars().count() > MAX_TAG_CHARS) {
+        let mut error = ValidationError::new("tag_length");
+        error.message =
+            Some(format!("each tag must be at most {} characters", MAX_TAG_CHARS).into());
+        return Err(error);
+    }
+    Ok(())
+}
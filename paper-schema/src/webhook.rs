@@ -0,0 +1,93 @@
+#[cfg(feature = "salvo")]
+use salvo::{
+    Response, Scribe,
+    oapi::{ToResponse, ToSchema},
+    writing::Json,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Response schema for a webhook, the secret is only returned on creation.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookResponse {
+    pub id: String,
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "https://hooks.slack.com/services/T000/B000/XXXX"))
+    )]
+    pub url: String,
+    /// Types of the delivered events, all when empty
+    pub events: Vec<String>,
+    /// Key of the HMAC-SHA256 signature of the payloads
+    pub secret: Option<String>,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+}
+
+#[cfg(feature = "salvo")]
+impl Scribe for WebhookResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct ListWebhooksResponse(pub Vec<WebhookResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListWebhooksResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}
+
+/// Create Webhook Request schema.
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "salvo", derive(ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWebhookRequest {
+    #[validate(length(max = 2048))]
+    #[cfg_attr(
+        feature = "salvo",
+        salvo(schema(example = "https://hooks.slack.com/services/T000/B000/XXXX"))
+    )]
+    pub url: String,
+    /// Types of the delivered events, all when empty
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+/// Response schema for a delivery attempt of an event to a webhook.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToSchema, ToResponse))]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveryResponse {
+    pub id: String,
+    pub event_id: String,
+    #[cfg_attr(feature = "salvo", salvo(schema(example = "paper_created")))]
+    pub event_type: String,
+    pub success: bool,
+    /// Attempts made, the last one is reported
+    pub attempts: u32,
+    /// Http status of the last attempt, none when it got no response
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    #[serde(with = "crate::time::millis")]
+    #[cfg_attr(feature = "salvo", salvo(schema(value_type = String, format = DateTime)))]
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "salvo", derive(ToResponse, ToSchema))]
+pub struct ListWebhookDeliveriesResponse(pub Vec<WebhookDeliveryResponse>);
+
+#[cfg(feature = "salvo")]
+impl Scribe for ListWebhookDeliveriesResponse {
+    fn render(self, res: &mut Response) {
+        res.render(Json(self));
+    }
+}